@@ -0,0 +1,249 @@
+//! End-to-end latency from transacting inputs to observing query
+//! outputs, for a few representative plans.
+//!
+//! By default this runs as a criterion benchmark, which keeps its
+//! estimates as JSON in `target/criterion`:
+//!
+//!     cargo bench --bench latency
+//!
+//! Setting `LATENCY_SECONDS` runs each plan for that long instead,
+//! writing one JSON summary per plan and line to the file named by
+//! `--output` (`target/latency.json` by default), s.t. results of
+//! long-running measurements can be compared across releases:
+//!
+//!     LATENCY_SECONDS=60 cargo bench --bench latency -- --output latency.json
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::time::{Duration, Instant};
+
+use criterion::{criterion_group, Criterion};
+
+use timely::communication::allocator::Thread;
+use timely::communication::Allocate;
+use timely::worker::Worker;
+
+use declarative_dataflow::plan::{Aggregate, AggregationFn, Join, Project};
+use declarative_dataflow::server::Server;
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+
+/// Number of entities transacted before measuring.
+const ENTITIES: u64 = 10_000;
+
+/// A plan, along with the attributes it queries.
+struct Case {
+    name: &'static str,
+    attributes: Vec<&'static str>,
+    plan: Plan<Aid>,
+}
+
+/// [:find ?e ?x ?y :where [?e :a ?x] [?e :b ?y]]
+fn two_way_join() -> Case {
+    let (e, x, y) = (0, 1, 2);
+
+    Case {
+        name: "2-way join",
+        attributes: vec![":a", ":b"],
+        plan: Plan::Join(Join {
+            variables: vec![e],
+            left_plan: Box::new(Plan::match_a(e, ":a", x)),
+            right_plan: Box::new(Plan::match_a(e, ":b", y)),
+        }),
+    }
+}
+
+/// [:find ?e ?v1 ... ?v5 :where [?e :a1 ?v1] ... [?e :a5 ?v5]]
+fn five_way_join() -> Case {
+    let attributes = vec![":a1", ":a2", ":a3", ":a4", ":a5"];
+    let e = 0;
+
+    let plan = attributes.iter().enumerate().skip(1).fold(
+        Plan::match_a(e, attributes[0], 1),
+        |plan, (i, a)| {
+            Plan::Join(Join {
+                variables: vec![e],
+                left_plan: Box::new(plan),
+                right_plan: Box::new(Plan::match_a(e, *a, i as u32 + 1)),
+            })
+        },
+    );
+
+    Case {
+        name: "5-way join",
+        attributes,
+        plan,
+    }
+}
+
+/// [:find (count ?x) :where [?e :a ?x]]
+fn aggregate() -> Case {
+    let (e, x) = (0, 1);
+
+    Case {
+        name: "aggregate",
+        attributes: vec![":a"],
+        plan: Plan::Aggregate(Aggregate {
+            variables: vec![x],
+            plan: Box::new(Plan::Project(Project {
+                variables: vec![x],
+                plan: Box::new(Plan::match_a(e, ":a", x)),
+            })),
+            aggregation_fns: vec![AggregationFn::COUNT],
+            key_variables: vec![],
+            aggregation_variables: vec![x],
+            with_variables: vec![],
+        }),
+    }
+}
+
+fn cases() -> Vec<Case> {
+    vec![two_way_join(), five_way_join(), aggregate()]
+}
+
+/// A server computing a single case, fed one entity per round.
+struct Harness {
+    server: Server<Aid, u64, u64>,
+    attributes: Vec<&'static str>,
+    next: u64,
+}
+
+impl Harness {
+    fn new<Al: Allocate>(worker: &mut Worker<Al>, case: Case) -> Self {
+        let Case {
+            attributes, plan, ..
+        } = case;
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for a in attributes.iter() {
+                server
+                    .create_attribute(scope, *a, AttributeConfig::tx_time(InputSemantics::Raw))
+                    .unwrap();
+            }
+
+            server.test_single(scope, Rule::named("query", plan));
+        });
+
+        let mut harness = Harness {
+            server,
+            attributes,
+            next: 0,
+        };
+
+        let tx_data = (0..ENTITIES).flat_map(|e| harness.datoms(e)).collect();
+        harness.server.transact(tx_data, 0, 0).unwrap();
+        harness.server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| harness.server.is_any_outdated());
+
+        harness.next = ENTITIES;
+        harness
+    }
+
+    fn datoms(&self, e: u64) -> Vec<Datom<Aid>> {
+        self.attributes
+            .iter()
+            .map(|a| Datom::add(e, *a, Value::Number((e % 100) as i64)))
+            .collect()
+    }
+
+    /// Transacts a new entity and returns the time until all query
+    /// outputs reflect it.
+    fn round<Al: Allocate>(&mut self, worker: &mut Worker<Al>) -> Duration {
+        let e = self.next;
+        let tx_data = self.datoms(e);
+        self.next += 1;
+
+        let start = Instant::now();
+
+        self.server.transact(tx_data, 0, 0).unwrap();
+        self.server.advance_domain(None, e - ENTITIES + 2).unwrap();
+
+        let server = &self.server;
+        worker.step_while(|| server.is_any_outdated());
+
+        start.elapsed()
+    }
+}
+
+// Workers are created by hand rather than via `execute_directly`,
+// because criterion has to be borrowed for the duration of each case.
+fn latency(c: &mut Criterion) {
+    for case in cases() {
+        let name = case.name;
+
+        let mut worker = Worker::new(Thread::new());
+        let mut harness = Harness::new(&mut worker, case);
+
+        c.bench_function(name, |b| {
+            b.iter_custom(|iters| (0..iters).map(|_i| harness.round(&mut worker)).sum())
+        });
+    }
+}
+
+criterion_group!(benches, latency);
+
+/// Measures each case for the specified duration, writing latency
+/// percentiles in microseconds to the output file.
+fn long_running(duration: Duration, output: &str) {
+    let file = File::create(output).expect("failed to create output file");
+    let mut writer = BufWriter::new(file);
+
+    for case in cases() {
+        let name = case.name;
+
+        let mut samples = timely::execute_directly(move |worker| {
+            let mut harness = Harness::new(worker, case);
+            let mut samples = Vec::new();
+
+            let start = Instant::now();
+            while samples.is_empty() || start.elapsed() < duration {
+                samples.push(harness.round(worker).as_micros() as u64);
+            }
+
+            samples
+        });
+
+        samples.sort();
+
+        let percentile = |p: usize| samples[(samples.len() - 1) * p / 100];
+
+        let summary = serde_json::json!({
+            "plan": name,
+            "samples": samples.len(),
+            "p50_us": percentile(50),
+            "p90_us": percentile(90),
+            "p99_us": percentile(99),
+            "max_us": samples[samples.len() - 1],
+        });
+
+        writeln!(writer, "{}", summary).expect("failed to write summary");
+    }
+
+    writer.flush().expect("failed to write summary");
+}
+
+/// Returns the file named by `--output`, if any.
+fn output_path() -> Option<String> {
+    let mut args = std::env::args();
+    while let Some(arg) = args.next() {
+        if arg == "--output" {
+            return args.next();
+        }
+    }
+
+    None
+}
+
+fn main() {
+    match std::env::var("LATENCY_SECONDS") {
+        Ok(seconds) => {
+            let seconds = seconds.parse().expect("failed to parse LATENCY_SECONDS");
+            let output = output_path().unwrap_or_else(|| "target/latency.json".to_string());
+            long_running(Duration::from_secs(seconds), &output);
+        }
+        Err(_) => {
+            benches();
+            Criterion::default().configure_from_args().final_summary();
+        }
+    }
+}
@@ -126,8 +126,10 @@ fn main() {
                     rules: vec![Rule {
                         name: name.to_string(),
                         plan: Plan::GraphQl(GraphQl::new(query)),
+                        semantics: None,
                     }],
                     publish: vec![name.to_string()],
+                    datalog: vec![],
                 }),
                 Request::Interest(Interest {
                     name: name.to_string(),
@@ -136,6 +138,11 @@ fn main() {
                         stateful: granularity,
                     })),
                     disable_logging: None,
+                    as_of: None,
+                    strategy: None,
+                    resume_after: None,
+                    limits: None,
+                    page: None,
                 }),
             ])
             .expect("failed to serialize requests");
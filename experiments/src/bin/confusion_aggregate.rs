@@ -33,6 +33,7 @@ fn main() {
                 key_variables: vec![country, target],
                 with_variables: vec![],
             }),
+            semantics: None,
         }];
 
         let obj_source = Source::JsonFile(JsonFile {
@@ -56,6 +57,7 @@ fn main() {
                 Register {
                     rules,
                     publish: vec!["q2".to_string()],
+                    datalog: vec![],
                 },
                 scope,
             );
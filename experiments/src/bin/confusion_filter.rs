@@ -35,6 +35,7 @@ fn main() {
                     Value::String("Russian".to_string()),
                 )),
             }),
+            semantics: None,
         }];
 
         let obj_source = Source::JsonFile(JsonFile {
@@ -54,6 +55,7 @@ fn main() {
                 Register {
                     rules,
                     publish: vec!["q1".to_string()],
+                    datalog: vec![],
                 },
                 scope,
             );
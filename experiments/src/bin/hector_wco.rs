@@ -91,6 +91,7 @@ fn main() {
                     Rule {
                         name: "triangles".to_string(),
                         plan,
+                        semantics: None,
                     },
                 )
                 .filter(move |_| inspect)
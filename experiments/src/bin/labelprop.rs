@@ -33,11 +33,14 @@ fn main() {
                             right_plan: Box::new(Plan::NameExpr(vec![x, z], "label".to_string())),
                         }),
                     ],
+                    semantics: None,
                 }),
+                semantics: None,
             },
             Rule {
                 name: "labelprop".to_string(),
                 plan: Plan::NameExpr(vec![x, y], "label".to_string()),
+                semantics: None,
                 // plan: Plan::Aggregate(Aggregate {
                 //     variables: vec![x, y],
                 //     key_variables: vec![],
@@ -76,6 +79,7 @@ fn main() {
                 .register(Register {
                     rules,
                     publish: vec!["labelprop".to_string()],
+                    datalog: vec![],
                 })
                 .unwrap();
 
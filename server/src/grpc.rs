@@ -0,0 +1,489 @@
+//! gRPC interface, served alongside the WebSocket interface.
+//!
+//! Each call is assigned a client token of its own, from a range that
+//! doesn't overlap with the tokens of WebSocket connections. Requests
+//! are handed to the I/O loop as regular domain events, s.t. they are
+//! sequenced and handled exactly like requests arriving via
+//! WebSockets. Unary calls return once all of their requests have
+//! been handled, or fail with the first error encountered.
+//!
+//! Calls carrying a bearer token in their `authorization` metadata
+//! authenticate with it, before their requests are handled.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc as std_mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use tokio::sync::{mpsc, oneshot};
+use tonic::{Code, Status};
+
+use declarative_dataflow::server::{CreateAttribute, Interest, Register, Request, TxId};
+use declarative_dataflow::{Datom, Error, Output, Rational32, ResultDiff, Time, Uuid, Value};
+
+use crate::http::bearer;
+use crate::networking::{DomainEvent, Token};
+use crate::Aid;
+
+/// Generated message and service definitions.
+pub mod proto {
+    tonic::include_proto!("declarative");
+}
+
+use proto::declarative_server::{Declarative, DeclarativeServer};
+
+/// First token handed out to gRPC calls. WebSocket connections and
+/// internal tokens never come close to this.
+const FIRST_TOKEN: usize = std::usize::MAX / 4;
+
+/// Category of the message acknowledging that all requests of a
+/// command have been handled.
+pub const DONE: &str = "df.grpc/done";
+
+/// Returns true iff the specified token belongs to a gRPC call.
+pub fn is_grpc(token: Token) -> bool {
+    token.0 >= FIRST_TOKEN && token.0 < std::usize::MAX / 2
+}
+
+/// A pending gRPC call.
+enum Client {
+    /// A unary call, waiting for its requests to be handled.
+    Call(oneshot::Sender<Result<TxId, Error>>),
+    /// A subscription, receiving results until it goes away.
+    Stream(mpsc::UnboundedSender<Result<proto::Output, Status>>),
+}
+
+/// State shared between the service and the I/O loop.
+struct Shared {
+    next_token: AtomicUsize,
+    process: usize,
+    processes: usize,
+    clients: Mutex<HashMap<Token, Client>>,
+    events: Mutex<std_mpsc::Sender<DomainEvent>>,
+}
+
+impl Shared {
+    fn register(&self, client: Client) -> Token {
+        // Processes of a cluster serve calls independently, so their
+        // tokens are interleaved.
+        let count = self.next_token.fetch_add(1, Ordering::SeqCst);
+        let token = Token(FIRST_TOKEN + count * self.processes + self.process);
+        self.clients.lock().unwrap().insert(token, client);
+
+        token
+    }
+
+    fn push(&self, event: DomainEvent) {
+        self.events
+            .lock()
+            .unwrap()
+            .send(event)
+            .expect("gRPC frontend disappeared");
+    }
+}
+
+/// Handle to the gRPC service, owned by the I/O loop.
+pub struct Frontend {
+    shared: Arc<Shared>,
+    events: std_mpsc::Receiver<DomainEvent>,
+}
+
+impl Frontend {
+    /// Serves the gRPC interface at the specified address, from a
+    /// thread of its own, on behalf of the specified process out of
+    /// all in the cluster.
+    pub fn serve(address: SocketAddr, process: usize, processes: usize) -> Self {
+        let (send, events) = std_mpsc::channel();
+
+        let shared = Arc::new(Shared {
+            next_token: AtomicUsize::new(0),
+            process,
+            processes,
+            clients: Mutex::new(HashMap::new()),
+            events: Mutex::new(send),
+        });
+
+        let service = Service {
+            shared: shared.clone(),
+        };
+
+        thread::spawn(move || {
+            let mut runtime = tokio::runtime::Runtime::new().expect("failed to start gRPC runtime");
+
+            info!("[gRPC] serving at {}", address);
+
+            let serving = tonic::transport::Server::builder()
+                .add_service(DeclarativeServer::new(service))
+                .serve(address);
+
+            if let Err(error) = runtime.block_on(serving) {
+                error!("[gRPC] {}", error);
+            }
+        });
+
+        Frontend { shared, events }
+    }
+
+    /// Returns the next domain event issued by a gRPC call, if any.
+    pub fn next_event(&self) -> Option<DomainEvent> {
+        self.events.try_recv().ok()
+    }
+
+    /// Forwards an output to the specified gRPC call. Subscriptions
+    /// that have gone away are disconnected.
+    pub fn deliver(&self, token: Token, out: &Output) {
+        let mut clients = self.shared.clients.lock().unwrap();
+
+        let (resolved, gone) = match clients.get(&token) {
+            None => (false, false),
+            Some(Client::Call(_)) => match out {
+                Output::Error(_, _, _) => (true, false),
+                Output::Message(_, msg) => (msg["category"] == DONE, false),
+                _ => (false, false),
+            },
+            Some(Client::Stream(stream)) => {
+                let item = match out {
+                    Output::Snapshot(_, name, results) => Some(Ok(proto::Output {
+                        output: Some(proto::output::Output::Snapshot(to_results(name, results))),
+                    })),
+                    Output::QueryDiff(name, results) => Some(Ok(proto::Output {
+                        output: Some(proto::output::Output::Diff(to_results(name, results))),
+                    })),
+                    Output::QueryBatch(name, time, batch) => {
+                        let results = batch
+                            .iter()
+                            .map(|(tuple, diff)| (tuple.clone(), time.clone(), *diff))
+                            .collect::<Vec<ResultDiff<Time>>>();
+
+                        Some(Ok(proto::Output {
+                            output: Some(proto::output::Output::Diff(to_results(name, &results))),
+                        }))
+                    }
+                    Output::Json(_, _, _, _) => Some(Ok(proto::Output {
+                        output: Some(proto::output::Output::Json(
+                            serde_json::to_string(out).expect("failed to serialize output"),
+                        )),
+                    })),
+                    Output::Error(_, error, _) => Some(Err(to_status(error))),
+                    Output::Message(_, _) | Output::ResumeToken(_, _) => None,
+                };
+
+                match item {
+                    None => (false, false),
+                    Some(item) => {
+                        let failed = item.is_err();
+                        (failed, stream.send(item).is_err())
+                    }
+                }
+            }
+        };
+
+        if resolved || gone {
+            match clients.remove(&token) {
+                Some(Client::Call(call)) => {
+                    let result = match out {
+                        Output::Error(_, error, _) => Err(error.clone()),
+                        Output::Message(_, msg) => Ok(msg["tx"].as_u64().unwrap_or(0)),
+                        _ => unreachable!(),
+                    };
+
+                    // The caller might have given up already.
+                    call.send(result).ok();
+                }
+                Some(Client::Stream(_)) => {
+                    info!("[gRPC] subscription {:?} ended", token);
+                    self.shared.push(DomainEvent::Disconnect(token));
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+/// Implementation of the generated service trait.
+#[derive(Clone)]
+struct Service {
+    shared: Arc<Shared>,
+}
+
+impl Service {
+    /// Submits requests on behalf of a unary call and waits for them
+    /// to be handled.
+    async fn call(
+        &self,
+        credentials: Option<String>,
+        requests: Vec<Request<Aid>>,
+    ) -> Result<tonic::Response<proto::Receipt>, Status> {
+        let (send, recv) = oneshot::channel();
+        let token = self.shared.register(Client::Call(send));
+
+        self.shared.push(DomainEvent::Requests(
+            token,
+            authenticated(credentials, requests),
+        ));
+
+        match recv.await {
+            Err(_) => Err(Status::unavailable("server went away")),
+            Ok(Err(error)) => Err(to_status(&error)),
+            Ok(Ok(tx)) => Ok(tonic::Response::new(proto::Receipt { tx })),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Declarative for Service {
+    async fn transact(
+        &self,
+        request: tonic::Request<proto::TransactRequest>,
+    ) -> Result<tonic::Response<proto::Receipt>, Status> {
+        let credentials = credentials(&request);
+        let datoms = request
+            .into_inner()
+            .datoms
+            .into_iter()
+            .map(from_datom)
+            .collect::<Result<Vec<Datom<Aid>>, Status>>()?;
+
+        self.call(credentials, vec![Request::Transact(datoms)])
+            .await
+    }
+
+    async fn create_attribute(
+        &self,
+        request: tonic::Request<proto::CreateAttributeRequest>,
+    ) -> Result<tonic::Response<proto::Receipt>, Status> {
+        let credentials = credentials(&request);
+        let request = request.into_inner();
+        let config = serde_json::from_str(&request.config_json)
+            .map_err(|error| Status::invalid_argument(error.to_string()))?;
+
+        self.call(
+            credentials,
+            vec![Request::CreateAttribute(CreateAttribute {
+                name: request.name,
+                config,
+            })],
+        )
+        .await
+    }
+
+    async fn register(
+        &self,
+        request: tonic::Request<proto::RegisterRequest>,
+    ) -> Result<tonic::Response<proto::Receipt>, Status> {
+        let credentials = credentials(&request);
+        let request = request.into_inner();
+        let rules = serde_json::from_str(&request.rules_json)
+            .map_err(|error| Status::invalid_argument(error.to_string()))?;
+
+        self.call(
+            credentials,
+            vec![Request::Register(Register {
+                rules,
+                publish: request.publish,
+                datalog: vec![],
+            })],
+        )
+        .await
+    }
+
+    type SubscribeStream = mpsc::UnboundedReceiver<Result<proto::Output, Status>>;
+
+    async fn subscribe(
+        &self,
+        request: tonic::Request<proto::SubscribeRequest>,
+    ) -> Result<tonic::Response<Self::SubscribeStream>, Status> {
+        let credentials = credentials(&request);
+        let request = request.into_inner();
+        let granularity = match request.granularity {
+            None => None,
+            Some(time) => Some(from_time(time)?),
+        };
+
+        let (send, recv) = mpsc::unbounded_channel();
+        let token = self.shared.register(Client::Stream(send));
+
+        self.shared.push(DomainEvent::Requests(
+            token,
+            authenticated(
+                credentials,
+                vec![Request::Interest(Interest {
+                    name: request.name,
+                    granularity,
+                    sink: None,
+                    disable_logging: None,
+                    as_of: None,
+                    strategy: None,
+                    resume_after: None,
+                    limits: None,
+                    page: None,
+                })],
+            ),
+        ));
+
+        Ok(tonic::Response::new(recv))
+    }
+}
+
+/// Returns the bearer token a call carries, if any.
+fn credentials<T>(request: &tonic::Request<T>) -> Option<String> {
+    request
+        .metadata()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(bearer)
+}
+
+/// Authenticates with the specified credentials, if any, before
+/// anything else.
+fn authenticated(credentials: Option<String>, requests: Vec<Request<Aid>>) -> Vec<Request<Aid>> {
+    match credentials {
+        None => requests,
+        Some(token) => std::iter::once(Request::Authenticate(token))
+            .chain(requests)
+            .collect(),
+    }
+}
+
+fn to_status(error: &Error) -> Status {
+    let code = match error.category.as_str() {
+        "df.error.category/incorrect" => Code::InvalidArgument,
+        "df.error.category/not-found" => Code::NotFound,
+        "df.error.category/conflict" => Code::AlreadyExists,
+        "df.error.category/forbidden" => Code::PermissionDenied,
+        "df.error.category/unsupported" => Code::Unimplemented,
+        "df.error.category/interrupted" => Code::Aborted,
+        "df.error.category/busy" => Code::ResourceExhausted,
+        _ => Code::Internal,
+    };
+
+    Status::new(code, error.message.clone())
+}
+
+fn from_datom(datom: proto::Datom) -> Result<Datom<Aid>, Status> {
+    let e = datom
+        .e
+        .ok_or_else(|| Status::invalid_argument("datom without entity"))
+        .and_then(from_value)?;
+    let v = datom
+        .v
+        .ok_or_else(|| Status::invalid_argument("datom without value"))
+        .and_then(from_value)?;
+    let time = match datom.time {
+        None => None,
+        Some(time) => Some(from_time(time)?),
+    };
+    let diff = if datom.diff == 0 {
+        1
+    } else {
+        datom.diff as isize
+    };
+
+    Ok(Datom(e, datom.a, v, time, diff))
+}
+
+fn from_value(value: proto::Value) -> Result<Value, Status> {
+    use proto::value::Value as V;
+
+    match value.value {
+        None => Err(Status::invalid_argument("empty value")),
+        Some(V::Aid(aid)) => Ok(Value::Aid(aid)),
+        Some(V::String(string)) => Ok(Value::String(string)),
+        Some(V::Bool(b)) => Ok(Value::Bool(b)),
+        Some(V::Number(n)) => Ok(Value::Number(n)),
+        Some(V::Rational(r)) => {
+            if r.denom == 0 {
+                Err(Status::invalid_argument("rational with zero denominator"))
+            } else {
+                Ok(Value::Rational32(Rational32::new(r.numer, r.denom)))
+            }
+        }
+        Some(V::Eid(eid)) => Ok(Value::Eid(eid)),
+        Some(V::Instant(instant)) => Ok(Value::Instant(instant)),
+        Some(V::Uuid(uuid)) => Uuid::parse_str(&uuid)
+            .map(Value::Uuid)
+            .map_err(|error| Status::invalid_argument(error.to_string())),
+        Some(V::TempId(id)) => Ok(Value::TempId(id)),
+        Some(V::Date(date)) => Ok(Value::Date(date)),
+        Some(V::Bytes(bytes)) => Ok(Value::Bytes(bytes)),
+        Some(V::Decimal(decimal)) => decimal
+            .parse()
+            .map(Value::Decimal)
+            .map_err(Status::invalid_argument),
+        #[cfg(feature = "real")]
+        Some(V::Real(real)) => real
+            .parse()
+            .map(Value::Real)
+            .map_err(|_| Status::invalid_argument("malformed real")),
+        #[cfg(not(feature = "real"))]
+        Some(V::Real(_)) => Err(Status::unimplemented("real values are not enabled")),
+    }
+}
+
+fn to_value(value: &Value) -> proto::Value {
+    use proto::value::Value as V;
+
+    let value = match value {
+        Value::Aid(aid) => V::Aid(aid.clone()),
+        Value::String(string) => V::String(string.clone()),
+        Value::Bool(b) => V::Bool(*b),
+        Value::Number(n) => V::Number(*n),
+        Value::Rational32(r) => V::Rational(proto::Rational {
+            numer: *r.numer(),
+            denom: *r.denom(),
+        }),
+        Value::Eid(eid) => V::Eid(*eid),
+        Value::Instant(instant) => V::Instant(*instant),
+        Value::Uuid(uuid) => V::Uuid(uuid.to_hyphenated().to_string()),
+        Value::TempId(id) => V::TempId(id.clone()),
+        Value::Date(date) => V::Date(*date),
+        Value::Bytes(bytes) => V::Bytes(bytes.clone()),
+        Value::Decimal(decimal) => V::Decimal(decimal.to_string()),
+        #[cfg(feature = "real")]
+        Value::Real(real) => V::Real(real.to_string()),
+    };
+
+    proto::Value { value: Some(value) }
+}
+
+fn from_time(time: proto::Time) -> Result<Time, Status> {
+    use proto::time::Time as T;
+
+    match time.time {
+        None => Err(Status::invalid_argument("empty time")),
+        Some(T::TxId(tx)) => Ok(Time::TxId(tx)),
+        Some(T::RealNanos(nanos)) => Ok(Time::Real(Duration::from_nanos(nanos))),
+        Some(T::Bitemporal(bi)) => Ok(Time::Bi(Duration::from_nanos(bi.event_nanos), bi.tx_id)),
+    }
+}
+
+fn to_time(time: &Time) -> proto::Time {
+    use proto::time::Time as T;
+
+    let time = match time {
+        Time::TxId(tx) => T::TxId(*tx),
+        Time::Real(real) => T::RealNanos(real.as_nanos() as u64),
+        Time::Bi(event, tx) => T::Bitemporal(proto::Bitemporal {
+            event_nanos: event.as_nanos() as u64,
+            tx_id: *tx,
+        }),
+    };
+
+    proto::Time { time: Some(time) }
+}
+
+fn to_results(name: &str, results: &[ResultDiff<Time>]) -> proto::Results {
+    proto::Results {
+        name: name.to_string(),
+        results: results
+            .iter()
+            .map(|(tuple, time, diff)| proto::Result {
+                tuple: tuple.iter().map(to_value).collect(),
+                time: Some(to_time(time)),
+                diff: *diff as i64,
+            })
+            .collect(),
+    }
+}
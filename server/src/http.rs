@@ -0,0 +1,228 @@
+//! HTTP interface for one-shot queries, for consumers that would
+//! rather issue requests and wait for responses than maintain a
+//! subscription.
+//!
+//! `POST /query` accepts a JSON query, e.g. `{"plan": ...}`. The plan
+//! is evaluated once against everything handled before the query
+//! itself, and its consolidated results are returned as a JSON array
+//! of (tuple, time, count) triples. Each request is assigned a client
+//! token of its own, from a range that doesn't overlap with those of
+//! other interfaces, and is sequenced like any other request.
+//! Requests carrying an `Authorization: Bearer <token>` header
+//! authenticate with it, before their query is handled.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use declarative_dataflow::server::{Query, Request};
+use declarative_dataflow::{Error, Output, ResultDiff, Time};
+
+use crate::networking::{DomainEvent, Token};
+use crate::Aid;
+
+/// First token handed out to HTTP requests. WebSocket connections
+/// never come close to this, and gRPC calls start above.
+const FIRST_TOKEN: usize = std::usize::MAX / 8;
+
+/// Returns true iff the specified token belongs to an HTTP request.
+pub fn is_http(token: Token) -> bool {
+    token.0 >= FIRST_TOKEN && token.0 < std::usize::MAX / 4
+}
+
+/// State shared between request threads and the I/O loop.
+struct Shared {
+    next_token: AtomicUsize,
+    process: usize,
+    processes: usize,
+    pending: Mutex<HashMap<Token, Sender<Result<Vec<ResultDiff<Time>>, Error>>>>,
+    events: Mutex<Sender<DomainEvent>>,
+}
+
+/// Handle to the HTTP interface, owned by the I/O loop.
+pub struct Frontend {
+    shared: Arc<Shared>,
+    events: Receiver<DomainEvent>,
+}
+
+impl Shared {
+    /// Returns a token for a new request. Processes of a cluster
+    /// serve requests independently, so their tokens are interleaved.
+    fn next_token(&self) -> Token {
+        let count = self.next_token.fetch_add(1, Ordering::SeqCst);
+        Token(FIRST_TOKEN + count * self.processes + self.process)
+    }
+}
+
+impl Frontend {
+    /// Serves the HTTP interface at the specified address, answering
+    /// each request from a thread of its own, on behalf of the
+    /// specified process out of all in the cluster.
+    pub fn serve(address: SocketAddr, process: usize, processes: usize) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let (send, events) = mpsc::channel();
+
+        let shared = Arc::new(Shared {
+            next_token: AtomicUsize::new(0),
+            process,
+            processes,
+            pending: Mutex::new(HashMap::new()),
+            events: Mutex::new(send),
+        });
+
+        let accepting = shared.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Err(error) => warn!("[HTTP] failed to accept connection: {}", error),
+                    Ok(stream) => {
+                        let shared = accepting.clone();
+                        thread::spawn(move || {
+                            if let Err(error) = respond(&shared, stream) {
+                                warn!("[HTTP] failed to answer request: {}", error);
+                            }
+                        });
+                    }
+                }
+            }
+        });
+
+        Ok(Frontend { shared, events })
+    }
+
+    /// Returns the next domain event issued by an HTTP request, if
+    /// any.
+    pub fn next_event(&self) -> Option<DomainEvent> {
+        self.events.try_recv().ok()
+    }
+
+    /// Answers the specified HTTP request, if the output resolves it.
+    pub fn deliver(&self, token: Token, out: &Output) {
+        let result = match out {
+            Output::Snapshot(_, _, results) => Ok(results.clone()),
+            Output::Error(_, error, _) => Err(error.clone()),
+            _ => return,
+        };
+
+        if let Some(waiting) = self.shared.pending.lock().unwrap().remove(&token) {
+            // The client might have given up already.
+            waiting.send(result).ok();
+        }
+    }
+}
+
+/// Reads a single HTTP request and answers it.
+fn respond(shared: &Shared, stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    let mut content_length = 0;
+    let mut credentials = None;
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        let mut parts = header.splitn(2, ':');
+        let name = parts.next().unwrap_or("").trim();
+        let value = parts.next().unwrap_or("").trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.parse().unwrap_or(0);
+        } else if name.eq_ignore_ascii_case("authorization") {
+            credentials = bearer(value);
+        }
+        header.clear();
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let (status, body) = if !request_line.starts_with("POST /query ") {
+        ("404 Not Found", String::new())
+    } else {
+        match serde_json::from_slice::<Query<Aid>>(&body) {
+            Err(error) => ("400 Bad Request", to_json(&Error::incorrect(error))),
+            Ok(query) => match evaluate(shared, credentials, query) {
+                Err(error) => (status_of(&error), to_json(&error)),
+                Ok(results) => (
+                    "200 OK",
+                    serde_json::to_string(&results).expect("failed to serialize results"),
+                ),
+            },
+        }
+    };
+
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+
+    stream.flush()
+}
+
+/// Submits a query on behalf of an HTTP request and waits for its
+/// results.
+fn evaluate(
+    shared: &Shared,
+    credentials: Option<String>,
+    query: Query<Aid>,
+) -> Result<Vec<ResultDiff<Time>>, Error> {
+    let token = shared.next_token();
+    let (send, recv) = mpsc::channel();
+
+    shared.pending.lock().unwrap().insert(token, send);
+
+    let mut requests = Vec::new();
+    if let Some(token) = credentials {
+        requests.push(Request::Authenticate(token));
+    }
+    requests.push(Request::Query(query));
+
+    shared
+        .events
+        .lock()
+        .unwrap()
+        .send(DomainEvent::Requests(token, requests))
+        .map_err(|_| Error::fault("Server went away."))?;
+
+    recv.recv().map_err(|_| Error::fault("Server went away."))?
+}
+
+/// Extracts the token from the value of an authorization header or
+/// metadata entry using the bearer scheme.
+pub fn bearer(authorization: &str) -> Option<String> {
+    let mut parts = authorization.splitn(2, ' ');
+    match (parts.next(), parts.next()) {
+        (Some(scheme), Some(token)) if scheme.eq_ignore_ascii_case("bearer") => {
+            Some(token.trim().to_string())
+        }
+        _ => None,
+    }
+}
+
+fn status_of(error: &Error) -> &'static str {
+    match error.category.as_str() {
+        "df.error.category/incorrect" => "400 Bad Request",
+        "df.error.category/not-found" => "404 Not Found",
+        "df.error.category/conflict" => "409 Conflict",
+        "df.error.category/forbidden" => "403 Forbidden",
+        "df.error.category/unsupported" => "501 Not Implemented",
+        "df.error.category/interrupted" => "503 Service Unavailable",
+        "df.error.category/busy" => "429 Too Many Requests",
+        _ => "500 Internal Server Error",
+    }
+}
+
+fn to_json(error: &Error) -> String {
+    serde_json::to_string(error).expect("failed to serialize error")
+}
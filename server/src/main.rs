@@ -6,29 +6,52 @@ extern crate serde_derive;
 #[macro_use]
 extern crate log;
 
-use std::collections::{HashSet, VecDeque};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs::File;
-use std::io::{BufRead, BufReader, Read};
+use std::io::Read;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use timely::dataflow::channels::pact::{Exchange, Pipeline};
 use timely::dataflow::operators::generic::OutputHandle;
 use timely::dataflow::operators::{Operator, Probe};
 use timely::logging::{Logger, TimelyEvent};
-use timely::synchronization::Sequencer;
 
+use differential_dataflow::lattice::Lattice;
 use differential_dataflow::logging::DifferentialEvent;
 use differential_dataflow::operators::Consolidate;
 
+use declarative_dataflow::metrics::{self, Metrics, Recorder};
+use declarative_dataflow::operators::{OneShot, SnapshotRequests, Snapshots};
 use declarative_dataflow::scheduling::{AsScheduler, SchedulingEvent};
 use declarative_dataflow::server;
-use declarative_dataflow::server::{CreateAttribute, Request, Server, TxId};
+use declarative_dataflow::server::backup::{self, BackupPart};
+use declarative_dataflow::server::cluster::{ClusterConfiguration, Membership, Rendezvous};
+use declarative_dataflow::server::pagination::Page;
+use declarative_dataflow::server::sequencing::{CommandLog, Stamped};
+use declarative_dataflow::server::snapshot::{self, SnapshotPart, SnapshotWriter};
+use declarative_dataflow::server::storage::StorageBackend;
+use declarative_dataflow::server::wal::WriteAheadLog;
+use declarative_dataflow::server::{
+    Backup, Bind, BulkLoad, CreateAttribute, Request, Server, TxId,
+};
 use declarative_dataflow::sinks::{Sinkable, SinkingContext};
+use declarative_dataflow::sources::Source;
 use declarative_dataflow::timestamp::{Coarsen, Time};
-use declarative_dataflow::{Output, ResultDiff};
+use declarative_dataflow::{Datom, Eid, Error, Output, ResultDiff, ShutdownHandle};
 
 mod networking;
-use crate::networking::{DomainEvent, Token, IO, SYSTEM};
+use crate::networking::{DomainEvent, OverflowPolicy, Token, IO, SYSTEM};
+
+mod http;
+
+#[cfg(feature = "grpc")]
+mod grpc;
+#[cfg(feature = "tls")]
+mod tls;
 
 /// Server attribute identifier type.
 type Aid = String;
@@ -53,16 +76,28 @@ struct Configuration {
     pub port: u16,
     /// File from which to read server configuration.
     pub config: Option<String>,
-    /// Number of threads to use.
-    pub threads: usize,
-    /// Number of processes to expect over the entire cluster.
-    pub processes: usize,
-    /// Host addresses.
-    pub addresses: Vec<String>,
-    /// ID of this process within the cluster.
-    pub timely_pid: usize,
-    /// Whether to report connection progress.
-    pub report: bool,
+    /// Processes making up the cluster.
+    pub cluster: ClusterConfiguration,
+    /// Port at which metrics should be served, if at all.
+    pub metrics_port: Option<u16>,
+    /// Number of outputs that may be queued for a client that doesn't
+    /// keep up, if limited.
+    pub output_queue_bound: Option<usize>,
+    /// What to do about clients exceeding the output queue bound.
+    pub overflow_policy: OverflowPolicy,
+    /// Port at which one-shot queries should be accepted via HTTP, if
+    /// at all.
+    pub http_port: Option<u16>,
+    /// Port at which the gRPC interface should be served, if at all.
+    pub grpc_port: Option<u16>,
+    /// Port at which TLS-encrypted client connections should be
+    /// accepted, if at all.
+    pub tls_port: Option<u16>,
+    /// PEM file holding the certificate chain presented to TLS
+    /// clients.
+    pub tls_cert: Option<String>,
+    /// PEM file holding the private key of the TLS certificate.
+    pub tls_key: Option<String>,
 }
 
 impl Default for Configuration {
@@ -70,11 +105,15 @@ impl Default for Configuration {
         Configuration {
             port: 6262,
             config: None,
-            threads: 1,
-            processes: 1,
-            addresses: vec!["localhost:2101".to_string()],
-            timely_pid: 0,
-            report: false,
+            cluster: ClusterConfiguration::default(),
+            metrics_port: None,
+            output_queue_bound: None,
+            overflow_policy: OverflowPolicy::Disconnect,
+            http_port: None,
+            grpc_port: None,
+            tls_port: None,
+            tls_cert: None,
+            tls_key: None,
         }
     }
 }
@@ -87,6 +126,39 @@ impl Configuration {
 
         opts.optopt("", "port", "server port", "PORT");
         opts.optopt("", "config", "server configuration file", "FILE");
+        opts.optopt("", "metrics-port", "port at which to serve metrics", "PORT");
+        opts.optopt(
+            "",
+            "output-queue-bound",
+            "number of outputs that may be queued for a slow client",
+            "NUM",
+        );
+        opts.optopt(
+            "",
+            "overflow-policy",
+            "handling of slow clients: disconnect, resnapshot, or pause",
+            "POLICY",
+        );
+        opts.optopt(
+            "",
+            "http-port",
+            "port at which to accept one-shot queries",
+            "PORT",
+        );
+        opts.optopt("", "grpc-port", "port at which to serve gRPC", "PORT");
+        opts.optopt(
+            "",
+            "tls-port",
+            "port at which to accept TLS connections",
+            "PORT",
+        );
+        opts.optopt(
+            "",
+            "tls-cert",
+            "PEM certificate chain for TLS connections",
+            "FILE",
+        );
+        opts.optopt("", "tls-key", "PEM private key for TLS connections", "FILE");
 
         // Timely arguments.
         opts.optopt(
@@ -103,6 +175,12 @@ impl Configuration {
             "text file whose lines are process addresses",
             "FILE",
         );
+        opts.optopt(
+            "",
+            "addresses",
+            "comma-separated list of process addresses",
+            "HOSTS",
+        );
         opts.optflag("r", "report", "reports connection progress");
 
         opts
@@ -123,51 +201,75 @@ impl Configuration {
         let threads = matches
             .opt_str("w")
             .map(|x| x.parse().expect("failed to parse threads"))
-            .unwrap_or(default.threads);
+            .unwrap_or(default.cluster.threads);
 
         let timely_pid = matches
             .opt_str("p")
             .map(|x| x.parse().expect("failed to parse process id"))
-            .unwrap_or(default.timely_pid);
+            .unwrap_or(default.cluster.process);
 
         let processes = matches
             .opt_str("n")
             .map(|x| x.parse().expect("failed to parse processes"))
-            .unwrap_or(default.processes);
-
-        let mut addresses = Vec::new();
-        if let Some(hosts) = matches.opt_str("h") {
-            let reader = BufReader::new(File::open(hosts.clone()).unwrap());
-            for x in reader.lines().take(processes) {
-                addresses.push(x.unwrap());
-            }
-            if addresses.len() < processes {
-                panic!(
-                    "could only read {} addresses from {}, but -n: {}",
-                    addresses.len(),
-                    hosts,
-                    processes
-                );
-            }
+            .unwrap_or(default.cluster.processes());
+
+        let mut cluster = if let Some(hosts) = matches.opt_str("addresses") {
+            let addresses = hosts.split(',').map(|x| x.trim().to_string()).collect();
+            ClusterConfiguration::with_addresses(threads, addresses, timely_pid)
+        } else if let Some(hosts) = matches.opt_str("h") {
+            let addresses = ClusterConfiguration::read_addresses(&hosts, processes)
+                .unwrap_or_else(|error| panic!("{}", error.message));
+            ClusterConfiguration::with_addresses(threads, addresses, timely_pid)
         } else {
-            for index in 0..processes {
-                addresses.push(format!("localhost:{}", 2101 + index));
-            }
+            ClusterConfiguration::local(threads, processes, timely_pid)
+        };
+
+        cluster.report = matches.opt_present("report");
+
+        if let Err(error) = cluster.validate() {
+            panic!("{}", error.message);
         }
 
-        assert!(processes == addresses.len());
-        assert!(timely_pid < processes);
+        let metrics_port = matches
+            .opt_str("metrics-port")
+            .map(|x| x.parse().expect("failed to parse metrics port"));
+
+        let output_queue_bound = matches
+            .opt_str("output-queue-bound")
+            .map(|x| x.parse().expect("failed to parse output queue bound"));
+
+        let overflow_policy = matches
+            .opt_str("overflow-policy")
+            .map(|x| {
+                x.parse()
+                    .unwrap_or_else(|error: String| panic!("{}", error))
+            })
+            .unwrap_or(default.overflow_policy);
+
+        let http_port = matches
+            .opt_str("http-port")
+            .map(|x| x.parse().expect("failed to parse HTTP port"));
 
-        let report = matches.opt_present("report");
+        let grpc_port = matches
+            .opt_str("grpc-port")
+            .map(|x| x.parse().expect("failed to parse gRPC port"));
+
+        let tls_port = matches
+            .opt_str("tls-port")
+            .map(|x| x.parse().expect("failed to parse TLS port"));
 
         Self {
             port,
             config: matches.opt_str("config"),
-            threads,
-            processes,
-            addresses,
-            timely_pid,
-            report,
+            cluster,
+            metrics_port,
+            output_queue_bound,
+            overflow_policy,
+            http_port,
+            grpc_port,
+            tls_port,
+            tls_cert: matches.opt_str("tls-cert"),
+            tls_key: matches.opt_str("tls-key"),
         }
     }
 }
@@ -193,19 +295,7 @@ impl Into<server::Configuration> for Configuration {
 
 impl Into<timely::Configuration> for Configuration {
     fn into(self) -> timely::Configuration {
-        if self.processes > 1 {
-            timely::Configuration::Cluster {
-                threads: self.threads,
-                process: self.timely_pid,
-                addresses: self.addresses,
-                report: self.report,
-                log_fn: Box::new(|_| None),
-            }
-        } else if self.threads > 1 {
-            timely::Configuration::Process(self.threads)
-        } else {
-            timely::Configuration::Thread
-        }
+        self.cluster.into()
     }
 }
 
@@ -222,6 +312,134 @@ struct Command {
     pub requests: Vec<Request<Aid>>,
 }
 
+/// Accepts TLS connections at the specified port, relaying them to
+/// the WebSocket interface.
+#[cfg(feature = "tls")]
+fn serve_tls(config: &Configuration, port: u16) {
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+    let (cert, key) = match (&config.tls_cert, &config.tls_key) {
+        (Some(cert), Some(key)) => (cert, key),
+        _ => panic!("TLS requires both --tls-cert and --tls-key"),
+    };
+
+    let tls_config = tls::configure(cert, key).expect("failed to configure TLS");
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), port);
+    let upstream = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), config.port);
+
+    tls::serve(addr, upstream, tls_config).expect("failed to serve TLS");
+}
+
+#[cfg(not(feature = "tls"))]
+fn serve_tls(_config: &Configuration, _port: u16) {
+    panic!("TLS requires the server to be built with the tls feature");
+}
+
+/// Returns true iff the request changes state that has to survive a
+/// restart.
+fn is_durable(req: &Request<Aid>) -> bool {
+    match *req {
+        Request::Transact(_)
+        | Request::TransactWithMeta(_, _)
+        | Request::RetractEntity(_, _)
+        | Request::BulkLoad(_)
+        | Request::CreateAttribute(_)
+        | Request::DropAttribute(_)
+        | Request::SetTraceSlack(_, _)
+        | Request::CreateParameter(_)
+        | Request::Bind(_)
+        | Request::Bridge(_)
+        | Request::CreateIndex(_)
+        | Request::Register(_)
+        | Request::Unregister(_)
+        | Request::Materialize(_)
+        | Request::RegisterSource(_)
+        | Request::AdvanceDomain(_, _)
+        | Request::CloseInput(_)
+        | Request::Restore(_)
+        | Request::RestoreSnapshot(_)
+        | Request::Reconfigure(_) => true,
+        _ => false,
+    }
+}
+
+/// Tells a client which entity ids the tempids of its transaction
+/// resolved to.
+fn resolved_tempids(tempids: HashMap<String, Eid>) -> serde_json::Value {
+    serde_json::json!({
+        "category": "df/tempids",
+        "tempids": tempids,
+    })
+}
+
+/// Returns the earliest time any pending snapshot or backup has to
+/// read attribute indices at, if any.
+fn compaction_hold(snapshots: &VecDeque<(TxId, T)>, backups: &VecDeque<(Backup, T)>) -> Option<T> {
+    snapshots
+        .iter()
+        .map(|(_, as_of)| as_of)
+        .chain(backups.iter().map(|(_, as_of)| as_of))
+        .fold(None, |hold: Option<T>, as_of| match hold {
+            None => Some(as_of.clone()),
+            Some(hold) => Some(hold.meet(as_of)),
+        })
+}
+
+/// Truncates the logged commands up to and including transaction
+/// `through`, whose effects on attributes have been captured by a
+/// snapshot. The truncated prefix is replaced by a single command
+/// re-creating everything the snapshot doesn't cover and restoring
+/// the snapshot itself, which is stamped with `through` s.t. later
+/// entries keep their transaction ids when replayed. Log entries
+/// start at transaction `logged_from`.
+fn compact(
+    entries: Vec<Stamped<Command>>,
+    logged_from: TxId,
+    through: TxId,
+) -> Vec<Stamped<Command>> {
+    let truncated = (through + 1).saturating_sub(logged_from) as usize;
+    let mut entries = entries.into_iter();
+
+    let mut requests = Vec::new();
+    let mut closed = Vec::new();
+    let mut issued_at = Duration::from_secs(0);
+
+    for entry in entries.by_ref().take(truncated) {
+        issued_at = entry.issued_at;
+
+        for req in entry.command.requests.into_iter() {
+            match req {
+                // Superseded by the snapshot.
+                Request::Transact(_)
+                | Request::TransactWithMeta(_, _)
+                | Request::RetractEntity(_, _)
+                | Request::BulkLoad(_)
+                | Request::AdvanceDomain(_, _)
+                | Request::Restore(_)
+                | Request::RestoreSnapshot(_) => {}
+                // Inputs can only be closed once restored.
+                Request::CloseInput(name) => closed.push(Request::CloseInput(name)),
+                req => requests.push(req),
+            }
+        }
+    }
+
+    requests.push(Request::RestoreSnapshot(through));
+    requests.extend(closed);
+
+    let preamble = Stamped {
+        issued_at,
+        command: Command {
+            owner: 0,
+            client: SYSTEM.0,
+            requests,
+        },
+        tx: Some(through),
+    };
+
+    std::iter::once(preamble).chain(entries).collect()
+}
+
 fn main() {
     env_logger::init();
 
@@ -229,15 +447,59 @@ fn main() {
     let timely_config: timely::Configuration = config.clone().into();
     let server_config: server::Configuration = config.clone().into();
 
+    #[cfg(all(not(feature = "real-time"), not(feature = "bitemporal")))]
+    {
+        if server_config.clock.is_some() {
+            panic!("The wall clock can only drive domains in real or bitemporal time");
+        }
+    }
+
+    // Metrics are shared by all workers in this process and served
+    // from a thread of their own.
+    let metrics = config.metrics_port.map(|port| {
+        use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+        let metrics = Arc::new(Metrics::new());
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), port);
+
+        metrics::serve(metrics.clone(), addr).expect("failed to serve metrics");
+
+        metrics
+    });
+
+    // The first interrupt or termination signal asks workers to drain
+    // and shut down, a second one gives up on that.
+    let interrupted = Arc::new(AtomicBool::new(false));
+    {
+        let interrupted = interrupted.clone();
+        ctrlc::set_handler(move || {
+            if interrupted.swap(true, Ordering::SeqCst) {
+                warn!("interrupted again, exiting immediately");
+                std::process::exit(130);
+            }
+        })
+        .expect("failed to install signal handler");
+    }
+
     timely::execute(timely_config, move |worker| {
         // Initialize server state (no networking).
         let mut server = Server::<Aid, T, Token>::new_at(server_config.clone(), worker.timer());
+        server.worker_index = worker.index();
+        server.internal.partition_across(worker.index(), worker.peers());
+
+        if let Some(ref metrics) = metrics {
+            server.enable_metrics(Recorder::new(metrics.clone(), worker.index()));
+        }
 
         if server_config.enable_logging {
             #[cfg(feature = "real-time")]
             server.enable_logging(worker).unwrap();
         }
 
+        if server_config.enable_meta {
+            worker.dataflow::<T, _, _>(|scope| server.enable_introspection(scope)).unwrap();
+        }
+
         // The server might specify a sequence of requests for
         // setting-up built-in arrangements. We serialize those here
         // and pre-load the sequencer with them, such that they will
@@ -249,13 +511,76 @@ fn main() {
             requests: builtins,
         };
 
+        // Commands persisted by a previous run are replayed right
+        // after the built-ins, s.t. they are assigned the same
+        // transaction ids as before. Compacted logs start with an
+        // entry stamped with the id it was compacted through.
+        let storage = server_config.storage();
+        let open_storage = || -> Option<Box<dyn StorageBackend>> {
+            storage.as_ref().map(|storage| storage.open().expect("failed to open storage"))
+        };
+
+        let replayed: Vec<Stamped<Command>> = match open_storage() {
+            None => Vec::new(),
+            Some(backend) => WriteAheadLog::with_backend(backend)
+                .entries()
+                .expect("failed to replay write-ahead log"),
+        };
+        let replayed_from = replayed.first().and_then(|entry| entry.tx).unwrap_or(2);
+        let replayed_through = replayed_from + replayed.len() as TxId - 1;
+
+        if !replayed.is_empty() {
+            info!("[W{}] replaying {} commands", worker.index(), replayed.len());
+        }
+
+        // All workers see the same commands, so only the first worker
+        // of each process writes them to its log.
+        let mut wal = if worker.index() % config.cluster.threads == 0 {
+            open_storage().map(WriteAheadLog::with_backend)
+        } else {
+            None
+        };
+
+        // Transaction id of the first entry in the log.
+        let mut logged_from: TxId = replayed_from;
+
+        // Snapshots are taken by all workers, whereas the log is
+        // compacted by those writing it.
+        let snapshot_reader = open_storage();
+        let snapshot_writer: Option<SnapshotWriter<Aid>> =
+            open_storage().map(SnapshotWriter::spawn);
+        let mut pending_snapshots: VecDeque<(TxId, T)> = VecDeque::new();
+        let mut pending_compactions: VecDeque<TxId> = VecDeque::new();
+        let mut pending_backups: VecDeque<(Backup, T)> = VecDeque::new();
+
+        let mut preload = vec![Stamped {
+            issued_at: Duration::from_secs(0),
+            command: preload_command,
+            tx: None,
+        }];
+        preload.extend(replayed);
+
         // Setup serializing command stream between all workers.
-        let mut sequencer: Sequencer<Command> =
-            Sequencer::preloaded(worker, Instant::now(), VecDeque::from(vec![preload_command]));
+        let timer = worker.timer();
+        let mut sequencer: CommandLog<Command> = CommandLog::replayed(worker, timer, preload);
+
+        // Announce this worker to all others. Client requests are
+        // only accepted once all workers have joined, which ensures
+        // that they are sequenced after all announcements and that
+        // every worker sees the same registrations.
+        let mut membership = Membership::new(worker.peers(), server.fingerprint());
+        sequencer.push(Command {
+            owner: worker.index(),
+            client: SYSTEM.0,
+            requests: vec![Request::Rendezvous(Rendezvous {
+                worker: worker.index(),
+                fingerprint: server.fingerprint(),
+            })],
+        });
 
         // Kickoff ticking, if configured. We only want to issue ticks
         // from a single worker, to avoid redundant ticking.
-        if worker.index() == 0 && server_config.tick.is_some() {
+        if worker.index() == 0 && (server_config.tick.is_some() || server_config.clock.is_some()) {
             sequencer.push(Command {
                 owner: 0,
                 client: SYSTEM.0,
@@ -263,6 +588,38 @@ fn main() {
             });
         }
 
+        // Kickoff re-planning checks, if configured. Statistics differ
+        // between workers, so only a single one decides.
+        if worker.index() == 0 && server_config.enable_reordering {
+            if let Some(interval) = server_config.replan_interval {
+                server
+                    .scheduler
+                    .borrow_mut()
+                    .realtime
+                    .event_after(interval, SchedulingEvent::Replan);
+            }
+        }
+
+        // Kickoff snapshots, if configured. Each process writes a log
+        // of its own, which it can only compact once the snapshot
+        // parts of all workers are available to it.
+        if worker.index() == 0 && storage.is_some() {
+            if let Some(interval) = server_config.snapshot_interval {
+                if config.cluster.processes() > 1 {
+                    warn!(
+                        "[W{}] snapshots are not supported across multiple processes",
+                        worker.index()
+                    );
+                } else {
+                    server
+                        .scheduler
+                        .borrow_mut()
+                        .realtime
+                        .event_after(interval, SchedulingEvent::Snapshot);
+                }
+            }
+        }
+
         // Set up I/O event loop.
         let mut io = {
             use std::net::{IpAddr, Ipv4Addr, SocketAddr};
@@ -270,9 +627,51 @@ fn main() {
             // let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), config.port);
             let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0,0,0,0)), config.port);
 
-            IO::new(addr)
+            let mut io = IO::new(addr, config.output_queue_bound, config.overflow_policy);
+
+            if let Some(ref metrics) = metrics {
+                io.recorder = Some(Recorder::new(metrics.clone(), worker.index()));
+            }
+
+            io
         };
 
+        // Additional interfaces can only be bound once per process, so
+        // they are served by the first worker of each.
+        if let Some(port) = config.http_port {
+            use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+            if worker.index() % config.cluster.threads == 0 {
+                let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), port);
+                let frontend =
+                    http::Frontend::serve(addr, config.cluster.process, config.cluster.processes())
+                        .expect("failed to serve HTTP");
+                io.http = Some(frontend);
+            }
+        }
+
+        if let Some(port) = config.tls_port {
+            if worker.index() % config.cluster.threads == 0 {
+                serve_tls(&config, port);
+            }
+        }
+
+        #[cfg(feature = "grpc")]
+        {
+            use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+            if let Some(port) = config.grpc_port {
+                if worker.index() % config.cluster.threads == 0 {
+                    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), port);
+                    io.grpc = Some(grpc::Frontend::serve(
+                        addr,
+                        config.cluster.process,
+                        config.cluster.processes(),
+                    ));
+                }
+            }
+        }
+
         info!(
             "[W{}] running with config {:?}, {} peers",
             worker.index(),
@@ -289,8 +688,24 @@ fn main() {
         // Sequence counter for commands.
         let mut next_tx: TxId = 0;
 
+        // Snapshot handles for all result dataflows, along with the
+        // owning worker and the granularity of results, by interest key.
+        let mut snapshot_requests: HashMap<String, (usize, Option<Time>, SnapshotRequests)> =
+            HashMap::new();
+
+        // Dataflows of one-shot queries, until they have answered.
+        let mut pending_queries: Vec<(Rc<Cell<bool>>, ShutdownHandle)> = Vec::new();
+
         let mut shutdown = false;
 
+        // Once shutdown has been requested, workers reject client
+        // requests until they have all drained. Holds the time of the
+        // request.
+        let mut draining: Option<Instant> = None;
+        let mut drained = false;
+        let mut drained_workers: HashSet<usize> = HashSet::new();
+        let mut signalled = false;
+
         while !shutdown {
             // each worker has to...
             //
@@ -320,6 +735,40 @@ fn main() {
                                     requests: vec![Request::Tick],
                                 });
                             }
+                            SchedulingEvent::Replan => {
+                                let requests: Vec<Request<Aid>> = server
+                                    .replan_candidates()
+                                    .into_iter()
+                                    .map(Request::Replan)
+                                    .collect();
+
+                                if !requests.is_empty() {
+                                    sequencer.push(Command {
+                                        owner: worker.index(),
+                                        client: SYSTEM.0,
+                                        requests,
+                                    });
+                                }
+
+                                if let Some(interval) = server_config.replan_interval {
+                                    scheduler
+                                        .realtime
+                                        .event_after(interval, SchedulingEvent::Replan);
+                                }
+                            }
+                            SchedulingEvent::Snapshot => {
+                                sequencer.push(Command {
+                                    owner: worker.index(),
+                                    client: SYSTEM.0,
+                                    requests: vec![Request::Snapshot],
+                                });
+
+                                if let Some(interval) = server_config.snapshot_interval {
+                                    scheduler
+                                        .realtime
+                                        .event_after(interval, SchedulingEvent::Snapshot);
+                                }
+                            }
                         }
                     }
                 }
@@ -329,36 +778,148 @@ fn main() {
                 // poll.poll(&mut events, None).expect("failed to poll I/O events");
             }
 
-            // Transform low-level I/O events into domain events.
-            io.step(next_tx, &server.interests);
+            // Queries exceeding their limits are killed on all workers.
+            for (name, error) in server.take_violations().into_iter() {
+                warn!("[W{}] killing {}: {}", worker.index(), name, error.message);
 
-            while let Some(event) = io.next() {
-                match event {
-                    DomainEvent::Requests(token, requests) => {
-                        trace!("[IO] command");
-                        sequencer.push(Command {
-                            owner: worker.index(),
-                            client: token.into(),
-                            requests,
-                        });
-                    }
-                    DomainEvent::Disconnect(token) => {
-                        info!("[IO] token={:?} disconnected", token);
-                        sequencer.push(Command {
-                            owner: worker.index(),
-                            client: token.into(),
-                            requests: vec![Request::Disconnect],
-                        });
+                sequencer.push(Command {
+                    owner: worker.index(),
+                    client: SYSTEM.0,
+                    requests: vec![Request::Kill(name)],
+                });
+            }
+
+            // Signals are forwarded by a single worker per process.
+            if !signalled
+                && worker.index() % config.cluster.threads == 0
+                && interrupted.load(Ordering::SeqCst)
+            {
+                info!("[W{}] interrupted, shutting down", worker.index());
+                signalled = true;
+
+                if draining.is_none() {
+                    sequencer.push(Command {
+                        owner: worker.index(),
+                        client: SYSTEM.0,
+                        requests: vec![Request::Shutdown],
+                    });
+                }
+            }
+
+            // Transform low-level I/O events into domain events.
+            if membership.is_complete() {
+                io.step(next_tx, &server.interests);
+
+                while let Some(event) = io.next() {
+                    match event {
+                        DomainEvent::Requests(token, requests) => {
+                            trace!("[IO] command");
+                            sequencer.push(Command {
+                                owner: worker.index(),
+                                client: token.into(),
+                                requests,
+                            });
+                        }
+                        DomainEvent::Disconnect(token) => {
+                            info!("[IO] token={:?} disconnected", token);
+                            sequencer.push(Command {
+                                owner: worker.index(),
+                                client: token.into(),
+                                requests: vec![Request::Disconnect],
+                            });
+                        }
                     }
                 }
             }
 
             // handle commands
 
-            while let Some(mut command) = sequencer.next() {
+            while let Some(sequenced) = sequencer.next() {
+
+                // Sequence numbers and issue times are the same on
+                // all workers.
+                next_tx = sequenced.tx;
+
+                // GraphQL requests are expanded into the requests
+                // implementing them, which are then authorized and
+                // logged in their place.
+                #[cfg(feature = "graphql")]
+                let sequenced = {
+                    let mut sequenced = sequenced;
+                    let client = sequenced.command.client;
+                    let mut expanded = Vec::with_capacity(sequenced.command.requests.len());
+
+                    for req in sequenced.command.requests.drain(..) {
+                        match req {
+                            Request::GraphQl(req) => match server.graphql(req) {
+                                Ok(requests) => expanded.extend(requests),
+                                Err(error) => {
+                                    io.send
+                                        .send(Output::Error(client, error, next_tx - 1))
+                                        .unwrap();
+                                }
+                            },
+                            req => expanded.push(req),
+                        }
+                    }
+
+                    sequenced.command.requests = expanded;
+                    sequenced
+                };
+
+                // Requests are authorized before anything else happens
+                // to them, s.t. rejected ones never make it into the
+                // log. Replayed commands have been authorized before,
+                // and the server trusts itself.
+                let trusted = next_tx <= replayed_through || sequenced.command.client >= SYSTEM.0;
+                let verdicts: Vec<Result<(), Error>> = sequenced.command.requests
+                    .iter()
+                    .map(|req| match req {
+                        _ if trusted => Ok(()),
+                        // Clients may still leave while the server is
+                        // shutting down, but nothing else.
+                        Request::Disconnect => Ok(()),
+                        _ if draining.is_some() => {
+                            Err(Error::conflict("The server is shutting down."))
+                        }
+                        _ => server
+                            .authorize(Token(sequenced.command.client), req)
+                            .and_then(|_| {
+                                server.check_quotas(
+                                    Token(sequenced.command.client),
+                                    req,
+                                    sequenced.issued_at,
+                                )
+                            }),
+                    })
+                    .collect();
+
+                // Commands are persisted before they are handled, and
+                // thus before anything is acknowledged.
+                if let Some(ref mut wal) = wal {
+                    if next_tx > replayed_through {
+                        let durable = Command {
+                            owner: sequenced.command.owner,
+                            client: sequenced.command.client,
+                            requests: sequenced.command.requests
+                                .iter()
+                                .zip(verdicts.iter())
+                                .filter(|(req, verdict)| is_durable(req) && verdict.is_ok())
+                                .map(|(req, _)| req.clone())
+                                .collect(),
+                        };
+
+                        wal.append(&Stamped {
+                            issued_at: sequenced.issued_at,
+                            command: durable,
+                            tx: None,
+                        })
+                        .expect("failed to append to write-ahead log");
+                    }
+                }
 
-                // Count-up sequence numbers.
-                next_tx += 1;
+                let issued_at = sequenced.issued_at;
+                let mut command = sequenced.command;
 
                 trace!("[W{}] {} requests by client {} at {}", worker.index(), command.requests.len(), command.client, next_tx);
 
@@ -366,14 +927,43 @@ fn main() {
                 let client = command.client;
                 let last_tx = next_tx - 1;
 
-                for req in command.requests.drain(..) {
+                for (req, verdict) in command.requests.drain(..).zip(verdicts.into_iter()) {
 
                     // @TODO only create a single dataflow, but only if req != Transact
 
                     trace!("[W{}] {:?}", worker.index(), req);
 
+                    if let Err(error) = verdict {
+                        io.send.send(Output::Error(client, error, last_tx)).unwrap();
+                        continue;
+                    }
+
                     let result = match req {
-                        Request::Transact(req) => server.transact(req, owner, worker.index()),
+                        Request::Transact(req) => {
+                            server.transact(req, owner, worker.index()).map(|tempids| {
+                                // Only the worker owning the client connection answers.
+                                if owner == worker.index() && !tempids.is_empty() {
+                                    io.send
+                                        .send(Output::Message(client, resolved_tempids(tempids)))
+                                        .unwrap();
+                                }
+                            })
+                        }
+                        Request::TransactWithMeta(req, meta) => {
+                            server
+                                .transact_with_meta(req, meta, owner, worker.index())
+                                .map(|tempids| {
+                                    // Only the worker owning the client connection answers.
+                                    if owner == worker.index() && !tempids.is_empty() {
+                                        io.send
+                                            .send(Output::Message(
+                                                client,
+                                                resolved_tempids(tempids),
+                                            ))
+                                            .unwrap();
+                                    }
+                                })
+                        }
                         Request::Subscribe(aid) => {
                             let interests = server.interests
                                 .entry(aid.clone())
@@ -445,9 +1035,13 @@ fn main() {
 
                             Ok(())
                         }
+                        #[cfg(feature = "graphql")]
+                        Request::GraphQl(_) => Err(Error::fault(
+                            "GraphQL requests must be expanded before they are handled.",
+                        )),
                         Request::Interest(req) => {
                             let interests = server.interests
-                                .entry(req.name.clone())
+                                .entry(req.key())
                                 .or_insert_with(HashSet::new);
 
                             // We need to check this, because we only want to setup
@@ -460,6 +1054,7 @@ fn main() {
 
                             if was_first {
                                 let send_results = io.send.clone();
+                                let is_owner = owner == worker.index();
 
                                 let disable_logging = req.disable_logging.unwrap_or(false);
                                 let mut timely_logger = None;
@@ -474,9 +1069,40 @@ fn main() {
                                 let result = worker.dataflow::<T, _, _>(|scope| {
                                     let sink_context: SinkingContext = (&req).into();
 
-                                    let relation = match server.interest(req.name, scope) {
+                                    // Oversized queries are rejected before anything is built.
+                                    let name = req.name.clone();
+                                    let limits = server.limits(req.limits);
+                                    if let Err(error) = server.check_limits(&name, &limits) {
+                                        return Err(error);
+                                    }
+
+                                    if let Some(Err(error)) = req.page.as_ref().map(Page::check) {
+                                        return Err(error);
+                                    }
+
+                                    let interest = match (req.as_of, req.resume_after) {
+                                        (Some(as_of), _) => {
+                                            server.interest_as_of(req.name, as_of.into(), scope)
+                                        }
+                                        (None, Some(after)) => {
+                                            server.interest_after(req.name, after.into(), scope)
+                                        }
+                                        (None, None) => {
+                                            server.interest_using(req.name, scope, req.strategy)
+                                        }
+                                    };
+
+                                    let relation = match interest {
                                         Err(error) => { return Err(error); }
-                                        Ok(relation) => relation,
+                                        Ok(relation) => {
+                                            server.enforce_limits(name, &limits, relation)
+                                        }
+                                    };
+
+                                    // Only changes to the visible page are sent.
+                                    let relation = match req.page {
+                                        None => relation,
+                                        Some(ref page) => page.paginate(relation),
                                     };
 
                                     let delayed = match req.granularity {
@@ -519,27 +1145,54 @@ fn main() {
                                             Ok(())
                                         }
                                         None => {
-                                            delayed
-                                                .inner
-                                                .unary(pact, "ResultsRecv", move |_cap, _info| {
-                                                    move |input, _output: &mut OutputHandle<_, ResultDiff<T>, _>| {
-                                                        // due to the exchange pact, this closure is only
-                                                        // executed by the owning worker
-
-                                                        // @TODO only forward inputs up to the frontier!
-
-                                                        input.for_each(|_time, data| {
-                                                            let data = data.iter()
-                                                                .map(|(tuple, t, diff)| (tuple.clone(), t.clone().into(), *diff))
-                                                                .collect::<Vec<ResultDiff<Time>>>();
-
-                                                            send_results
-                                                                .send(Output::QueryDiff(sink_context.name.clone(), data))
-                                                                .expect("internal channel send failed");
-                                                        });
-                                                    }
-                                                })
-                                                .probe_with(&mut server.probe);
+                                            // Resuming clients already hold everything
+                                            // up to their token, and only need what
+                                            // they have missed since.
+                                            let requests = if req.resume_after.is_some() {
+                                                SnapshotRequests::resuming().with_resume_tokens()
+                                            } else {
+                                                let requests =
+                                                    SnapshotRequests::new().with_resume_tokens();
+                                                if is_owner {
+                                                    requests.request(client);
+                                                }
+                                                requests
+                                            };
+                                            snapshot_requests.insert(
+                                                req.key(),
+                                                (owner, req.granularity.clone(), requests.clone()),
+                                            );
+
+                                            // Due to the exchange pact, only the owning
+                                            // worker forwards results and serves snapshots.
+                                            let batch_size = server.batch_size();
+                                            let send = move |out: Output| {
+                                                let outputs = match batch_size.get() {
+                                                    None => vec![out],
+                                                    Some(batch_size) => out.split(batch_size),
+                                                };
+
+                                                for out in outputs.into_iter() {
+                                                    send_results
+                                                        .send(out)
+                                                        .expect("internal channel send failed");
+                                                }
+                                            };
+
+                                            let forwarded = if server_config.batch_results {
+                                                delayed.inner.batched_snapshots(
+                                                    pact,
+                                                    &req.key(),
+                                                    &requests,
+                                                    send,
+                                                )
+                                            } else {
+                                                delayed
+                                                    .inner
+                                                    .snapshots(pact, &req.key(), &requests, send)
+                                            };
+
+                                            forwarded.probe_with(&mut server.probe);
 
                                             Ok(())
                                         }
@@ -566,14 +1219,56 @@ fn main() {
 
                                 result
                             } else {
+                                // Later subscribers are caught up via a
+                                // snapshot, served by the worker owning the
+                                // results.
+                                if let Some((results_owner, _, requests)) =
+                                    snapshot_requests.get(&req.key())
+                                {
+                                    if *results_owner == worker.index() {
+                                        requests.request(client);
+                                    }
+                                }
+
                                 Ok(())
                             }
                         }
-                        Request::Uninterest(name) => server.uninterest(Token(command.client), &name),
+                        Request::Resnapshot(name) => {
+                            if let Some((results_owner, _, requests)) = snapshot_requests.get(&name)
+                            {
+                                if *results_owner == worker.index() {
+                                    requests.request(client);
+                                }
+                            }
+
+                            Ok(())
+                        }
+                        Request::Uninterest(name) => {
+                            let result = server.uninterest(Token(command.client), &name);
+
+                            if !server.interests.contains_key(&name) {
+                                snapshot_requests.remove(&name);
+                            }
+
+                            result
+                        }
                         Request::Register(req) => server.register(req),
+                        Request::Unregister(name) => server.unregister(&name),
+                        Request::Materialize(name) => {
+                            worker.dataflow::<T, _, _>(|scope| {
+                                server.materialize(scope, name)
+                            })
+                        }
                         Request::RegisterSource(source) => {
                             worker.dataflow::<T, _, _>(|scope| {
-                                server.register_source(Box::new(source), scope)
+                                match source {
+                                    // Introspection goes into the system domain,
+                                    // s.t. it never holds back user inputs.
+                                    Source::TimelyLogging(_) | Source::DifferentialLogging(_) => {
+                                        server.register_system_source(Box::new(source), scope)
+                                    }
+                                    _ => server.register_source(Box::new(source), scope),
+                                }
                             })
                         }
                         Request::CreateAttribute(CreateAttribute { name, config }) => {
@@ -581,25 +1276,246 @@ fn main() {
                                 server.create_attribute(scope, name, config)
                             })
                         }
+                        Request::RetractEntity(tenant, e) => {
+                            server.retract_entity(tenant, e, owner, worker.index())
+                        }
+                        Request::BulkLoad(BulkLoad { name, data }) => {
+                            server.bulk_load(name, data, owner, worker.index())
+                        }
+                        Request::SetTraceSlack(name, slack) => {
+                            server
+                                .domain_of_mut(&name)
+                                .and_then(|domain| domain.set_trace_slack(&name, slack))
+                        }
+                        Request::Reconfigure(req) => server.reconfigure(req),
+                        Request::Kill(name) => match server.kill(&name) {
+                            // Violations may be observed by several workers at
+                            // once, only the first kill has anything to do.
+                            Err(_) if client == SYSTEM.0 => Ok(()),
+                            Err(error) => Err(error),
+                            Ok(killed) => {
+                                for (key, clients) in killed.into_iter() {
+                                    if let Some((results_owner, _, requests)) =
+                                        snapshot_requests.remove(&key)
+                                    {
+                                        requests.retire();
+
+                                        // Clients are notified by the worker that
+                                        // would have sent them results.
+                                        if results_owner == worker.index() {
+                                            for token in clients.iter() {
+                                                let error = Error::interrupted(format!(
+                                                    "Query {} has been killed.",
+                                                    name
+                                                ));
+                                                io.send
+                                                    .send(Output::Error(token.0, error, last_tx))
+                                                    .unwrap();
+                                            }
+                                        }
+                                    }
+                                }
+
+                                Ok(())
+                            }
+                        },
+                        Request::DropAttribute(name) => server.drop_attribute(&name),
+                        Request::Evict(name) => server.evict(&name, worker.index()),
+                        Request::CreateParameter(name) => {
+                            worker.dataflow::<T, _, _>(|scope| {
+                                server.create_parameter(scope, name)
+                            })
+                        }
+                        Request::Bind(Bind { name, tuples }) => {
+                            server.bind(name, tuples, owner, worker.index())
+                        }
+                        Request::Bridge(req) => {
+                            worker.dataflow::<T, _, _>(|scope| {
+                                server.bridge(scope, req)
+                            })
+                        }
+                        Request::CreateIndex(req) => {
+                            worker.dataflow::<T, _, _>(|scope| {
+                                server.create_index(scope, req)
+                            })
+                        }
                         Request::AdvanceDomain(name, next) => server.advance_domain(name, next.into()),
-                        Request::CloseInput(name) => server.internal.close_input(name),
-                        Request::Disconnect => server.disconnect_client(Token(command.client)),
+                        Request::CloseInput(name) => {
+                            server.domain_of_mut(&name).and_then(|domain| domain.close_input(name))
+                        }
+                        Request::Disconnect => {
+                            let result = server.disconnect_client(Token(command.client));
+
+                            let interests = &server.interests;
+                            snapshot_requests.retain(|name, _| interests.contains_key(name));
+
+                            result
+                        }
+                        Request::Authenticate(_) => Ok(()),
                         Request::Setup => unimplemented!(),
                         Request::Tick => {
-                            // We don't actually have to do any actual worker here, because we are
-                            // ticking the domain on each command anyways. We do have to schedule
-                            // the next tick, however.
+                            // We are ticking the domain on each command anyways, unless it
+                            // has to be advanced manually. The wall clock advances it
+                            // regardless, if configured. We do have to schedule the next
+                            // tick, however.
+                            let result = server.advance_clock(issued_at);
 
                             // We only want to issue ticks from a single worker, to avoid
                             // redundant ticking.
                             if worker.index() == 0 {
-                                if let Some(tick) = server_config.tick {
+                                if let Some(tick) = server_config.clock.or(server_config.tick) {
                                     let interval_end = Instant::now().duration_since(worker.timer()).coarsen(&tick);
                                     let at = worker.timer() + interval_end;
                                     server.scheduler.borrow_mut().realtime.event_at(at, SchedulingEvent::Tick);
                                 }
                             }
 
+                            result
+                        }
+                        Request::Explain(req) => {
+                            // Only the worker owning the client connection answers.
+                            if owner == worker.index() {
+                                let explanation = server.explain(&req.plan, req.strategy);
+
+                                let explained = serde_json::json!({
+                                    "category": "df/explain",
+                                    "explanation": serde_json::to_value(explanation).unwrap(),
+                                });
+
+                                io.send.send(Output::Message(client, explained)).unwrap();
+                            }
+
+                            Ok(())
+                        }
+                        Request::Query(req) => {
+                            let send_results = io.send.clone();
+                            let is_owner = owner == worker.index();
+
+                            worker.dataflow::<T, _, _>(|scope| {
+                                // Results include everything handled before
+                                // this request.
+                                let as_of = server.query_epoch(&req)?;
+
+                                let (relation, shutdown_handle) = server.query(req, scope)?;
+                                let pact = Exchange::new(move |_| owner as u64);
+
+                                let done = relation
+                                    .inner
+                                    .one_shot(pact, "df/query", client, as_of, move |out| {
+                                        // Only the owning worker holds results.
+                                        if is_owner {
+                                            send_results
+                                                .send(out)
+                                                .expect("internal channel send failed");
+                                        }
+                                    });
+
+                                pending_queries.push((done, shutdown_handle));
+
+                                Ok(())
+                            })
+                        }
+                        Request::Replan(req) => {
+                            // Only relations forwarded to clients directly
+                            // are re-planned, sinks and pinned interests
+                            // are left alone.
+                            match snapshot_requests.get(&req.name).cloned() {
+                                None => Ok(()),
+                                Some((results_owner, granularity, previous)) => {
+                                    let send_results = io.send.clone();
+                                    let name = req.name.clone();
+                                    let requests = SnapshotRequests::new().with_resume_tokens();
+
+                                    let result = worker.dataflow::<T, _, _>(|scope| {
+                                        let relation = server.replan(req, scope)?;
+
+                                        let delayed = match granularity {
+                                            None => relation.consolidate(),
+                                            Some(granularity) => {
+                                                let granularity: T = granularity.into();
+                                                relation
+                                                    .delay(move |t| t.coarsen(&granularity))
+                                                    .consolidate()
+                                            }
+                                        };
+
+                                        let pact = Exchange::new(move |_| results_owner as u64);
+
+                                        delayed
+                                            .inner
+                                            .snapshots(pact, &name, &requests, move |out| {
+                                                send_results
+                                                    .send(out)
+                                                    .expect("internal channel send failed");
+                                            })
+                                            .probe_with(&mut server.probe);
+
+                                        Ok(())
+                                    });
+
+                                    if result.is_ok() {
+                                        // Clients are handed over to the new dataflow,
+                                        // starting with a fresh snapshot.
+                                        previous.retire();
+
+                                        if results_owner == worker.index() {
+                                            if let Some(clients) = server.interests.get(&name) {
+                                                for token in clients.iter() {
+                                                    requests.request(token.0);
+                                                }
+                                            }
+                                        }
+
+                                        snapshot_requests.insert(
+                                            name,
+                                            (results_owner, granularity, requests),
+                                        );
+                                    }
+
+                                    result
+                                }
+                            }
+                        }
+                        Request::Schema => {
+                            // Only the worker owning the client connection answers.
+                            if owner == worker.index() {
+                                let attributes: serde_json::Map<String, serde_json::Value> = server
+                                    .internal
+                                    .schema()
+                                    .into_iter()
+                                    .map(|(name, config)| {
+                                        (name, serde_json::to_value(config).unwrap())
+                                    })
+                                    .collect();
+
+                                #[allow(unused_mut)]
+                                let mut schema = serde_json::json!({
+                                    "category": "df/schema",
+                                    "attributes": attributes,
+                                });
+
+                                #[cfg(feature = "graphql")]
+                                {
+                                    let graphql = declarative_dataflow::plan::graphql::schema(
+                                        &server.internal.schema(),
+                                    );
+                                    schema["graphql"] = serde_json::Value::String(graphql);
+                                }
+
+                                io.send.send(Output::Message(client, schema)).unwrap();
+                            }
+
+                            Ok(())
+                        }
+                        Request::IndexSizes => {
+                            let sizes = serde_json::json!({
+                                "category": "df/index-sizes",
+                                "worker": worker.index(),
+                                "indices": serde_json::to_value(server.index_sizes()).unwrap(),
+                            });
+
+                            io.send.send(Output::Message(client, sizes)).unwrap();
+
                             Ok(())
                         }
                         Request::Status => {
@@ -613,9 +1529,178 @@ fn main() {
                             Ok(())
                         }
                         Request::Shutdown => {
-                            shutdown = true;
+                            if draining.is_none() {
+                                info!("[W{}] draining", worker.index());
+                                draining = Some(Instant::now());
+
+                                // Commands up to this one are covered by
+                                // the final snapshot, later ones are
+                                // rejected.
+                                if server_config.shutdown_snapshot {
+                                    if snapshot_writer.is_none()
+                                        || server_config.manual_advance
+                                        || !server.tenants.is_empty()
+                                    {
+                                        warn!("[W{}] can't write a final snapshot", worker.index());
+                                    } else {
+                                        let as_of = server.internal.epoch().clone();
+
+                                        pending_snapshots.push_back((next_tx, as_of));
+                                        server
+                                            .internal
+                                            .hold_compaction(compaction_hold(
+                                                &pending_snapshots,
+                                                &pending_backups,
+                                            ));
+
+                                        if wal.is_some() {
+                                            pending_compactions.push_back(next_tx);
+                                        }
+                                    }
+                                }
+                            }
+
                             Ok(())
                         }
+                        Request::Drained(drained_worker) => {
+                            drained_workers.insert(drained_worker);
+
+                            if drained_workers.len() == worker.peers() {
+                                info!(
+                                    "[W{}] all {} workers drained",
+                                    worker.index(),
+                                    worker.peers()
+                                );
+                                shutdown = true;
+                            }
+
+                            Ok(())
+                        }
+                        Request::Rendezvous(rendezvous) => {
+                            let result = membership.join(&rendezvous);
+
+                            match result {
+                                Err(ref error) => {
+                                    // Workers disagreeing on their setup can't
+                                    // be reconciled.
+                                    error!("[W{}] {}", worker.index(), error.message);
+                                    shutdown = true;
+                                }
+                                Ok(_) => {
+                                    if membership.is_complete() {
+                                        info!(
+                                            "[W{}] all {} workers joined",
+                                            worker.index(),
+                                            worker.peers()
+                                        );
+                                    }
+                                }
+                            }
+
+                            result
+                        }
+                        Request::Snapshot => {
+                            if snapshot_writer.is_none() {
+                                Err(Error::unsupported("Snapshots require a write-ahead log."))
+                            } else if server_config.manual_advance {
+                                Err(Error::unsupported(
+                                    "Snapshots require automatic domain advances.",
+                                ))
+                            } else if !server.tenants.is_empty() {
+                                Err(Error::unsupported(
+                                    "Snapshots don't cover the domains of tenants.",
+                                ))
+                            } else {
+                                // Commands before this one have been applied at
+                                // earlier epochs, later ones will be applied at
+                                // later epochs. The current epoch thus separates
+                                // the two, on all workers.
+                                let as_of = server.internal.epoch().clone();
+
+                                pending_snapshots.push_back((next_tx, as_of));
+                                server
+                                    .internal
+                                    .hold_compaction(compaction_hold(
+                                        &pending_snapshots,
+                                        &pending_backups,
+                                    ));
+
+                                if wal.is_some() {
+                                    pending_compactions.push_back(next_tx);
+                                }
+
+                                Ok(())
+                            }
+                        }
+                        Request::Backup(req) => {
+                            if !server.internal.has_attribute(&req.name) {
+                                Err(Error::not_found(format!(
+                                    "Attribute {} does not exist.",
+                                    req.name
+                                )))
+                            } else if server_config.manual_advance {
+                                Err(Error::unsupported(
+                                    "Backups require automatic domain advances.",
+                                ))
+                            } else {
+                                // Like snapshots, backups are taken as of
+                                // the epoch separating earlier commands from
+                                // later ones.
+                                let as_of = server.internal.epoch().clone();
+
+                                pending_backups.push_back((req, as_of));
+                                server
+                                    .internal
+                                    .hold_compaction(compaction_hold(
+                                        &pending_snapshots,
+                                        &pending_backups,
+                                    ));
+
+                                Ok(())
+                            }
+                        }
+                        Request::Restore(req) => {
+                            if !server.internal.has_attribute(&req.name) {
+                                Err(Error::not_found(format!(
+                                    "Attribute {} does not exist.",
+                                    req.name
+                                )))
+                            } else {
+                                backup::read::<Aid, _>(&req.directory).and_then(|parts| {
+                                    let name = req.name.clone();
+                                    let tx_data = parts
+                                        .into_iter()
+                                        .flat_map(|part| part.tuples.into_iter())
+                                        .map(|(e, v, count)| Datom(e, name.clone(), v, None, count))
+                                        .collect();
+
+                                    server.transact(tx_data, 0, worker.index()).map(|_tempids| ())
+                                })
+                            }
+                        }
+                        Request::RestoreSnapshot(through) => match snapshot_reader {
+                            None => Err(Error::unsupported("Snapshots require a write-ahead log.")),
+                            Some(ref backend) => {
+                                snapshot::read::<Aid>(&**backend, through).and_then(|attributes| {
+                                    let tx_data = attributes
+                                        .into_iter()
+                                        .flat_map(|(a, tuples)| {
+                                            tuples.into_iter().map(move |(e, v, count)| {
+                                                Datom(e, a.clone(), v, None, count)
+                                            })
+                                        })
+                                        .collect();
+
+                                    info!(
+                                        "[W{}] restoring snapshot through {}",
+                                        worker.index(),
+                                        through
+                                    );
+
+                                    server.transact(tx_data, 0, worker.index()).map(|_tempids| ())
+                                })
+                            }
+                        },
                     };
 
                     if let Err(error) = result {
@@ -623,15 +1708,45 @@ fn main() {
                     }
                 }
 
+                // HTTP requests and gRPC calls authenticate anew with
+                // each command, and don't accumulate usage across
+                // commands either.
+                if http::is_http(Token(client)) {
+                    server.forget_principal(Token(client));
+                    server.forget_usage(Token(client));
+                }
+
+                #[cfg(feature = "grpc")]
+                {
+                    if grpc::is_grpc(Token(client)) {
+                        server.forget_principal(Token(client));
+                        server.forget_usage(Token(client));
+                    }
+                }
+
+                // gRPC calls return once all of their requests have
+                // been handled, unless they failed already.
+                #[cfg(feature = "grpc")]
+                {
+                    if owner == worker.index() && grpc::is_grpc(Token(client)) {
+                        let done = serde_json::json!({
+                            "category": grpc::DONE,
+                            "tx": next_tx,
+                        });
+
+                        io.send.send(Output::Message(client, done)).unwrap();
+                    }
+                }
+
                 if !server_config.manual_advance {
                     #[cfg(all(not(feature = "real-time"), not(feature = "bitemporal")))]
                     let next = next_tx as u64;
                     #[cfg(feature = "real-time")]
-                    let next = Instant::now().duration_since(worker.timer());
+                    let next = issued_at;
                     #[cfg(feature = "bitemporal")]
-                    let next = Pair::new(Instant::now().duration_since(worker.timer()), next_tx as u64);
+                    let next = Pair::new(issued_at, next_tx as u64);
 
-                    server.internal.advance_epoch(next).expect("failed to advance epoch");
+                    server.advance_epochs(next).expect("failed to advance epoch");
                 }
             }
 
@@ -644,11 +1759,143 @@ fn main() {
                 worker.step();
             }
 
+            // Dropping the shutdown handle of an answered query
+            // releases the traces its dataflow imported.
+            pending_queries.retain(|(done, _shutdown_handle)| !done.get());
+
             // We advance before `step_or_park`, because advancing
             // might take a decent amount of time, in case traces get
             // compacted. If that happens, we can park less before
             // scheduling the next activator.
-            server.internal.advance().expect("failed to advance domain");
+            server.advance().expect("failed to advance domain");
+
+            // Pending backups are written as soon as the attribute's
+            // indices have caught up with them.
+            while let Some((req, as_of)) = pending_backups.pop_front() {
+                match server.internal.contents(&req.name, &as_of) {
+                    Ok(None) => {
+                        pending_backups.push_front((req, as_of));
+                        break;
+                    }
+                    Ok(Some(tuples)) => {
+                        let part = BackupPart {
+                            name: req.name.clone(),
+                            config: server.internal.attributes[&req.name].clone(),
+                            as_of: as_of.into(),
+                            worker: worker.index(),
+                            tuples,
+                        };
+
+                        match backup::write_part(&req.directory, &part) {
+                            Err(error) => error!(
+                                "[W{}] failed to back up {}: {}",
+                                worker.index(),
+                                req.name,
+                                error.message
+                            ),
+                            Ok(_) => info!(
+                                "[W{}] backed up {} to {}",
+                                worker.index(),
+                                req.name,
+                                req.directory
+                            ),
+                        }
+                    }
+                    Err(error) => {
+                        // The attribute was dropped in the meantime.
+                        error!(
+                            "[W{}] failed to back up {}: {}",
+                            worker.index(),
+                            req.name,
+                            error.message
+                        );
+                    }
+                }
+            }
+
+            // Pending snapshots are written as soon as all indices
+            // have caught up with them.
+            if let Some(ref snapshot_writer) = snapshot_writer {
+                while let Some((through, as_of)) = pending_snapshots.pop_front() {
+                    match server.internal.snapshot(&as_of) {
+                        None => {
+                            pending_snapshots.push_front((through, as_of));
+                            break;
+                        }
+                        Some(attributes) => {
+                            let part = SnapshotPart { through, worker: worker.index(), attributes };
+                            snapshot_writer.write(part);
+                        }
+                    }
+                }
+
+                if let Some(ref mut wal) = wal {
+                    while let Some(through) = pending_compactions.pop_front() {
+                        if !snapshot::is_complete(wal.backend(), through, worker.peers()) {
+                            pending_compactions.push_front(through);
+                            break;
+                        }
+
+                        let entries: Vec<Stamped<Command>> =
+                            wal.entries().expect("failed to read write-ahead log");
+
+                        wal.rewrite(&compact(entries, logged_from, through))
+                            .expect("failed to compact write-ahead log");
+                        logged_from = through;
+
+                        if let Err(error) = wal.backend_mut().retain_snapshot(through) {
+                            warn!(
+                                "[W{}] failed to remove old snapshots: {}",
+                                worker.index(),
+                                error.message
+                            );
+                        }
+
+                        info!(
+                            "[W{}] compacted write-ahead log through {}",
+                            worker.index(),
+                            through
+                        );
+                    }
+                }
+            }
+
+            server.internal.hold_compaction(compaction_hold(&pending_snapshots, &pending_backups));
+
+            // While shutting down, each worker announces when its
+            // outputs have caught up with the final epoch and nothing
+            // else remains to be written or answered. It keeps stepping
+            // afterwards, s.t. the others can make progress as well.
+            if let Some(requested_at) = draining {
+                if !drained {
+                    let caught_up = pending_snapshots.is_empty()
+                        && pending_compactions.is_empty()
+                        && pending_backups.is_empty()
+                        && pending_queries.is_empty()
+                        && !server.is_any_outdated();
+
+                    let timed_out = server_config.drain_timeout
+                        .map(|timeout| requested_at.elapsed() >= timeout)
+                        .unwrap_or(false);
+
+                    if caught_up || timed_out {
+                        if !caught_up {
+                            warn!(
+                                "[W{}] gave up on draining after {:?}",
+                                worker.index(),
+                                requested_at.elapsed()
+                            );
+                        }
+
+                        drained = true;
+                        sequencer.push(Command {
+                            owner: worker.index(),
+                            client: SYSTEM.0,
+                            requests: vec![Request::Drained(worker.index())],
+                        });
+                    }
+                }
+            }
 
             // Finally, we give the CPU a chance to chill, if no work
             // remains.
@@ -660,6 +1907,11 @@ fn main() {
 
         drop(sequencer);
 
+        // Snapshot parts still queued must not be lost.
+        if let Some(snapshot_writer) = snapshot_writer {
+            snapshot_writer.finish();
+        }
+
         // Shutdown loggers s.t. logging dataflows can shut down.
         #[cfg(feature = "real-time")]
         server.shutdown_logging(worker).unwrap();
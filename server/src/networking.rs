@@ -1,5 +1,6 @@
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::net::SocketAddr;
+use std::str::FromStr;
 use std::time::Duration;
 
 use slab::Slab;
@@ -11,9 +12,13 @@ use mio_extras::channel;
 
 use ws::connection::{ConnEvent, Connection};
 
+use declarative_dataflow::metrics::{self, Recorder};
 use declarative_dataflow::server::Request;
 use declarative_dataflow::{Error, Output};
 
+#[cfg(feature = "grpc")]
+use crate::grpc::{self, Frontend};
+use crate::http;
 use crate::Aid;
 
 const SERVER: Token = Token(std::usize::MAX - 1);
@@ -30,6 +35,80 @@ pub enum DomainEvent {
 
 use DomainEvent::*;
 
+/// Wire format spoken on a client connection. Connections speak
+/// JSON, unless the first message a client sends is a binary frame,
+/// in which case they switch to MessagePack in both directions for
+/// as long as they remain open.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// Requests and outputs as JSON text frames.
+    Json,
+    /// Requests and outputs as MessagePack binary frames.
+    MessagePack,
+}
+
+impl Encoding {
+    /// Decodes a batch of requests from a message.
+    fn decode(message: &ws::Message) -> Result<Vec<Request<Aid>>, Error> {
+        match message {
+            ws::Message::Text(string) => serde_json::from_str(string).map_err(Error::incorrect),
+            ws::Message::Binary(bytes) => rmp_serde::from_slice(bytes).map_err(Error::incorrect),
+        }
+    }
+
+    /// Encodes an output as a message.
+    fn encode(self, out: &Output) -> ws::Message {
+        match self {
+            Encoding::Json => ws::Message::text(
+                serde_json::to_string::<Output>(out).expect("failed to serialize output"),
+            ),
+            Encoding::MessagePack => ws::Message::binary(
+                rmp_serde::to_vec_named(out).expect("failed to serialize output"),
+            ),
+        }
+    }
+}
+
+/// What to do about a client whose output queue has grown beyond
+/// its bound, because it doesn't keep up with its results.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Closes the connection.
+    Disconnect,
+    /// Discards everything queued and catches the client up via fresh
+    /// snapshots of all of its subscriptions.
+    Resnapshot,
+    /// Discards further results until the queue has drained, then
+    /// catches the client up via fresh snapshots of the subscriptions
+    /// affected.
+    Pause,
+}
+
+impl FromStr for OverflowPolicy {
+    type Err = String;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy {
+            "disconnect" => Ok(OverflowPolicy::Disconnect),
+            "resnapshot" => Ok(OverflowPolicy::Resnapshot),
+            "pause" => Ok(OverflowPolicy::Pause),
+            other => Err(format!("unknown overflow policy {}", other)),
+        }
+    }
+}
+
+/// Outputs waiting to be handed to a client connection.
+#[derive(Default)]
+struct OutputQueue {
+    messages: VecDeque<ws::Message>,
+    /// Results whose outputs are discarded until a fresh snapshot of
+    /// them arrives.
+    stale: HashSet<String>,
+    /// Results whose outputs were discarded since the queue overflowed,
+    /// if it has.
+    paused: Option<HashSet<String>>,
+}
+
 /// State for translating low-level I/O events into domain events.
 pub struct IO {
     // Event loop.
@@ -51,10 +130,29 @@ pub struct IO {
     next_connection_id: u32,
     // WebSocket settings.
     ws_settings: ws::Settings,
+    // Wire format negotiated by each client connection.
+    encodings: HashMap<Token, Encoding>,
+    // Outputs not yet handed to each client connection.
+    queues: HashMap<Token, OutputQueue>,
+    // Number of messages a client's queue may hold, if limited.
+    queue_bound: Option<usize>,
+    // What to do about clients exceeding the bound.
+    overflow_policy: OverflowPolicy,
+    /// Where to report queue depths, if anywhere.
+    pub recorder: Option<Recorder>,
+    /// HTTP interface, if served by this worker.
+    pub http: Option<http::Frontend>,
+    /// gRPC interface, if served by this worker.
+    #[cfg(feature = "grpc")]
+    pub grpc: Option<Frontend>,
 }
 
 impl IO {
-    pub fn new(address: SocketAddr) -> Self {
+    pub fn new(
+        address: SocketAddr,
+        queue_bound: Option<usize>,
+        overflow_policy: OverflowPolicy,
+    ) -> Self {
         let poll = Poll::new().expect("failed to setup event loop");
 
         let (send, recv) = channel::channel::<Output>();
@@ -88,6 +186,14 @@ impl IO {
             connections: Slab::with_capacity(ws_settings.max_connections),
             next_connection_id: 0,
             ws_settings,
+            encodings: HashMap::new(),
+            queues: HashMap::new(),
+            queue_bound,
+            overflow_policy,
+            recorder: None,
+            http: None,
+            #[cfg(feature = "grpc")]
+            grpc: None,
         }
     }
 
@@ -98,6 +204,26 @@ impl IO {
             .poll(&mut self.events, Some(Duration::from_millis(0)))
             .expect("failed to poll I/O events");
 
+        if let Some(ref frontend) = self.http {
+            while let Some(event) = frontend.next_event() {
+                self.domain_events.push_back(event);
+            }
+        }
+
+        #[cfg(feature = "grpc")]
+        {
+            if let Some(ref frontend) = self.grpc {
+                while let Some(event) = frontend.next_event() {
+                    self.domain_events.push_back(event);
+                }
+            }
+        }
+
+        // Results and connections that became writable are handled
+        // once all events have been processed.
+        let mut results_ready = false;
+        let mut flushable = Vec::new();
+
         for event in self.events.iter() {
             trace!("[IO] recv event on {:?}", event.token());
 
@@ -145,68 +271,7 @@ impl IO {
                     }
                 }
                 RESULTS => {
-                    while let Ok(out) = self.recv.try_recv() {
-                        let tokens: Box<dyn Iterator<Item = Token>> = match &out {
-                            &Output::QueryDiff(ref name, ref results) => {
-                                info!("[IO] {} {} results", name, results.len());
-
-                                match interests.get(name) {
-                                    None => {
-                                        warn!("result on query {} w/o interested clients", name);
-                                        Box::new(std::iter::empty())
-                                    }
-                                    Some(tokens) => Box::new(tokens.iter().cloned()),
-                                }
-                            }
-                            &Output::Json(ref name, _, _, _) => {
-                                info!("[IO] json on query {}", name);
-
-                                match interests.get(name) {
-                                    None => {
-                                        warn!("result on query {} w/o interested clients", name);
-                                        Box::new(std::iter::empty())
-                                    }
-                                    Some(tokens) => Box::new(tokens.iter().cloned()),
-                                }
-                            }
-                            &Output::Message(client, ref msg) => {
-                                info!("[IO] {:?}", msg);
-                                Box::new(std::iter::once(client.into()))
-                            }
-                            &Output::Error(client, ref error, _) => {
-                                error!("[IO] {:?}", error);
-                                Box::new(std::iter::once(client.into()))
-                            }
-                        };
-
-                        let serialized = serde_json::to_string::<Output>(&out)
-                            .expect("failed to serialize output");
-
-                        let msg = ws::Message::text(serialized);
-
-                        for token in tokens {
-                            match self.connections.get_mut(token.into()) {
-                                None => {
-                                    // @TODO we need to clean up the connection here
-                                    warn!("client {:?} has gone away undetected", token);
-                                    self.domain_events.push_back(Disconnect(token));
-                                }
-                                Some(conn) => {
-                                    conn.send_message(msg.clone())
-                                        .expect("failed to send message");
-
-                                    self.poll
-                                        .reregister(
-                                            conn.socket(),
-                                            conn.token(),
-                                            conn.events(),
-                                            PollOpt::edge() | PollOpt::oneshot(),
-                                        )
-                                        .unwrap();
-                                }
-                            }
-                        }
-                    }
+                    results_ready = true;
 
                     self.poll
                         .reregister(
@@ -219,6 +284,12 @@ impl IO {
                 }
                 _ => {
                     let token = event.token();
+
+                    // The connection might have been closed already.
+                    if !self.connections.contains(token.into()) {
+                        continue;
+                    }
+
                     let active = {
                         let event_readiness = event.readiness();
 
@@ -250,25 +321,24 @@ impl IO {
                            match conn_event {
                                 ConnEvent::Message(msg) => {
                                     trace!("[WS] ConnEvent::Message");
-                                    match msg {
-                                        ws::Message::Text(string) => {
-                                            match serde_json::from_str::<Vec<Request<Aid>>>(&string) {
-                                                Err(serde_error) => {
-                                                    self.send
-                                                        .send(Output::Error(
-                                                            token.into(),
-                                                            Error::incorrect(serde_error),
-                                                            t,
-                                                        ))
-                                                        .unwrap();
-                                                }
-                                                Ok(requests) => {
-                                                    self.domain_events
-                                                        .push_back(Requests(token, requests));
-                                                }
-                                            }
+
+                                    // The first message decides the format of
+                                    // everything sent to this client.
+                                    self.encodings.entry(token).or_insert(match msg {
+                                        ws::Message::Text(_) => Encoding::Json,
+                                        ws::Message::Binary(_) => Encoding::MessagePack,
+                                    });
+
+                                    match Encoding::decode(&msg) {
+                                        Err(error) => {
+                                            self.send
+                                                .send(Output::Error(token.into(), error, t))
+                                                .unwrap();
+                                        }
+                                        Ok(requests) => {
+                                            self.domain_events
+                                                .push_back(Requests(token, requests));
                                         }
-                                        ws::Message::Binary(_) => unimplemented!(),
                                     }
                                 }
                                 ConnEvent::Close(code, reason) => {
@@ -294,21 +364,290 @@ impl IO {
                     // state if the handshake fails.
                     if !active {
                         self.domain_events.push_back(Disconnect(token.clone()));
-                        self.connections.remove(token.into());
+                        self.close(token);
                     } else {
-                        let conn = &self.connections[token.into()];
-                        self.poll
-                            .reregister(
-                                conn.socket(),
-                                conn.token(),
-                                conn.events(),
-                                PollOpt::edge() | PollOpt::oneshot(),
-                            )
-                            .unwrap();
+                        flushable.push(token);
                     }
                 }
             }
         }
+
+        for token in flushable.into_iter() {
+            self.flush(token, interests);
+        }
+
+        if results_ready {
+            self.deliver(interests);
+        }
+    }
+
+    /// Routes all outputs received via the internal channel to their
+    /// clients.
+    fn deliver(&mut self, interests: &HashMap<String, HashSet<Token>>) {
+        while let Ok(out) = self.recv.try_recv() {
+            let tokens: Box<dyn Iterator<Item = Token>> = match &out {
+                &Output::QueryDiff(ref name, ref results) => {
+                    info!("[IO] {} {} results", name, results.len());
+
+                    match interests.get(name) {
+                        None => {
+                            warn!("result on query {} w/o interested clients", name);
+                            Box::new(std::iter::empty())
+                        }
+                        Some(tokens) => Box::new(tokens.iter().cloned()),
+                    }
+                }
+                &Output::QueryBatch(ref name, _, ref results) => {
+                    info!("[IO] {} {} batched results", name, results.len());
+
+                    match interests.get(name) {
+                        None => {
+                            warn!("result on query {} w/o interested clients", name);
+                            Box::new(std::iter::empty())
+                        }
+                        Some(tokens) => Box::new(tokens.iter().cloned()),
+                    }
+                }
+                &Output::ResumeToken(ref name, ref time) => {
+                    trace!("[IO] {} complete through {:?}", name, time);
+
+                    match interests.get(name) {
+                        None => Box::new(std::iter::empty()),
+                        Some(tokens) => Box::new(tokens.iter().cloned()),
+                    }
+                }
+                &Output::Json(ref name, _, _, _) => {
+                    info!("[IO] json on query {}", name);
+
+                    match interests.get(name) {
+                        None => {
+                            warn!("result on query {} w/o interested clients", name);
+                            Box::new(std::iter::empty())
+                        }
+                        Some(tokens) => Box::new(tokens.iter().cloned()),
+                    }
+                }
+                &Output::Message(client, ref msg) => {
+                    info!("[IO] {:?}", msg);
+                    Box::new(std::iter::once(client.into()))
+                }
+                &Output::Snapshot(client, ref name, ref results) => {
+                    info!("[IO] {} {} snapshot results", name, results.len());
+                    Box::new(std::iter::once(client.into()))
+                }
+                &Output::Error(client, ref error, _) => {
+                    error!("[IO] {:?}", error);
+                    Box::new(std::iter::once(client.into()))
+                }
+            };
+
+            // Outputs are encoded at most once per format.
+            let mut json = None;
+            let mut msgpack = None;
+
+            for token in tokens {
+                if http::is_http(token) {
+                    if let Some(ref frontend) = self.http {
+                        frontend.deliver(token, &out);
+                    }
+                    continue;
+                }
+
+                #[cfg(feature = "grpc")]
+                {
+                    if grpc::is_grpc(token) {
+                        if let Some(ref frontend) = self.grpc {
+                            frontend.deliver(token, &out);
+                        }
+                        continue;
+                    }
+                }
+
+                if !self.connections.contains(token.into()) {
+                    // @TODO we need to clean up the connection here
+                    warn!("client {:?} has gone away undetected", token);
+                    self.domain_events.push_back(Disconnect(token));
+                    continue;
+                }
+
+                let queue = self
+                    .queues
+                    .entry(token)
+                    .or_insert_with(OutputQueue::default);
+
+                let name = match &out {
+                    Output::QueryDiff(name, _)
+                    | Output::QueryBatch(name, _, _)
+                    | Output::ResumeToken(name, _)
+                    | Output::Json(name, _, _, _)
+                    | Output::Snapshot(_, name, _) => Some(name),
+                    Output::Message(_, _) | Output::Error(_, _, _) => None,
+                };
+
+                if let Some(name) = name {
+                    // Outputs the client has missed are superseded by
+                    // the next snapshot.
+                    if queue.stale.contains(name) {
+                        match out {
+                            Output::Snapshot(_, _, _) => {
+                                queue.stale.remove(name);
+                            }
+                            _ => continue,
+                        }
+                    }
+
+                    if let Some(ref mut dropped) = queue.paused {
+                        dropped.insert(name.clone());
+                        continue;
+                    }
+                }
+
+                let encoding = self
+                    .encodings
+                    .get(&token)
+                    .cloned()
+                    .unwrap_or(Encoding::Json);
+
+                let msg = match encoding {
+                    Encoding::Json => json.get_or_insert_with(|| encoding.encode(&out)),
+                    Encoding::MessagePack => msgpack.get_or_insert_with(|| encoding.encode(&out)),
+                };
+
+                queue.messages.push_back(msg.clone());
+
+                match self.queue_bound {
+                    Some(bound) if queue.messages.len() > bound => self.overflow(token, interests),
+                    _ => self.flush(token, interests),
+                }
+            }
+        }
+    }
+
+    /// Hands queued outputs to a client connection, once it has
+    /// written out everything it was handed before.
+    fn flush(&mut self, token: Token, interests: &HashMap<String, HashSet<Token>>) {
+        let conn = match self.connections.get_mut(token.into()) {
+            None => return,
+            Some(conn) => conn,
+        };
+
+        if let Some(queue) = self.queues.get_mut(&token) {
+            if !conn.events().is_writable() {
+                if queue.messages.is_empty() {
+                    // The client has caught up, so it can resume from
+                    // fresh snapshots of everything it missed.
+                    if let Some(dropped) = queue.paused.take() {
+                        info!("[IO] resuming outputs to client {:?}", token);
+
+                        let names: Vec<String> = dropped
+                            .into_iter()
+                            .filter(|name| interests.contains_key(name))
+                            .collect();
+
+                        queue.stale.extend(names.iter().cloned());
+                        self.domain_events.push_back(Requests(
+                            token,
+                            names.into_iter().map(Request::Resnapshot).collect(),
+                        ));
+                    }
+                }
+
+                for msg in queue.messages.drain(..) {
+                    conn.send_message(msg).expect("failed to send message");
+                }
+            }
+
+            if let Some(ref recorder) = self.recorder {
+                recorder.set(
+                    metrics::OUTPUT_QUEUE_DEPTH,
+                    vec![("client", token.0.to_string())],
+                    queue.messages.len() as f64,
+                );
+            }
+        }
+
+        self.poll
+            .reregister(
+                conn.socket(),
+                conn.token(),
+                conn.events(),
+                PollOpt::edge() | PollOpt::oneshot(),
+            )
+            .unwrap();
+    }
+
+    /// Applies the overflow policy to a client whose queue has grown
+    /// beyond its bound.
+    fn overflow(&mut self, token: Token, interests: &HashMap<String, HashSet<Token>>) {
+        warn!(
+            "[IO] outputs to client {:?} overflowed, applying {:?}",
+            token, self.overflow_policy
+        );
+
+        if let Some(ref recorder) = self.recorder {
+            recorder.increment(
+                metrics::OUTPUT_OVERFLOWS,
+                vec![("policy", format!("{:?}", self.overflow_policy))],
+                1.0,
+            );
+        }
+
+        match self.overflow_policy {
+            OverflowPolicy::Disconnect => {
+                self.domain_events.push_back(Disconnect(token));
+                self.close(token);
+            }
+            OverflowPolicy::Resnapshot => {
+                let queue = self
+                    .queues
+                    .entry(token)
+                    .or_insert_with(OutputQueue::default);
+                queue.messages.clear();
+
+                let names: Vec<String> = interests
+                    .iter()
+                    .filter(|(_name, tokens)| tokens.contains(&token))
+                    .map(|(name, _tokens)| name.clone())
+                    .collect();
+
+                queue.stale.extend(names.iter().cloned());
+                self.domain_events.push_back(Requests(
+                    token,
+                    names.into_iter().map(Request::Resnapshot).collect(),
+                ));
+
+                self.flush(token, interests);
+            }
+            OverflowPolicy::Pause => {
+                let queue = self
+                    .queues
+                    .entry(token)
+                    .or_insert_with(OutputQueue::default);
+
+                if queue.paused.is_none() {
+                    queue.paused = Some(HashSet::new());
+                }
+
+                self.flush(token, interests);
+            }
+        }
+    }
+
+    /// Drops all state about a client connection.
+    fn close(&mut self, token: Token) {
+        if self.connections.contains(token.into()) {
+            self.connections.remove(token.into());
+        }
+
+        self.encodings.remove(&token);
+        self.queues.remove(&token);
+
+        if let Some(ref recorder) = self.recorder {
+            recorder.remove(
+                metrics::OUTPUT_QUEUE_DEPTH,
+                vec![("client", token.0.to_string())],
+            );
+        }
     }
 }
 
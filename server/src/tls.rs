@@ -0,0 +1,173 @@
+//! TLS termination for WebSocket connections, s.t. clients can
+//! connect via `wss://` without a separate terminating proxy.
+//!
+//! Connections accepted on the TLS port are decrypted by rustls and
+//! relayed to the plain WebSocket port on the loopback interface,
+//! each from a thread of its own. The WebSocket interface itself is
+//! unaware of TLS and handles relayed connections like any other.
+
+use std::fs::File;
+use std::io::{self, BufReader, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
+use std::thread;
+
+use mio::net::TcpStream;
+use mio::{Events, Poll, PollOpt, Ready, Token};
+
+use rustls::internal::pemfile;
+use rustls::{NoClientAuth, ServerConfig, ServerSession, Session};
+
+const CLIENT: Token = Token(0);
+const UPSTREAM: Token = Token(1);
+
+/// Reads a certificate chain and the matching private key, both PEM
+/// encoded, into a server configuration. Keys may be given in either
+/// PKCS#8 or RSA format.
+pub fn configure(cert_path: &str, key_path: &str) -> io::Result<ServerConfig> {
+    let invalid = |message: String| io::Error::new(ErrorKind::InvalidData, message);
+
+    let certs = pemfile::certs(&mut BufReader::new(File::open(cert_path)?))
+        .map_err(|_| invalid(format!("failed to read certificates from {}", cert_path)))?;
+
+    let mut keys = pemfile::pkcs8_private_keys(&mut BufReader::new(File::open(key_path)?))
+        .map_err(|_| invalid(format!("failed to read private key from {}", key_path)))?;
+
+    if keys.is_empty() {
+        keys = pemfile::rsa_private_keys(&mut BufReader::new(File::open(key_path)?))
+            .map_err(|_| invalid(format!("failed to read private key from {}", key_path)))?;
+    }
+
+    let key = keys
+        .into_iter()
+        .next()
+        .ok_or_else(|| invalid(format!("no private key found in {}", key_path)))?;
+
+    let mut config = ServerConfig::new(NoClientAuth::new());
+    config
+        .set_single_cert(certs, key)
+        .map_err(|error| invalid(error.to_string()))?;
+
+    Ok(config)
+}
+
+/// Accepts TLS connections at the specified address and relays them
+/// to `upstream`.
+pub fn serve(address: SocketAddr, upstream: SocketAddr, config: ServerConfig) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    let config = Arc::new(config);
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Err(error) => warn!("[TLS] failed to accept connection: {}", error),
+                Ok(stream) => {
+                    let session = ServerSession::new(&config);
+                    thread::spawn(move || {
+                        let peer = stream.peer_addr().ok();
+                        if let Err(error) = relay(session, stream, upstream) {
+                            info!("[TLS] connection from {:?} closed: {}", peer, error);
+                        }
+                    });
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Relays a single connection until either side closes it.
+fn relay(
+    mut session: ServerSession,
+    client: std::net::TcpStream,
+    upstream: SocketAddr,
+) -> io::Result<()> {
+    let mut client = TcpStream::from_stream(client)?;
+    let mut upstream = TcpStream::from_stream(std::net::TcpStream::connect(upstream)?)?;
+
+    let poll = Poll::new()?;
+    let mut events = Events::with_capacity(16);
+
+    poll.register(&client, CLIENT, Ready::readable(), PollOpt::level())?;
+    poll.register(&upstream, UPSTREAM, Ready::readable(), PollOpt::level())?;
+
+    // Plaintext received from the client, not yet written upstream.
+    let mut decrypted: Vec<u8> = Vec::new();
+    let mut buffer = [0; 16 * 1024];
+
+    loop {
+        poll.poll(&mut events, None)?;
+
+        for event in events.iter() {
+            let readiness = event.readiness();
+
+            match event.token() {
+                CLIENT => {
+                    if readiness.is_readable() {
+                        match session.read_tls(&mut client) {
+                            Ok(0) => return Ok(()),
+                            Ok(_) => {}
+                            Err(ref error) if error.kind() == ErrorKind::WouldBlock => {}
+                            Err(error) => return Err(error),
+                        }
+
+                        session
+                            .process_new_packets()
+                            .map_err(|error| io::Error::new(ErrorKind::InvalidData, error))?;
+
+                        session.read_to_end(&mut decrypted)?;
+                    }
+
+                    if readiness.is_writable() {
+                        match session.write_tls(&mut client) {
+                            Ok(_) => {}
+                            Err(ref error) if error.kind() == ErrorKind::WouldBlock => {}
+                            Err(error) => return Err(error),
+                        }
+                    }
+                }
+                UPSTREAM => {
+                    if readiness.is_readable() {
+                        match upstream.read(&mut buffer) {
+                            Ok(0) => {
+                                session.send_close_notify();
+                                session.write_tls(&mut client).ok();
+                                return Ok(());
+                            }
+                            Ok(n) => session.write_all(&buffer[..n])?,
+                            Err(ref error) if error.kind() == ErrorKind::WouldBlock => {}
+                            Err(error) => return Err(error),
+                        }
+                    }
+
+                    if readiness.is_writable() {
+                        match upstream.write(&decrypted) {
+                            Ok(n) => {
+                                decrypted.drain(..n);
+                            }
+                            Err(ref error) if error.kind() == ErrorKind::WouldBlock => {}
+                            Err(error) => return Err(error),
+                        }
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        // Only wait for writability while there is something to
+        // write.
+        let mut client_interest = Ready::readable();
+        if session.wants_write() {
+            client_interest |= Ready::writable();
+        }
+
+        let mut upstream_interest = Ready::readable();
+        if !decrypted.is_empty() {
+            upstream_interest |= Ready::writable();
+        }
+
+        poll.reregister(&client, CLIENT, client_interest, PollOpt::level())?;
+        poll.reregister(&upstream, UPSTREAM, upstream_interest, PollOpt::level())?;
+    }
+}
@@ -25,4 +25,59 @@ fn test_serialization() {
         to_string(&uuid).unwrap(),
         "{\"Uuid\":\"71828aae-4fc8-421b-82ca-68c5f4981d74\"}".to_string(),
     );
+
+    let bytes = Value::Bytes(vec![0xca, 0xfe, 0x00, 0x01]);
+    assert_eq!(
+        to_string(&bytes).unwrap(),
+        "{\"Bytes\":\"cafe0001\"}".to_string(),
+    );
+    assert_eq!(
+        serde_json::from_str::<Value>("{\"Bytes\":\"cafe0001\"}").unwrap(),
+        bytes
+    );
+    assert!(serde_json::from_str::<Value>("{\"Bytes\":\"caf\"}").is_err());
+
+    // Decimals are normalized and carried as strings, s.t. no
+    // precision is lost to floating point parsers.
+    let decimal = Value::decimal_str("-1234.5000");
+    assert_eq!(
+        to_string(&decimal).unwrap(),
+        "{\"Decimal\":\"-1234.5\"}".to_string(),
+    );
+    assert_eq!(
+        serde_json::from_str::<Value>("{\"Decimal\":\"-1234.50\"}").unwrap(),
+        decimal
+    );
+    assert!(serde_json::from_str::<Value>("{\"Decimal\":\"12.3.4\"}").is_err());
+}
+
+#[test]
+fn test_msgpack_roundtrip() {
+    use declarative_dataflow::server::Request;
+    use declarative_dataflow::{Datom, Error, Output, Rational32};
+
+    let requests: Vec<Request<std::string::String>> = vec![
+        Request::Transact(vec![
+            Datom::add(1, ":name", String("Dipper".to_string())),
+            Datom::add(1, ":age", Value::Rational32(Rational32::new(1, 2))),
+            Datom::add(1, ":avatar", Value::Bytes(vec![0xca, 0xfe])),
+            Datom::add(1, ":balance", Value::decimal_str("0.07")),
+        ]),
+        Request::Subscribe(":name".to_string()),
+    ];
+
+    let encoded = rmp_serde::to_vec_named(&requests).unwrap();
+    let decoded: Vec<Request<std::string::String>> = rmp_serde::from_slice(&encoded).unwrap();
+    assert_eq!(decoded, requests);
+
+    let out = Output::Error(0, Error::not_found("missing"), 1);
+    let encoded = rmp_serde::to_vec_named(&out).unwrap();
+    match rmp_serde::from_slice::<Output>(&encoded).unwrap() {
+        Output::Error(client, error, tx) => {
+            assert_eq!(client, 0);
+            assert_eq!(error.category, "df.error.category/not-found");
+            assert_eq!(tx, 1);
+        }
+        other => panic!("expected an error, got {:?}", other),
+    }
 }
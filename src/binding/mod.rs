@@ -294,6 +294,19 @@ pub enum BinaryPredicate {
     EQ,
     /// Not equal
     NEQ,
+    /// String containment, true if the first argument contains the
+    /// second
+    CONTAINS,
+    /// String prefix, true if the first argument starts with the
+    /// second
+    #[allow(non_camel_case_types)]
+    STARTS_WITH,
+    /// Temporal precedence, true if the first argument is an instant
+    /// or date strictly before the second one of the same type
+    BEFORE,
+    /// Temporal succession, true if the first argument is an instant
+    /// or date strictly after the second one of the same type
+    AFTER,
 }
 
 /// Describe a binary predicate constraint.
@@ -0,0 +1,200 @@
+//! A typed, asynchronous client for talking to a 3DF server over
+//! its WebSocket interface, s.t. applications don't have to speak the
+//! JSON protocol by hand.
+//!
+//! All futures returned here, as well as `connect` itself, must be
+//! driven from within a Tokio runtime.
+
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use futures::lock::Mutex as AsyncMutex;
+use futures::stream::{SplitSink, Stream, StreamExt};
+use futures::SinkExt;
+
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+use crate::server::{CreateAttribute, Interest, Register, Request};
+use crate::{Aid, AttributeConfig, Datom, Error, Output, Plan, ResultDiff, Rule, Time};
+
+type Subscribers =
+    Arc<Mutex<HashMap<String, UnboundedSender<Result<Vec<ResultDiff<Time>>, Error>>>>>;
+
+/// A connection to a server.
+pub struct Client {
+    sink: AsyncMutex<SplitSink<WebSocketStream<TcpStream>, Message>>,
+    subscribers: Subscribers,
+}
+
+/// Connects to the server listening at the specified URL,
+/// e.g. `ws://127.0.0.1:6262`.
+pub async fn connect(url: &str) -> Result<Client, Error> {
+    let (socket, _response) = tokio_tungstenite::connect_async(url)
+        .await
+        .map_err(Error::fault)?;
+
+    let (sink, mut stream) = socket.split();
+    let subscribers: Subscribers = Arc::new(Mutex::new(HashMap::new()));
+    let dispatch = subscribers.clone();
+
+    tokio::spawn(async move {
+        while let Some(message) = stream.next().await {
+            match message {
+                Ok(Message::Text(text)) => match serde_json::from_str::<Output>(&text) {
+                    Err(error) => error!("failed to parse output: {}", error),
+                    Ok(output) => dispatch_output(&dispatch, output),
+                },
+                Ok(Message::Close(_)) => break,
+                Ok(_) => {}
+                Err(error) => {
+                    error!("connection failed: {}", error);
+                    break;
+                }
+            }
+        }
+
+        // Result streams end along with the connection.
+        dispatch.lock().unwrap().clear();
+    });
+
+    Ok(Client {
+        sink: AsyncMutex::new(sink),
+        subscribers,
+    })
+}
+
+/// Forwards an output to whoever is interested in it. Errors can't
+/// be attributed to a single query, so they are forwarded to all of
+/// them.
+fn dispatch_output(subscribers: &Subscribers, output: Output) {
+    let mut subscribers = subscribers.lock().unwrap();
+
+    let (name, batch) = match output {
+        Output::QueryDiff(name, diffs) => (name, Ok(diffs)),
+        Output::Snapshot(_client, name, diffs) => (name, Ok(diffs)),
+        Output::QueryBatch(name, t, tuples) => (
+            name,
+            Ok(tuples
+                .into_iter()
+                .map(|(tuple, diff)| (tuple, t.clone(), diff))
+                .collect()),
+        ),
+        Output::Error(_client, error, _tx_id) => {
+            subscribers.retain(|_name, sender| sender.send(Err(error.clone())).is_ok());
+            return;
+        }
+        other => {
+            debug!("ignoring output {:?}", other);
+            return;
+        }
+    };
+
+    let closed = match subscribers.get(&name) {
+        None => false,
+        Some(sender) => sender.send(batch).is_err(),
+    };
+
+    if closed {
+        subscribers.remove(&name);
+    }
+}
+
+impl Client {
+    /// Sends the specified requests, to be handled in order.
+    pub async fn send(&self, requests: Vec<Request<Aid>>) -> Result<(), Error> {
+        let json = serde_json::to_string(&requests).map_err(Error::fault)?;
+
+        self.sink
+            .lock()
+            .await
+            .send(Message::Text(json))
+            .await
+            .map_err(Error::fault)
+    }
+
+    /// Creates an attribute that can be transacted upon.
+    pub async fn create_attribute(&self, name: &str, config: AttributeConfig) -> Result<(), Error> {
+        self.send(vec![Request::CreateAttribute(CreateAttribute {
+            name: name.to_string(),
+            config,
+        })])
+        .await
+    }
+
+    /// Transacts the specified datoms.
+    pub async fn transact(&self, tx_data: Vec<Datom<Aid>>) -> Result<(), Error> {
+        self.send(vec![Request::Transact(tx_data)]).await
+    }
+
+    /// Registers a plan under the specified name and expresses
+    /// interest in it, returning a stream of its result batches.
+    pub async fn register(&self, name: &str, plan: Plan<Aid>) -> Result<Results, Error> {
+        let results = self.subscribe(name);
+
+        self.send(vec![
+            Request::Register(Register {
+                rules: vec![Rule {
+                    name: name.to_string(),
+                    plan,
+                    semantics: None,
+                }],
+                publish: vec![name.to_string()],
+                datalog: vec![],
+            }),
+            Request::Interest(Interest {
+                name: name.to_string(),
+                granularity: None,
+                sink: None,
+                disable_logging: None,
+                as_of: None,
+                strategy: None,
+                resume_after: None,
+                limits: None,
+                page: None,
+            }),
+        ])
+        .await?;
+
+        Ok(results)
+    }
+
+    /// Expresses interest in a relation registered by someone else.
+    pub async fn interest(&self, interest: Interest) -> Result<Results, Error> {
+        let results = self.subscribe(&interest.name);
+        self.send(vec![Request::Interest(interest)]).await?;
+
+        Ok(results)
+    }
+
+    /// Routes all results published under the specified name to a
+    /// new stream, replacing any earlier one.
+    fn subscribe(&self, name: &str) -> Results {
+        let (sender, receiver) = unbounded_channel();
+
+        self.subscribers
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), sender);
+
+        Results { receiver }
+    }
+}
+
+/// Batches of (tuple, time, diff) results of a single relation, as
+/// sent by the server. The stream ends once the connection closes.
+pub struct Results {
+    receiver: UnboundedReceiver<Result<Vec<ResultDiff<Time>>, Error>>,
+}
+
+impl Stream for Results {
+    type Item = Result<Vec<ResultDiff<Time>>, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.get_mut().receiver.poll_recv(cx)
+    }
+}
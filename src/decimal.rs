@@ -0,0 +1,348 @@
+//! Exact decimal numbers, for quantities such as monetary amounts
+//! that must neither overflow nor accumulate rounding errors when
+//! aggregated.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::Value;
+
+/// The maximum number of fractional digits a decimal may carry.
+pub const MAX_SCALE: u32 = 28;
+
+/// The minimum number of fractional digits of decimal averages.
+pub const AVG_SCALE: u32 = 10;
+
+/// A signed decimal number, represented exactly as a 128 bit integer
+/// mantissa scaled down by a power of ten. Decimals are kept
+/// normalized, s.t. numerically equal decimals are equal, regardless
+/// of how many trailing zeros they were written with.
+#[derive(Hash, PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+fn pow10(exponent: u32) -> i128 {
+    10i128.pow(exponent)
+}
+
+impl Decimal {
+    /// The largest representable decimal.
+    pub const MAX: Decimal = Decimal {
+        mantissa: std::i128::MAX,
+        scale: 0,
+    };
+
+    /// The smallest representable decimal.
+    pub const MIN: Decimal = Decimal {
+        mantissa: std::i128::MIN,
+        scale: 0,
+    };
+
+    /// Creates the decimal `mantissa * 10^-scale`.
+    pub fn new(mantissa: i128, scale: u32) -> Self {
+        assert!(scale <= MAX_SCALE, "scale out of range");
+
+        let mut decimal = Decimal { mantissa, scale };
+        while decimal.scale > 0 && decimal.mantissa % 10 == 0 {
+            decimal.mantissa /= 10;
+            decimal.scale -= 1;
+        }
+
+        decimal
+    }
+
+    /// The unscaled integer value.
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    /// The number of fractional digits.
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// Returns the mantissa this decimal would have at a larger
+    /// scale, if it fits.
+    fn rescaled(&self, scale: u32) -> Option<i128> {
+        self.mantissa.checked_mul(pow10(scale - self.scale))
+    }
+
+    /// Adds two decimals, returning `None` on overflow.
+    pub fn checked_add(&self, other: &Decimal) -> Option<Decimal> {
+        let scale = std::cmp::max(self.scale, other.scale);
+        let sum = self.rescaled(scale)?.checked_add(other.rescaled(scale)?)?;
+
+        Some(Decimal::new(sum, scale))
+    }
+
+    /// Returns the mantissa this decimal would have at the given
+    /// scale, rounding half away from zero if that is smaller.
+    fn rounded(&self, scale: u32) -> Option<i128> {
+        if scale >= self.scale {
+            return self.rescaled(scale);
+        }
+
+        let unit = pow10(self.scale - scale);
+        let quotient = self.mantissa / unit;
+        let remainder = self.mantissa % unit;
+
+        Some(if remainder.abs() >= unit / 2 + unit % 2 {
+            quotient + self.mantissa.signum()
+        } else {
+            quotient
+        })
+    }
+
+    /// Adds two decimals. Fractional digits that no longer fit are
+    /// rounded off, and sums beyond the range of decimals saturate
+    /// at its bounds.
+    pub fn saturating_add(&self, other: &Decimal) -> Decimal {
+        let mut scale = std::cmp::max(self.scale, other.scale);
+
+        loop {
+            if let (Some(x), Some(y)) = (self.rounded(scale), other.rounded(scale)) {
+                match x.checked_add(y) {
+                    Some(sum) => return Decimal::new(sum, scale),
+                    // Only summands of the same sign overflow.
+                    None if scale == 0 => {
+                        return if x > 0 { Decimal::MAX } else { Decimal::MIN };
+                    }
+                    None => {}
+                }
+            }
+
+            scale -= 1;
+        }
+    }
+
+    /// Multiplies a decimal by an integer, returning `None` on
+    /// overflow.
+    pub fn checked_mul_int(&self, factor: i128) -> Option<Decimal> {
+        Some(Decimal::new(self.mantissa.checked_mul(factor)?, self.scale))
+    }
+
+    /// Divides a decimal by an integer, rounding half away from zero
+    /// to at least `scale` fractional digits. Returns `None` when
+    /// dividing by zero or on overflow.
+    pub fn checked_div_int(&self, divisor: i128, scale: u32) -> Option<Decimal> {
+        if divisor == 0 {
+            return None;
+        }
+
+        let scale = std::cmp::min(std::cmp::max(self.scale, scale), MAX_SCALE);
+        let dividend = self.rescaled(scale)?;
+
+        let mut quotient = dividend / divisor;
+        let remainder = dividend % divisor;
+        if remainder.checked_abs()?.checked_mul(2)? >= divisor.checked_abs()? {
+            quotient += if (dividend < 0) == (divisor < 0) {
+                1
+            } else {
+                -1
+            };
+        }
+
+        Some(Decimal::new(quotient, scale))
+    }
+}
+
+/// Returns the value as a decimal, if it is numeric.
+pub fn as_decimal(value: &Value) -> Option<Decimal> {
+    match *value {
+        Value::Decimal(decimal) => Some(decimal),
+        Value::Number(number) => Some(Decimal::from(number)),
+        _ => None,
+    }
+}
+
+/// Whether the value is a decimal.
+pub fn is_decimal(value: &Value) -> bool {
+    match value {
+        Value::Decimal(_) => true,
+        _ => false,
+    }
+}
+
+/// Sums numeric values weighted by their multiplicities, returning
+/// the sum along with the total multiplicity. Numbers are summed as
+/// decimals, and the sum saturates rather than overflowing.
+pub fn weighted_sum<'a, I>(values: I) -> (Decimal, isize)
+where
+    I: IntoIterator<Item = (&'a Value, isize)>,
+{
+    let mut sum = Decimal::from(0);
+    let mut count = 0;
+
+    for (value, diff) in values {
+        if let Some(decimal) = as_decimal(value) {
+            let weighted = decimal.checked_mul_int(diff as i128).unwrap_or_else(|| {
+                if (decimal.mantissa < 0) == (diff < 0) {
+                    Decimal::MAX
+                } else {
+                    Decimal::MIN
+                }
+            });
+
+            sum = sum.saturating_add(&weighted);
+            count += diff;
+        }
+    }
+
+    (sum, count)
+}
+
+/// Divides a sum by the number of summands, with at least
+/// `AVG_SCALE` fractional digits where they fit, and fewer where
+/// they don't.
+pub fn average(sum: &Decimal, count: isize) -> Decimal {
+    (0..=AVG_SCALE)
+        .rev()
+        .filter_map(|scale| sum.checked_div_int(count as i128, scale))
+        .next()
+        .unwrap_or(*sum)
+}
+
+impl From<i64> for Decimal {
+    fn from(v: i64) -> Self {
+        Decimal::new(v as i128, 0)
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        // Aligning both mantissas to a common scale might overflow,
+        // so integral and fractional parts are compared separately.
+        // Both truncate towards zero, hence carry the sign of the
+        // whole number.
+        let scale = std::cmp::max(self.scale, other.scale);
+        let parts = |d: &Decimal| {
+            let unit = pow10(d.scale);
+            let fraction = (d.mantissa % unit) * pow10(scale - d.scale);
+
+            (d.mantissa / unit, fraction)
+        };
+
+        parts(self).cmp(&parts(other))
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // The magnitude of i128::MIN doesn't fit an i128.
+        let magnitude = if self.mantissa < 0 {
+            (self.mantissa as u128).wrapping_neg()
+        } else {
+            self.mantissa as u128
+        };
+
+        let digits = format!("{:0width$}", magnitude, width = self.scale as usize + 1);
+        let (integral, fraction) = digits.split_at(digits.len() - self.scale as usize);
+
+        if self.mantissa < 0 {
+            write!(f, "-")?;
+        }
+
+        if fraction.is_empty() {
+            write!(f, "{}", integral)
+        } else {
+            write!(f, "{}.{}", integral, fraction)
+        }
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Decimal, String> {
+        let malformed = || format!("Decimal {} is malformed.", s);
+
+        let (negative, unsigned) = match s.as_bytes().first() {
+            Some(b'-') => (true, &s[1..]),
+            Some(b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+
+        let (integral, fraction) = match unsigned.find('.') {
+            None => (unsigned, ""),
+            Some(offset) => (&unsigned[..offset], &unsigned[offset + 1..]),
+        };
+
+        if integral.is_empty() && fraction.is_empty() {
+            return Err(malformed());
+        }
+
+        if fraction.len() > MAX_SCALE as usize {
+            return Err(format!(
+                "Decimal {} has more than {} fractional digits.",
+                s, MAX_SCALE
+            ));
+        }
+
+        let mut mantissa: i128 = 0;
+        for digit in integral.chars().chain(fraction.chars()) {
+            let digit = digit.to_digit(10).ok_or_else(malformed)? as i128;
+            mantissa = mantissa
+                .checked_mul(10)
+                .and_then(|m| {
+                    if negative {
+                        m.checked_sub(digit)
+                    } else {
+                        m.checked_add(digit)
+                    }
+                })
+                .ok_or_else(|| format!("Decimal {} is out of range.", s))?;
+        }
+
+        Ok(Decimal::new(mantissa, fraction.len() as u32))
+    }
+}
+
+impl Serialize for Decimal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Decimal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Decimal, D::Error> {
+        struct DecimalVisitor;
+
+        impl<'de> Visitor<'de> for DecimalVisitor {
+            type Value = Decimal;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a decimal string or an integer")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Decimal, E> {
+                s.parse().map_err(E::custom)
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Decimal, E> {
+                Ok(Decimal::from(v))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Decimal, E> {
+                Ok(Decimal::new(v as i128, 0))
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(DecimalVisitor)
+        } else {
+            deserializer.deserialize_str(DecimalVisitor)
+        }
+    }
+}
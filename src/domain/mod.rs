@@ -1,8 +1,9 @@
 //! Logic for working with attributes under a shared timestamp
 //! semantics.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::{Add, AddAssign};
+use std::time::Duration;
 
 use timely::dataflow::operators::unordered_input::{ActivateCapability, UnorderedHandle};
 use timely::dataflow::operators::Map;
@@ -10,18 +11,65 @@ use timely::dataflow::{ProbeHandle, Scope, Stream};
 use timely::progress::frontier::AntichainRef;
 use timely::progress::Timestamp;
 
+use differential_dataflow::hashable::Hashable;
 use differential_dataflow::lattice::Lattice;
 use differential_dataflow::operators::arrange::Arrange;
-use differential_dataflow::trace::TraceReader;
+use differential_dataflow::trace::{BatchReader, Cursor, TraceReader};
 use differential_dataflow::{AsCollection, Collection};
 
-use crate::{AsAid, Datom, Error, Rewind, Rule, Value};
-use crate::{AttributeConfig, QuerySupport};
+use crate::metrics::{self, Recorder};
+use crate::plan::fulltext::tokenize;
+use crate::plan::Implementable;
+use crate::{AsAid, Datom, Eid, Error, Rewind, Rule, Time, Value};
+use crate::{
+    AttributeConfig, Cardinality, CompositeIndex, IndexDirection, QuerySupport, Uniqueness,
+};
 use crate::{ShutdownHandle, TraceKeyHandle, TraceValHandle};
 
+mod sizes;
+mod statistics;
 mod unordered_session;
+pub use self::sizes::IndexSize;
+pub use self::statistics::{AttributeStatistics, IndexStatistics};
 use unordered_session::UnorderedSession;
 
+/// Entity ids allocated for tempids are handed out starting from
+/// here, to stay clear of ids chosen by clients themselves.
+pub const TEMPID_PARTITION: Eid = 1 << 32;
+
+/// Attribute holding the wall-clock time (as `Value::Instant`) at
+/// which a transaction was processed. Asserted on transaction
+/// entities, iff the attribute exists in the domain.
+pub const TX_INSTANT: &str = ":db/txInstant";
+
+/// Attribute linking entities to the transactions that touched
+/// them. Asserted iff the attribute exists in the domain.
+pub const TX_LINK: &str = ":db/tx";
+
+/// Transaction data that has been validated and grouped by
+/// attribute, ready to be applied to input sessions.
+pub type TxBatches<A, T> = HashMap<A, Vec<((Value, Value), Option<T>, isize)>>;
+
+/// An index spanning several attributes of a domain, maintained in
+/// addition to their per-attribute indices.
+pub struct Composite<A, T>
+where
+    A: AsAid,
+    T: Timestamp + Lattice,
+{
+    /// The layout of the index.
+    pub kind: CompositeIndex,
+    /// Attributes covered by the index.
+    pub attributes: Vec<A>,
+    /// For entity indices, the trace mapping entities to their (a,
+    /// v) pairs. For ref indices, the trace mapping referenced
+    /// entities to the (a, e) pairs referring to them.
+    pub trace: TraceValHandle<Value, (Value, Value), T, isize>,
+    /// Shuts down the imports of the covered attributes, once
+    /// dropped.
+    _shutdown: ShutdownHandle,
+}
+
 /// A domain manages attributes that share a timestamp semantics. Each
 /// attribute within a domain can be either fed from an external
 /// system, or from user transactions. The former are referred to as
@@ -43,6 +91,10 @@ use unordered_session::UnorderedSession;
 /// attributes must be automatically advanced in lockstep with a
 /// high-watermark of all timeful domain inputs. This ensures that
 /// they will never block overall progress.
+///
+/// Each attribute is arranged exactly once, when it is created. All
+/// queries using it import the domain's traces into their own
+/// dataflows, sharing the underlying state.
 pub struct Domain<A, T>
 where
     A: AsAid,
@@ -53,8 +105,18 @@ where
     namespace: String,
     /// The current input epoch.
     now_at: T,
+    /// The next entity id to hand out when resolving tempids.
+    next_eid: Eid,
     /// Last trace advance.
     last_advance: Vec<T>,
+    /// Time beyond which attribute traces must not compact, e.g.
+    /// because a snapshot as of that time is pending.
+    compaction_hold: Option<T>,
+    /// Index of the worker holding this domain.
+    worker_index: usize,
+    /// Number of workers across which partitioned attributes are
+    /// ingested.
+    peers: usize,
     /// Input handles to attributes in this domain.
     input_sessions: HashMap<A, UnorderedSession<T, (Value, Value), isize>>,
     /// The probe keeping track of source progress in this domain.
@@ -65,6 +127,16 @@ where
     probed_source_count: usize,
     /// Configurations for attributes in this domain.
     pub attributes: HashMap<A, AttributeConfig>,
+    /// Current values per entity, for cardinality-one attributes.
+    current_values: HashMap<A, HashMap<Value, Value>>,
+    /// Pending retractions per windowed attribute, together with the
+    /// times at which they are due.
+    expirations: HashMap<A, Vec<((Value, Value), T, isize)>>,
+    /// Owning entity per value, for unique-identity attributes.
+    identities: HashMap<A, HashMap<Value, Value>>,
+    /// Current (a, v) pairs per entity, as established by
+    /// transactions, together with their multiplicities.
+    entities: HashMap<Value, HashMap<(A, Value), isize>>,
     /// Forward count traces.
     pub forward_count: HashMap<A, TraceKeyHandle<Value, T, isize>>,
     /// Forward propose traces.
@@ -77,10 +149,36 @@ where
     pub reverse_propose: HashMap<A, TraceValHandle<Value, Value, T, isize>>,
     /// Reverse validate traces.
     pub reverse_validate: HashMap<A, TraceKeyHandle<(Value, Value), T, isize>>,
+    /// Fulltext traces, mapping tokens to the (e, v) pairs whose
+    /// values contain them.
+    pub fulltext: HashMap<A, TraceValHandle<String, (Value, Value), T, isize>>,
+    /// Composite indices, by name.
+    pub composite: HashMap<A, Composite<A, T>>,
+    /// Imports feeding reverse indices that were built on demand,
+    /// by attribute.
+    reverse_imports: HashMap<A, ShutdownHandle>,
+    /// Attributes whose indices have been offloaded, see `evict`.
+    evicted: HashSet<A>,
+    /// Cardinality statistics per attribute, refreshed as the domain
+    /// advances.
+    pub statistics: HashMap<A, AttributeStatistics>,
+    /// Input handles to query parameters in this domain.
+    parameter_sessions: HashMap<A, UnorderedSession<T, Vec<Value>, isize>>,
+    /// Query parameter traces.
+    pub parameters: HashMap<A, TraceKeyHandle<Vec<Value>, T, isize>>,
+    /// Catalog of materialized rules, i.e. views and shared
+    /// intermediate results, mapping their names to the traces of
+    /// their results.
+    pub intermediates: HashMap<A, TraceKeyHandle<Vec<Value>, T, isize>>,
     /// Representation of named rules.
     pub rules: HashMap<A, Rule<A>>,
     /// Mapping from query names to their shutdown handles.
     pub shutdown_handles: HashMap<String, ShutdownHandle>,
+    /// Where to report transactions and progress, if anywhere.
+    metrics: Option<Recorder>,
+    /// Interval at which the wall clock advances the domain, if at
+    /// all.
+    clock: Option<Duration>,
 }
 
 // We're defining domain composition here.
@@ -90,8 +188,10 @@ where
     T: Timestamp + Lattice,
 {
     fn add_assign(&mut self, other: Self) {
-        assert_eq!(
-            self.namespace, other.namespace,
+        // Anonymous domains, e.g. those holding single attributes,
+        // can be composed into any namespace.
+        assert!(
+            other.namespace.is_empty() || self.namespace == other.namespace,
             "Only domains within a namespace can be composed."
         );
         assert!(
@@ -103,6 +203,7 @@ where
         );
 
         self.now_at = self.now_at.meet(&other.now_at);
+        self.next_eid = std::cmp::max(self.next_eid, other.next_eid);
         // @TODO
         // self.last_advance = ???
         self.input_sessions.extend(other.input_sessions.into_iter());
@@ -118,6 +219,16 @@ where
         }
 
         self.attributes.extend(other.attributes.into_iter());
+        self.current_values.extend(other.current_values.into_iter());
+        self.expirations.extend(other.expirations.into_iter());
+        self.identities.extend(other.identities.into_iter());
+
+        for (e, pairs) in other.entities.into_iter() {
+            self.entities
+                .entry(e)
+                .or_insert_with(HashMap::new)
+                .extend(pairs.into_iter());
+        }
 
         self.forward_count.extend(other.forward_count.into_iter());
         self.forward_propose
@@ -130,11 +241,26 @@ where
             .extend(other.reverse_propose.into_iter());
         self.reverse_validate
             .extend(other.reverse_validate.into_iter());
+        self.fulltext.extend(other.fulltext.into_iter());
+        self.composite.extend(other.composite.into_iter());
+        self.reverse_imports
+            .extend(other.reverse_imports.into_iter());
+        self.evicted.extend(other.evicted.into_iter());
+        self.statistics.extend(other.statistics.into_iter());
+
+        self.parameter_sessions
+            .extend(other.parameter_sessions.into_iter());
+        self.parameters.extend(other.parameters.into_iter());
+        self.intermediates.extend(other.intermediates.into_iter());
 
         self.rules.extend(other.rules.into_iter());
 
         self.shutdown_handles
             .extend(other.shutdown_handles.into_iter());
+
+        if self.metrics.is_none() {
+            self.metrics = other.metrics;
+        }
     }
 }
 
@@ -162,19 +288,37 @@ where
         Domain {
             namespace: Default::default(),
             now_at: start_at,
+            next_eid: TEMPID_PARTITION,
             last_advance: vec![<T as Lattice>::minimum()],
+            compaction_hold: None,
+            worker_index: 0,
+            peers: 1,
             input_sessions: HashMap::new(),
             domain_probe: ProbeHandle::new(),
             probed_source_count: 0,
             attributes: HashMap::new(),
+            current_values: HashMap::new(),
+            expirations: HashMap::new(),
+            identities: HashMap::new(),
+            entities: HashMap::new(),
             forward_count: HashMap::new(),
             forward_propose: HashMap::new(),
             forward_validate: HashMap::new(),
             reverse_count: HashMap::new(),
             reverse_propose: HashMap::new(),
             reverse_validate: HashMap::new(),
+            fulltext: HashMap::new(),
+            composite: HashMap::new(),
+            reverse_imports: HashMap::new(),
+            evicted: HashSet::new(),
+            statistics: HashMap::new(),
+            parameter_sessions: HashMap::new(),
+            parameters: HashMap::new(),
+            intermediates: HashMap::new(),
             rules: HashMap::new(),
             shutdown_handles: HashMap::new(),
+            metrics: None,
+            clock: None,
         }
     }
 
@@ -183,39 +327,622 @@ where
         Domain {
             namespace: namespace.to_string(),
             now_at: base.now_at.clone(),
+            next_eid: base.next_eid,
             last_advance: base.last_advance.clone(),
+            compaction_hold: None,
+            worker_index: base.worker_index,
+            peers: base.peers,
             input_sessions: HashMap::new(),
             domain_probe: ProbeHandle::new(),
             probed_source_count: 0,
             attributes: HashMap::new(),
+            current_values: HashMap::new(),
+            expirations: HashMap::new(),
+            identities: HashMap::new(),
+            entities: HashMap::new(),
             forward_count: HashMap::new(),
             forward_propose: HashMap::new(),
             forward_validate: HashMap::new(),
             reverse_count: HashMap::new(),
             reverse_propose: HashMap::new(),
             reverse_validate: HashMap::new(),
+            fulltext: HashMap::new(),
+            composite: HashMap::new(),
+            reverse_imports: HashMap::new(),
+            evicted: HashSet::new(),
+            statistics: HashMap::new(),
+            parameter_sessions: HashMap::new(),
+            parameters: HashMap::new(),
+            intermediates: HashMap::new(),
             rules: HashMap::new(),
             shutdown_handles: HashMap::new(),
+            metrics: base.metrics.clone(),
+            clock: base.clock,
         }
     }
 
-    /// Transact data into one or more inputs.
+    /// Reports transactions and progress of this domain to the
+    /// specified recorder from now on.
+    pub fn instrument(&mut self, recorder: Recorder) {
+        self.metrics = Some(recorder);
+    }
+
+    /// Declares this domain to be held by the specified worker, out
+    /// of the specified number of peers. Each worker then introduces
+    /// the inputs to partitioned attributes for its share of
+    /// entities.
+    pub fn partition_across(&mut self, worker_index: usize, peers: usize) {
+        assert!(worker_index < peers, "Worker index out of range.");
+
+        self.worker_index = worker_index;
+        self.peers = peers;
+    }
+
+    /// Returns the index of the worker responsible for introducing
+    /// inputs about the specified entity to partitioned attributes.
+    /// This agrees with the routing of the exchange in front of their
+    /// indices.
+    pub fn partition_of(&self, e: &Value) -> usize {
+        (e.hashed() % self.peers as u64) as usize
+    }
+
+    /// Transact data into one or more inputs. Transactions are
+    /// all-or-nothing: updates are staged and validated first, and
+    /// only flushed into the input sessions if the whole batch is
+    /// valid.
     pub fn transact(&mut self, tx_data: Vec<Datom<A>>) -> Result<(), Error> {
-        for Datom(e, a, v, t, diff) in tx_data {
-            match self.input_sessions.get_mut(&a) {
-                None => {
-                    return Err(Error::not_found(format!("Attribute {} does not exist.", a)));
+        self.transact_batched(tx_data)
+    }
+
+    /// Transact data into one or more inputs, grouping updates by
+    /// attribute and handing each group to its input session in one
+    /// go. All attributes are checked before anything is applied, so
+    /// a failing transaction leaves all inputs untouched.
+    pub fn transact_batched(&mut self, tx_data: Vec<Datom<A>>) -> Result<(), Error> {
+        let batches = self.prepare(tx_data)?;
+        self.apply(batches);
+
+        Ok(())
+    }
+
+    /// Turns transaction data into per-attribute batches ready to be
+    /// applied. This resolves tempids, validates all datoms, and
+    /// rewrites updates to cardinality-one attributes. Preparation is
+    /// deterministic and must happen on all workers, even those that
+    /// won't apply the batches themselves, s.t. their bookkeeping
+    /// stays in sync.
+    pub fn prepare(&mut self, tx_data: Vec<Datom<A>>) -> Result<TxBatches<A, T>, Error> {
+        self.prepare_with_meta(tx_data, Vec::new())
+            .map(|(batches, _tempids)| batches)
+    }
+
+    /// Prepares transaction data like `prepare`, additionally
+    /// recording the transaction as an entity of its own. The
+    /// transaction entity carries the provided (a, v) annotations, a
+    /// `TX_INSTANT`, and is linked to all entities touched by the
+    /// transaction via `TX_LINK`. The latter two are only asserted if
+    /// their attributes exist. Transaction instants are taken from
+    /// the local clock, which is why only the batches prepared by the
+    /// owning worker should be applied. Also returns the mapping from
+    /// tempids to the ids they resolved to.
+    pub fn prepare_with_meta(
+        &mut self,
+        tx_data: Vec<Datom<A>>,
+        meta: Vec<(A, Value)>,
+    ) -> Result<(TxBatches<A, T>, HashMap<String, Eid>), Error> {
+        // Entity ids allocated for a rejected transaction are handed
+        // out again, s.t. it leaves no trace.
+        let next_eid = self.next_eid;
+
+        let upserts = self.resolve_upserts(&tx_data);
+        let (mut tx_data, tempids) = self.resolve_tempids_with(tx_data, upserts);
+        self.annotate(&mut tx_data, meta);
+
+        let staged = self.stage(tx_data).and_then(|batches| {
+            self.validate_uniqueness(&batches)?;
+            Ok(batches)
+        });
+
+        let mut batches = match staged {
+            Ok(batches) => batches,
+            Err(error) => {
+                self.next_eid = next_eid;
+                return Err(error);
+            }
+        };
+
+        self.enforce_cardinality(&mut batches);
+        self.enforce_uniqueness(&mut batches);
+        self.track_entities(&batches);
+
+        Ok((batches, tempids))
+    }
+
+    /// Hands previously prepared batches to their input sessions.
+    pub fn apply(&mut self, mut batches: TxBatches<A, T>) {
+        if let Some(ref recorder) = self.metrics {
+            recorder.increment(metrics::TRANSACTIONS, vec![], 1.0);
+
+            for (a, batch) in batches.iter() {
+                let net: isize = batch.iter().map(|(_datom, _t, diff)| diff).sum();
+                let labels = vec![("attribute", a.to_string())];
+
+                recorder.increment(metrics::DATOMS, labels.clone(), batch.len() as f64);
+                recorder.increment(metrics::ATTRIBUTE_DATOMS, labels, net as f64);
+            }
+        }
+
+        for (a, batch) in batches.drain() {
+            let handle = self
+                .input_sessions
+                .get_mut(&a)
+                .expect("input session disappeared during transaction");
+
+            let window = self
+                .attributes
+                .get(&a)
+                .and_then(|config| config.window.clone());
+
+            if let Some(window) = window {
+                let pending = self.expirations.entry(a.clone()).or_insert_with(Vec::new);
+
+                for ((e, v), t, diff) in batch.iter() {
+                    if *diff > 0 {
+                        let t: Time = t.clone().unwrap_or_else(|| handle.time().clone()).into();
+                        let expiry: T = (t + window.clone()).into();
+
+                        pending.push(((e.clone(), v.clone()), expiry, *diff));
+                    } else {
+                        // Explicit retractions cancel pending ones,
+                        // earliest assertions first.
+                        let mut remaining = -*diff;
+                        for (datom, _expiry, count) in pending.iter_mut() {
+                            if remaining > 0 && datom.0 == *e && datom.1 == *v {
+                                let cancelled = std::cmp::min(remaining, *count);
+                                *count -= cancelled;
+                                remaining -= cancelled;
+                            }
+                        }
+                        pending.retain(|(_datom, _expiry, count)| *count > 0);
+                    }
+                }
+            }
+
+            handle.update_batch(batch);
+        }
+    }
+
+    /// Hands this worker's share of previously prepared batches to
+    /// its input sessions. Batches for partitioned attributes are
+    /// split by entity across all workers, everything else is
+    /// introduced only by the owning worker. Returns the number of
+    /// datoms applied.
+    pub fn apply_partitioned(&mut self, mut batches: TxBatches<A, T>, is_owner: bool) -> usize {
+        let mut local = HashMap::new();
+
+        for (a, batch) in batches.drain() {
+            let is_partitioned = self
+                .attributes
+                .get(&a)
+                .map(|config| config.partitioned)
+                .unwrap_or(false);
+
+            let batch: Vec<_> = if is_partitioned {
+                batch
+                    .into_iter()
+                    .filter(|((e, _v), _t, _diff)| self.partition_of(e) == self.worker_index)
+                    .collect()
+            } else if is_owner {
+                batch
+            } else {
+                Vec::new()
+            };
+
+            if !batch.is_empty() {
+                local.insert(a, batch);
+            }
+        }
+
+        let datoms = local.values().map(|batch| batch.len()).sum();
+        if !local.is_empty() {
+            self.apply(local);
+        }
+
+        datoms
+    }
+
+    /// Retracts all datoms of windowed attributes whose windows close
+    /// before the specified time, at the exact time they expire.
+    fn expire(&mut self, next: &T) {
+        for (a, pending) in self.expirations.iter_mut() {
+            if let Some(handle) = self.input_sessions.get_mut(a) {
+                let (due, remaining): (Vec<_>, Vec<_>) = pending
+                    .drain(..)
+                    .partition(|(_datom, expiry, _count)| expiry.less_than(next));
+                *pending = remaining;
+
+                for (datom, expiry, count) in due.into_iter() {
+                    if handle.time().less_equal(&expiry) {
+                        handle.update_at(datom, expiry, -count);
+                    } else {
+                        handle.update(datom, -count);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Loads (e, v, diff) updates for a single attribute straight into
+    /// its input, bypassing tempid resolution and all transaction
+    /// checks. Meant for initial imports of large amounts of data, and
+    /// therefore not available for attributes with cardinality or
+    /// uniqueness constraints.
+    pub fn bulk_load<I>(&mut self, name: &A, data: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = (Value, Value, isize)>,
+    {
+        if let Some(config) = self.attributes.get(name) {
+            if config.cardinality != Cardinality::Many
+                || config.unique != Uniqueness::None
+                || config.window.is_some()
+            {
+                return Err(Error::unsupported(format!(
+                    "Attribute {} is constrained and can't be bulk loaded.",
+                    name
+                )));
+            }
+        }
+
+        match self.input_sessions.get_mut(name) {
+            None => Err(Error::not_found(format!(
+                "Attribute {} does not exist.",
+                name
+            ))),
+            Some(handle) => {
+                handle.load(data.into_iter().map(|(e, v, diff)| ((e, v), diff)));
+                Ok(())
+            }
+        }
+    }
+
+    /// Allocates a transaction entity and appends its datoms to the
+    /// transaction data, if there is anything to record.
+    fn annotate(&mut self, tx_data: &mut Vec<Datom<A>>, meta: Vec<(A, Value)>) {
+        let tx_instant: A = TX_INSTANT.to_string().into();
+        let tx_link: A = TX_LINK.to_string().into();
+
+        let record_instant = self.attributes.contains_key(&tx_instant);
+        let record_links = self.attributes.contains_key(&tx_link);
+
+        if tx_data.is_empty() || (meta.is_empty() && !record_instant && !record_links) {
+            return;
+        }
+
+        let tx = Value::Eid(self.allocate_eid());
+
+        if record_links {
+            let mut touched: Vec<Value> = tx_data.iter().map(|datom| datom.0.clone()).collect();
+            touched.sort();
+            touched.dedup();
+
+            for e in touched.into_iter() {
+                tx_data.push(Datom(e, tx_link.clone(), tx.clone(), None, 1));
+            }
+        }
+
+        if record_instant {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .expect("system clock before unix epoch");
+
+            tx_data.push(Datom(
+                tx.clone(),
+                tx_instant,
+                Value::Instant(now.as_millis() as u64),
+                None,
+                1,
+            ));
+        }
+
+        for (a, v) in meta.into_iter() {
+            tx_data.push(Datom(tx.clone(), a, v, None, 1));
+        }
+    }
+
+    /// Allocates a fresh entity id.
+    pub fn allocate_eid(&mut self) -> Eid {
+        let eid = self.next_eid;
+        self.next_eid += 1;
+        eid
+    }
+
+    /// Replaces all tempids in the given transaction data with freshly
+    /// allocated entity ids, consistently within the transaction. Also
+    /// returns the mapping from tempids to the ids they resolved
+    /// to. Allocation is deterministic, s.t. all workers resolving
+    /// the same sequence of transactions agree on entity ids.
+    pub fn resolve_tempids(
+        &mut self,
+        tx_data: Vec<Datom<A>>,
+    ) -> (Vec<Datom<A>>, HashMap<String, Eid>) {
+        self.resolve_tempids_with(tx_data, HashMap::new())
+    }
+
+    fn resolve_tempids_with(
+        &mut self,
+        tx_data: Vec<Datom<A>>,
+        mut tempids: HashMap<String, Eid>,
+    ) -> (Vec<Datom<A>>, HashMap<String, Eid>) {
+        let tx_data = tx_data
+            .into_iter()
+            .map(|Datom(e, a, v, t, diff)| {
+                let e = self.resolve_tempid(e, &mut tempids);
+                let v = self.resolve_tempid(v, &mut tempids);
+
+                Datom(e, a, v, t, diff)
+            })
+            .collect();
+
+        (tx_data, tempids)
+    }
+
+    fn resolve_tempid(&mut self, value: Value, tempids: &mut HashMap<String, Eid>) -> Value {
+        match value {
+            Value::TempId(tempid) => {
+                let eid = match tempids.get(&tempid) {
+                    Some(eid) => *eid,
+                    None => {
+                        let eid = self.allocate_eid();
+                        tempids.insert(tempid, eid);
+                        eid
+                    }
+                };
+
+                Value::Eid(eid)
+            }
+            other => other,
+        }
+    }
+
+    fn is_identity(&self, a: &A) -> bool {
+        match self.attributes.get(a) {
+            None => false,
+            Some(config) => config.unique == Uniqueness::Identity,
+        }
+    }
+
+    /// Finds tempids asserting a value on a unique-identity attribute
+    /// that is already owned by an existing entity. Those tempids
+    /// resolve to the existing entity instead of a fresh one.
+    fn resolve_upserts(&self, tx_data: &[Datom<A>]) -> HashMap<String, Eid> {
+        let mut upserts = HashMap::new();
+
+        for Datom(e, a, v, _t, diff) in tx_data.iter() {
+            if let Value::TempId(ref tempid) = e {
+                if *diff > 0 && self.is_identity(a) {
+                    let owner = self.identities.get(a).and_then(|owners| owners.get(v));
+
+                    if let Some(Value::Eid(eid)) = owner {
+                        upserts.insert(tempid.clone(), *eid);
+                    }
+                }
+            }
+        }
+
+        upserts
+    }
+
+    /// Ensures that no value of a unique-identity attribute would end
+    /// up being owned by more than one entity.
+    fn validate_uniqueness(&self, batches: &TxBatches<A, T>) -> Result<(), Error> {
+        for (a, batch) in batches.iter() {
+            if !self.is_identity(a) {
+                continue;
+            }
+
+            let mut owners = match self.identities.get(a) {
+                None => HashMap::new(),
+                Some(owners) => owners.clone(),
+            };
+
+            for ((e, v), _t, diff) in batch.iter() {
+                if *diff < 0 && owners.get(v) == Some(e) {
+                    owners.remove(v);
+                }
+            }
+
+            for ((e, v), _t, diff) in batch.iter() {
+                if *diff > 0 {
+                    match owners.get(v) {
+                        Some(owner) if owner != e => {
+                            return Err(Error::conflict(format!(
+                                "Value {:?} of unique attribute {} is already owned by {:?}.",
+                                v, a, owner
+                            )));
+                        }
+                        _ => {
+                            owners.insert(v.clone(), e.clone());
+                        }
+                    }
                 }
-                Some(handle) => match t {
-                    None => handle.update((e, v), diff),
-                    Some(t) => handle.update_at((e, v), t.into(), diff),
-                },
             }
         }
 
         Ok(())
     }
 
+    /// Maintains value ownership for unique-identity attributes,
+    /// dropping redundant assertions and retractions of values not
+    /// owned by the retracting entity.
+    fn enforce_uniqueness(&mut self, batches: &mut TxBatches<A, T>) {
+        for (a, batch) in batches.iter_mut() {
+            if !self.is_identity(a) {
+                continue;
+            }
+
+            let owners = self
+                .identities
+                .entry(a.clone())
+                .or_insert_with(HashMap::new);
+
+            batch.retain(|((e, v), _t, diff)| {
+                if *diff > 0 {
+                    match owners.insert(v.clone(), e.clone()) {
+                        None => true,
+                        Some(_) => false,
+                    }
+                } else if owners.get(v) == Some(e) {
+                    owners.remove(v);
+                    true
+                } else {
+                    false
+                }
+            });
+        }
+    }
+
+    /// Maintains the (a, v) pairs currently asserted about each entity.
+    fn track_entities(&mut self, batches: &TxBatches<A, T>) {
+        for (a, batch) in batches.iter() {
+            // Windowed datoms retract themselves.
+            if self
+                .attributes
+                .get(a)
+                .map_or(false, |config| config.window.is_some())
+            {
+                continue;
+            }
+
+            for ((e, v), _t, diff) in batch.iter() {
+                let pairs = self.entities.entry(e.clone()).or_insert_with(HashMap::new);
+                let count = {
+                    let count = pairs.entry((a.clone(), v.clone())).or_insert(0);
+                    *count += diff;
+                    *count
+                };
+
+                if count == 0 {
+                    pairs.remove(&(a.clone(), v.clone()));
+                }
+
+                if pairs.is_empty() {
+                    self.entities.remove(e);
+                }
+            }
+        }
+    }
+
+    /// Returns transaction data retracting everything currently
+    /// asserted about the specified entity, across all
+    /// attributes. Only data that went through `transact` is known
+    /// here, bulk loads and sourced attributes are not covered.
+    pub fn retract_entity(&self, e: &Value) -> Vec<Datom<A>> {
+        let mut retractions: Vec<Datom<A>> = match self.entities.get(e) {
+            None => Vec::new(),
+            Some(pairs) => pairs
+                .iter()
+                .map(|((a, v), count)| Datom(e.clone(), a.clone(), v.clone(), None, -count))
+                .collect(),
+        };
+
+        // Keep the order stable across workers.
+        retractions.sort_by(|x, y| (&x.1, &x.2).cmp(&(&y.1, &y.2)));
+
+        retractions
+    }
+
+    /// Validates transaction data and groups it by attribute, without
+    /// touching any input sessions.
+    fn stage(&self, tx_data: Vec<Datom<A>>) -> Result<TxBatches<A, T>, Error> {
+        let mut batches = TxBatches::new();
+
+        for (idx, Datom(e, a, v, t, diff)) in tx_data.into_iter().enumerate() {
+            let handle = match self.input_sessions.get(&a) {
+                None => {
+                    return Err(Error::not_found(format!(
+                        "Attribute {} does not exist (datom {}).",
+                        a, idx
+                    )));
+                }
+                Some(handle) => handle,
+            };
+
+            if let Some(config) = self.attributes.get(&a) {
+                if let Some(expected) = config.value_type {
+                    if v.value_type() != Some(expected) {
+                        return Err(Error::incorrect(format!(
+                            "Attribute {} expects values of type {:?}, datom {} has value {:?}.",
+                            a, expected, idx, v
+                        )));
+                    }
+                }
+            }
+
+            let t: Option<T> = t.map(|t| t.into());
+
+            if let Some(ref t) = t {
+                if !handle.time().less_equal(t) {
+                    return Err(Error::conflict(format!(
+                        "Attribute {} is at {:?}, datom {} attempted to write at {:?}.",
+                        a,
+                        handle.time(),
+                        idx,
+                        t
+                    )));
+                }
+            }
+
+            batches
+                .entry(a)
+                .or_insert_with(Vec::new)
+                .push(((e, v), t, diff));
+        }
+
+        Ok(batches)
+    }
+
+    /// Rewrites batches for cardinality-one attributes, s.t. asserting
+    /// a new value for an entity retracts its previous one. Redundant
+    /// assertions and retractions of values that are not current are
+    /// dropped.
+    fn enforce_cardinality(&mut self, batches: &mut TxBatches<A, T>) {
+        for (a, batch) in batches.iter_mut() {
+            let is_cardinality_one = match self.attributes.get(a) {
+                None => false,
+                Some(config) => config.cardinality == Cardinality::One,
+            };
+
+            if !is_cardinality_one {
+                continue;
+            }
+
+            let current = self
+                .current_values
+                .entry(a.clone())
+                .or_insert_with(HashMap::new);
+
+            let mut rewritten = Vec::with_capacity(batch.len());
+
+            for ((e, v), t, diff) in batch.drain(..) {
+                if diff > 0 {
+                    match current.insert(e.clone(), v.clone()) {
+                        None => rewritten.push(((e, v), t, 1)),
+                        Some(ref old) if *old == v => {}
+                        Some(old) => {
+                            rewritten.push(((e.clone(), old), t.clone(), -1));
+                            rewritten.push(((e, v), t, 1));
+                        }
+                    }
+                } else if diff < 0 && current.get(&e) == Some(&v) {
+                    current.remove(&e);
+                    rewritten.push(((e, v), t, -1));
+                }
+            }
+
+            *batch = rewritten;
+        }
+    }
+
     /// Closes and drops an existing input.
     pub fn close_input(&mut self, name: A) -> Result<(), Error> {
         match self.input_sessions.remove(&name) {
@@ -227,10 +954,112 @@ where
         }
     }
 
+    /// Returns the configurations of all attributes in this domain,
+    /// ordered by attribute name.
+    pub fn schema(&self) -> Vec<(A, AttributeConfig)> {
+        let mut schema: Vec<(A, AttributeConfig)> = self
+            .attributes
+            .iter()
+            .map(|(name, config)| (name.clone(), config.clone()))
+            .collect();
+
+        schema.sort_by(|x, y| x.0.cmp(&y.0));
+
+        schema
+    }
+
+    /// Returns the names of all registered rules that depend on the
+    /// specified attribute, either directly or via other rules.
+    pub fn dependents(&self, name: &A) -> Vec<A> {
+        let mut dependents: HashSet<A> = HashSet::new();
+        let mut changed = true;
+
+        while changed {
+            changed = false;
+
+            for (rule_name, rule) in self.rules.iter() {
+                if dependents.contains(rule_name) {
+                    continue;
+                }
+
+                let dependencies = rule.plan.dependencies();
+
+                if dependencies.attributes.contains(name)
+                    || dependencies.names.iter().any(|n| dependents.contains(n))
+                {
+                    dependents.insert(rule_name.clone());
+                    changed = true;
+                }
+            }
+        }
+
+        let mut dependents: Vec<A> = dependents.into_iter().collect();
+        dependents.sort();
+
+        dependents
+    }
+
+    /// Removes an attribute from the domain, closing its input and
+    /// dropping all of its indices. Fails if any registered rules
+    /// still depend on the attribute.
+    pub fn drop_attribute(&mut self, name: &A) -> Result<(), Error> {
+        if !self.has_attribute(name) {
+            return Err(Error::not_found(format!(
+                "Attribute {} does not exist.",
+                name
+            )));
+        }
+
+        let dependents = self.dependents(name);
+        if !dependents.is_empty() {
+            return Err(Error::conflict(format!(
+                "Attribute {} is still used by rules {:?}.",
+                name, dependents
+            )));
+        }
+
+        if let Some(handle) = self.input_sessions.remove(name) {
+            handle.close();
+        }
+
+        self.attributes.remove(name);
+        self.current_values.remove(name);
+        self.expirations.remove(name);
+        self.identities.remove(name);
+
+        for pairs in self.entities.values_mut() {
+            pairs.retain(|(a, _v), _count| a != name);
+        }
+        self.entities.retain(|_e, pairs| !pairs.is_empty());
+
+        self.forward_count.remove(name);
+        self.forward_propose.remove(name);
+        self.forward_validate.remove(name);
+        self.reverse_count.remove(name);
+        self.reverse_propose.remove(name);
+        self.reverse_validate.remove(name);
+        self.fulltext.remove(name);
+        self.composite
+            .retain(|_name, composite| !composite.attributes.contains(name));
+        self.reverse_imports.remove(name);
+        self.evicted.remove(name);
+        self.statistics.remove(name);
+
+        Ok(())
+    }
+
     /// Advances the domain to the current domain frontier, thus
     /// allowing traces to compact. All domain input handles are
     /// forwarded up to the frontier, so as not to stall progress.
     pub fn advance(&mut self) -> Result<(), Error> {
+        let result = self.advance_inputs();
+        self.refresh_statistics();
+        self.record_progress();
+
+        result
+    }
+
+    fn advance_inputs(&mut self) -> Result<(), Error> {
         if self.probed_source_count() == 0 {
             // No sources registered.
             self.advance_traces(&[self.epoch().clone()])
@@ -260,6 +1089,87 @@ where
         }
     }
 
+    /// Recomputes the statistics of all attributes whose indices
+    /// have changed significantly since they were last computed.
+    fn refresh_statistics(&mut self) {
+        for (aid, forward) in self.forward_propose.iter_mut() {
+            let is_stale = match self.statistics.get(aid) {
+                None => true,
+                Some(statistics) => statistics.forward.is_stale(forward),
+            };
+
+            if is_stale {
+                let reverse = self.reverse_propose.get_mut(aid).map(IndexStatistics::of);
+                let statistics = AttributeStatistics {
+                    forward: IndexStatistics::of(forward),
+                    reverse,
+                };
+
+                self.statistics.insert(aid.clone(), statistics);
+            }
+        }
+    }
+
+    /// Reports the domain frontier and the sizes of all attribute
+    /// arrangements, if metrics are enabled.
+    fn record_progress(&mut self) {
+        let recorder = match self.metrics {
+            None => return,
+            Some(ref recorder) => recorder,
+        };
+
+        let epoch = metrics::time_to_sample(self.now_at.clone().into());
+
+        let frontier = self
+            .domain_probe
+            .with_frontier(|frontier| frontier.iter().min().cloned());
+
+        if let Some(frontier) = frontier {
+            if self.probed_source_count > 0 {
+                let frontier = metrics::time_to_sample(frontier.into());
+                recorder.set(metrics::DOMAIN_FRONTIER, vec![], frontier);
+                recorder.set(metrics::FRONTIER_LAG, vec![], (epoch - frontier).max(0.0));
+            }
+        }
+
+        fn size<Tr: TraceReader>(trace: &mut Tr) -> usize {
+            let mut size = 0;
+            trace.map_batches(|batch| size += batch.len());
+            size
+        }
+
+        let mut sizes = Vec::new();
+        for (aid, trace) in self.forward_count.iter_mut() {
+            sizes.push((aid.to_string(), "forward_count", size(trace)));
+        }
+        for (aid, trace) in self.forward_propose.iter_mut() {
+            sizes.push((aid.to_string(), "forward_propose", size(trace)));
+        }
+        for (aid, trace) in self.forward_validate.iter_mut() {
+            sizes.push((aid.to_string(), "forward_validate", size(trace)));
+        }
+        for (aid, trace) in self.reverse_count.iter_mut() {
+            sizes.push((aid.to_string(), "reverse_count", size(trace)));
+        }
+        for (aid, trace) in self.reverse_propose.iter_mut() {
+            sizes.push((aid.to_string(), "reverse_propose", size(trace)));
+        }
+        for (aid, trace) in self.reverse_validate.iter_mut() {
+            sizes.push((aid.to_string(), "reverse_validate", size(trace)));
+        }
+        for (aid, trace) in self.fulltext.iter_mut() {
+            sizes.push((aid.to_string(), "fulltext", size(trace)));
+        }
+        for (name, composite) in self.composite.iter_mut() {
+            sizes.push((name.to_string(), "composite", size(&mut composite.trace)));
+        }
+
+        for (aid, index, size) in sizes {
+            let labels = vec![("attribute", aid), ("index", index.to_string())];
+            recorder.set(metrics::ARRANGEMENT_SIZE, labels, size as f64);
+        }
+    }
+
     /// Advances the domain epoch. The domain epoch can be in advance
     /// of or lag behind the domain frontier. It is used by timeless
     /// attributes to avoid stalling timeful inputs.
@@ -273,12 +1183,25 @@ where
         } else if !self.now_at.eq(&next) {
             trace!("Advancing domain epoch to {:?} ", next);
 
+            self.expire(&next);
+
             for handle in self.input_sessions.values_mut() {
                 handle.advance_to(next.clone());
                 handle.flush();
             }
+
+            for handle in self.parameter_sessions.values_mut() {
+                handle.advance_to(next.clone());
+                handle.flush();
+            }
+
             self.now_at = next;
 
+            if let Some(ref recorder) = self.metrics {
+                let epoch = metrics::time_to_sample(self.now_at.clone().into());
+                recorder.set(metrics::DOMAIN_EPOCH, vec![], epoch);
+            }
+
             Ok(())
         } else {
             Ok(())
@@ -294,13 +1217,45 @@ where
             trace!("Advancing traces to {:?}", frontier);
 
             self.last_advance = frontier.to_vec();
+
+            // Parameters and intermediates are only ever queried at
+            // the current time.
+            for trace in self
+                .parameters
+                .values_mut()
+                .chain(self.intermediates.values_mut())
+            {
+                trace.advance_by(frontier);
+                trace.distinguish_since(frontier);
+            }
+
             let frontier = AntichainRef::new(frontier);
+            let hold = self.compaction_hold.clone();
+
+            // Composite indices don't retain any slack of the
+            // attributes they cover.
+            let composite_frontier = frontier
+                .iter()
+                .map(|t| match hold {
+                    None => t.clone(),
+                    Some(ref hold) => t.meet(hold),
+                })
+                .collect::<Vec<T>>();
+
+            for composite in self.composite.values_mut() {
+                composite.trace.advance_by(&composite_frontier);
+                composite.trace.distinguish_since(&composite_frontier);
+            }
 
             for (aid, config) in self.attributes.iter() {
                 if let Some(ref trace_slack) = config.trace_slack {
                     let slacking_frontier = frontier
                         .iter()
                         .map(|t| t.rewind(trace_slack.clone().into()))
+                        .map(|t| match hold {
+                            None => t,
+                            Some(ref hold) => t.meet(hold),
+                        })
                         .collect::<Vec<T>>();;
 
                     if let Some(trace) = self.forward_count.get_mut(aid) {
@@ -332,6 +1287,11 @@ where
                         trace.advance_by(&slacking_frontier);
                         trace.distinguish_since(&slacking_frontier);
                     }
+
+                    if let Some(trace) = self.fulltext.get_mut(aid) {
+                        trace.advance_by(&slacking_frontier);
+                        trace.distinguish_since(&slacking_frontier);
+                    }
                 }
             }
         }
@@ -339,21 +1299,168 @@ where
         Ok(())
     }
 
+    /// Prevents attribute traces from compacting beyond the specified
+    /// time, until the hold is released again by passing `None`.
+    pub fn hold_compaction(&mut self, hold: Option<T>) {
+        self.compaction_hold = hold;
+    }
+
+    /// Reads the consolidated contents of all attributes fed by
+    /// input handles, as of the specified time, from this worker's
+    /// share of their forward indices. Returns `None` if the indices
+    /// haven't caught up with that time yet.
+    ///
+    /// Callers have to make sure traces didn't compact beyond the
+    /// requested time (see `hold_compaction`), or later updates will
+    /// be mistaken for earlier ones.
+    pub fn snapshot(&mut self, as_of: &T) -> Option<Vec<(A, Vec<(Value, Value, isize)>)>> {
+        let mut names: Vec<A> = self.input_sessions.keys().cloned().collect();
+        names.sort();
+
+        let mut attributes = Vec::with_capacity(names.len());
+
+        for name in names.into_iter() {
+            if self.forward_propose.contains_key(&name) {
+                let tuples = self.contents(&name, as_of).ok()??;
+                attributes.push((name, tuples));
+            }
+        }
+
+        Some(attributes)
+    }
+
+    /// Reads the consolidated (e, v, count) contents of a single
+    /// attribute as of the specified time, like `snapshot` does.
+    pub fn contents(
+        &mut self,
+        name: &A,
+        as_of: &T,
+    ) -> Result<Option<Vec<(Value, Value, isize)>>, Error> {
+        let trace = match self.forward_propose.get_mut(name) {
+            None => {
+                return Err(Error::not_found(format!(
+                    "Attribute {} does not exist.",
+                    name
+                )))
+            }
+            Some(trace) => trace,
+        };
+
+        let mut upper = None;
+        trace.map_batches(|batch| upper = Some(batch.upper().to_vec()));
+
+        match upper {
+            None => return Ok(None),
+            Some(upper) => {
+                if AntichainRef::new(&upper).less_equal(as_of) {
+                    return Ok(None);
+                }
+            }
+        }
+
+        let mut tuples = Vec::new();
+        let (mut cursor, storage) = trace.cursor();
+
+        while let Some(e) = cursor.get_key(&storage) {
+            while let Some(v) = cursor.get_val(&storage) {
+                let mut count = 0;
+                cursor.map_times(&storage, |t, diff| {
+                    if t.less_equal(as_of) {
+                        count += diff;
+                    }
+                });
+
+                if count != 0 {
+                    tuples.push((e.clone(), v.clone(), count));
+                }
+
+                cursor.step_val(&storage);
+            }
+
+            cursor.step_key(&storage);
+        }
+
+        Ok(Some(tuples))
+    }
+
     /// Returns a handle to the domain's input probe.
     pub fn domain_probe(&self) -> &ProbeHandle<T> {
         &self.domain_probe
     }
 
+    /// Lets the wall clock advance the domain at the specified
+    /// interval, see `advance_clock`.
+    pub fn set_clock(&mut self, interval: Option<Duration>) {
+        self.clock = interval;
+    }
+
+    /// Advances the epoch to the specified time elapsed on the wall
+    /// clock, rounded down to the clock interval. The epoch advances
+    /// regardless of whether any inputs arrived in the meantime, s.t.
+    /// windowed attributes expire and results are revealed even in
+    /// idle domains. Only domains in real time, or in bitemporal time
+    /// with the wall clock as their first coordinate, can be driven
+    /// this way. Does nothing for domains without a clock, or whose
+    /// epoch is ahead of it already.
+    pub fn advance_clock(&mut self, elapsed: Duration) -> Result<(), Error> {
+        let interval = match self.clock {
+            None => return Ok(()),
+            Some(interval) => interval.as_nanos().max(1),
+        };
+
+        let now = Duration::from_nanos((elapsed.as_nanos() / interval * interval) as u64);
+
+        let next: T = match self.now_at.clone().into() {
+            Time::TxId(_) => {
+                return Err(Error::unsupported(
+                    "Domains in transaction time can't be driven by a clock.",
+                ))
+            }
+            Time::Real(_) => Time::Real(now).into(),
+            Time::Bi(_, tx) => Time::Bi(now, tx).into(),
+        };
+
+        if self.now_at.less_than(&next) {
+            self.advance_epoch(next)
+        } else {
+            Ok(())
+        }
+    }
+
     /// Reports the current input epoch.
     pub fn epoch(&self) -> &T {
         &self.now_at
     }
 
+    /// Counts one more probed source and returns the probe it must
+    /// report its progress to.
+    pub fn track_source(&mut self) -> ProbeHandle<T> {
+        self.probed_source_count += 1;
+        self.domain_probe.clone()
+    }
+
     /// Reports the number of probed (timeful) sources in the domain.
     pub fn probed_source_count(&self) -> usize {
         self.probed_source_count
     }
 
+    /// Reports the frontier before which all domain inputs are
+    /// complete, i.e. that of its sources or, absent any, its epoch.
+    pub fn input_frontier(&self) -> Vec<T> {
+        let frontier = if self.probed_source_count() == 0 {
+            Vec::new()
+        } else {
+            self.domain_probe
+                .with_frontier(|frontier| (*frontier).to_vec())
+        };
+
+        if frontier.is_empty() {
+            vec![self.epoch().clone()]
+        } else {
+            frontier
+        }
+    }
+
     /// Returns true iff the frontier dominates all domain inputs.
     pub fn dominates(&self, frontier: AntichainRef<T>) -> bool {
         // We must distinguish the scenario where the internal domain
@@ -377,11 +1484,397 @@ where
         self.rules.get(name)
     }
 
+    /// Removes the named rule from the domain. Fails if any other
+    /// registered rules still depend on it.
+    pub fn unregister(&mut self, name: &A) -> Result<(), Error> {
+        if !self.rules.contains_key(name) {
+            return Err(Error::not_found(format!("Rule {} does not exist.", name)));
+        }
+
+        let mut dependents: Vec<A> = self
+            .rules
+            .iter()
+            .filter(|(_, rule)| rule.plan.dependencies().names.contains(name))
+            .map(|(rule_name, _)| rule_name.clone())
+            .filter(|rule_name| rule_name != name)
+            .collect();
+
+        if !dependents.is_empty() {
+            dependents.sort();
+
+            return Err(Error::conflict(format!(
+                "Rule {} is still used by rules {:?}.",
+                name, dependents
+            )));
+        }
+
+        self.rules.remove(name);
+        self.intermediates.remove(name);
+        self.shutdown_handles.remove(&name.to_string());
+
+        Ok(())
+    }
+
+    /// Changes how closely an attribute's traces follow the domain
+    /// frontier from now on. History that has already been compacted
+    /// can't be restored by widening the slack.
+    pub fn set_trace_slack(&mut self, name: &A, slack: Option<Time>) -> Result<(), Error> {
+        match self.attributes.get_mut(name) {
+            None => Err(Error::not_found(format!(
+                "Attribute {} does not exist.",
+                name
+            ))),
+            Some(config) => {
+                config.trace_slack = slack;
+                Ok(())
+            }
+        }
+    }
+
+    /// Checks whether an attribute's traces are never compacted, thus
+    /// retaining its full history.
+    pub fn keeps_history(&self, name: &A) -> bool {
+        match self.attributes.get(name) {
+            None => false,
+            Some(config) => config.trace_slack.is_none(),
+        }
+    }
+
+    /// Checks whether an attribute's traces still distinguish all
+    /// times from `t` onwards, i.e. whether queries as of `t` can be
+    /// answered.
+    pub fn retains(&mut self, name: &A, t: &T) -> bool {
+        match self.forward_propose.get_mut(name) {
+            None => false,
+            Some(trace) => trace.advance_frontier().iter().all(|f| f.less_equal(t)),
+        }
+    }
+
+    /// Installs a query parameter, i.e. a named input relation of
+    /// arbitrary arity that rules can refer to via
+    /// `Plan::Parameter`, and that clients can bind tuples to later.
+    pub fn create_parameter(
+        &mut self,
+        name: A,
+        handle: UnorderedHandle<T, (Vec<Value>, T, isize)>,
+        cap: ActivateCapability<T>,
+        trace: TraceKeyHandle<Vec<Value>, T, isize>,
+    ) -> Result<(), Error> {
+        if self.parameters.contains_key(&name) {
+            return Err(Error::conflict(format!(
+                "A parameter of name {} already exists.",
+                name
+            )));
+        }
+
+        let mut session = UnorderedSession::from(handle, cap);
+        session.advance_to(self.now_at.clone());
+        session.flush();
+
+        self.parameter_sessions.insert(name.clone(), session);
+        self.parameters.insert(name, trace);
+
+        Ok(())
+    }
+
+    /// Registers a composite index over the specified attributes. The
+    /// shutdown handle keeps the imports feeding the index alive
+    /// until any of its attributes are dropped.
+    pub fn create_composite(
+        &mut self,
+        name: A,
+        kind: CompositeIndex,
+        attributes: Vec<A>,
+        trace: TraceValHandle<Value, (Value, Value), T, isize>,
+        shutdown: ShutdownHandle,
+    ) -> Result<(), Error> {
+        if self.composite.contains_key(&name) {
+            return Err(Error::conflict(format!(
+                "An index of name {} already exists.",
+                name
+            )));
+        }
+
+        let composite = Composite {
+            kind,
+            attributes,
+            trace,
+            _shutdown: shutdown,
+        };
+
+        self.composite.insert(name, composite);
+
+        Ok(())
+    }
+
+    /// Builds the reverse indices of an attribute created with
+    /// forward indices only, from its forward propose index. Reverse
+    /// count and validate indices are only built if their forward
+    /// counterparts exist. History the forward index has compacted
+    /// already is not recovered. Attributes maintaining reverse
+    /// indices are left alone.
+    pub fn build_reverse_indices<S>(&mut self, scope: &mut S, name: &A) -> Result<(), Error>
+    where
+        S: Scope<Timestamp = T>,
+    {
+        if self.reverse_propose.contains_key(name) {
+            return Ok(());
+        }
+
+        let (propose, shutdown) = match self.forward_propose.get_mut(name) {
+            None => {
+                return Err(Error::not_found(format!(
+                    "Attribute {} does not exist.",
+                    name
+                )))
+            }
+            Some(propose_trace) => {
+                propose_trace.import_frontier(scope, &format!("Propose({})", name))
+            }
+        };
+
+        let pairs = propose.as_collection(|e, v| (e.clone(), v.clone()));
+
+        if self.forward_count.contains_key(name) {
+            self.reverse_count.insert(
+                name.clone(),
+                pairs
+                    .map(|(_e, v)| (v, ()))
+                    .arrange_named(&format!("->_Count({})", name))
+                    .trace,
+            );
+        }
+
+        self.reverse_propose.insert(
+            name.clone(),
+            pairs
+                .map(|(e, v)| (v, e))
+                .arrange_named(&format!("->_Propose({})", name))
+                .trace,
+        );
+
+        if self.forward_validate.contains_key(name) {
+            self.reverse_validate.insert(
+                name.clone(),
+                pairs
+                    .map(|pair| (pair, ()))
+                    .arrange_named(&format!("->_Validate({})", name))
+                    .trace,
+            );
+        }
+
+        if let Some(config) = self.attributes.get_mut(name) {
+            config.index_direction = IndexDirection::Both;
+        }
+
+        self.reverse_imports
+            .insert(name.clone(), ShutdownHandle::from_button(shutdown));
+
+        Ok(())
+    }
+
+    /// Drops all indices of an attribute that can't change anymore,
+    /// because its input has been closed and all of its updates have
+    /// been indexed, after handing this worker's share of its
+    /// (compacted) updates to `offload`. Indices are only dropped
+    /// once `offload` succeeded. Their memory is released as soon as
+    /// no running query imports them any longer.
+    ///
+    /// The attribute's configuration is retained, s.t. its updates
+    /// can be paged back in later, see `unevict`.
+    pub fn evict<F>(&mut self, name: &A, offload: F) -> Result<(), Error>
+    where
+        F: FnOnce(Vec<((Value, Value), T, isize)>) -> Result<(), Error>,
+    {
+        if self.evicted.contains(name) {
+            return Err(Error::conflict(format!(
+                "Attribute {} has been evicted already.",
+                name
+            )));
+        }
+
+        let trace = match self.forward_propose.get_mut(name) {
+            None => {
+                return Err(Error::not_found(format!(
+                    "Attribute {} does not exist.",
+                    name
+                )))
+            }
+            Some(trace) => trace,
+        };
+
+        let mut sealed = false;
+        trace.map_batches(|batch| sealed = batch.upper().is_empty());
+
+        if self.input_sessions.contains_key(name) || !sealed {
+            return Err(Error::conflict(format!(
+                "Attribute {} may still change, only attributes whose inputs have been closed can be evicted.",
+                name
+            )));
+        }
+
+        let mut updates = Vec::new();
+        {
+            let (mut cursor, storage) = trace.cursor();
+
+            while let Some(e) = cursor.get_key(&storage) {
+                while let Some(v) = cursor.get_val(&storage) {
+                    cursor.map_times(&storage, |t, diff| {
+                        updates.push(((e.clone(), v.clone()), t.clone(), *diff));
+                    });
+
+                    cursor.step_val(&storage);
+                }
+
+                cursor.step_key(&storage);
+            }
+        }
+
+        offload(updates)?;
+
+        self.forward_count.remove(name);
+        self.forward_propose.remove(name);
+        self.forward_validate.remove(name);
+        self.reverse_count.remove(name);
+        self.reverse_propose.remove(name);
+        self.reverse_validate.remove(name);
+        self.fulltext.remove(name);
+        self.reverse_imports.remove(name);
+        self.statistics.remove(name);
+
+        self.evicted.insert(name.clone());
+
+        Ok(())
+    }
+
+    /// Checks whether the attribute's indices have been evicted.
+    pub fn is_evicted(&self, name: &A) -> bool {
+        self.evicted.contains(name)
+    }
+
+    /// Forgets that an attribute has been evicted, removing and
+    /// returning its configuration, s.t. it can be installed anew
+    /// from its offloaded updates.
+    pub fn unevict(&mut self, name: &A) -> Option<AttributeConfig> {
+        if self.evicted.remove(name) {
+            self.attributes.remove(name)
+        } else {
+            None
+        }
+    }
+
+    /// Registers the arranged results of a rule, s.t. later
+    /// dataflows can import them instead of implementing the rule
+    /// again. The shutdown handle keeps the materializing operators
+    /// alive until the rule is unregistered.
+    pub fn materialize(
+        &mut self,
+        name: A,
+        trace: TraceKeyHandle<Vec<Value>, T, isize>,
+        shutdown_handle: ShutdownHandle,
+    ) {
+        self.shutdown_handles
+            .insert(name.to_string(), shutdown_handle);
+        self.intermediates.insert(name, trace);
+    }
+
+    /// Checks whether the results of the named rule have been
+    /// materialized.
+    pub fn is_materialized(&self, name: &A) -> bool {
+        self.intermediates.contains_key(name)
+    }
+
+    /// Binds tuples to (or, given negative multiplicities, unbinds
+    /// them from) the specified query parameter.
+    pub fn bind(&mut self, name: &A, tuples: Vec<(Vec<Value>, isize)>) -> Result<(), Error> {
+        match self.parameter_sessions.get_mut(name) {
+            None => Err(Error::not_found(format!(
+                "Parameter {} does not exist.",
+                name
+            ))),
+            Some(session) => {
+                for (tuple, diff) in tuples.into_iter() {
+                    session.update(tuple, diff);
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the number of arrangements maintained by this
+    /// domain. Queries import these traces rather than arranging
+    /// attributes themselves, so this grows with the number of
+    /// attributes and parameters, not with the number of queries
+    /// using them.
+    pub fn arrangement_count(&self) -> usize {
+        self.forward_count.len()
+            + self.forward_propose.len()
+            + self.forward_validate.len()
+            + self.reverse_count.len()
+            + self.reverse_propose.len()
+            + self.reverse_validate.len()
+            + self.fulltext.len()
+            + self.composite.len()
+            + self.parameters.len()
+            + self.intermediates.len()
+    }
+
+    /// Measures all arrangements held by this domain on this worker,
+    /// ordered by name and kind.
+    pub fn index_sizes(&mut self) -> Vec<IndexSize> {
+        let mut sizes = Vec::new();
+
+        for (aid, trace) in self.forward_count.iter_mut() {
+            sizes.push(IndexSize::of(aid.to_string(), "forward_count", trace));
+        }
+        for (aid, trace) in self.forward_propose.iter_mut() {
+            sizes.push(IndexSize::of(aid.to_string(), "forward_propose", trace));
+        }
+        for (aid, trace) in self.forward_validate.iter_mut() {
+            sizes.push(IndexSize::of(aid.to_string(), "forward_validate", trace));
+        }
+        for (aid, trace) in self.reverse_count.iter_mut() {
+            sizes.push(IndexSize::of(aid.to_string(), "reverse_count", trace));
+        }
+        for (aid, trace) in self.reverse_propose.iter_mut() {
+            sizes.push(IndexSize::of(aid.to_string(), "reverse_propose", trace));
+        }
+        for (aid, trace) in self.reverse_validate.iter_mut() {
+            sizes.push(IndexSize::of(aid.to_string(), "reverse_validate", trace));
+        }
+        for (aid, trace) in self.fulltext.iter_mut() {
+            sizes.push(IndexSize::of(aid.to_string(), "fulltext", trace));
+        }
+        for (name, composite) in self.composite.iter_mut() {
+            sizes.push(IndexSize::of(
+                name.to_string(),
+                "composite",
+                &mut composite.trace,
+            ));
+        }
+        for (name, trace) in self.parameters.iter_mut() {
+            sizes.push(IndexSize::of(name.to_string(), "parameter", trace));
+        }
+        for (name, trace) in self.intermediates.iter_mut() {
+            sizes.push(IndexSize::of(name.to_string(), "materialized", trace));
+        }
+
+        sizes.sort();
+        sizes
+    }
+
     /// Checks whether an attribute of that name exists.
     pub fn has_attribute(&self, name: &A) -> bool {
         self.attributes.contains_key(name)
     }
 
+    /// Checks whether the specified attribute still accepts
+    /// transactions, i.e. whether it is neither sourced nor closed.
+    pub fn is_transactable(&self, name: &A) -> bool {
+        self.input_sessions.contains_key(name)
+    }
+
     /// Retrieves the forward count trace for the specified aid.
     pub fn forward_count(&mut self, name: &A) -> Option<&mut TraceKeyHandle<Value, T, isize>> {
         self.forward_count.get_mut(name)
@@ -423,6 +1916,44 @@ where
     ) -> Option<&mut TraceKeyHandle<(Value, Value), T, isize>> {
         self.reverse_validate.get_mut(name)
     }
+
+    /// Retrieves the fulltext trace for the specified aid.
+    pub fn fulltext(
+        &mut self,
+        name: &A,
+    ) -> Option<&mut TraceValHandle<String, (Value, Value), T, isize>> {
+        self.fulltext.get_mut(name)
+    }
+
+    /// Returns the name of an entity index covering all of the
+    /// specified attributes, if there is one.
+    pub fn entity_index_covering(&self, attributes: &[A]) -> Option<A> {
+        self.composite_covering(CompositeIndex::Entity, attributes)
+    }
+
+    /// Returns the name of a ref index covering the specified
+    /// attribute, if there is one.
+    pub fn ref_index_covering(&self, attribute: &A) -> Option<A> {
+        self.composite_covering(CompositeIndex::Ref, std::slice::from_ref(attribute))
+    }
+
+    fn composite_covering(&self, kind: CompositeIndex, attributes: &[A]) -> Option<A> {
+        let mut candidates: Vec<&A> = self
+            .composite
+            .iter()
+            .filter(|(_name, composite)| composite.kind == kind)
+            .filter(|(_name, composite)| {
+                attributes.iter().all(|a| composite.attributes.contains(a))
+            })
+            .map(|(name, _composite)| name)
+            .collect();
+
+        // Prefer the narrowest index, breaking ties by name, s.t. all
+        // workers agree.
+        candidates.sort_by_key(|name| (self.composite[*name].attributes.len(), (*name).clone()));
+
+        candidates.first().map(|name| (*name).clone())
+    }
 }
 
 /// A domain that is still under construction in a specific scope.
@@ -533,6 +2064,31 @@ where
 
         self
     }
+
+    /// Installs fulltext indices for all attributes in the domain.
+    /// Only string values are tokenized, all others are ignored.
+    pub fn with_fulltext_index(mut self) -> Self {
+        for aid in self.domain.forward_propose.keys() {
+            self.domain.fulltext.insert(
+                aid.clone(),
+                self.raw[aid]
+                    .flat_map(|(e, v)| {
+                        let tokens = match v {
+                            Value::String(ref text) => tokenize(text),
+                            _ => Vec::new(),
+                        };
+
+                        tokens
+                            .into_iter()
+                            .map(move |token| (token, (e.clone(), v.clone())))
+                    })
+                    .arrange_named(&format!("->Fulltext({})", aid))
+                    .trace,
+            );
+        }
+
+        self
+    }
 }
 
 impl<A, S> ScopedDomain<A, S>
@@ -656,6 +2212,50 @@ where
     }
 }
 
+impl<A, S> AsSingletonDomain<A, S>
+    for (
+        ProbeHandle<S::Timestamp>,
+        Collection<S, (Value, Value), isize>,
+    )
+where
+    A: AsAid,
+    S: Scope,
+    S::Timestamp: Timestamp + Lattice + Rewind,
+{
+    fn as_singleton_domain<X: Into<A>>(self, name: X) -> ScopedDomain<A, S> {
+        let name: A = name.into();
+
+        let mut domain = Domain::new(Default::default());
+
+        // When given a collection together with the probe of an
+        // existing domain, the source is tracked by that domain
+        // instead (see `Domain::track_source`). The resulting domain
+        // has no sources of its own, s.t. it can be composed with
+        // the tracking domain, even if that one has sources already.
+        let (mut probe, pairs) = self;
+        let pairs = pairs.probe_with(&mut probe);
+
+        let mut raw = HashMap::new();
+        raw.insert(name.clone(), pairs);
+
+        // Propose traces are used in general, whereas the other
+        // indices are only relevant to Hector.
+        domain.forward_propose.insert(
+            name.clone(),
+            raw[&name]
+                .arrange_named(&format!("->Propose({})", &name))
+                .trace,
+        );
+
+        // This is crucial. If we forget to install the attribute
+        // configuration, its traces will be ignored when advancing
+        // the domain.
+        domain.attributes.insert(name, Default::default());
+
+        ScopedDomain { raw, domain }
+    }
+}
+
 impl<A, S> AsSingletonDomain<A, S> for Stream<S, ((Value, Value), S::Timestamp, isize)>
 where
     A: AsAid,
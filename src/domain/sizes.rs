@@ -0,0 +1,46 @@
+//! Size accounting for the arrangements held by a domain, s.t. the
+//! indices dominating memory can be identified.
+
+use std::mem::size_of;
+
+use differential_dataflow::trace::{BatchReader, TraceReader};
+
+/// The size of a single arrangement on this worker.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct IndexSize {
+    /// The attribute, parameter, rule, or composite index arranged.
+    pub name: String,
+    /// The kind of arrangement, e.g. `forward_propose`.
+    pub index: String,
+    /// Number of batches the trace currently consists of.
+    pub batches: usize,
+    /// Number of updates held across all batches.
+    pub records: usize,
+    /// Approximate number of bytes held by those updates. Heap data
+    /// owned by values, e.g. the contents of strings, isn't
+    /// accounted for.
+    pub bytes: usize,
+}
+
+impl IndexSize {
+    /// Measures the current contents of a trace. This only inspects
+    /// batch sizes and is therefore cheap.
+    pub fn of<Tr: TraceReader>(name: String, index: &str, trace: &mut Tr) -> Self {
+        let mut batches = 0;
+        let mut records = 0;
+        trace.map_batches(|batch| {
+            batches += 1;
+            records += batch.len();
+        });
+
+        let record_size = size_of::<(Tr::Key, Tr::Val, Tr::Time, Tr::R)>();
+
+        IndexSize {
+            name,
+            index: index.to_string(),
+            batches,
+            records,
+            bytes: records * record_size,
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Lightweight cardinality statistics over attribute indices, used
+//! to estimate the selectivity of data patterns.
+
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::trace::{BatchReader, Cursor, TraceReader};
+
+use crate::{TraceValHandle, Value};
+
+/// Statistics are recomputed once the number of updates held by an
+/// index has changed by more than this fraction.
+const REFRESH_THRESHOLD: f64 = 0.1;
+
+/// Cardinality statistics of a single index.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexStatistics {
+    /// Number of distinct keys.
+    pub keys: usize,
+    /// Number of (key, value) tuples.
+    pub tuples: usize,
+    /// Number of updates held by the index at the time these
+    /// statistics were computed.
+    #[serde(skip)]
+    updates: usize,
+}
+
+impl IndexStatistics {
+    /// Computes statistics over the current contents of a propose
+    /// trace, regardless of whether all updates are complete yet.
+    pub fn of<T>(trace: &mut TraceValHandle<Value, Value, T, isize>) -> Self
+    where
+        T: Timestamp + Lattice,
+    {
+        let mut statistics = IndexStatistics {
+            updates: updates(trace),
+            ..Default::default()
+        };
+
+        let (mut cursor, storage) = trace.cursor();
+        while cursor.key_valid(&storage) {
+            let mut has_values = false;
+
+            while cursor.val_valid(&storage) {
+                let mut count = 0;
+                cursor.map_times(&storage, |_t, d| count += d);
+
+                if count > 0 {
+                    statistics.tuples += 1;
+                    has_values = true;
+                }
+
+                cursor.step_val(&storage);
+            }
+
+            if has_values {
+                statistics.keys += 1;
+            }
+
+            cursor.step_key(&storage);
+        }
+
+        statistics
+    }
+
+    /// Returns true iff the trace has changed significantly since
+    /// these statistics were computed. This only inspects batch
+    /// sizes and is therefore cheap.
+    pub fn is_stale<T>(&self, trace: &mut TraceValHandle<Value, Value, T, isize>) -> bool
+    where
+        T: Timestamp + Lattice,
+    {
+        let current = updates(trace) as f64;
+        let last = self.updates as f64;
+
+        (current - last).abs() > last * REFRESH_THRESHOLD
+    }
+
+    /// Average number of values per key.
+    pub fn fanout(&self) -> f64 {
+        if self.keys == 0 {
+            0.0
+        } else {
+            self.tuples as f64 / self.keys as f64
+        }
+    }
+}
+
+/// Cardinality statistics of an attribute.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttributeStatistics {
+    /// Statistics of the forward index, keyed by entity.
+    pub forward: IndexStatistics,
+    /// Statistics of the reverse index, keyed by value, if the
+    /// attribute maintains one.
+    pub reverse: Option<IndexStatistics>,
+}
+
+fn updates<T>(trace: &mut TraceValHandle<Value, Value, T, isize>) -> usize
+where
+    T: Timestamp + Lattice,
+{
+    let mut updates = 0;
+    trace.map_batches(|batch| updates += batch.len());
+    updates
+}
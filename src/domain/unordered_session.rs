@@ -70,6 +70,49 @@ impl<T: Timestamp + Clone, D: Data, R: Monoid> UnorderedSession<T, D, R> {
         self.buffer.push((element, time, change));
     }
 
+    /// Adds a whole batch of updates at once, shipping them together
+    /// with anything still buffered. Updates without an explicit time
+    /// are applied at the time of the current capability.
+    pub fn update_batch<I>(&mut self, updates: I)
+    where
+        I: IntoIterator<Item = (D, Option<T>, R)>,
+    {
+        let time = self.cap.time().clone();
+
+        self.buffer
+            .extend(updates.into_iter().map(|(element, t, change)| match t {
+                None => (element, time.clone(), change),
+                Some(t) => {
+                    assert!(time.less_equal(&t));
+                    (element, t, change)
+                }
+            }));
+
+        if !self.buffer.is_empty() {
+            self.handle
+                .session(self.cap.clone())
+                .give_iterator(self.buffer.drain(..));
+        }
+    }
+
+    /// Feeds an entire iterator of updates into the input at the time
+    /// of the current capability, without staging them in the session
+    /// buffer first.
+    pub fn load<I>(&mut self, updates: I)
+    where
+        I: IntoIterator<Item = (D, R)>,
+    {
+        let time = self.cap.time().clone();
+        let mut session = self.handle.session(self.cap.clone());
+
+        session.give_iterator(self.buffer.drain(..));
+        session.give_iterator(
+            updates
+                .into_iter()
+                .map(|(element, change)| (element, time.clone(), change)),
+        );
+    }
+
     /// Forces buffered data into the timely dataflow input, and
     /// advances its time to match that of the session.
     ///
@@ -110,10 +153,11 @@ impl<T: Timestamp + Clone, D: Data, R: Monoid> UnorderedSession<T, D, R> {
         &self.time
     }
 
-    // /// Reveals the current time of the session.
-    // pub fn time(&self) -> &T {
-    //     &self.time
-    // }
+    /// Reveals the time of the capability currently held by the
+    /// session, i.e. the earliest time at which updates are accepted.
+    pub fn time(&self) -> &T {
+        self.cap.time()
+    }
 
     /// Closes the input, flushing and sealing the wrapped timely input.
     pub fn close(self) {}
@@ -12,15 +12,21 @@ extern crate log;
 extern crate serde_derive;
 
 pub mod binding;
+mod bytes;
+#[cfg(feature = "client")]
+pub mod client;
+mod decimal;
 pub mod derive;
 pub mod domain;
 pub mod logging;
+pub mod metrics;
 pub mod operators;
 pub mod plan;
 pub mod scheduling;
 pub mod server;
 pub mod sinks;
 pub mod sources;
+pub mod testing;
 pub mod timestamp;
 
 use std::collections::{HashMap, HashSet, VecDeque};
@@ -33,19 +39,21 @@ use timely::order::Product;
 use timely::progress::Timestamp;
 
 use differential_dataflow::lattice::Lattice;
-use differential_dataflow::operators::arrange::{ShutdownButton, TraceAgent};
+use differential_dataflow::operators::arrange::{Arrange, ShutdownButton, TraceAgent};
 use differential_dataflow::operators::iterate::Variable;
-#[cfg(not(feature = "set-semantics"))]
-use differential_dataflow::operators::Consolidate;
-#[cfg(feature = "set-semantics")]
-use differential_dataflow::operators::Threshold;
+use differential_dataflow::operators::{Consolidate, Threshold};
 use differential_dataflow::trace::implementations::ord::{OrdKeySpine, OrdValSpine};
 use differential_dataflow::{Collection, ExchangeData};
 
+use crate::plan::sharing::is_shared;
+use crate::plan::validation::{validate, validate_bindings};
+
 pub use uuid::Uuid;
 
 pub use num_rational::Rational32;
 
+pub use decimal::Decimal;
+
 pub use binding::{AsBinding, AttributeBinding, Binding};
 pub use domain::Domain;
 pub use plan::{Hector, Implementable, Plan};
@@ -100,6 +108,15 @@ pub enum Value {
     Instant(u64),
     /// A 16 byte unique identifier.
     Uuid(Uuid),
+    /// A temporary entity identifier, only valid within a single
+    /// transaction. Resolved to a fresh entity id at transact time.
+    TempId(String),
+    /// Days since January 1, 1970 UTC
+    Date(i32),
+    /// An opaque binary payload
+    Bytes(#[serde(with = "bytes")] Vec<u8>),
+    /// An exact, arbitrary-scale decimal number
+    Decimal(Decimal),
     /// A fixed-precision real number.
     #[cfg(feature = "real")]
     Real(fixed::types::I16F16),
@@ -116,6 +133,115 @@ impl Value {
         let uuid = Uuid::parse_str(v).expect("failed to parse UUID");
         Value::Uuid(uuid)
     }
+
+    /// Helper to create a Bytes value from a hexadecimal string
+    /// representation.
+    pub fn bytes_hex(v: &str) -> Self {
+        let bytes = bytes::from_hex(v).expect("failed to parse hex string");
+        Value::Bytes(bytes)
+    }
+
+    /// Helper to create a Decimal value from a string representation.
+    pub fn decimal_str(v: &str) -> Self {
+        let decimal = v.parse().expect("failed to parse decimal");
+        Value::Decimal(decimal)
+    }
+
+    /// Helper to create a Date value from a (proleptic Gregorian)
+    /// calendar date.
+    pub fn date(year: i32, month: u32, day: u32) -> Self {
+        assert!(month >= 1 && month <= 12, "month out of range");
+        assert!(day >= 1 && day <= 31, "day out of range");
+
+        // See http://howardhinnant.github.io/date_algorithms.html
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = (if year >= 0 { year } else { year - 399 }) / 400;
+        let year_of_era = year - era * 400;
+        let month = month as i32;
+        let month_of_year = if month > 2 { month - 3 } else { month + 9 };
+        let day_of_year = (153 * month_of_year + 2) / 5 + day as i32 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+        Value::Date(era * 146_097 + day_of_era - 719_468)
+    }
+}
+
+impl std::convert::From<std::time::SystemTime> for Value {
+    fn from(t: std::time::SystemTime) -> Self {
+        let since_epoch = t
+            .duration_since(std::time::UNIX_EPOCH)
+            .expect("times before the epoch can't be represented as instants");
+
+        Value::Instant(since_epoch.as_millis() as u64)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl std::convert::From<chrono::NaiveDate> for Value {
+    fn from(date: chrono::NaiveDate) -> Self {
+        use chrono::Datelike;
+        Value::date(date.year(), date.month(), date.day())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> std::convert::From<chrono::DateTime<Tz>> for Value {
+    fn from(t: chrono::DateTime<Tz>) -> Self {
+        Value::Instant(t.timestamp_millis() as u64)
+    }
+}
+
+/// The possible types of values, as declared in attribute schemas.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum ValueType {
+    /// An attribute identifier
+    Aid,
+    /// A string
+    String,
+    /// A boolean
+    Bool,
+    /// A 64 bit signed integer
+    Number,
+    /// A 32 bit rational
+    Rational32,
+    /// An entity identifier
+    Eid,
+    /// Milliseconds since midnight, January 1, 1970 UTC
+    Instant,
+    /// A 16 byte unique identifier.
+    Uuid,
+    /// Days since January 1, 1970 UTC
+    Date,
+    /// An opaque binary payload
+    Bytes,
+    /// An exact, arbitrary-scale decimal number
+    Decimal,
+    /// A fixed-precision real number.
+    #[cfg(feature = "real")]
+    Real,
+}
+
+impl Value {
+    /// Returns the type of this value, if it has one. Tempids are
+    /// untyped, as they are resolved to entity ids at transact time.
+    pub fn value_type(&self) -> Option<ValueType> {
+        match self {
+            Value::Aid(_) => Some(ValueType::Aid),
+            Value::String(_) => Some(ValueType::String),
+            Value::Bool(_) => Some(ValueType::Bool),
+            Value::Number(_) => Some(ValueType::Number),
+            Value::Rational32(_) => Some(ValueType::Rational32),
+            Value::Eid(_) => Some(ValueType::Eid),
+            Value::Instant(_) => Some(ValueType::Instant),
+            Value::Uuid(_) => Some(ValueType::Uuid),
+            Value::TempId(_) => None,
+            Value::Date(_) => Some(ValueType::Date),
+            Value::Bytes(_) => Some(ValueType::Bytes),
+            Value::Decimal(_) => Some(ValueType::Decimal),
+            #[cfg(feature = "real")]
+            Value::Real(_) => Some(ValueType::Real),
+        }
+    }
 }
 
 impl std::convert::From<&str> for Value {
@@ -124,6 +250,18 @@ impl std::convert::From<&str> for Value {
     }
 }
 
+impl std::convert::From<i64> for Value {
+    fn from(v: i64) -> Self {
+        Value::Number(v)
+    }
+}
+
+impl std::convert::From<bool> for Value {
+    fn from(v: bool) -> Self {
+        Value::Bool(v)
+    }
+}
+
 #[cfg(feature = "real")]
 impl std::convert::From<f64> for Value {
     fn from(v: f64) -> Self {
@@ -143,7 +281,26 @@ impl std::convert::From<Value> for serde_json::Value {
             Value::String(v) => serde_json::Value::String(v),
             Value::Bool(v) => serde_json::Value::Bool(v),
             Value::Number(v) => serde_json::Value::Number(serde_json::Number::from(v)),
-            _ => unimplemented!(),
+            Value::Rational32(v) => serde_json::Value::String(v.to_string()),
+            Value::Instant(v) => serde_json::Value::Number(serde_json::Number::from(v)),
+            Value::Uuid(v) => serde_json::Value::String(v.to_string()),
+            Value::TempId(v) => serde_json::Value::String(v),
+            Value::Date(v) => serde_json::Value::Number(serde_json::Number::from(v)),
+            Value::Bytes(v) => serde_json::Value::String(bytes::to_hex(&v)),
+            Value::Decimal(v) => serde_json::Value::String(v.to_string()),
+            #[cfg(feature = "real")]
+            Value::Real(v) => serde_json::Value::String(v.to_string()),
+        }
+    }
+}
+
+impl std::convert::From<Time> for Value {
+    fn from(t: Time) -> Self {
+        match t {
+            Time::TxId(tx) => Value::Number(tx as i64),
+            Time::Real(duration) => Value::Instant(duration.as_millis() as u64),
+            // Bitemporal times are reported by their event time.
+            Time::Bi(duration, _) => Value::Instant(duration.as_millis() as u64),
         }
     }
 }
@@ -167,6 +324,14 @@ pub struct Error {
     /// Free-frorm description.
     #[serde(rename = "df.error/message")]
     pub message: String,
+    /// Position of the offending clause within a plan, as the index
+    /// of each sub-plan leading to it, if the error is due to one.
+    #[serde(
+        rename = "df.error/clause",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub clause: Option<Vec<usize>>,
 }
 
 impl Error {
@@ -175,6 +340,7 @@ impl Error {
         Error {
             category: "df.error.category/incorrect".to_string(),
             message: error.to_string(),
+            clause: None,
         }
     }
 
@@ -183,6 +349,7 @@ impl Error {
         Error {
             category: "df.error.category/not-found".to_string(),
             message: error.to_string(),
+            clause: None,
         }
     }
 
@@ -191,6 +358,7 @@ impl Error {
         Error {
             category: "df.error.category/conflict".to_string(),
             message: error.to_string(),
+            clause: None,
         }
     }
 
@@ -199,6 +367,16 @@ impl Error {
         Error {
             category: "df.error.category/fault".to_string(),
             message: error.to_string(),
+            clause: None,
+        }
+    }
+
+    /// Fix client credentials.
+    pub fn forbidden<E: std::string::ToString>(error: E) -> Error {
+        Error {
+            category: "df.error.category/forbidden".to_string(),
+            message: error.to_string(),
+            clause: None,
         }
     }
 
@@ -207,8 +385,34 @@ impl Error {
         Error {
             category: "df.error.category/unsupported".to_string(),
             message: error.to_string(),
+            clause: None,
         }
     }
+
+    /// Back off and retry later.
+    pub fn busy<E: std::string::ToString>(error: E) -> Error {
+        Error {
+            category: "df.error.category/busy".to_string(),
+            message: error.to_string(),
+            clause: None,
+        }
+    }
+
+    /// Resubmit, the server gave up on it.
+    pub fn interrupted<E: std::string::ToString>(error: E) -> Error {
+        Error {
+            category: "df.error.category/interrupted".to_string(),
+            message: error.to_string(),
+            clause: None,
+        }
+    }
+
+    /// Attributes the error to the clause at the specified position
+    /// within a plan.
+    pub fn with_clause(mut self, clause: Vec<usize>) -> Error {
+        self.clause = Some(clause);
+        self
+    }
 }
 
 /// Transaction data.
@@ -227,6 +431,21 @@ impl<A: AsAid + ExchangeData> Datom<A> {
         Self(Value::Eid(e), a.into(), v, Some(t), 1)
     }
 
+    /// Creates a datom representing the addition of a single fact
+    /// that happened at the specified event time, as of the specified
+    /// transaction.
+    pub fn add_bitemporal<X: Into<A>>(e: Eid, a: X, v: Value, event: Duration, tx: u64) -> Self {
+        Self(Value::Eid(e), a.into(), v, Some(Time::Bi(event, tx)), 1)
+    }
+
+    /// Creates a datom representing the addition of a single fact
+    /// about a new entity, identified by a temporary id. All datoms
+    /// in a transaction sharing a tempid will be asserted about the
+    /// same, freshly allocated entity.
+    pub fn add_with_tempid<X: Into<A>>(tempid: &str, a: X, v: Value) -> Self {
+        Self(Value::TempId(tempid.to_string()), a.into(), v, None, 1)
+    }
+
     /// Creates a datom representing the retraction of a single fact.
     pub fn retract<X: Into<A>>(e: Eid, a: X, v: Value) -> Self {
         Self(Value::Eid(e), a.into(), v, None, -1)
@@ -251,6 +470,15 @@ pub enum Output {
     /// A batch of (tuple, time, diff) triples as returned by Datalog
     /// queries.
     QueryDiff(String, Vec<ResultDiff<Time>>),
+    /// The consolidated changes to the results of a query at a single
+    /// time, as sent once that time is complete.
+    QueryBatch(String, Time, Vec<(Vec<Value>, isize)>),
+    /// The latest time a client has received all results of a query
+    /// for. Reconnecting clients can resume from there.
+    ResumeToken(String, Time),
+    /// The consolidated results of a query at the time a specific
+    /// client subscribed to it. Subsequent `QueryDiff`s apply on top.
+    Snapshot(Client, String, Vec<ResultDiff<Time>>),
     /// A JSON object, e.g. as returned by GraphQL queries.
     #[cfg(feature = "serde_json")]
     Json(String, serde_json::Value, Time, isize),
@@ -261,6 +489,23 @@ pub enum Output {
     Error(Client, Error, server::TxId),
 }
 
+impl Output {
+    /// Splits diffs into messages holding at most the specified
+    /// number of results each. Snapshots and consolidated batches
+    /// must arrive whole, and are thus never split.
+    pub fn split(self, batch_size: usize) -> Vec<Output> {
+        match self {
+            Output::QueryDiff(name, results) if results.len() > batch_size && batch_size > 0 => {
+                results
+                    .chunks(batch_size)
+                    .map(|chunk| Output::QueryDiff(name.clone(), chunk.to_vec()))
+                    .collect()
+            }
+            other => vec![other],
+        }
+    }
+}
+
 /// A trace of values indexed by self.
 pub type TraceKeyHandle<K, T, R> = TraceAgent<OrdKeySpine<K, T, R>>;
 
@@ -341,9 +586,13 @@ impl ShutdownHandle {
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub enum InputSemantics {
     /// No special semantics enforced. Source is responsible for
-    /// everything.
+    /// everything. Inputs aren't deduplicated, which saves an
+    /// arrangement for attributes that are unique already, and keeps
+    /// multiplicities intact for those where they are meaningful.
     Raw,
-    /// Only the last input for each eid is kept.
+    /// Only the last input for each eid is kept. Also accepted as
+    /// `CardinalityOne`.
+    #[serde(alias = "CardinalityOne")]
     LastWriteWins,
     // @TODO
     // /// Only the first input for each eid is kept, all subsequent ones
@@ -360,7 +609,9 @@ pub enum InputSemantics {
 /// the other way around. More powerful query capabilities may rely on
 /// both directions being available, whereas simple queries, such as
 /// star-joins and pull queries, might get by with just a forward
-/// index.
+/// index. Queries requiring reverse indices of attributes that don't
+/// maintain them fail, unless the server is configured to build them
+/// on demand.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub enum IndexDirection {
     /// Forward index only.
@@ -369,6 +620,19 @@ pub enum IndexDirection {
     Both,
 }
 
+/// Composite indices span several attributes, serving access
+/// patterns that would otherwise require joining per-attribute
+/// indices.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum CompositeIndex {
+    /// Maps entities to all of their (a, v) pairs, s.t. pull
+    /// expressions can retrieve many attributes at once.
+    Entity,
+    /// Maps entities to the (a, e) pairs of all entities referring
+    /// to them, s.t. references can be navigated in reverse.
+    Ref,
+}
+
 /// Attributes might only appear in certain classes of queries. If
 /// that is the case, indexing overhead can be reduced.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
@@ -385,6 +649,41 @@ pub enum QuerySupport {
     AdaptiveWCO = 2,
 }
 
+/// Attributes can either allow many values per entity, or only a
+/// single one. Asserting a new value for a cardinality-one attribute
+/// retracts the previous one.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum Cardinality {
+    /// Any number of values per entity.
+    Many,
+    /// At most a single value per entity.
+    One,
+}
+
+impl Default for Cardinality {
+    fn default() -> Self {
+        Cardinality::Many
+    }
+}
+
+/// Attributes can require their values to be unique across
+/// entities.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum Uniqueness {
+    /// Any number of entities may share a value.
+    None,
+    /// Values identify entities. Transacting a datom about a tempid
+    /// with a value already owned by some entity resolves the tempid
+    /// to that entity (upsert).
+    Identity,
+}
+
+impl Default for Uniqueness {
+    fn default() -> Self {
+        Uniqueness::None
+    }
+}
+
 /// Per-attribute semantics.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub struct AttributeConfig {
@@ -392,12 +691,38 @@ pub struct AttributeConfig {
     /// the most recent value per eid, or compare-and-swap.
     pub input_semantics: InputSemantics,
     /// How close indexed traces should follow the computation
-    /// frontier.
+    /// frontier. Traces retain distinguishable times within this
+    /// window behind the frontier, and never compact at all if no
+    /// slack is given, thus keeping the attribute's full history.
     pub trace_slack: Option<Time>,
     /// Index directions to maintain for this attribute.
     pub index_direction: IndexDirection,
     /// Query capabilities supported by this attribute.
     pub query_support: QuerySupport,
+    /// Number of values allowed per entity.
+    #[serde(default)]
+    pub cardinality: Cardinality,
+    /// Whether values must be unique across entities.
+    #[serde(default)]
+    pub unique: Uniqueness,
+    /// The type all values of this attribute must have. Attributes
+    /// without a declared type accept any value.
+    pub value_type: Option<ValueType>,
+    /// Assertions are retracted automatically once this much time
+    /// has passed since they became valid. Expiry happens outside of
+    /// transactions, so windowed attributes shouldn't also carry
+    /// cardinality or uniqueness constraints.
+    pub window: Option<Time>,
+    /// Whether inputs are spread across all workers by entity, rather
+    /// than introduced by the worker that received them. This allows
+    /// a heavily written attribute to be ingested and indexed in
+    /// parallel.
+    #[serde(default)]
+    pub partitioned: bool,
+    /// Whether string values are additionally indexed by the tokens
+    /// they contain, s.t. they can be searched via Fulltext plans.
+    #[serde(default)]
+    pub fulltext: bool,
 }
 
 impl Default for AttributeConfig {
@@ -407,6 +732,12 @@ impl Default for AttributeConfig {
             trace_slack: None,
             index_direction: IndexDirection::Forward,
             query_support: QuerySupport::Basic,
+            cardinality: Cardinality::Many,
+            unique: Uniqueness::None,
+            value_type: None,
+            window: None,
+            partitioned: false,
+            fulltext: false,
         }
     }
 }
@@ -452,6 +783,18 @@ impl AttributeConfig {
 /// A variable used in a query.
 type Var = u32;
 
+/// How a relation treats the multiplicities of its tuples.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Semantics {
+    /// Each tuple is present at most once. Rules, unions, and the
+    /// left-hand sides of antijoins are thresholded.
+    Set,
+    /// Multiplicities are kept, e.g. for counting observations.
+    /// Nothing is thresholded within the relation, but its inputs
+    /// still are, unless their attributes use `InputSemantics::Raw`.
+    Bag,
+}
+
 /// A named relation.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub struct Rule<A: AsAid> {
@@ -459,6 +802,11 @@ pub struct Rule<A: AsAid> {
     pub name: A,
     /// The plan describing contents of the relation.
     pub plan: Plan<A>,
+    /// How the relation treats multiplicities. By default, rules are
+    /// thresholded only with the `set-semantics` feature enabled,
+    /// unions and antijoins always are.
+    #[serde(default)]
+    pub semantics: Option<Semantics>,
 }
 
 impl<A: AsAid> Rule<A> {
@@ -467,6 +815,15 @@ impl<A: AsAid> Rule<A> {
         Rule {
             name: name.into(),
             plan,
+            semantics: None,
+        }
+    }
+
+    /// Returns the same rule, treating multiplicities as specified.
+    pub fn with_semantics(self, semantics: Semantics) -> Self {
+        Rule {
+            semantics: Some(semantics),
+            ..self
         }
     }
 }
@@ -899,7 +1256,16 @@ where
 
     while let Some(next) = queue.pop_front() {
         let dependencies = next.plan.dependencies();
-        for dep_name in dependencies.names.into_iter() {
+
+        // Materialized rules are imported as a whole, so we don't
+        // need anything they depend on.
+        let names = if domain.is_materialized(&next.name) {
+            HashSet::new()
+        } else {
+            dependencies.names
+        };
+
+        for dep_name in names.into_iter() {
             if !seen.contains(&dep_name) {
                 match domain.rule(&dep_name) {
                     None => {
@@ -913,15 +1279,13 @@ where
             }
         }
 
-        // Ensure all required attributes exist.
-        for aid in dependencies.attributes.iter() {
-            if !domain.has_attribute(aid) {
-                return Err(Error::not_found(format!(
-                    "Rule {:?} depends on unknown attribute",
-                    aid
-                )));
-            }
-        }
+        // Ensure all required attributes exist, all variables are
+        // bound where they are used, and all patterns, predicates,
+        // and functions are applied to values of a permissible type.
+        validate(&next.plan, domain).map_err(|mut error| {
+            error.message = format!("Rule {}: {}", next.name, error.message);
+            error
+        })?;
 
         rules.push(next);
     }
@@ -929,6 +1293,55 @@ where
     Ok(rules)
 }
 
+/// Ensures that the given set of rules is stratifiable, i.e. that no
+/// rule refers to itself (directly or via other rules) from within a
+/// negation. Negation is only well-defined against relations that
+/// are fully computed before the negating rule is evaluated.
+pub fn validate_stratification<A: AsAid>(rules: &HashMap<A, Rule<A>>) -> Result<(), Error> {
+    for (name, rule) in rules.iter() {
+        let mut seen = HashSet::new();
+        let mut queue: VecDeque<A> = rule.plan.negated_names().into_iter().collect();
+
+        while let Some(next) = queue.pop_front() {
+            if next == *name {
+                return Err(Error::incorrect(format!(
+                    "Rule {} negates a relation in its own recursive stratum.",
+                    name
+                )));
+            }
+
+            if seen.insert(next.clone()) {
+                if let Some(dependency) = rules.get(&next) {
+                    queue.extend(dependency.plan.dependencies().names.into_iter());
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Completes the tuples of a rule as required by its semantics, or
+/// by the `set-semantics` feature if unspecified.
+fn threshold<G>(
+    tuples: Collection<G, Vec<Value>, isize>,
+    semantics: Option<Semantics>,
+) -> Collection<G, Vec<Value>, isize>
+where
+    G: Scope,
+    G::Timestamp: Lattice + Ord,
+{
+    #[cfg(feature = "set-semantics")]
+    let default = Semantics::Set;
+    #[cfg(not(feature = "set-semantics"))]
+    let default = Semantics::Bag;
+
+    match semantics.unwrap_or(default) {
+        Semantics::Set => tuples.distinct(),
+        Semantics::Bag => tuples.consolidate(),
+    }
+}
+
 /// Takes a query plan and turns it into a differential dataflow.
 pub fn implement<A, S>(
     scope: &mut S,
@@ -988,12 +1401,36 @@ where
         // Step 3: Define the executions for each rule.
         let mut executions = Vec::with_capacity(rules.len());
         let mut shutdown_handle = ShutdownHandle::empty();
+        // Shared rules materialized by this dataflow must outlive it.
+        let mut materializing = HashMap::new();
         for rule in rules.iter() {
-            info!("planning {:?}", rule.name);
-            let (relation, shutdown) = rule.plan.implement(nested, domain, &local_arrangements);
+            if let Some(trace) = domain.intermediates.get_mut(&rule.name) {
+                info!("importing {:?}", rule.name);
+                let (arranged, shutdown_button) = trace
+                    .import_frontier(&nested.parent, &format!("Intermediate({:?})", rule.name));
+
+                let relation = CollectionRelation {
+                    variables: rule.plan.variables(),
+                    tuples: arranged
+                        .enter(nested)
+                        .as_collection(|tuple, _| tuple.clone()),
+                };
 
-            executions.push(relation);
-            shutdown_handle.merge_with(shutdown);
+                executions.push(Implemented::Collection(relation));
+                shutdown_handle.add_button(shutdown_button);
+            } else {
+                info!("planning {:?}", rule.name);
+                let plan = rule.plan.with_semantics(rule.semantics);
+                let (relation, shutdown) = plan.implement(nested, domain, &local_arrangements);
+
+                executions.push(relation);
+
+                if is_shared(&rule.name) {
+                    materializing.insert(rule.name.clone(), shutdown);
+                } else {
+                    shutdown_handle.merge_with(shutdown);
+                }
+            }
         }
 
         // Step 4: Complete named relations in a specific order (sorted by name).
@@ -1007,13 +1444,24 @@ where
                 }
                 Some(variable) => {
                     let (tuples, shutdown) = execution.tuples(nested, domain);
-                    shutdown_handle.merge_with(shutdown);
+                    let tuples = threshold(tuples, rule.semantics);
+
+                    variable.set(&tuples);
 
-                    #[cfg(feature = "set-semantics")]
-                    variable.set(&tuples.distinct());
+                    match materializing.remove(&rule.name) {
+                        None => shutdown_handle.merge_with(shutdown),
+                        Some(mut materialized_shutdown) => {
+                            materialized_shutdown.merge_with(shutdown);
 
-                    #[cfg(not(feature = "set-semantics"))]
-                    variable.set(&tuples.consolidate());
+                            let trace = tuples
+                                .leave()
+                                .map(|tuple| (tuple, ()))
+                                .arrange_named(&format!("->Intermediate({})", &rule.name))
+                                .trace;
+
+                            domain.materialize(rule.name.clone(), trace, materialized_shutdown);
+                        }
+                    }
                 }
             }
         }
@@ -1092,6 +1540,11 @@ where
         for rule in rules.iter() {
             info!("neu_planning {:?}", rule.name);
 
+            validate_bindings(&rule.plan).map_err(|mut error| {
+                error.message = format!("Rule {}: {}", rule.name, error.message);
+                error
+            })?;
+
             let plan = q(rule.plan.variables(), rule.plan.into_bindings());
 
             let (relation, shutdown) = plan.implement(nested, domain, &local_arrangements);
@@ -1113,11 +1566,7 @@ where
                     let (tuples, shutdown) = execution.tuples(nested, domain);
                     shutdown_handle.merge_with(shutdown);
 
-                    #[cfg(feature = "set-semantics")]
-                    variable.set(&tuples.distinct());
-
-                    #[cfg(not(feature = "set-semantics"))]
-                    variable.set(&tuples.consolidate());
+                    variable.set(&threshold(tuples, rule.semantics));
                 }
             }
         }
@@ -1,5 +1,8 @@
 //! Loggers and logging events for declarative dataflow.
 
+use std::fmt;
+use std::time::Duration;
+
 /// Logger for differential dataflow events.
 pub type Logger = ::timely::logging::Logger<DeclarativeEvent>;
 
@@ -22,3 +25,130 @@ impl From<JoinTuplesEvent> for DeclarativeEvent {
         DeclarativeEvent::JoinTuples(e)
     }
 }
+
+/// Target under which lifecycle events are logged, s.t. they can be
+/// enabled separately, e.g. via
+/// `RUST_LOG=declarative_dataflow::lifecycle=info`.
+pub const LIFECYCLE: &str = "declarative_dataflow::lifecycle";
+
+/// Changes to the state of a server, as observed by a single worker.
+#[derive(Debug, Clone, Serialize, Ord, PartialOrd, Eq, PartialEq)]
+pub enum LifecycleEvent {
+    /// A transactable attribute was created.
+    AttributeCreated {
+        /// Attribute name.
+        name: String,
+    },
+    /// A source was attached, feeding the listed attributes.
+    SourceAttached {
+        /// Names of the sourced attributes.
+        attributes: Vec<String>,
+    },
+    /// A rule was registered.
+    QueryRegistered {
+        /// Rule name.
+        name: String,
+    },
+    /// A dataflow was created for a rule of interest.
+    QueryImplemented {
+        /// Rule name.
+        name: String,
+    },
+    /// The dataflow of a rule of interest was replaced by one using a
+    /// different join order.
+    QueryReplanned {
+        /// Rule name.
+        name: String,
+    },
+    /// A transaction was applied to the domain inputs.
+    TxApplied {
+        /// Number of datoms applied.
+        datoms: usize,
+        /// Domain epoch at which they were applied.
+        epoch: String,
+    },
+    /// The domain epoch advanced.
+    EpochAdvanced {
+        /// New domain epoch.
+        epoch: String,
+    },
+    /// The domain frontier advanced.
+    FrontierAdvanced {
+        /// New domain frontier.
+        frontier: String,
+    },
+}
+
+impl LifecycleEvent {
+    /// Returns the level at which this event is logged. Events that
+    /// happen continuously, as opposed to in response to a request
+    /// changing what the server computes, are only logged at debug
+    /// level.
+    pub fn level(&self) -> log::Level {
+        match self {
+            LifecycleEvent::TxApplied { .. }
+            | LifecycleEvent::EpochAdvanced { .. }
+            | LifecycleEvent::FrontierAdvanced { .. } => log::Level::Debug,
+            _ => log::Level::Info,
+        }
+    }
+}
+
+/// Quotes a value for use in logfmt output, if necessary.
+fn quote(value: &str) -> String {
+    if !value.is_empty() && !value.contains(|c: char| c.is_whitespace() || c == '"' || c == '=') {
+        value.to_string()
+    } else {
+        format!("{:?}", value)
+    }
+}
+
+impl fmt::Display for LifecycleEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LifecycleEvent::AttributeCreated { name } => {
+                write!(f, "event=attribute_created name={}", quote(name))
+            }
+            LifecycleEvent::SourceAttached { attributes } => write!(
+                f,
+                "event=source_attached attributes={}",
+                quote(&attributes.join(","))
+            ),
+            LifecycleEvent::QueryRegistered { name } => {
+                write!(f, "event=query_registered name={}", quote(name))
+            }
+            LifecycleEvent::QueryImplemented { name } => {
+                write!(f, "event=query_implemented name={}", quote(name))
+            }
+            LifecycleEvent::QueryReplanned { name } => {
+                write!(f, "event=query_replanned name={}", quote(name))
+            }
+            LifecycleEvent::TxApplied { datoms, epoch } => write!(
+                f,
+                "event=tx_applied datoms={} epoch={}",
+                datoms,
+                quote(epoch)
+            ),
+            LifecycleEvent::EpochAdvanced { epoch } => {
+                write!(f, "event=epoch_advanced epoch={}", quote(epoch))
+            }
+            LifecycleEvent::FrontierAdvanced { frontier } => {
+                write!(f, "event=frontier_advanced frontier={}", quote(frontier))
+            }
+        }
+    }
+}
+
+/// Logs a lifecycle event in logfmt, tagged with the index of the
+/// observing worker and the time elapsed since the computation
+/// started.
+pub fn log_lifecycle(worker_index: usize, elapsed: Duration, event: LifecycleEvent) {
+    log!(
+        target: LIFECYCLE,
+        event.level(),
+        "worker={} elapsed_ms={} {}",
+        worker_index,
+        elapsed.as_millis(),
+        event
+    );
+}
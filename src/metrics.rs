@@ -0,0 +1,236 @@
+//! Process-wide metrics about server internals, exposed in the
+//! Prometheus text format.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::Time;
+
+/// Number of transactions applied.
+pub const TRANSACTIONS: &str = "declarative_transactions_total";
+/// Number of datoms transacted, per attribute.
+pub const DATOMS: &str = "declarative_datoms_total";
+/// Net number of datoms (assertions minus retractions) transacted,
+/// per attribute.
+pub const ATTRIBUTE_DATOMS: &str = "declarative_attribute_datoms";
+/// Number of updates held by attribute arrangements, per attribute
+/// and index.
+pub const ARRANGEMENT_SIZE: &str = "declarative_arrangement_size";
+/// The domain input epoch.
+pub const DOMAIN_EPOCH: &str = "declarative_domain_epoch";
+/// The domain frontier, i.e. the progress of all domain inputs.
+pub const DOMAIN_FRONTIER: &str = "declarative_domain_frontier";
+/// How far the domain frontier lags behind the domain epoch.
+pub const FRONTIER_LAG: &str = "declarative_frontier_lag";
+/// Number of queries registered.
+pub const QUERIES: &str = "declarative_queries_registered_total";
+/// Number of result diffs produced, per query.
+pub const QUERY_OUTPUTS: &str = "declarative_query_outputs_total";
+/// Number of outputs waiting to be sent, per client.
+pub const OUTPUT_QUEUE_DEPTH: &str = "declarative_output_queue_depth";
+/// Number of times a client's output queue overflowed, per policy.
+pub const OUTPUT_OVERFLOWS: &str = "declarative_output_overflows_total";
+
+/// Returns help text and type of a known metric.
+fn describe(name: &str) -> (&'static str, &'static str) {
+    match name {
+        TRANSACTIONS => ("Number of transactions applied.", "counter"),
+        DATOMS => ("Number of datoms transacted.", "counter"),
+        ATTRIBUTE_DATOMS => ("Net number of datoms transacted.", "gauge"),
+        ARRANGEMENT_SIZE => ("Number of updates held by an arrangement.", "gauge"),
+        DOMAIN_EPOCH => ("The domain input epoch.", "gauge"),
+        DOMAIN_FRONTIER => ("The domain frontier.", "gauge"),
+        FRONTIER_LAG => ("Lag of the domain frontier behind the epoch.", "gauge"),
+        QUERIES => ("Number of queries registered.", "counter"),
+        QUERY_OUTPUTS => ("Number of result diffs produced.", "counter"),
+        OUTPUT_QUEUE_DEPTH => ("Number of outputs waiting to be sent.", "gauge"),
+        OUTPUT_OVERFLOWS => ("Number of output queue overflows.", "counter"),
+        _ => ("", "untyped"),
+    }
+}
+
+/// Converts a time into a sample value. Transaction times are
+/// reported as is, real times in seconds.
+pub fn time_to_sample(time: Time) -> f64 {
+    let seconds = |duration: std::time::Duration| {
+        duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9
+    };
+
+    match time {
+        Time::TxId(tx) => tx as f64,
+        Time::Real(duration) => seconds(duration),
+        Time::Bi(duration, _) => seconds(duration),
+    }
+}
+
+type Labels = Vec<(&'static str, String)>;
+
+/// Samples of all metrics in a process, shared between workers.
+#[derive(Default)]
+pub struct Metrics {
+    samples: Mutex<BTreeMap<&'static str, BTreeMap<Labels, f64>>>,
+}
+
+impl Metrics {
+    /// Creates an empty set of metrics.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Increments the specified sample.
+    pub fn increment(&self, name: &'static str, labels: Labels, by: f64) {
+        let mut samples = self.samples.lock().expect("metrics poisoned");
+        *samples
+            .entry(name)
+            .or_insert_with(BTreeMap::new)
+            .entry(labels)
+            .or_insert(0.0) += by;
+    }
+
+    /// Replaces the specified sample.
+    pub fn set(&self, name: &'static str, labels: Labels, value: f64) {
+        let mut samples = self.samples.lock().expect("metrics poisoned");
+        samples
+            .entry(name)
+            .or_insert_with(BTreeMap::new)
+            .insert(labels, value);
+    }
+
+    /// Removes the specified sample, e.g. once the entity it
+    /// describes has gone away.
+    pub fn remove(&self, name: &'static str, labels: Labels) {
+        let mut samples = self.samples.lock().expect("metrics poisoned");
+        if let Some(family) = samples.get_mut(name) {
+            family.remove(&labels);
+        }
+    }
+
+    /// Renders all samples in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let samples = self.samples.lock().expect("metrics poisoned");
+
+        let escape = |v: &str| {
+            v.replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+        };
+
+        let mut out = String::new();
+        for (name, family) in samples.iter() {
+            let (help, kind) = describe(name);
+            out.push_str(&format!(
+                "# HELP {} {}\n# TYPE {} {}\n",
+                name, help, name, kind
+            ));
+
+            for (labels, value) in family.iter() {
+                let labels: Vec<String> = labels
+                    .iter()
+                    .map(|(k, v)| format!("{}=\"{}\"", k, escape(v)))
+                    .collect();
+
+                if labels.is_empty() {
+                    out.push_str(&format!("{} {}\n", name, value));
+                } else {
+                    out.push_str(&format!("{}{{{}}} {}\n", name, labels.join(","), value));
+                }
+            }
+        }
+
+        out
+    }
+}
+
+/// A handle to process-wide metrics, labelling all samples with the
+/// index of the worker recording them.
+#[derive(Clone)]
+pub struct Recorder {
+    metrics: Arc<Metrics>,
+    worker: String,
+}
+
+impl Recorder {
+    /// Creates a recorder for the specified worker.
+    pub fn new(metrics: Arc<Metrics>, worker_index: usize) -> Self {
+        Recorder {
+            metrics,
+            worker: worker_index.to_string(),
+        }
+    }
+
+    fn labelled(&self, mut labels: Labels) -> Labels {
+        labels.insert(0, ("worker", self.worker.clone()));
+        labels
+    }
+
+    /// Increments the specified sample of this worker.
+    pub fn increment(&self, name: &'static str, labels: Labels, by: f64) {
+        self.metrics.increment(name, self.labelled(labels), by);
+    }
+
+    /// Replaces the specified sample of this worker.
+    pub fn set(&self, name: &'static str, labels: Labels, value: f64) {
+        self.metrics.set(name, self.labelled(labels), value);
+    }
+
+    /// Removes the specified sample of this worker.
+    pub fn remove(&self, name: &'static str, labels: Labels) {
+        self.metrics.remove(name, self.labelled(labels));
+    }
+}
+
+/// Answers a single HTTP request, serving metrics on GET /metrics.
+fn respond(metrics: &Metrics, stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // Skip headers, we don't care about any of them.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let (status, body) = if request_line.starts_with("GET /metrics ") {
+        ("200 OK", metrics.render())
+    } else {
+        ("404 Not Found", String::new())
+    };
+
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\n\
+         Content-Type: text/plain; version=0.0.4\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+
+    stream.flush()
+}
+
+/// Serves the specified metrics via HTTP at /metrics, from a thread
+/// of its own.
+pub fn serve(metrics: Arc<Metrics>, addr: SocketAddr) -> std::io::Result<thread::JoinHandle<()>> {
+    let listener = TcpListener::bind(addr)?;
+
+    Ok(thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Err(error) => warn!("failed to accept metrics connection: {}", error),
+                Ok(stream) => {
+                    if let Err(error) = respond(&metrics, stream) {
+                        warn!("failed to serve metrics: {}", error);
+                    }
+                }
+            }
+        }
+    }))
+}
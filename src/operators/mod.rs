@@ -2,5 +2,11 @@
 //! declarative-specific operators.
 
 mod last_write_wins;
+mod one_shot;
+mod reclock;
+mod snapshots;
 
 pub use last_write_wins::LastWriteWins;
+pub use one_shot::OneShot;
+pub use reclock::Reclock;
+pub use snapshots::{SnapshotRequests, Snapshots};
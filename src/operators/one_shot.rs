@@ -0,0 +1,114 @@
+//! Operator answering a query exactly once, with its consolidated
+//! results as of a specific time.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use timely::dataflow::channels::pact::ParallelizationContract;
+use timely::dataflow::operators::generic::{operator::Operator, OutputHandle};
+use timely::dataflow::{Scope, Stream};
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+
+use crate::timestamp::Time;
+use crate::{Client, Output, ResultDiff, Value};
+
+/// Provides the `one_shot` method.
+pub trait OneShot<S: Scope> {
+    /// Accumulates all results at times before `as_of`. Once those
+    /// are complete, they are sent to `send` as a single snapshot
+    /// addressed to `client`, and everything arriving afterwards is
+    /// discarded. The returned flag is set once the snapshot has been
+    /// sent, s.t. the dataflow can be shut down.
+    fn one_shot<P, F>(
+        &self,
+        pact: P,
+        name: &str,
+        client: Client,
+        as_of: S::Timestamp,
+        send: F,
+    ) -> Rc<Cell<bool>>
+    where
+        P: ParallelizationContract<S::Timestamp, ResultDiff<S::Timestamp>>,
+        F: FnOnce(Output) + 'static;
+}
+
+impl<S> OneShot<S> for Stream<S, ResultDiff<S::Timestamp>>
+where
+    S: Scope,
+    S::Timestamp: Lattice + Into<Time>,
+{
+    fn one_shot<P, F>(
+        &self,
+        pact: P,
+        name: &str,
+        client: Client,
+        as_of: S::Timestamp,
+        send: F,
+    ) -> Rc<Cell<bool>>
+    where
+        P: ParallelizationContract<S::Timestamp, ResultDiff<S::Timestamp>>,
+        F: FnOnce(Output) + 'static,
+    {
+        let name = name.to_string();
+        let done = Rc::new(Cell::new(false));
+        let done_inner = done.clone();
+
+        self.unary_frontier(pact, "OneShot", move |_cap, _info| {
+            let mut vector = Vec::new();
+            let mut state: HashMap<Vec<Value>, isize> = HashMap::new();
+            let mut snapshot_time = S::Timestamp::minimum();
+            let mut send = Some(send);
+
+            move |input, _output: &mut OutputHandle<_, ResultDiff<S::Timestamp>, _>| {
+                if send.is_none() {
+                    input.for_each(|_time, _data| {});
+                    return;
+                }
+
+                input.for_each(|_time, data| {
+                    data.swap(&mut vector);
+
+                    for (tuple, t, diff) in vector.drain(..) {
+                        if t.less_than(&as_of) {
+                            snapshot_time = snapshot_time.join(&t);
+
+                            let count = state.entry(tuple.clone()).or_insert(0);
+                            *count += diff;
+
+                            if *count == 0 {
+                                state.remove(&tuple);
+                            }
+                        }
+                    }
+                });
+
+                // Results are complete once no times before `as_of`
+                // can show up anymore.
+                if !input
+                    .frontier()
+                    .frontier()
+                    .iter()
+                    .any(|t| t.less_than(&as_of))
+                {
+                    let mut results = state
+                        .drain()
+                        .map(|(tuple, count)| (tuple, snapshot_time.clone().into(), count))
+                        .collect::<Vec<ResultDiff<Time>>>();
+
+                    results.sort();
+
+                    if let Some(send) = send.take() {
+                        send(Output::Snapshot(client, name.clone(), results));
+                    }
+
+                    done_inner.set(true);
+                }
+            }
+        });
+
+        done
+    }
+}
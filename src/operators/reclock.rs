@@ -0,0 +1,97 @@
+//! Operator translating a collection from the timestamps of one
+//! domain into those of another.
+
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::generic::builder_rc::OperatorBuilder;
+use timely::dataflow::operators::Capability;
+use timely::dataflow::{Scope, Stream};
+use timely::progress::frontier::{Antichain, AntichainRef};
+
+use differential_dataflow::difference::Monoid;
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::{AsCollection, Collection, Data};
+
+/// Provides the `reclock` method.
+pub trait Reclock<S: Scope, D, R> {
+    /// Moves all updates to the times dictated by `bindings`. Each
+    /// binding is a frontier of the original times, sent at the time
+    /// that all updates not beyond it should take on. Bindings must
+    /// be sent in order of their frontiers. Updates are emitted once
+    /// the collection itself has advanced past the first binding
+    /// they fall before.
+    ///
+    /// The output frontier is determined by the bindings alone, s.t.
+    /// the reclocked collection progresses in lockstep with whatever
+    /// drives them, not with its original times.
+    fn reclock(&self, bindings: &Stream<S, Vec<S::Timestamp>>) -> Collection<S, D, R>;
+}
+
+impl<S, D, R> Reclock<S, D, R> for Collection<S, D, R>
+where
+    S: Scope,
+    S::Timestamp: Lattice,
+    D: Data,
+    R: Monoid,
+{
+    fn reclock(&self, bindings: &Stream<S, Vec<S::Timestamp>>) -> Collection<S, D, R> {
+        let mut builder = OperatorBuilder::new("Reclock".to_string(), self.scope());
+
+        // Updates don't hold back the output, because their times are
+        // replaced entirely.
+        let mut updates_input =
+            builder.new_input_connection(&self.inner, Pipeline, vec![Antichain::new()]);
+        let mut bindings_input = builder.new_input(bindings, Pipeline);
+        let (mut output, reclocked) = builder.new_output();
+
+        builder.build(move |_capabilities| {
+            let mut vector = Vec::new();
+            let mut pending: Vec<(D, S::Timestamp, R)> = Vec::new();
+            let mut bindings: Vec<(Capability<S::Timestamp>, Vec<S::Timestamp>)> = Vec::new();
+
+            move |frontiers| {
+                updates_input.for_each(|_cap, data| {
+                    data.swap(&mut vector);
+                    pending.extend(vector.drain(..));
+                });
+
+                bindings_input.for_each(|cap, data| {
+                    let cap = cap.retain();
+                    for bound in data.iter() {
+                        bindings.push((cap.clone(), bound.clone()));
+                    }
+                });
+
+                let mut output = output.activate();
+                let mut applied = 0;
+
+                for (cap, bound) in bindings.iter() {
+                    let bound = AntichainRef::new(bound);
+
+                    // Updates before the bound might still arrive,
+                    // so it can't be applied yet.
+                    if !frontiers[0].frontier().iter().all(|t| bound.less_equal(t)) {
+                        break;
+                    }
+
+                    let (ready, later): (Vec<_>, Vec<_>) = pending
+                        .drain(..)
+                        .partition(|(_data, t, _diff)| !bound.less_equal(t));
+                    pending = later;
+
+                    let time = cap.time().clone();
+                    output.session(cap).give_iterator(
+                        ready
+                            .into_iter()
+                            .map(|(data, _t, diff)| (data, time.clone(), diff)),
+                    );
+
+                    applied += 1;
+                }
+
+                bindings.drain(..applied);
+            }
+        });
+
+        reclocked.as_collection()
+    }
+}
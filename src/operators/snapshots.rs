@@ -0,0 +1,302 @@
+//! Operator forwarding query results to clients, answering each new
+//! subscription with a consolidated snapshot before any further diffs.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use timely::dataflow::channels::pact::ParallelizationContract;
+use timely::dataflow::operators::generic::{operator::Operator, OutputHandle};
+use timely::dataflow::{Scope, Stream};
+use timely::progress::Timestamp;
+use timely::scheduling::Activator;
+
+use differential_dataflow::lattice::Lattice;
+
+use crate::timestamp::Time;
+use crate::{Client, Output, ResultDiff, Value};
+
+/// A handle through which clients can ask a running `snapshots`
+/// operator for the current state of its results.
+#[derive(Clone, Default)]
+pub struct SnapshotRequests {
+    queue: Rc<RefCell<Vec<Client>>>,
+    activator: Rc<RefCell<Option<Activator>>>,
+    retired: Rc<Cell<bool>>,
+    resuming: bool,
+    resume_tokens: bool,
+}
+
+impl SnapshotRequests {
+    /// Creates a new handle, not yet attached to any operator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new handle for clients that already hold the results
+    /// up to some time. All results are forwarded as diffs, without
+    /// an initial snapshot.
+    pub fn resuming() -> Self {
+        SnapshotRequests {
+            resuming: true,
+            ..Default::default()
+        }
+    }
+
+    /// Makes the attached operator follow each batch of diffs it
+    /// forwards by a resume token, carrying the latest time clients
+    /// have received all results for.
+    pub fn with_resume_tokens(mut self) -> Self {
+        self.resume_tokens = true;
+        self
+    }
+
+    /// Requests a snapshot on behalf of the specified client. The
+    /// snapshot will be sent before any diffs at later times.
+    pub fn request(&self, client: Client) {
+        self.queue.borrow_mut().push(client);
+
+        if let Some(ref activator) = *self.activator.borrow() {
+            activator.activate();
+        }
+    }
+
+    /// Stops the attached operator from sending anything further,
+    /// because another one has taken over its clients.
+    pub fn retire(&self) {
+        self.retired.set(true);
+    }
+}
+
+/// Provides the `snapshots` method.
+pub trait Snapshots<S: Scope> {
+    /// Forwards results to `send` once their times are complete. Each
+    /// client requesting a snapshot via `requests` first receives the
+    /// consolidated results accumulated so far, tagged as a
+    /// snapshot. Diffs forwarded afterwards apply on top of it.
+    ///
+    /// Diffs are only forwarded once the initial results are
+    /// complete, so early clients receive those as a snapshot as well.
+    fn snapshots<P, F>(
+        &self,
+        pact: P,
+        name: &str,
+        requests: &SnapshotRequests,
+        send: F,
+    ) -> Stream<S, ResultDiff<S::Timestamp>>
+    where
+        P: ParallelizationContract<S::Timestamp, ResultDiff<S::Timestamp>>,
+        F: FnMut(Output) + 'static;
+
+    /// Like `snapshots`, but forwards diffs as one consolidated batch
+    /// per completed time, rather than as they become ready.
+    fn batched_snapshots<P, F>(
+        &self,
+        pact: P,
+        name: &str,
+        requests: &SnapshotRequests,
+        send: F,
+    ) -> Stream<S, ResultDiff<S::Timestamp>>
+    where
+        P: ParallelizationContract<S::Timestamp, ResultDiff<S::Timestamp>>,
+        F: FnMut(Output) + 'static;
+}
+
+impl<S> Snapshots<S> for Stream<S, ResultDiff<S::Timestamp>>
+where
+    S: Scope,
+    S::Timestamp: Lattice + Into<Time>,
+{
+    fn snapshots<P, F>(
+        &self,
+        pact: P,
+        name: &str,
+        requests: &SnapshotRequests,
+        send: F,
+    ) -> Stream<S, ResultDiff<S::Timestamp>>
+    where
+        P: ParallelizationContract<S::Timestamp, ResultDiff<S::Timestamp>>,
+        F: FnMut(Output) + 'static,
+    {
+        forward(self, pact, name, requests, false, send)
+    }
+
+    fn batched_snapshots<P, F>(
+        &self,
+        pact: P,
+        name: &str,
+        requests: &SnapshotRequests,
+        send: F,
+    ) -> Stream<S, ResultDiff<S::Timestamp>>
+    where
+        P: ParallelizationContract<S::Timestamp, ResultDiff<S::Timestamp>>,
+        F: FnMut(Output) + 'static,
+    {
+        forward(self, pact, name, requests, true, send)
+    }
+}
+
+/// Implements both `snapshots` and `batched_snapshots`.
+fn forward<S, P, F>(
+    stream: &Stream<S, ResultDiff<S::Timestamp>>,
+    pact: P,
+    name: &str,
+    requests: &SnapshotRequests,
+    batched: bool,
+    mut send: F,
+) -> Stream<S, ResultDiff<S::Timestamp>>
+where
+    S: Scope,
+    S::Timestamp: Lattice + Into<Time>,
+    P: ParallelizationContract<S::Timestamp, ResultDiff<S::Timestamp>>,
+    F: FnMut(Output) + 'static,
+{
+    let name = name.to_string();
+    let requests = requests.clone();
+    let scope = stream.scope();
+
+    stream.unary_frontier(pact, "Snapshots", move |_cap, info| {
+        *requests.activator.borrow_mut() = Some(scope.activator_for(&info.address[..]));
+
+        let mut vector = Vec::new();
+        let mut pending: Vec<ResultDiff<S::Timestamp>> = Vec::new();
+        let mut state: HashMap<Vec<Value>, isize> = HashMap::new();
+        let mut snapshot_time = S::Timestamp::minimum();
+        let mut initialized = requests.resuming;
+
+        move |input, _output: &mut OutputHandle<_, ResultDiff<S::Timestamp>, _>| {
+            if requests.retired.get() {
+                input.for_each(|_time, _data| {});
+                pending.clear();
+                return;
+            }
+
+            input.for_each(|_time, data| {
+                data.swap(&mut vector);
+                pending.extend(vector.drain(..));
+            });
+
+            let frontier = input.frontier();
+
+            let mut ready = Vec::new();
+            let mut i = 0;
+            while i < pending.len() {
+                if frontier.less_equal(&pending[i].1) {
+                    i += 1;
+                } else {
+                    ready.push(pending.swap_remove(i));
+                }
+            }
+
+            ready.sort_by(|x, y| x.1.partial_cmp(&y.1).expect("incomparable times"));
+
+            // Snapshots must reflect exactly the diffs sent prior
+            // to them, so we serve them before forwarding anything
+            // newly completed.
+            if initialized {
+                serve(&name, &requests, &state, &snapshot_time, &mut send);
+            }
+
+            let forwarded = initialized && !ready.is_empty();
+
+            if initialized && batched {
+                // Ready diffs are sorted by time, s.t. each time
+                // forms a contiguous run.
+                let mut start = 0;
+                while start < ready.len() {
+                    let time = ready[start].1.clone();
+                    let end = start + ready[start..].iter().take_while(|x| x.1 == time).count();
+
+                    let diffs = consolidate(&ready[start..end]);
+                    if !diffs.is_empty() {
+                        send(Output::QueryBatch(name.clone(), time.into(), diffs));
+                    }
+
+                    start = end;
+                }
+            } else if initialized && !ready.is_empty() {
+                let diffs = ready
+                    .iter()
+                    .map(|(tuple, t, diff)| (tuple.clone(), t.clone().into(), *diff))
+                    .collect::<Vec<ResultDiff<Time>>>();
+
+                send(Output::QueryDiff(name.clone(), diffs));
+            }
+
+            for (tuple, t, diff) in ready.drain(..) {
+                snapshot_time = snapshot_time.join(&t);
+
+                let count = state.entry(tuple.clone()).or_insert(0);
+                *count += diff;
+
+                if *count == 0 {
+                    state.remove(&tuple);
+                }
+            }
+
+            if forwarded && requests.resume_tokens {
+                send(Output::ResumeToken(
+                    name.clone(),
+                    snapshot_time.clone().into(),
+                ));
+            }
+
+            if !initialized && !frontier.less_equal(&S::Timestamp::minimum()) {
+                initialized = true;
+                serve(&name, &requests, &state, &snapshot_time, &mut send);
+            }
+        }
+    })
+}
+
+/// Sends the accumulated state to each client in the request queue.
+fn serve<T, F>(
+    name: &str,
+    requests: &SnapshotRequests,
+    state: &HashMap<Vec<Value>, isize>,
+    snapshot_time: &T,
+    send: &mut F,
+) where
+    T: Clone + Into<Time>,
+    F: FnMut(Output),
+{
+    for client in requests.queue.borrow_mut().drain(..) {
+        let mut snapshot = state
+            .iter()
+            .map(|(tuple, count)| (tuple.clone(), snapshot_time.clone().into(), *count))
+            .collect::<Vec<ResultDiff<Time>>>();
+
+        snapshot.sort();
+
+        send(Output::Snapshot(client, name.to_string(), snapshot));
+    }
+}
+
+/// Sums up the diffs of each tuple, dropping those that cancel out.
+fn consolidate<T>(diffs: &[ResultDiff<T>]) -> Vec<(Vec<Value>, isize)> {
+    let mut consolidated: Vec<(Vec<Value>, isize)> = diffs
+        .iter()
+        .map(|(tuple, _t, diff)| (tuple.clone(), *diff))
+        .collect();
+
+    consolidated.sort();
+
+    let mut i = 0;
+    while i < consolidated.len() {
+        let mut j = i + 1;
+        while j < consolidated.len() && consolidated[j].0 == consolidated[i].0 {
+            consolidated[i].1 += consolidated[j].1;
+            j += 1;
+        }
+
+        consolidated.drain(i + 1..j);
+
+        if consolidated[i].1 == 0 {
+            consolidated.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+
+    consolidated
+}
@@ -10,8 +10,9 @@ use differential_dataflow::operators::Join as JoinMap;
 use differential_dataflow::operators::{Count, Reduce, Threshold};
 
 use crate::binding::{AsBinding, Binding};
+use crate::decimal::{average, weighted_sum};
 use crate::domain::Domain;
-use crate::plan::{Dependencies, Implementable};
+use crate::plan::{decimal_keys, nearest_rank, Dependencies, Implementable};
 use crate::timestamp::Rewind;
 use crate::{CollectionRelation, Implemented, Relation, ShutdownHandle, Value, Var, VariableMap};
 
@@ -26,8 +27,14 @@ pub enum AggregationFn {
     MAX,
     /// MEDIAN
     MEDIAN,
+    /// Quantile at the given fraction (e.g. 95/100 for p95), using
+    /// the nearest-rank method
+    QUANTILE(Rational32),
     /// Count
     COUNT,
+    /// Count of distinct values
+    #[allow(non_camel_case_types)]
+    COUNT_DISTINCT,
     /// Sum
     SUM,
     /// Average
@@ -172,6 +179,18 @@ impl<P: Implementable> Implementable for Aggregate<P> {
                         .map(move |(key, med)| (key, vec![med]));
                     collections.push(tuples);
                 }
+                AggregationFn::QUANTILE(q) => {
+                    let q = *q;
+                    let tuples = tuples
+                        .map(prepare_unary)
+                        .reduce(move |_key, vals, output| {
+                            let rank = nearest_rank(q, vals.len() as isize);
+                            let quantile = &vals[(rank - 1) as usize].0[0];
+                            output.push((quantile.clone(), 1));
+                        })
+                        .map(move |(key, quantile)| (key, vec![quantile]));
+                    collections.push(tuples);
+                }
                 AggregationFn::COUNT => {
                     let tuples = tuples
                         .map(prepare_unary)
@@ -179,29 +198,50 @@ impl<P: Implementable> Implementable for Aggregate<P> {
                         .map(move |(key, count)| (key, vec![Value::Number(count as i64)]));
                     collections.push(tuples);
                 }
-                AggregationFn::SUM => {
+                AggregationFn::COUNT_DISTINCT => {
                     let tuples = tuples
                         .map(prepare_unary)
-                        .distinct()
+                        .map(|(key, val)| (key, val[0].clone()))
+                        .reduce(|_key, input, output| output.push((input.len(), 1)))
+                        .map(move |(key, count)| (key, vec![Value::Number(count as i64)]));
+                    collections.push(tuples);
+                }
+                AggregationFn::SUM => {
+                    // Numbers are summed incrementally as differences.
+                    // Groups holding any decimals are summed exactly
+                    // instead, at the cost of a reduction.
+                    let prepared = tuples.map(prepare_unary).distinct();
+                    let decimal_keys = decimal_keys(&prepared);
+                    let numbers = prepared
+                        .antijoin(&decimal_keys)
                         .explode(|(key, val)| {
                             let v = match val[0] {
                                 Value::Number(num) => num,
-                                _ => panic!("SUM can only be applied on type Number."),
+                                _ => panic!("SUM can only be applied on type Number or Decimal."),
                             };
                             Some((key, v as isize))
                         })
                         .count()
                         .map(move |(key, count)| (key, vec![Value::Number(count as i64)]));
-                    collections.push(tuples);
+                    let decimals = prepared
+                        .semijoin(&decimal_keys)
+                        .reduce(|_key, vals, output| {
+                            let (sum, _count) =
+                                weighted_sum(vals.iter().map(|(val, diff)| (&val[0], *diff)));
+                            output.push((Value::Decimal(sum), 1));
+                        })
+                        .map(move |(key, sum)| (key, vec![sum]));
+                    collections.push(numbers.concat(&decimals));
                 }
                 AggregationFn::AVG => {
-                    let tuples = tuples
-                        .map(prepare_unary)
-                        .distinct()
+                    let prepared = tuples.map(prepare_unary).distinct();
+                    let decimal_keys = decimal_keys(&prepared);
+                    let numbers = prepared
+                        .antijoin(&decimal_keys)
                         .explode(move |(key, val)| {
                             let v = match val[0] {
                                 Value::Number(num) => num,
-                                _ => panic!("AVG can only be applied on type Number."),
+                                _ => panic!("AVG can only be applied on type Number or Decimal."),
                             };
                             Some((key, DiffPair::new(v as isize, 1)))
                         })
@@ -215,7 +255,15 @@ impl<P: Implementable> Implementable for Aggregate<P> {
                                 ))],
                             )
                         });
-                    collections.push(tuples);
+                    let decimals = prepared
+                        .semijoin(&decimal_keys)
+                        .reduce(|_key, vals, output| {
+                            let (sum, count) =
+                                weighted_sum(vals.iter().map(|(val, diff)| (&val[0], *diff)));
+                            output.push((Value::Decimal(average(&sum, count)), 1));
+                        })
+                        .map(move |(key, avg)| (key, vec![avg]));
+                    collections.push(numbers.concat(&decimals));
                 }
                 AggregationFn::VARIANCE => {
                     let tuples = tuples
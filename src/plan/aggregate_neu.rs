@@ -10,8 +10,9 @@ use differential_dataflow::operators::Join as JoinMap;
 use differential_dataflow::operators::{Count, Reduce};
 
 use crate::binding::{AsBinding, Binding};
+use crate::decimal::{average, weighted_sum};
 use crate::domain::Domain;
-use crate::plan::{Dependencies, Implementable};
+use crate::plan::{decimal_keys, nearest_rank, Dependencies, Implementable};
 use crate::timestamp::Rewind;
 use crate::{CollectionRelation, Implemented, Relation, ShutdownHandle, Value, Var, VariableMap};
 
@@ -26,8 +27,14 @@ pub enum AggregationFn {
     MAX,
     /// MEDIAN
     MEDIAN,
+    /// Quantile at the given fraction (e.g. 95/100 for p95), using
+    /// the nearest-rank method
+    QUANTILE(Rational32),
     /// Count
     COUNT,
+    /// Count of distinct values
+    #[allow(non_camel_case_types)]
+    COUNT_DISTINCT,
     /// Sum
     SUM,
     /// Average
@@ -164,6 +171,23 @@ impl<P: Implementable> Implementable for Aggregate<P> {
                     });
                     collections.push(tuples);
                 }
+                AggregationFn::QUANTILE(q) => {
+                    let q = *q;
+                    let tuples = tuples.map(prepare_unary).reduce(move |_key, vals, output| {
+                        let total: isize = vals.iter().map(|(_val, count)| count).sum();
+                        let rank = nearest_rank(q, total);
+
+                        let mut seen = 0;
+                        for (val, count) in vals.iter() {
+                            seen += count;
+                            if seen >= rank {
+                                output.push((vec![val[0].clone()], 1));
+                                break;
+                            }
+                        }
+                    });
+                    collections.push(tuples);
+                }
                 AggregationFn::COUNT => {
                     let tuples = tuples.map(prepare_unary).reduce(|_key, input, output| {
                         let mut total_count = 0;
@@ -175,27 +199,59 @@ impl<P: Implementable> Implementable for Aggregate<P> {
                     });
                     collections.push(tuples);
                 }
+                AggregationFn::COUNT_DISTINCT => {
+                    let tuples = tuples.map(prepare_unary).reduce(|_key, input, output| {
+                        // Values arrive sorted, possibly extended by
+                        // with-values, so we only count the first of
+                        // each run of equal values.
+                        let mut distinct_count = 0;
+                        let mut last: Option<&Value> = None;
+                        for (val, _count) in input.iter() {
+                            if last != Some(&val[0]) {
+                                distinct_count += 1;
+                                last = Some(&val[0]);
+                            }
+                        }
+
+                        output.push((vec![Value::Number(distinct_count)], 1))
+                    });
+                    collections.push(tuples);
+                }
                 AggregationFn::SUM => {
-                    let tuples = tuples
-                        .map(prepare_unary)
+                    // Numbers are summed incrementally as differences.
+                    // Groups holding any decimals are summed exactly
+                    // instead, at the cost of a reduction.
+                    let prepared = tuples.map(prepare_unary);
+                    let decimal_keys = decimal_keys(&prepared);
+                    let numbers = prepared
+                        .antijoin(&decimal_keys)
                         .explode(|(key, val)| {
                             let v = match val[0] {
                                 Value::Number(num) => num,
-                                _ => panic!("SUM can only be applied on type Number."),
+                                _ => panic!("SUM can only be applied on type Number or Decimal."),
                             };
                             Some((key, v as isize))
                         })
                         .count()
                         .map(move |(key, count)| (key, vec![Value::Number(count as i64)]));
-                    collections.push(tuples);
+                    let decimals = prepared
+                        .semijoin(&decimal_keys)
+                        .reduce(|_key, vals, output| {
+                            let (sum, _count) =
+                                weighted_sum(vals.iter().map(|(val, diff)| (&val[0], *diff)));
+                            output.push((vec![Value::Decimal(sum)], 1));
+                        });
+                    collections.push(numbers.concat(&decimals));
                 }
                 AggregationFn::AVG => {
-                    let tuples = tuples
-                        .map(prepare_unary)
+                    let prepared = tuples.map(prepare_unary);
+                    let decimal_keys = decimal_keys(&prepared);
+                    let numbers = prepared
+                        .antijoin(&decimal_keys)
                         .explode(move |(key, val)| {
                             let v = match val[0] {
                                 Value::Number(num) => num,
-                                _ => panic!("AVG can only be applied on type Number."),
+                                _ => panic!("AVG can only be applied on type Number or Decimal."),
                             };
                             Some((key, DiffPair::new(v as isize, 1)))
                         })
@@ -209,7 +265,14 @@ impl<P: Implementable> Implementable for Aggregate<P> {
                                 ))],
                             )
                         });
-                    collections.push(tuples);
+                    let decimals = prepared
+                        .semijoin(&decimal_keys)
+                        .reduce(|_key, vals, output| {
+                            let (sum, count) =
+                                weighted_sum(vals.iter().map(|(val, diff)| (&val[0], *diff)));
+                            output.push((vec![Value::Decimal(average(&sum, count))], 1));
+                        });
+                    collections.push(numbers.concat(&decimals));
                 }
                 AggregationFn::VARIANCE => {
                     let tuples = tuples
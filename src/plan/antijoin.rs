@@ -11,19 +11,40 @@ use crate::binding::{AsBinding, Binding};
 use crate::domain::Domain;
 use crate::plan::{Dependencies, Implementable};
 use crate::timestamp::Rewind;
-use crate::{CollectionRelation, Implemented, Relation, ShutdownHandle, Var, VariableMap};
+use crate::{
+    CollectionRelation, Implemented, Relation, Semantics, ShutdownHandle, Var, VariableMap,
+};
 
 /// A plan stage anti-joining both its sources on the specified
-/// variables. Throws if the sources are not union-compatible, i.e. bind
-/// all of the same variables in the same order.
+/// variables, i.e. retaining only those tuples from the left source
+/// that have no match in the right source. Throws if any of the
+/// specified variables is not bound by both sources.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub struct Antijoin<P1: Implementable, P2: Implementable> {
-    /// TODO
+    /// Variables shared between both sources.
     pub variables: Vec<Var>,
     /// Plan for the left input.
     pub left_plan: Box<P1>,
     /// Plan for the right input.
     pub right_plan: Box<P2>,
+    /// Whether to keep the multiplicities of tuples on the left.
+    /// Filled in from the semantics of the enclosing rule, if
+    /// unspecified.
+    #[serde(default)]
+    pub semantics: Option<Semantics>,
+}
+
+impl<P1: Implementable, P2: Implementable<A = P1::A>> Antijoin<P1, P2> {
+    /// Returns all join variables not bound by the given source
+    /// variables. Negated variables must be bound elsewhere, because
+    /// the antijoin can only remove tuples, never produce them.
+    pub fn unbound_variables(&self, bound: &[Var]) -> Vec<Var> {
+        self.variables
+            .iter()
+            .filter(|x| !bound.contains(x))
+            .cloned()
+            .collect()
+    }
 }
 
 impl<P1: Implementable, P2: Implementable<A = P1::A>> Implementable for Antijoin<P1, P2> {
@@ -69,6 +90,22 @@ impl<P1: Implementable, P2: Implementable<A = P1::A>> Implementable for Antijoin
             right
         };
 
+        let unbound = self.unbound_variables(&left.variables());
+        if !unbound.is_empty() {
+            panic!(
+                "Antijoin variables {:?} are not bound by the left input.",
+                unbound
+            );
+        }
+
+        let unbound = self.unbound_variables(&right.variables());
+        if !unbound.is_empty() {
+            panic!(
+                "Antijoin variables {:?} are not bound by the right input.",
+                unbound
+            );
+        }
+
         let variables = self
             .variables
             .iter()
@@ -92,8 +129,13 @@ impl<P1: Implementable, P2: Implementable<A = P1::A>> Implementable for Antijoin
             arranged
         };
 
+        // Only the presence of matching tuples on the right matters.
+        let left_arranged = match self.semantics {
+            Some(Semantics::Bag) => left_arranged,
+            _ => left_arranged.distinct(),
+        };
+
         let tuples = left_arranged
-            .distinct()
             .antijoin(&right_projected.distinct())
             .map(|(key, tuple)| key.iter().cloned().chain(tuple.iter().cloned()).collect());
 
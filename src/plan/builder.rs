@@ -0,0 +1,481 @@
+//! A fluent builder for conjunctive queries, along with the `q!`
+//! macro offering Datalog-like syntax on top of it. Disjunctions
+//! are built from nested builders, one for each branch, and compile
+//! to unions.
+//!
+//! ```
+//! use declarative_dataflow::plan::{Predicate, QueryBuilder};
+//! use declarative_dataflow::{q, Aid, Plan, Value};
+//!
+//! let built: Plan<Aid> = QueryBuilder::new()
+//!     .pattern("?e", ":person/name", "?name")
+//!     .pattern("?e", ":person/age", "?age")
+//!     .filter(Predicate::GT, "?age", Value::Number(21))
+//!     .build(&["?e", "?name"])
+//!     .unwrap();
+//!
+//! let expanded: Plan<Aid> = q! {
+//!     find ?e ?name
+//!     where [?e :person/name ?name] [?e :person/age ?age] (> ?age 21)
+//! }
+//! .unwrap();
+//!
+//! assert_eq!(built, expanded);
+//! ```
+
+use std::collections::{BTreeSet, HashSet};
+
+use crate::plan::{Filter, Join, Plan, Predicate, Project, Union};
+use crate::{AsAid, Error, Value, Var};
+
+/// A variable, written with a leading `?`, or a constant.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Term {
+    /// A named variable, e.g. `?e`.
+    Var(String),
+    /// A constant value.
+    Constant(Value),
+}
+
+impl From<&str> for Term {
+    fn from(name: &str) -> Self {
+        Term::Var(name.to_string())
+    }
+}
+
+impl From<Value> for Term {
+    fn from(v: Value) -> Self {
+        Term::Constant(v)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Clause<A: AsAid> {
+    Pattern(String, A, Term),
+    Predicate(Predicate, Term, Term),
+    /// Join variables, unless they are to be inferred from the
+    /// branches, followed by those that must be bound beforehand.
+    Or(Option<Vec<String>>, Vec<String>, Vec<QueryBuilder<A>>),
+}
+
+/// Assembles a plan from data patterns and predicates, joining
+/// patterns on the variables they share. Mistakes, like unbound
+/// variables, are reported by `build`.
+#[derive(Clone, Debug)]
+pub struct QueryBuilder<A: AsAid> {
+    clauses: Vec<Clause<A>>,
+}
+
+impl<A: AsAid> Default for QueryBuilder<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: AsAid> QueryBuilder<A> {
+    /// Creates a builder without any clauses.
+    pub fn new() -> Self {
+        QueryBuilder {
+            clauses: Vec::new(),
+        }
+    }
+
+    /// Adds the data pattern [e a v], where the value is either a
+    /// variable or a constant.
+    pub fn pattern<X: Into<A>, V: Into<Term>>(mut self, e: &str, a: X, v: V) -> Self {
+        self.clauses
+            .push(Clause::Pattern(e.to_string(), a.into(), v.into()));
+        self
+    }
+
+    /// Adds the predicate (predicate left right). Both arguments
+    /// must be bound by patterns, unless they are constants.
+    pub fn filter<L: Into<Term>, R: Into<Term>>(
+        mut self,
+        predicate: Predicate,
+        left: L,
+        right: R,
+    ) -> Self {
+        self.clauses
+            .push(Clause::Predicate(predicate, left.into(), right.into()));
+        self
+    }
+
+    /// Adds the disjunction (or branch...), matching wherever any of
+    /// the branches matches. All branches must use the same
+    /// variables, apart from anonymous ones starting with `?_`, and
+    /// are joined with the other clauses on all of them.
+    pub fn or(mut self, branches: Vec<QueryBuilder<A>>) -> Self {
+        self.clauses.push(Clause::Or(None, Vec::new(), branches));
+        self
+    }
+
+    /// Adds the disjunction (or-join [[required...] variables...]
+    /// branch...), which is joined with the other clauses on the
+    /// specified variables only. Each branch must bind all of them,
+    /// any others are local to the branch. Required variables must
+    /// be bound by other clauses, the disjunction is joined with
+    /// them only afterwards.
+    pub fn or_join(
+        mut self,
+        variables: &[&str],
+        required: &[&str],
+        branches: Vec<QueryBuilder<A>>,
+    ) -> Self {
+        let variables = variables.iter().map(|x| x.to_string()).collect();
+        let required = required.iter().map(|x| x.to_string()).collect();
+
+        self.clauses
+            .push(Clause::Or(Some(variables), required, branches));
+        self
+    }
+
+    /// Returns a plan binding the specified variables, in order.
+    pub fn build(self, find: &[&str]) -> Result<Plan<A>, Error> {
+        let mut names: Vec<String> = Vec::new();
+        let (bound, plan) = self.compile(&mut names)?;
+
+        let find = find
+            .iter()
+            .map(|name| var(&mut names, name))
+            .collect::<Result<Vec<Var>, Error>>()?;
+
+        if let Some(error) = unbound(&names, &bound, &find) {
+            return Err(error);
+        }
+
+        Ok(Plan::Project(Project {
+            variables: find,
+            plan: Box::new(plan),
+        }))
+    }
+
+    /// Returns the named, non-anonymous variables used by the
+    /// clauses, which must agree across the branches of `or`.
+    fn variables(&self) -> BTreeSet<String> {
+        let mut variables = BTreeSet::new();
+
+        for clause in self.clauses.iter() {
+            match clause {
+                Clause::Pattern(e, _, v) => {
+                    variables.insert(e.clone());
+                    if let Term::Var(v) = v {
+                        variables.insert(v.clone());
+                    }
+                }
+                Clause::Predicate(_, left, right) => {
+                    for term in &[left, right] {
+                        if let Term::Var(name) = term {
+                            variables.insert(name.clone());
+                        }
+                    }
+                }
+                Clause::Or(Some(join), required, _) => {
+                    variables.extend(join.iter().chain(required.iter()).cloned());
+                }
+                Clause::Or(None, _, branches) => {
+                    if let Some(first) = branches.first() {
+                        variables.extend(first.variables());
+                    }
+                }
+            }
+        }
+
+        variables.retain(|name| !name.starts_with("?_"));
+        variables
+    }
+
+    /// Returns the variables bound by the clauses, along with a plan
+    /// binding them. Variables are numbered via `names`, which is
+    /// shared with the branches of disjunctions.
+    fn compile(self, names: &mut Vec<String>) -> Result<(HashSet<Var>, Plan<A>), Error> {
+        // Each relation to join is listed alongside the variables it
+        // binds and those that must be bound before joining it.
+        let mut patterns: Vec<(Vec<Var>, Vec<Var>, Plan<A>)> = Vec::new();
+        let mut predicates = Vec::new();
+
+        for clause in self.clauses.into_iter() {
+            match clause {
+                Clause::Pattern(e, a, Term::Var(v)) => {
+                    let (e, v) = (var(names, &e)?, var(names, &v)?);
+                    patterns.push((vec![e, v], vec![], Plan::match_a(e, a, v)));
+                }
+                Clause::Pattern(e, a, Term::Constant(v)) => {
+                    let e = var(names, &e)?;
+                    patterns.push((vec![e], vec![], Plan::MatchAV(e, a, v)));
+                }
+                Clause::Predicate(predicate, left, right) => {
+                    let mut variables = Vec::new();
+                    let mut constants = Vec::new();
+
+                    for term in vec![left, right].into_iter() {
+                        match term {
+                            Term::Var(name) => {
+                                variables.push(var(names, &name)?);
+                                constants.push(None);
+                            }
+                            Term::Constant(v) => constants.push(Some(v)),
+                        }
+                    }
+
+                    if variables.is_empty() {
+                        return Err(Error::incorrect(format!(
+                            "Predicate {:?} doesn't refer to any variable.",
+                            predicate
+                        )));
+                    }
+
+                    predicates.push((predicate, variables, constants));
+                }
+                Clause::Or(join, required, branches) => {
+                    if branches.is_empty() {
+                        return Err(Error::incorrect(
+                            "Disjunctions require at least one branch.",
+                        ));
+                    }
+
+                    let join = match join {
+                        Some(join) => join,
+                        None => {
+                            let expected = branches[0].variables();
+                            if branches.iter().any(|branch| branch.variables() != expected) {
+                                return Err(Error::incorrect(
+                                    "All branches of or must use the same variables, use or-join otherwise.",
+                                ));
+                            }
+
+                            expected.into_iter().collect()
+                        }
+                    };
+
+                    let required = required
+                        .iter()
+                        .map(|name| var(names, name))
+                        .collect::<Result<Vec<Var>, Error>>()?;
+
+                    let mut variables = required.clone();
+                    for name in join.iter() {
+                        let x = var(names, name)?;
+                        if !variables.contains(&x) {
+                            variables.push(x);
+                        }
+                    }
+
+                    let mut plans = Vec::with_capacity(branches.len());
+                    for branch in branches.into_iter() {
+                        let (bound, plan) = branch.compile(names)?;
+
+                        if let Some(error) = unbound(names, &bound, &variables) {
+                            return Err(error);
+                        }
+
+                        plans.push(Plan::Project(Project {
+                            variables: variables.clone(),
+                            plan: Box::new(plan),
+                        }));
+                    }
+
+                    let union = Plan::Union(Union {
+                        variables: variables.clone(),
+                        plans,
+                        semantics: None,
+                    });
+
+                    patterns.push((variables, required, union));
+                }
+            }
+        }
+
+        let names: &[String] = names;
+
+        // Relations are joined in order, each one with the first
+        // relation sharing a variable with those joined so far and
+        // whose required variables are bound already.
+        if patterns.is_empty() {
+            return Err(Error::incorrect("Queries require at least one pattern."));
+        }
+
+        let ready = |bound: &HashSet<Var>, required: &[Var]| -> bool {
+            required.iter().all(|x| bound.contains(x))
+        };
+
+        let first = patterns
+            .iter()
+            .position(|(_, required, _)| required.is_empty())
+            .ok_or_else(|| unready(names, &HashSet::new(), &patterns))?;
+
+        let (mut bound, mut plan) = {
+            let (variables, _, plan) = patterns.remove(first);
+            (variables.into_iter().collect::<HashSet<Var>>(), plan)
+        };
+
+        while !patterns.is_empty() {
+            let next = patterns
+                .iter()
+                .position(|(variables, required, _)| {
+                    variables.iter().any(|x| bound.contains(x)) && ready(&bound, required)
+                })
+                .ok_or_else(|| unready(names, &bound, &patterns))?;
+
+            let (variables, _, right) = patterns.remove(next);
+            let shared = variables
+                .iter()
+                .filter(|x| bound.contains(x))
+                .cloned()
+                .collect();
+
+            bound.extend(variables.into_iter());
+
+            plan = Plan::Join(Join {
+                variables: shared,
+                left_plan: Box::new(plan),
+                right_plan: Box::new(right),
+            });
+        }
+
+        for (predicate, variables, constants) in predicates.into_iter() {
+            if let Some(error) = unbound(names, &bound, &variables) {
+                return Err(error);
+            }
+
+            plan = Plan::Filter(Filter {
+                variables,
+                predicate,
+                plan: Box::new(plan),
+                constants,
+            });
+        }
+
+        Ok((bound, plan))
+    }
+}
+
+/// Returns the number of the named variable, assigning the next one
+/// if it hasn't been seen before.
+fn var(names: &mut Vec<String>, name: &str) -> Result<Var, Error> {
+    if !name.starts_with('?') {
+        return Err(Error::incorrect(format!(
+            "Variable {} must start with a ?.",
+            name
+        )));
+    }
+
+    match names.iter().position(|x| x == name) {
+        Some(idx) => Ok(idx as Var),
+        None => {
+            names.push(name.to_string());
+            Ok((names.len() - 1) as Var)
+        }
+    }
+}
+
+/// Reports the first of the specified variables that isn't bound.
+fn unbound(names: &[String], bound: &HashSet<Var>, variables: &[Var]) -> Option<Error> {
+    variables
+        .iter()
+        .find(|x| !bound.contains(x))
+        .map(|x| Error::incorrect(format!("Variable {} is never bound.", names[*x as usize])))
+}
+
+/// Explains why none of the remaining relations can be joined next.
+fn unready<A: AsAid>(
+    names: &[String],
+    bound: &HashSet<Var>,
+    patterns: &[(Vec<Var>, Vec<Var>, Plan<A>)],
+) -> Error {
+    let required = patterns
+        .iter()
+        .flat_map(|(_, required, _)| required.iter())
+        .find(|x| !bound.contains(x));
+
+    match required {
+        Some(x) => Error::incorrect(format!(
+            "Variable {} must be bound by other clauses before or-join.",
+            names[*x as usize]
+        )),
+        None => Error::incorrect("Patterns must be connected by shared variables."),
+    }
+}
+
+/// Builds a plan from a Datalog-like query, e.g.
+///
+/// ```
+/// # use declarative_dataflow::{q, Aid, Plan};
+/// let plan: Plan<Aid> = q! {
+///     find ?e ?age
+///     where [?e ":person/first-name" "Dipper"] [?e :age ?age] (>= ?age 12)
+/// }
+/// .unwrap();
+/// ```
+///
+/// The syntax is checked at compile time. Attributes are written
+/// as `:namespace/name` or `:name`, or as string literals if they
+/// contain other characters. Predicates are one of `<`, `<=`, `>`,
+/// `>=`, `=`, `!=`, `contains`, `starts_with`, `before`, and
+/// `after`, applied to variables or literals. Evaluates to the
+/// result of `QueryBuilder::build`.
+#[macro_export]
+macro_rules! q {
+    (find $(? $find:ident)+ where $($clauses:tt)+) => {
+        $crate::q!(@clauses $crate::plan::QueryBuilder::new(); $($clauses)+)
+            .build(&[$(concat!("?", stringify!($find))),+])
+    };
+
+    (@clauses $builder:expr; ) => { $builder };
+    (@clauses $builder:expr; [$($pattern:tt)+] $($rest:tt)*) => {
+        $crate::q!(@clauses $crate::q!(@pattern $builder; $($pattern)+); $($rest)*)
+    };
+    (@clauses $builder:expr; ($($predicate:tt)+) $($rest:tt)*) => {
+        $crate::q!(@clauses $crate::q!(@predicate $builder; $($predicate)+); $($rest)*)
+    };
+
+    (@pattern $builder:expr; ? $e:ident : $ns:ident / $name:ident $($v:tt)+) => {
+        $crate::q!(@value $builder; $e; concat!(":", stringify!($ns), "/", stringify!($name)); $($v)+)
+    };
+    (@pattern $builder:expr; ? $e:ident : $name:ident $($v:tt)+) => {
+        $crate::q!(@value $builder; $e; concat!(":", stringify!($name)); $($v)+)
+    };
+    (@pattern $builder:expr; ? $e:ident $a:literal $($v:tt)+) => {
+        $crate::q!(@value $builder; $e; $a; $($v)+)
+    };
+
+    (@value $builder:expr; $e:ident; $a:expr; ? $v:ident) => {
+        $builder.pattern(concat!("?", stringify!($e)), $a, concat!("?", stringify!($v)))
+    };
+    (@value $builder:expr; $e:ident; $a:expr; $v:literal) => {
+        $builder.pattern(concat!("?", stringify!($e)), $a, $crate::Value::from($v))
+    };
+
+    (@predicate $builder:expr; $op:tt ? $x:ident ? $y:ident) => {
+        $builder.filter(
+            $crate::q!(@op $op),
+            concat!("?", stringify!($x)),
+            concat!("?", stringify!($y)),
+        )
+    };
+    (@predicate $builder:expr; $op:tt ? $x:ident $c:literal) => {
+        $builder.filter(
+            $crate::q!(@op $op),
+            concat!("?", stringify!($x)),
+            $crate::Value::from($c),
+        )
+    };
+    (@predicate $builder:expr; $op:tt $c:literal ? $y:ident) => {
+        $builder.filter(
+            $crate::q!(@op $op),
+            $crate::Value::from($c),
+            concat!("?", stringify!($y)),
+        )
+    };
+
+    (@op <) => { $crate::plan::Predicate::LT };
+    (@op <=) => { $crate::plan::Predicate::LTE };
+    (@op >) => { $crate::plan::Predicate::GT };
+    (@op >=) => { $crate::plan::Predicate::GTE };
+    (@op =) => { $crate::plan::Predicate::EQ };
+    (@op !=) => { $crate::plan::Predicate::NEQ };
+    (@op contains) => { $crate::plan::Predicate::CONTAINS };
+    (@op starts_with) => { $crate::plan::Predicate::STARTS_WITH };
+    (@op before) => { $crate::plan::Predicate::BEFORE };
+    (@op after) => { $crate::plan::Predicate::AFTER };
+}
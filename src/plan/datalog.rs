@@ -0,0 +1,358 @@
+//! A parser for Datomic-flavored query strings, e.g.
+//!
+//! ```text
+//! [:find ?e ?name
+//!  :where [?e :person/name ?name] [?e :person/age ?age] [(> ?age 21)]]
+//! ```
+//!
+//! Supported are data patterns, with variables, `_`, or constants in
+//! value position, and the binary predicates `<`, `<=`, `>`, `>=`,
+//! `=`, `!=` (or `not=`), `contains`, `starts-with`, `before`, and
+//! `after`. Constants are strings, integers, booleans, and keywords,
+//! which denote attribute identifiers. Disjunctions are written as
+//! `(or branch...)` or `(or-join [[required...] variables...]
+//! branch...)`, where each branch is either a single clause or
+//! `(and clause...)`:
+//!
+//! ```text
+//! [:find ?e ?name
+//!  :where [?e :person/name ?name]
+//!         (or-join [?e] [?e :person/nickname _]
+//!                       (and [?e :person/age ?age] [(< ?age 18)]))]
+//! ```
+//!
+//! Queries are compiled via the `QueryBuilder`.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::plan::{Plan, Predicate, QueryBuilder, Term};
+use crate::{AsAid, Error, Value};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Open(char),
+    Close(char),
+    Str(String),
+    Atom(String),
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars: Peekable<Chars> = query.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '[' | '(' => tokens.push(Token::Open(c)),
+            ']' | ')' => tokens.push(Token::Close(c)),
+            '"' => {
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        None => return Err(Error::incorrect("Unterminated string.")),
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => s.push('\n'),
+                            Some('t') => s.push('\t'),
+                            Some(escaped) => s.push(escaped),
+                            None => return Err(Error::incorrect("Unterminated string.")),
+                        },
+                        Some(c) => s.push(c),
+                    }
+                }
+                tokens.push(Token::Str(s));
+            }
+            c if c.is_whitespace() || c == ',' => {}
+            c => {
+                let mut atom = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || "[]()\",".contains(next) {
+                        break;
+                    }
+                    atom.push(next);
+                    chars.next();
+                }
+                tokens.push(Token::Atom(atom));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<A: AsAid> {
+    tokens: std::vec::IntoIter<Token>,
+    builder: QueryBuilder<A>,
+    wildcards: usize,
+}
+
+impl<A: AsAid> Parser<A> {
+    fn next(&mut self) -> Result<Token, Error> {
+        self.tokens
+            .next()
+            .ok_or_else(|| Error::incorrect("Unexpected end of query."))
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), Error> {
+        let token = self.next()?;
+        if token == expected {
+            Ok(())
+        } else {
+            Err(Error::incorrect(format!(
+                "Expected {:?}, found {:?}.",
+                expected, token
+            )))
+        }
+    }
+
+    /// Variables are kept as they are, `_` becomes a fresh variable
+    /// each time.
+    fn variable(&mut self, atom: &str) -> Option<String> {
+        if atom == "_" {
+            self.wildcards += 1;
+            Some(format!("?_{}", self.wildcards))
+        } else if atom.starts_with('?') {
+            Some(atom.to_string())
+        } else {
+            None
+        }
+    }
+
+    fn term(&mut self, token: Token) -> Result<Term, Error> {
+        match token {
+            Token::Str(s) => Ok(Term::Constant(Value::String(s))),
+            Token::Atom(atom) => match self.variable(&atom) {
+                Some(name) => Ok(Term::Var(name)),
+                None => constant(&atom).map(Term::Constant),
+            },
+            other => Err(Error::incorrect(format!("Unexpected {:?}.", other))),
+        }
+    }
+
+    fn clause(&mut self) -> Result<(), Error> {
+        match self.next()? {
+            Token::Open('(') => {
+                let predicate = match self.next()? {
+                    Token::Atom(symbol) => predicate(&symbol)?,
+                    other => return Err(Error::incorrect(format!("Unexpected {:?}.", other))),
+                };
+
+                let left = self.next().and_then(|token| self.term(token))?;
+                let right = self.next().and_then(|token| self.term(token))?;
+
+                self.expect(Token::Close(')'))?;
+                self.expect(Token::Close(']'))?;
+
+                let builder = std::mem::replace(&mut self.builder, QueryBuilder::new());
+                self.builder = builder.filter(predicate, left, right);
+
+                Ok(())
+            }
+            Token::Atom(e) => {
+                let e = self.variable(&e).ok_or_else(|| {
+                    Error::unsupported(format!("Entity {} must be a variable.", e))
+                })?;
+
+                let a = match self.next()? {
+                    Token::Atom(ref a) if a.starts_with(':') => A::from(a.to_string()),
+                    other => {
+                        return Err(Error::unsupported(format!(
+                            "Attribute {:?} must be a keyword.",
+                            other
+                        )))
+                    }
+                };
+
+                let v = self.next().and_then(|token| self.term(token))?;
+                self.expect(Token::Close(']'))?;
+
+                let builder = std::mem::replace(&mut self.builder, QueryBuilder::new());
+                self.builder = builder.pattern(&e, a, v);
+
+                Ok(())
+            }
+            other => Err(Error::incorrect(format!("Unexpected {:?}.", other))),
+        }
+    }
+
+    /// Parses a disjunction, following its opening parenthesis.
+    fn disjunction(&mut self) -> Result<(), Error> {
+        let mut variables = Vec::new();
+        let mut required = Vec::new();
+
+        let join = match self.next()? {
+            Token::Atom(ref keyword) if keyword == "or" => false,
+            Token::Atom(ref keyword) if keyword == "or-join" => {
+                self.expect(Token::Open('['))?;
+                loop {
+                    match self.next()? {
+                        Token::Close(']') => break,
+                        Token::Open('[') if variables.is_empty() && required.is_empty() => loop {
+                            match self.next()? {
+                                Token::Close(']') => break,
+                                Token::Atom(ref var) if var.starts_with('?') => {
+                                    required.push(var.to_string())
+                                }
+                                other => {
+                                    return Err(Error::incorrect(format!(
+                                        "Expected a variable, found {:?}.",
+                                        other
+                                    )))
+                                }
+                            }
+                        },
+                        Token::Atom(ref var) if var.starts_with('?') => {
+                            variables.push(var.to_string())
+                        }
+                        other => {
+                            return Err(Error::incorrect(format!(
+                                "Expected a variable, found {:?}.",
+                                other
+                            )))
+                        }
+                    }
+                }
+                true
+            }
+            other => {
+                return Err(Error::unsupported(format!(
+                    "Unsupported clause {:?}.",
+                    other
+                )))
+            }
+        };
+
+        let mut branches = Vec::new();
+        loop {
+            let outer = std::mem::replace(&mut self.builder, QueryBuilder::new());
+
+            let parsed = match self.next()? {
+                Token::Close(')') => {
+                    self.builder = outer;
+                    break;
+                }
+                Token::Open('[') => self.clause(),
+                Token::Open('(') => match self.next()? {
+                    Token::Atom(ref keyword) if keyword == "and" => self.conjunction(),
+                    other => Err(Error::incorrect(format!(
+                        "Expected a clause or (and ...), found {:?}.",
+                        other
+                    ))),
+                },
+                other => Err(Error::incorrect(format!("Unexpected {:?}.", other))),
+            };
+
+            branches.push(std::mem::replace(&mut self.builder, outer));
+            parsed?;
+        }
+
+        let builder = std::mem::replace(&mut self.builder, QueryBuilder::new());
+        self.builder = if join {
+            let variables: Vec<&str> = variables.iter().map(String::as_str).collect();
+            let required: Vec<&str> = required.iter().map(String::as_str).collect();
+
+            builder.or_join(&variables, &required, branches)
+        } else {
+            builder.or(branches)
+        };
+
+        Ok(())
+    }
+
+    /// Parses clauses up to the closing parenthesis of the enclosing
+    /// `(and ...)`.
+    fn conjunction(&mut self) -> Result<(), Error> {
+        loop {
+            match self.next()? {
+                Token::Open('[') => self.clause()?,
+                Token::Open('(') => self.disjunction()?,
+                Token::Close(')') => return Ok(()),
+                other => return Err(Error::incorrect(format!("Unexpected {:?}.", other))),
+            }
+        }
+    }
+}
+
+fn constant(atom: &str) -> Result<Value, Error> {
+    match atom {
+        "true" => Ok(Value::Bool(true)),
+        "false" => Ok(Value::Bool(false)),
+        _ if atom.starts_with(':') => Ok(Value::Aid(atom.to_string())),
+        _ => atom
+            .parse::<i64>()
+            .map(Value::Number)
+            .map_err(|_| Error::unsupported(format!("Unsupported constant {}.", atom))),
+    }
+}
+
+fn predicate(symbol: &str) -> Result<Predicate, Error> {
+    match symbol {
+        "<" => Ok(Predicate::LT),
+        "<=" => Ok(Predicate::LTE),
+        ">" => Ok(Predicate::GT),
+        ">=" => Ok(Predicate::GTE),
+        "=" => Ok(Predicate::EQ),
+        "!=" | "not=" => Ok(Predicate::NEQ),
+        "contains" => Ok(Predicate::CONTAINS),
+        "starts-with" => Ok(Predicate::STARTS_WITH),
+        "before" => Ok(Predicate::BEFORE),
+        "after" => Ok(Predicate::AFTER),
+        _ => Err(Error::unsupported(format!(
+            "Unsupported predicate {}.",
+            symbol
+        ))),
+    }
+}
+
+/// Compiles a query string into a plan binding the variables of its
+/// `:find` clause, in order.
+pub fn parse<A: AsAid>(query: &str) -> Result<Plan<A>, Error> {
+    let mut parser = Parser {
+        tokens: tokenize(query)?.into_iter(),
+        builder: QueryBuilder::new(),
+        wildcards: 0,
+    };
+
+    parser.expect(Token::Open('['))?;
+    parser.expect(Token::Atom(":find".to_string()))?;
+
+    let mut find = Vec::new();
+    loop {
+        match parser.next()? {
+            Token::Atom(ref keyword) if keyword == ":where" => break,
+            Token::Atom(ref keyword) if keyword.starts_with(':') => {
+                return Err(Error::unsupported(format!(
+                    "Unsupported clause {}.",
+                    keyword
+                )))
+            }
+            Token::Atom(ref var) if var.starts_with('?') => find.push(var.to_string()),
+            other => {
+                return Err(Error::incorrect(format!(
+                    "Expected a variable, found {:?}.",
+                    other
+                )))
+            }
+        }
+    }
+
+    loop {
+        match parser.next()? {
+            Token::Open('[') => parser.clause()?,
+            Token::Open('(') => parser.disjunction()?,
+            Token::Close(']') => break,
+            other => return Err(Error::incorrect(format!("Unexpected {:?}.", other))),
+        }
+    }
+
+    if let Some(token) = parser.tokens.next() {
+        return Err(Error::incorrect(format!(
+            "Unexpected {:?} after the query.",
+            token
+        )));
+    }
+
+    let find: Vec<&str> = find.iter().map(String::as_str).collect();
+
+    parser.builder.build(&find)
+}
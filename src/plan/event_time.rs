@@ -0,0 +1,84 @@
+//! Event-time restriction plan for bitemporal domains.
+
+use std::time::Duration;
+
+use timely::dataflow::operators::Filter;
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::AsCollection;
+
+use crate::domain::Domain;
+use crate::plan::{Dependencies, Implementable};
+use crate::timestamp::{Rewind, Time};
+use crate::{CollectionRelation, Implemented, Relation, ShutdownHandle, Var, VariableMap};
+
+/// A plan stage restricting source tuples to those changes whose
+/// event time lies within the half-open interval [from, until). Only
+/// bitemporal times carry an event-time coordinate, changes at any
+/// other kind of time pass through untouched.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct EventWindow<P: Implementable> {
+    /// TODO
+    pub variables: Vec<Var>,
+    /// Plan for the data source.
+    pub plan: Box<P>,
+    /// Earliest event time to include, if any.
+    pub from: Option<Duration>,
+    /// Event time from which on changes are excluded, if any.
+    pub until: Option<Duration>,
+}
+
+impl<P: Implementable> Implementable for EventWindow<P> {
+    type A = P::A;
+
+    fn dependencies(&self) -> Dependencies<Self::A> {
+        self.plan.dependencies()
+    }
+
+    fn implement<'b, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        domain: &mut Domain<Self::A, S::Timestamp>,
+        local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
+    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+    where
+        S: Scope,
+        S::Timestamp: Timestamp + Lattice + Rewind,
+    {
+        let (relation, mut shutdown_handle) =
+            self.plan.implement(nested, domain, local_arrangements);
+
+        let variables = relation.variables();
+        let projected = {
+            let (projected, shutdown) = relation.projected(nested, domain, &variables);
+            shutdown_handle.merge_with(shutdown);
+            projected
+        };
+
+        let from = self.from;
+        let until = self.until;
+
+        let tuples = projected
+            .inner
+            .filter(move |(_tuple, t, _diff)| {
+                let time: Time = t.outer.clone().into();
+
+                match time.event_time() {
+                    None => true,
+                    Some(event) => {
+                        from.map_or(true, |from| from <= event)
+                            && until.map_or(true, |until| event < until)
+                    }
+                }
+            })
+            .as_collection();
+
+        (
+            Implemented::Collection(CollectionRelation { variables, tuples }),
+            shutdown_handle,
+        )
+    }
+}
@@ -0,0 +1,675 @@
+//! Descriptions of the physical plans that queries would be
+//! implemented by, for debugging purposes.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+
+use crate::binding::{AsBinding, Binding};
+use crate::domain::Domain;
+use crate::plan::hector::{direction, plan_order, source_conflicts, Direction};
+use crate::plan::validation::validate_bindings;
+use crate::plan::{reverse_attribute, Implementable, Join, Plan};
+use crate::server::Strategy;
+use crate::timestamp::Rewind;
+use crate::{collect_dependencies, AsAid, Var};
+
+/// The indices a domain may maintain for an attribute.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Index {
+    /// Number of values per entity.
+    ForwardCount,
+    /// Values by entity.
+    ForwardPropose,
+    /// (entity, value) pairs.
+    ForwardValidate,
+    /// Number of entities per value.
+    ReverseCount,
+    /// Entities by value.
+    ReversePropose,
+    /// (value, entity) pairs.
+    ReverseValidate,
+    /// (entity, value) pairs by token.
+    Fulltext,
+}
+
+/// An arrangement read by a step of a physical plan.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum Arrangement {
+    /// An attribute index, maintained by the domain and shared by
+    /// all queries using it.
+    Attribute {
+        /// The indexed attribute.
+        attribute: String,
+        /// The index used.
+        index: Index,
+    },
+    /// Tuples bound to a query parameter, maintained by the domain.
+    Parameter(String),
+    /// The results of a materialized rule, maintained by the domain.
+    Materialized(String),
+    /// A composite index over several attributes, maintained by the
+    /// domain.
+    Composite(String),
+    /// Intermediate results keyed by the specified variables,
+    /// arranged by the query itself.
+    Private(Vec<Var>),
+}
+
+impl Arrangement {
+    /// Returns true iff the arrangement is maintained by the domain,
+    /// rather than built anew for each query.
+    pub fn is_shared(&self) -> bool {
+        match *self {
+            Arrangement::Private(_) => false,
+            _ => true,
+        }
+    }
+}
+
+/// A single step of a physical plan.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Step {
+    /// The operation performed, e.g. `Join` or `Extend`.
+    pub operator: String,
+    /// Variables bound by the results of this step, in order.
+    pub variables: Vec<Var>,
+    /// Arrangements read by this step.
+    pub arrangements: Vec<Arrangement>,
+    /// Steps feeding into this one. The steps of a delta pipeline
+    /// are its extensions, in the order they are applied.
+    pub inputs: Vec<Step>,
+}
+
+impl Step {
+    fn new(operator: &str, variables: Vec<Var>, arrangements: Vec<Arrangement>) -> Self {
+        Step {
+            operator: operator.to_string(),
+            variables,
+            arrangements,
+            inputs: Vec::new(),
+        }
+    }
+
+    fn with_inputs(mut self, inputs: Vec<Step>) -> Self {
+        self.inputs = inputs;
+        self
+    }
+
+    fn visit<F: FnMut(&Step)>(&self, f: &mut F) {
+        f(self);
+        for input in self.inputs.iter() {
+            input.visit(f);
+        }
+    }
+}
+
+/// The physical plan of a named rule.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct RulePlan {
+    /// The name of the rule.
+    pub name: String,
+    /// Its physical plan.
+    pub plan: Step,
+}
+
+/// A description of how a plan would be implemented.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Explanation {
+    /// The strategy used for multi-way joins.
+    pub strategy: Strategy,
+    /// The physical plan of the explained plan itself.
+    pub plan: Step,
+    /// Physical plans of all rules the plan depends on.
+    pub rules: Vec<RulePlan>,
+    /// All distinct arrangements that would be imported from the
+    /// domain, rather than built by the query.
+    pub shared_arrangements: Vec<Arrangement>,
+    /// Number of arrangements the query would build itself.
+    pub private_arrangements: usize,
+    /// Attribute indices that would be imported, but which the
+    /// domain doesn't maintain.
+    pub missing_indices: Vec<Arrangement>,
+    /// Problems that would prevent the plan from being implemented.
+    pub warnings: Vec<String>,
+}
+
+/// Describes how the specified plan would be implemented on top of
+/// the specified domain, using the specified strategy.
+pub fn explain<A, T>(plan: &Plan<A>, strategy: Strategy, domain: &Domain<A, T>) -> Explanation
+where
+    A: AsAid + timely::ExchangeData,
+    T: Timestamp + Lattice + Rewind,
+{
+    let mut explainer = Explainer {
+        domain,
+        strategy,
+        warnings: Vec::new(),
+        missing: BTreeSet::new(),
+    };
+
+    if let Err(error) = plan.type_check(domain) {
+        explainer.warn(error.message);
+    }
+
+    let root = explainer.rule("(explained)", plan);
+
+    let names: Vec<A> = plan.dependencies().names.into_iter().collect();
+    let mut rules = match collect_dependencies(domain, &names) {
+        Ok(rules) => rules,
+        Err(error) => {
+            explainer.warn(error.message);
+            Vec::new()
+        }
+    };
+    rules.sort_by(|x, y| x.name.cmp(&y.name));
+
+    let rules: Vec<RulePlan> = rules
+        .iter()
+        .map(|rule| {
+            let name = rule.name.to_string();
+            let plan = if strategy == Strategy::BinaryJoins && domain.is_materialized(&rule.name) {
+                Step::new(
+                    "Import",
+                    rule.plan.variables(),
+                    vec![Arrangement::Materialized(name.clone())],
+                )
+            } else {
+                explainer.rule(&name, &rule.plan)
+            };
+
+            RulePlan { name, plan }
+        })
+        .collect();
+
+    let mut shared = BTreeSet::new();
+    let mut private_arrangements = 0;
+    {
+        let mut count = |step: &Step| {
+            for arrangement in step.arrangements.iter() {
+                if arrangement.is_shared() {
+                    shared.insert(arrangement.clone());
+                } else {
+                    private_arrangements += 1;
+                }
+            }
+        };
+
+        root.visit(&mut count);
+        for rule in rules.iter() {
+            rule.plan.visit(&mut count);
+        }
+    }
+
+    Explanation {
+        strategy,
+        plan: root,
+        rules,
+        shared_arrangements: shared.into_iter().collect(),
+        private_arrangements,
+        missing_indices: explainer.missing.into_iter().collect(),
+        warnings: explainer.warnings,
+    }
+}
+
+/// Returns the attribute pattern underlying a plan, if it is one.
+fn as_attribute<A: AsAid>(plan: &Plan<A>) -> Option<(Var, A, Var)> {
+    match plan.reversed().as_ref().unwrap_or(plan) {
+        Plan::MatchA(e, a, v) => Some((*e, a.clone(), *v)),
+        _ => None,
+    }
+}
+
+struct Explainer<'a, A, T>
+where
+    A: AsAid,
+    T: Timestamp + Lattice + Rewind,
+{
+    domain: &'a Domain<A, T>,
+    strategy: Strategy,
+    warnings: Vec<String>,
+    missing: BTreeSet<Arrangement>,
+}
+
+impl<'a, A, T> Explainer<'a, A, T>
+where
+    A: AsAid,
+    T: Timestamp + Lattice + Rewind,
+{
+    fn warn<S: ToString>(&mut self, warning: S) {
+        let warning = warning.to_string();
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+
+    fn rule(&mut self, name: &str, plan: &Plan<A>) -> Step {
+        match self.strategy {
+            Strategy::BinaryJoins => self.binary(plan),
+            Strategy::WorstCaseOptimal => {
+                if validate_bindings(plan).is_ok() {
+                    self.delta_query(&plan.variables(), &plan.into_bindings())
+                } else {
+                    self.warn(format!(
+                        "Rule {} can't be implemented via worst-case optimal joins.",
+                        name
+                    ));
+                    Step::new("Hector", plan.variables(), vec![])
+                }
+            }
+        }
+    }
+
+    fn index(&mut self, aid: &A, index: Index) -> Arrangement {
+        let domain = self.domain;
+        let available = match index {
+            Index::ForwardCount => domain.forward_count.contains_key(aid),
+            Index::ForwardPropose => domain.forward_propose.contains_key(aid),
+            Index::ForwardValidate => domain.forward_validate.contains_key(aid),
+            Index::ReverseCount => domain.reverse_count.contains_key(aid),
+            Index::ReversePropose => domain.reverse_propose.contains_key(aid),
+            Index::ReverseValidate => domain.reverse_validate.contains_key(aid),
+            Index::Fulltext => domain.fulltext.contains_key(aid),
+        };
+
+        let arrangement = Arrangement::Attribute {
+            attribute: aid.to_string(),
+            index,
+        };
+
+        if !domain.has_attribute(aid) {
+            self.warn(format!("Attribute {} does not exist.", aid));
+        } else if !available {
+            self.warn(format!(
+                "Attribute {} doesn't maintain a {:?} index.",
+                aid, index
+            ));
+            self.missing.insert(arrangement.clone());
+        }
+
+        arrangement
+    }
+
+    fn binary(&mut self, plan: &Plan<A>) -> Step {
+        if let Some(plan) = plan.reversed() {
+            return self.binary(&plan);
+        }
+
+        match *plan {
+            Plan::Project(ref projection) => {
+                let input = self.binary(&projection.plan);
+                Step::new("Project", projection.variables.clone(), vec![]).with_inputs(vec![input])
+            }
+            Plan::Aggregate(ref aggregate) => {
+                let input = self.binary(&aggregate.plan);
+                let arranged = Arrangement::Private(aggregate.key_variables.clone());
+                Step::new("Aggregate", aggregate.variables.clone(), vec![arranged])
+                    .with_inputs(vec![input])
+            }
+            Plan::Union(ref union) => {
+                let inputs = union.plans.iter().map(|plan| self.binary(plan)).collect();
+                Step::new("Union", union.variables.clone(), vec![]).with_inputs(inputs)
+            }
+            Plan::Join(ref join) => self.join(join),
+            Plan::Hector(ref hector) => self.delta_query(&hector.variables, &hector.bindings),
+            Plan::Antijoin(ref antijoin) => {
+                let left = self.binary(&antijoin.left_plan);
+                let right = self.binary(&antijoin.right_plan);
+
+                for input in [&left, &right].iter() {
+                    if antijoin
+                        .variables
+                        .iter()
+                        .any(|x| !input.variables.contains(x))
+                    {
+                        self.warn(format!(
+                            "Antijoin variables {:?} are not bound by both inputs.",
+                            antijoin.variables
+                        ));
+                    }
+                }
+
+                let arranged = Arrangement::Private(antijoin.variables.clone());
+                Step::new(
+                    "Antijoin",
+                    plan.variables(),
+                    vec![arranged.clone(), arranged],
+                )
+                .with_inputs(vec![left, right])
+            }
+            Plan::Negate(ref inner) => {
+                let input = self.binary(inner);
+                Step::new("Negate", input.variables.clone(), vec![]).with_inputs(vec![input])
+            }
+            Plan::Filter(ref filter) => {
+                let input = self.binary(&filter.plan);
+                Step::new("Filter", filter.variables.clone(), vec![]).with_inputs(vec![input])
+            }
+            Plan::Transform(ref transform) => {
+                let input = self.binary(&transform.plan);
+                Step::new("Transform", transform.variables.clone(), vec![]).with_inputs(vec![input])
+            }
+            Plan::MatchA(e, ref a, v) => {
+                let propose = self.index(a, Index::ForwardPropose);
+                Step::new("MatchA", vec![e, v], vec![propose])
+            }
+            Plan::MatchEA(_, ref a, v) => {
+                let propose = self.index(a, Index::ForwardPropose);
+                Step::new("MatchEA", vec![v], vec![propose])
+            }
+            Plan::MatchAV(e, ref a, _) => {
+                let propose = self.index(a, Index::ForwardPropose);
+                Step::new("MatchAV", vec![e], vec![propose])
+            }
+            Plan::NameExpr(ref variables, ref name) => {
+                if self.domain.rule(name).is_none() {
+                    self.warn(format!("Unknown rule {}.", name));
+                }
+
+                Step::new("NameExpr", variables.clone(), vec![])
+            }
+            Plan::Parameter(ref variables, ref name) => {
+                if !self.domain.parameters.contains_key(name) {
+                    self.warn(format!("Parameter {} does not exist.", name));
+                }
+
+                let parameter = Arrangement::Parameter(name.to_string());
+                Step::new("Parameter", variables.clone(), vec![parameter])
+            }
+            Plan::Pull(ref pull) => {
+                let inputs = pull.paths.iter().map(|path| self.binary(path)).collect();
+                Step::new("Pull", pull.variables.clone(), vec![]).with_inputs(inputs)
+            }
+            Plan::PullLevel(ref path) => {
+                let input = self.binary(&path.plan);
+                let mut arrangements = vec![Arrangement::Private(vec![path.pull_variable])];
+
+                match self.domain.entity_index_covering(&path.pull_attributes) {
+                    Some(name) => arrangements.push(Arrangement::Composite(name.to_string())),
+                    None => {
+                        for aid in path.pull_attributes.iter() {
+                            let arrangement = match reverse_attribute(aid) {
+                                None => self.index(aid, Index::ForwardPropose),
+                                Some(forward) => match self.domain.ref_index_covering(&forward) {
+                                    Some(name)
+                                        if !self.domain.reverse_propose.contains_key(&forward) =>
+                                    {
+                                        Arrangement::Composite(name.to_string())
+                                    }
+                                    _ => self.index(&forward, Index::ReversePropose),
+                                },
+                            };
+
+                            arrangements.push(arrangement);
+                        }
+                    }
+                }
+
+                Step::new("PullLevel", path.variables.clone(), arrangements)
+                    .with_inputs(vec![input])
+            }
+            Plan::PullAll(ref path) => {
+                let arrangements = path
+                    .pull_attributes
+                    .iter()
+                    .map(|aid| self.index(aid, Index::ForwardPropose))
+                    .collect();
+
+                Step::new("PullAll", path.variables.clone(), arrangements)
+            }
+            Plan::History(ref history) => {
+                let propose = self.index(&history.attribute, Index::ForwardPropose);
+                Step::new("History", history.variables.clone(), vec![propose])
+            }
+            Plan::EventWindow(ref window) => {
+                let input = self.binary(&window.plan);
+                Step::new("EventWindow", window.variables.clone(), vec![]).with_inputs(vec![input])
+            }
+            Plan::Fulltext(ref fulltext) => {
+                let index = self.index(&fulltext.attribute, Index::Fulltext);
+                Step::new("Fulltext", fulltext.variables.clone(), vec![index])
+            }
+            Plan::Range(ref range) => {
+                let propose = self.index(&range.attribute, Index::ReversePropose);
+                Step::new("Range", range.variables.clone(), vec![propose])
+            }
+            Plan::TopK(ref top_k) => {
+                let input = self.binary(&top_k.plan);
+                let arranged = Arrangement::Private(top_k.key_variables.clone());
+                Step::new("TopK", top_k.variables.clone(), vec![arranged]).with_inputs(vec![input])
+            }
+            Plan::Session(ref session) => {
+                let input = self.binary(&session.plan);
+                let arranged = Arrangement::Private(session.key_variables.clone());
+                Step::new("Session", session.bound_variables(), vec![arranged])
+                    .with_inputs(vec![input])
+            }
+            Plan::Window(ref window) => {
+                let input = self.binary(&window.plan);
+                Step::new("Window", window.bound_variables(), vec![]).with_inputs(vec![input])
+            }
+            Plan::TransitiveClosure(ref closure) => {
+                let input = self.binary(&closure.edges());
+                let arranged = Arrangement::Private(vec![closure.variables[1]]);
+                Step::new(
+                    "TransitiveClosure",
+                    closure.variables.clone(),
+                    vec![arranged],
+                )
+                .with_inputs(vec![input])
+            }
+            Plan::Graph(ref graph) => {
+                let input = self.binary(&graph.edges());
+                let arranged = Arrangement::Private(vec![graph.variables[0]]);
+                Step::new("Graph", graph.variables.clone(), vec![arranged]).with_inputs(vec![input])
+            }
+            #[cfg(feature = "graphql")]
+            Plan::GraphQl(_) => Step::new("GraphQl", vec![], vec![]),
+        }
+    }
+
+    /// Explains a pattern joined on the specified variable directly
+    /// via one of its propose indices.
+    fn propose_by(&mut self, (e, a, v): (Var, A, Var), target: Option<Var>) -> Step {
+        if target == Some(e) {
+            let propose = self.index(&a, Index::ForwardPropose);
+            Step::new("MatchA", vec![e, v], vec![propose])
+        } else if target == Some(v) {
+            let propose = self.index(&a, Index::ReversePropose);
+            Step::new("MatchA", vec![v, e], vec![propose])
+        } else {
+            self.warn("Unbound target variable in Attribute<->Attribute join.");
+            Step::new("MatchA", vec![e, v], vec![])
+        }
+    }
+
+    fn join(&mut self, join: &Join<Plan<A>, Plan<A>>) -> Step {
+        let target = &join.variables;
+        if target.is_empty() {
+            self.warn("Joins must target at least one variable.");
+        }
+
+        let attributes = (
+            as_attribute(&join.left_plan),
+            as_attribute(&join.right_plan),
+        );
+        let (left, right, arrangements) = match attributes {
+            (Some(left), Some(right)) => {
+                if target.len() > 1 {
+                    self.warn("Attribute<->Attribute joins can only target a single variable.");
+                }
+
+                let left = self.propose_by(left, target.first().cloned());
+                let right = self.propose_by(right, target.first().cloned());
+
+                (left, right, vec![])
+            }
+            _ => {
+                let left = self.binary(&join.left_plan);
+                let right = self.binary(&join.right_plan);
+                let arranged = Arrangement::Private(target.clone());
+
+                (left, right, vec![arranged.clone(), arranged])
+            }
+        };
+
+        if target
+            .iter()
+            .any(|x| !left.variables.contains(x) || !right.variables.contains(x))
+        {
+            self.warn(format!(
+                "Join variables {:?} are not bound by both inputs.",
+                target
+            ));
+        }
+
+        let variables = target
+            .iter()
+            .chain(left.variables.iter().filter(|x| !target.contains(x)))
+            .chain(right.variables.iter().filter(|x| !target.contains(x)))
+            .cloned()
+            .collect();
+
+        Step::new("Join", variables, arrangements).with_inputs(vec![left, right])
+    }
+
+    /// Explains a worst-case optimal join, mirroring the delta
+    /// pipelines constructed by Hector.
+    fn delta_query(&mut self, variables: &[Var], bindings: &[Binding<A>]) -> Step {
+        if variables.is_empty() {
+            self.warn("No variables requested.");
+        }
+
+        match bindings.len() {
+            0 => {
+                self.warn("No bindings passed.");
+                return Step::new("Hector", variables.to_vec(), vec![]);
+            }
+            1 => {
+                let propose = match bindings[0] {
+                    Binding::Attribute(ref binding) => {
+                        vec![self.index(&binding.source_attribute, Index::ForwardPropose)]
+                    }
+                    _ => {
+                        self.warn("Passed a single, non-sourceable binding.");
+                        vec![]
+                    }
+                };
+
+                return Step::new("Hector", variables.to_vec(), propose);
+            }
+            _ => {}
+        }
+
+        let mut pipelines = Vec::new();
+
+        for (idx, delta_binding) in bindings.iter().enumerate() {
+            let delta_binding = match delta_binding {
+                Binding::Attribute(binding) => binding,
+                _ => continue,
+            };
+
+            let source = self.index(&delta_binding.source_attribute, Index::ForwardPropose);
+            let (order, _) = plan_order(idx, bindings);
+
+            let mut prefix = Vec::with_capacity(order.len());
+            let conflicts = source_conflicts(idx, bindings);
+            match conflicts.len() {
+                0 => {
+                    prefix.push(delta_binding.variables.0);
+                    prefix.push(delta_binding.variables.1);
+                }
+                1 => match conflicts[0] {
+                    Binding::Constant(constant) => {
+                        prefix.push(constant.variable);
+                        match direction(&prefix, delta_binding.variables) {
+                            Ok(Direction::Forward(_)) => prefix.push(delta_binding.variables.1),
+                            Ok(Direction::Reverse(_)) => prefix.push(delta_binding.variables.0),
+                            Err(msg) => self.warn(msg),
+                        }
+                    }
+                    other => {
+                        self.warn(format!("Can't resolve conflicts on {:?} bindings.", other));
+                        continue;
+                    }
+                },
+                _ => {
+                    self.warn(format!(
+                        "Delta pipeline for {:?} conflicts with more than one binding.",
+                        delta_binding
+                    ));
+                    continue;
+                }
+            }
+
+            let mut extensions = Vec::new();
+
+            for target in order.iter() {
+                if AsBinding::binds(&prefix, *target).is_some() {
+                    continue;
+                }
+
+                // Antijoin bindings wrap the extender of the binding
+                // they negate, which is enqueued right before them.
+                let mut candidates = VecDeque::new();
+                for (other_idx, binding) in bindings.iter().enumerate() {
+                    if let Binding::Not(ref antijoin_binding) = binding {
+                        candidates.push_back((other_idx, (*antijoin_binding.binding).clone()));
+                    }
+                    candidates.push_back((other_idx, binding.clone()));
+                }
+
+                let mut extenders = 0;
+                let mut arrangements = Vec::new();
+
+                while let Some((other_idx, other)) = candidates.pop_front() {
+                    if other_idx == idx
+                        || other.binds(*target).is_none()
+                        || !other.can_extend(&prefix, *target)
+                    {
+                        continue;
+                    }
+
+                    match other {
+                        Binding::Not(_) => {}
+                        Binding::Constant(_) | Binding::BinaryPredicate(_) => extenders += 1,
+                        Binding::Attribute(other) => {
+                            let aid = &other.source_attribute;
+                            match direction(&prefix, other.variables) {
+                                Err(msg) => self.warn(msg),
+                                Ok(Direction::Forward(_)) => {
+                                    arrangements.push(self.index(aid, Index::ForwardCount));
+                                    arrangements.push(self.index(aid, Index::ForwardPropose));
+                                    arrangements.push(self.index(aid, Index::ForwardValidate));
+                                }
+                                Ok(Direction::Reverse(_)) => {
+                                    arrangements.push(self.index(aid, Index::ReverseCount));
+                                    arrangements.push(self.index(aid, Index::ReversePropose));
+                                    arrangements.push(self.index(aid, Index::ReverseValidate));
+                                }
+                            }
+
+                            extenders += 1;
+                        }
+                    }
+                }
+
+                if extenders == 0 {
+                    self.warn(format!(
+                        "No binding can extend prefix {:?} to variable {}.",
+                        prefix, target
+                    ));
+                }
+
+                prefix.push(*target);
+                extensions.push(Step::new("Extend", prefix.clone(), arrangements));
+            }
+
+            pipelines.push(Step::new("Delta", prefix, vec![source]).with_inputs(extensions));
+        }
+
+        Step::new("Hector", variables.to_vec(), vec![]).with_inputs(pipelines)
+    }
+}
@@ -38,6 +38,32 @@ fn eq(a: &Value, b: &Value) -> bool {
 fn neq(a: &Value, b: &Value) -> bool {
     a != b
 }
+#[inline(always)]
+fn contains(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => a.contains(b.as_str()),
+        _ => false,
+    }
+}
+#[inline(always)]
+fn starts_with(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => a.starts_with(b.as_str()),
+        _ => false,
+    }
+}
+#[inline(always)]
+fn before(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Instant(a), Value::Instant(b)) => a < b,
+        (Value::Date(a), Value::Date(b)) => a < b,
+        _ => false,
+    }
+}
+#[inline(always)]
+fn after(a: &Value, b: &Value) -> bool {
+    before(b, a)
+}
 
 /// A plan stage filtering source tuples by the specified
 /// predicate. Frontends are responsible for ensuring that the source
@@ -100,6 +126,10 @@ impl<P: Implementable> Implementable for Filter<P> {
             Predicate::GTE => gte,
             Predicate::EQ => eq,
             Predicate::NEQ => neq,
+            Predicate::CONTAINS => contains,
+            Predicate::STARTS_WITH => starts_with,
+            Predicate::BEFORE => before,
+            Predicate::AFTER => after,
         };
 
         let variables = relation.variables();
@@ -0,0 +1,104 @@
+//! Full-text search plan over tokenized string attributes.
+
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::Reduce;
+
+use crate::domain::Domain;
+use crate::plan::{Dependencies, Implementable};
+use crate::timestamp::Rewind;
+use crate::{AsAid, Var};
+use crate::{CollectionRelation, Implemented, ShutdownHandle, VariableMap};
+
+/// Splits text into the distinct, lowercased alphanumeric runs it
+/// consists of. Both indexed values and queries are tokenized this
+/// way, s.t. matching ignores case and punctuation.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_lowercase())
+        .collect();
+
+    tokens.sort();
+    tokens.dedup();
+
+    tokens
+}
+
+/// A plan stage binding [e v] for all string values of an attribute
+/// that contain every token of the query, in any order. Queries
+/// without any tokens match nothing.
+///
+/// Only attributes configured with a fulltext index can be searched.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Fulltext<A: AsAid> {
+    /// Variables to bind e and v to, in that order.
+    pub variables: Vec<Var>,
+    /// Attribute to search.
+    pub attribute: A,
+    /// Text whose tokens values must contain.
+    pub query: String,
+}
+
+impl<A: AsAid> Implementable for Fulltext<A> {
+    type A = A;
+
+    fn dependencies(&self) -> Dependencies<A> {
+        Dependencies::attribute(self.attribute.clone())
+    }
+
+    fn implement<'b, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        domain: &mut Domain<A, S::Timestamp>,
+        _local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
+    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+    where
+        S: Scope,
+        S::Timestamp: Timestamp + Lattice + Rewind,
+    {
+        assert_eq!(self.variables.len(), 2);
+
+        let a = &self.attribute;
+        let query = tokenize(&self.query);
+
+        let (tuples, shutdown_fulltext) = match domain.fulltext(a) {
+            None => panic!("attribute {:?} has no fulltext index", a),
+            Some(fulltext_trace) => {
+                let (fulltext, shutdown_fulltext) =
+                    fulltext_trace.import_frontier(&nested.parent, &format!("Fulltext({:?})", a));
+
+                let required = query.len();
+
+                // Values hold each of their tokens once, so a value
+                // matches if it was found under all query tokens.
+                let tuples = fulltext
+                    .enter(nested)
+                    .filter(move |token, _ev| query.contains(token))
+                    .as_collection(|token, (e, v)| (vec![e.clone(), v.clone()], token.clone()))
+                    .reduce(move |_ev, tokens, output| {
+                        if tokens.len() == required {
+                            output.push(((), tokens[0].1));
+                        }
+                    })
+                    .map(|(ev, ())| ev);
+
+                (tuples, shutdown_fulltext)
+            }
+        };
+
+        let relation = CollectionRelation {
+            variables: self.variables.clone(),
+            tuples,
+        };
+
+        (
+            Implemented::Collection(relation),
+            ShutdownHandle::from_button(shutdown_fulltext),
+        )
+    }
+}
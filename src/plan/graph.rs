@@ -0,0 +1,238 @@
+//! Graph algorithm plan, over edges stored as entities.
+
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::Join as JoinMap;
+use differential_dataflow::operators::{Iterate, Reduce, Threshold};
+
+use crate::domain::Domain;
+use crate::plan::{Dependencies, Implementable, Join, Plan, Project};
+use crate::timestamp::Rewind;
+use crate::VariableMap;
+use crate::{AsAid, CollectionRelation, Implemented, Relation, ShutdownHandle, Value, Var};
+
+/// Ranks are fixed-point numbers in millionths, s.t. results are
+/// exact and identical across workers.
+const RANK_SCALE: i64 = 1_000_000;
+
+/// Percentage of each rank passed on along outgoing edges.
+const DAMPING: i64 = 85;
+
+/// Maximum number of PageRank rounds, as each of them adds its own
+/// join and reduction to the dataflow.
+pub const MAX_ITERATIONS: usize = 100;
+
+/// Permitted graph algorithms.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum GraphAlgorithm {
+    /// Binds the length of the shortest path from the source to each
+    /// node reachable from it.
+    ShortestPaths {
+        /// Node to measure distances from.
+        source: Value,
+    },
+    /// Binds the smallest node connected to each node, ignoring edge
+    /// directions, s.t. nodes share labels iff they are connected.
+    ConnectedComponents,
+    /// Binds the PageRank of each node after a fixed number of
+    /// rounds, in millionths. Ranks are passed on in proportion to
+    /// edge weights.
+    PageRank {
+        /// Number of rounds to compute, at most `MAX_ITERATIONS`.
+        iterations: usize,
+    },
+}
+
+/// A plan stage running a graph algorithm over edges stored as
+/// entities, with references to their source and target nodes and,
+/// optionally, a numeric weight. Binds [node result] for each node.
+///
+/// Results are maintained incrementally as edges change. Unweighted
+/// edges count as one, edges lacking their weight or carrying a
+/// negative one are ignored.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Graph<A: AsAid> {
+    /// Variables to bind nodes and their results to, in that order.
+    pub variables: Vec<Var>,
+    /// Attribute referring from edges to their source nodes.
+    pub source: A,
+    /// Attribute referring from edges to their target nodes.
+    pub target: A,
+    /// Attribute holding the weight of each edge, if any.
+    pub weight: Option<A>,
+    /// Algorithm to run.
+    pub algorithm: GraphAlgorithm,
+}
+
+impl<A: AsAid> Graph<A> {
+    /// Returns a plan binding the source, target, and weight of each
+    /// edge, in that order, omitting the weight if there is none.
+    pub fn edges(&self) -> Plan<A> {
+        let (edge, source, target, weight) = (0, 1, 2, 3);
+
+        let mut plan = Plan::Join(Join {
+            variables: vec![edge],
+            left_plan: Box::new(Plan::MatchA(edge, self.source.clone(), source)),
+            right_plan: Box::new(Plan::MatchA(edge, self.target.clone(), target)),
+        });
+
+        if let Some(ref attribute) = self.weight {
+            plan = Plan::Join(Join {
+                variables: vec![edge],
+                left_plan: Box::new(plan),
+                right_plan: Box::new(Plan::MatchA(edge, attribute.clone(), weight)),
+            });
+        }
+
+        let variables = match self.weight {
+            None => vec![source, target],
+            Some(_) => vec![source, target, weight],
+        };
+
+        Plan::Project(Project {
+            variables,
+            plan: Box::new(plan),
+        })
+    }
+}
+
+impl<A: AsAid> Implementable for Graph<A> {
+    type A = A;
+
+    fn dependencies(&self) -> Dependencies<A> {
+        self.edges().dependencies()
+    }
+
+    fn implement<'b, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        domain: &mut Domain<A, S::Timestamp>,
+        local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
+    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+    where
+        S: Scope,
+        S::Timestamp: Timestamp + Lattice + Rewind,
+    {
+        assert_eq!(self.variables.len(), 2);
+
+        let plan = self.edges();
+        let (relation, mut shutdown_handle) = plan.implement(nested, domain, local_arrangements);
+
+        let edges = {
+            let (projected, shutdown) = relation.projected(nested, domain, &plan.variables());
+            shutdown_handle.merge_with(shutdown);
+
+            projected.flat_map(|tuple| {
+                let weight = match tuple.get(2) {
+                    None => 1,
+                    Some(Value::Number(weight)) if *weight >= 0 => *weight,
+                    Some(_) => return None,
+                };
+
+                Some((tuple[0].clone(), (tuple[1].clone(), weight)))
+            })
+        };
+
+        let nodes = edges
+            .flat_map(|(source, (target, _weight))| vec![source, target])
+            .distinct();
+
+        let tuples = match self.algorithm {
+            GraphAlgorithm::ShortestPaths { ref source } => {
+                let source = source.clone();
+                let roots = nodes
+                    .filter(move |node| *node == source)
+                    .map(|node| (node, 0));
+
+                // Distances only ever shrink, so this converges as
+                // long as weights are non-negative. Those too long to
+                // represent are capped.
+                roots
+                    .iterate(|distances| {
+                        let edges = edges.enter(&distances.scope());
+                        let roots = roots.enter(&distances.scope());
+
+                        distances
+                            .join_map(&edges, |_node, distance, (target, weight)| {
+                                (target.clone(), distance.saturating_add(*weight))
+                            })
+                            .concat(&roots)
+                            .reduce(|_node, distances, output| output.push((*distances[0].0, 1)))
+                    })
+                    .map(|(node, distance)| vec![node, Value::Number(distance)])
+            }
+            GraphAlgorithm::ConnectedComponents => {
+                let links = edges.flat_map(|(source, (target, _weight))| {
+                    vec![(source.clone(), target.clone()), (target, source)]
+                });
+                let labels = nodes.map(|node| (node.clone(), node));
+
+                labels
+                    .iterate(|inner| {
+                        let links = links.enter(&inner.scope());
+                        let labels = labels.enter(&inner.scope());
+
+                        inner
+                            .join_map(&links, |_node, label, neighbour| {
+                                (neighbour.clone(), label.clone())
+                            })
+                            .concat(&labels)
+                            .reduce(|_node, labels, output| output.push((labels[0].0.clone(), 1)))
+                    })
+                    .map(|(node, label)| vec![node, label])
+            }
+            GraphAlgorithm::PageRank { iterations } => {
+                // Totals too large to represent are capped, which
+                // still leaves each share no larger than its rank.
+                let totals = edges
+                    .map(|(source, (_target, weight))| (source, weight))
+                    .reduce(|_source, weights, output| {
+                        let total = weights.iter().fold(0i64, |total, (weight, count)| {
+                            total.saturating_add(weight.saturating_mul(*count as i64))
+                        });
+                        output.push((total, 1));
+                    });
+
+                let shares = edges.join_map(&totals, |source, (target, weight), total| {
+                    (source.clone(), (target.clone(), *weight, *total))
+                });
+
+                // Nodes without incoming edges keep a rank as well.
+                let unranked = nodes.map(|node| (node, 0));
+
+                // Rounds are unrolled, as their number is fixed.
+                let mut ranks = nodes.map(|node| (node, RANK_SCALE));
+                for _round in 0..iterations {
+                    ranks = ranks
+                        .join_map(&shares, |_source, rank, (target, weight, total)| {
+                            // Heavy edges would overflow the product.
+                            let share = i128::from(*rank) * i128::from(*weight)
+                                / i128::from(std::cmp::max(*total, 1));
+                            (target.clone(), share as i64)
+                        })
+                        .concat(&unranked)
+                        .reduce(|_node, shares, output| {
+                            let received = shares.iter().fold(0i64, |received, (share, count)| {
+                                received.saturating_add(share.saturating_mul(*count as i64))
+                            });
+                            let reset = RANK_SCALE * (100 - DAMPING) / 100;
+                            let passed = i128::from(received) * i128::from(DAMPING) / 100;
+                            output.push((reset.saturating_add(passed as i64), 1));
+                        });
+                }
+
+                ranks.map(|(node, rank)| vec![node, Value::Number(rank)])
+            }
+        };
+
+        let relation = CollectionRelation {
+            variables: self.variables.clone(),
+            tuples,
+        };
+
+        (Implemented::Collection(relation), shutdown_handle)
+    }
+}
@@ -1,4 +1,10 @@
 //! GraphQL expression plan.
+//!
+//! Attributes are exposed as fields of a single `Entity` type, named
+//! as by `field_name`. Fields of entity type follow references,
+//! arguments restrict entities to those holding the specified values.
+
+use std::collections::HashMap;
 
 use timely::dataflow::scopes::child::Iterative;
 use timely::dataflow::Scope;
@@ -15,7 +21,7 @@ use crate::domain::Domain;
 use crate::plan::{gensym, Dependencies, Implementable};
 use crate::plan::{Hector, Plan, Pull, PullAll, PullLevel};
 use crate::timestamp::Rewind;
-use crate::{AsAid, Var};
+use crate::{AsAid, AttributeConfig, Error, ValueType, Var};
 use crate::{Implemented, ShutdownHandle, VariableMap};
 
 /// A plan for GraphQL queries, e.g. `{ Heroes { name age weight } }`.
@@ -54,6 +60,186 @@ impl<A: AsAid + From<String>> GraphQl<A> {
 
         GraphQl { query, paths }
     }
+
+    /// Compiles a GraphQL operation against the specified attributes,
+    /// resolving field names to the attributes they expose. Fails for
+    /// unknown fields, as well as for anything but a single query or
+    /// subscription.
+    pub fn compile(
+        query: String,
+        attributes: &[(A, AttributeConfig)],
+    ) -> Result<(OperationKind, Self), Error> {
+        let fields: HashMap<String, A> = fields(attributes)
+            .into_iter()
+            .map(|(field, (name, _config))| (field, name.clone()))
+            .collect();
+
+        let mut ast = parse_query(&query).map_err(Error::incorrect)?;
+
+        if ast.definitions.len() != 1 {
+            return Err(Error::unsupported(
+                "GraphQL documents must contain exactly one operation.",
+            ));
+        }
+
+        let kind = match ast.definitions[0] {
+            Definition::Operation(OperationDefinition::SelectionSet(ref mut selection_set)) => {
+                resolve(selection_set, &fields)?;
+                OperationKind::Query
+            }
+            Definition::Operation(OperationDefinition::Query(ref mut query)) => {
+                resolve(&mut query.selection_set, &fields)?;
+                OperationKind::Query
+            }
+            Definition::Operation(OperationDefinition::Subscription(ref mut subscription)) => {
+                resolve(&mut subscription.selection_set, &fields)?;
+                OperationKind::Subscription
+            }
+            Definition::Operation(OperationDefinition::Mutation(_)) => {
+                return Err(Error::unsupported(
+                    "GraphQL mutations are not supported, use Transact requests instead.",
+                ));
+            }
+            Definition::Fragment(_) => {
+                return Err(Error::unsupported("GraphQL fragments are not supported."));
+            }
+        };
+
+        let empty_plan = Hector {
+            variables: vec![0],
+            bindings: vec![],
+        };
+
+        let paths = ast.into_paths(empty_plan);
+
+        Ok((kind, GraphQl { query, paths }))
+    }
+}
+
+/// The kinds of GraphQL operations that can be compiled.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum OperationKind {
+    /// Evaluated once, against the current state.
+    Query,
+    /// Evaluated continuously, streaming changes to its results.
+    Subscription,
+}
+
+/// Returns the GraphQL field name an attribute is exposed as, if it
+/// can be exposed at all. Leading colons are dropped and namespace
+/// separators, dots, and dashes become underscores, e.g.
+/// `:person/first-name` is exposed as `person_first_name`.
+pub fn field_name(name: &str) -> Option<String> {
+    let field: String = name
+        .trim_start_matches(':')
+        .chars()
+        .map(|c| match c {
+            '/' | '.' | '-' => '_',
+            c => c,
+        })
+        .collect();
+
+    let is_valid = match field.chars().next() {
+        None => false,
+        Some(first) => {
+            (first == '_' || first.is_ascii_alphabetic())
+                && field.chars().all(|c| c == '_' || c.is_ascii_alphanumeric())
+        }
+    };
+
+    if is_valid {
+        Some(field)
+    } else {
+        None
+    }
+}
+
+/// Assigns field names to attributes. Should two attributes map to
+/// the same field, the first one in the given order wins.
+fn fields<A: AsAid>(attributes: &[(A, AttributeConfig)]) -> Vec<(String, &(A, AttributeConfig))> {
+    let mut fields: Vec<(String, &(A, AttributeConfig))> = Vec::new();
+
+    for attribute in attributes.iter() {
+        if let Some(field) = field_name(&attribute.0.to_string()) {
+            if fields.iter().all(|(other, _)| other != &field) {
+                fields.push((field, attribute));
+            }
+        }
+    }
+
+    fields
+}
+
+/// Generates a GraphQL schema exposing the specified attributes, for
+/// use by GraphQL tooling. Attributes holding entity ids are typed as
+/// lists of entities, those of unknown or other types as `Value`.
+pub fn schema<A: AsAid>(attributes: &[(A, AttributeConfig)]) -> String {
+    let mut schema = String::from(
+        "schema {\n  query: Entity\n  subscription: Entity\n}\n\nscalar Value\n\ntype Entity {\n",
+    );
+
+    for (field, (name, config)) in fields(attributes).into_iter() {
+        let field_type = match config.value_type {
+            Some(ValueType::Eid) => "[Entity]",
+            Some(ValueType::String) => "String",
+            Some(ValueType::Number) => "Int",
+            Some(ValueType::Bool) => "Boolean",
+            Some(ValueType::Rational32) | Some(ValueType::Decimal) => "Float",
+            Some(ValueType::Aid) | Some(ValueType::Uuid) => "ID",
+            _ => "Value",
+        };
+
+        let description = name.to_string().replace('\\', "\\\\").replace('"', "\\\"");
+
+        schema.push_str(&format!(
+            "  \"{}\"\n  {}: {}\n",
+            description, field, field_type
+        ));
+    }
+
+    schema.push_str("}\n");
+    schema
+}
+
+/// Replaces field and argument names by the names of the attributes
+/// they expose.
+fn resolve<A: AsAid>(
+    selection_set: &mut SelectionSet,
+    fields: &HashMap<String, A>,
+) -> Result<(), Error> {
+    let lookup = |field: &str| {
+        fields
+            .get(field)
+            .map(|name| name.to_string())
+            .ok_or_else(|| Error::not_found(format!("Unknown field {}.", field)))
+    };
+
+    for item in selection_set.items.iter_mut() {
+        match item {
+            Selection::Field(field) => {
+                field.name = lookup(&field.name)?;
+
+                for (name, value) in field.arguments.iter_mut() {
+                    *name = lookup(name)?;
+
+                    match value {
+                        Value::Int(_) | Value::String(_) | Value::Boolean(_) => {}
+                        other => {
+                            return Err(Error::unsupported(format!(
+                                "Unsupported argument {:?}.",
+                                other
+                            )));
+                        }
+                    }
+                }
+
+                resolve(&mut field.selection_set, fields)?;
+            }
+            _ => return Err(Error::unsupported("GraphQL fragments are not supported.")),
+        }
+    }
+
+    Ok(())
 }
 
 trait IntoPaths {
@@ -98,13 +284,16 @@ impl IntoPaths for Definition {
 
 impl IntoPaths for OperationDefinition {
     fn into_paths<A: AsAid + From<String>>(&self, root_plan: Hector<A>) -> Vec<Plan<A>> {
-        use OperationDefinition::{Query, SelectionSet};
+        use OperationDefinition::{Query, SelectionSet, Subscription};
 
         match self {
-            Query(_) => unimplemented!(),
             SelectionSet(selection_set) => {
                 selection_set_to_paths(&selection_set, root_plan, &[], &[])
             }
+            Query(query) => selection_set_to_paths(&query.selection_set, root_plan, &[], &[]),
+            Subscription(subscription) => {
+                selection_set_to_paths(&subscription.selection_set, root_plan, &[], &[])
+            }
             _ => unimplemented!(),
         }
     }
@@ -133,12 +133,15 @@ pub struct Hector<A: AsAid> {
     pub bindings: Vec<Binding<A>>,
 }
 
-enum Direction {
+pub(crate) enum Direction {
     Forward(usize),
     Reverse(usize),
 }
 
-fn direction<P>(prefix: &P, extender_variables: (Var, Var)) -> Result<Direction, &'static str>
+pub(crate) fn direction<P>(
+    prefix: &P,
+    extender_variables: (Var, Var),
+) -> Result<Direction, &'static str>
 where
     P: AsBinding + std::fmt::Debug,
 {
@@ -1052,7 +1055,9 @@ where
     }
 
     fn validate(&mut self, extensions: &Collection<S, (P, V)>) -> Collection<S, (P, V)> {
-        use self::BinaryPredicate::{EQ, GT, GTE, LT, LTE, NEQ};
+        use self::BinaryPredicate::{
+            AFTER, BEFORE, CONTAINS, EQ, GT, GTE, LT, LTE, NEQ, STARTS_WITH,
+        };
         match self.direction {
             Direction::Reverse(offset) => {
                 match self.predicate {
@@ -1068,6 +1073,12 @@ where
                         .filter(move |(prefix, extension)| *extension == prefix.index(offset)),
                     NEQ => extensions
                         .filter(move |(prefix, extension)| *extension != prefix.index(offset)),
+                    CONTAINS | STARTS_WITH => {
+                        panic!("String predicates can't be implemented via Hector.")
+                    }
+                    BEFORE | AFTER => {
+                        panic!("Temporal predicates can't be implemented via Hector.")
+                    }
                 }
             }
             Direction::Forward(offset) => {
@@ -1084,6 +1095,12 @@ where
                         .filter(move |(prefix, extension)| *extension == prefix.index(offset)),
                     NEQ => extensions
                         .filter(move |(prefix, extension)| *extension != prefix.index(offset)),
+                    CONTAINS | STARTS_WITH => {
+                        panic!("String predicates can't be implemented via Hector.")
+                    }
+                    BEFORE | AFTER => {
+                        panic!("Temporal predicates can't be implemented via Hector.")
+                    }
                 }
             }
         }
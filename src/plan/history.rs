@@ -0,0 +1,94 @@
+//! History plan, exposing all changes made to an attribute.
+
+use timely::dataflow::operators::Map;
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::order::Product;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::AsCollection;
+
+use crate::domain::Domain;
+use crate::plan::{Dependencies, Implementable};
+use crate::timestamp::{Rewind, Time};
+use crate::{AsAid, Value, Var};
+use crate::{CollectionRelation, Implemented, ShutdownHandle, VariableMap};
+
+/// A plan stage binding [e v t diff] for every change ever made to an
+/// attribute, rather than just its consolidated present. Changes are
+/// revealed at the times they happened, s.t. consumers can
+/// restrict themselves to specific intervals by filtering on t.
+///
+/// Histories are only complete for attributes whose traces aren't
+/// compacted, i.e. attributes configured without trace slack.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct History<A: AsAid> {
+    /// Variables to bind e, v, t, and diff to, in that order.
+    pub variables: Vec<Var>,
+    /// Attribute whose history to expose.
+    pub attribute: A,
+}
+
+impl<A: AsAid> Implementable for History<A> {
+    type A = A;
+
+    fn dependencies(&self) -> Dependencies<A> {
+        Dependencies::attribute(self.attribute.clone())
+    }
+
+    fn implement<'b, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        domain: &mut Domain<A, S::Timestamp>,
+        _local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
+    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+    where
+        S: Scope,
+        S::Timestamp: Timestamp + Lattice + Rewind,
+    {
+        assert_eq!(self.variables.len(), 4);
+
+        let a = &self.attribute;
+
+        if !domain.keeps_history(a) {
+            panic!("attribute {:?} does not keep its history", a);
+        }
+
+        let (tuples, shutdown_propose) = match domain.forward_propose(a) {
+            None => panic!("attribute {:?} does not exist", a),
+            Some(propose_trace) => {
+                let (propose, shutdown_propose) =
+                    propose_trace.import_frontier(&nested.parent, &format!("History({:?})", a));
+
+                let tuples = propose
+                    .enter(nested)
+                    .as_collection(|e, v| vec![e.clone(), v.clone()])
+                    .inner
+                    .map(
+                        |(mut tuple, t, diff): (Vec<Value>, Product<S::Timestamp, u64>, isize)| {
+                            let time: Time = t.outer.clone().into();
+
+                            tuple.push(time.into());
+                            tuple.push(Value::Number(diff as i64));
+
+                            (tuple, t, 1)
+                        },
+                    )
+                    .as_collection();
+
+                (tuples, shutdown_propose)
+            }
+        };
+
+        let relation = CollectionRelation {
+            variables: self.variables.clone(),
+            tuples,
+        };
+
+        (
+            Implemented::Collection(relation),
+            ShutdownHandle::from_button(shutdown_propose),
+        )
+    }
+}
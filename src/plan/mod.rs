@@ -1,6 +1,6 @@
 //! Types and traits for implementing query plans.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::ops::Deref;
 use std::sync::atomic::{self, AtomicUsize};
 
@@ -8,12 +8,18 @@ use timely::dataflow::scopes::child::Iterative;
 use timely::dataflow::Scope;
 use timely::progress::Timestamp;
 
+use differential_dataflow::collection::Collection;
 use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::Threshold;
+
+use num_rational::Rational32;
 
 use crate::binding::{AsBinding, AttributeBinding, Binding};
+use crate::decimal::is_decimal;
 use crate::domain::Domain;
+use crate::plan::sharing::children_mut;
 use crate::timestamp::Rewind;
-use crate::{AsAid, Eid, Value, Var};
+use crate::{AsAid, Eid, Error, Semantics, Value, ValueType, Var};
 use crate::{CollectionRelation, Implemented, Relation, ShutdownHandle, VariableMap};
 
 #[cfg(feature = "set-semantics")]
@@ -21,33 +27,58 @@ pub mod aggregate;
 #[cfg(not(feature = "set-semantics"))]
 pub mod aggregate_neu;
 pub mod antijoin;
+pub mod builder;
+pub mod datalog;
+pub mod event_time;
+pub mod explain;
 pub mod filter;
+pub mod fulltext;
 #[cfg(feature = "graphql")]
 pub mod graphql;
 // #[cfg(feature = "graphql")]
 // pub mod graphql_v2;
+pub mod graph;
 pub mod hector;
+pub mod history;
 pub mod join;
+pub mod ordering;
 pub mod project;
 pub mod pull;
+pub mod range;
 // pub mod pull_v2;
+pub mod session;
+pub mod sharing;
+pub mod top_k;
 pub mod transform;
+pub mod transitive_closure;
 pub mod union;
+pub mod validation;
+pub mod window;
 
 #[cfg(feature = "set-semantics")]
 pub use self::aggregate::{Aggregate, AggregationFn};
 #[cfg(not(feature = "set-semantics"))]
 pub use self::aggregate_neu::{Aggregate, AggregationFn};
 pub use self::antijoin::Antijoin;
+pub use self::builder::{QueryBuilder, Term};
+pub use self::event_time::EventWindow;
 pub use self::filter::{Filter, Predicate};
+pub use self::fulltext::Fulltext;
+pub use self::graph::{Graph, GraphAlgorithm};
 #[cfg(feature = "graphql")]
 pub use self::graphql::GraphQl;
 pub use self::hector::Hector;
+pub use self::history::History;
 pub use self::join::Join;
 pub use self::project::Project;
-pub use self::pull::{Pull, PullAll, PullLevel};
+pub use self::pull::{Pull, PullAll, PullLevel, PullPattern};
+pub use self::range::Range;
+pub use self::session::Session;
+pub use self::top_k::TopK;
 pub use self::transform::{Function, Transform};
+pub use self::transitive_closure::TransitiveClosure;
 pub use self::union::Union;
+pub use self::window::Window;
 
 static SYM: AtomicUsize = AtomicUsize::new(std::usize::MAX);
 
@@ -56,6 +87,31 @@ pub fn gensym() -> Var {
     SYM.fetch_sub(1, atomic::Ordering::SeqCst) as Var
 }
 
+/// Returns the 1-based nearest rank of quantile `q` among `total`
+/// sorted values.
+pub(crate) fn nearest_rank(q: Rational32, total: isize) -> isize {
+    let numer = *q.numer() as isize;
+    let denom = *q.denom() as isize;
+    let rank = (numer * total + denom - 1) / denom;
+
+    std::cmp::max(1, std::cmp::min(rank, total))
+}
+
+/// Returns the keys of all groups holding decimal values, which are
+/// aggregated exactly as decimals, rather than as differences.
+pub(crate) fn decimal_keys<S>(
+    prepared: &Collection<S, (Vec<Value>, Vec<Value>), isize>,
+) -> Collection<S, Vec<Value>, isize>
+where
+    S: Scope,
+    S::Timestamp: Lattice + Ord,
+{
+    prepared
+        .filter(|(_key, val)| is_decimal(&val[0]))
+        .map(|(key, _val)| key)
+        .distinct()
+}
+
 /// Description of everything a plan needs prior to synthesis.
 pub struct Dependencies<A: AsAid> {
     /// NameExpr's used by this plan.
@@ -149,6 +205,69 @@ pub trait Implementable {
         S::Timestamp: Timestamp + Lattice + Rewind;
 }
 
+/// Returns the types of all arguments to a predicate or function in
+/// positional order, i.e. constants interleaved with the variables
+/// bound to the remaining positions.
+fn argument_types(
+    variables: &[Var],
+    constants: &[Option<Value>],
+    types: &HashMap<Var, ValueType>,
+) -> Vec<Option<ValueType>> {
+    let mut variables = variables.iter();
+    let mut arguments: Vec<Option<ValueType>> = constants
+        .iter()
+        .map(|constant| match constant {
+            Some(constant) => constant.value_type(),
+            None => variables.next().and_then(|v| types.get(v).cloned()),
+        })
+        .collect();
+
+    arguments.extend(variables.map(|v| types.get(v).cloned()));
+
+    arguments
+}
+
+/// Ensures that all arguments of known type are of the expected type.
+fn expect_type<F: std::fmt::Debug>(
+    function: &F,
+    arguments: &[Option<ValueType>],
+    expected: ValueType,
+) -> Result<(), Error> {
+    for argument in arguments.iter() {
+        if let Some(value_type) = argument {
+            if *value_type != expected {
+                return Err(Error::incorrect(format!(
+                    "{:?} expects arguments of type {:?}, not {:?}.",
+                    function, expected, value_type
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the attribute navigated by a reverse reference attribute,
+/// e.g. `:parent/children` for `:parent/_children`, or None if the
+/// attribute is not a reverse reference.
+pub fn reverse_attribute<A: AsAid>(aid: &A) -> Option<A> {
+    let name = aid.to_string();
+    let offset = match name.rfind('/') {
+        Some(offset) => offset + 1,
+        None if name.starts_with(':') => 1,
+        None => 0,
+    };
+
+    if name[offset..].starts_with('_') {
+        let mut forward = name[..offset].to_string();
+        forward.push_str(&name[offset + 1..]);
+
+        Some(A::from(forward))
+    } else {
+        None
+    }
+}
+
 /// Possible query plan types.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub enum Plan<A: AsAid> {
@@ -178,12 +297,32 @@ pub enum Plan<A: AsAid> {
     MatchAV(Var, A, Value),
     /// Sources data from another relation.
     NameExpr(Vec<Var>, A),
+    /// Sources tuples bound to a query parameter.
+    Parameter(Vec<Var>, A),
     /// Pull expression
     Pull(Pull<Plan<A>>),
     /// Single-level pull expression
     PullLevel(PullLevel<A, Plan<A>>),
     /// Single-level pull expression
     PullAll(PullAll<A>),
+    /// Full history of an attribute
+    History(History<A>),
+    /// Restricts changes to an event-time interval
+    EventWindow(EventWindow<Plan<A>>),
+    /// Searches the tokens of a string attribute
+    Fulltext(Fulltext<A>),
+    /// Scans the values of an attribute within bounds
+    Range(Range<A>),
+    /// Keeps the first few tuples of each group
+    TopK(TopK<Plan<A>>),
+    /// Assigns tuples to sessions of events
+    Session(Session<Plan<A>>),
+    /// Assigns tuples to tumbling or hopping windows
+    Window(Window<Plan<A>>),
+    /// Finds all entities reachable along a reference attribute
+    TransitiveClosure(TransitiveClosure<A>),
+    /// Runs a graph algorithm over weighted edges
+    Graph(Graph<A>),
     /// GraphQl pull expression
     #[cfg(feature = "graphql")]
     GraphQl(GraphQl<A>),
@@ -205,15 +344,491 @@ impl<A: AsAid> Plan<A> {
         Plan::MatchAV(e, a.into(), v.into())
     }
 
+    /// Rewrites data patterns navigating a reference attribute in
+    /// reverse, e.g. `[?child :parent/_children ?parent]`, into the
+    /// equivalent pattern on the forward attribute, e.g. `[?parent
+    /// :parent/children ?child]`. Returns None for all other plans.
+    fn reversed(&self) -> Option<Plan<A>> {
+        match *self {
+            Plan::MatchA(e, ref a, v) => reverse_attribute(a).map(|a| Plan::MatchA(v, a, e)),
+            Plan::MatchEA(e, ref a, v) => {
+                reverse_attribute(a).map(|a| Plan::MatchAV(v, a, Value::Eid(e)))
+            }
+            Plan::MatchAV(e, ref a, Value::Eid(v)) => {
+                reverse_attribute(a).map(|a| Plan::MatchEA(v, a, e))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns a copy of this plan, whose unions and antijoins keep
+    /// multiplicities as required by the specified rule semantics,
+    /// unless they specify semantics of their own.
+    pub(crate) fn with_semantics(&self, semantics: Option<Semantics>) -> Plan<A> {
+        let mut plan = self.clone();
+        if let Some(semantics) = semantics {
+            plan.fill_semantics(semantics);
+        }
+
+        plan
+    }
+
+    fn fill_semantics(&mut self, semantics: Semantics) {
+        match *self {
+            Plan::Union(ref mut union) => {
+                union.semantics.get_or_insert(semantics);
+            }
+            Plan::Antijoin(ref mut antijoin) => {
+                antijoin.semantics.get_or_insert(semantics);
+            }
+            _ => {}
+        }
+
+        for child in children_mut(self) {
+            child.fill_semantics(semantics);
+        }
+    }
+
+    /// Returns the names of all relations this plan refers to from
+    /// within a negation, i.e. from the body of a Negate or the
+    /// right-hand side of an Antijoin.
+    pub fn negated_names(&self) -> HashSet<A> {
+        match *self {
+            Plan::Project(ref projection) => projection.plan.negated_names(),
+            Plan::Aggregate(ref aggregate) => aggregate.plan.negated_names(),
+            Plan::Union(ref union) => union
+                .plans
+                .iter()
+                .flat_map(|plan| plan.negated_names().into_iter())
+                .collect(),
+            Plan::Join(ref join) => {
+                let mut names = join.left_plan.negated_names();
+                names.extend(join.right_plan.negated_names().into_iter());
+                names
+            }
+            Plan::Antijoin(ref antijoin) => {
+                let mut names = antijoin.left_plan.negated_names();
+                names.extend(antijoin.right_plan.dependencies().names.into_iter());
+                names
+            }
+            Plan::Negate(ref plan) => plan.dependencies().names,
+            Plan::Filter(ref filter) => filter.plan.negated_names(),
+            Plan::Transform(ref transform) => transform.plan.negated_names(),
+            Plan::Pull(ref pull) => pull
+                .paths
+                .iter()
+                .flat_map(|path| path.negated_names().into_iter())
+                .collect(),
+            Plan::PullLevel(ref path) => path.plan.negated_names(),
+            Plan::EventWindow(ref window) => window.plan.negated_names(),
+            Plan::TopK(ref top_k) => top_k.plan.negated_names(),
+            Plan::Session(ref session) => session.plan.negated_names(),
+            Plan::Window(ref window) => window.plan.negated_names(),
+            _ => HashSet::new(),
+        }
+    }
+
+    /// Returns the number of clauses this plan consists of, counting
+    /// itself and all of its sub-plans, but not the rules it refers
+    /// to by name.
+    pub fn clauses(&self) -> usize {
+        let nested = match *self {
+            Plan::Project(ref projection) => projection.plan.clauses(),
+            Plan::Aggregate(ref aggregate) => aggregate.plan.clauses(),
+            Plan::Union(ref union) => union.plans.iter().map(Plan::clauses).sum(),
+            Plan::Join(ref join) => join.left_plan.clauses() + join.right_plan.clauses(),
+            Plan::Antijoin(ref antijoin) => {
+                antijoin.left_plan.clauses() + antijoin.right_plan.clauses()
+            }
+            Plan::Negate(ref plan) => plan.clauses(),
+            Plan::Filter(ref filter) => filter.plan.clauses(),
+            Plan::Transform(ref transform) => transform.plan.clauses(),
+            Plan::Pull(ref pull) => pull.paths.iter().map(Plan::clauses).sum(),
+            Plan::PullLevel(ref path) => path.plan.clauses(),
+            Plan::EventWindow(ref window) => window.plan.clauses(),
+            Plan::Hector(ref hector) => hector.bindings.len(),
+            Plan::TopK(ref top_k) => top_k.plan.clauses(),
+            Plan::Session(ref session) => session.plan.clauses(),
+            Plan::Window(ref window) => window.plan.clauses(),
+            _ => 0,
+        };
+
+        1 + nested
+    }
+
+    /// Returns the value types of those variables bound by this plan
+    /// whose type can be inferred from the attribute schemas.
+    pub fn value_types<T>(&self, domain: &Domain<A, T>) -> HashMap<Var, ValueType>
+    where
+        T: Timestamp + Lattice,
+    {
+        if let Some(plan) = self.reversed() {
+            return plan.value_types(domain);
+        }
+
+        let attribute_type = |aid: &A| {
+            domain
+                .attributes
+                .get(aid)
+                .and_then(|config| config.value_type)
+        };
+
+        let mut types = HashMap::new();
+
+        match *self {
+            Plan::MatchA(_, ref aid, v) | Plan::MatchEA(_, ref aid, v) => {
+                if let Some(value_type) = attribute_type(aid) {
+                    types.insert(v, value_type);
+                }
+            }
+            Plan::Project(ref projection) => {
+                let inner = projection.plan.value_types(domain);
+                for variable in projection.variables.iter() {
+                    if let Some(value_type) = inner.get(variable) {
+                        types.insert(*variable, *value_type);
+                    }
+                }
+            }
+            Plan::TopK(ref top_k) => {
+                let inner = top_k.plan.value_types(domain);
+                for variable in top_k.variables.iter() {
+                    if let Some(value_type) = inner.get(variable) {
+                        types.insert(*variable, *value_type);
+                    }
+                }
+            }
+            Plan::Session(ref session) => {
+                let inner = session.plan.value_types(domain);
+                for variable in session.variables.iter() {
+                    if let Some(value_type) = inner.get(variable) {
+                        types.insert(*variable, *value_type);
+                    }
+                }
+                types.insert(session.session_variable, ValueType::Instant);
+            }
+            Plan::Window(ref window) => {
+                let inner = window.plan.value_types(domain);
+                for variable in window.variables.iter() {
+                    if let Some(value_type) = inner.get(variable) {
+                        types.insert(*variable, *value_type);
+                    }
+                }
+                types.insert(window.window_variable, ValueType::Instant);
+            }
+            Plan::Union(ref union) => {
+                // Only types agreed upon by all branches are known.
+                let mut branches = union.plans.iter().map(|plan| plan.value_types(domain));
+                if let Some(first) = branches.next() {
+                    types = first;
+                    for branch in branches {
+                        types.retain(|variable, value_type| {
+                            branch.get(variable) == Some(&*value_type)
+                        });
+                    }
+                }
+            }
+            Plan::Join(ref join) => {
+                types = join.left_plan.value_types(domain);
+                types.extend(join.right_plan.value_types(domain).into_iter());
+            }
+            Plan::Antijoin(ref antijoin) => types = antijoin.left_plan.value_types(domain),
+            Plan::Negate(ref plan) => types = plan.value_types(domain),
+            Plan::Filter(ref filter) => types = filter.plan.value_types(domain),
+            Plan::Transform(ref transform) => {
+                types = transform.plan.value_types(domain);
+                let value_type = match transform.function {
+                    Function::TRUNCATE | Function::BUCKET => ValueType::Instant,
+                    Function::DATE => ValueType::Date,
+                    _ => ValueType::Number,
+                };
+                types.insert(transform.result_variable, value_type);
+            }
+            Plan::EventWindow(ref window) => types = window.plan.value_types(domain),
+            Plan::Fulltext(ref fulltext) => {
+                types.insert(fulltext.variables[1], ValueType::String);
+            }
+            Plan::Range(ref range) => {
+                if let Some(value_type) = range.lower.value_type() {
+                    types.insert(range.variables[1], value_type);
+                }
+            }
+            Plan::TransitiveClosure(ref closure) => {
+                for variable in closure.variables.iter() {
+                    types.insert(*variable, ValueType::Eid);
+                }
+            }
+            Plan::Graph(ref graph) => match graph.algorithm {
+                GraphAlgorithm::ConnectedComponents => {}
+                _ => {
+                    types.insert(graph.variables[1], ValueType::Number);
+                }
+            },
+            _ => {}
+        }
+
+        types
+    }
+
+    /// Ensures that all predicates and functions used in this plan
+    /// are applied to arguments of a permissible type, as far as
+    /// argument types are known, and that only reference attributes
+    /// are navigated in reverse.
+    pub fn type_check<T>(&self, domain: &Domain<A, T>) -> Result<(), Error>
+    where
+        T: Timestamp + Lattice,
+    {
+        match *self {
+            Plan::Project(ref projection) => projection.plan.type_check(domain),
+            Plan::Aggregate(ref aggregate) => aggregate.plan.type_check(domain),
+            Plan::TopK(ref top_k) => top_k.plan.type_check(domain),
+            Plan::Session(ref session) => {
+                session.plan.type_check(domain)?;
+
+                match session.plan.value_types(domain).get(&session.time_variable) {
+                    None | Some(ValueType::Instant) => Ok(()),
+                    Some(value_type) => Err(Error::incorrect(format!(
+                        "Sessions are delimited by instants, not {:?}.",
+                        value_type
+                    ))),
+                }
+            }
+            Plan::Window(ref window) => {
+                window.plan.type_check(domain)?;
+
+                match window.plan.value_types(domain).get(&window.time_variable) {
+                    None | Some(ValueType::Instant) => Ok(()),
+                    Some(value_type) => Err(Error::incorrect(format!(
+                        "Windows are delimited by instants, not {:?}.",
+                        value_type
+                    ))),
+                }
+            }
+            Plan::TransitiveClosure(ref closure) => {
+                let value_type = domain
+                    .attributes
+                    .get(&closure.forward_attribute())
+                    .and_then(|config| config.value_type);
+
+                match value_type {
+                    None | Some(ValueType::Eid) => Ok(()),
+                    Some(value_type) => Err(Error::incorrect(format!(
+                        "Attribute {} of type {:?} can't be followed transitively.",
+                        closure.attribute, value_type
+                    ))),
+                }
+            }
+            Plan::Graph(ref graph) => {
+                let value_type = |aid: &A| {
+                    let forward = reverse_attribute(aid).unwrap_or_else(|| aid.clone());
+                    domain
+                        .attributes
+                        .get(&forward)
+                        .and_then(|config| config.value_type)
+                };
+
+                for aid in &[&graph.source, &graph.target] {
+                    match value_type(*aid) {
+                        None | Some(ValueType::Eid) => {}
+                        Some(other) => {
+                            return Err(Error::incorrect(format!(
+                                "Attribute {} of type {:?} can't refer to nodes.",
+                                aid, other
+                            )));
+                        }
+                    }
+                }
+
+                match graph.weight.as_ref().and_then(value_type) {
+                    None | Some(ValueType::Number) => Ok(()),
+                    Some(other) => Err(Error::incorrect(format!(
+                        "Edge weights must be numbers, not {:?}.",
+                        other
+                    ))),
+                }
+            }
+            Plan::Union(ref union) => union
+                .plans
+                .iter()
+                .map(|plan| plan.type_check(domain))
+                .collect(),
+            Plan::Join(ref join) => {
+                join.left_plan.type_check(domain)?;
+                join.right_plan.type_check(domain)
+            }
+            Plan::Antijoin(ref antijoin) => {
+                antijoin.left_plan.type_check(domain)?;
+                antijoin.right_plan.type_check(domain)
+            }
+            Plan::Negate(ref plan) => plan.type_check(domain),
+            Plan::MatchA(_, ref a, _) | Plan::MatchEA(_, ref a, _) | Plan::MatchAV(_, ref a, _) => {
+                match reverse_attribute(a) {
+                    None => Ok(()),
+                    Some(forward) => {
+                        if let Plan::MatchAV(_, _, ref v) = *self {
+                            if v.value_type() != Some(ValueType::Eid) {
+                                return Err(Error::incorrect(format!(
+                                    "Reverse reference {} can only match entity ids.",
+                                    a
+                                )));
+                            }
+                        }
+
+                        let value_type = domain
+                            .attributes
+                            .get(&forward)
+                            .and_then(|config| config.value_type);
+
+                        match value_type {
+                            None | Some(ValueType::Eid) => Ok(()),
+                            Some(value_type) => Err(Error::incorrect(format!(
+                                "Attribute {} of type {:?} can't be navigated in reverse.",
+                                forward, value_type
+                            ))),
+                        }
+                    }
+                }
+            }
+            Plan::Filter(ref filter) => {
+                filter.plan.type_check(domain)?;
+
+                let types = filter.plan.value_types(domain);
+                let arguments = argument_types(&filter.variables, &filter.constants, &types);
+
+                match filter.predicate {
+                    Predicate::CONTAINS | Predicate::STARTS_WITH => {
+                        expect_type(&filter.predicate, &arguments, ValueType::String)
+                    }
+                    _ => {
+                        let temporal = match filter.predicate {
+                            Predicate::BEFORE | Predicate::AFTER => true,
+                            _ => false,
+                        };
+
+                        if temporal {
+                            for value_type in arguments.iter().filter_map(Option::as_ref) {
+                                if *value_type != ValueType::Instant
+                                    && *value_type != ValueType::Date
+                                {
+                                    return Err(Error::incorrect(format!(
+                                        "{:?} expects instants or dates, not {:?}.",
+                                        filter.predicate, value_type
+                                    )));
+                                }
+                            }
+                        }
+
+                        if let (Some(Some(x)), Some(Some(y))) = (arguments.get(0), arguments.get(1))
+                        {
+                            if x != y {
+                                return Err(Error::incorrect(format!(
+                                    "{:?} can't compare values of type {:?} and {:?}.",
+                                    filter.predicate, x, y
+                                )));
+                            }
+                        }
+
+                        Ok(())
+                    }
+                }
+            }
+            Plan::Transform(ref transform) => {
+                transform.plan.type_check(domain)?;
+
+                let types = transform.plan.value_types(domain);
+                let arguments = argument_types(&transform.variables, &transform.constants, &types);
+
+                match transform.function {
+                    Function::TRUNCATE => expect_type(
+                        &transform.function,
+                        &arguments[..arguments.len().min(1)],
+                        ValueType::Instant,
+                    ),
+                    Function::DATE => {
+                        expect_type(&transform.function, &arguments, ValueType::Instant)
+                    }
+                    Function::BUCKET => {
+                        let (instants, widths) = arguments.split_at(arguments.len().min(1));
+                        expect_type(&transform.function, instants, ValueType::Instant)?;
+                        expect_type(&transform.function, widths, ValueType::Number)
+                    }
+                    _ => expect_type(&transform.function, &arguments, ValueType::Number),
+                }
+            }
+            Plan::Pull(ref pull) => pull
+                .paths
+                .iter()
+                .map(|path| path.type_check(domain))
+                .collect(),
+            Plan::PullLevel(ref path) => path.plan.type_check(domain),
+            Plan::EventWindow(ref window) => window.plan.type_check(domain),
+            Plan::Fulltext(ref fulltext) => {
+                let a = &fulltext.attribute;
+                if domain.attributes.contains_key(a) && !domain.fulltext.contains_key(a) {
+                    Err(Error::incorrect(format!(
+                        "Attribute {} has no fulltext index.",
+                        a
+                    )))
+                } else {
+                    Ok(())
+                }
+            }
+            Plan::Range(ref range) => {
+                let a = &range.attribute;
+                let bound_type = range.lower.value_type();
+
+                if bound_type.is_none() || range.upper.value_type() != bound_type {
+                    return Err(Error::incorrect(format!(
+                        "Bounds {:?} and {:?} of a range must be of the same type.",
+                        range.lower, range.upper
+                    )));
+                }
+
+                match domain.attributes.get(a) {
+                    None => Ok(()),
+                    Some(config) => {
+                        if !domain.reverse_propose.contains_key(a) {
+                            Err(Error::incorrect(format!(
+                                "Attribute {} has no reverse index to scan.",
+                                a
+                            )))
+                        } else if config.value_type.is_some() && config.value_type != bound_type {
+                            Err(Error::incorrect(format!(
+                                "Bounds {:?} and {:?} don't match the type of {}.",
+                                range.lower, range.upper, a
+                            )))
+                        } else {
+                            Ok(())
+                        }
+                    }
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Returns the variables bound by this plan.
     pub fn variables(&self) -> Vec<Var> {
+        if let Some(plan) = self.reversed() {
+            return plan.variables();
+        }
+
         match *self {
             Plan::Project(ref projection) => projection.variables.clone(),
             Plan::Aggregate(ref aggregate) => aggregate.variables.clone(),
             Plan::Union(ref union) => union.variables.clone(),
             Plan::Join(ref join) => join.variables.clone(),
             Plan::Hector(ref hector) => hector.variables.clone(),
-            Plan::Antijoin(ref antijoin) => antijoin.variables.clone(),
+            Plan::Antijoin(ref antijoin) => {
+                let mut variables = antijoin.variables.clone();
+                variables.extend(
+                    antijoin
+                        .left_plan
+                        .variables()
+                        .into_iter()
+                        .filter(|x| !antijoin.variables.contains(x)),
+                );
+                variables
+            }
             Plan::Negate(ref plan) => plan.variables(),
             Plan::Filter(ref filter) => filter.variables.clone(),
             Plan::Transform(ref transform) => transform.variables.clone(),
@@ -221,11 +836,21 @@ impl<A: AsAid> Plan<A> {
             Plan::MatchEA(_, _, v) => vec![v],
             Plan::MatchAV(e, _, _) => vec![e],
             Plan::NameExpr(ref variables, ref _name) => variables.clone(),
+            Plan::Parameter(ref variables, ref _name) => variables.clone(),
             Plan::Pull(ref pull) => pull.variables.clone(),
             Plan::PullLevel(ref path) => path.variables.clone(),
             Plan::PullAll(ref path) => path.variables.clone(),
+            Plan::History(ref history) => history.variables.clone(),
+            Plan::EventWindow(ref window) => window.variables.clone(),
+            Plan::Fulltext(ref fulltext) => fulltext.variables.clone(),
+            Plan::Range(ref range) => range.variables.clone(),
+            Plan::TopK(ref top_k) => top_k.variables.clone(),
+            Plan::Session(ref session) => session.bound_variables(),
+            Plan::Window(ref window) => window.bound_variables(),
+            Plan::TransitiveClosure(ref closure) => closure.variables.clone(),
+            Plan::Graph(ref graph) => graph.variables.clone(),
             #[cfg(feature = "graphql")]
-            Plan::GraphQl(_) => unimplemented!(),
+            Plan::GraphQl(_) => vec![],
         }
     }
 }
@@ -238,6 +863,10 @@ where
 
     fn dependencies(&self) -> Dependencies<A> {
         // @TODO provide a general fold for plans
+        if let Some(plan) = self.reversed() {
+            return plan.dependencies();
+        }
+
         match *self {
             Plan::Project(ref projection) => projection.dependencies(),
             Plan::Aggregate(ref aggregate) => aggregate.dependencies(),
@@ -252,9 +881,19 @@ where
             Plan::MatchEA(_, ref a, _) => Dependencies::attribute(a.clone()),
             Plan::MatchAV(_, ref a, _) => Dependencies::attribute(a.clone()),
             Plan::NameExpr(_, ref name) => Dependencies::name(name.clone()),
+            Plan::Parameter(_, _) => Dependencies::none(),
             Plan::Pull(ref pull) => pull.dependencies(),
             Plan::PullLevel(ref path) => path.dependencies(),
             Plan::PullAll(ref path) => path.dependencies(),
+            Plan::History(ref history) => history.dependencies(),
+            Plan::EventWindow(ref window) => window.dependencies(),
+            Plan::Fulltext(ref fulltext) => fulltext.dependencies(),
+            Plan::Range(ref range) => range.dependencies(),
+            Plan::TopK(ref top_k) => top_k.dependencies(),
+            Plan::Session(ref session) => session.dependencies(),
+            Plan::Window(ref window) => window.dependencies(),
+            Plan::TransitiveClosure(ref closure) => closure.dependencies(),
+            Plan::Graph(ref graph) => graph.dependencies(),
             #[cfg(feature = "graphql")]
             Plan::GraphQl(ref q) => q.dependencies(),
         }
@@ -262,6 +901,10 @@ where
 
     fn into_bindings(&self) -> Vec<Binding<Self::A>> {
         // @TODO provide a general fold for plans
+        if let Some(plan) = self.reversed() {
+            return plan.into_bindings();
+        }
+
         match *self {
             Plan::Project(ref projection) => projection.into_bindings(),
             Plan::Aggregate(ref aggregate) => aggregate.into_bindings(),
@@ -291,8 +934,16 @@ where
             Plan::Pull(ref pull) => pull.into_bindings(),
             Plan::PullLevel(ref path) => path.into_bindings(),
             Plan::PullAll(ref path) => path.into_bindings(),
+            Plan::History(ref history) => history.into_bindings(),
+            Plan::EventWindow(ref window) => window.into_bindings(),
+            Plan::Fulltext(ref fulltext) => fulltext.into_bindings(),
+            Plan::Range(ref range) => range.into_bindings(),
+            Plan::TransitiveClosure(ref closure) => closure.into_bindings(),
+            Plan::Graph(ref graph) => graph.into_bindings(),
             #[cfg(feature = "graphql")]
             Plan::GraphQl(ref q) => q.into_bindings(),
+            // Anything else is rejected by `validate_bindings` beforehand.
+            _ => panic!("This plan can't be implemented via Hector."),
         }
     }
 
@@ -306,6 +957,10 @@ where
         S: Scope,
         S::Timestamp: Timestamp + Lattice + Rewind,
     {
+        if let Some(plan) = self.reversed() {
+            return plan.implement(nested, domain, local_arrangements);
+        }
+
         match *self {
             Plan::Project(ref projection) => {
                 projection.implement(nested, domain, local_arrangements)
@@ -412,9 +1067,45 @@ where
                     }
                 }
             }
+            Plan::Parameter(ref syms, ref name) => {
+                let (tuples, shutdown_parameter) = match domain.parameters.get_mut(name) {
+                    None => panic!("parameter {:?} does not exist", name),
+                    Some(trace) => {
+                        let (arranged, shutdown_parameter) = trace
+                            .import_frontier(&nested.parent, &format!("Parameter({:?})", name));
+
+                        let tuples = arranged
+                            .enter(nested)
+                            .as_collection(|tuple, _| tuple.clone());
+
+                        (tuples, shutdown_parameter)
+                    }
+                };
+
+                let relation = CollectionRelation {
+                    variables: syms.clone(),
+                    tuples,
+                };
+
+                (
+                    Implemented::Collection(relation),
+                    ShutdownHandle::from_button(shutdown_parameter),
+                )
+            }
             Plan::Pull(ref pull) => pull.implement(nested, domain, local_arrangements),
             Plan::PullLevel(ref path) => path.implement(nested, domain, local_arrangements),
             Plan::PullAll(ref path) => path.implement(nested, domain, local_arrangements),
+            Plan::History(ref history) => history.implement(nested, domain, local_arrangements),
+            Plan::EventWindow(ref window) => window.implement(nested, domain, local_arrangements),
+            Plan::Fulltext(ref fulltext) => fulltext.implement(nested, domain, local_arrangements),
+            Plan::Range(ref range) => range.implement(nested, domain, local_arrangements),
+            Plan::TopK(ref top_k) => top_k.implement(nested, domain, local_arrangements),
+            Plan::Session(ref session) => session.implement(nested, domain, local_arrangements),
+            Plan::Window(ref window) => window.implement(nested, domain, local_arrangements),
+            Plan::TransitiveClosure(ref closure) => {
+                closure.implement(nested, domain, local_arrangements)
+            }
+            Plan::Graph(ref graph) => graph.implement(nested, domain, local_arrangements),
             #[cfg(feature = "graphql")]
             Plan::GraphQl(ref query) => query.implement(nested, domain, local_arrangements),
         }
@@ -0,0 +1,264 @@
+//! Planner pass reordering join trees by the estimated selectivity
+//! of their data patterns.
+
+use std::collections::HashMap;
+
+use crate::domain::AttributeStatistics;
+use crate::plan::sharing::{children_mut, is_join_tree};
+use crate::plan::{Join, Plan, Project};
+use crate::{AsAid, Var};
+
+/// Rewrites all join trees within the given plan into left-deep
+/// trees, starting from the most selective data pattern and then
+/// greedily joining whichever connected pattern is estimated to
+/// produce the smallest intermediate result.
+///
+/// Estimates are derived from the given attribute statistics.
+/// Patterns over attributes without statistics are joined last, in
+/// the order they were written. Join trees that would require a
+/// cartesian product, or that don't join on all of their shared
+/// variables, are left as they are.
+pub fn order_joins<A: AsAid>(plan: &mut Plan<A>, statistics: &HashMap<A, AttributeStatistics>) {
+    if is_join_tree(plan) {
+        if let Some(ordered) = reorder(plan, statistics) {
+            let variables = bound_variables(plan);
+
+            // Consumers might rely on the original variable order.
+            *plan = if bound_variables(&ordered) == variables {
+                ordered
+            } else {
+                Plan::Project(Project {
+                    variables,
+                    plan: Box::new(ordered),
+                })
+            };
+        }
+    } else if let Plan::Project(ref mut projection) = *plan {
+        // Projections select variables by name, so there is no need
+        // to restore the original order below them.
+        if is_join_tree(&projection.plan) {
+            if let Some(ordered) = reorder(&projection.plan, statistics) {
+                *projection.plan = ordered;
+            }
+        } else {
+            order_joins(&mut projection.plan, statistics);
+        }
+    } else {
+        for child in children_mut(plan) {
+            order_joins(child, statistics);
+        }
+    }
+}
+
+/// Returns the variables bound by a join tree, in order.
+fn bound_variables<A: AsAid>(plan: &Plan<A>) -> Vec<Var> {
+    match *plan {
+        Plan::Join(ref join) => {
+            let left = bound_variables(&join.left_plan);
+            let right = bound_variables(&join.right_plan);
+
+            join.variables
+                .iter()
+                .chain(left.iter().filter(|x| !join.variables.contains(x)))
+                .chain(right.iter().filter(|x| !join.variables.contains(x)))
+                .cloned()
+                .collect()
+        }
+        _ => plan.variables(),
+    }
+}
+
+/// A data pattern along with its estimated number of results and
+/// the estimated number of distinct bindings for each of its
+/// variables.
+struct Pattern<A: AsAid> {
+    plan: Plan<A>,
+    variables: Vec<Var>,
+    cardinality: f64,
+    distinct: Vec<(Var, f64)>,
+}
+
+impl<A: AsAid> Pattern<A> {
+    fn new(plan: Plan<A>, statistics: &HashMap<A, AttributeStatistics>) -> Self {
+        let unknown = std::f64::INFINITY;
+        let at_least_one = |x: f64| x.max(1.0);
+
+        let (cardinality, distinct) = match plan {
+            Plan::MatchA(e, ref a, v) => match statistics.get(a) {
+                None => (unknown, vec![]),
+                Some(statistics) => {
+                    let tuples = statistics.forward.tuples as f64;
+                    let values = statistics
+                        .reverse
+                        .map(|reverse| reverse.keys as f64)
+                        .unwrap_or(tuples);
+
+                    (
+                        tuples,
+                        vec![(e, statistics.forward.keys as f64), (v, values)],
+                    )
+                }
+            },
+            Plan::MatchEA(_, ref a, v) => match statistics.get(a) {
+                None => (unknown, vec![]),
+                Some(statistics) => {
+                    let tuples = statistics.forward.fanout();
+                    let values = statistics
+                        .reverse
+                        .map(|reverse| reverse.keys as f64)
+                        .unwrap_or(tuples);
+
+                    (tuples, vec![(v, values)])
+                }
+            },
+            Plan::MatchAV(e, ref a, _) => match statistics.get(a) {
+                None => (unknown, vec![]),
+                Some(statistics) => {
+                    // Without a reverse index, we have to assume the
+                    // worst.
+                    let tuples = statistics
+                        .reverse
+                        .map(|reverse| reverse.fanout())
+                        .unwrap_or(statistics.forward.tuples as f64);
+
+                    (tuples, vec![(e, statistics.forward.keys as f64)])
+                }
+            },
+            _ => (unknown, vec![]),
+        };
+
+        Pattern {
+            variables: plan.variables(),
+            plan,
+            cardinality: at_least_one(cardinality),
+            distinct: distinct
+                .into_iter()
+                .map(|(x, count)| (x, at_least_one(count)))
+                .collect(),
+        }
+    }
+
+    /// Estimates the size of joining this pattern with an
+    /// intermediate result of the given size, on the given
+    /// variables.
+    fn joined_size(&self, size: f64, on: &[Var]) -> f64 {
+        let distinct = self
+            .distinct
+            .iter()
+            .filter(|(x, _)| on.contains(x))
+            .map(|(_, count)| *count)
+            .fold(1.0, f64::max);
+
+        size * self.cardinality / distinct
+    }
+
+    fn is_attribute(&self) -> bool {
+        match self.plan {
+            Plan::MatchA(..) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Collects the data patterns of a join tree, failing if any of its
+/// joins doesn't target exactly the variables shared between its
+/// inputs.
+fn flatten<A: AsAid>(plan: &Plan<A>, patterns: &mut Vec<Plan<A>>) -> bool {
+    match *plan {
+        Plan::Join(ref join) => {
+            let left = bound_variables(&join.left_plan);
+            let right = bound_variables(&join.right_plan);
+
+            let mut shared: Vec<Var> = left.into_iter().filter(|x| right.contains(x)).collect();
+            let mut targets = join.variables.clone();
+            shared.sort();
+            targets.sort();
+
+            shared == targets
+                && flatten(&join.left_plan, patterns)
+                && flatten(&join.right_plan, patterns)
+        }
+        _ => {
+            patterns.push(plan.reversed().unwrap_or_else(|| plan.clone()));
+            true
+        }
+    }
+}
+
+fn reorder<A: AsAid>(
+    tree: &Plan<A>,
+    statistics: &HashMap<A, AttributeStatistics>,
+) -> Option<Plan<A>> {
+    let mut plans = Vec::new();
+    if !flatten(tree, &mut plans) {
+        return None;
+    }
+
+    let mut remaining: Vec<Pattern<A>> = plans
+        .into_iter()
+        .map(|plan| Pattern::new(plan, statistics))
+        .collect();
+
+    let mut first = 0;
+    for (index, pattern) in remaining.iter().enumerate() {
+        if pattern.cardinality < remaining[first].cardinality {
+            first = index;
+        }
+    }
+
+    let first = remaining.remove(first);
+    let mut size = first.cardinality;
+    let mut bound = first.variables.clone();
+    let mut is_attribute = first.is_attribute();
+    let mut ordered = first.plan;
+
+    while !remaining.is_empty() {
+        let mut next: Option<(usize, Vec<Var>, f64)> = None;
+
+        for (index, pattern) in remaining.iter().enumerate() {
+            let shared: Vec<Var> = bound
+                .iter()
+                .filter(|x| pattern.variables.contains(x))
+                .cloned()
+                .collect();
+
+            // Attribute<->Attribute joins can only target a single
+            // variable.
+            if shared.is_empty() || (is_attribute && pattern.is_attribute() && shared.len() > 1) {
+                continue;
+            }
+
+            let estimate = pattern.joined_size(size, &shared);
+            let is_better = match next {
+                None => true,
+                Some((_, _, best)) => estimate < best,
+            };
+
+            if is_better {
+                next = Some((index, shared, estimate));
+            }
+        }
+
+        // Disconnected patterns would require a cartesian product.
+        let (index, shared, estimate) = next?;
+        let pattern = remaining.remove(index);
+
+        bound.extend(
+            pattern
+                .variables
+                .iter()
+                .filter(|x| !shared.contains(x))
+                .cloned(),
+        );
+
+        size = estimate;
+        is_attribute = false;
+        ordered = Plan::Join(Join {
+            variables: shared,
+            left_plan: Box::new(ordered),
+            right_plan: Box::new(pattern.plan),
+        });
+    }
+
+    Some(ordered)
+}
@@ -11,7 +11,7 @@ use differential_dataflow::AsCollection;
 
 use crate::binding::AsBinding;
 use crate::domain::Domain;
-use crate::plan::{Dependencies, Implementable};
+use crate::plan::{gensym, reverse_attribute, Dependencies, Implementable, Join, Plan, Project};
 use crate::timestamp::Rewind;
 use crate::{AsAid, Value, Var};
 use crate::{CollectionRelation, Implemented, Relation, ShutdownHandle, VariableMap};
@@ -49,6 +49,124 @@ pub struct Pull<P: Implementable> {
     pub paths: Vec<P>,
 }
 
+/// A declarative pull pattern, describing the shape of the nested
+/// result to be pulled for each root entity. So
+/// `[:parent/name {:parent/child [:child/name]}]` would be
+/// represented as:
+///
+/// [Attribute(:parent/name),
+///  Nested { attribute: :parent/child, pattern: [Attribute(:child/name)] }]
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum PullPattern<A: AsAid> {
+    /// Pull the values of an attribute.
+    Attribute(A),
+    /// Follow a reference attribute and pull the specified pattern
+    /// for each referenced entity.
+    Nested {
+        /// Reference attribute to follow.
+        attribute: A,
+        /// Pattern to pull for the referenced entities.
+        pattern: Vec<PullPattern<A>>,
+        /// Whether the attribute may reference more than one
+        /// entity, in which case entity ids are retained in result
+        /// paths.
+        cardinality_many: bool,
+    },
+}
+
+impl<A: AsAid + 'static> Pull<Plan<A>> {
+    /// Creates a pull plan walking the specified pattern for all
+    /// entities bound to `root_variable` by the root plan. The
+    /// resulting paths can be fed into an `AssocIn` sink to obtain
+    /// nested results.
+    pub fn from_pattern(
+        root_plan: Plan<A>,
+        root_variable: Var,
+        pattern: &[PullPattern<A>],
+    ) -> Self {
+        let root = Plan::Project(Project {
+            variables: vec![root_variable],
+            plan: Box::new(root_plan),
+        });
+
+        let mut paths = Vec::new();
+        pattern_to_paths(pattern, root, &[root_variable], &[], false, &mut paths);
+
+        Pull {
+            variables: vec![],
+            paths,
+        }
+    }
+}
+
+/// Recursively transforms a pull pattern into individual
+/// `PullLevel`s. The plan for each level binds the entities along the
+/// path from the root, in order.
+fn pattern_to_paths<A: AsAid + 'static>(
+    pattern: &[PullPattern<A>],
+    plan: Plan<A>,
+    path_variables: &[Var],
+    path_attributes: &[A],
+    cardinality_many: bool,
+    paths: &mut Vec<Plan<A>>,
+) {
+    let this = *path_variables.last().expect("empty path");
+
+    let pull_attributes: Vec<A> = pattern
+        .iter()
+        .filter_map(|item| match item {
+            PullPattern::Attribute(aid) => Some(aid.clone()),
+            PullPattern::Nested { .. } => None,
+        })
+        .collect();
+
+    if !pull_attributes.is_empty() {
+        paths.push(Plan::PullLevel(PullLevel {
+            variables: vec![],
+            plan: Box::new(plan.clone()),
+            pull_variable: this,
+            pull_attributes,
+            path_attributes: path_attributes.to_vec(),
+            cardinality_many,
+        }));
+    }
+
+    for item in pattern.iter() {
+        if let PullPattern::Nested {
+            attribute,
+            pattern,
+            cardinality_many,
+        } = item
+        {
+            let child = gensym();
+
+            let mut child_variables = path_variables.to_vec();
+            child_variables.push(child);
+
+            let mut child_attributes = path_attributes.to_vec();
+            child_attributes.push(attribute.clone());
+
+            let child_plan = Plan::Project(Project {
+                variables: child_variables.clone(),
+                plan: Box::new(Plan::Join(Join {
+                    variables: vec![this],
+                    left_plan: Box::new(plan.clone()),
+                    right_plan: Box::new(Plan::MatchA(this, attribute.clone(), child)),
+                })),
+            });
+
+            pattern_to_paths(
+                pattern,
+                child_plan,
+                &child_variables,
+                &child_attributes,
+                *cardinality_many,
+                paths,
+            );
+        }
+    }
+}
+
 fn interleave<A: AsAid>(values: &[Value], constants: &[A]) -> Vec<Value> {
     if values.is_empty() || constants.is_empty() {
         values.to_owned()
@@ -77,6 +195,29 @@ fn interleave<A: AsAid>(values: &[Value], constants: &[A]) -> Vec<Value> {
     }
 }
 
+/// Returns the result tuple for a pulled attribute value, holding
+/// the interleaved path, the attribute, and the value, i.e. [?p
+/// "parent/child" ?c ?a ?v]. Single-cardinality paths omit the
+/// last child id.
+fn pulled<A: AsAid>(
+    path: &[Value],
+    path_attributes: &[A],
+    single: bool,
+    attribute: &Value,
+    v: &Value,
+) -> Vec<Value> {
+    let mut result = interleave(path, path_attributes);
+
+    if single {
+        result.pop().expect("malformed path");
+    }
+
+    result.push(attribute.clone());
+    result.push(v.clone());
+
+    result
+}
+
 impl<A: AsAid + 'static, P: Implementable<A = A>> Implementable for PullLevel<A, P> {
     type A = A;
 
@@ -84,7 +225,7 @@ impl<A: AsAid + 'static, P: Implementable<A = A>> Implementable for PullLevel<A,
         let attribute_dependencies = self
             .pull_attributes
             .iter()
-            .cloned()
+            .map(|a| reverse_attribute(a).unwrap_or_else(|| a.clone()))
             .map(Dependencies::attribute)
             .sum();
 
@@ -147,63 +288,136 @@ impl<A: AsAid + 'static, P: Implementable<A = A>> Implementable for PullLevel<A,
             > = paths.map(move |t| (t[e_offset].clone(), t)).arrange();
 
             let mut shutdown_handle = shutdown_handle;
-            let streams = self.pull_attributes.iter().map(|a| {
-                let e_v = match domain.forward_propose(a) {
-                    None => panic!("attribute {:?} does not exist", a),
-                    Some(propose_trace) => {
-                        let frontier: Vec<S::Timestamp> = propose_trace.advance_frontier().to_vec();
-                        let (arranged, shutdown_propose) = propose_trace
-                            .import_frontier(&nested.parent, &format!("Propose({:?})", a));
-
-                        let e_v = arranged.enter_at(nested, move |_, _, time| {
-                            let mut forwarded = time.clone();
-                            forwarded.advance_by(&frontier);
-                            Product::new(forwarded, 0)
-                        });
-
-                        shutdown_handle.add_button(shutdown_propose);
-
-                        e_v
-                    }
-                };
 
-                let attribute = a.clone().into_value();
-                let path_attributes: Vec<Self::A> = self.path_attributes.clone();
+            // Cardinality single means we don't need to distinguish
+            // child ids (there can only be one).
+            let single = !(self.path_attributes.is_empty() || self.cardinality_many);
 
-                if path_attributes.is_empty() || self.cardinality_many {
-                    e_path
-                        .join_core(&e_v, move |_e, path: &Vec<Value>, v: &Value| {
-                            // Each result tuple must hold the interleaved
-                            // path, the attribute, and the value,
-                            // i.e. [?p "parent/child" ?c ?a ?v]
-                            let mut result = interleave(path, &path_attributes);
-                            result.push(attribute.clone());
-                            result.push(v.clone());
+            let streams: Vec<_> = match domain.entity_index_covering(&self.pull_attributes) {
+                Some(name) => {
+                    // All attributes can be pulled via a single
+                    // entity index, instead of one join each.
+                    let composite = &mut domain.composite.get_mut(&name).unwrap().trace;
+                    let frontier: Vec<S::Timestamp> = composite.advance_frontier().to_vec();
+                    let (arranged, shutdown_composite) = composite
+                        .import_frontier(&nested.parent, &format!("Composite({:?})", name));
 
-                            Some(result)
-                        })
-                        .inner
-                } else {
-                    e_path
-                        .join_core(&e_v, move |_e, path: &Vec<Value>, v: &Value| {
-                            // Each result tuple must hold the interleaved
-                            // path, the attribute, and the value,
-                            // i.e. [?p "parent/child" ?c ?a ?v]
-                            let mut result = interleave(path, &path_attributes);
-
-                            // Cardinality single means we don't need
-                            // to distinguish child ids (there can
-                            // only be one).
-                            result.pop().expect("malformed path");
-
-                            result.push(attribute.clone());
-                            result.push(v.clone());
-
-                            Some(result)
-                        })
-                        .inner
+                    let e_av = arranged.enter_at(nested, move |_, _, time| {
+                        let mut forwarded = time.clone();
+                        forwarded.advance_by(&frontier);
+                        Product::new(forwarded, 0)
+                    });
+
+                    shutdown_handle.add_button(shutdown_composite);
+
+                    let attributes: Vec<Value> = self
+                        .pull_attributes
+                        .iter()
+                        .cloned()
+                        .map(AsAid::into_value)
+                        .collect();
+                    let path_attributes: Vec<Self::A> = self.path_attributes.clone();
+
+                    let tuples = e_path.join_core(
+                        &e_av,
+                        move |_e, path: &Vec<Value>, (a, v): &(Value, Value)| {
+                            if attributes.contains(a) {
+                                Some(pulled(path, &path_attributes, single, a, v))
+                            } else {
+                                None
+                            }
+                        },
+                    );
+
+                    vec![tuples.inner]
                 }
-            });
+                None => self
+                    .pull_attributes
+                    .iter()
+                    .map(|a| {
+                        let attribute = a.clone().into_value();
+                        let path_attributes: Vec<Self::A> = self.path_attributes.clone();
+
+                        // Reverse references are pulled via the
+                        // reverse index of their forward attribute,
+                        // or via a ref index covering it.
+                        let forward = reverse_attribute(a);
+                        let via_ref_index = match forward {
+                            Some(ref forward) if !domain.reverse_propose.contains_key(forward) => {
+                                domain.ref_index_covering(forward)
+                            }
+                            _ => None,
+                        };
+
+                        if let Some(name) = via_ref_index {
+                            let composite = &mut domain.composite.get_mut(&name).unwrap().trace;
+                            let frontier: Vec<S::Timestamp> = composite.advance_frontier().to_vec();
+                            let (arranged, shutdown_composite) = composite
+                                .import_frontier(&nested.parent, &format!("Composite({:?})", name));
+
+                            let v_ae = arranged.enter_at(nested, move |_, _, time| {
+                                let mut forwarded = time.clone();
+                                forwarded.advance_by(&frontier);
+                                Product::new(forwarded, 0)
+                            });
+
+                            shutdown_handle.add_button(shutdown_composite);
+
+                            let forward = forward.unwrap().into_value();
+
+                            return e_path
+                                .join_core(
+                                    &v_ae,
+                                    move |_v, path: &Vec<Value>, (a, e): &(Value, Value)| {
+                                        if *a == forward {
+                                            Some(pulled(
+                                                path,
+                                                &path_attributes,
+                                                single,
+                                                &attribute,
+                                                e,
+                                            ))
+                                        } else {
+                                            None
+                                        }
+                                    },
+                                )
+                                .inner;
+                        }
+
+                        let propose_trace = match forward {
+                            None => domain.forward_propose(a),
+                            Some(ref forward) => domain.reverse_propose(forward),
+                        };
+
+                        let e_v = match propose_trace {
+                            None => panic!("attribute {:?} does not exist", a),
+                            Some(propose_trace) => {
+                                let frontier: Vec<S::Timestamp> =
+                                    propose_trace.advance_frontier().to_vec();
+                                let (arranged, shutdown_propose) = propose_trace
+                                    .import_frontier(&nested.parent, &format!("Propose({:?})", a));
+
+                                let e_v = arranged.enter_at(nested, move |_, _, time| {
+                                    let mut forwarded = time.clone();
+                                    forwarded.advance_by(&frontier);
+                                    Product::new(forwarded, 0)
+                                });
+
+                                shutdown_handle.add_button(shutdown_propose);
+
+                                e_v
+                            }
+                        };
+
+                        e_path
+                            .join_core(&e_v, move |_e, path: &Vec<Value>, v: &Value| {
+                                Some(pulled(path, &path_attributes, single, &attribute, v))
+                            })
+                            .inner
+                    })
+                    .collect(),
+            };
 
             let tuples = if self.path_attributes.is_empty() || self.cardinality_many {
                 nested.concatenate(streams)
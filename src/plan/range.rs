@@ -0,0 +1,121 @@
+//! Range plan, scanning an attribute's values within bounds.
+
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::Operator;
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::trace::{BatchReader, Cursor};
+use differential_dataflow::AsCollection;
+
+use crate::domain::Domain;
+use crate::plan::{Dependencies, Implementable};
+use crate::timestamp::Rewind;
+use crate::{AsAid, Value, Var};
+use crate::{CollectionRelation, Implemented, ShutdownHandle, VariableMap};
+
+/// A plan stage binding [e v] for all values of an attribute within
+/// the closed interval [lower, upper], e.g. all orders with a total
+/// between 10 and 100. Equivalent to matching the attribute and
+/// filtering by comparison predicates, but rather than inspecting
+/// every value, only the matching range of the attribute's reverse
+/// index is visited.
+///
+/// Only attributes maintaining reverse indices can be scanned, and
+/// both bounds must be of the same type.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Range<A: AsAid> {
+    /// Variables to bind e and v to, in that order.
+    pub variables: Vec<Var>,
+    /// Attribute to scan.
+    pub attribute: A,
+    /// Smallest value to include.
+    pub lower: Value,
+    /// Largest value to include.
+    pub upper: Value,
+}
+
+impl<A: AsAid> Implementable for Range<A> {
+    type A = A;
+
+    fn dependencies(&self) -> Dependencies<A> {
+        Dependencies::attribute(self.attribute.clone())
+    }
+
+    fn implement<'b, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        domain: &mut Domain<A, S::Timestamp>,
+        _local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
+    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+    where
+        S: Scope,
+        S::Timestamp: Timestamp + Lattice + Rewind,
+    {
+        assert_eq!(self.variables.len(), 2);
+
+        let a = &self.attribute;
+        let lower = self.lower.clone();
+        let upper = self.upper.clone();
+
+        let (tuples, shutdown_propose) = match domain.reverse_propose(a) {
+            None => panic!("attribute {:?} has no reverse index", a),
+            Some(propose_trace) => {
+                let (propose, shutdown_propose) =
+                    propose_trace.import_frontier(&nested.parent, &format!("_Propose({:?})", a));
+
+                // Batches are sorted by value, so each of them can be
+                // scanned from the lower bound up to the upper one.
+                let tuples = propose
+                    .enter(nested)
+                    .stream
+                    .unary(Pipeline, "Range", move |_capability, _info| {
+                        move |input, output| {
+                            input.for_each(|time, data| {
+                                let mut session = output.session(&time);
+
+                                for batch in data.iter() {
+                                    let mut cursor = batch.cursor();
+                                    cursor.seek_key(batch, &lower);
+
+                                    while let Some(v) = cursor.get_key(batch) {
+                                        if *v > upper {
+                                            break;
+                                        }
+
+                                        while let Some(e) = cursor.get_val(batch) {
+                                            cursor.map_times(batch, |t, diff| {
+                                                session.give((
+                                                    vec![e.clone(), v.clone()],
+                                                    t.clone(),
+                                                    *diff,
+                                                ));
+                                            });
+                                            cursor.step_val(batch);
+                                        }
+
+                                        cursor.step_key(batch);
+                                    }
+                                }
+                            });
+                        }
+                    })
+                    .as_collection();
+
+                (tuples, shutdown_propose)
+            }
+        };
+
+        let relation = CollectionRelation {
+            variables: self.variables.clone(),
+            tuples,
+        };
+
+        (
+            Implemented::Collection(relation),
+            ShutdownHandle::from_button(shutdown_propose),
+        )
+    }
+}
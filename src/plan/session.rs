@@ -0,0 +1,125 @@
+//! Session window plan.
+
+use std::time::Duration;
+
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::Reduce;
+
+use crate::binding::AsBinding;
+use crate::domain::Domain;
+use crate::plan::{Dependencies, Implementable};
+use crate::timestamp::Rewind;
+use crate::{CollectionRelation, Implemented, Relation, ShutdownHandle, Value, Var, VariableMap};
+
+/// A plan stage assigning the tuples of each group to sessions, i.e.
+/// runs of events whose instants are no further than `gap` apart.
+/// Tuples are extended by the instant their session started at, s.t.
+/// aggregates grouping by it compute results per session.
+///
+/// Sessions are derived anew for any group receiving changes, so
+/// late events merging or splitting sessions move all affected
+/// tuples to their new sessions.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Session<P: Implementable> {
+    /// Variables to bind, in order, followed by the session variable.
+    pub variables: Vec<Var>,
+    /// Plan for the data source.
+    pub plan: Box<P>,
+    /// Relation variables that determine the grouping, e.g. the
+    /// entity events belong to.
+    pub key_variables: Vec<Var>,
+    /// Variable bound to the instant of each event.
+    pub time_variable: Var,
+    /// Maximum time between two events of the same session.
+    pub gap: Duration,
+    /// Variable to bind the instant each session started at to.
+    pub session_variable: Var,
+}
+
+impl<P: Implementable> Session<P> {
+    /// Returns the variables bound by this stage.
+    pub fn bound_variables(&self) -> Vec<Var> {
+        let mut variables = self.variables.clone();
+        variables.push(self.session_variable);
+        variables
+    }
+}
+
+impl<P: Implementable> Implementable for Session<P> {
+    type A = P::A;
+
+    fn dependencies(&self) -> Dependencies<Self::A> {
+        self.plan.dependencies()
+    }
+
+    fn implement<'b, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        domain: &mut Domain<Self::A, S::Timestamp>,
+        local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
+    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+    where
+        S: Scope,
+        S::Timestamp: Timestamp + Lattice + Rewind,
+    {
+        let (relation, mut shutdown_handle) =
+            self.plan.implement(nested, domain, local_arrangements);
+
+        let tuples = {
+            let (projected, shutdown) = relation.projected(nested, domain, &self.variables);
+            shutdown_handle.merge_with(shutdown);
+            projected
+        };
+
+        let key_offsets: Vec<usize> = self
+            .key_variables
+            .iter()
+            .map(|x| AsBinding::binds(&self.variables, *x).unwrap())
+            .collect();
+
+        let time_offset = AsBinding::binds(&self.variables, self.time_variable).unwrap();
+        let gap = self.gap.as_millis() as u64;
+
+        // Events are sorted by their instants within each group, s.t.
+        // sessions can be read off in a single pass. Tuples without
+        // an instant don't belong to any session.
+        let tuples = tuples
+            .flat_map(move |tuple| {
+                let instant = match tuple[time_offset] {
+                    Value::Instant(instant) => instant,
+                    _ => return None,
+                };
+                let key: Vec<Value> = key_offsets.iter().map(|i| tuple[*i].clone()).collect();
+
+                Some((key, (instant, tuple)))
+            })
+            .reduce(move |_key, vals, output| {
+                let mut start = 0;
+                let mut last = None;
+
+                for ((instant, tuple), diff) in vals.iter() {
+                    match last {
+                        Some(last) if *instant - last <= gap => {}
+                        _ => start = *instant,
+                    }
+                    last = Some(*instant);
+
+                    let mut sessionized = tuple.clone();
+                    sessionized.push(Value::Instant(start));
+                    output.push((sessionized, *diff));
+                }
+            })
+            .map(|(_key, tuple)| tuple);
+
+        let relation = CollectionRelation {
+            variables: self.bound_variables(),
+            tuples,
+        };
+
+        (Implemented::Collection(relation), shutdown_handle)
+    }
+}
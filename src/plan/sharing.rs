@@ -0,0 +1,211 @@
+//! Planner pass sharing common subplans between rules.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+
+use crate::plan::{Join, Plan, Project};
+use crate::{AsAid, Rule, Var};
+
+/// Namespace under which shared subplans are registered as rules.
+pub const SHARED_NAMESPACE: &str = "df.shared";
+
+/// Returns true iff the given name refers to a shared subplan.
+pub fn is_shared<A: AsAid>(name: &A) -> bool {
+    name.to_string()
+        .starts_with(&format!("{}/", SHARED_NAMESPACE))
+}
+
+/// Detects join trees that occur more than once across the given
+/// rules and registers each of them as a rule of its own, under the
+/// shared namespace. All occurrences are replaced by references to
+/// the shared rule, s.t. the join is implemented only once per
+/// dataflow and can be materialized for re-use across dataflows.
+///
+/// Join trees are compared modulo variable naming.
+pub fn share_subplans<A: AsAid>(rules: &mut HashMap<A, Rule<A>>) {
+    // Canonical join trees, with their number of occurrences and
+    // the number of variables they bind.
+    let mut occurrences: BTreeMap<Plan<A>, (usize, usize)> = BTreeMap::new();
+
+    for rule in rules.values() {
+        let mut trees = Vec::new();
+        join_trees(&rule.plan, &mut trees);
+
+        for tree in trees.into_iter() {
+            let mut bound = Vec::new();
+            let canonical = canonicalize(tree, &mut bound);
+
+            let entry = occurrences.entry(canonical).or_insert((0, bound.len()));
+            entry.0 += 1;
+        }
+    }
+
+    let mut shared = HashMap::new();
+    for (canonical, (count, arity)) in occurrences.into_iter() {
+        let name = shared_name(&canonical);
+
+        if count > 1 || rules.contains_key(&name) {
+            shared.insert(canonical, (name, arity));
+        }
+    }
+
+    if shared.is_empty() {
+        return;
+    }
+
+    for rule in rules.values_mut() {
+        if !is_shared(&rule.name) {
+            rewrite(&mut rule.plan, &shared);
+        }
+    }
+
+    for (canonical, (name, arity)) in shared.into_iter() {
+        rules.entry(name.clone()).or_insert_with(|| Rule {
+            name,
+            plan: Plan::Project(Project {
+                variables: (0..arity as Var).collect(),
+                plan: Box::new(canonical),
+            }),
+            semantics: None,
+        });
+    }
+}
+
+/// Derives a stable name for a canonical join tree.
+fn shared_name<A: AsAid>(canonical: &Plan<A>) -> A {
+    let mut hasher = DefaultHasher::new();
+    canonical.hash(&mut hasher);
+
+    format!("{}/{:x}", SHARED_NAMESPACE, hasher.finish()).into()
+}
+
+/// Returns true iff the plan is a join over data patterns, or over
+/// other such joins.
+pub(crate) fn is_join_tree<A: AsAid>(plan: &Plan<A>) -> bool {
+    match *plan {
+        Plan::Join(ref join) => {
+            is_join_tree_input(&join.left_plan) && is_join_tree_input(&join.right_plan)
+        }
+        _ => false,
+    }
+}
+
+fn is_join_tree_input<A: AsAid>(plan: &Plan<A>) -> bool {
+    match *plan {
+        Plan::MatchA(..) | Plan::MatchEA(..) | Plan::MatchAV(..) => true,
+        _ => is_join_tree(plan),
+    }
+}
+
+/// Collects all maximal join trees within the given plan.
+fn join_trees<'p, A: AsAid>(plan: &'p Plan<A>, trees: &mut Vec<&'p Plan<A>>) {
+    if is_join_tree(plan) {
+        trees.push(plan);
+    } else {
+        for child in children(plan) {
+            join_trees(child, trees);
+        }
+    }
+}
+
+/// Replaces all join trees that are known to be shared by a
+/// reference to the corresponding shared rule.
+fn rewrite<A: AsAid>(plan: &mut Plan<A>, shared: &HashMap<Plan<A>, (A, usize)>) {
+    if is_join_tree(plan) {
+        let mut bound = Vec::new();
+        let canonical = canonicalize(plan, &mut bound);
+
+        if let Some((name, _arity)) = shared.get(&canonical) {
+            *plan = Plan::NameExpr(bound, name.clone());
+        }
+    } else {
+        for child in children_mut(plan) {
+            rewrite(child, shared);
+        }
+    }
+}
+
+/// Renames all variables of a join tree in order of their first
+/// appearance. Afterwards, `bound[i]` holds the original name of
+/// variable `i`.
+fn canonicalize<A: AsAid>(plan: &Plan<A>, bound: &mut Vec<Var>) -> Plan<A> {
+    fn rename(variable: Var, bound: &mut Vec<Var>) -> Var {
+        match bound.iter().position(|x| *x == variable) {
+            Some(index) => index as Var,
+            None => {
+                bound.push(variable);
+                (bound.len() - 1) as Var
+            }
+        }
+    }
+
+    match *plan {
+        Plan::Join(ref join) => {
+            let variables = join
+                .variables
+                .iter()
+                .map(|variable| rename(*variable, bound))
+                .collect();
+            let left_plan = Box::new(canonicalize(&join.left_plan, bound));
+            let right_plan = Box::new(canonicalize(&join.right_plan, bound));
+
+            Plan::Join(Join {
+                variables,
+                left_plan,
+                right_plan,
+            })
+        }
+        Plan::MatchA(e, ref a, v) => {
+            let e = rename(e, bound);
+            let v = rename(v, bound);
+
+            Plan::MatchA(e, a.clone(), v)
+        }
+        Plan::MatchEA(e, ref a, v) => Plan::MatchEA(e, a.clone(), rename(v, bound)),
+        Plan::MatchAV(e, ref a, ref v) => Plan::MatchAV(rename(e, bound), a.clone(), v.clone()),
+        _ => panic!("only join trees can be canonicalized"),
+    }
+}
+
+pub(crate) fn children<A: AsAid>(plan: &Plan<A>) -> Vec<&Plan<A>> {
+    match *plan {
+        Plan::Project(ref projection) => vec![&*projection.plan],
+        Plan::Aggregate(ref aggregate) => vec![&*aggregate.plan],
+        Plan::Union(ref union) => union.plans.iter().collect(),
+        Plan::Join(ref join) => vec![&*join.left_plan, &*join.right_plan],
+        Plan::Antijoin(ref antijoin) => vec![&*antijoin.left_plan, &*antijoin.right_plan],
+        Plan::Negate(ref plan) => vec![&**plan],
+        Plan::Filter(ref filter) => vec![&*filter.plan],
+        Plan::Transform(ref transform) => vec![&*transform.plan],
+        Plan::Pull(ref pull) => pull.paths.iter().collect(),
+        Plan::PullLevel(ref path) => vec![&*path.plan],
+        Plan::EventWindow(ref window) => vec![&*window.plan],
+        Plan::TopK(ref top_k) => vec![&*top_k.plan],
+        Plan::Session(ref session) => vec![&*session.plan],
+        Plan::Window(ref window) => vec![&*window.plan],
+        _ => Vec::new(),
+    }
+}
+
+pub(crate) fn children_mut<A: AsAid>(plan: &mut Plan<A>) -> Vec<&mut Plan<A>> {
+    match *plan {
+        Plan::Project(ref mut projection) => vec![&mut *projection.plan],
+        Plan::Aggregate(ref mut aggregate) => vec![&mut *aggregate.plan],
+        Plan::Union(ref mut union) => union.plans.iter_mut().collect(),
+        Plan::Join(ref mut join) => vec![&mut *join.left_plan, &mut *join.right_plan],
+        Plan::Antijoin(ref mut antijoin) => {
+            vec![&mut *antijoin.left_plan, &mut *antijoin.right_plan]
+        }
+        Plan::Negate(ref mut plan) => vec![&mut **plan],
+        Plan::Filter(ref mut filter) => vec![&mut *filter.plan],
+        Plan::Transform(ref mut transform) => vec![&mut *transform.plan],
+        Plan::Pull(ref mut pull) => pull.paths.iter_mut().collect(),
+        Plan::PullLevel(ref mut path) => vec![&mut *path.plan],
+        Plan::EventWindow(ref mut window) => vec![&mut *window.plan],
+        Plan::TopK(ref mut top_k) => vec![&mut *top_k.plan],
+        Plan::Session(ref mut session) => vec![&mut *session.plan],
+        Plan::Window(ref mut window) => vec![&mut *window.plan],
+        _ => Vec::new(),
+    }
+}
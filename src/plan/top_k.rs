@@ -0,0 +1,122 @@
+//! Top-k expression plan.
+
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::Reduce;
+
+use crate::binding::AsBinding;
+use crate::domain::Domain;
+use crate::plan::{Dependencies, Implementable};
+use crate::timestamp::Rewind;
+use crate::{CollectionRelation, Implemented, Relation, ShutdownHandle, Value, Var, VariableMap};
+
+/// A plan stage keeping only the first `limit` tuples of each group,
+/// when ordered by the sort variables, e.g. the ten most recent
+/// events of each user. Ties are broken by the remaining variables.
+///
+/// Results are maintained incrementally, via a reduction holding on
+/// to no more than `limit` output tuples per group.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct TopK<P: Implementable> {
+    /// Variables to bind, in order.
+    pub variables: Vec<Var>,
+    /// Plan for the data source.
+    pub plan: Box<P>,
+    /// Relation variables that determine the grouping.
+    pub key_variables: Vec<Var>,
+    /// Variables to order tuples by within each group, in order of
+    /// precedence.
+    pub sort_variables: Vec<Var>,
+    /// Whether to keep the tuples sorting last, rather than first.
+    pub descending: bool,
+    /// Maximum number of tuples to keep per group.
+    pub limit: usize,
+}
+
+impl<P: Implementable> Implementable for TopK<P> {
+    type A = P::A;
+
+    fn dependencies(&self) -> Dependencies<Self::A> {
+        self.plan.dependencies()
+    }
+
+    fn implement<'b, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        domain: &mut Domain<Self::A, S::Timestamp>,
+        local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
+    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+    where
+        S: Scope,
+        S::Timestamp: Timestamp + Lattice + Rewind,
+    {
+        let (relation, mut shutdown_handle) =
+            self.plan.implement(nested, domain, local_arrangements);
+
+        let tuples = {
+            let (projected, shutdown) = relation.projected(nested, domain, &self.variables);
+            shutdown_handle.merge_with(shutdown);
+            projected
+        };
+
+        let key_offsets: Vec<usize> = self
+            .key_variables
+            .iter()
+            .map(|x| AsBinding::binds(&self.variables, *x).unwrap())
+            .collect();
+
+        let sort_offsets: Vec<usize> = self
+            .sort_variables
+            .iter()
+            .map(|x| AsBinding::binds(&self.variables, *x).unwrap())
+            .collect();
+
+        let limit = self.limit as isize;
+        let descending = self.descending;
+
+        // Values are sorted within each group, s.t. prefixing tuples
+        // with their sort values orders them as requested.
+        let tuples = tuples
+            .map(move |tuple| {
+                let key: Vec<Value> = key_offsets.iter().map(|i| tuple[*i].clone()).collect();
+                let sort: Vec<Value> = sort_offsets.iter().map(|i| tuple[*i].clone()).collect();
+
+                (key, (sort, tuple))
+            })
+            .reduce(move |_key, vals, output| {
+                let ranked: Box<dyn Iterator<Item = _>> = if descending {
+                    Box::new(vals.iter().rev())
+                } else {
+                    Box::new(vals.iter())
+                };
+
+                let mut remaining = limit;
+                for ((_sort, tuple), diff) in ranked {
+                    if remaining <= 0 {
+                        break;
+                    }
+
+                    // Tuples occurring multiple times count as often,
+                    // those retracted more often than added not at all.
+                    if *diff <= 0 {
+                        continue;
+                    }
+
+                    let taken = std::cmp::min(*diff, remaining);
+                    output.push((tuple.clone(), taken));
+                    remaining -= taken;
+                }
+            })
+            .map(|(_key, tuple)| tuple);
+
+        let relation = CollectionRelation {
+            variables: self.variables.to_vec(),
+            tuples,
+        };
+
+        (Implemented::Collection(relation), shutdown_handle)
+    }
+}
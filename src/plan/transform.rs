@@ -21,6 +21,48 @@ pub enum Function {
     ADD,
     /// Subtracts one or more numbers from the first provided
     SUBTRACT,
+    /// Multiplies all provided numbers
+    MULTIPLY,
+    /// Divides the first provided number by one or more others,
+    /// binding nothing on division by zero
+    DIVIDE,
+    /// Truncates a unix timestamp to the date it falls on
+    DATE,
+    /// Truncates a unix timestamp to the start of the interval of the
+    /// specified width (in milliseconds) it falls into, binding
+    /// nothing for widths that aren't positive
+    BUCKET,
+}
+
+/// Milliseconds in a day.
+const MILLIS_PER_DAY: u64 = 86_400_000;
+
+/// Returns the numeric arguments to a function in positional order,
+/// i.e. constants interleaved with the values bound to variables.
+fn numeric_arguments(
+    tuple: &[Value],
+    key_offsets: &[usize],
+    constants: &[Option<Value>],
+    function: &str,
+) -> Vec<i64> {
+    let mut offsets = key_offsets.iter();
+    let mut arguments: Vec<&Value> = constants
+        .iter()
+        .filter_map(|constant| match constant {
+            Some(constant) => Some(constant),
+            None => offsets.next().map(|offset| &tuple[*offset]),
+        })
+        .collect();
+
+    arguments.extend(offsets.map(|offset| &tuple[*offset]));
+
+    arguments
+        .into_iter()
+        .map(|argument| match argument {
+            Value::Number(n) => *n,
+            _ => panic!("{} can only be applied to numbers", function),
+        })
+        .collect()
 }
 
 /// A plan stage applying a built-in function to source tuples.
@@ -190,6 +232,76 @@ impl<P: Implementable> Implementable for Transform<P> {
                     v
                 }),
             },
+            Function::MULTIPLY => CollectionRelation {
+                variables,
+                tuples: tuples.map(move |tuple| {
+                    let result: i64 =
+                        numeric_arguments(&tuple, &key_offsets, &constants_local, "MULTIPLY")
+                            .iter()
+                            .product();
+
+                    let mut v = tuple.clone();
+                    v.push(Value::Number(result));
+                    v
+                }),
+            },
+            Function::DIVIDE => CollectionRelation {
+                variables,
+                tuples: tuples.flat_map(move |tuple| {
+                    let arguments =
+                        numeric_arguments(&tuple, &key_offsets, &constants_local, "DIVIDE");
+
+                    let mut result = arguments[0];
+                    for divisor in arguments.iter().skip(1) {
+                        if *divisor == 0 {
+                            return None;
+                        }
+
+                        result /= divisor;
+                    }
+
+                    let mut v = tuple.clone();
+                    v.push(Value::Number(result));
+                    Some(v)
+                }),
+            },
+            Function::DATE => CollectionRelation {
+                variables,
+                tuples: tuples.map(move |tuple| {
+                    let date = match tuple[key_offsets[0]] {
+                        Value::Instant(inst) => (inst / MILLIS_PER_DAY) as i32,
+                        _ => panic!("DATE can only be applied to timestamps"),
+                    };
+
+                    let mut v = tuple.clone();
+                    v.push(Value::Date(date));
+                    v
+                }),
+            },
+            Function::BUCKET => CollectionRelation {
+                variables,
+                tuples: tuples.flat_map(move |tuple| {
+                    let t = match tuple[key_offsets[0]] {
+                        Value::Instant(inst) => inst,
+                        _ => panic!("BUCKET can only be applied to timestamps"),
+                    };
+
+                    let width = match constants_local[1] {
+                        Some(Value::Number(width)) => width,
+                        _ => panic!("Parameter for BUCKET must be a number"),
+                    };
+
+                    if width <= 0 {
+                        return None;
+                    }
+
+                    let width = width as u64;
+
+                    let mut v = tuple.clone();
+                    v.push(Value::Instant(t - (t % width)));
+                    Some(v)
+                }),
+            },
         };
 
         (Implemented::Collection(transformed), shutdown_handle)
@@ -0,0 +1,117 @@
+//! Transitive closure plan, for reachability along a reference
+//! attribute.
+
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::{Iterate, Join, Reduce, Threshold};
+
+use crate::domain::Domain;
+use crate::plan::{reverse_attribute, Dependencies, Implementable, Plan};
+use crate::timestamp::Rewind;
+use crate::{AsAid, CollectionRelation, Implemented, Relation, ShutdownHandle, Var, VariableMap};
+
+/// A plan stage binding [e v] for all entities v reachable from e by
+/// following a reference attribute one or more times, e.g. all
+/// ancestors of a person via `:person/parent`. Reverse attributes
+/// are followed in reverse, e.g. to find all descendants instead.
+///
+/// Given a hop limit, only entities reachable within that many steps
+/// are bound. Cycles are fine either way, as reachability is derived
+/// iteratively until nothing new is found.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct TransitiveClosure<A: AsAid> {
+    /// Variables to bind e and v to, in that order.
+    pub variables: Vec<Var>,
+    /// Reference attribute to follow.
+    pub attribute: A,
+    /// Maximum number of times to follow the attribute, if any.
+    pub max_hops: Option<usize>,
+}
+
+impl<A: AsAid> TransitiveClosure<A> {
+    /// Returns the attribute holding the references followed, which
+    /// differs from the one given if it is followed in reverse.
+    pub fn forward_attribute(&self) -> A {
+        reverse_attribute(&self.attribute).unwrap_or_else(|| self.attribute.clone())
+    }
+
+    /// Returns a plan matching a single step along the attribute.
+    pub fn edges(&self) -> Plan<A> {
+        Plan::MatchA(self.variables[0], self.attribute.clone(), self.variables[1])
+    }
+}
+
+impl<A: AsAid> Implementable for TransitiveClosure<A> {
+    type A = A;
+
+    fn dependencies(&self) -> Dependencies<A> {
+        Dependencies::attribute(self.forward_attribute())
+    }
+
+    fn implement<'b, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        domain: &mut Domain<A, S::Timestamp>,
+        local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
+    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+    where
+        S: Scope,
+        S::Timestamp: Timestamp + Lattice + Rewind,
+    {
+        assert_eq!(self.variables.len(), 2);
+
+        let (relation, mut shutdown_handle) =
+            self.edges().implement(nested, domain, local_arrangements);
+
+        let edges = {
+            let (projected, shutdown) = relation.projected(nested, domain, &self.variables);
+            shutdown_handle.merge_with(shutdown);
+            projected.map(|tuple| (tuple[0].clone(), tuple[1].clone()))
+        };
+
+        let reachable = match self.max_hops {
+            None => edges.iterate(|reached| {
+                let edges = edges.enter(&reached.scope());
+
+                reached
+                    .map(|(e, v)| (v, e))
+                    .join_map(&edges, |_via, e, v| (e.clone(), v.clone()))
+                    .concat(&edges)
+                    .distinct()
+            }),
+            Some(max_hops) => {
+                let max_hops = max_hops as u64;
+
+                // Only the fewest hops needed to reach each entity are
+                // kept, which guarantees termination on cycles.
+                edges
+                    .map(|edge| (edge, 1))
+                    .iterate(|reached| {
+                        let edges = edges.enter(&reached.scope());
+
+                        reached
+                            .filter(move |(_edge, hops)| *hops < max_hops)
+                            .map(|((e, v), hops)| (v, (e, hops)))
+                            .join_map(&edges, |_via, (e, hops), v| {
+                                ((e.clone(), v.clone()), hops + 1)
+                            })
+                            .concat(&edges.map(|edge| (edge, 1)))
+                            .reduce(|_edge, hops, output| output.push((*hops[0].0, 1)))
+                    })
+                    .map(|(edge, _hops)| edge)
+            }
+        };
+
+        let tuples = reachable.map(|(e, v)| vec![e, v]);
+
+        let relation = CollectionRelation {
+            variables: self.variables.clone(),
+            tuples,
+        };
+
+        (Implemented::Collection(relation), shutdown_handle)
+    }
+}
@@ -11,17 +11,23 @@ use crate::binding::Binding;
 use crate::domain::Domain;
 use crate::plan::{Dependencies, Implementable};
 use crate::timestamp::Rewind;
-use crate::{CollectionRelation, Implemented, Relation, ShutdownHandle, Var, VariableMap};
+use crate::{
+    CollectionRelation, Implemented, Relation, Semantics, ShutdownHandle, Var, VariableMap,
+};
 
 /// A plan stage taking the union over its sources. Frontends are
 /// responsible to ensure that the sources are union-compatible
 /// (i.e. bind all of the same variables in the same order).
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub struct Union<P: Implementable> {
-    /// TODO
+    /// Variables bound by every source, in the order of the union.
     pub variables: Vec<Var>,
     /// Plan for the data source.
     pub plans: Vec<P>,
+    /// Whether to keep the multiplicities of united tuples. Filled in
+    /// from the semantics of the enclosing rule, if unspecified.
+    #[serde(default)]
+    pub semantics: Option<Semantics>,
 }
 
 impl<P: Implementable> Implementable for Union<P> {
@@ -72,9 +78,14 @@ impl<P: Implementable> Implementable for Union<P> {
 
         let concat = nested.concatenate(streams).as_collection();
 
+        let tuples = match self.semantics {
+            Some(Semantics::Bag) => concat,
+            _ => concat.distinct(),
+        };
+
         let concatenated = CollectionRelation {
             variables: self.variables.to_vec(),
-            tuples: concat.distinct(),
+            tuples,
         };
 
         (Implemented::Collection(concatenated), shutdown_handle)
@@ -0,0 +1,630 @@
+//! Checks plans against a domain before they are implemented, s.t.
+//! mistakes are reported to clients instead of panicking during
+//! dataflow construction.
+//!
+//! Errors are attributed to the offending clause, identified by the
+//! position of each sub-plan leading to it within its parent, e.g.
+//! `[0, 1]` for the right-hand side of a join projected by the root.
+//!
+//! Whether rules agree on the variables they bind doesn't depend on
+//! the schema, and is checked as soon as they are registered.
+
+use std::collections::{HashMap, HashSet};
+
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+
+use crate::domain::Domain;
+use crate::plan::graph::MAX_ITERATIONS;
+use crate::plan::sharing::children;
+use crate::plan::{AggregationFn, GraphAlgorithm};
+use crate::plan::{Implementable, Plan};
+use crate::timestamp::Rewind;
+use crate::{AsAid, Error, Rule, ValueType, Var};
+
+/// Ensures that the plan only refers to known attributes, binds all
+/// variables it projects, negates, filters, transforms, or aggregates
+/// over, applies patterns, predicates, and functions to values of a
+/// permissible type, and doesn't ask for anything its implementation
+/// can't provide.
+pub fn validate<A, T>(plan: &Plan<A>, domain: &Domain<A, T>) -> Result<(), Error>
+where
+    A: AsAid,
+    T: Timestamp + Lattice + Rewind,
+{
+    check(plan, domain, &mut Vec::new()).map(|_bound| ())
+}
+
+/// Ensures that every branch of a union binds all of the variables
+/// it unites, and that rules referring to one another do so with as
+/// many variables as the referred rule declares in its head.
+pub fn validate_heads<A: AsAid>(rules: &HashMap<A, Rule<A>>) -> Result<(), Error> {
+    let mut names: Vec<&A> = rules.keys().collect();
+    names.sort();
+
+    for name in names.into_iter() {
+        heads(&rules[name].plan, rules, &mut Vec::new()).map_err(|mut error| {
+            error.message = format!("Rule {}: {}", name, error.message);
+            error
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Returns the variables explicitly declared by the root of a plan,
+/// which rules referring to it have to match.
+fn head<A: AsAid>(plan: &Plan<A>) -> Option<&[Var]> {
+    match *plan {
+        Plan::Project(ref projection) => Some(&projection.variables[..]),
+        Plan::Aggregate(ref aggregate) => Some(&aggregate.variables[..]),
+        Plan::Union(ref union) => Some(&union.variables[..]),
+        Plan::TopK(ref top_k) => Some(&top_k.variables[..]),
+        _ => None,
+    }
+}
+
+/// Ensures that the plan can be translated into bindings for a
+/// worst-case optimal join. Only data patterns, Hector plans, and
+/// projections, aggregations, unions, joins, negations, and
+/// transformations over those can.
+pub fn validate_bindings<A: AsAid>(plan: &Plan<A>) -> Result<(), Error> {
+    bindings(plan, &mut Vec::new())
+}
+
+fn bindings<A: AsAid>(plan: &Plan<A>, path: &mut Vec<usize>) -> Result<(), Error> {
+    match *plan {
+        Plan::Project(_)
+        | Plan::Aggregate(_)
+        | Plan::Union(_)
+        | Plan::Join(_)
+        | Plan::Negate(_)
+        | Plan::Transform(_) => {
+            for (index, child) in children(plan).into_iter().enumerate() {
+                path.push(index);
+                bindings(child, path)?;
+                path.pop();
+            }
+
+            Ok(())
+        }
+        Plan::Hector(_) | Plan::MatchA(..) | Plan::MatchEA(..) | Plan::MatchAV(..) => Ok(()),
+        _ => Err(Error::unsupported(
+            "This clause can't be implemented via worst-case optimal joins.",
+        )
+        .with_clause(path.clone())),
+    }
+}
+
+/// Checks the heads of unions and named relations within the plan,
+/// returning the variables it binds, unless they are unknown. Unlike
+/// `check`, this doesn't consult the schema.
+fn heads<A: AsAid>(
+    plan: &Plan<A>,
+    rules: &HashMap<A, Rule<A>>,
+    path: &mut Vec<usize>,
+) -> Result<Option<HashSet<Var>>, Error> {
+    if let Some(forward) = plan.reversed() {
+        return heads(&forward, rules, path);
+    }
+
+    let known = |variables: &[Var]| Some(variables.iter().cloned().collect::<HashSet<Var>>());
+    let nested = |plan: &Plan<A>, index: usize, path: &mut Vec<usize>| {
+        path.push(index);
+        let bound = heads(plan, rules, path);
+        path.pop();
+
+        bound
+    };
+
+    let bound = match *plan {
+        Plan::Project(ref projection) => {
+            nested(&projection.plan, 0, path)?;
+            known(&projection.variables)
+        }
+        Plan::Aggregate(ref aggregate) => {
+            nested(&aggregate.plan, 0, path)?;
+            known(&aggregate.variables)
+        }
+        Plan::Union(ref union) => {
+            for (index, plan) in union.plans.iter().enumerate() {
+                let inner = nested(plan, index, path)?;
+
+                if let Some(inner) = inner {
+                    if let Some(unbound) = union.variables.iter().find(|x| !inner.contains(x)) {
+                        let mut clause = path.clone();
+                        clause.push(index);
+
+                        return Err(Error::incorrect(format!(
+                            "Branch {} doesn't bind variable {}, which all branches of the union must.",
+                            index, unbound
+                        ))
+                        .with_clause(clause));
+                    }
+                }
+            }
+            known(&union.variables)
+        }
+        Plan::Join(ref join) => {
+            let left = nested(&join.left_plan, 0, path)?;
+            let right = nested(&join.right_plan, 1, path)?;
+
+            match (left, right) {
+                (Some(mut left), Some(right)) => {
+                    left.extend(right.into_iter());
+                    Some(left)
+                }
+                _ => None,
+            }
+        }
+        Plan::Antijoin(ref antijoin) => {
+            let left = nested(&antijoin.left_plan, 0, path)?;
+            nested(&antijoin.right_plan, 1, path)?;
+            left
+        }
+        Plan::Negate(ref plan) => nested(plan, 0, path)?,
+        Plan::Filter(ref filter) => nested(&filter.plan, 0, path)?,
+        Plan::Transform(ref transform) => nested(&transform.plan, 0, path)?.map(|mut inner| {
+            inner.insert(transform.result_variable);
+            inner
+        }),
+        Plan::EventWindow(ref window) => nested(&window.plan, 0, path)?,
+        Plan::TopK(ref top_k) => {
+            nested(&top_k.plan, 0, path)?;
+            known(&top_k.variables)
+        }
+        Plan::Session(ref session) => {
+            nested(&session.plan, 0, path)?;
+            known(&session.bound_variables())
+        }
+        Plan::Window(ref window) => {
+            nested(&window.plan, 0, path)?;
+            known(&window.bound_variables())
+        }
+        Plan::TransitiveClosure(ref closure) => known(&closure.variables),
+        Plan::Graph(ref graph) => known(&graph.variables),
+        Plan::Pull(ref pull) => {
+            for (index, level) in pull.paths.iter().enumerate() {
+                nested(level, index, path)?;
+            }
+            None
+        }
+        Plan::PullLevel(ref level) => {
+            nested(&level.plan, 0, path)?;
+            None
+        }
+        Plan::NameExpr(ref variables, ref name) => {
+            let declared = rules.get(name).and_then(|rule| head(&rule.plan));
+
+            if let Some(declared) = declared {
+                if declared.len() != variables.len() {
+                    return Err(Error::incorrect(format!(
+                        "Rule {} binds {} variables, but is referred to with {}.",
+                        name,
+                        declared.len(),
+                        variables.len()
+                    ))
+                    .with_clause(path.clone()));
+                }
+            }
+
+            known(variables)
+        }
+        Plan::MatchA(e, _, v) => known(&[e, v]),
+        Plan::MatchEA(_, _, v) => known(&[v]),
+        Plan::MatchAV(e, _, _) => known(&[e]),
+        #[cfg(feature = "graphql")]
+        Plan::GraphQl(_) => None,
+        _ => known(&plan.variables()),
+    };
+
+    Ok(bound)
+}
+
+/// Validates children before their parents, s.t. any problem found
+/// through recursive helpers like `type_check` must be local to the
+/// clause at hand. Returns the variables bound by the plan, unless
+/// they are unknown.
+fn check<A, T>(
+    plan: &Plan<A>,
+    domain: &Domain<A, T>,
+    path: &mut Vec<usize>,
+) -> Result<Option<HashSet<Var>>, Error>
+where
+    A: AsAid,
+    T: Timestamp + Lattice + Rewind,
+{
+    if let Some(forward) = plan.reversed() {
+        if let Err(error) = plan.type_check(domain) {
+            return Err(error.with_clause(path.clone()));
+        }
+
+        return check(&forward, domain, path);
+    }
+
+    let known = |variables: &[Var]| Some(variables.iter().cloned().collect::<HashSet<Var>>());
+
+    let bound = match *plan {
+        Plan::Project(ref projection) => {
+            let inner = child(&projection.plan, 0, domain, path)?;
+            expect_bound(&projection.variables, &inner, "projected", path)?;
+            known(&projection.variables)
+        }
+        Plan::Aggregate(ref aggregate) => {
+            let inner = child(&aggregate.plan, 0, domain, path)?;
+            expect_bound(&aggregate.key_variables, &inner, "grouped by", path)?;
+            expect_bound(
+                &aggregate.aggregation_variables,
+                &inner,
+                "aggregated over",
+                path,
+            )?;
+            expect_bound(&aggregate.with_variables, &inner, "aggregated with", path)?;
+
+            if aggregate.aggregation_fns.len() != aggregate.aggregation_variables.len() {
+                return Err(Error::incorrect(format!(
+                    "{} aggregations are applied to {} variables.",
+                    aggregate.aggregation_fns.len(),
+                    aggregate.aggregation_variables.len()
+                ))
+                .with_clause(path.clone()));
+            }
+
+            let outputs = known(&aggregate.variables);
+            expect_bound(
+                &aggregate.aggregation_variables,
+                &outputs,
+                "aggregated over",
+                path,
+            )?;
+
+            let types = aggregate.plan.value_types(domain);
+            for (aggregation_fn, variable) in aggregate
+                .aggregation_fns
+                .iter()
+                .zip(aggregate.aggregation_variables.iter())
+            {
+                let numeric = match aggregation_fn {
+                    AggregationFn::SUM | AggregationFn::AVG => {
+                        vec![ValueType::Number, ValueType::Decimal]
+                    }
+                    AggregationFn::VARIANCE => vec![ValueType::Number],
+                    _ => continue,
+                };
+
+                if let Some(value_type) = types.get(variable) {
+                    if !numeric.contains(value_type) {
+                        return Err(Error::incorrect(format!(
+                            "{:?} can't be applied to values of type {:?}.",
+                            aggregation_fn, value_type
+                        ))
+                        .with_clause(path.clone()));
+                    }
+                }
+            }
+
+            outputs
+        }
+        Plan::Union(ref union) => {
+            for (index, plan) in union.plans.iter().enumerate() {
+                let inner = child(plan, index, domain, path)?;
+
+                path.push(index);
+                expect_bound(&union.variables, &inner, "united", path)?;
+                path.pop();
+            }
+            known(&union.variables)
+        }
+        Plan::Join(ref join) => {
+            let left = child(&join.left_plan, 0, domain, path)?;
+            let right = child(&join.right_plan, 1, domain, path)?;
+            expect_bound(&join.variables, &left, "joined on", path)?;
+            expect_bound(&join.variables, &right, "joined on", path)?;
+
+            if join.variables.is_empty() {
+                return Err(Error::unsupported(
+                    "Joins must be on at least one variable, cross products aren't supported.",
+                )
+                .with_clause(path.clone()));
+            }
+
+            // Two data patterns are joined directly via their
+            // indices, which only works on a single variable.
+            if let (Plan::MatchA(..), Plan::MatchA(..)) = (&*join.left_plan, &*join.right_plan) {
+                if join.variables.len() != 1 {
+                    return Err(Error::unsupported(
+                        "Joins between two data patterns must be on exactly one variable.",
+                    )
+                    .with_clause(path.clone()));
+                }
+            }
+
+            match (left, right) {
+                (Some(mut left), Some(right)) => {
+                    left.extend(right.into_iter());
+                    Some(left)
+                }
+                _ => None,
+            }
+        }
+        Plan::Antijoin(ref antijoin) => {
+            let left = child(&antijoin.left_plan, 0, domain, path)?;
+            let right = child(&antijoin.right_plan, 1, domain, path)?;
+            expect_bound(&antijoin.variables, &left, "negated", path)?;
+            expect_bound(&antijoin.variables, &right, "negated", path)?;
+            left
+        }
+        Plan::Negate(ref plan) => child(plan, 0, domain, path)?,
+        Plan::Filter(ref filter) => {
+            let inner = child(&filter.plan, 0, domain, path)?;
+            expect_bound(&filter.variables, &inner, "filtered", path)?;
+            inner
+        }
+        Plan::Transform(ref transform) => {
+            let inner = child(&transform.plan, 0, domain, path)?;
+            expect_bound(&transform.variables, &inner, "transformed", path)?;
+            inner.map(|mut inner| {
+                inner.insert(transform.result_variable);
+                inner
+            })
+        }
+        Plan::MatchA(e, _, v) => known(&[e, v]),
+        Plan::MatchEA(_, _, v) => known(&[v]),
+        Plan::MatchAV(e, ref a, ref v) => {
+            let expected = domain
+                .attributes
+                .get(a)
+                .and_then(|config| config.value_type);
+
+            match (expected, v.value_type()) {
+                (Some(expected), Some(actual)) if expected != actual => {
+                    return Err(Error::incorrect(format!(
+                        "Attribute {} holds values of type {:?}, not {:?}.",
+                        a, expected, actual
+                    ))
+                    .with_clause(path.clone()));
+                }
+                _ => known(&[e]),
+            }
+        }
+        Plan::Pull(ref pull) => {
+            for (index, level) in pull.paths.iter().enumerate() {
+                child(level, index, domain, path)?;
+            }
+            None
+        }
+        Plan::PullLevel(ref level) => {
+            let inner = child(&level.plan, 0, domain, path)?;
+            expect_bound(&[level.pull_variable], &inner, "pulled from", path)?;
+            None
+        }
+        Plan::EventWindow(ref window) => child(&window.plan, 0, domain, path)?,
+        Plan::TopK(ref top_k) => {
+            let inner = child(&top_k.plan, 0, domain, path)?;
+            expect_bound(&top_k.variables, &inner, "projected", path)?;
+
+            let outputs = known(&top_k.variables);
+            expect_bound(&top_k.key_variables, &outputs, "grouped by", path)?;
+            expect_bound(&top_k.sort_variables, &outputs, "sorted by", path)?;
+
+            if top_k.limit == 0 {
+                return Err(
+                    Error::incorrect("TopK plans must keep at least one tuple per group.")
+                        .with_clause(path.clone()),
+                );
+            }
+
+            outputs
+        }
+        Plan::Session(ref session) => {
+            let inner = child(&session.plan, 0, domain, path)?;
+            expect_bound(&session.variables, &inner, "projected", path)?;
+
+            let outputs = known(&session.variables);
+            expect_bound(&session.key_variables, &outputs, "grouped by", path)?;
+            expect_bound(
+                &[session.time_variable],
+                &outputs,
+                "delimiting sessions",
+                path,
+            )?;
+
+            if session.variables.contains(&session.session_variable) {
+                return Err(Error::incorrect(format!(
+                    "Session variable {} is bound already.",
+                    session.session_variable
+                ))
+                .with_clause(path.clone()));
+            }
+
+            known(&session.bound_variables())
+        }
+        Plan::Window(ref window) => {
+            let inner = child(&window.plan, 0, domain, path)?;
+            expect_bound(&window.variables, &inner, "projected", path)?;
+
+            let outputs = known(&window.variables);
+            expect_bound(
+                &[window.time_variable],
+                &outputs,
+                "delimiting windows",
+                path,
+            )?;
+
+            if window.variables.contains(&window.window_variable) {
+                return Err(Error::incorrect(format!(
+                    "Window variable {} is bound already.",
+                    window.window_variable
+                ))
+                .with_clause(path.clone()));
+            }
+
+            // Windows are measured in milliseconds, like instants.
+            let empty = |duration: &std::time::Duration| duration.as_millis() == 0;
+            if empty(&window.size) || window.hop.as_ref().map_or(false, empty) {
+                return Err(
+                    Error::incorrect("Windows must span and hop at least a millisecond.")
+                        .with_clause(path.clone()),
+                );
+            }
+
+            known(&window.bound_variables())
+        }
+        Plan::Hector(ref hector) => {
+            if hector.bindings.is_empty() || hector.variables.is_empty() {
+                return Err(
+                    Error::incorrect("Hector plans require both bindings and variables.")
+                        .with_clause(path.clone()),
+                );
+            }
+
+            known(&hector.variables)
+        }
+        Plan::NameExpr(ref variables, _) => known(variables),
+        Plan::Parameter(ref variables, ref name) => {
+            if !domain.parameters.contains_key(name) {
+                return Err(Error::not_found(format!("Unknown parameter {}.", name))
+                    .with_clause(path.clone()));
+            }
+
+            known(variables)
+        }
+        Plan::PullAll(ref pull) => {
+            if pull.pull_attributes.is_empty() {
+                return Err(Error::incorrect("PullAll requires attributes to pull.")
+                    .with_clause(path.clone()));
+            }
+
+            known(&pull.variables)
+        }
+        Plan::History(ref history) => {
+            let a = &history.attribute;
+            if domain.has_attribute(a) && !domain.keeps_history(a) {
+                return Err(Error::unsupported(format!(
+                    "Attribute {} does not keep its history, create it without trace slack.",
+                    a
+                ))
+                .with_clause(path.clone()));
+            }
+
+            expect_arity(&history.variables, 4, "History", path)?;
+            known(&history.variables)
+        }
+        Plan::Fulltext(ref fulltext) => {
+            expect_arity(&fulltext.variables, 2, "Fulltext", path)?;
+            known(&fulltext.variables)
+        }
+        Plan::Range(ref range) => {
+            expect_arity(&range.variables, 2, "Range", path)?;
+            known(&range.variables)
+        }
+        Plan::TransitiveClosure(ref closure) => {
+            expect_arity(&closure.variables, 2, "TransitiveClosure", path)?;
+
+            if closure.max_hops == Some(0) {
+                return Err(Error::incorrect(
+                    "TransitiveClosure plans must follow at least one hop.",
+                )
+                .with_clause(path.clone()));
+            }
+
+            known(&closure.variables)
+        }
+        Plan::Graph(ref graph) => {
+            expect_arity(&graph.variables, 2, "Graph", path)?;
+
+            if let GraphAlgorithm::PageRank { iterations } = graph.algorithm {
+                if iterations == 0 {
+                    return Err(
+                        Error::incorrect("PageRank requires at least one iteration.")
+                            .with_clause(path.clone()),
+                    );
+                }
+
+                if iterations > MAX_ITERATIONS {
+                    return Err(Error::unsupported(format!(
+                        "PageRank is limited to {} iterations.",
+                        MAX_ITERATIONS
+                    ))
+                    .with_clause(path.clone()));
+                }
+            }
+
+            known(&graph.variables)
+        }
+        #[cfg(feature = "graphql")]
+        Plan::GraphQl(_) => None,
+    };
+
+    // Children have been checked already, anything reported from here
+    // on is due to this clause.
+    let local = || -> Result<(), Error> {
+        for aid in plan.dependencies().attributes.iter() {
+            if !domain.has_attribute(aid) {
+                return Err(Error::not_found(format!("Unknown attribute {}.", aid)));
+            }
+        }
+
+        plan.type_check(domain)
+    };
+
+    local().map_err(|error| error.with_clause(path.clone()))?;
+
+    Ok(bound)
+}
+
+/// Checks a sub-plan at the specified position within its parent.
+fn child<A, T>(
+    plan: &Plan<A>,
+    index: usize,
+    domain: &Domain<A, T>,
+    path: &mut Vec<usize>,
+) -> Result<Option<HashSet<Var>>, Error>
+where
+    A: AsAid,
+    T: Timestamp + Lattice + Rewind,
+{
+    path.push(index);
+    let bound = check(plan, domain, path);
+    path.pop();
+
+    bound
+}
+
+/// Ensures that a plan binds exactly the expected number of
+/// variables.
+fn expect_arity(variables: &[Var], arity: usize, plan: &str, path: &[usize]) -> Result<(), Error> {
+    if variables.len() != arity {
+        Err(Error::incorrect(format!(
+            "{} plans bind exactly {} variables, not {}.",
+            plan,
+            arity,
+            variables.len()
+        ))
+        .with_clause(path.to_vec()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Ensures that all the specified variables are bound, as far as the
+/// bound variables are known.
+fn expect_bound(
+    variables: &[Var],
+    bound: &Option<HashSet<Var>>,
+    usage: &str,
+    path: &[usize],
+) -> Result<(), Error> {
+    if let Some(bound) = bound {
+        if let Some(unbound) = variables.iter().find(|x| !bound.contains(x)) {
+            return Err(Error::incorrect(format!(
+                "Variable {} is {} but never bound.",
+                unbound, usage
+            ))
+            .with_clause(path.to_vec()));
+        }
+    }
+
+    Ok(())
+}
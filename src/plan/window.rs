@@ -0,0 +1,144 @@
+//! Tumbling and hopping window plan.
+
+use std::time::Duration;
+
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::{Join, Reduce, Threshold};
+
+use crate::binding::AsBinding;
+use crate::domain::Domain;
+use crate::plan::{Dependencies, Implementable};
+use crate::timestamp::Rewind;
+use crate::{CollectionRelation, Implemented, Relation, ShutdownHandle, Value, Var, VariableMap};
+
+/// A plan stage assigning tuples to fixed-size windows on an instant
+/// they carry, e.g. to count events per five-minute bucket. Windows
+/// start every `hop` and are `size` long, s.t. tuples belong to as
+/// many windows as overlap their instant. Without a hop, windows are
+/// tumbling, i.e. adjacent and non-overlapping.
+///
+/// Tuples are extended by the instant their window starts at. Given
+/// a retention horizon, windows starting more than `retention`
+/// before the latest window are retracted, along with everything
+/// derived from them.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Window<P: Implementable> {
+    /// Variables to bind, in order, followed by the window variable.
+    pub variables: Vec<Var>,
+    /// Plan for the data source.
+    pub plan: Box<P>,
+    /// Variable bound to the instant of each tuple.
+    pub time_variable: Var,
+    /// Length of each window.
+    pub size: Duration,
+    /// Time between the starts of subsequent windows, if other than
+    /// their size.
+    pub hop: Option<Duration>,
+    /// How far behind the latest window others are retained, if not
+    /// forever.
+    pub retention: Option<Duration>,
+    /// Variable to bind the instant each window starts at to.
+    pub window_variable: Var,
+}
+
+impl<P: Implementable> Window<P> {
+    /// Returns the variables bound by this stage.
+    pub fn bound_variables(&self) -> Vec<Var> {
+        let mut variables = self.variables.clone();
+        variables.push(self.window_variable);
+        variables
+    }
+}
+
+impl<P: Implementable> Implementable for Window<P> {
+    type A = P::A;
+
+    fn dependencies(&self) -> Dependencies<Self::A> {
+        self.plan.dependencies()
+    }
+
+    fn implement<'b, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        domain: &mut Domain<Self::A, S::Timestamp>,
+        local_arrangements: &VariableMap<Self::A, Iterative<'b, S, u64>>,
+    ) -> (Implemented<'b, Self::A, S>, ShutdownHandle)
+    where
+        S: Scope,
+        S::Timestamp: Timestamp + Lattice + Rewind,
+    {
+        let (relation, mut shutdown_handle) =
+            self.plan.implement(nested, domain, local_arrangements);
+
+        let tuples = {
+            let (projected, shutdown) = relation.projected(nested, domain, &self.variables);
+            shutdown_handle.merge_with(shutdown);
+            projected
+        };
+
+        let time_offset = AsBinding::binds(&self.variables, self.time_variable).unwrap();
+        let size = self.size.as_millis() as u64;
+        let hop = self.hop.map_or(size, |hop| hop.as_millis() as u64);
+
+        // Walks back from the latest window containing the instant,
+        // for as long as windows still overlap it. Tuples without an
+        // instant don't belong to any window.
+        let windowed = tuples.flat_map(move |tuple| {
+            let instant = match tuple[time_offset] {
+                Value::Instant(instant) => instant,
+                _ => return Vec::new(),
+            };
+
+            let mut windows = Vec::new();
+            let mut start = Some(instant - instant % hop);
+            while let Some(window) = start.filter(|window| window + size > instant) {
+                windows.push((window, tuple.clone()));
+                start = window.checked_sub(hop);
+            }
+
+            windows
+        });
+
+        let windowed = match self.retention {
+            None => windowed,
+            Some(retention) => {
+                let retention = retention.as_millis() as u64;
+
+                // Only the distinct window starts are brought together
+                // on a single worker to determine the latest one.
+                let retained = windowed
+                    .map(|(window, _tuple)| ((), window))
+                    .distinct()
+                    .reduce(move |_key, windows, output| {
+                        let (latest, _) = windows[windows.len() - 1];
+                        let horizon = latest.saturating_sub(retention);
+
+                        for (window, _) in windows.iter() {
+                            if **window >= horizon {
+                                output.push((**window, 1));
+                            }
+                        }
+                    })
+                    .map(|((), window)| window);
+
+                windowed.semijoin(&retained)
+            }
+        };
+
+        let tuples = windowed.map(|(window, mut tuple)| {
+            tuple.push(Value::Instant(window));
+            tuple
+        });
+
+        let relation = CollectionRelation {
+            variables: self.bound_variables(),
+            tuples,
+        };
+
+        (Implemented::Collection(relation), shutdown_handle)
+    }
+}
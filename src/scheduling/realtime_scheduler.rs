@@ -104,6 +104,10 @@ impl Iterator for RealtimeScheduler {
 pub enum Event {
     /// A domain tick.
     Tick,
+    /// A check for relations that should be re-planned.
+    Replan,
+    /// A compacted snapshot of all attributes.
+    Snapshot,
 }
 
 /// A thing that can be scheduled at an instant. Scheduling this
@@ -0,0 +1,207 @@
+//! Authentication of clients and authorization of their requests.
+//!
+//! Clients authenticate by sending an `Authenticate` request carrying
+//! a token, which the access control list maps to a principal. Every
+//! later request is checked against the operations granted to that
+//! principal, on the namespaces of the attributes and relations it
+//! touches. A namespace covers all names equal to it or starting with
+//! it followed by a slash, e.g. `:user` covers `:user/name`, and `*`
+//! covers everything.
+
+use std::collections::HashMap;
+
+use crate::server::Request;
+use crate::{AsAid, Error, Plan};
+
+/// Operations that can be granted on a namespace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Operation {
+    /// Subscribing to, querying, and exporting attributes and
+    /// relations.
+    Read,
+    /// Sending inputs to attributes and parameters.
+    Transact,
+    /// Creating, registering, changing, and removing attributes and
+    /// relations.
+    Create,
+}
+
+/// Operations granted to a principal on a namespace.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Grant {
+    /// The principal operations are granted to.
+    pub principal: String,
+    /// The namespace operations are granted on.
+    pub namespace: String,
+    /// The operations granted.
+    pub operations: Vec<Operation>,
+}
+
+/// Access control list, mapping tokens to principals and principals
+/// to the operations they are allowed to perform.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Acl {
+    /// Mapping from tokens to the principals they authenticate.
+    pub tokens: HashMap<String, String>,
+    /// All grants, to any principal.
+    pub grants: Vec<Grant>,
+}
+
+/// The namespace covering everything.
+const ANY: &str = "*";
+
+fn covers(namespace: &str, name: &str) -> bool {
+    namespace == ANY
+        || name == namespace
+        || (name.starts_with(namespace) && name[namespace.len()..].starts_with('/'))
+}
+
+impl Acl {
+    /// Returns the principal the specified token belongs to.
+    pub fn authenticate(&self, token: &str) -> Result<String, Error> {
+        self.tokens
+            .get(token)
+            .cloned()
+            .ok_or_else(|| Error::forbidden("Unknown token."))
+    }
+
+    /// Returns true iff the principal may perform the operation on
+    /// the specified name.
+    pub fn allows(&self, principal: &str, operation: Operation, name: &str) -> bool {
+        self.grants.iter().any(|grant| {
+            grant.principal == principal
+                && grant.operations.contains(&operation)
+                && covers(&grant.namespace, name)
+        })
+    }
+
+    /// Returns true iff the principal may perform the operation on
+    /// every namespace.
+    pub fn allows_all(&self, principal: &str, operation: Operation) -> bool {
+        self.grants.iter().any(|grant| {
+            grant.principal == principal
+                && grant.operations.contains(&operation)
+                && grant.namespace == ANY
+        })
+    }
+
+    /// Checks whether the specified principal, if any, may issue the
+    /// request. Requests by clients that haven't authenticated are
+    /// only allowed if they touch nothing protected.
+    pub fn authorize<A>(&self, principal: Option<&str>, req: &Request<A>) -> Result<(), Error>
+    where
+        A: AsAid + From<&'static str>,
+    {
+        let required = requirements(req);
+
+        let principal = match principal {
+            Some(principal) => principal,
+            None if required.is_empty() => return Ok(()),
+            None => return Err(Error::forbidden("Client has not authenticated.")),
+        };
+
+        for (operation, name) in required {
+            let allowed = match name {
+                None => self.allows_all(principal, operation),
+                Some(ref name) => self.allows(principal, operation, name),
+            };
+
+            if !allowed {
+                return Err(Error::forbidden(format!(
+                    "{} may not {:?} {}.",
+                    principal,
+                    operation,
+                    name.as_ref().map(String::as_str).unwrap_or("everything")
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the operations a request performs, along with the names
+/// they are performed on. Operations on everything are marked by the
+/// absence of a name.
+fn requirements<A>(req: &Request<A>) -> Vec<(Operation, Option<String>)>
+where
+    A: AsAid + From<&'static str>,
+{
+    use self::Operation::*;
+
+    let on = |operation, name: &dyn ToString| (operation, Some(name.to_string()));
+    let reads = |plan: &Plan<A>| {
+        let dependencies = plan.dependencies();
+        dependencies
+            .attributes
+            .iter()
+            .chain(dependencies.names.iter())
+            .map(|name| on(Read, name))
+            .collect::<Vec<_>>()
+    };
+
+    match req {
+        Request::Transact(datoms) => datoms.iter().map(|datom| on(Transact, &datom.1)).collect(),
+        Request::TransactWithMeta(datoms, meta) => datoms
+            .iter()
+            .map(|datom| on(Transact, &datom.1))
+            .chain(meta.iter().map(|(a, _v)| on(Transact, a)))
+            .collect(),
+        Request::Subscribe(name) => vec![on(Read, name)],
+        #[cfg(feature = "graphql")]
+        Request::Derive(namespace, _) => vec![on(Create, namespace)],
+        // The server authorizes the requests GraphQL requests expand
+        // to, this only guards against unexpanded ones.
+        #[cfg(feature = "graphql")]
+        Request::GraphQl(req) => vec![on(Create, &req.name)],
+        Request::Interest(req) => vec![on(Read, &req.name)],
+        Request::Explain(req) => reads(&req.plan),
+        Request::Query(req) => reads(&req.plan),
+        Request::Replan(req) => vec![on(Create, &req.name)],
+        Request::Register(req) => req
+            .rules
+            .iter()
+            .map(|rule| on(Create, &rule.name))
+            .chain(req.publish.iter().map(|name| on(Create, name)))
+            .chain(req.datalog.iter().map(|rule| on(Create, &rule.name)))
+            .collect(),
+        Request::Materialize(name) | Request::Unregister(name) => vec![on(Create, name)],
+        Request::CreateAttribute(req) => vec![on(Create, &req.name)],
+        Request::BulkLoad(req) => vec![on(Transact, &req.name)],
+        Request::SetTraceSlack(name, _)
+        | Request::DropAttribute(name)
+        | Request::Evict(name)
+        | Request::CreateParameter(name)
+        | Request::CloseInput(name) => vec![on(Create, name)],
+        Request::Bind(req) => vec![on(Transact, &req.name)],
+        Request::Bridge(req) => vec![on(Read, &req.source), on(Create, &req.name)],
+        Request::CreateIndex(req) => std::iter::once(on(Create, &req.name))
+            .chain(req.attributes.iter().map(|name| on(Read, name)))
+            .collect(),
+        Request::Backup(req) => vec![on(Read, &req.name)],
+        Request::Restore(req) => vec![on(Transact, &req.name)],
+        // Retractions may touch any attribute.
+        Request::RetractEntity(_, _) => vec![(Transact, None)],
+        // Sources may publish anything.
+        Request::RegisterSource(_) => vec![(Create, None)],
+        // Administrative requests affect everyone.
+        Request::AdvanceDomain(_, _)
+        | Request::Setup
+        | Request::Shutdown
+        | Request::Drained(_)
+        | Request::Rendezvous(_)
+        | Request::Snapshot
+        | Request::RestoreSnapshot(_)
+        | Request::Reconfigure(_)
+        | Request::Kill(_) => vec![(Create, None)],
+        // Sizes reveal the names of everything arranged.
+        Request::IndexSizes => vec![(Read, None)],
+        Request::Authenticate(_)
+        | Request::Resnapshot(_)
+        | Request::Uninterest(_)
+        | Request::Tick
+        | Request::Disconnect
+        | Request::Schema
+        | Request::Status => vec![],
+    }
+}
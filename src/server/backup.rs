@@ -0,0 +1,108 @@
+//! Exports of single attributes, for operational backups and for
+//! cloning data into other environments.
+//!
+//! A backup is a directory holding one part per worker, each of which
+//! carries the attribute's configuration and the time as of which
+//! its contents were read. Backups are written to and read from each
+//! process's local filesystem.
+
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{AttributeConfig, Error, Time, Value};
+
+/// The share of a backup written by a single worker.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupPart<A> {
+    /// Name of the exported attribute.
+    pub name: A,
+    /// Configuration of the exported attribute, s.t. it can be
+    /// re-created elsewhere.
+    pub config: AttributeConfig,
+    /// Time as of which the attribute's contents were read.
+    pub as_of: Time,
+    /// Index of the worker that wrote this part.
+    pub worker: usize,
+    /// Consolidated (e, v, count) contents.
+    pub tuples: Vec<(Value, Value, isize)>,
+}
+
+/// Writes a part of a backup into the specified directory. Parts
+/// only appear under their final name once they have been written
+/// completely.
+pub fn write_part<A, P>(directory: P, part: &BackupPart<A>) -> Result<(), Error>
+where
+    A: Serialize,
+    P: AsRef<Path>,
+{
+    fs::create_dir_all(&directory).map_err(Error::fault)?;
+
+    let path = directory
+        .as_ref()
+        .join(format!("worker-{}.json", part.worker));
+    let pending = path.with_extension("pending");
+
+    let contents = serde_json::to_vec(part).map_err(Error::fault)?;
+
+    let mut file = File::create(&pending).map_err(Error::fault)?;
+    file.write_all(&contents).map_err(Error::fault)?;
+    file.sync_all().map_err(Error::fault)?;
+
+    fs::rename(&pending, &path).map_err(Error::fault)
+}
+
+/// Reads all parts of the backup in the specified directory,
+/// ordered by worker. Parts can be read by any number of workers,
+/// regardless of how many wrote them.
+pub fn read<A, P>(directory: P) -> Result<Vec<BackupPart<A>>, Error>
+where
+    A: DeserializeOwned,
+    P: AsRef<Path>,
+{
+    let directory = directory.as_ref();
+
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(ref error) if error.kind() == ErrorKind::NotFound => {
+            return Err(Error::not_found(format!(
+                "Backup {} does not exist.",
+                directory.display()
+            )))
+        }
+        Err(error) => return Err(Error::fault(error)),
+    };
+
+    let mut parts = Vec::new();
+
+    for entry in entries {
+        let path = entry.map_err(Error::fault)?.path();
+
+        // Parts that are still being written have a different
+        // extension.
+        if path.extension().map(|x| x != "json").unwrap_or(true) {
+            continue;
+        }
+
+        let contents = fs::read(&path).map_err(Error::fault)?;
+        let part: BackupPart<A> = serde_json::from_slice(&contents).map_err(|error| {
+            Error::incorrect(format!("Corrupt backup part {}: {}", path.display(), error))
+        })?;
+
+        parts.push(part);
+    }
+
+    if parts.is_empty() {
+        return Err(Error::not_found(format!(
+            "Backup {} holds no parts.",
+            directory.display()
+        )));
+    }
+
+    parts.sort_by_key(|part| part.worker);
+
+    Ok(parts)
+}
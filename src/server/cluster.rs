@@ -0,0 +1,190 @@
+//! Configuration for running a server across multiple processes, as
+//! well as the rendezvous through which all workers make sure they
+//! agree on a common setup, before any client requests are accepted.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use crate::Error;
+
+/// Port at which the first process of a local cluster listens for
+/// its peers. Further processes use consecutive ports.
+pub const BASE_PORT: u16 = 2101;
+
+/// Describes the processes making up a cluster, and which one of
+/// them is the current process.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClusterConfiguration {
+    /// Number of worker threads per process.
+    pub threads: usize,
+    /// Addresses of all processes, ordered by process id.
+    pub addresses: Vec<String>,
+    /// Id of the current process within the cluster.
+    pub process: usize,
+    /// Whether to report connection progress.
+    pub report: bool,
+}
+
+impl Default for ClusterConfiguration {
+    fn default() -> Self {
+        ClusterConfiguration::local(1, 1, 0)
+    }
+}
+
+impl ClusterConfiguration {
+    /// Creates a configuration for a cluster whose processes all run
+    /// on the local host.
+    pub fn local(threads: usize, processes: usize, process: usize) -> Self {
+        let addresses = (0..processes)
+            .map(|index| format!("localhost:{}", BASE_PORT as usize + index))
+            .collect();
+
+        ClusterConfiguration {
+            threads,
+            addresses,
+            process,
+            report: false,
+        }
+    }
+
+    /// Creates a configuration for the specified process of a
+    /// cluster spanning the given addresses.
+    pub fn with_addresses(threads: usize, addresses: Vec<String>, process: usize) -> Self {
+        ClusterConfiguration {
+            threads,
+            addresses,
+            process,
+            report: false,
+        }
+    }
+
+    /// Reads the addresses of the first `processes` processes from a
+    /// file holding one address per line.
+    pub fn read_addresses(path: &str, processes: usize) -> Result<Vec<String>, Error> {
+        let file = File::open(path)
+            .map_err(|error| Error::not_found(format!("Failed to open {}: {}", path, error)))?;
+
+        let mut addresses = Vec::new();
+        for line in BufReader::new(file).lines().take(processes) {
+            let line =
+                line.map_err(|error| Error::fault(format!("Failed to read {}: {}", path, error)))?;
+
+            addresses.push(line.trim().to_string());
+        }
+
+        if addresses.len() < processes {
+            return Err(Error::incorrect(format!(
+                "Could only read {} addresses from {}, but expected {}.",
+                addresses.len(),
+                path,
+                processes
+            )));
+        }
+
+        Ok(addresses)
+    }
+
+    /// Number of processes in the cluster.
+    pub fn processes(&self) -> usize {
+        self.addresses.len()
+    }
+
+    /// Number of workers across all processes.
+    pub fn peers(&self) -> usize {
+        self.threads * self.processes()
+    }
+
+    /// Checks that the configuration describes a valid cluster,
+    /// including the current process.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.threads == 0 {
+            Err(Error::incorrect("Processes need at least one thread."))
+        } else if self.addresses.is_empty() {
+            Err(Error::incorrect("Clusters need at least one process."))
+        } else if self.process >= self.processes() {
+            Err(Error::incorrect(format!(
+                "Process id {} is out of range for {} processes.",
+                self.process,
+                self.processes()
+            )))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Into<timely::Configuration> for ClusterConfiguration {
+    fn into(self) -> timely::Configuration {
+        if self.processes() > 1 {
+            timely::Configuration::Cluster {
+                threads: self.threads,
+                process: self.process,
+                addresses: self.addresses,
+                report: self.report,
+                log_fn: Box::new(|_| None),
+            }
+        } else if self.threads > 1 {
+            timely::Configuration::Process(self.threads)
+        } else {
+            timely::Configuration::Thread
+        }
+    }
+}
+
+/// Announces a worker to all others. Rendezvous requests must be
+/// sequenced like any other request, s.t. all workers observe them
+/// before any requests issued by clients.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Rendezvous {
+    /// Index of the announced worker.
+    pub worker: usize,
+    /// Fingerprint of the announced worker's server configuration.
+    pub fingerprint: u64,
+}
+
+/// Keeps track of the workers that have joined the cluster so far.
+#[derive(Clone, Debug)]
+pub struct Membership {
+    peers: usize,
+    fingerprint: u64,
+    joined: HashSet<usize>,
+}
+
+impl Membership {
+    /// Creates a new membership for the specified number of workers,
+    /// each of which is expected to announce the given configuration
+    /// fingerprint.
+    pub fn new(peers: usize, fingerprint: u64) -> Self {
+        Membership {
+            peers,
+            fingerprint,
+            joined: HashSet::new(),
+        }
+    }
+
+    /// Handles a Rendezvous request. Workers running with a
+    /// different configuration would register attributes and
+    /// queries differently, and are therefore refused.
+    pub fn join(&mut self, rendezvous: &Rendezvous) -> Result<(), Error> {
+        if rendezvous.worker >= self.peers {
+            Err(Error::incorrect(format!(
+                "Worker {} is not part of the cluster.",
+                rendezvous.worker
+            )))
+        } else if rendezvous.fingerprint != self.fingerprint {
+            Err(Error::conflict(format!(
+                "Worker {} runs with a different server configuration.",
+                rendezvous.worker
+            )))
+        } else {
+            self.joined.insert(rendezvous.worker);
+            Ok(())
+        }
+    }
+
+    /// Returns true iff all workers have joined.
+    pub fn is_complete(&self) -> bool {
+        self.joined.len() == self.peers
+    }
+}
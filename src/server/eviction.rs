@@ -0,0 +1,97 @@
+//! Offloading of cold attributes to disk, for datasets exceeding
+//! memory.
+//!
+//! Each worker writes its share of an evicted attribute's updates,
+//! with their (compacted) times, into a directory per attribute. The
+//! share is read back by the same worker once a query requires the
+//! attribute again, so attributes must be paged back in by the same
+//! number of workers that evicted them.
+
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{Error, Time, Value};
+
+/// The share of an evicted attribute held by a single worker.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvictedPart<A> {
+    /// Name of the evicted attribute.
+    pub name: A,
+    /// Index of the worker that wrote this part.
+    pub worker: usize,
+    /// ((e, v), time, diff) updates, as held by the attribute's
+    /// forward index.
+    pub updates: Vec<((Value, Value), Time, isize)>,
+}
+
+/// Returns where the specified worker's part of an attribute lives
+/// within the eviction directory. Attribute names are hex-encoded,
+/// as they typically contain path separators.
+fn part_path<P: AsRef<Path>>(directory: P, name: &str, worker: usize) -> PathBuf {
+    let encoded: String = name.bytes().map(|byte| format!("{:02x}", byte)).collect();
+
+    directory
+        .as_ref()
+        .join(encoded)
+        .join(format!("worker-{}.json", worker))
+}
+
+/// Writes a worker's part of an evicted attribute. Parts only
+/// appear under their final name once they have been written
+/// completely.
+pub fn write_part<A, P>(directory: P, part: &EvictedPart<A>) -> Result<(), Error>
+where
+    A: Serialize + ToString,
+    P: AsRef<Path>,
+{
+    let path = part_path(directory, &part.name.to_string(), part.worker);
+    let pending = path.with_extension("pending");
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(Error::fault)?;
+    }
+
+    let contents = serde_json::to_vec(part).map_err(Error::fault)?;
+
+    let mut file = File::create(&pending).map_err(Error::fault)?;
+    file.write_all(&contents).map_err(Error::fault)?;
+    file.sync_all().map_err(Error::fault)?;
+
+    fs::rename(&pending, &path).map_err(Error::fault)
+}
+
+/// Reads and removes a worker's part of an evicted attribute.
+pub fn take_part<A, P>(directory: P, name: &str, worker: usize) -> Result<EvictedPart<A>, Error>
+where
+    A: DeserializeOwned,
+    P: AsRef<Path>,
+{
+    let path = part_path(directory, name, worker);
+
+    let contents = match fs::read(&path) {
+        Ok(contents) => contents,
+        Err(ref error) if error.kind() == ErrorKind::NotFound => {
+            return Err(Error::not_found(format!(
+                "Attribute {} has no evicted part for worker {}.",
+                name, worker
+            )))
+        }
+        Err(error) => return Err(Error::fault(error)),
+    };
+
+    let part = serde_json::from_slice(&contents).map_err(|error| {
+        Error::incorrect(format!(
+            "Corrupt evicted part {}: {}",
+            path.display(),
+            error
+        ))
+    })?;
+
+    fs::remove_file(&path).map_err(Error::fault)?;
+
+    Ok(part)
+}
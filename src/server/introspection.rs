@@ -0,0 +1,152 @@
+//! Server metadata, exposed as attributes of the system domain s.t.
+//! it can be queried and subscribed to like any other data. A
+//! dashboard might for example subscribe to all registered queries
+//! and the number of clients interested in each:
+//!
+//! ```text
+//! [:find ?name ?clients
+//!  :where [?q :df.query/name ?name] [?q :df.query/clients ?clients]]
+//! ```
+//!
+//! Attributes and queries are entities of their own, whose ids are
+//! derived from their names. Metadata is refreshed whenever the
+//! server advances its epochs, by retracting whatever changed since
+//! the last refresh.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::ExchangeData;
+
+use crate::domain::Domain;
+use crate::timestamp::Rewind;
+use crate::{AsAid, Datom, Eid, Value, ValueType};
+
+/// Name of an attribute.
+pub const ATTRIBUTE_NAME: &str = ":df.attribute/name";
+/// Type of the values an attribute holds, if declared.
+pub const ATTRIBUTE_VALUE_TYPE: &str = ":df.attribute/value-type";
+/// How inputs to an attribute are interpreted.
+pub const ATTRIBUTE_INPUT_SEMANTICS: &str = ":df.attribute/input-semantics";
+/// Whether an attribute still accepts transactions, as opposed to
+/// being fed by a source or having its input closed.
+pub const ATTRIBUTE_TRANSACTABLE: &str = ":df.attribute/transactable";
+/// Name of a registered query.
+pub const QUERY_NAME: &str = ":df.query/name";
+/// Names of the attributes and rules a query refers to directly.
+pub const QUERY_DEPENDS_ON: &str = ":df.query/depends-on";
+/// Number of clients interested in a query.
+pub const QUERY_CLIENTS: &str = ":df.query/clients";
+
+/// All introspection attributes, along with the type of their values.
+pub const ATTRIBUTES: [(&str, ValueType); 7] = [
+    (ATTRIBUTE_NAME, ValueType::String),
+    (ATTRIBUTE_VALUE_TYPE, ValueType::String),
+    (ATTRIBUTE_INPUT_SEMANTICS, ValueType::String),
+    (ATTRIBUTE_TRANSACTABLE, ValueType::Bool),
+    (QUERY_NAME, ValueType::String),
+    (QUERY_DEPENDS_ON, ValueType::String),
+    (QUERY_CLIENTS, ValueType::Number),
+];
+
+/// A single fact about the server.
+pub type Fact = (Eid, &'static str, Value);
+
+/// Returns the id of the entity describing the named attribute or
+/// query, as indicated by `kind`.
+pub fn entity(kind: &str, name: &str) -> Eid {
+    let mut hasher = DefaultHasher::new();
+    kind.hash(&mut hasher);
+    name.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Collects all facts about the attributes and rules of the
+/// specified domains and the clients interested in them.
+pub fn facts<'a, A, T, Token, I>(
+    domains: I,
+    interests: &HashMap<A, HashSet<Token>>,
+) -> BTreeSet<Fact>
+where
+    A: AsAid + 'a,
+    T: Timestamp + Lattice + Rewind + 'a,
+    I: IntoIterator<Item = &'a Domain<A, T>>,
+{
+    let mut facts = BTreeSet::new();
+
+    for domain in domains.into_iter() {
+        for (name, config) in domain.attributes.iter() {
+            let e = entity("attribute", &name.to_string());
+
+            facts.insert((e, ATTRIBUTE_NAME, Value::String(name.to_string())));
+            facts.insert((
+                e,
+                ATTRIBUTE_INPUT_SEMANTICS,
+                Value::String(format!("{:?}", config.input_semantics)),
+            ));
+            facts.insert((
+                e,
+                ATTRIBUTE_TRANSACTABLE,
+                Value::Bool(domain.is_transactable(name)),
+            ));
+
+            if let Some(value_type) = config.value_type {
+                facts.insert((
+                    e,
+                    ATTRIBUTE_VALUE_TYPE,
+                    Value::String(format!("{:?}", value_type)),
+                ));
+            }
+        }
+
+        for (name, rule) in domain.rules.iter() {
+            let e = entity("query", &name.to_string());
+            let clients = interests.get(name).map(HashSet::len).unwrap_or(0);
+
+            facts.insert((e, QUERY_NAME, Value::String(name.to_string())));
+            facts.insert((e, QUERY_CLIENTS, Value::Number(clients as i64)));
+
+            let dependencies = rule.plan.dependencies();
+            for dependency in dependencies
+                .names
+                .iter()
+                .chain(dependencies.attributes.iter())
+            {
+                facts.insert((e, QUERY_DEPENDS_ON, Value::String(dependency.to_string())));
+            }
+        }
+    }
+
+    facts
+}
+
+/// Keeps track of the facts last transacted into the introspection
+/// attributes.
+#[derive(Default)]
+pub struct Introspection {
+    facts: BTreeSet<Fact>,
+}
+
+impl Introspection {
+    /// Returns the datoms turning the facts transacted so far into
+    /// the specified ones, which are remembered for the next update.
+    pub fn update<A: AsAid + ExchangeData>(&mut self, facts: BTreeSet<Fact>) -> Vec<Datom<A>> {
+        let retractions = self
+            .facts
+            .difference(&facts)
+            .map(|(e, a, v)| Datom::retract(*e, a.to_string(), v.clone()));
+
+        let additions = facts
+            .difference(&self.facts)
+            .map(|(e, a, v)| Datom::add(*e, a.to_string(), v.clone()));
+
+        let tx_data = retractions.chain(additions).collect();
+        self.facts = facts;
+
+        tx_data
+    }
+}
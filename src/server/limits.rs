@@ -0,0 +1,147 @@
+//! Limits on the resources individual queries may claim. Queries
+//! exceeding them at runtime are reported, s.t. the server can kill
+//! them before a single runaway query takes everything else down
+//! with it.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::rc::Rc;
+
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::Operator;
+use timely::dataflow::Scope;
+
+use differential_dataflow::collection::{AsCollection, Collection};
+
+use crate::{AsAid, Error, Rule, Value};
+
+/// Limits on the resources claimed by a single query. Unset limits
+/// don't apply.
+#[derive(
+    Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Default, Serialize, Deserialize,
+)]
+pub struct Limits {
+    /// Maximum number of results a query may produce at any single
+    /// time, on any single worker.
+    #[serde(default)]
+    pub max_outputs: Option<usize>,
+    /// Maximum number of bytes the results of a query may be
+    /// estimated to take up, on any single worker. Heap data owned by
+    /// values, e.g. the contents of strings, isn't accounted for.
+    #[serde(default)]
+    pub max_bytes: Option<usize>,
+    /// Maximum number of clauses across the rules implementing a
+    /// query. Checked before its dataflow is built.
+    #[serde(default)]
+    pub max_clauses: Option<usize>,
+}
+
+/// Queries that have exceeded their limits, along with the reason,
+/// as reported by the operators enforcing them.
+pub type Violations<A> = Rc<RefCell<Vec<(A, Error)>>>;
+
+impl Limits {
+    /// Returns these limits, falling back to the specified defaults
+    /// for those left unset.
+    pub fn or(self, defaults: Limits) -> Limits {
+        Limits {
+            max_outputs: self.max_outputs.or(defaults.max_outputs),
+            max_bytes: self.max_bytes.or(defaults.max_bytes),
+            max_clauses: self.max_clauses.or(defaults.max_clauses),
+        }
+    }
+
+    /// Ensures that the rules implementing the named query don't
+    /// consist of more clauses than allowed.
+    pub fn check_size<A: AsAid>(&self, name: &A, rules: &[Rule<A>]) -> Result<(), Error> {
+        if let Some(max_clauses) = self.max_clauses {
+            let clauses: usize = rules.iter().map(|rule| rule.plan.clauses()).sum();
+
+            if clauses > max_clauses {
+                return Err(Error::unsupported(format!(
+                    "Query {} consists of {} clauses, more than the limit of {}.",
+                    name, clauses, max_clauses
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reports the named query to `violations` once its results
+    /// exceed the runtime limits. Results are passed on unchanged,
+    /// whoever receives the report is expected to kill the query.
+    pub fn enforce<S, A>(
+        &self,
+        name: A,
+        relation: Collection<S, Vec<Value>, isize>,
+        violations: Violations<A>,
+    ) -> Collection<S, Vec<Value>, isize>
+    where
+        S: Scope,
+        A: AsAid + 'static,
+    {
+        if self.max_outputs.is_none() && self.max_bytes.is_none() {
+            return relation;
+        }
+
+        let limits = *self;
+
+        relation
+            .inner
+            .unary_frontier(Pipeline, "Limits", move |_cap, _info| {
+                let mut outputs: HashMap<S::Timestamp, usize> = HashMap::new();
+                let mut bytes: isize = 0;
+                let mut reported = false;
+                let mut vector = Vec::new();
+
+                move |input, output| {
+                    input.for_each(|cap, data| {
+                        data.swap(&mut vector);
+
+                        for (tuple, t, diff) in vector.iter() {
+                            *outputs.entry(t.clone()).or_insert(0) += 1;
+
+                            let size = size_of::<(Vec<Value>, S::Timestamp, isize)>()
+                                + tuple.len() * size_of::<Value>();
+                            bytes += diff * size as isize;
+                        }
+
+                        output.session(&cap).give_vec(&mut vector);
+                    });
+
+                    if !reported {
+                        let busiest = outputs.values().cloned().max().unwrap_or(0);
+
+                        let violation = match (limits.max_outputs, limits.max_bytes) {
+                            (Some(max_outputs), _) if busiest > max_outputs => {
+                                Some(Error::interrupted(format!(
+                                    "Query {} produced {} results at a single time, more than the limit of {}.",
+                                    name, busiest, max_outputs
+                                )))
+                            }
+                            (_, Some(max_bytes)) if bytes > max_bytes as isize => {
+                                Some(Error::interrupted(format!(
+                                    "Query {} holds an estimated {} bytes of results, more than the limit of {}.",
+                                    name, bytes, max_bytes
+                                )))
+                            }
+                            _ => None,
+                        };
+
+                        if let Some(error) = violation {
+                            reported = true;
+                            violations.borrow_mut().push((name.clone(), error));
+                        }
+                    }
+
+                    // Times that are complete can't produce any more
+                    // results.
+                    let frontier = input.frontier();
+                    outputs.retain(|t, _count| frontier.less_equal(t));
+                }
+            })
+            .as_collection()
+    }
+}
@@ -1,61 +1,268 @@
 //! Server logic for driving the library via commands.
 
-use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::hash::Hash;
 use std::rc::Rc;
 use std::time::{Duration, Instant};
 
 use timely::communication::Allocate;
 use timely::dataflow::operators::capture::event::link::EventLink;
-use timely::dataflow::operators::UnorderedInput;
-use timely::dataflow::{ProbeHandle, Scope};
+use timely::dataflow::operators::unordered_input::{ActivateCapability, UnorderedHandle};
+use timely::dataflow::operators::{Broadcast, Concatenate, Exchange, Filter, UnorderedInput};
+use timely::dataflow::{ProbeHandle, Scope, Stream};
 use timely::logging::{BatchLogger, TimelyEvent};
+use timely::order::PartialOrder;
 use timely::progress::Timestamp;
 use timely::worker::Worker;
 
 use differential_dataflow::collection::{AsCollection, Collection};
+use differential_dataflow::hashable::Hashable;
 use differential_dataflow::lattice::Lattice;
 use differential_dataflow::logging::DifferentialEvent;
+use differential_dataflow::operators::arrange::Arrange;
 use differential_dataflow::operators::Threshold;
 use differential_dataflow::ExchangeData;
 
-use crate::domain::{AsSingletonDomain, Domain};
-use crate::logging::DeclarativeEvent;
-use crate::operators::LastWriteWins;
+use crate::domain::{AsSingletonDomain, AttributeStatistics, Domain, IndexSize};
+use crate::logging::{log_lifecycle, DeclarativeEvent, LifecycleEvent};
+use crate::metrics::{self, Recorder};
+use crate::operators::{LastWriteWins, Reclock};
+use crate::plan::explain::{explain, Arrangement, Explanation, Index};
+use crate::plan::ordering::order_joins;
+use crate::plan::sharing::{is_shared, share_subplans};
+use crate::plan::validation::{validate_bindings, validate_heads};
+use crate::plan::{datalog, Implementable, Plan};
 use crate::scheduling::Scheduler;
 use crate::sinks::Sink;
-use crate::sources::{Source, Sourceable, SourcingContext};
+use crate::sources::checkpoint::CheckpointStore;
+use crate::sources::{demultiplex, MultiplexedSourceable, Source, Sourceable, SourcingContext};
 use crate::Rule;
 use crate::{
-    implement, implement_neu, AttributeConfig, IndexDirection, InputSemantics, ShutdownHandle,
+    collect_dependencies, implement, implement_neu, validate_stratification, AttributeConfig,
+    CompositeIndex, IndexDirection, InputSemantics, ShutdownHandle,
 };
-use crate::{AsAid, Datom, Error, Rewind, Time, Value};
+use crate::{AsAid, Datom, Eid, Error, Rewind, Time, Value};
+
+pub mod auth;
+#[cfg(feature = "serde_json")]
+pub mod backup;
+pub mod cluster;
+#[cfg(feature = "serde_json")]
+pub mod eviction;
+pub mod introspection;
+pub mod limits;
+pub mod pagination;
+pub mod quotas;
+pub mod sequencing;
+#[cfg(feature = "serde_json")]
+pub mod snapshot;
+pub mod storage;
+#[cfg(feature = "serde_json")]
+pub mod wal;
+use self::auth::Acl;
+use self::cluster::Rendezvous;
+use self::introspection::Introspection;
+use self::limits::{Limits, Violations};
+use self::pagination::Page;
+use self::quotas::{Quotas, Usage};
+use self::storage::Storage;
+
+/// Separates the tenant a name belongs to from the rest of the name,
+/// e.g. `acme//:user/name` is the attribute `:user/name` of tenant
+/// `acme`.
+pub const TENANT_SEPARATOR: &str = "//";
+
+/// Returns the tenant the specified name belongs to, if any. Names
+/// without a tenant belong to the internal domain.
+pub fn tenant_of(name: &str) -> Option<&str> {
+    name.find(TENANT_SEPARATOR).map(|i| &name[..i])
+}
+
+/// Returns the tenant that all of the specified names belong to.
+/// Names of different tenants can't be mixed.
+fn common_tenant<'a, A: AsAid, I: IntoIterator<Item = &'a A>>(
+    names: I,
+) -> Result<Option<String>, Error> {
+    let mut common: Option<Option<String>> = None;
+
+    for name in names.into_iter() {
+        let tenant = tenant_of(&name.to_string()).map(str::to_string);
+
+        match common {
+            None => common = Some(tenant),
+            Some(ref common) if *common != tenant => {
+                return Err(Error::incorrect(format!(
+                    "{} belongs to another tenant than the names it is used with.",
+                    name
+                )));
+            }
+            Some(_) => {}
+        }
+    }
+
+    Ok(common.unwrap_or_default())
+}
+
+/// Rejects rules referring to attributes or rules of tenants other
+/// than their own.
+fn check_tenancy<A: AsAid>(rule: &Rule<A>) -> Result<(), Error> {
+    let dependencies = rule.plan.dependencies();
+
+    common_tenant(
+        std::iter::once(&rule.name)
+            .chain(dependencies.attributes.iter())
+            .chain(dependencies.names.iter()),
+    )
+    .map(|_| ())
+}
+
+/// Returns true iff the key tracks a dataflow implementing the named
+/// rule, i.e. one following the present, pinned to a past time, or
+/// resuming after one.
+fn is_dataflow_of<A: AsAid>(name: &A, key: &A) -> bool {
+    let name = name.to_string();
+    let key = key.to_string();
+
+    key == name
+        || key.starts_with(&format!("{}@", name))
+        || key.starts_with(&format!("{}>", name))
+        || key.starts_with(&format!("{}#", name))
+}
+
+/// Factor by which the observed cardinality of an attribute has to
+/// differ from the one a relation was planned with, before it is
+/// considered for re-planning.
+const REPLAN_THRESHOLD: f64 = 4.0;
 
 /// Server configuration.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Configuration {
     /// Automatic domain tick interval.
     pub tick: Option<Duration>,
+    /// Interval at which the wall clock advances domains in real
+    /// time, even if no inputs arrive. Takes precedence over `tick`
+    /// when scheduling ticks.
+    #[serde(default)]
+    pub clock: Option<Duration>,
     /// Do clients have to call AdvanceDomain explicitely?
     pub manual_advance: bool,
     /// Should logging streams be created?
     pub enable_logging: bool,
+    /// Should server metadata be exposed as attributes of the system
+    /// domain, see `introspection`?
+    #[serde(default)]
+    pub enable_meta: bool,
     /// Should queries use the optimizer during implementation?
     pub enable_optimizer: bool,
+    /// Should join trees common to multiple rules be implemented
+    /// only once and shared between queries?
+    #[serde(default)]
+    pub enable_sharing: bool,
+    /// Should binary joins be reordered by their estimated
+    /// selectivity, rather than implemented as written?
+    #[serde(default)]
+    pub enable_reordering: bool,
+    /// Interval at which long-lived queries are checked for join
+    /// orders that no longer match observed cardinalities, if at
+    /// all. Only has an effect if reordering is enabled.
+    #[serde(default)]
+    pub replan_interval: Option<Duration>,
+    /// Should reverse indices missing from attributes be built once
+    /// a query first requires them, rather than failing the query?
+    #[serde(default)]
+    pub enable_lazy_indices: bool,
+    /// Directory in which sources persist their read positions.
+    pub checkpoint_directory: Option<String>,
+    /// Directory to which evicted attributes are offloaded, if
+    /// attributes may be evicted at all.
+    #[serde(default)]
+    pub eviction_directory: Option<String>,
+    /// Directory holding the write-ahead log of commands, if
+    /// commands should be persisted at all.
+    #[serde(default)]
+    pub wal_directory: Option<String>,
+    /// Interval at which compacted snapshots of all attributes are
+    /// written, s.t. the write-ahead log can be truncated. Only has
+    /// an effect if commands are persisted.
+    #[serde(default)]
+    pub snapshot_interval: Option<Duration>,
+    /// Where to keep the write-ahead log and its snapshots. Takes
+    /// precedence over `wal_directory`.
+    #[serde(default)]
+    pub storage: Option<Storage>,
+    /// Should results be sent as one consolidated batch per query
+    /// and time, rather than as individual diffs?
+    #[serde(default)]
+    pub batch_results: bool,
+    /// Should a final snapshot be written while shutting down, s.t.
+    /// the next start doesn't have to replay the write-ahead log?
+    #[serde(default)]
+    pub shutdown_snapshot: bool,
+    /// How long to wait for outputs and snapshots to catch up while
+    /// shutting down, before giving up on them. Waits indefinitely,
+    /// if not set.
+    #[serde(default)]
+    pub drain_timeout: Option<Duration>,
+    /// Maximum number of results sent to clients in a single message,
+    /// if limited. Applies to results sent as individual diffs.
+    #[serde(default)]
+    pub batch_size: Option<usize>,
+    /// Interval at which all polling sources check for new inputs,
+    /// overriding the intervals they were registered with.
+    #[serde(default)]
+    pub poll_interval: Option<Duration>,
+    /// Limits applying to every query, unless overridden by the
+    /// interest in it.
+    #[serde(default)]
+    pub query_limits: Limits,
+    /// Quotas applying to every client connection.
+    #[serde(default)]
+    pub client_quotas: Quotas,
+    /// Who may do what, if clients have to authenticate at all.
+    #[serde(default)]
+    pub acl: Option<Acl>,
 }
 
 impl Default for Configuration {
     fn default() -> Self {
         Configuration {
             tick: None,
+            clock: None,
             manual_advance: false,
             enable_logging: false,
+            enable_meta: false,
             enable_optimizer: false,
+            enable_sharing: false,
+            enable_reordering: false,
+            replan_interval: None,
+            enable_lazy_indices: false,
+            checkpoint_directory: None,
+            eviction_directory: None,
+            wal_directory: None,
+            snapshot_interval: None,
+            storage: None,
+            batch_results: false,
+            shutdown_snapshot: false,
+            drain_timeout: None,
+            batch_size: None,
+            poll_interval: None,
+            query_limits: Limits::default(),
+            client_quotas: Quotas::default(),
+            acl: None,
         }
     }
 }
 
+impl Configuration {
+    /// Returns where durable state is kept, if anywhere.
+    pub fn storage(&self) -> Option<Storage> {
+        self.storage
+            .clone()
+            .or_else(|| self.wal_directory.clone().map(Storage::FileSystem))
+    }
+}
+
 #[cfg(feature = "getopts")]
 impl Configuration {
     /// Returns a `getopts::Options` struct describing all available
@@ -69,6 +276,12 @@ impl Configuration {
             "advance domain at a regular interval",
             "SECONDS",
         );
+        opts.optopt(
+            "",
+            "clock",
+            "advance domains by the wall clock at a regular interval",
+            "MILLISECONDS",
+        );
         opts.optflag(
             "",
             "manual-advance",
@@ -76,7 +289,116 @@ impl Configuration {
         );
         opts.optflag("", "enable-logging", "enable log event sources");
         opts.optflag("", "enable-optimizer", "enable WCO queries");
-        opts.optflag("", "enable-meta", "enable queries on the query graph");
+        opts.optflag(
+            "",
+            "enable-sharing",
+            "share common join trees between queries",
+        );
+        opts.optflag(
+            "",
+            "enable-reordering",
+            "reorder joins by estimated selectivity",
+        );
+        opts.optopt(
+            "",
+            "replan-interval",
+            "re-plan queries with outdated join orders at a regular interval",
+            "SECONDS",
+        );
+        opts.optflag(
+            "",
+            "enable-lazy-indices",
+            "build reverse indices once queries require them",
+        );
+        opts.optflag("", "enable-meta", "enable queries on server metadata");
+        opts.optopt(
+            "",
+            "checkpoint-dir",
+            "persist source read positions in a directory",
+            "DIR",
+        );
+        opts.optopt(
+            "",
+            "eviction-dir",
+            "offload evicted attributes to a directory",
+            "DIR",
+        );
+        opts.optopt(
+            "",
+            "wal-dir",
+            "persist commands in a write-ahead log",
+            "DIR",
+        );
+        opts.optopt(
+            "",
+            "snapshot-interval",
+            "compact the write-ahead log at a regular interval",
+            "SECONDS",
+        );
+        opts.optopt(
+            "",
+            "storage",
+            "persist commands in a directory or at s3://BUCKET/PREFIX",
+            "LOCATION",
+        );
+        opts.optflag(
+            "",
+            "batch-results",
+            "send one consolidated batch of results per query and time",
+        );
+        opts.optflag(
+            "",
+            "shutdown-snapshot",
+            "write a final snapshot when shutting down",
+        );
+        opts.optopt(
+            "",
+            "drain-timeout",
+            "give up on outputs catching up when shutting down, after a while",
+            "SECONDS",
+        );
+        opts.optopt(
+            "",
+            "batch-size",
+            "send at most this many results per message",
+            "NUM",
+        );
+        opts.optopt(
+            "",
+            "poll-interval",
+            "interval at which all sources poll for new inputs",
+            "MILLISECONDS",
+        );
+        opts.optopt(
+            "",
+            "max-query-outputs",
+            "kill queries producing more results at a single time",
+            "NUM",
+        );
+        opts.optopt(
+            "",
+            "max-query-bytes",
+            "kill queries whose results are estimated to take up more bytes",
+            "NUM",
+        );
+        opts.optopt(
+            "",
+            "max-query-clauses",
+            "reject queries consisting of more clauses",
+            "NUM",
+        );
+        opts.optopt(
+            "",
+            "max-client-tps",
+            "reject transactions beyond this many per second and client",
+            "NUM",
+        );
+        opts.optopt(
+            "",
+            "max-client-queries",
+            "reject registrations beyond this many queries per client",
+            "NUM",
+        );
 
         opts
     }
@@ -92,11 +414,79 @@ impl Configuration {
             .opt_str("tick")
             .map(|x| Duration::from_secs(x.parse().expect("failed to parse tick duration")));
 
+        let clock: Option<Duration> = matches
+            .opt_str("clock")
+            .map(|x| Duration::from_millis(x.parse().expect("failed to parse clock interval")));
+
+        let replan_interval: Option<Duration> = matches
+            .opt_str("replan-interval")
+            .map(|x| Duration::from_secs(x.parse().expect("failed to parse replan interval")));
+
+        let snapshot_interval: Option<Duration> = matches
+            .opt_str("snapshot-interval")
+            .map(|x| Duration::from_secs(x.parse().expect("failed to parse snapshot interval")));
+
+        let drain_timeout: Option<Duration> = matches
+            .opt_str("drain-timeout")
+            .map(|x| Duration::from_secs(x.parse().expect("failed to parse drain timeout")));
+
+        let batch_size: Option<usize> = matches
+            .opt_str("batch-size")
+            .map(|x| x.parse().expect("failed to parse batch size"));
+
+        let poll_interval: Option<Duration> = matches
+            .opt_str("poll-interval")
+            .map(|x| Duration::from_millis(x.parse().expect("failed to parse poll interval")));
+
+        let query_limits = Limits {
+            max_outputs: matches
+                .opt_str("max-query-outputs")
+                .map(|x| x.parse().expect("failed to parse output limit")),
+            max_bytes: matches
+                .opt_str("max-query-bytes")
+                .map(|x| x.parse().expect("failed to parse memory limit")),
+            max_clauses: matches
+                .opt_str("max-query-clauses")
+                .map(|x| x.parse().expect("failed to parse clause limit")),
+        };
+
+        let client_quotas = Quotas {
+            max_transactions_per_second: matches
+                .opt_str("max-client-tps")
+                .map(|x| x.parse().expect("failed to parse transaction quota")),
+            max_queries: matches
+                .opt_str("max-client-queries")
+                .map(|x| x.parse().expect("failed to parse query quota")),
+        };
+
+        let storage: Option<Storage> = matches
+            .opt_str("storage")
+            .map(|x| Storage::parse(&x).expect("failed to parse storage location"));
+
         Self {
             tick,
+            clock,
             manual_advance: matches.opt_present("manual-advance"),
             enable_logging: matches.opt_present("enable-logging"),
+            enable_meta: matches.opt_present("enable-meta"),
             enable_optimizer: matches.opt_present("enable-optimizer"),
+            enable_sharing: matches.opt_present("enable-sharing"),
+            enable_reordering: matches.opt_present("enable-reordering"),
+            replan_interval,
+            enable_lazy_indices: matches.opt_present("enable-lazy-indices"),
+            checkpoint_directory: matches.opt_str("checkpoint-dir"),
+            eviction_directory: matches.opt_str("eviction-dir"),
+            wal_directory: matches.opt_str("wal-dir"),
+            snapshot_interval,
+            storage,
+            batch_results: matches.opt_present("batch-results"),
+            shutdown_snapshot: matches.opt_present("shutdown-snapshot"),
+            drain_timeout,
+            batch_size,
+            poll_interval,
+            query_limits,
+            client_quotas,
+            acl: default.acl,
         }
     }
 }
@@ -116,6 +506,57 @@ pub struct Interest {
     pub sink: Option<Sink>,
     /// Whether or not to log events from this dataflow.
     pub disable_logging: Option<bool>,
+    /// Pins results to the state as of the specified time, instead of
+    /// following the relation as it evolves.
+    #[serde(default)]
+    pub as_of: Option<Time>,
+    /// How to implement the relation, if different from the server
+    /// default.
+    #[serde(default)]
+    pub strategy: Option<Strategy>,
+    /// Resumes results after the time carried by a resume token,
+    /// rather than starting from a snapshot. Only updates at later
+    /// times are sent, which requires all attributes involved to
+    /// have retained their history back to it.
+    #[serde(default)]
+    pub resume_after: Option<Time>,
+    /// Limits on the resources the query may claim, overriding
+    /// those configured for the server.
+    #[serde(default)]
+    pub limits: Option<Limits>,
+    /// Restricts results to a window in the order of a sort key,
+    /// instead of delivering the relation as a whole.
+    #[serde(default)]
+    pub page: Option<Page>,
+}
+
+/// Strategies for implementing multi-way joins.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Strategy {
+    /// A tree of binary joins, materializing intermediate results.
+    BinaryJoins,
+    /// Worst-case optimal delta queries over the attribute
+    /// arrangements, avoiding large intermediate results.
+    WorstCaseOptimal,
+}
+
+impl Interest {
+    /// Returns the name under which the resulting dataflow is
+    /// tracked. Interests pinned to a time, resuming after one, or
+    /// asking for a page of results get a dataflow of their own,
+    /// separate from the one following the present.
+    pub fn key(&self) -> String {
+        let key = match (&self.as_of, &self.resume_after) {
+            (Some(ref as_of), _) => format!("{}@{:?}", self.name, as_of),
+            (None, Some(ref after)) => format!("{}>{:?}", self.name, after),
+            (None, None) => self.name.clone(),
+        };
+
+        match self.page {
+            None => key,
+            Some(ref page) => format!("{}{}", key, page.key()),
+        }
+    }
 }
 
 impl std::convert::From<&Interest> for crate::sinks::SinkingContext {
@@ -135,6 +576,63 @@ pub struct Register<A: AsAid> {
     pub rules: Vec<Rule<A>>,
     /// The names of rules that should be published.
     pub publish: Vec<A>,
+    /// Rules given as Datalog query strings, to be parsed and
+    /// registered along with the others.
+    #[serde(default)]
+    pub datalog: Vec<DatalogRule>,
+}
+
+/// A named query string, in the syntax accepted by
+/// `plan::datalog::parse`.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct DatalogRule {
+    /// The name to register the query under.
+    pub name: String,
+    /// The query string.
+    pub query: String,
+}
+
+/// A GraphQL operation over the attributes of the domain its name
+/// belongs to, whose fields are named as by `plan::graphql::field_name`.
+#[cfg(feature = "graphql")]
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct GraphQlRequest {
+    /// The name to register subscriptions under, and to publish
+    /// their results as. Queries only use its tenant.
+    pub name: String,
+    /// The GraphQL document, holding a single query or subscription.
+    pub query: String,
+}
+
+/// A request to describe how a plan would be implemented, without
+/// actually implementing it.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Explain<A: AsAid> {
+    /// The plan to explain.
+    pub plan: Plan<A>,
+    /// How to implement the plan, if different from the server
+    /// default.
+    #[serde(default)]
+    pub strategy: Option<Strategy>,
+}
+
+/// A request to evaluate a plan once, returning its consolidated
+/// results as of the time the request is handled.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Query<A: AsAid> {
+    /// The plan to evaluate.
+    pub plan: Plan<A>,
+}
+
+/// A request to re-implement a relation of interest using a
+/// different join order. Only the server itself issues these, s.t.
+/// all workers agree on the new plan.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Replan<A: AsAid> {
+    /// The name of the relation to re-implement.
+    pub name: String,
+    /// Reordered versions of the rules the relation depends on.
+    pub rules: Vec<Rule<A>>,
 }
 
 /// A request with the intent of creating a new named, globally
@@ -148,29 +646,195 @@ pub struct CreateAttribute {
     pub config: AttributeConfig,
 }
 
+/// A chunk of data to be loaded into a single attribute, bypassing
+/// the usual transaction processing. Large imports are expected to
+/// be split across many such requests.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct BulkLoad {
+    /// The name of the attribute to load into.
+    pub name: String,
+    /// (e, v, diff) updates to load.
+    pub data: Vec<(Value, Value, isize)>,
+}
+
+/// A request with the intent of exporting the consolidated contents
+/// of an attribute, as of the current epoch.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Backup {
+    /// The name of the attribute to export.
+    pub name: String,
+    /// Directory to write the backup to. It should be empty, as
+    /// parts of earlier backups are only replaced by those of
+    /// workers with the same index.
+    pub directory: String,
+}
+
+/// A request with the intent of changing the settings of a running
+/// server. Settings left unset remain as they are.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Reconfigure {
+    /// Slack of all attributes whose traces are compacted, s.t. they
+    /// retain a longer or shorter window of history. Attributes
+    /// keeping their full history are left alone.
+    #[serde(default)]
+    pub trace_slack: Option<Time>,
+    /// Maximum number of results sent to clients in a single message.
+    #[serde(default)]
+    pub batch_size: Option<usize>,
+    /// Interval at which all polling sources check for new inputs.
+    #[serde(default)]
+    pub poll_interval: Option<Duration>,
+}
+
+/// A request with the intent of importing a previously exported
+/// attribute.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Restore {
+    /// The name of the attribute to import into. It must have been
+    /// created already, but needn't be the one that was exported.
+    pub name: String,
+    /// Directory holding the backup.
+    pub directory: String,
+}
+
+/// A request with the intent of binding tuples to (or, given
+/// negative multiplicities, unbinding them from) a query parameter.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Bind {
+    /// The name of the parameter to bind.
+    pub name: String,
+    /// (tuple, diff) updates to the parameter relation.
+    pub tuples: Vec<(Vec<Value>, isize)>,
+}
+
+/// A request with the intent of making an attribute available to the
+/// domain of another tenant, translated into that domain's
+/// timestamps.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Bridge {
+    /// The name of the attribute to bridge.
+    pub source: String,
+    /// The name to publish the bridged attribute under. Its tenant
+    /// determines the domain it is published to.
+    pub name: String,
+}
+
+/// A request with the intent of maintaining a composite index over
+/// several attributes, see `CompositeIndex`.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct CreateIndex {
+    /// The name of the index.
+    pub name: String,
+    /// The layout of the index.
+    pub kind: CompositeIndex,
+    /// The attributes to cover.
+    pub attributes: Vec<String>,
+}
+
+/// Bindings driving the reclocking of a bridged attribute. They
+/// relate the input frontier of the source domain to the epoch of
+/// the target domain, as observed whenever the server advances.
+struct Bindings<T: Timestamp> {
+    /// Tenant of the source domain.
+    source: Option<String>,
+    /// Tenant of the target domain.
+    target: Option<String>,
+    /// Whether this worker sends bindings for all others.
+    sending: bool,
+    /// Handle to the bindings input.
+    handle: UnorderedHandle<T, Vec<T>>,
+    /// Capability at the current epoch of the target domain.
+    cap: ActivateCapability<T>,
+    /// The source frontier last sent.
+    last: Vec<T>,
+    /// Shuts down the import of the source attribute, once dropped.
+    _shutdown: ShutdownHandle,
+}
+
+impl<T: Timestamp> Bindings<T> {
+    /// Binds everything before the source frontier to the target
+    /// epoch, unless nothing has changed since the last binding.
+    fn bind(&mut self, frontier: Vec<T>, epoch: T) {
+        if self.cap.time().less_than(&epoch) {
+            self.cap = self.cap.delayed(&epoch);
+        }
+
+        if self.sending && frontier != self.last {
+            self.handle.session(self.cap.clone()).give(frontier.clone());
+
+            self.last = frontier;
+        }
+    }
+}
+
 /// Possible request types.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub enum Request<A: AsAid + From<&'static str>> {
     /// Sends inputs via one or more registered handles.
     Transact(Vec<Datom<A>>),
+    /// Sends inputs like `Transact`, annotating the transaction
+    /// entity with the given (a, v) pairs.
+    TransactWithMeta(Vec<Datom<A>>, Vec<(A, Value)>),
     /// Expresses interest in an entire attribute.
     Subscribe(String),
     /// Derives new attributes under a new namespace.
     #[cfg(feature = "graphql")]
     Derive(String, String),
+    /// Evaluates a GraphQL query once, or streams the results of a
+    /// GraphQL subscription.
+    #[cfg(feature = "graphql")]
+    GraphQl(GraphQlRequest),
     /// Expresses interest in a named relation.
     Interest(Interest),
+    /// Describes the physical plan a plan would be implemented by.
+    Explain(Explain<A>),
+    /// Evaluates a plan once, without registering it, and returns its
+    /// results to the requesting client.
+    Query(Query<A>),
+    /// Replaces the dataflow of a relation of interest by one
+    /// implementing the specified, reordered rules.
+    Replan(Replan<A>),
+    /// Requests a fresh snapshot of a relation of interest, e.g.
+    /// because outputs to the client had to be discarded.
+    Resnapshot(String),
     /// Expresses that the interest in a named relation has
     /// stopped. Once all interested clients have sent this, the
     /// dataflow can be cleaned up.
     Uninterest(String),
     /// Registers one or more named relations.
     Register(Register<A>),
+    /// Materializes a named relation as a view, that other queries
+    /// can use without implementing it themselves.
+    Materialize(String),
+    /// Removes a named relation that is no longer needed, shutting
+    /// down its dataflows.
+    Unregister(String),
     /// A request with the intent of attaching to an external data
     /// source that publishes one or more attributes and relations.
     RegisterSource(Source<A>),
     /// Creates a named input handle that can be `Transact`ed upon.
     CreateAttribute(CreateAttribute),
+    /// Retracts everything currently asserted about an entity within
+    /// the domain of the specified tenant, or the internal domain.
+    RetractEntity(Option<String>, Value),
+    /// Loads a chunk of data into an attribute.
+    BulkLoad(BulkLoad),
+    /// Changes the trace slack of an attribute.
+    SetTraceSlack(String, Option<Time>),
+    /// Removes an attribute along with its input handle and indices.
+    DropAttribute(String),
+    /// Offloads the indices of an attribute whose input has been
+    /// closed to disk, until a query requires them again.
+    Evict(String),
+    /// Creates a named query parameter, that rules can refer to via
+    /// `Plan::Parameter`.
+    CreateParameter(String),
+    /// Binds tuples to a query parameter.
+    Bind(Bind),
+    /// Makes an attribute available to the domain of another tenant.
+    Bridge(Bridge),
+    /// Creates a composite index over several attributes.
+    CreateIndex(CreateIndex),
     /// Advances the specified domain to the specified time.
     AdvanceDomain(Option<String>, Time),
     /// Requests a domain advance to whatever epoch the server
@@ -179,15 +843,49 @@ pub enum Request<A: AsAid + From<&'static str>> {
     Tick,
     /// Closes a named input handle.
     CloseInput(String),
+    /// Authenticates the client via the specified token. Later
+    /// requests by the client are authorized on behalf of the
+    /// principal the token belongs to.
+    Authenticate(String),
     /// Client has disconnected.
     Disconnect,
     /// Requests any setup logic that needs to be executed
     /// deterministically across all workers.
     Setup,
+    /// Requests the configurations of all known attributes.
+    Schema,
     /// Requests a heartbeat containing status information.
     Status,
-    /// Requests orderly shutdown of the system.
+    /// Requests the sizes of all arrangements held by domains. Each
+    /// worker reports its own share.
+    IndexSizes,
+    /// Requests orderly shutdown of the system. Workers stop accepting
+    /// client requests and drain, before terminating.
     Shutdown,
+    /// Announces that a worker has drained, after shutdown was
+    /// requested. Workers terminate once all of them have drained.
+    Drained(usize),
+    /// Announces a worker to all others, before any client requests
+    /// are accepted.
+    Rendezvous(Rendezvous),
+    /// Writes a compacted snapshot of all attributes, as of the
+    /// current epoch. Once all workers have written theirs, the
+    /// write-ahead log is truncated up to this request.
+    Snapshot,
+    /// Exports the consolidated contents of an attribute.
+    Backup(Backup),
+    /// Transacts the contents of a backup into an attribute.
+    Restore(Restore),
+    /// Transacts the contents of the snapshot written for the
+    /// `Snapshot` request with the specified transaction id. Issued
+    /// by the server itself, when truncating its write-ahead log.
+    RestoreSnapshot(TxId),
+    /// Changes settings of the running server. Changes take effect
+    /// from the next epoch on.
+    Reconfigure(Reconfigure),
+    /// Tears down all dataflows implementing the named query, without
+    /// unregistering it. Interested clients are notified.
+    Kill(String),
 }
 
 /// Server context maintaining globally registered arrangements and
@@ -203,8 +901,20 @@ where
     /// A timer started at the initiation of the timely computation
     /// (copied from worker).
     pub t0: Instant,
+    /// Index of the worker holding this server state, used to tag
+    /// lifecycle events.
+    pub worker_index: usize,
     /// Internal domain in server time.
     pub internal: Domain<A, T>,
+    /// System domain holding introspection attributes, e.g. those
+    /// fed by Timely and Differential logging. It is kept apart from
+    /// the internal domain, s.t. the progress of logging streams
+    /// never holds back user inputs.
+    pub system: Domain<A, T>,
+    /// Domains of all tenants, by tenant. Each holds the attributes
+    /// and rules whose names carry its tenant's prefix, and progresses
+    /// independently of all others.
+    pub tenants: HashMap<String, Domain<A, T>>,
     /// Mapping from query names to interested client tokens.
     pub interests: HashMap<A, HashSet<Token>>,
     // Mapping from query names to their shutdown handles. This is
@@ -220,6 +930,33 @@ where
     timely_events: Option<Rc<EventLink<Duration, (Duration, usize, TimelyEvent)>>>,
     // Link to replayable Differential logging events.
     differential_events: Option<Rc<EventLink<Duration, (Duration, usize, DifferentialEvent)>>>,
+    // Store for source read positions.
+    checkpoints: Option<Rc<CheckpointStore>>,
+    // Where to report registrations and query outputs, if anywhere.
+    metrics: Option<Recorder>,
+    // Domain frontier as of the last lifecycle event.
+    last_frontier: Vec<T>,
+    // Attribute statistics that reordered relations of interest were
+    // planned with, by relation name.
+    estimates: HashMap<A, HashMap<A, AttributeStatistics>>,
+    // Number of one-shot queries handled so far, used for naming
+    // their rules.
+    next_query: usize,
+    // Principals that clients have authenticated as.
+    principals: HashMap<Token, String>,
+    // What clients have used up of their quotas.
+    usage: HashMap<Token, Usage>,
+    // Bindings of all bridged attributes.
+    bridges: Vec<Bindings<T>>,
+    // Maximum number of results per message, shared with all outputs.
+    batch_size: Rc<Cell<Option<usize>>>,
+    // Polling interval, shared with all sources.
+    poll_interval: Rc<Cell<Option<Duration>>>,
+    // Metadata last published to the system domain, if enabled.
+    introspection: Option<Introspection>,
+    // Queries that have exceeded their limits, until they are
+    // killed.
+    violations: Violations<A>,
 }
 
 impl<A, T, Token> Server<A, T, Token>
@@ -242,16 +979,97 @@ where
 
         let probe = ProbeHandle::new();
 
+        let checkpoints =
+            config
+                .checkpoint_directory
+                .as_ref()
+                .and_then(|directory| match CheckpointStore::open(directory) {
+                    Ok(store) => Some(Rc::new(store)),
+                    Err(error) => {
+                        error!("failed to open checkpoint store: {}", error.message);
+                        None
+                    }
+                });
+
+        let mut internal = Domain::new(Default::default());
+        let system = Domain::new_from("system", &internal);
+
+        // Tenants inherit the clock, the system domain is driven by
+        // its sources.
+        internal.set_clock(config.clock);
+
+        let batch_size = Rc::new(Cell::new(config.batch_size));
+        let poll_interval = Rc::new(Cell::new(config.poll_interval));
+
         Server {
             config,
             t0,
-            internal: Domain::new(Default::default()),
+            worker_index: 0,
+            internal,
+            system,
+            tenants: HashMap::new(),
             interests: HashMap::new(),
             shutdown_handles: HashMap::new(),
             scheduler: Rc::new(RefCell::new(Scheduler::from(probe.clone()))),
             probe,
             timely_events,
             differential_events,
+            checkpoints,
+            metrics: None,
+            last_frontier: Vec::new(),
+            estimates: HashMap::new(),
+            next_query: 0,
+            principals: HashMap::new(),
+            usage: HashMap::new(),
+            bridges: Vec::new(),
+            batch_size,
+            poll_interval,
+            introspection: None,
+            violations: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    /// Returns a fingerprint of the server configuration. Workers
+    /// can only cooperate if all of them share the same
+    /// configuration.
+    pub fn fingerprint(&self) -> u64 {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::Hasher;
+
+        let mut hasher = DefaultHasher::new();
+        hasher.write(format!("{:?}", self.config).as_bytes());
+        hasher.finish()
+    }
+
+    /// Logs a lifecycle event observed by this worker.
+    fn log_event(&self, event: LifecycleEvent) {
+        log_lifecycle(self.worker_index, self.t0.elapsed(), event);
+    }
+
+    /// Reports transactions, progress, registrations, and query
+    /// outputs to the specified recorder from now on.
+    pub fn enable_metrics(&mut self, recorder: Recorder) {
+        self.internal.instrument(recorder.clone());
+        self.metrics = Some(recorder);
+    }
+
+    /// Counts the result diffs produced by the named relation, if
+    /// metrics are enabled.
+    fn instrument_relation<S: Scope<Timestamp = T>>(
+        &self,
+        name: &A,
+        relation: Collection<S, Vec<Value>, isize>,
+    ) -> Collection<S, Vec<Value>, isize> {
+        match self.metrics {
+            None => relation,
+            Some(ref recorder) => {
+                let recorder = recorder.clone();
+                let labels = vec![("query", name.to_string())];
+
+                relation.inspect_batch(move |_time, batch| {
+                    recorder.increment(metrics::QUERY_OUTPUTS, labels.clone(), batch.len() as f64);
+                })
+            }
         }
     }
 
@@ -278,18 +1096,98 @@ where
     fn shutdown_query(&mut self, name: &A) {
         info!("Shutting down {}", name);
         self.shutdown_handles.remove(name);
+        self.estimates.remove(name);
     }
 
-    /// Handles a Transact request.
+    /// Handles a Transact request, returning the entity ids its
+    /// tempids resolved to.
     pub fn transact(
         &mut self,
         tx_data: Vec<Datom<A>>,
         owner: usize,
         worker_index: usize,
+    ) -> Result<HashMap<String, Eid>, Error> {
+        self.transact_with_meta(tx_data, Vec::new(), owner, worker_index)
+    }
+
+    /// Handles a TransactWithMeta request, returning the entity ids
+    /// its tempids resolved to.
+    pub fn transact_with_meta(
+        &mut self,
+        tx_data: Vec<Datom<A>>,
+        meta: Vec<(A, Value)>,
+        owner: usize,
+        worker_index: usize,
+    ) -> Result<HashMap<String, Eid>, Error> {
+        // Transactions are confined to a single tenant.
+        let tenant = common_tenant(
+            tx_data
+                .iter()
+                .map(|datom| &datom.1)
+                .chain(meta.iter().map(|(a, _v)| a)),
+        )?;
+        let domain = self.tenant_domain_mut(tenant.as_ref().map(String::as_str))?;
+
+        // Transactions are prepared on all workers, s.t. tempid
+        // allocation and cardinality bookkeeping stay in sync.
+        let (batches, tempids) = domain.prepare_with_meta(tx_data, meta)?;
+
+        // Only the owner should actually introduce new inputs, except
+        // for partitioned attributes, which all workers share.
+        let is_owner = owner == worker_index;
+        let datoms: usize = batches.values().map(|batch| batch.len()).sum();
+        domain.apply_partitioned(batches, is_owner);
+        let epoch = format!("{:?}", domain.epoch());
+
+        if is_owner {
+            self.log_event(LifecycleEvent::TxApplied { datoms, epoch });
+        }
+
+        Ok(tempids)
+    }
+
+    /// Handles a RetractEntity request.
+    pub fn retract_entity(
+        &mut self,
+        tenant: Option<String>,
+        e: Value,
+        owner: usize,
+        worker_index: usize,
+    ) -> Result<(), Error> {
+        let tx_data = self
+            .tenant_domain(tenant.as_ref().map(String::as_str))?
+            .retract_entity(&e);
+        self.transact(tx_data, owner, worker_index)
+            .map(|_tempids| ())
+    }
+
+    /// Handles a BulkLoad request.
+    pub fn bulk_load(
+        &mut self,
+        name: A,
+        data: Vec<(Value, Value, isize)>,
+        owner: usize,
+        worker_index: usize,
+    ) -> Result<(), Error> {
+        // only the owner should actually introduce new inputs
+        if owner == worker_index {
+            self.domain_of_mut(&name)?.bulk_load(&name, data)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Handles a Bind request.
+    pub fn bind(
+        &mut self,
+        name: A,
+        tuples: Vec<(Vec<Value>, isize)>,
+        owner: usize,
+        worker_index: usize,
     ) -> Result<(), Error> {
         // only the owner should actually introduce new inputs
         if owner == worker_index {
-            self.internal.transact(tx_data)
+            self.domain_of_mut(&name)?.bind(&name, tuples)
         } else {
             Ok(())
         }
@@ -301,84 +1199,1224 @@ where
         name: A,
         scope: &mut S,
     ) -> Result<Collection<S, Vec<Value>, isize>, Error> {
-        let (mut rel_map, shutdown_handle) = if self.config.enable_optimizer {
-            implement_neu(scope, &mut self.internal, name.clone())?
-        } else {
-            implement(scope, &mut self.internal, name.clone())?
-        };
-
-        match rel_map.remove(&name) {
-            None => Err(Error::fault(format!(
-                "Relation of interest ({}) wasn't actually implemented.",
-                name
-            ))),
-            Some(relation) => {
-                self.shutdown_handles.insert(name, shutdown_handle);
+        self.interest_using(name, scope, None)
+    }
 
-                Ok(relation)
-            }
+    /// Handles an Interest request, implementing the relation via the
+    /// specified strategy rather than the server-wide default.
+    pub fn interest_using<S: Scope<Timestamp = T>>(
+        &mut self,
+        name: A,
+        scope: &mut S,
+        strategy: Option<Strategy>,
+    ) -> Result<Collection<S, Vec<Value>, isize>, Error> {
+        let (relation, shutdown_handle) = self.implement_relation(name.clone(), scope, strategy)?;
+        self.log_event(LifecycleEvent::QueryImplemented {
+            name: name.to_string(),
+        });
+        let relation = self.instrument_relation(&name, relation);
+        self.shutdown_handles.insert(name.clone(), shutdown_handle);
+
+        if self.config.enable_reordering && self.strategy(strategy) == Strategy::BinaryJoins {
+            self.record_estimates(&name)?;
         }
+
+        Ok(relation)
     }
 
-    /// Handles a Register request.
-    pub fn register(&mut self, req: Register<A>) -> Result<(), Error> {
-        let Register { rules, .. } = req;
+    /// Remembers the statistics that the named relation has been
+    /// ordered by, s.t. it can be re-planned once they turn out to be
+    /// far off.
+    fn record_estimates(&mut self, name: &A) -> Result<(), Error> {
+        // Introspective relations are left alone, their inputs
+        // change all the time anyways.
+        if self.is_introspective(name)? {
+            return Ok(());
+        }
 
-        for rule in rules.into_iter() {
-            if self.internal.rules.contains_key(&rule.name) {
-                // @TODO panic if hashes don't match
-                // panic!("Attempted to re-register a named relation");
-                continue;
-            } else {
-                self.internal.rules.insert(rule.name.clone(), rule);
+        let domain = self.domain_of(name)?;
+        let mut estimates = HashMap::new();
+        for rule in collect_dependencies(domain, &[name.clone()])?.iter() {
+            for aid in rule.plan.dependencies().attributes.into_iter() {
+                let statistics = domain.statistics.get(&aid).cloned().unwrap_or_default();
+
+                estimates.insert(aid, statistics);
             }
         }
 
+        self.estimates.insert(name.clone(), estimates);
+
         Ok(())
     }
 
-    /// Handles a CreateAttribute request.
-    pub fn create_attribute<X, S>(
-        &mut self,
-        scope: &mut S,
-        name: X,
-        config: AttributeConfig,
-    ) -> Result<(), Error>
-    where
-        X: Into<A>,
-        S: Scope<Timestamp = T>,
-        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
-    {
-        let ((handle, cap), pairs) =
-            scope.new_unordered_input::<((Value, Value), S::Timestamp, isize)>();
+    /// Returns re-planning requests for all relations of interest
+    /// whose join order was chosen based on statistics that have
+    /// since diverged from the observed cardinalities by more than
+    /// `REPLAN_THRESHOLD`, and which would be ordered differently
+    /// today.
+    ///
+    /// Statistics are local to each worker, so only a single worker
+    /// should make this decision and sequence the resulting requests.
+    pub fn replan_candidates(&self) -> Vec<Replan<A>> {
+        let mut names: Vec<&A> = self.estimates.keys().collect();
+        names.sort();
+
+        let mut candidates = Vec::new();
+        for name in names.into_iter() {
+            let domain = match self.domain_of(name) {
+                Err(_) => continue,
+                Ok(domain) => domain,
+            };
 
-        let tuples = match config.input_semantics {
-            InputSemantics::Raw => pairs.as_collection(),
-            InputSemantics::LastWriteWins => pairs.as_collection().last_write_wins(),
-            // Ensure that redundant (e,v) pairs don't cause
-            // misleading proposals during joining.
-            InputSemantics::Distinct => pairs.as_collection().distinct(),
-        };
+            let has_diverged = self.estimates[name].iter().any(|(aid, estimate)| {
+                let observed = domain
+                    .statistics
+                    .get(aid)
+                    .map(|statistics| statistics.forward.tuples)
+                    .unwrap_or(0);
 
-        let mut scoped_domain = ((handle, cap), tuples).as_singleton_domain(name.into());
+                let observed = observed.max(1) as f64;
+                let estimated = estimate.forward.tuples.max(1) as f64;
 
-        if let Some(slack) = config.trace_slack {
-            scoped_domain = scoped_domain.with_slack(slack.into());
-        }
+                observed / estimated > REPLAN_THRESHOLD || estimated / observed > REPLAN_THRESHOLD
+            });
 
-        // LastWriteWins is a special case, because count, propose,
-        // and validate are all essentially the same.
-        if config.input_semantics != InputSemantics::LastWriteWins {
-            scoped_domain = scoped_domain.with_query_support(config.query_support);
-        }
+            if !has_diverged {
+                continue;
+            }
 
-        if config.index_direction == IndexDirection::Both {
-            scoped_domain = scoped_domain.with_reverse_indices();
-        }
+            let rules = match collect_dependencies(domain, &[name.clone()]) {
+                Err(_) => continue,
+                Ok(rules) => rules,
+            };
 
-        self.internal += scoped_domain.into();
+            let mut is_reordered = false;
+            let reordered = rules
+                .into_iter()
+                .map(|mut rule| {
+                    let before = rule.plan.clone();
+                    order_joins(&mut rule.plan, &domain.statistics);
+                    is_reordered = is_reordered || rule.plan != before;
+                    rule
+                })
+                .collect();
+
+            if is_reordered {
+                candidates.push(Replan {
+                    name: name.to_string(),
+                    rules: reordered,
+                });
+            }
+        }
 
-        Ok(())
+        candidates
+    }
+
+    /// Handles a Replan request, replacing the dataflow of a
+    /// relation of interest with one implementing the given
+    /// rules. The old dataflow is shut down, and the returned
+    /// relation starts over from the current state of all inputs,
+    /// so clients have to be handed over via a snapshot.
+    pub fn replan<S: Scope<Timestamp = T>>(
+        &mut self,
+        req: Replan<A>,
+        scope: &mut S,
+    ) -> Result<Collection<S, Vec<Value>, isize>, Error> {
+        let Replan { name, rules } = req;
+        let name: A = name.into();
+
+        if !self.shutdown_handles.contains_key(&name) {
+            return Err(Error::not_found(format!(
+                "Relation {} isn't being computed.",
+                name
+            )));
+        }
+
+        let lazy_indices = self.config.enable_lazy_indices;
+        #[cfg(feature = "serde_json")]
+        let eviction_directory = self.config.eviction_directory.clone();
+        let domain = self.domain_of_mut(&name)?;
+
+        for rule in rules.iter() {
+            if !domain.rules.contains_key(&rule.name) {
+                return Err(Error::not_found(format!(
+                    "Rule {} does not exist.",
+                    rule.name
+                )));
+            }
+        }
+
+        for rule in rules.into_iter() {
+            domain.rules.insert(rule.name.clone(), rule);
+        }
+
+        #[cfg(feature = "serde_json")]
+        Self::page_in(domain, &name, scope, eviction_directory)?;
+        Self::ensure_indices(domain, &name, scope, Strategy::BinaryJoins, lazy_indices)?;
+
+        // Rules were reordered already, and must stay exactly as
+        // they are on all workers.
+        let (relation, shutdown_handle) =
+            Self::implement_in(domain, name.clone(), scope, Strategy::BinaryJoins)?;
+        self.log_event(LifecycleEvent::QueryReplanned {
+            name: name.to_string(),
+        });
+        let relation = self.instrument_relation(&name, relation);
+
+        // Replacing the old handle shuts its dataflow down.
+        self.shutdown_handles.insert(name.clone(), shutdown_handle);
+        self.record_estimates(&name)?;
+
+        Ok(relation)
+    }
+
+    /// Returns true iff the named relation depends only on attributes
+    /// of the system domain. Relations mixing system and user
+    /// attributes are not supported, because the two domains progress
+    /// independently.
+    fn is_introspective(&self, name: &A) -> Result<bool, Error> {
+        // Tenants can't refer to system attributes.
+        if tenant_of(&name.to_string()).is_some() {
+            return Ok(false);
+        }
+
+        let mut system = false;
+        let mut user = false;
+
+        for rule in collect_dependencies(&self.internal, &[name.clone()])?.iter() {
+            for aid in rule.plan.dependencies().attributes.iter() {
+                if self.system.attributes.contains_key(aid) {
+                    system = true;
+                } else {
+                    user = true;
+                }
+            }
+        }
+
+        if system && user {
+            Err(Error::unsupported(format!(
+                "Relation {} mixes system and user attributes.",
+                name
+            )))
+        } else {
+            Ok(system)
+        }
+    }
+
+    /// Returns the domain of the specified tenant, or the internal
+    /// domain.
+    fn tenant_domain(&self, tenant: Option<&str>) -> Result<&Domain<A, T>, Error> {
+        match tenant {
+            None => Ok(&self.internal),
+            Some(tenant) => self
+                .tenants
+                .get(tenant)
+                .ok_or_else(|| Error::not_found(format!("Tenant {} does not exist.", tenant))),
+        }
+    }
+
+    /// Mutable version of `tenant_domain`.
+    fn tenant_domain_mut(&mut self, tenant: Option<&str>) -> Result<&mut Domain<A, T>, Error> {
+        match tenant {
+            None => Ok(&mut self.internal),
+            Some(tenant) => self
+                .tenants
+                .get_mut(tenant)
+                .ok_or_else(|| Error::not_found(format!("Tenant {} does not exist.", tenant))),
+        }
+    }
+
+    /// Returns the domain of the specified tenant, creating it if
+    /// necessary, or the internal domain.
+    fn tenant_domain_or_insert(&mut self, tenant: Option<&str>) -> &mut Domain<A, T> {
+        match tenant {
+            None => &mut self.internal,
+            Some(tenant) => {
+                let base = &self.internal;
+                self.tenants
+                    .entry(tenant.to_string())
+                    .or_insert_with(|| Domain::new_from(tenant, base))
+            }
+        }
+    }
+
+    /// Returns the domain holding the named attribute or rule, as
+    /// determined by its tenant.
+    pub fn domain_of(&self, name: &A) -> Result<&Domain<A, T>, Error> {
+        self.tenant_domain(tenant_of(&name.to_string()))
+    }
+
+    /// Mutable version of `domain_of`.
+    pub fn domain_of_mut(&mut self, name: &A) -> Result<&mut Domain<A, T>, Error> {
+        self.tenant_domain_mut(tenant_of(&name.to_string()))
+    }
+
+    /// Handles an IndexSizes request, measuring the arrangements of
+    /// all domains on this worker, largest first.
+    pub fn index_sizes(&mut self) -> Vec<IndexSize> {
+        let mut sizes = self.internal.index_sizes();
+        sizes.extend(self.system.index_sizes());
+
+        for domain in self.tenants.values_mut() {
+            sizes.extend(domain.index_sizes());
+        }
+
+        sizes.sort_by(|x, y| y.bytes.cmp(&x.bytes).then_with(|| x.cmp(y)));
+        sizes
+    }
+
+    /// Returns the specified strategy, falling back to the
+    /// server-wide default.
+    fn strategy(&self, strategy: Option<Strategy>) -> Strategy {
+        strategy.unwrap_or_else(|| {
+            if self.config.enable_optimizer {
+                Strategy::WorstCaseOptimal
+            } else {
+                Strategy::BinaryJoins
+            }
+        })
+    }
+
+    /// Handles an Explain request, describing how the specified plan
+    /// would be implemented: the order of joins, the indices used,
+    /// and which arrangements would be shared with other queries.
+    pub fn explain(&self, plan: &Plan<A>, strategy: Option<Strategy>) -> Explanation {
+        let strategy = self.strategy(strategy);
+
+        let mut plan = plan.clone();
+        if self.config.enable_reordering && strategy == Strategy::BinaryJoins {
+            order_joins(&mut plan, &self.internal.statistics);
+        }
+
+        explain(&plan, strategy, &self.internal)
+    }
+
+    /// Implements the named relation using the specified strategy,
+    /// falling back to the server-wide default.
+    fn implement_relation<S: Scope<Timestamp = T>>(
+        &mut self,
+        name: A,
+        scope: &mut S,
+        strategy: Option<Strategy>,
+    ) -> Result<(Collection<S, Vec<Value>, isize>, ShutdownHandle), Error> {
+        let strategy = self.strategy(strategy);
+        let reorder = self.config.enable_reordering && strategy == Strategy::BinaryJoins;
+        let lazy_indices = self.config.enable_lazy_indices;
+        #[cfg(feature = "serde_json")]
+        let eviction_directory = self.config.eviction_directory.clone();
+
+        let domain = if self.is_introspective(&name)? {
+            // Rules are only ever registered with the internal
+            // domain.
+            self.system.rules = self.internal.rules.clone();
+            &mut self.system
+        } else {
+            self.domain_of_mut(&name)?
+        };
+
+        // Evicted attributes have to be back in place before any
+        // rule is inspected further.
+        #[cfg(feature = "serde_json")]
+        Self::page_in(domain, &name, scope, eviction_directory)?;
+
+        // Interests may ask for worst-case optimal joins, which only
+        // some plans can be translated to.
+        if strategy == Strategy::WorstCaseOptimal {
+            for rule in collect_dependencies(domain, &[name.clone()])?.into_iter() {
+                validate_bindings(&rule.plan).map_err(|mut error| {
+                    error.message = format!("Rule {}: {}", rule.name, error.message);
+                    error
+                })?;
+            }
+        }
+
+        if reorder {
+            // Reordered rules are equivalent to the original ones, so
+            // they simply replace them.
+            for mut rule in collect_dependencies(domain, &[name.clone()])?.into_iter() {
+                order_joins(&mut rule.plan, &domain.statistics);
+                domain.rules.insert(rule.name.clone(), rule);
+            }
+        }
+
+        Self::ensure_indices(domain, &name, scope, strategy, lazy_indices)?;
+
+        Self::implement_in(domain, name, scope, strategy)
+    }
+
+    /// Makes sure the domain maintains all attribute indices the
+    /// named relation would import. Missing reverse indices are built
+    /// on demand if so configured, any other missing index fails the
+    /// relation before it is implemented.
+    fn ensure_indices<S: Scope<Timestamp = T>>(
+        domain: &mut Domain<A, T>,
+        name: &A,
+        scope: &mut S,
+        strategy: Strategy,
+        lazy_indices: bool,
+    ) -> Result<(), Error> {
+        let plan = match domain.rule(name) {
+            // Unknown rules are reported during implementation.
+            None => return Ok(()),
+            Some(rule) => rule.plan.clone(),
+        };
+
+        let explanation = explain(&plan, strategy, domain);
+
+        for arrangement in explanation.missing_indices.into_iter() {
+            if let Arrangement::Attribute { attribute, index } = arrangement {
+                let aid: A = attribute.into();
+                let reverse = match index {
+                    Index::ReverseCount | Index::ReversePropose | Index::ReverseValidate => true,
+                    _ => false,
+                };
+
+                if reverse && lazy_indices {
+                    domain.build_reverse_indices(scope, &aid)?;
+                } else if reverse {
+                    return Err(Error::unsupported(format!(
+                        "Attribute {} doesn't maintain reverse indices, create it with index direction Both.",
+                        aid
+                    )));
+                } else {
+                    return Err(Error::unsupported(format!(
+                        "Attribute {} doesn't maintain a {:?} index.",
+                        aid, index
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Installs all evicted attributes the named relation depends
+    /// on anew, from the updates this worker offloaded for them.
+    #[cfg(feature = "serde_json")]
+    fn page_in<S: Scope<Timestamp = T>>(
+        domain: &mut Domain<A, T>,
+        name: &A,
+        scope: &mut S,
+        directory: Option<String>,
+    ) -> Result<(), Error> {
+        use timely::dataflow::operators::ToStream;
+
+        // Rules are only type checked once their attributes are
+        // back, so dependencies are gathered without going through
+        // `collect_dependencies`.
+        let mut seen = HashSet::new();
+        let mut queue = vec![name.clone()];
+        let mut evicted = Vec::new();
+
+        while let Some(next) = queue.pop() {
+            if !seen.insert(next.clone()) {
+                continue;
+            }
+
+            if let Some(rule) = domain.rule(&next) {
+                let dependencies = rule.plan.dependencies();
+
+                queue.extend(dependencies.names.into_iter());
+                evicted.extend(
+                    dependencies
+                        .attributes
+                        .into_iter()
+                        .filter(|aid| domain.is_evicted(aid)),
+                );
+            }
+        }
+
+        if evicted.is_empty() {
+            return Ok(());
+        }
+
+        let directory = directory.ok_or_else(|| {
+            Error::fault("Attributes have been evicted, but no eviction directory is configured.")
+        })?;
+
+        evicted.sort();
+        evicted.dedup();
+
+        for aid in evicted.into_iter() {
+            let part: self::eviction::EvictedPart<A> =
+                self::eviction::take_part(&directory, &aid.to_string(), scope.index())?;
+
+            let config = domain
+                .unevict(&aid)
+                .ok_or_else(|| Error::fault(format!("Attribute {} has no configuration.", aid)))?;
+
+            let pairs = part
+                .updates
+                .into_iter()
+                .map(|(datum, t, diff)| (datum, T::from(t), diff))
+                .to_stream(scope);
+
+            // Updates were put into shape by the original input
+            // semantics already.
+            let raw = AttributeConfig {
+                input_semantics: InputSemantics::Raw,
+                ..config.clone()
+            };
+
+            Self::install_attribute_stream(domain, aid.clone(), raw, pairs);
+            domain.attributes.insert(aid, config);
+        }
+
+        Ok(())
+    }
+
+    /// Implements the named relation within the specified domain,
+    /// leaving its rules as they are.
+    fn implement_in<S: Scope<Timestamp = T>>(
+        domain: &mut Domain<A, T>,
+        name: A,
+        scope: &mut S,
+        strategy: Strategy,
+    ) -> Result<(Collection<S, Vec<Value>, isize>, ShutdownHandle), Error> {
+        let (mut rel_map, shutdown_handle) = match strategy {
+            Strategy::WorstCaseOptimal => implement_neu(scope, domain, name.clone())?,
+            Strategy::BinaryJoins => implement(scope, domain, name.clone())?,
+        };
+
+        match rel_map.remove(&name) {
+            None => Err(Error::fault(format!(
+                "Relation of interest ({}) wasn't actually implemented.",
+                name
+            ))),
+            Some(relation) => Ok((relation, shutdown_handle)),
+        }
+    }
+
+    /// Handles an Interest request pinned to a past time. Only updates
+    /// at times less than or equal to `as_of` are revealed, which
+    /// together accumulate to the relation as it was at that time. This
+    /// requires all attributes involved to have retained their history
+    /// back to `as_of`.
+    pub fn interest_as_of<S: Scope<Timestamp = T>>(
+        &mut self,
+        name: A,
+        as_of: T,
+        scope: &mut S,
+    ) -> Result<Collection<S, Vec<Value>, isize>, Error> {
+        let domain = self.domain_of(&name)?;
+        for rule in collect_dependencies(domain, &[name.clone()])?.iter() {
+            for aid in rule.plan.dependencies().attributes.iter() {
+                if !domain.retains(aid, &as_of) {
+                    return Err(Error::conflict(format!(
+                        "Attribute {} has been compacted beyond {:?}.",
+                        aid, as_of
+                    )));
+                }
+            }
+        }
+
+        let (relation, shutdown_handle) = self.implement_relation(name.clone(), scope, None)?;
+        self.log_event(LifecycleEvent::QueryImplemented {
+            name: format!("{}@{:?}", name, as_of),
+        });
+        let relation = self.instrument_relation(&name, relation);
+
+        let key: A = format!("{}@{:?}", name, as_of).into();
+        self.shutdown_handles.insert(key, shutdown_handle);
+
+        let pinned = relation
+            .inner
+            .filter(move |(_tuple, t, _diff)| t.less_equal(&as_of))
+            .as_collection();
+
+        Ok(pinned)
+    }
+
+    /// Returns the tenant a query is evaluated within, which is the
+    /// one all names it refers to belong to.
+    fn query_tenant(query: &Query<A>) -> Result<Option<String>, Error> {
+        let dependencies = query.plan.dependencies();
+
+        common_tenant(
+            dependencies
+                .attributes
+                .iter()
+                .chain(dependencies.names.iter()),
+        )
+    }
+
+    /// Returns the current epoch of the domain a query would be
+    /// evaluated in. All commands handled so far have been applied
+    /// at earlier epochs.
+    pub fn query_epoch(&self, query: &Query<A>) -> Result<T, Error> {
+        let tenant = Self::query_tenant(query)?;
+
+        Ok(self
+            .tenant_domain(tenant.as_ref().map(String::as_str))?
+            .epoch()
+            .clone())
+    }
+
+    /// Handles a Query request, implementing its plan in the
+    /// specified scope. The plan is only registered for as long as it
+    /// takes to implement it, s.t. it never shows up among the
+    /// server's rules. The returned handle keeps the dataflow's inputs
+    /// alive, until it is dropped.
+    pub fn query<S: Scope<Timestamp = T>>(
+        &mut self,
+        query: Query<A>,
+        scope: &mut S,
+    ) -> Result<(Collection<S, Vec<Value>, isize>, ShutdownHandle), Error> {
+        let name: A = match Self::query_tenant(&query)? {
+            None => format!("df.query/{}", self.next_query),
+            Some(tenant) => format!("{}{}df.query/{}", tenant, TENANT_SEPARATOR, self.next_query),
+        }
+        .into();
+        self.next_query += 1;
+
+        self.register(Register {
+            rules: vec![Rule::named(name.clone(), query.plan)],
+            publish: vec![],
+            datalog: vec![],
+        })?;
+
+        let implemented = self.implement_relation(name.clone(), scope, None);
+
+        self.unregister(&name)?;
+
+        if implemented.is_ok() {
+            self.log_event(LifecycleEvent::QueryImplemented {
+                name: name.to_string(),
+            });
+        }
+
+        implemented
+    }
+
+    /// Handles an Interest request resuming after a past time. Only
+    /// updates at times not less than or equal to `after` are
+    /// revealed, i.e. those a client holding all results up to
+    /// `after` has missed. This requires all attributes involved to
+    /// have retained their history back to `after`.
+    pub fn interest_after<S: Scope<Timestamp = T>>(
+        &mut self,
+        name: A,
+        after: T,
+        scope: &mut S,
+    ) -> Result<Collection<S, Vec<Value>, isize>, Error> {
+        let domain = self.domain_of(&name)?;
+        for rule in collect_dependencies(domain, &[name.clone()])?.iter() {
+            for aid in rule.plan.dependencies().attributes.iter() {
+                if !domain.retains(aid, &after) {
+                    return Err(Error::conflict(format!(
+                        "Attribute {} has been compacted beyond {:?}.",
+                        aid, after
+                    )));
+                }
+            }
+        }
+
+        let (relation, shutdown_handle) = self.implement_relation(name.clone(), scope, None)?;
+        self.log_event(LifecycleEvent::QueryImplemented {
+            name: format!("{}>{:?}", name, after),
+        });
+        let relation = self.instrument_relation(&name, relation);
+
+        let key: A = format!("{}>{:?}", name, after).into();
+        self.shutdown_handles.insert(key, shutdown_handle);
+
+        let missed = relation
+            .inner
+            .filter(move |(_tuple, t, _diff)| !t.less_equal(&after))
+            .as_collection();
+
+        Ok(missed)
+    }
+
+    /// Translates a GraphQL request into the requests implementing
+    /// it. Queries become one-off Query requests, subscriptions are
+    /// registered and published under the requested name, along with
+    /// an interest in them.
+    #[cfg(feature = "graphql")]
+    pub fn graphql(&self, req: GraphQlRequest) -> Result<Vec<Request<A>>, Error> {
+        use crate::plan::graphql::{GraphQl, OperationKind};
+
+        let schema = self.tenant_domain(tenant_of(&req.name))?.schema();
+        let (kind, query) = GraphQl::compile(req.query, &schema)?;
+        let plan = Plan::GraphQl(query);
+
+        match kind {
+            OperationKind::Query => Ok(vec![Request::Query(Query { plan })]),
+            OperationKind::Subscription => {
+                let name = A::from(req.name.clone());
+
+                Ok(vec![
+                    Request::Register(Register {
+                        rules: vec![Rule {
+                            name: name.clone(),
+                            plan,
+                            semantics: None,
+                        }],
+                        publish: vec![name],
+                        datalog: vec![],
+                    }),
+                    Request::Interest(Interest {
+                        name: req.name,
+                        granularity: None,
+                        sink: None,
+                        disable_logging: None,
+                        as_of: None,
+                        strategy: None,
+                        resume_after: None,
+                        limits: None,
+                        page: None,
+                    }),
+                ])
+            }
+        }
+    }
+
+    /// Handles a Register request.
+    pub fn register(&mut self, req: Register<A>) -> Result<(), Error> {
+        let Register {
+            mut rules, datalog, ..
+        } = req;
+
+        for rule in datalog.into_iter() {
+            rules.push(Rule {
+                name: A::from(rule.name),
+                plan: datalog::parse(&rule.query)?,
+                semantics: None,
+            });
+        }
+
+        // Rules are registered with the domain of their tenant, and
+        // may only refer to names within it.
+        let mut by_tenant: BTreeMap<Option<String>, Vec<Rule<A>>> = BTreeMap::new();
+        for rule in rules.into_iter() {
+            check_tenancy(&rule)?;
+
+            let tenant = tenant_of(&rule.name.to_string()).map(str::to_string);
+            by_tenant.entry(tenant).or_insert_with(Vec::new).push(rule);
+        }
+
+        // Check the combined rule sets before registering anything,
+        // s.t. a rejected request leaves all domains unchanged.
+        for (tenant, rules) in by_tenant.iter() {
+            let mut combined = self
+                .tenant_domain(tenant.as_ref().map(String::as_str))?
+                .rules
+                .clone();
+
+            for rule in rules.iter() {
+                combined
+                    .entry(rule.name.clone())
+                    .or_insert_with(|| rule.clone());
+            }
+
+            validate_heads(&combined)?;
+            validate_stratification(&combined)?;
+        }
+
+        for (tenant, rules) in by_tenant.into_iter() {
+            let tenant = tenant.as_ref().map(String::as_str);
+
+            for rule in rules.into_iter() {
+                if self.tenant_domain(tenant)?.rules.contains_key(&rule.name) {
+                    // @TODO panic if hashes don't match
+                    // panic!("Attempted to re-register a named relation");
+                    continue;
+                } else {
+                    if let Some(ref recorder) = self.metrics {
+                        recorder.increment(metrics::QUERIES, vec![], 1.0);
+                    }
+
+                    self.log_event(LifecycleEvent::QueryRegistered {
+                        name: rule.name.to_string(),
+                    });
+
+                    self.tenant_domain_mut(tenant)?
+                        .rules
+                        .insert(rule.name.clone(), rule);
+                }
+            }
+
+            if self.config.enable_sharing {
+                share_subplans(&mut self.tenant_domain_mut(tenant)?.rules);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handles an Unregister request, removing the named rule and
+    /// shutting down any dataflows still implementing it. Rules that
+    /// are subscribed to, or used by other rules, can't be
+    /// unregistered.
+    pub fn unregister(&mut self, name: &A) -> Result<(), Error> {
+        if self.interests.keys().any(|key| is_dataflow_of(name, key)) {
+            return Err(Error::conflict(format!(
+                "Rule {} still has subscribers.",
+                name
+            )));
+        }
+
+        let domain = self.domain_of_mut(name)?;
+        domain.unregister(name)?;
+
+        // Shared rules are dropped along with their last user.
+        let unused: Vec<A> = domain
+            .rules
+            .keys()
+            .filter(|shared| is_shared(*shared))
+            .filter(|shared| {
+                !domain
+                    .rules
+                    .values()
+                    .any(|rule| rule.plan.dependencies().names.contains(*shared))
+            })
+            .cloned()
+            .collect();
+
+        for shared in unused.iter() {
+            domain.unregister(shared)?;
+        }
+
+        let dataflows: Vec<A> = self
+            .shutdown_handles
+            .keys()
+            .filter(|key| is_dataflow_of(name, key))
+            .cloned()
+            .collect();
+
+        for key in dataflows.iter() {
+            self.shutdown_query(key);
+        }
+
+        Ok(())
+    }
+
+    /// Handles a Kill request, shutting down all dataflows
+    /// implementing the named rule, while leaving the rule itself in
+    /// place. Returns the keys of all interests dropped in the
+    /// process, along with the clients that held them.
+    pub fn kill(&mut self, name: &A) -> Result<Vec<(A, HashSet<Token>)>, Error> {
+        let dataflows: Vec<A> = self
+            .shutdown_handles
+            .keys()
+            .filter(|key| is_dataflow_of(name, key))
+            .cloned()
+            .collect();
+
+        if dataflows.is_empty() {
+            return Err(Error::not_found(format!(
+                "No dataflow implements {}.",
+                name
+            )));
+        }
+
+        for key in dataflows.iter() {
+            self.shutdown_query(key);
+        }
+
+        let keys: Vec<A> = self
+            .interests
+            .keys()
+            .filter(|key| is_dataflow_of(name, key))
+            .cloned()
+            .collect();
+
+        Ok(keys
+            .into_iter()
+            .filter_map(|key| self.interests.remove(&key).map(|clients| (key, clients)))
+            .collect())
+    }
+
+    /// Returns the limits applying to a query, i.e. those requested
+    /// for it, falling back to the ones configured for the server.
+    pub fn limits(&self, requested: Option<Limits>) -> Limits {
+        requested.unwrap_or_default().or(self.config.query_limits)
+    }
+
+    /// Ensures that the named relation is small enough to be
+    /// implemented within the specified limits.
+    pub fn check_limits(&self, name: &A, limits: &Limits) -> Result<(), Error> {
+        let rules = collect_dependencies(self.domain_of(name)?, &[name.clone()])?;
+
+        limits.check_size(name, &rules)
+    }
+
+    /// Enforces the runtime limits on the relation implementing the
+    /// named query. Violations are reported via `take_violations`.
+    pub fn enforce_limits<S: Scope<Timestamp = T>>(
+        &self,
+        name: A,
+        limits: &Limits,
+        relation: Collection<S, Vec<Value>, isize>,
+    ) -> Collection<S, Vec<Value>, isize> {
+        limits.enforce(name, relation, self.violations.clone())
+    }
+
+    /// Returns all queries that have exceeded their limits since the
+    /// last call, along with the reason. Only the worker observing a
+    /// violation learns about it, so it is up to that worker to kill
+    /// the query everywhere.
+    pub fn take_violations(&self) -> Vec<(A, Error)> {
+        self.violations.borrow_mut().drain(..).collect()
+    }
+
+    /// Handles a Materialize request, implementing the named rule in
+    /// a dataflow of its own and registering its results as a view,
+    /// s.t. later queries referring to it import them rather than
+    /// implementing the rule again.
+    pub fn materialize<S: Scope<Timestamp = T>>(
+        &mut self,
+        scope: &mut S,
+        name: A,
+    ) -> Result<(), Error> {
+        let domain = self.domain_of_mut(&name)?;
+
+        if domain.is_materialized(&name) {
+            return Err(Error::conflict(format!(
+                "Rule {} is already materialized.",
+                name
+            )));
+        }
+
+        let (mut relations, shutdown_handle) = implement(scope, domain, name.clone())?;
+
+        let trace = relations
+            .remove(&name)
+            .ok_or_else(|| Error::fault(format!("Rule {} was not implemented.", name)))?
+            .map(|tuple| (tuple, ()))
+            .arrange_named(&format!("->View({})", &name))
+            .trace;
+
+        self.domain_of_mut(&name)?
+            .materialize(name, trace, shutdown_handle);
+
+        Ok(())
+    }
+
+    /// Creates the input and indices of a transactable attribute, as
+    /// a domain holding only this attribute.
+    fn transactable_attribute<S>(scope: &mut S, name: A, config: &AttributeConfig) -> Domain<A, T>
+    where
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        let ((handle, cap), pairs) =
+            scope.new_unordered_input::<((Value, Value), S::Timestamp, isize)>();
+
+        // Inputs to partitioned attributes are routed to the worker
+        // owning their entity, which is also where the forward
+        // indices keep them. Inputs introduced locally already are
+        // not affected by this.
+        let pairs = if config.partitioned {
+            pairs.exchange(|((e, _v), _t, _diff)| e.hashed())
+        } else {
+            pairs
+        };
+
+        let tuples = match config.input_semantics {
+            InputSemantics::Raw => pairs.as_collection(),
+            InputSemantics::LastWriteWins => pairs.as_collection().last_write_wins(),
+            // Ensure that redundant (e,v) pairs don't cause
+            // misleading proposals during joining.
+            InputSemantics::Distinct => pairs.as_collection().distinct(),
+        };
+
+        let mut scoped_domain = ((handle, cap), tuples).as_singleton_domain(name);
+
+        if let Some(slack) = config.trace_slack.clone() {
+            scoped_domain = scoped_domain.with_slack(slack.into());
+        }
+
+        // LastWriteWins is a special case, because count, propose,
+        // and validate are all essentially the same.
+        if config.input_semantics != InputSemantics::LastWriteWins {
+            scoped_domain = scoped_domain.with_query_support(config.query_support.clone());
+        }
+
+        if config.index_direction == IndexDirection::Both {
+            scoped_domain = scoped_domain.with_reverse_indices();
+        }
+
+        if config.fulltext {
+            scoped_domain = scoped_domain.with_fulltext_index();
+        }
+
+        scoped_domain.into()
+    }
+
+    /// Creates the introspection attributes in the system domain and
+    /// keeps them in sync with the server's metadata from now on, see
+    /// `introspection`.
+    pub fn enable_introspection<S>(&mut self, scope: &mut S) -> Result<(), Error>
+    where
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        if self.introspection.is_some() {
+            return Err(Error::conflict("Introspection is enabled already."));
+        }
+
+        for (name, value_type) in introspection::ATTRIBUTES.iter() {
+            let name = A::from(*name);
+            let config = AttributeConfig {
+                value_type: Some(*value_type),
+                ..AttributeConfig::uncompacted(InputSemantics::Raw)
+            };
+
+            self.system += Self::transactable_attribute(scope, name.clone(), &config);
+            self.system.attributes.insert(name, config);
+        }
+
+        self.introspection = Some(Introspection::default());
+
+        Ok(())
+    }
+
+    /// Publishes all changes to server metadata since the last
+    /// refresh at the current epoch of the system domain, then
+    /// advances the latter along with the internal domain. The system
+    /// domain is otherwise driven by its sources, which might be
+    /// ahead already.
+    fn advance_introspection(&mut self, next: &T) -> Result<(), Error> {
+        let domains = std::iter::once(&self.internal).chain(self.tenants.values());
+
+        let tx_data = match self.introspection {
+            None => return Ok(()),
+            Some(ref mut introspection) => {
+                introspection.update(introspection::facts(domains, &self.interests))
+            }
+        };
+
+        if !tx_data.is_empty() {
+            // Metadata is the same on all workers, so only the first
+            // one introduces it.
+            let is_owner = self.worker_index == 0;
+            let batches = self.system.prepare(tx_data)?;
+            self.system.apply_partitioned(batches, is_owner);
+        }
+
+        if self.system.epoch().less_than(next) {
+            self.system.advance_epoch(next.clone())?;
+        }
+
+        Ok(())
+    }
+
+    /// Handles a CreateAttribute request.
+    pub fn create_attribute<X, S>(
+        &mut self,
+        scope: &mut S,
+        name: X,
+        config: AttributeConfig,
+    ) -> Result<(), Error>
+    where
+        X: Into<A>,
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        let name: A = name.into();
+
+        let exists = self
+            .tenant_domain(tenant_of(&name.to_string()))
+            .map_or(false, |domain| domain.has_attribute(&name));
+
+        if exists {
+            return Err(Error::conflict(format!(
+                "An attribute of name {} already exists.",
+                name
+            )));
+        }
+
+        let attribute = Self::transactable_attribute(scope, name.clone(), &config);
+
+        // Attributes of a tenant create its domain, if necessary.
+        let domain = self.tenant_domain_or_insert(tenant_of(&name.to_string()));
+
+        *domain += attribute;
+
+        // Singleton domains start out with a default configuration.
+        domain.attributes.insert(name.clone(), config);
+
+        self.log_event(LifecycleEvent::AttributeCreated {
+            name: name.to_string(),
+        });
+
+        Ok(())
+    }
+
+    /// Handles a Bridge request. The source attribute is imported
+    /// and reclocked into the target domain, where it is published as
+    /// a sourced attribute of its own. Updates before the input
+    /// frontier of the source domain become visible at the target
+    /// epoch this frontier is observed at, see `advance`. Queries
+    /// can therefore join bridged attributes like any other.
+    pub fn bridge<S>(&mut self, scope: &mut S, req: Bridge) -> Result<(), Error>
+    where
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        let source_tenant = tenant_of(&req.source).map(str::to_string);
+        let target_tenant = tenant_of(&req.name).map(str::to_string);
+
+        if source_tenant == target_tenant {
+            return Err(Error::incorrect(format!(
+                "Attributes {} and {} belong to the same domain.",
+                req.source, req.name
+            )));
+        }
+
+        let source: A = req.source.into();
+        let name: A = req.name.into();
+
+        let (config, propose, shutdown) = {
+            let domain = self.domain_of_mut(&source)?;
+
+            let config =
+                domain.attributes.get(&source).cloned().ok_or_else(|| {
+                    Error::not_found(format!("Attribute {} does not exist.", source))
+                })?;
+
+            let (propose, shutdown) = match domain.forward_propose(&source) {
+                None => {
+                    return Err(Error::unsupported(format!(
+                        "Attribute {} can't be bridged without a propose index.",
+                        source
+                    )))
+                }
+                Some(propose_trace) => {
+                    propose_trace.import_frontier(scope, &format!("Bridge({})", source))
+                }
+            };
+
+            (config, propose, shutdown)
+        };
+
+        let target = self.tenant_domain_or_insert(target_tenant.as_ref().map(String::as_str));
+
+        if target.attributes.contains_key(&name) {
+            return Err(Error::conflict(format!(
+                "Attribute {} already exists.",
+                name
+            )));
+        }
+
+        // The first worker observes all frontiers, s.t. all workers
+        // bind them to the same epochs.
+        let ((handle, cap), bindings) = scope.new_unordered_input::<Vec<T>>();
+        let cap = cap.delayed(target.epoch());
+
+        let pairs = propose
+            .as_collection(|e, v| (e.clone(), v.clone()))
+            .reclock(&bindings.broadcast())
+            .inner;
+
+        // Input semantics have been enforced on the source already.
+        let config = AttributeConfig {
+            input_semantics: InputSemantics::Raw,
+            ..config
+        };
+
+        Self::install_attribute_stream(target, name.clone(), config, pairs);
+
+        self.bridges.push(Bindings {
+            source: source_tenant,
+            target: target_tenant,
+            sending: scope.index() == 0,
+            handle,
+            cap,
+            last: Vec::new(),
+            _shutdown: ShutdownHandle::from_button(shutdown),
+        });
+
+        self.log_event(LifecycleEvent::AttributeCreated {
+            name: name.to_string(),
+        });
+
+        Ok(())
+    }
+
+    /// Handles a CreateIndex request. The forward indices of all
+    /// covered attributes are imported and arranged into a single
+    /// trace, which pull expressions and reverse references use
+    /// whenever it covers them.
+    pub fn create_index<S>(&mut self, scope: &mut S, req: CreateIndex) -> Result<(), Error>
+    where
+        S: Scope<Timestamp = T>,
+    {
+        if req.attributes.is_empty() {
+            return Err(Error::incorrect(format!(
+                "Index {} doesn't cover any attributes.",
+                req.name
+            )));
+        }
+
+        let name: A = req.name.into();
+        let attributes: Vec<A> = req.attributes.into_iter().map(A::from).collect();
+
+        common_tenant(std::iter::once(&name).chain(attributes.iter()))?;
+
+        let domain = self.domain_of_mut(&name)?;
+        let mut shutdown_handle = ShutdownHandle::empty();
+
+        let mut streams = Vec::with_capacity(attributes.len());
+        for aid in attributes.iter() {
+            let (propose, shutdown) = match domain.forward_propose(aid) {
+                None => {
+                    return Err(Error::not_found(format!(
+                        "Attribute {} does not exist.",
+                        aid
+                    )))
+                }
+                Some(propose_trace) => {
+                    propose_trace.import_frontier(scope, &format!("Propose({})", aid))
+                }
+            };
+
+            shutdown_handle.add_button(shutdown);
+
+            let a = aid.clone().into_value();
+            let pairs = match req.kind {
+                CompositeIndex::Entity => {
+                    propose.as_collection(move |e, v| (e.clone(), (a.clone(), v.clone())))
+                }
+                // Only references can be navigated in reverse.
+                CompositeIndex::Ref => propose
+                    .as_collection(move |e, v| (v.clone(), (a.clone(), e.clone())))
+                    .filter(|(v, _ae)| match v {
+                        Value::Eid(_) => true,
+                        _ => false,
+                    }),
+            };
+
+            streams.push(pairs.inner);
+        }
+
+        let trace = scope
+            .concatenate(streams)
+            .as_collection()
+            .arrange_named(&format!("->Composite({})", &name))
+            .trace;
+
+        domain.create_composite(name, req.kind, attributes, trace, shutdown_handle)
+    }
+
+    /// Handles a CreateParameter request.
+    pub fn create_parameter<X, S>(&mut self, scope: &mut S, name: X) -> Result<(), Error>
+    where
+        X: Into<A>,
+        S: Scope<Timestamp = T>,
+    {
+        let ((handle, cap), tuples) = scope.new_unordered_input::<(Vec<Value>, T, isize)>();
+
+        let name: A = name.into();
+        let trace = tuples
+            .as_collection()
+            .distinct()
+            .map(|tuple| (tuple, ()))
+            .arrange_named(&format!("->Parameter({})", &name))
+            .trace;
+
+        self.internal.create_parameter(name, handle, cap, trace)
     }
 
     /// Returns a fresh sourcing context, useful for installing 3DF
@@ -390,7 +2428,53 @@ where
             domain_probe: self.internal.domain_probe().clone(),
             timely_events: self.timely_events.clone().unwrap(),
             differential_events: self.differential_events.clone().unwrap(),
+            checkpoints: self.checkpoints.clone(),
+            poll_interval: self.poll_interval.clone(),
+        }
+    }
+
+    /// Returns a handle to the maximum number of results per message,
+    /// which follows later reconfigurations.
+    pub fn batch_size(&self) -> Rc<Cell<Option<usize>>> {
+        self.batch_size.clone()
+    }
+
+    /// Handles a Reconfigure request. Trace slack is applied by the
+    /// next trace advance, batch sizes by the next results sent, and
+    /// polling intervals by the next activation of each source.
+    pub fn reconfigure(&mut self, req: Reconfigure) -> Result<(), Error> {
+        if req.batch_size == Some(0) {
+            return Err(Error::incorrect("Batch sizes must be positive."));
+        }
+
+        if let Some(slack) = req.trace_slack {
+            let domains = std::iter::once(&mut self.internal).chain(self.tenants.values_mut());
+
+            for domain in domains {
+                let compacted: Vec<A> = domain
+                    .attributes
+                    .iter()
+                    .filter(|(_, config)| config.trace_slack.is_some())
+                    .map(|(name, _)| name.clone())
+                    .collect();
+
+                for name in compacted.iter() {
+                    domain.set_trace_slack(name, Some(slack.clone()))?;
+                }
+            }
         }
+
+        if let Some(batch_size) = req.batch_size {
+            self.config.batch_size = Some(batch_size);
+            self.batch_size.set(Some(batch_size));
+        }
+
+        if let Some(poll_interval) = req.poll_interval {
+            self.config.poll_interval = Some(poll_interval);
+            self.poll_interval.set(Some(poll_interval));
+        }
+
+        Ok(())
     }
 
     /// Handles a RegisterSource request.
@@ -415,32 +2499,15 @@ where
 
         let mut attribute_streams = source.source(scope, context);
 
-        for (aid, config, pairs) in attribute_streams.drain(..) {
-            let pairs = match config.input_semantics {
-                InputSemantics::Raw => pairs.as_collection(),
-                InputSemantics::LastWriteWins => pairs.as_collection().last_write_wins(),
-                // Ensure that redundant (e,v) pairs don't cause
-                // misleading proposals during joining.
-                InputSemantics::Distinct => pairs.as_collection().distinct(),
-            };
-
-            let mut scoped_domain = pairs.as_singleton_domain(aid);
-
-            if let Some(slack) = config.trace_slack {
-                scoped_domain = scoped_domain.with_slack(slack.into());
-            }
-
-            // LastWriteWins is a special case, because count, propose,
-            // and validate are all essentially the same.
-            if config.input_semantics != InputSemantics::LastWriteWins {
-                scoped_domain = scoped_domain.with_query_support(config.query_support);
-            }
-
-            if config.index_direction == IndexDirection::Both {
-                scoped_domain = scoped_domain.with_reverse_indices();
-            }
+        self.log_event(LifecycleEvent::SourceAttached {
+            attributes: attribute_streams
+                .iter()
+                .map(|(aid, _config, _pairs)| aid.to_string())
+                .collect(),
+        });
 
-            self.internal += scoped_domain.into();
+        for (aid, config, pairs) in attribute_streams.drain(..) {
+            Self::install_attribute_stream(&mut self.internal, aid, config, pairs);
         }
 
         // if let Some(logger) = timely_logger {
@@ -462,12 +2529,225 @@ where
         Ok(())
     }
 
+    /// Installs a sourced stream of (e, v) pairs as a new attribute of
+    /// the specified domain, according to the specified
+    /// configuration.
+    fn install_attribute_stream<S>(
+        domain: &mut Domain<A, T>,
+        aid: A,
+        config: AttributeConfig,
+        pairs: Stream<S, ((Value, Value), T, isize)>,
+    ) where
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        let pairs = match config.input_semantics {
+            InputSemantics::Raw => pairs.as_collection(),
+            InputSemantics::LastWriteWins => pairs.as_collection().last_write_wins(),
+            // Ensure that redundant (e,v) pairs don't cause
+            // misleading proposals during joining.
+            InputSemantics::Distinct => pairs.as_collection().distinct(),
+        };
+
+        // Sources report their progress to the domain they feed
+        // into directly, s.t. any number of them can be composed.
+        let probe = domain.track_source();
+        let mut scoped_domain = (probe, pairs).as_singleton_domain(aid.clone());
+
+        if let Some(slack) = config.trace_slack.clone() {
+            scoped_domain = scoped_domain.with_slack(slack.into());
+        }
+
+        // LastWriteWins is a special case, because count, propose,
+        // and validate are all essentially the same.
+        if config.input_semantics != InputSemantics::LastWriteWins {
+            scoped_domain = scoped_domain.with_query_support(config.query_support.clone());
+        }
+
+        if config.index_direction == IndexDirection::Both {
+            scoped_domain = scoped_domain.with_reverse_indices();
+        }
+
+        if config.fulltext {
+            scoped_domain = scoped_domain.with_fulltext_index();
+        }
+
+        *domain += scoped_domain.into();
+        domain.attributes.insert(aid, config);
+    }
+
+    /// Handles a RegisterSource request for an introspection source,
+    /// installing its attributes into the system domain rather than
+    /// the internal one.
+    pub fn register_system_source<S>(
+        &mut self,
+        source: Box<dyn Sourceable<A, S>>,
+        scope: &mut S,
+    ) -> Result<(), Error>
+    where
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        let context = self.make_sourcing_context();
+
+        let mut attribute_streams = source.source(scope, context);
+
+        self.log_event(LifecycleEvent::SourceAttached {
+            attributes: attribute_streams
+                .iter()
+                .map(|(aid, _config, _pairs)| aid.to_string())
+                .collect(),
+        });
+
+        for (aid, config, pairs) in attribute_streams.drain(..) {
+            Self::install_attribute_stream(&mut self.system, aid, config, pairs);
+        }
+
+        Ok(())
+    }
+
+    /// Handles a RegisterSource request for a source feeding many
+    /// attributes from a single stream. The stream is demultiplexed
+    /// once, after which each attribute is installed exactly as with
+    /// `register_source`.
+    pub fn register_multiplexed_source<S>(
+        &mut self,
+        source: Box<dyn MultiplexedSourceable<A, S>>,
+        scope: &mut S,
+    ) -> Result<(), Error>
+    where
+        S: Scope<Timestamp = T>,
+        S::Timestamp: std::convert::Into<crate::timestamp::Time>,
+    {
+        let context = self.make_sourcing_context();
+
+        let (attributes, datoms) = source.source(scope, context);
+
+        self.log_event(LifecycleEvent::SourceAttached {
+            attributes: attributes
+                .iter()
+                .map(|(aid, _config)| aid.to_string())
+                .collect(),
+        });
+
+        for (aid, _config) in attributes.iter() {
+            if self.internal.attributes.contains_key(aid) {
+                return Err(Error::conflict(format!(
+                    "An attribute of name {} already exists.",
+                    aid
+                )));
+            }
+        }
+
+        for (aid, config, pairs) in demultiplex(attributes, &datoms) {
+            Self::install_attribute_stream(&mut self.internal, aid, config, pairs);
+        }
+
+        Ok(())
+    }
+
     /// Handles an AdvanceDomain request.
     pub fn advance_domain(&mut self, name: Option<String>, next: T) -> Result<(), Error> {
         match name {
-            None => self.internal.advance_epoch(next),
-            Some(_) => Err(Error::unsupported("Named domains are not yet supported.")),
+            None => {
+                self.advance_introspection(&next)?;
+
+                let previous = self.internal.epoch().clone();
+                self.internal.advance_epoch(next)?;
+
+                if previous != *self.internal.epoch() {
+                    self.log_event(LifecycleEvent::EpochAdvanced {
+                        epoch: format!("{:?}", self.internal.epoch()),
+                    });
+                }
+
+                Ok(())
+            }
+            Some(tenant) => match self.tenants.get_mut(&tenant) {
+                None => Err(Error::not_found(format!(
+                    "Tenant {} does not exist.",
+                    tenant
+                ))),
+                Some(domain) => domain.advance_epoch(next),
+            },
+        }
+    }
+
+    /// Advances the internal domain and the domains of all tenants
+    /// to the specified epoch.
+    pub fn advance_epochs(&mut self, next: T) -> Result<(), Error> {
+        self.advance_introspection(&next)?;
+
+        for domain in self.tenants.values_mut() {
+            domain.advance_epoch(next.clone())?;
+        }
+
+        self.internal.advance_epoch(next)
+    }
+
+    /// Advances the internal domain and the domains of all tenants by
+    /// the wall clock, see `Domain::advance_clock`.
+    pub fn advance_clock(&mut self, elapsed: Duration) -> Result<(), Error> {
+        for domain in self.tenants.values_mut() {
+            domain.advance_clock(elapsed)?;
+        }
+
+        self.internal.advance_clock(elapsed)
+    }
+
+    /// Advances the internal and the system domain, as well as the
+    /// domains of all tenants, to their current frontiers, see
+    /// `Domain::advance`.
+    pub fn advance(&mut self) -> Result<(), Error> {
+        self.internal.advance()?;
+        self.system.advance()?;
+
+        for domain in self.tenants.values_mut() {
+            domain.advance()?;
+        }
+
+        self.advance_bridges()?;
+
+        let frontier = self
+            .internal
+            .domain_probe()
+            .with_frontier(|frontier| frontier.to_vec());
+
+        if self.internal.probed_source_count() > 0 && frontier != self.last_frontier {
+            self.log_event(LifecycleEvent::FrontierAdvanced {
+                frontier: format!("{:?}", frontier),
+            });
+
+            self.last_frontier = frontier;
+        }
+
+        Ok(())
+    }
+
+    /// Binds the current input frontiers of the source domains of all
+    /// bridges to the current epochs of their target domains.
+    fn advance_bridges(&mut self) -> Result<(), Error> {
+        let observations = self
+            .bridges
+            .iter()
+            .map(|bridge| {
+                let frontier = self
+                    .tenant_domain(bridge.source.as_ref().map(String::as_str))?
+                    .input_frontier();
+                let epoch = self
+                    .tenant_domain(bridge.target.as_ref().map(String::as_str))?
+                    .epoch()
+                    .clone();
+
+                Ok((frontier, epoch))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        for (bridge, (frontier, epoch)) in self.bridges.iter_mut().zip(observations) {
+            bridge.bind(frontier, epoch);
         }
+
+        Ok(())
     }
 
     /// Handles an Uninterest request, possibly cleaning up dataflows
@@ -489,6 +2769,9 @@ where
 
     /// Cleans up all bookkeeping state for the specified client.
     pub fn disconnect_client(&mut self, client: Token) -> Result<(), Error> {
+        self.forget_principal(client);
+        self.forget_usage(client);
+
         let names: Vec<A> = self.interests.keys().cloned().collect();
 
         for query_name in names.iter() {
@@ -498,12 +2781,119 @@ where
         Ok(())
     }
 
+    /// Checks whether the client may issue the specified request,
+    /// before it is handled. `Authenticate` requests are handled
+    /// here, by remembering the principal the client has
+    /// authenticated as. Without an access control list, everything
+    /// is allowed.
+    pub fn authorize(&mut self, client: Token, req: &Request<A>) -> Result<(), Error> {
+        let acl = match self.config.acl {
+            None => return Ok(()),
+            Some(ref acl) => acl,
+        };
+
+        if let Request::Authenticate(ref token) = req {
+            let principal = acl.authenticate(token)?;
+            self.principals.insert(client, principal);
+
+            return Ok(());
+        }
+
+        acl.authorize(self.principals.get(&client).map(String::as_str), req)
+    }
+
+    /// Checks whether the request, issued at the specified time, is
+    /// within the client's quotas. If so, it is charged to them.
+    pub fn check_quotas(
+        &mut self,
+        client: Token,
+        req: &Request<A>,
+        issued_at: Duration,
+    ) -> Result<(), Error> {
+        let mut usage = self.usage.remove(&client).unwrap_or_default();
+
+        let result = self
+            .config
+            .client_quotas
+            .charge(&mut usage, req, issued_at, |name| {
+                let name = A::from(name.to_string());
+                self.domain_of(&name)
+                    .map(|domain| domain.rules.contains_key(&name))
+                    .unwrap_or(false)
+            });
+
+        self.usage.insert(client, usage);
+
+        result
+    }
+
+    /// Forgets what a client has used up of its quotas.
+    pub fn forget_usage(&mut self, client: Token) {
+        self.usage.remove(&client);
+    }
+
+    /// Forgets the principal a client has authenticated as, e.g.
+    /// because the client went away.
+    pub fn forget_principal(&mut self, client: Token) {
+        self.principals.remove(&client);
+    }
+
+    /// Handles a DropAttribute request. Attributes that are still
+    /// subscribed to, or used by registered rules, can't be dropped.
+    pub fn drop_attribute(&mut self, name: &A) -> Result<(), Error> {
+        if self.interests.contains_key(name) {
+            return Err(Error::conflict(format!(
+                "Attribute {} still has subscribers.",
+                name
+            )));
+        }
+
+        self.domain_of_mut(name)?.drop_attribute(name)
+    }
+
+    /// Handles an Evict request, offloading this worker's share of a
+    /// sealed attribute to the eviction directory and dropping its
+    /// indices. Queries requiring the attribute later on page it back
+    /// in, which requires the same number of workers. Evicted
+    /// attributes don't survive restarts of the server.
+    #[cfg(feature = "serde_json")]
+    pub fn evict(&mut self, name: &A, worker: usize) -> Result<(), Error> {
+        let directory = match self.config.eviction_directory.clone() {
+            None => {
+                return Err(Error::unsupported(
+                    "Eviction requires an eviction directory to be configured.",
+                ))
+            }
+            Some(directory) => directory,
+        };
+
+        let offload_name = name.clone();
+
+        self.domain_of_mut(name)?.evict(name, move |updates| {
+            let part = self::eviction::EvictedPart {
+                name: offload_name,
+                worker,
+                updates: updates
+                    .into_iter()
+                    .map(|(datum, t, diff)| (datum, t.into(), diff))
+                    .collect(),
+            };
+
+            self::eviction::write_part(&directory, &part)
+        })
+    }
+
     /// Returns true iff the probe is behind any input handle. Mostly
     /// used as a convenience method during testing. Using this within
     /// `step_while` is not safe in general and might lead to stalls.
     pub fn is_any_outdated(&self) -> bool {
-        self.probe
-            .with_frontier(|out_frontier| self.internal.dominates(out_frontier))
+        self.probe.with_frontier(|out_frontier| {
+            self.internal.dominates(out_frontier)
+                || self
+                    .tenants
+                    .values()
+                    .any(|domain| domain.dominates(out_frontier))
+        })
     }
 
     /// Helper for registering, publishing, and indicating interest in
@@ -519,6 +2909,7 @@ where
         self.register(Register {
             rules: vec![rule],
             publish: vec![publish_name],
+            datalog: vec![],
         })
         .unwrap();
 
@@ -0,0 +1,115 @@
+//! Ordered, paginated delivery of query results, for consumers like
+//! UIs that only ever show a window onto a relation.
+//!
+//! Pages are maintained incrementally, s.t. clients only receive
+//! diffs for tuples entering or leaving the page, rather than for
+//! every change to the relation as a whole.
+
+use timely::dataflow::Scope;
+
+use differential_dataflow::collection::Collection;
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::Reduce;
+
+use crate::{Error, Value};
+
+/// A window onto the results of a query, in the order given by a
+/// sort key.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Page {
+    /// Offsets of the tuple fields to order results by, in order of
+    /// precedence. Ties are broken by the remaining fields.
+    pub sort_by: Vec<usize>,
+    /// Whether to order results from greatest to least.
+    #[serde(default)]
+    pub descending: bool,
+    /// Number of results to skip before the page starts.
+    #[serde(default)]
+    pub offset: usize,
+    /// Maximum number of results on the page.
+    pub limit: usize,
+}
+
+impl Page {
+    /// Returns the suffix distinguishing the dataflow serving this
+    /// page from others on the same relation. Keys are derived before
+    /// pages are checked, so they must not assume sensible bounds.
+    pub fn key(&self) -> String {
+        format!(
+            "#{:?}{}[{}+{}]",
+            self.sort_by,
+            if self.descending { "desc" } else { "asc" },
+            self.offset,
+            self.limit
+        )
+    }
+
+    /// Ensures that the page can hold any results at all, and that
+    /// its bounds can be counted in multiplicities.
+    pub fn check(&self) -> Result<(), Error> {
+        let max = std::isize::MAX as usize;
+
+        if self.limit == 0 {
+            Err(Error::incorrect("Pages must hold at least one result."))
+        } else if self.offset > max || self.limit > max {
+            Err(Error::unsupported(format!(
+                "Page offsets and limits may not exceed {}.",
+                max
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Restricts the relation to the tuples on this page. All tuples
+    /// are ordered on a single worker, so the page is best requested
+    /// of relations small enough to sort, or of their aggregates.
+    pub fn paginate<S>(
+        &self,
+        relation: Collection<S, Vec<Value>, isize>,
+    ) -> Collection<S, Vec<Value>, isize>
+    where
+        S: Scope,
+        S::Timestamp: Lattice + Ord,
+    {
+        let sort_by = self.sort_by.clone();
+        let descending = self.descending;
+        let offset = self.offset as isize;
+        let limit = self.limit as isize;
+
+        // Fields missing from a tuple sort before all others.
+        relation
+            .map(move |tuple| {
+                let sort: Vec<Option<Value>> =
+                    sort_by.iter().map(|i| tuple.get(*i).cloned()).collect();
+                ((), (sort, tuple))
+            })
+            .reduce(move |_key, vals, output| {
+                let ranked: Box<dyn Iterator<Item = _>> = if descending {
+                    Box::new(vals.iter().rev())
+                } else {
+                    Box::new(vals.iter())
+                };
+
+                let mut skipped = 0;
+                let mut remaining = limit;
+                for ((_sort, tuple), diff) in ranked {
+                    if remaining <= 0 {
+                        break;
+                    }
+
+                    // Tuples occurring multiple times take up as many
+                    // positions on the page.
+                    let skipping = std::cmp::min(*diff, offset - skipped);
+                    skipped += skipping;
+
+                    let taken = std::cmp::min(*diff - skipping, remaining);
+                    if taken > 0 {
+                        output.push((tuple.clone(), taken));
+                        remaining -= taken;
+                    }
+                }
+            })
+            .map(|((), tuple)| tuple)
+    }
+}
@@ -0,0 +1,106 @@
+//! Quotas on what a single client may ask of the server, s.t. one
+//! misbehaving client can't starve everyone else.
+//!
+//! Quotas are checked when requests are sequenced, against the time
+//! they were issued at, so all workers come to the same verdict.
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::server::Request;
+use crate::{AsAid, Error};
+
+/// Quotas applying to each client connection. Unset quotas don't
+/// apply.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quotas {
+    /// Maximum number of transactions a client may issue within the
+    /// same second. Bulk loads and entity retractions count as well.
+    #[serde(default)]
+    pub max_transactions_per_second: Option<usize>,
+    /// Maximum number of rules a client may have registered at any
+    /// time.
+    #[serde(default)]
+    pub max_queries: Option<usize>,
+}
+
+/// What a single client has used up of its quotas.
+#[derive(Clone, Debug, Default)]
+pub struct Usage {
+    /// The second the transactions counted below were issued in.
+    window: u64,
+    /// Number of transactions issued within that second.
+    transactions: usize,
+    /// Names of the rules registered by the client.
+    queries: HashSet<String>,
+}
+
+impl Quotas {
+    /// Charges the request to the client's usage, unless that would
+    /// exceed its quotas. Only rules not registered already count
+    /// against the query quota, as determined by `is_registered`.
+    pub fn charge<A, F>(
+        &self,
+        usage: &mut Usage,
+        req: &Request<A>,
+        issued_at: Duration,
+        is_registered: F,
+    ) -> Result<(), Error>
+    where
+        A: AsAid + From<&'static str>,
+        F: Fn(&str) -> bool,
+    {
+        match req {
+            Request::Transact(_)
+            | Request::TransactWithMeta(_, _)
+            | Request::RetractEntity(_, _)
+            | Request::BulkLoad(_) => {
+                if let Some(max_transactions) = self.max_transactions_per_second {
+                    let window = issued_at.as_secs();
+                    if usage.window != window {
+                        usage.window = window;
+                        usage.transactions = 0;
+                    }
+
+                    if usage.transactions >= max_transactions {
+                        return Err(Error::busy(format!(
+                            "Clients may issue at most {} transactions per second.",
+                            max_transactions
+                        )));
+                    }
+
+                    usage.transactions += 1;
+                }
+
+                Ok(())
+            }
+            Request::Register(req) => {
+                // Rules unregistered in the meantime, or whose
+                // registration failed, no longer count.
+                usage.queries.retain(|name| is_registered(name));
+
+                let names: HashSet<String> = req
+                    .rules
+                    .iter()
+                    .map(|rule| rule.name.to_string())
+                    .chain(req.datalog.iter().map(|rule| rule.name.clone()))
+                    .filter(|name| !usage.queries.contains(name) && !is_registered(name))
+                    .collect();
+
+                if let Some(max_queries) = self.max_queries {
+                    if usage.queries.len() + names.len() > max_queries {
+                        return Err(Error::conflict(format!(
+                            "Clients may register at most {} queries, unregister some first.",
+                            max_queries
+                        )));
+                    }
+                }
+
+                usage.queries.extend(names);
+
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Totally ordered log of commands shared by all workers.
+//!
+//! Commands can be issued on any worker, but arrive on all of them in
+//! the same order. Each command is assigned a transaction id and an
+//! issue time by the log, both of which are identical on all
+//! workers, s.t. they apply creations, registrations, and
+//! transactions at the same domain epochs.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use timely::communication::Allocate;
+use timely::synchronization::Sequencer;
+use timely::worker::Worker;
+use timely::ExchangeData;
+
+use crate::server::TxId;
+
+/// A command along with the time at which it was issued, relative to
+/// the issuing worker's timer.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Stamped<C> {
+    /// Time since the issuing worker started.
+    pub issued_at: Duration,
+    /// The issued command.
+    pub command: C,
+    /// Transaction id to deliver the command at, if it must be
+    /// retained from a previous run, e.g. for a command replacing a
+    /// compacted prefix of the log. Later commands are numbered
+    /// after it.
+    #[serde(default)]
+    pub tx: Option<TxId>,
+}
+
+/// A command as delivered by the log.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sequenced<C> {
+    /// Position of the command within the log, starting at one,
+    /// unless retained from a previous run.
+    pub tx: TxId,
+    /// Time at which the command was issued. Issue times strictly
+    /// increase along the log, even if the clocks of the issuing
+    /// workers disagree.
+    pub issued_at: Duration,
+    /// The sequenced command.
+    pub command: C,
+}
+
+/// A totally ordered broadcast of commands between all workers.
+pub struct CommandLog<C: ExchangeData> {
+    sequencer: Sequencer<Stamped<C>>,
+    timer: Instant,
+    last_tx: TxId,
+    last_issued: Duration,
+}
+
+impl<C: ExchangeData> CommandLog<C> {
+    /// Creates a new log, relative to the specified timer.
+    pub fn new<A: Allocate>(worker: &mut Worker<A>, timer: Instant) -> Self {
+        Self::preloaded(worker, timer, Vec::new())
+    }
+
+    /// Creates a new log, relative to the specified timer. The
+    /// specified commands are delivered locally before anything
+    /// else, and must therefore be preloaded identically on all
+    /// workers.
+    pub fn preloaded<A: Allocate>(worker: &mut Worker<A>, timer: Instant, preload: Vec<C>) -> Self {
+        let preload = preload
+            .into_iter()
+            .map(|command| Stamped {
+                issued_at: Duration::from_secs(0),
+                command,
+                tx: None,
+            })
+            .collect();
+
+        Self::replayed(worker, timer, preload)
+    }
+
+    /// Creates a new log like `preloaded`, retaining the issue times
+    /// of the preloaded commands, e.g. because they are replayed from
+    /// a previous run.
+    pub fn replayed<A: Allocate>(
+        worker: &mut Worker<A>,
+        timer: Instant,
+        preload: Vec<Stamped<C>>,
+    ) -> Self {
+        CommandLog {
+            sequencer: Sequencer::preloaded(worker, timer, VecDeque::from(preload)),
+            timer,
+            last_tx: 0,
+            last_issued: Duration::from_secs(0),
+        }
+    }
+
+    /// Issues a command, to be delivered to all workers.
+    pub fn push(&mut self, command: C) {
+        let issued_at = Instant::now().duration_since(self.timer);
+        self.sequencer.push(Stamped {
+            issued_at,
+            command,
+            tx: None,
+        });
+    }
+
+    /// Returns the next command in the log, if any has arrived.
+    pub fn next(&mut self) -> Option<Sequenced<C>> {
+        self.sequencer.next().map(|stamped| {
+            self.last_tx = match stamped.tx {
+                Some(tx) if tx > self.last_tx => tx,
+                _ => self.last_tx + 1,
+            };
+
+            // Issue times strictly increase along the log, s.t. each
+            // command is applied at a distinct epoch.
+            self.last_issued = if stamped.issued_at > self.last_issued {
+                stamped.issued_at
+            } else {
+                self.last_issued + Duration::from_nanos(1)
+            };
+
+            Sequenced {
+                tx: self.last_tx,
+                issued_at: self.last_issued,
+                command: stamped.command,
+            }
+        })
+    }
+
+    /// Returns the transaction id of the command delivered last.
+    pub fn last_tx(&self) -> TxId {
+        self.last_tx
+    }
+}
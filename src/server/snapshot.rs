@@ -0,0 +1,137 @@
+//! Compacted snapshots of attribute contents, bounding the amount of
+//! history a restarting server has to replay.
+//!
+//! A snapshot is requested through the command log, s.t. all workers
+//! agree on the transaction it was taken at, and thus on the time as
+//! of which they read their indices. Each worker writes the share of
+//! attribute contents it holds into a part of its own, in the
+//! background. Once all parts are in place, the write-ahead log is
+//! rewritten to start with a single command restoring the snapshot,
+//! followed by everything logged afterwards.
+//!
+//! Only the consolidated contents of attributes fed by input handles
+//! are captured. History before the snapshot is collapsed into a
+//! single transaction, windowed attributes restart their windows
+//! when restored, and updates transacted at explicit times after the
+//! snapshot was taken are not included.
+
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::server::storage::StorageBackend;
+use crate::server::TxId;
+use crate::{Error, Value};
+
+/// The share of a snapshot written by a single worker.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotPart<A> {
+    /// Transaction id of the `Snapshot` request.
+    pub through: TxId,
+    /// Index of the worker that wrote this part.
+    pub worker: usize,
+    /// Consolidated (e, v, count) contents per attribute.
+    pub attributes: Vec<(A, Vec<(Value, Value, isize)>)>,
+}
+
+/// Returns true iff all of the specified number of workers have
+/// written their parts of a snapshot.
+pub fn is_complete(backend: &dyn StorageBackend, through: TxId, peers: usize) -> bool {
+    match backend.snapshot_parts(through) {
+        Err(error) => {
+            warn!("Failed to list snapshot parts: {}", error.message);
+            false
+        }
+        Ok(workers) => (0..peers).all(|worker| workers.contains(&worker)),
+    }
+}
+
+/// Reads all parts of a snapshot and merges them, s.t. each
+/// attribute appears exactly once. Parts can be read by any number
+/// of workers, regardless of how many wrote them.
+pub fn read<A>(
+    backend: &dyn StorageBackend,
+    through: TxId,
+) -> Result<Vec<(A, Vec<(Value, Value, isize)>)>, Error>
+where
+    A: DeserializeOwned + Ord,
+{
+    let parts = backend.read_snapshot(through)?;
+
+    if parts.is_empty() {
+        return Err(Error::not_found(format!(
+            "Snapshot through {} does not exist.",
+            through
+        )));
+    }
+
+    let mut merged: Vec<(A, Vec<(Value, Value, isize)>)> = Vec::new();
+
+    for (worker, contents) in parts.into_iter() {
+        let part: SnapshotPart<A> = serde_json::from_slice(&contents).map_err(|error| {
+            Error::incorrect(format!(
+                "Corrupt part {} of snapshot through {}: {}",
+                worker, through, error
+            ))
+        })?;
+
+        for (name, mut tuples) in part.attributes.into_iter() {
+            match merged.binary_search_by(|(other, _)| other.cmp(&name)) {
+                Ok(index) => merged[index].1.append(&mut tuples),
+                Err(index) => merged.insert(index, (name, tuples)),
+            }
+        }
+    }
+
+    Ok(merged)
+}
+
+/// Writes snapshot parts from a thread of its own, s.t. serializing
+/// and storing large snapshots doesn't block the worker.
+pub struct SnapshotWriter<A> {
+    send: Sender<SnapshotPart<A>>,
+    thread: JoinHandle<()>,
+}
+
+impl<A: Serialize + Send + 'static> SnapshotWriter<A> {
+    /// Spawns a new writer thread, storing parts in the specified
+    /// backend.
+    pub fn spawn(mut backend: Box<dyn StorageBackend>) -> Self {
+        let (send, recv) = mpsc::channel::<SnapshotPart<A>>();
+
+        let thread = thread::spawn(move || {
+            for part in recv.iter() {
+                let result = serde_json::to_vec(&part)
+                    .map_err(Error::fault)
+                    .and_then(|contents| {
+                        backend.write_snapshot(part.through, part.worker, &contents)
+                    });
+
+                if let Err(error) = result {
+                    error!(
+                        "Failed to write part {} of snapshot through {}: {}",
+                        part.worker, part.through, error.message
+                    );
+                }
+            }
+        });
+
+        SnapshotWriter { send, thread }
+    }
+
+    /// Queues a part for writing.
+    pub fn write(&self, part: SnapshotPart<A>) {
+        self.send.send(part).expect("snapshot writer disappeared");
+    }
+
+    /// Waits for all queued parts to be written.
+    pub fn finish(self) {
+        drop(self.send);
+
+        if self.thread.join().is_err() {
+            error!("Snapshot writer panicked.");
+        }
+    }
+}
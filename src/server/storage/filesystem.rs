@@ -0,0 +1,227 @@
+//! Durable state kept in a local directory.
+//!
+//! The log is stored as a single file holding one entry per line. A
+//! crash during an append can only ever leave a partial last line
+//! behind, which is ignored when reading and discarded before
+//! appending again. Each snapshot is a directory of its
+//! own, holding one file per worker.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use crate::server::storage::StorageBackend;
+use crate::server::TxId;
+use crate::Error;
+
+/// Name of the log file within the storage directory.
+const LOG_FILE: &str = "commands.wal";
+
+/// Name of the directory holding all snapshots, within the storage
+/// directory.
+const SNAPSHOTS: &str = "snapshots";
+
+/// A directory on the local filesystem.
+pub struct FileSystem {
+    directory: PathBuf,
+    log_path: PathBuf,
+    log: File,
+    /// Whether a partial entry left behind by a crash has been
+    /// discarded already. This happens before the first append, s.t.
+    /// backends that only ever read the log never modify it.
+    truncated: bool,
+}
+
+impl FileSystem {
+    /// Opens the specified directory for storage, creating it if
+    /// necessary.
+    pub fn open<P: AsRef<Path>>(directory: P) -> Result<Self, Error> {
+        fs::create_dir_all(&directory).map_err(Error::fault)?;
+
+        let directory = directory.as_ref().to_path_buf();
+        let log_path = directory.join(LOG_FILE);
+
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)
+            .map_err(Error::fault)?;
+
+        Ok(FileSystem {
+            directory,
+            log_path,
+            log,
+            truncated: false,
+        })
+    }
+
+    /// Returns the path of the log file.
+    pub fn log_path(&self) -> &Path {
+        &self.log_path
+    }
+
+    fn snapshot_directory(&self, through: TxId) -> PathBuf {
+        self.directory.join(SNAPSHOTS).join(through.to_string())
+    }
+
+    /// Lists the completely written parts of a snapshot, ordered by
+    /// worker.
+    fn part_paths(&self, through: TxId) -> Result<Vec<(usize, PathBuf)>, Error> {
+        let entries = match fs::read_dir(self.snapshot_directory(through)) {
+            Ok(entries) => entries,
+            Err(ref error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(Error::fault(error)),
+        };
+
+        let mut parts = Vec::new();
+
+        for entry in entries {
+            let path = entry.map_err(Error::fault)?.path();
+
+            // Parts that are still being written have a different
+            // extension.
+            if path.extension().map(|x| x != "json").unwrap_or(true) {
+                continue;
+            }
+
+            let worker = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.trim_start_matches("worker-").parse::<usize>().ok());
+
+            if let Some(worker) = worker {
+                parts.push((worker, path));
+            }
+        }
+
+        parts.sort_by_key(|(worker, _path)| *worker);
+
+        Ok(parts)
+    }
+}
+
+impl StorageBackend for FileSystem {
+    fn append_tx(&mut self, entry: &[u8]) -> Result<(), Error> {
+        if !self.truncated {
+            let contents = fs::read(&self.log_path).map_err(Error::fault)?;
+            let complete = contents
+                .iter()
+                .rposition(|byte| *byte == b'\n')
+                .map(|position| position + 1)
+                .unwrap_or(0);
+
+            if complete < contents.len() {
+                warn!(
+                    "Discarding partial entry at the end of {}.",
+                    self.log_path.display()
+                );
+                self.log.set_len(complete as u64).map_err(Error::fault)?;
+            }
+
+            self.truncated = true;
+        }
+
+        let mut line = entry.to_vec();
+        line.push(b'\n');
+
+        self.log.write_all(&line).map_err(Error::fault)?;
+        self.log.sync_data().map_err(Error::fault)
+    }
+
+    fn read_range(&self, start: usize, end: usize) -> Result<Vec<Vec<u8>>, Error> {
+        let contents = match fs::read(&self.log_path) {
+            Ok(contents) => contents,
+            Err(ref error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(Error::fault(error)),
+        };
+
+        // Only lines terminated by a newline have been appended
+        // completely.
+        let complete = match contents.iter().rposition(|byte| *byte == b'\n') {
+            None => &contents[..0],
+            Some(position) => &contents[..position],
+        };
+
+        Ok(complete
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .skip(start)
+            .take(end.saturating_sub(start))
+            .map(|line| line.to_vec())
+            .collect())
+    }
+
+    fn rewrite_log(&mut self, entries: &[Vec<u8>]) -> Result<(), Error> {
+        let pending = self.log_path.with_extension("pending");
+
+        {
+            let mut file = File::create(&pending).map_err(Error::fault)?;
+
+            for entry in entries.iter() {
+                file.write_all(entry).map_err(Error::fault)?;
+                file.write_all(b"\n").map_err(Error::fault)?;
+            }
+
+            file.sync_all().map_err(Error::fault)?;
+        }
+
+        fs::rename(&pending, &self.log_path).map_err(Error::fault)?;
+
+        self.log = OpenOptions::new()
+            .append(true)
+            .open(&self.log_path)
+            .map_err(Error::fault)?;
+        self.truncated = true;
+
+        Ok(())
+    }
+
+    fn write_snapshot(&mut self, through: TxId, worker: usize, part: &[u8]) -> Result<(), Error> {
+        let directory = self.snapshot_directory(through);
+        fs::create_dir_all(&directory).map_err(Error::fault)?;
+
+        let path = directory.join(format!("worker-{}.json", worker));
+        let pending = path.with_extension("pending");
+
+        let mut file = File::create(&pending).map_err(Error::fault)?;
+        file.write_all(part).map_err(Error::fault)?;
+        file.sync_all().map_err(Error::fault)?;
+
+        fs::rename(&pending, &path).map_err(Error::fault)
+    }
+
+    fn snapshot_parts(&self, through: TxId) -> Result<Vec<usize>, Error> {
+        Ok(self
+            .part_paths(through)?
+            .into_iter()
+            .map(|(worker, _path)| worker)
+            .collect())
+    }
+
+    fn read_snapshot(&self, through: TxId) -> Result<Vec<(usize, Vec<u8>)>, Error> {
+        self.part_paths(through)?
+            .into_iter()
+            .map(|(worker, path)| Ok((worker, fs::read(&path).map_err(Error::fault)?)))
+            .collect()
+    }
+
+    fn retain_snapshot(&mut self, through: TxId) -> Result<(), Error> {
+        let current = self.snapshot_directory(through);
+
+        let entries = match fs::read_dir(self.directory.join(SNAPSHOTS)) {
+            Ok(entries) => entries,
+            Err(ref error) if error.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(Error::fault(error)),
+        };
+
+        for entry in entries {
+            let path = entry.map_err(Error::fault)?.path();
+
+            if path != current {
+                fs::remove_dir_all(&path).map_err(Error::fault)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+//! Backends holding a server's durable state, i.e. its write-ahead
+//! log of commands and the snapshots it is compacted to.
+//!
+//! Backends only ever store opaque bytes. Encoding entries and
+//! snapshot parts is up to the write-ahead log and the snapshot
+//! writer, s.t. either can be used with any backend.
+
+use crate::server::TxId;
+use crate::Error;
+
+mod filesystem;
+pub use self::filesystem::FileSystem;
+
+#[cfg(feature = "s3-storage")]
+mod s3;
+#[cfg(feature = "s3-storage")]
+pub use self::s3::S3Storage;
+
+/// Somewhere durable state can be kept.
+pub trait StorageBackend: Send {
+    /// Appends an entry to the log, returning only once it has been
+    /// stored durably. Entries must not contain newlines.
+    fn append_tx(&mut self, entry: &[u8]) -> Result<(), Error>;
+
+    /// Reads all complete log entries at positions `start` up to,
+    /// but not including, `end`, in the order they were appended.
+    fn read_range(&self, start: usize, end: usize) -> Result<Vec<Vec<u8>>, Error>;
+
+    /// Replaces the contents of the log by the specified entries.
+    /// Backends must make sure that a crash leaves either the old or
+    /// the new contents behind.
+    fn rewrite_log(&mut self, entries: &[Vec<u8>]) -> Result<(), Error>;
+
+    /// Stores a worker's part of the snapshot taken through the
+    /// specified transaction. Parts must only become visible to
+    /// `read_snapshot` once they have been stored completely.
+    fn write_snapshot(&mut self, through: TxId, worker: usize, part: &[u8]) -> Result<(), Error>;
+
+    /// Lists the workers whose parts of the snapshot taken through
+    /// the specified transaction have been stored.
+    fn snapshot_parts(&self, through: TxId) -> Result<Vec<usize>, Error>;
+
+    /// Reads all parts of the snapshot taken through the specified
+    /// transaction, as pairs of worker index and contents.
+    fn read_snapshot(&self, through: TxId) -> Result<Vec<(usize, Vec<u8>)>, Error>;
+
+    /// Removes all snapshots other than the one taken through the
+    /// specified transaction.
+    fn retain_snapshot(&mut self, through: TxId) -> Result<(), Error>;
+}
+
+/// Where a server keeps its durable state.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum Storage {
+    /// A directory on the local filesystem.
+    FileSystem(String),
+    /// A prefix within an S3 bucket.
+    #[cfg(feature = "s3-storage")]
+    S3 {
+        /// Region name, e.g. "eu-central-1". Taken from the
+        /// environment if not specified.
+        region: Option<String>,
+        /// Bucket name.
+        bucket: String,
+        /// Prefix under which all objects are stored.
+        prefix: String,
+    },
+}
+
+impl Storage {
+    /// Parses a storage location, which is either a directory or,
+    /// if S3 support is enabled, of the form `s3://BUCKET/PREFIX`.
+    pub fn parse(location: &str) -> Result<Self, Error> {
+        if location.starts_with("s3://") {
+            Self::parse_s3(&location["s3://".len()..])
+        } else {
+            Ok(Storage::FileSystem(location.to_string()))
+        }
+    }
+
+    #[cfg(feature = "s3-storage")]
+    fn parse_s3(location: &str) -> Result<Self, Error> {
+        let mut parts = location.splitn(2, '/');
+
+        match parts.next() {
+            None | Some("") => Err(Error::incorrect(format!(
+                "Storage location s3://{} names no bucket.",
+                location
+            ))),
+            Some(bucket) => Ok(Storage::S3 {
+                region: None,
+                bucket: bucket.to_string(),
+                prefix: parts.next().unwrap_or("").to_string(),
+            }),
+        }
+    }
+
+    #[cfg(not(feature = "s3-storage"))]
+    fn parse_s3(_location: &str) -> Result<Self, Error> {
+        Err(Error::unsupported(
+            "S3 storage requires the s3-storage feature.",
+        ))
+    }
+
+    /// Opens a backend for this location.
+    pub fn open(&self) -> Result<Box<dyn StorageBackend>, Error> {
+        match *self {
+            Storage::FileSystem(ref directory) => Ok(Box::new(FileSystem::open(directory)?)),
+            #[cfg(feature = "s3-storage")]
+            Storage::S3 {
+                ref region,
+                ref bucket,
+                ref prefix,
+            } => Ok(Box::new(S3Storage::open(
+                region.as_ref().map(|x| x.as_str()),
+                bucket,
+                prefix,
+            )?)),
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! Durable state kept under a prefix within an S3 bucket.
+//!
+//! S3 has no notion of appending to an object, so each log entry is
+//! stored as an object of its own, keyed by its zero-padded position
+//! within the log. Rewriting the log writes a new generation of
+//! entries, before switching the `HEAD` object over to it. Snapshot
+//! parts are stored as one object per worker, which S3 only makes
+//! visible once they have been uploaded completely.
+
+use std::io::Read;
+use std::str::FromStr;
+
+use rusoto_core::{Region, RusotoError};
+use rusoto_s3::{
+    DeleteObjectRequest, GetObjectError, GetObjectRequest, ListObjectsV2Request, PutObjectRequest,
+    S3Client, S3,
+};
+
+use crate::server::storage::StorageBackend;
+use crate::server::TxId;
+use crate::Error;
+
+/// A prefix within an S3 bucket.
+pub struct S3Storage {
+    client: S3Client,
+    bucket: String,
+    prefix: String,
+    /// Generation of log entries `HEAD` points to.
+    generation: u64,
+    /// Position of the next entry to append.
+    next: usize,
+}
+
+impl S3Storage {
+    /// Opens the specified prefix for storage. The region is taken
+    /// from the environment, if none is specified.
+    pub fn open(region: Option<&str>, bucket: &str, prefix: &str) -> Result<Self, Error> {
+        let region = match region {
+            None => Region::default(),
+            Some(region) => Region::from_str(region).map_err(Error::incorrect)?,
+        };
+
+        let mut storage = S3Storage {
+            client: S3Client::new(region),
+            bucket: bucket.to_string(),
+            prefix: prefix.trim_end_matches('/').to_string(),
+            generation: 0,
+            next: 0,
+        };
+
+        storage.generation = match storage.get(&storage.key("log/HEAD"))? {
+            None => 0,
+            Some(head) => String::from_utf8_lossy(&head)
+                .trim()
+                .parse::<u64>()
+                .map_err(|error| Error::incorrect(format!("Corrupt log head: {}", error)))?,
+        };
+
+        storage.next = storage.list(&storage.log_prefix(storage.generation))?.len();
+
+        Ok(storage)
+    }
+
+    fn key(&self, suffix: &str) -> String {
+        if self.prefix.is_empty() {
+            suffix.to_string()
+        } else {
+            format!("{}/{}", self.prefix, suffix)
+        }
+    }
+
+    fn log_prefix(&self, generation: u64) -> String {
+        self.key(&format!("log/{:010}/", generation))
+    }
+
+    fn entry_key(&self, generation: u64, position: usize) -> String {
+        format!("{}{:020}", self.log_prefix(generation), position)
+    }
+
+    fn snapshot_prefix(&self, through: TxId) -> String {
+        self.key(&format!("snapshots/{}/", through))
+    }
+
+    /// Lists all keys with the specified prefix, in lexicographic
+    /// order.
+    fn list(&self, prefix: &str) -> Result<Vec<String>, Error> {
+        let mut keys = Vec::new();
+        let mut continuation_token = None;
+
+        loop {
+            let request = ListObjectsV2Request {
+                bucket: self.bucket.clone(),
+                prefix: Some(prefix.to_string()),
+                continuation_token: continuation_token.take(),
+                ..Default::default()
+            };
+
+            let response = self
+                .client
+                .list_objects_v2(request)
+                .sync()
+                .map_err(Error::fault)?;
+
+            for object in response.contents.unwrap_or_default() {
+                if let Some(key) = object.key {
+                    keys.push(key);
+                }
+            }
+
+            match response.next_continuation_token {
+                None => break,
+                Some(token) => continuation_token = Some(token),
+            }
+        }
+
+        keys.sort();
+
+        Ok(keys)
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        let request = GetObjectRequest {
+            bucket: self.bucket.clone(),
+            key: key.to_string(),
+            ..Default::default()
+        };
+
+        match self.client.get_object(request).sync() {
+            Err(RusotoError::Service(GetObjectError::NoSuchKey(_))) => Ok(None),
+            Err(error) => Err(Error::fault(error)),
+            Ok(response) => {
+                let mut contents = Vec::new();
+
+                if let Some(body) = response.body {
+                    body.into_blocking_read()
+                        .read_to_end(&mut contents)
+                        .map_err(Error::fault)?;
+                }
+
+                Ok(Some(contents))
+            }
+        }
+    }
+
+    fn put(&self, key: String, contents: Vec<u8>) -> Result<(), Error> {
+        let request = PutObjectRequest {
+            bucket: self.bucket.clone(),
+            key,
+            body: Some(contents.into()),
+            ..Default::default()
+        };
+
+        self.client
+            .put_object(request)
+            .sync()
+            .map(|_| ())
+            .map_err(Error::fault)
+    }
+
+    fn delete(&self, key: String) -> Result<(), Error> {
+        let request = DeleteObjectRequest {
+            bucket: self.bucket.clone(),
+            key,
+            ..Default::default()
+        };
+
+        self.client
+            .delete_object(request)
+            .sync()
+            .map(|_| ())
+            .map_err(Error::fault)
+    }
+}
+
+impl StorageBackend for S3Storage {
+    fn append_tx(&mut self, entry: &[u8]) -> Result<(), Error> {
+        self.put(self.entry_key(self.generation, self.next), entry.to_vec())?;
+        self.next += 1;
+
+        Ok(())
+    }
+
+    fn read_range(&self, start: usize, end: usize) -> Result<Vec<Vec<u8>>, Error> {
+        let keys = self.list(&self.log_prefix(self.generation))?;
+
+        keys.iter()
+            .skip(start)
+            .take(end.saturating_sub(start))
+            .map(|key| {
+                self.get(key)?
+                    .ok_or_else(|| Error::not_found(format!("Log entry {} disappeared.", key)))
+            })
+            .collect()
+    }
+
+    fn rewrite_log(&mut self, entries: &[Vec<u8>]) -> Result<(), Error> {
+        let generation = self.generation + 1;
+
+        // A crash during an earlier rewrite might have left entries
+        // of the new generation behind.
+        for key in self.list(&self.log_prefix(generation))? {
+            self.delete(key)?;
+        }
+
+        for (position, entry) in entries.iter().enumerate() {
+            self.put(self.entry_key(generation, position), entry.clone())?;
+        }
+
+        self.put(self.key("log/HEAD"), generation.to_string().into_bytes())?;
+
+        let previous = self.generation;
+        self.generation = generation;
+        self.next = entries.len();
+
+        for key in self.list(&self.log_prefix(previous))? {
+            if let Err(error) = self.delete(key) {
+                warn!("Failed to remove outdated log entry: {}", error.message);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_snapshot(&mut self, through: TxId, worker: usize, part: &[u8]) -> Result<(), Error> {
+        let key = format!("{}worker-{}.json", self.snapshot_prefix(through), worker);
+        self.put(key, part.to_vec())
+    }
+
+    fn snapshot_parts(&self, through: TxId) -> Result<Vec<usize>, Error> {
+        let prefix = self.snapshot_prefix(through);
+
+        let mut workers: Vec<usize> = self
+            .list(&prefix)?
+            .iter()
+            .filter_map(|key| {
+                key[prefix.len()..]
+                    .trim_start_matches("worker-")
+                    .trim_end_matches(".json")
+                    .parse::<usize>()
+                    .ok()
+            })
+            .collect();
+
+        workers.sort();
+
+        Ok(workers)
+    }
+
+    fn read_snapshot(&self, through: TxId) -> Result<Vec<(usize, Vec<u8>)>, Error> {
+        let prefix = self.snapshot_prefix(through);
+
+        self.snapshot_parts(through)?
+            .into_iter()
+            .map(|worker| {
+                let key = format!("{}worker-{}.json", prefix, worker);
+                let part = self.get(&key)?.ok_or_else(|| {
+                    Error::not_found(format!("Snapshot part {} disappeared.", key))
+                })?;
+
+                Ok((worker, part))
+            })
+            .collect()
+    }
+
+    fn retain_snapshot(&mut self, through: TxId) -> Result<(), Error> {
+        let current = self.snapshot_prefix(through);
+
+        for key in self.list(&self.key("snapshots/"))? {
+            if !key.starts_with(&current) {
+                self.delete(key)?;
+            }
+        }
+
+        Ok(())
+    }
+}
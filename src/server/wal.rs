@@ -0,0 +1,84 @@
+//! Write-ahead log of sequenced commands, allowing a server to
+//! restart without losing state.
+//!
+//! Entries are stored as one JSON document each and stored durably
+//! before they are handled. Replaying a log therefore yields every
+//! entry that might have been acknowledged. Where entries end up is
+//! up to the storage backend the log is opened with.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::server::storage::{FileSystem, StorageBackend};
+use crate::Error;
+
+/// An append-only log of entries.
+pub struct WriteAheadLog {
+    backend: Box<dyn StorageBackend>,
+}
+
+impl WriteAheadLog {
+    /// Opens the log in the specified directory, creating both if
+    /// necessary. A partial entry left behind by a crash is
+    /// truncated before anything else is appended.
+    pub fn open<P: AsRef<std::path::Path>>(directory: P) -> Result<Self, Error> {
+        Ok(Self::with_backend(Box::new(FileSystem::open(directory)?)))
+    }
+
+    /// Creates a log storing its entries in the specified backend.
+    pub fn with_backend(backend: Box<dyn StorageBackend>) -> Self {
+        WriteAheadLog { backend }
+    }
+
+    /// Returns the backend holding the log.
+    pub fn backend(&self) -> &dyn StorageBackend {
+        &*self.backend
+    }
+
+    /// Returns the backend holding the log, for modification.
+    pub fn backend_mut(&mut self) -> &mut dyn StorageBackend {
+        &mut *self.backend
+    }
+
+    /// Appends an entry to the log, returning only once it has been
+    /// stored durably.
+    pub fn append<E: Serialize>(&mut self, entry: &E) -> Result<(), Error> {
+        let entry = serde_json::to_vec(entry).map_err(Error::fault)?;
+        self.backend.append_tx(&entry)
+    }
+
+    /// Replaces the contents of the log by the specified entries,
+    /// e.g. to truncate its prefix after a snapshot.
+    pub fn rewrite<E: Serialize>(&mut self, entries: &[E]) -> Result<(), Error> {
+        let entries = entries
+            .iter()
+            .map(|entry| serde_json::to_vec(entry).map_err(Error::fault))
+            .collect::<Result<Vec<Vec<u8>>, Error>>()?;
+
+        self.backend.rewrite_log(&entries)
+    }
+
+    /// Reads all complete entries from the log, in the order they
+    /// were appended.
+    pub fn entries<E: DeserializeOwned>(&self) -> Result<Vec<E>, Error> {
+        self.backend
+            .read_range(0, usize::max_value())?
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                serde_json::from_slice(entry).map_err(|error| {
+                    Error::incorrect(format!("Corrupt log entry {}: {}", index, error))
+                })
+            })
+            .collect()
+    }
+
+    /// Reads all complete entries from the log in the specified
+    /// directory, in the order they were appended. A missing log is
+    /// treated as an empty one.
+    pub fn replay<E: DeserializeOwned, P: AsRef<std::path::Path>>(
+        directory: P,
+    ) -> Result<Vec<E>, Error> {
+        Self::open(directory)?.entries()
+    }
+}
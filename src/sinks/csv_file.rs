@@ -1,86 +1,95 @@
 //! Operator and utilities to write output diffs into csv files.
 
 use timely::dataflow::channels::pact::ParallelizationContract;
-use timely::dataflow::operators::generic::builder_rc::OperatorBuilder;
-use timely::dataflow::operators::generic::FrontieredInputHandle;
-use timely::dataflow::{Scope, Stream};
+use timely::dataflow::operators::generic::{Operator, OutputHandle};
+use timely::dataflow::operators::probe::Probe;
+use timely::dataflow::{ProbeHandle, Scope, Stream};
+use timely::progress::Timestamp;
 
-use super::Sinkable;
-use crate::{Error, ResultDiff};
+use differential_dataflow::lattice::Lattice;
 
-/// A local filesystem data sink.
+use crate::{Error, Output, ResultDiff, Time};
+
+use super::{drain_complete, render, Sinkable, SinkingContext};
+
+/// A local filesystem data sink. Each completed time is appended as
+/// one record per changed tuple, holding the tuple's values followed
+/// by the time and the diff.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub struct CsvFile {
-    /// Path to a file on each workers local filesystem.
+    /// Path to a file on the local filesystem of the worker owning
+    /// the query.
     pub path: String,
-    /// Does the file include a header?
-    pub has_headers: bool,
+    /// Column names to write as the first record, if any.
+    pub headers: Option<Vec<String>>,
     /// Column delimiter to use.
     pub delimiter: u8,
-    /// Allow flexible length records?
-    pub flexible: bool,
 }
 
-impl Sinkable<u64> for CsvFile {
+impl<T> Sinkable<T> for CsvFile
+where
+    T: Timestamp + Lattice + std::convert::Into<Time>,
+{
     fn sink<S, P>(
         &self,
-        stream: &Stream<S, ResultDiff<u64>>,
+        stream: &Stream<S, ResultDiff<T>>,
         pact: P,
-    ) -> Result<Stream<S, ResultDiff<u64>>, Error>
+        probe: &mut ProbeHandle<T>,
+        _context: SinkingContext,
+    ) -> Result<Option<Stream<S, Output>>, Error>
     where
-        S: Scope<Timestamp = u64>,
-        P: ParallelizationContract<S::Timestamp, ResultDiff<u64>>,
+        S: Scope<Timestamp = T>,
+        P: ParallelizationContract<S::Timestamp, ResultDiff<T>>,
     {
-        let writer_result = csv::WriterBuilder::new()
-            .has_headers(self.has_headers)
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
             .delimiter(self.delimiter)
-            .from_path(&self.path);
-
-        match writer_result {
-            Err(error) => Err(Error::fault(format!("Failed to create writer: {}", error))),
-            Ok(mut writer) => {
-                let mut recvd = Vec::new();
-                let mut vector = Vec::new();
-
-                let name = format!("CsvFile({})", &self.path);
-
-                let mut builder = OperatorBuilder::new(name, stream.scope());
-                let mut input = builder.new_input(stream, pact);
-                let (_output, sunk) = builder.new_output();
-
-                builder.build(|_capabilities| {
-                    move |frontiers| {
-                        let mut input_handle =
-                            FrontieredInputHandle::new(&mut input, &frontiers[0]);
-
-                        input_handle.for_each(|_cap, data| {
-                            data.swap(&mut vector);
-                            // @TODO what to do with diff here?
-                            for (tuple, time, _diff) in vector.drain(..) {
-                                recvd.push((time, tuple));
-                            }
-                        });
-
-                        recvd.sort_by(|x, y| x.0.cmp(&y.0));
-
-                        // determine how many (which) elements to read from `recvd`.
-                        let count = recvd
-                            .iter()
-                            .filter(|&(ref time, _)| !input_handle.frontier().less_equal(time))
-                            .count();
-
-                        for (_, tuple) in recvd.drain(..count) {
-                            writer.serialize(tuple).expect("failed to write record");
-                        }
-
-                        if input_handle.frontier.is_empty() {
-                            println!("Inputs to csv sink have ceased.");
-                        }
-                    }
-                });
+            .flexible(true)
+            .from_path(&self.path)
+            .map_err(|error| Error::fault(format!("Failed to create writer: {}", error)))?;
 
-                Ok(sunk)
-            }
+        if let Some(ref headers) = self.headers {
+            writer
+                .write_record(headers)
+                .map_err(|error| Error::fault(format!("Failed to write headers: {}", error)))?;
         }
+
+        let mut vector = Vec::new();
+        let mut pending = Vec::new();
+
+        let name = format!("CsvFile({})", &self.path);
+
+        stream
+            .unary_frontier(pact, &name, move |_cap, _info| {
+                move |input, _output: &mut OutputHandle<_, ResultDiff<T>, _>| {
+                    input.for_each(|_time, data| {
+                        data.swap(&mut vector);
+                        pending.extend(vector.drain(..));
+                    });
+
+                    let complete = drain_complete(&mut pending, input.frontier());
+
+                    if complete.is_empty() {
+                        return;
+                    }
+
+                    for (tuple, time, diff) in complete {
+                        let time: Time = time.into();
+
+                        let mut record: Vec<String> = tuple.into_iter().map(render).collect();
+                        record.push(render(time.into()));
+                        record.push(diff.to_string());
+
+                        writer
+                            .write_record(&record)
+                            .expect("failed to write record");
+                    }
+
+                    writer.flush().expect("failed to flush records");
+                }
+            })
+            .probe_with(probe);
+
+        Ok(None)
     }
 }
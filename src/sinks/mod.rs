@@ -8,21 +8,42 @@ use timely::dataflow::channels::pact::ParallelizationContract;
 use timely::dataflow::operators::generic::{Operator, OutputHandle};
 use timely::dataflow::operators::probe::Probe;
 use timely::dataflow::{ProbeHandle, Scope, Stream};
+#[cfg(any(
+    feature = "csv",
+    feature = "postgres-sink",
+    feature = "redis-sink",
+    feature = "serde_json"
+))]
+use timely::progress::frontier::MutableAntichain;
 use timely::progress::Timestamp;
 
 use differential_dataflow::lattice::Lattice;
 
+#[cfg(any(feature = "csv", feature = "postgres-sink", feature = "redis-sink"))]
+use crate::Value;
 use crate::{Error, Output, ResultDiff, Time};
 
-// #[cfg(feature = "csv-source")]
-// pub mod csv_file;
-// #[cfg(feature = "csv-source")]
-// pub use self::csv_file::CsvFile;
-
 #[cfg(feature = "serde_json")]
 pub mod assoc_in;
+#[cfg(feature = "csv")]
+pub mod csv_file;
+#[cfg(feature = "postgres-sink")]
+pub mod postgres_upsert;
+#[cfg(feature = "redis-sink")]
+pub mod redis_hash;
+#[cfg(feature = "serde_json")]
+pub mod stdout;
+
 #[cfg(feature = "serde_json")]
 pub use self::assoc_in::AssocIn;
+#[cfg(feature = "csv")]
+pub use self::csv_file::CsvFile;
+#[cfg(feature = "postgres-sink")]
+pub use self::postgres_upsert::PostgresUpsert;
+#[cfg(feature = "redis-sink")]
+pub use self::redis_hash::RedisHash;
+#[cfg(feature = "serde_json")]
+pub use self::stdout::Stdout;
 
 /// A struct encapsulating any state required to create sinks.
 pub struct SinkingContext {
@@ -51,14 +72,78 @@ where
         P: ParallelizationContract<S::Timestamp, ResultDiff<T>>;
 }
 
+/// Removes all diffs at times the frontier has passed from the
+/// pending ones, returning them consolidated and in time order. Diffs
+/// that cancel out are dropped entirely.
+#[cfg(any(
+    feature = "csv",
+    feature = "postgres-sink",
+    feature = "redis-sink",
+    feature = "serde_json"
+))]
+fn drain_complete<T: Timestamp>(
+    pending: &mut Vec<ResultDiff<T>>,
+    frontier: &MutableAntichain<T>,
+) -> Vec<ResultDiff<T>> {
+    let (mut complete, incomplete): (Vec<_>, Vec<_>) = pending
+        .drain(..)
+        .partition(|(_tuple, time, _diff)| !frontier.less_equal(time));
+
+    *pending = incomplete;
+
+    complete.sort_by(|x, y| (&x.1, &x.0).cmp(&(&y.1, &y.0)));
+
+    let mut consolidated: Vec<ResultDiff<T>> = Vec::with_capacity(complete.len());
+    for (tuple, time, diff) in complete.drain(..) {
+        match consolidated.last_mut() {
+            Some(last) if last.0 == tuple && last.1 == time => last.2 += diff,
+            _ => consolidated.push((tuple, time, diff)),
+        }
+    }
+
+    consolidated.retain(|(_tuple, _time, diff)| *diff != 0);
+    consolidated
+}
+
+/// Renders a value as plain text, e.g. for a CSV field or a Redis
+/// hash value.
+#[cfg(any(feature = "csv", feature = "postgres-sink", feature = "redis-sink"))]
+fn render(value: Value) -> String {
+    match value {
+        Value::Aid(v) => v,
+        Value::String(v) => v,
+        Value::Bool(v) => v.to_string(),
+        Value::Number(v) => v.to_string(),
+        Value::Rational32(v) => v.to_string(),
+        Value::Eid(v) => v.to_string(),
+        Value::Instant(v) => v.to_string(),
+        Value::Uuid(v) => v.to_string(),
+        Value::TempId(v) => v,
+        Value::Date(v) => v.to_string(),
+        Value::Bytes(v) => crate::bytes::to_hex(&v),
+        Value::Decimal(v) => v.to_string(),
+        #[cfg(feature = "real")]
+        Value::Real(v) => v.to_string(),
+    }
+}
+
 /// Supported external systems.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub enum Sink {
     /// /dev/null, used for benchmarking
     TheVoid(Option<String>),
-    // /// CSV files
-    // #[cfg(feature = "csv-source")]
-    // CsvFile(CsvFile),
+    /// CSV files
+    #[cfg(feature = "csv")]
+    CsvFile(CsvFile),
+    /// JSON lines on standard output
+    #[cfg(feature = "serde_json")]
+    Stdout(Stdout),
+    /// Redis hashes
+    #[cfg(feature = "redis-sink")]
+    RedisHash(RedisHash),
+    /// PostgreSQL tables
+    #[cfg(feature = "postgres-sink")]
+    PostgresUpsert(PostgresUpsert),
     /// Nested Hash-Maps
     #[cfg(feature = "serde_json")]
     AssocIn(AssocIn),
@@ -126,6 +211,14 @@ where
 
                 Ok(None)
             }
+            #[cfg(feature = "csv")]
+            Sink::CsvFile(ref sink) => sink.sink(stream, pact, probe, context),
+            #[cfg(feature = "serde_json")]
+            Sink::Stdout(ref sink) => sink.sink(stream, pact, probe, context),
+            #[cfg(feature = "redis-sink")]
+            Sink::RedisHash(ref sink) => sink.sink(stream, pact, probe, context),
+            #[cfg(feature = "postgres-sink")]
+            Sink::PostgresUpsert(ref sink) => sink.sink(stream, pact, probe, context),
             #[cfg(feature = "serde_json")]
             Sink::AssocIn(ref sink) => sink.sink(stream, pact, probe, context),
             _ => unimplemented!(),
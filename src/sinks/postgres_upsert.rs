@@ -0,0 +1,232 @@
+//! Operator and utilities to maintain output tuples in PostgreSQL
+//! tables.
+
+use std::collections::{HashMap, HashSet};
+
+use timely::dataflow::channels::pact::ParallelizationContract;
+use timely::dataflow::operators::generic::{Operator, OutputHandle};
+use timely::dataflow::operators::probe::Probe;
+use timely::dataflow::{ProbeHandle, Scope, Stream};
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+
+use postgres::types::ToSql;
+use postgres::{Connection, TlsMode};
+
+use crate::{Error, Output, ResultDiff, Time, Value};
+
+use super::{drain_complete, render, Sinkable, SinkingContext};
+
+/// A sink maintaining query results in a PostgreSQL table, with one
+/// row per result tuple.
+///
+/// Each completed time is applied in a single transaction. Tuples
+/// that are added are written via `INSERT ... ON CONFLICT DO UPDATE`,
+/// tuples that are retracted and not replaced by another tuple with
+/// the same key are removed via `DELETE`. The key columns must
+/// therefore carry a unique constraint.
+///
+/// Numbers, entity ids, and instants are written as BIGINT, booleans
+/// as BOOLEAN, all other values as TEXT.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct PostgresUpsert {
+    /// Connection string, e.g. "postgres://user@localhost/db".
+    pub url: String,
+    /// Name of the target table.
+    pub table: String,
+    /// Column names for all output variables, in order.
+    pub columns: Vec<String>,
+    /// Offsets of the output variables identifying rows.
+    pub key_offsets: Vec<usize>,
+}
+
+/// Converts a value into a statement parameter.
+fn to_param(value: Value) -> Box<dyn ToSql> {
+    match value {
+        Value::Number(v) => Box::new(v),
+        Value::Eid(v) => Box::new(v as i64),
+        Value::Instant(v) => Box::new(v as i64),
+        Value::Bool(v) => Box::new(v),
+        Value::Bytes(v) => Box::new(v),
+        other => Box::new(render(other)),
+    }
+}
+
+impl PostgresUpsert {
+    /// Returns the upsert and delete statements for the configured
+    /// table.
+    fn statements(&self) -> Result<(String, String), Error> {
+        if self.key_offsets.is_empty() {
+            return Err(Error::incorrect("At least one key column is required."));
+        }
+
+        let mut keys = Vec::with_capacity(self.key_offsets.len());
+        for offset in self.key_offsets.iter() {
+            match self.columns.get(*offset) {
+                None => {
+                    return Err(Error::incorrect(format!(
+                        "Key offset {} is out of bounds.",
+                        offset
+                    )));
+                }
+                Some(column) => keys.push(column.clone()),
+            }
+        }
+
+        let placeholders: Vec<String> = (1..=self.columns.len())
+            .map(|idx| format!("${}", idx))
+            .collect();
+
+        let updates: Vec<String> = self
+            .columns
+            .iter()
+            .filter(|column| !keys.contains(*column))
+            .map(|column| format!("{} = EXCLUDED.{}", column, column))
+            .collect();
+
+        let on_conflict = if updates.is_empty() {
+            "DO NOTHING".to_string()
+        } else {
+            format!("DO UPDATE SET {}", updates.join(", "))
+        };
+
+        let upsert = format!(
+            "INSERT INTO {} ({}) VALUES ({}) ON CONFLICT ({}) {}",
+            self.table,
+            self.columns.join(", "),
+            placeholders.join(", "),
+            keys.join(", "),
+            on_conflict
+        );
+
+        let conditions: Vec<String> = keys
+            .iter()
+            .enumerate()
+            .map(|(idx, column)| format!("{} = ${}", column, idx + 1))
+            .collect();
+
+        let delete = format!(
+            "DELETE FROM {} WHERE {}",
+            self.table,
+            conditions.join(" AND ")
+        );
+
+        Ok((upsert, delete))
+    }
+
+    /// Applies the changes of a single completed time.
+    fn apply(
+        &self,
+        connection: &Connection,
+        statements: &(String, String),
+        changes: Vec<(Vec<Value>, isize)>,
+    ) -> Result<(), Error> {
+        let (ref upsert, ref delete) = *statements;
+
+        let mut assertions = HashMap::new();
+        let mut retractions = HashSet::new();
+
+        for (tuple, diff) in changes {
+            if tuple.len() != self.columns.len() {
+                return Err(Error::incorrect(format!(
+                    "Tuple of arity {} does not match table {}.",
+                    tuple.len(),
+                    self.table
+                )));
+            }
+
+            let key: Vec<Value> = self
+                .key_offsets
+                .iter()
+                .map(|offset| tuple[*offset].clone())
+                .collect();
+
+            if diff > 0 {
+                assertions.insert(key, tuple);
+            } else {
+                retractions.insert(key);
+            }
+        }
+
+        let transaction = connection.transaction().map_err(Error::fault)?;
+
+        {
+            let delete = transaction.prepare_cached(delete).map_err(Error::fault)?;
+            for key in retractions.drain() {
+                if !assertions.contains_key(&key) {
+                    let params: Vec<Box<dyn ToSql>> = key.into_iter().map(to_param).collect();
+                    let params: Vec<&dyn ToSql> = params.iter().map(|p| p.as_ref()).collect();
+                    delete.execute(&params).map_err(Error::fault)?;
+                }
+            }
+
+            let upsert = transaction.prepare_cached(upsert).map_err(Error::fault)?;
+            for (_key, tuple) in assertions.drain() {
+                let params: Vec<Box<dyn ToSql>> = tuple.into_iter().map(to_param).collect();
+                let params: Vec<&dyn ToSql> = params.iter().map(|p| p.as_ref()).collect();
+                upsert.execute(&params).map_err(Error::fault)?;
+            }
+        }
+
+        transaction.commit().map_err(Error::fault)
+    }
+}
+
+impl<T> Sinkable<T> for PostgresUpsert
+where
+    T: Timestamp + Lattice + std::convert::Into<Time>,
+{
+    fn sink<S, P>(
+        &self,
+        stream: &Stream<S, ResultDiff<T>>,
+        pact: P,
+        probe: &mut ProbeHandle<T>,
+        _context: SinkingContext,
+    ) -> Result<Option<Stream<S, Output>>, Error>
+    where
+        S: Scope<Timestamp = T>,
+        P: ParallelizationContract<S::Timestamp, ResultDiff<T>>,
+    {
+        let statements = self.statements()?;
+        let connection =
+            Connection::connect(self.url.as_str(), TlsMode::None).map_err(Error::fault)?;
+
+        let mut vector = Vec::new();
+        let mut pending = Vec::new();
+
+        let sink = self.clone();
+        let name = format!("PostgresUpsert({})", &self.table);
+
+        stream
+            .unary_frontier(pact, &name, move |_cap, _info| {
+                move |input, _output: &mut OutputHandle<_, ResultDiff<T>, _>| {
+                    input.for_each(|_time, data| {
+                        data.swap(&mut vector);
+                        pending.extend(vector.drain(..));
+                    });
+
+                    // Changes arrive in time order, s.t. consecutive
+                    // changes at the same time form one batch.
+                    let mut batches: Vec<(T, Vec<(Vec<Value>, isize)>)> = Vec::new();
+                    for (tuple, time, diff) in drain_complete(&mut pending, input.frontier()) {
+                        match batches.last_mut() {
+                            Some((batch_time, batch)) if *batch_time == time => {
+                                batch.push((tuple, diff))
+                            }
+                            _ => batches.push((time, vec![(tuple, diff)])),
+                        }
+                    }
+
+                    for (_time, batch) in batches {
+                        if let Err(error) = sink.apply(&connection, &statements, batch) {
+                            error!("failed to update {}: {}", sink.table, error.message);
+                        }
+                    }
+                }
+            })
+            .probe_with(probe);
+
+        Ok(None)
+    }
+}
@@ -0,0 +1,144 @@
+//! Operator and utilities to maintain output tuples as Redis hashes.
+
+use std::collections::{HashMap, HashSet};
+
+use timely::dataflow::channels::pact::ParallelizationContract;
+use timely::dataflow::operators::generic::{Operator, OutputHandle};
+use timely::dataflow::operators::probe::Probe;
+use timely::dataflow::{ProbeHandle, Scope, Stream};
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+
+use crate::{Error, Output, ResultDiff, Time, Value};
+
+use super::{drain_complete, render, Sinkable, SinkingContext};
+
+/// A sink maintaining query results as a Redis view, with one hash
+/// per result tuple. The hash is stored under the value of the
+/// chosen key column, and holds the remaining columns as fields.
+///
+/// Each completed time is applied atomically. Tuples that are added
+/// are written via HSET, tuples that are retracted and not replaced
+/// by another tuple with the same key are removed via DEL. Results
+/// must therefore be functional in the key column, otherwise tuples
+/// sharing a key will overwrite one another.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct RedisHash {
+    /// Redis connection url, e.g. "redis://127.0.0.1/".
+    pub url: String,
+    /// Prefix prepended to every key, e.g. to namespace the view.
+    pub prefix: String,
+    /// Offset of the column holding keys.
+    pub key_offset: usize,
+    /// Field names for all other columns, in order.
+    pub fields: Vec<String>,
+}
+
+impl RedisHash {
+    /// Translates a single completed time into a batch of commands.
+    fn to_pipeline(&self, changes: Vec<(Vec<Value>, isize)>) -> Result<redis::Pipeline, Error> {
+        let mut assertions = HashMap::new();
+        let mut retractions = HashSet::new();
+
+        for (mut tuple, diff) in changes {
+            if self.key_offset >= tuple.len() || tuple.len() != self.fields.len() + 1 {
+                return Err(Error::incorrect(format!(
+                    "Tuple of arity {} does not match the Redis schema.",
+                    tuple.len()
+                )));
+            }
+
+            let key = format!("{}{}", self.prefix, render(tuple.remove(self.key_offset)));
+
+            if diff > 0 {
+                assertions.insert(key, tuple);
+            } else {
+                retractions.insert(key);
+            }
+        }
+
+        let mut pipeline = redis::pipe();
+        pipeline.atomic();
+
+        for key in retractions.iter() {
+            if !assertions.contains_key(key) {
+                pipeline.cmd("DEL").arg(key).ignore();
+            }
+        }
+
+        for (key, tuple) in assertions.drain() {
+            let mut cmd = redis::cmd("HSET");
+            cmd.arg(key);
+
+            for (field, v) in self.fields.iter().zip(tuple.into_iter()) {
+                cmd.arg(field).arg(render(v));
+            }
+
+            pipeline.add_command(cmd).ignore();
+        }
+
+        Ok(pipeline)
+    }
+}
+
+impl<T> Sinkable<T> for RedisHash
+where
+    T: Timestamp + Lattice + std::convert::Into<Time>,
+{
+    fn sink<S, P>(
+        &self,
+        stream: &Stream<S, ResultDiff<T>>,
+        pact: P,
+        probe: &mut ProbeHandle<T>,
+        _context: SinkingContext,
+    ) -> Result<Option<Stream<S, Output>>, Error>
+    where
+        S: Scope<Timestamp = T>,
+        P: ParallelizationContract<S::Timestamp, ResultDiff<T>>,
+    {
+        let client = redis::Client::open(self.url.as_str()).map_err(Error::fault)?;
+        let mut connection = client.get_connection().map_err(Error::fault)?;
+
+        let mut vector = Vec::new();
+        let mut pending = Vec::new();
+
+        let sink = self.clone();
+
+        stream
+            .unary_frontier(pact, "RedisHash", move |_cap, _info| {
+                move |input, _output: &mut OutputHandle<_, ResultDiff<T>, _>| {
+                    input.for_each(|_time, data| {
+                        data.swap(&mut vector);
+                        pending.extend(vector.drain(..));
+                    });
+
+                    // Changes arrive in time order, s.t. consecutive
+                    // changes at the same time form one batch.
+                    let mut batches: Vec<(T, Vec<(Vec<Value>, isize)>)> = Vec::new();
+                    for (tuple, time, diff) in drain_complete(&mut pending, input.frontier()) {
+                        match batches.last_mut() {
+                            Some((batch_time, batch)) if *batch_time == time => {
+                                batch.push((tuple, diff))
+                            }
+                            _ => batches.push((time, vec![(tuple, diff)])),
+                        }
+                    }
+
+                    for (_time, batch) in batches {
+                        match sink.to_pipeline(batch) {
+                            Err(error) => error!("failed to update Redis: {}", error.message),
+                            Ok(pipeline) => {
+                                if let Err(error) = pipeline.query::<()>(&mut connection) {
+                                    error!("failed to update Redis: {}", error);
+                                }
+                            }
+                        }
+                    }
+                }
+            })
+            .probe_with(probe);
+
+        Ok(None)
+    }
+}
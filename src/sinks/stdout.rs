@@ -0,0 +1,82 @@
+//! Operator and utilities to print output diffs as JSON lines.
+
+use std::io::Write;
+
+use timely::dataflow::channels::pact::ParallelizationContract;
+use timely::dataflow::operators::generic::{Operator, OutputHandle};
+use timely::dataflow::operators::probe::Probe;
+use timely::dataflow::{ProbeHandle, Scope, Stream};
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+
+use serde_json::json;
+
+use crate::{Error, Output, ResultDiff, Time, Value};
+
+use super::{drain_complete, Sinkable, SinkingContext};
+
+/// A sink printing each change to standard output, once its time is
+/// complete, as a JSON object of the form `{"name": .., "tuple":
+/// [..], "time": .., "diff": ..}`, one per line.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Stdout {}
+
+impl<T> Sinkable<T> for Stdout
+where
+    T: Timestamp + Lattice + std::convert::Into<Time>,
+{
+    fn sink<S, P>(
+        &self,
+        stream: &Stream<S, ResultDiff<T>>,
+        pact: P,
+        probe: &mut ProbeHandle<T>,
+        context: SinkingContext,
+    ) -> Result<Option<Stream<S, Output>>, Error>
+    where
+        S: Scope<Timestamp = T>,
+        P: ParallelizationContract<S::Timestamp, ResultDiff<T>>,
+    {
+        let mut vector = Vec::new();
+        let mut pending = Vec::new();
+
+        let name = context.name;
+
+        stream
+            .unary_frontier(pact, "Stdout", move |_cap, _info| {
+                move |input, _output: &mut OutputHandle<_, ResultDiff<T>, _>| {
+                    input.for_each(|_time, data| {
+                        data.swap(&mut vector);
+                        pending.extend(vector.drain(..));
+                    });
+
+                    let complete = drain_complete(&mut pending, input.frontier());
+
+                    if complete.is_empty() {
+                        return;
+                    }
+
+                    let stdout = std::io::stdout();
+                    let mut stdout = stdout.lock();
+
+                    for (tuple, time, diff) in complete {
+                        let time: Time = time.into();
+                        let tuple: Vec<serde_json::Value> =
+                            tuple.into_iter().map(serde_json::Value::from).collect();
+
+                        let line = json!({
+                            "name": name,
+                            "tuple": tuple,
+                            "time": serde_json::Value::from(Value::from(time)),
+                            "diff": diff,
+                        });
+
+                        writeln!(stdout, "{}", line).expect("failed to write to stdout");
+                    }
+                }
+            })
+            .probe_with(probe);
+
+        Ok(None)
+    }
+}
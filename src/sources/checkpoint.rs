@@ -0,0 +1,164 @@
+//! Persistent read positions, allowing sources to resume after a
+//! restart without re-ingesting everything.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use timely::dataflow::ProbeHandle;
+use timely::progress::Timestamp;
+
+use crate::Error;
+
+/// Read positions of a source, e.g. offsets per partition, or a byte
+/// offset into a file.
+pub type Positions = BTreeMap<String, u64>;
+
+/// A source whose read positions can be checkpointed.
+pub trait SourceCheckpoint {
+    /// Returns the name under which the positions of this source
+    /// are stored. It must identify the source across restarts.
+    fn checkpoint_name(&self) -> String;
+}
+
+/// A directory holding one checkpoint file per source.
+pub struct CheckpointStore {
+    directory: PathBuf,
+}
+
+impl CheckpointStore {
+    /// Opens a checkpoint store in the specified directory, creating
+    /// it if necessary.
+    pub fn open<P: Into<PathBuf>>(directory: P) -> Result<Self, Error> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory).map_err(Error::fault)?;
+
+        Ok(CheckpointStore { directory })
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        let file_name: String = name
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+
+        self.directory.join(format!("{}.checkpoint", file_name))
+    }
+
+    /// Loads the last positions stored under the specified name, if
+    /// there are any.
+    pub fn load(&self, name: &str) -> Result<Option<Positions>, Error> {
+        let contents = match fs::read_to_string(self.path(name)) {
+            Ok(contents) => contents,
+            Err(ref error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(Error::fault(error)),
+        };
+
+        let mut positions = Positions::new();
+        for line in contents.lines() {
+            let mut parts = line.rsplitn(2, '\t');
+
+            match (parts.next(), parts.next()) {
+                (Some(position), Some(key)) => {
+                    let position = position.parse::<u64>().map_err(Error::incorrect)?;
+                    positions.insert(key.to_string(), position);
+                }
+                _ => return Err(Error::incorrect(format!("Corrupt checkpoint {}.", name))),
+            }
+        }
+
+        Ok(Some(positions))
+    }
+
+    /// Stores the specified positions under the specified name,
+    /// replacing any previous ones.
+    pub fn store(&self, name: &str, positions: &Positions) -> Result<(), Error> {
+        let mut contents = String::new();
+        for (key, position) in positions.iter() {
+            contents.push_str(&format!("{}\t{}\n", key, position));
+        }
+
+        // Write to a temporary file first, s.t. a crash never leaves
+        // a corrupted checkpoint behind.
+        let path = self.path(name);
+        let tmp_path = path.with_extension("tmp");
+
+        fs::write(&tmp_path, contents).map_err(Error::fault)?;
+        fs::rename(&tmp_path, &path).map_err(Error::fault)
+    }
+}
+
+/// Tracks the read positions of a running source and persists them
+/// once the domain has caught up with them.
+///
+/// Sources record their positions along with a time beyond all
+/// datoms read before reaching them. A position is only persisted
+/// once the domain frontier has reached that time, i.e. once all
+/// datoms up to it have been ingested. Resuming from a persisted
+/// position therefore never skips any datoms, but may re-introduce
+/// some of those that were read after it.
+pub struct Checkpointer<T: Timestamp> {
+    name: String,
+    store: Rc<CheckpointStore>,
+    domain_probe: ProbeHandle<T>,
+    pending: Vec<(T, Positions)>,
+}
+
+impl<T: Timestamp> Checkpointer<T> {
+    /// Creates a checkpointer storing positions under the specified
+    /// name.
+    pub fn new(name: String, store: Rc<CheckpointStore>, domain_probe: ProbeHandle<T>) -> Self {
+        Checkpointer {
+            name,
+            store,
+            domain_probe,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Returns the last persisted positions, or no positions if
+    /// there are none.
+    pub fn restore(&self) -> Positions {
+        match self.store.load(&self.name) {
+            Ok(positions) => positions.unwrap_or_default(),
+            Err(error) => {
+                error!("failed to load checkpoint {}: {}", self.name, error.message);
+                Positions::new()
+            }
+        }
+    }
+
+    /// Records positions reached after reading datoms at times
+    /// strictly before `time`.
+    pub fn record(&mut self, time: T, positions: Positions) {
+        self.pending.push((time, positions));
+    }
+
+    /// Persists the most recent positions the domain has caught up
+    /// with, if any.
+    pub fn persist(&mut self) {
+        let domain_probe = &self.domain_probe;
+        let complete = self
+            .pending
+            .iter()
+            .rposition(|(time, _)| !domain_probe.less_than(time));
+
+        if let Some(idx) = complete {
+            let (_time, positions) = self.pending.drain(..=idx).last().unwrap();
+
+            if let Err(error) = self.store.store(&self.name, &positions) {
+                error!(
+                    "failed to store checkpoint {}: {}",
+                    self.name, error.message
+                );
+            }
+        }
+    }
+}
@@ -98,6 +98,7 @@ impl<A: AsAid, S: Scope<Timestamp = Duration>> Sourceable<A, S> for CsvFile<A> {
             let scheduler = context.scheduler;
             let t0 = context.t0;
             let interval = self.interval.unwrap_or(Duration::from_secs(1));
+            let poll_interval = context.poll_interval.clone();
 
             move |_frontiers| {
                 if iterator.reader().is_done() {
@@ -194,7 +195,10 @@ impl<A: AsAid, S: Scope<Timestamp = Duration>> Sourceable<A, S> for CsvFile<A> {
                                 .unwrap()
                                 .borrow_mut()
                                 .realtime
-                                .schedule_after(interval, Rc::downgrade(&activator))
+                                .schedule_after(
+                                    poll_interval.get().unwrap_or(interval),
+                                    Rc::downgrade(&activator),
+                                )
                         }
                     }
                 }
@@ -0,0 +1,194 @@
+//! Wire formats that can be combined with any transport.
+//!
+//! Sources reading raw records (messages, lines, payloads) delegate
+//! turning them into datoms to a `Decoder`. Decoders only produce
+//! values and diffs, assigning times is left to the transport, which
+//! knows about offsets, commits, or arrival times.
+
+#[cfg(feature = "bincode")]
+use std::collections::HashMap;
+
+use crate::sources::decode_json;
+use crate::{AsAid, Eid, Error, Value};
+
+/// A decoded datom, tagged with the index of the attribute it
+/// belongs to.
+pub type DecodedDatom = (usize, (Value, Value), isize);
+
+/// A wire format for datoms.
+pub trait Decoder<A: AsAid> {
+    /// The attributes datoms are decoded into. Transports create one
+    /// output per attribute, in this order.
+    fn attributes(&self) -> Vec<A>;
+
+    /// Decodes a single record. Each resulting datom is tagged with
+    /// the index of its attribute.
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<DecodedDatom>, Error>;
+}
+
+/// Records holding one JSON object each. Fields are decoded
+/// according to the type of the corresponding value in the schema.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct JsonDecoder<A: AsAid> {
+    /// Name of the field holding the entity id.
+    pub eid_field: String,
+    /// Specifies the fields and their value types, that should be
+    /// introduced as attributes.
+    pub schema: Vec<(A, (String, Value))>,
+}
+
+impl<A: AsAid> Decoder<A> for JsonDecoder<A> {
+    fn attributes(&self) -> Vec<A> {
+        self.schema.iter().map(|(aid, _)| aid.clone()).collect()
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<DecodedDatom>, Error> {
+        let obj: serde_json::Value = serde_json::from_slice(bytes).map_err(Error::incorrect)?;
+
+        let eid = obj
+            .get(&self.eid_field)
+            .and_then(|e| e.as_u64())
+            .ok_or_else(|| Error::incorrect(format!("Missing eid field {}.", self.eid_field)))?;
+
+        let mut datoms = Vec::with_capacity(self.schema.len());
+        for (idx, (_aid, (field, type_hint))) in self.schema.iter().enumerate() {
+            if let Some(v) = obj.get(field).and_then(|v| decode_json(v, type_hint)) {
+                datoms.push((idx, (Value::Eid(eid), v), 1));
+            }
+        }
+
+        Ok(datoms)
+    }
+}
+
+/// Records holding one line of delimiter-separated values each.
+/// Columns are parsed according to the type of the corresponding
+/// value in the schema.
+#[cfg(feature = "csv")]
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct CsvDecoder<A: AsAid> {
+    /// Column delimiter to use.
+    pub delimiter: u8,
+    /// Special column offset for the entity id.
+    pub eid_offset: usize,
+    /// Specifies the column offsets and their value types, that
+    /// should be introduced.
+    pub schema: Vec<(A, (usize, Value))>,
+}
+
+#[cfg(feature = "csv")]
+impl<A: AsAid> Decoder<A> for CsvDecoder<A> {
+    fn attributes(&self) -> Vec<A> {
+        self.schema.iter().map(|(aid, _)| aid.clone()).collect()
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<DecodedDatom>, Error> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .delimiter(self.delimiter)
+            .from_reader(bytes);
+
+        let mut record = csv::StringRecord::new();
+        if !reader.read_record(&mut record).map_err(Error::incorrect)? {
+            return Ok(Vec::new());
+        }
+
+        let eid = record
+            .get(self.eid_offset)
+            .and_then(|e| e.parse::<Eid>().ok())
+            .ok_or_else(|| Error::incorrect(format!("Missing eid column {}.", self.eid_offset)))?;
+
+        let mut datoms = Vec::with_capacity(self.schema.len());
+        for (idx, (_aid, (offset, type_hint))) in self.schema.iter().enumerate() {
+            let field = match record.get(*offset) {
+                None => continue,
+                Some(field) => field,
+            };
+
+            let v = match type_hint {
+                Value::String(_) => Value::String(field.to_string()),
+                Value::Number(_) => Value::Number(field.parse::<i64>().map_err(Error::incorrect)?),
+                Value::Eid(_) => Value::Eid(field.parse::<Eid>().map_err(Error::incorrect)?),
+                Value::Bool(_) => Value::Bool(field.parse::<bool>().map_err(Error::incorrect)?),
+                _ => {
+                    return Err(Error::unsupported(
+                        "Only String, Number, Eid, and Bool are supported at the moment.",
+                    ));
+                }
+            };
+
+            datoms.push((idx, (Value::Eid(eid), v), 1));
+        }
+
+        Ok(datoms)
+    }
+}
+
+/// Records holding a bincode-encoded `Vec<(Value, String, Value,
+/// isize)>` of (e, a, v, diff) tuples each. Tuples on attributes
+/// other than the configured ones are ignored.
+#[cfg(feature = "bincode")]
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct BinaryDecoder<A: AsAid> {
+    /// Attributes to decode.
+    pub attributes: Vec<A>,
+}
+
+#[cfg(feature = "bincode")]
+impl<A: AsAid> Decoder<A> for BinaryDecoder<A> {
+    fn attributes(&self) -> Vec<A> {
+        self.attributes.clone()
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<DecodedDatom>, Error> {
+        let tuples: Vec<(Value, String, Value, isize)> =
+            bincode::deserialize(bytes).map_err(Error::incorrect)?;
+
+        let outputs: HashMap<String, usize> = self
+            .attributes
+            .iter()
+            .enumerate()
+            .map(|(idx, aid)| (aid.to_string(), idx))
+            .collect();
+
+        Ok(tuples
+            .into_iter()
+            .filter_map(|(e, a, v, diff)| outputs.get(&a).map(|idx| (*idx, (e, v), diff)))
+            .collect())
+    }
+}
+
+/// Supported wire formats.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum Decoding<A: AsAid> {
+    /// JSON objects
+    Json(JsonDecoder<A>),
+    /// Delimiter-separated values
+    #[cfg(feature = "csv")]
+    Csv(CsvDecoder<A>),
+    /// Bincode-encoded tuples
+    #[cfg(feature = "bincode")]
+    Binary(BinaryDecoder<A>),
+}
+
+impl<A: AsAid> Decoder<A> for Decoding<A> {
+    fn attributes(&self) -> Vec<A> {
+        match *self {
+            Decoding::Json(ref decoder) => decoder.attributes(),
+            #[cfg(feature = "csv")]
+            Decoding::Csv(ref decoder) => decoder.attributes(),
+            #[cfg(feature = "bincode")]
+            Decoding::Binary(ref decoder) => decoder.attributes(),
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<DecodedDatom>, Error> {
+        match *self {
+            Decoding::Json(ref decoder) => decoder.decode(bytes),
+            #[cfg(feature = "csv")]
+            Decoding::Csv(ref decoder) => decoder.decode(bytes),
+            #[cfg(feature = "bincode")]
+            Decoding::Binary(ref decoder) => decoder.decode(bytes),
+        }
+    }
+}
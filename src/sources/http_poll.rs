@@ -0,0 +1,189 @@
+//! Operator and utilities to source data by periodically polling
+//! HTTP endpoints.
+
+use std::collections::HashSet;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use timely::dataflow::operators::generic::builder_rc::OperatorBuilder;
+use timely::dataflow::{Scope, Stream};
+
+use crate::sources::{decode_json, Sourceable, SourcingContext};
+use crate::{AsAid, Value};
+use crate::{AttributeConfig, InputSemantics};
+
+/// An HTTP endpoint responding with a JSON array of objects, which
+/// is fetched once per interval. Only the first worker polls.
+///
+/// Each response is treated as the complete current state. It is
+/// compared against the previous response, and only the differences
+/// are introduced: values that disappeared are retracted, new ones
+/// are asserted. Rows are identified by their key field, s.t. a
+/// changed field of an existing row yields a retraction and an
+/// assertion for that attribute only.
+///
+/// Requests are blocking, so the timeout should be kept well below
+/// the interval.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct HttpPoll<A: AsAid> {
+    /// URL to GET.
+    pub url: String,
+    /// Name of the field identifying rows, which also serves as
+    /// their entity id.
+    pub key_field: String,
+    /// Specifies the fields and their value types, that should be
+    /// introduced as attributes.
+    pub schema: Vec<(A, (String, Value))>,
+    /// Request timeout.
+    pub timeout: Option<Duration>,
+    /// Polling interval.
+    pub interval: Option<Duration>,
+}
+
+/// Fetches and decodes the current result set.
+fn poll<A: AsAid>(
+    client: &reqwest::Client,
+    url: &str,
+    key_field: &str,
+    schema: &[(A, (String, Value))],
+) -> Result<HashSet<(usize, (Value, Value))>, String> {
+    let rows: serde_json::Value = client
+        .get(url)
+        .send()
+        .and_then(|response| response.error_for_status()?.json())
+        .map_err(|error| error.to_string())?;
+
+    let rows = rows
+        .as_array()
+        .ok_or_else(|| "response is not an array".to_string())?;
+
+    let mut datoms = HashSet::new();
+    for row in rows.iter() {
+        if let Some(eid) = row.get(key_field).and_then(|e| e.as_u64()) {
+            for (idx, (_aid, (field, type_hint))) in schema.iter().enumerate() {
+                if let Some(v) = row.get(field).and_then(|v| decode_json(v, type_hint)) {
+                    datoms.insert((idx, (Value::Eid(eid), v)));
+                }
+            }
+        }
+    }
+
+    Ok(datoms)
+}
+
+impl<A: AsAid, S: Scope<Timestamp = Duration>> Sourceable<A, S> for HttpPoll<A> {
+    fn source(
+        &self,
+        scope: &mut S,
+        context: SourcingContext<S::Timestamp>,
+    ) -> Vec<(
+        A,
+        AttributeConfig,
+        Stream<S, ((Value, Value), Duration, isize)>,
+    )> {
+        let url = self.url.clone();
+
+        let mut demux = OperatorBuilder::new(format!("HttpPoll({})", url), scope.clone());
+        let operator_info = demux.operator_info();
+        demux.set_notify(false);
+
+        // Order is very important here, because otherwise the
+        // capabilities won't match up with the output streams later
+        // on (when creating sessions). We stick to the order dictated
+        // by the schema.
+        let mut wrappers = Vec::with_capacity(self.schema.len());
+        let mut streams = Vec::with_capacity(self.schema.len());
+
+        for _ in self.schema.iter() {
+            let (wrapper, stream) = demux.new_output();
+            wrappers.push(wrapper);
+            streams.push(stream);
+        }
+
+        let key_field = self.key_field.clone();
+        let schema = self.schema.clone();
+        let timeout = self.timeout.unwrap_or(Duration::from_secs(5));
+
+        // Grab scheduler handle for deferred re-activation.
+        let scheduler = context.scheduler;
+        let t0 = context.t0;
+        let interval = self.interval.unwrap_or(Duration::from_secs(10));
+        let poll_interval = context.poll_interval.clone();
+
+        demux.build(move |mut capabilities| {
+            let activator = Rc::new(scope.activator_for(&operator_info.address[..]));
+
+            let worker_index = scope.index();
+
+            if worker_index != 0 {
+                capabilities.drain(..);
+            }
+
+            let client = reqwest::Client::builder()
+                .timeout(timeout)
+                .build()
+                .expect("failed to create client");
+
+            let mut previous = HashSet::new();
+
+            move |_frontiers| {
+                if capabilities.is_empty() {
+                    return;
+                }
+
+                match poll(&client, &url, &key_field, &schema) {
+                    Err(error) => warn!("[W{}] failed to poll {}: {}", worker_index, url, error),
+                    Ok(current) => {
+                        let mut handles = Vec::with_capacity(schema.len());
+                        for wrapper in wrappers.iter_mut() {
+                            handles.push(wrapper.activate());
+                        }
+
+                        let mut sessions = Vec::with_capacity(schema.len());
+                        for (idx, handle) in handles.iter_mut().enumerate() {
+                            sessions.push(handle.session(&capabilities[idx]));
+                        }
+
+                        let time = Instant::now().duration_since(t0);
+
+                        for (idx, datom) in previous.difference(&current) {
+                            sessions[*idx].give((datom.clone(), time, -1));
+                        }
+
+                        for (idx, datom) in current.difference(&previous) {
+                            sessions[*idx].give((datom.clone(), time, 1));
+                        }
+
+                        previous = current;
+                    }
+                }
+
+                // Incorporate processing time in downgrade
+                let time = Instant::now().duration_since(t0);
+
+                for cap in capabilities.iter_mut() {
+                    cap.downgrade(&time);
+                }
+
+                // Notify the server that we want to be scheduled again soon
+                scheduler
+                    .upgrade()
+                    .unwrap()
+                    .borrow_mut()
+                    .realtime
+                    .schedule_after(
+                        poll_interval.get().unwrap_or(interval),
+                        Rc::downgrade(&activator),
+                    );
+            }
+        });
+
+        let mut out = Vec::with_capacity(streams.len());
+        for (idx, stream) in streams.drain(..).enumerate() {
+            let aid = self.schema[idx].0.clone();
+            out.push((aid, AttributeConfig::real_time(InputSemantics::Raw), stream));
+        }
+
+        out
+    }
+}
@@ -1,40 +1,74 @@
 //! Operator and utilities to source data from plain files containing
-//! arbitrary json structures.
+//! newline-delimited json objects.
 
-use std::cell::RefCell;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::path::Path;
-use std::rc::Weak;
+use std::rc::Rc;
 use std::time::{Duration, Instant};
 
 use timely::dataflow::operators::generic::builder_rc::OperatorBuilder;
 use timely::dataflow::{Scope, Stream};
 
-// use sources::json_file::flate2::read::GzDecoder;
-
-use crate::scheduling::Scheduler;
-use crate::sources::Sourceable;
-use crate::{AttributeConfig, InputSemantics};
+use crate::sources::{Sourceable, SourcingContext};
 use crate::{AsAid, Eid, Value};
-use Value::{Bool, Number};
+use crate::{AttributeConfig, InputSemantics};
 
-/// A local filesystem data source containing JSON objects.
+/// A local filesystem data source containing one JSON object per
+/// line. Lines are distributed round-robin across workers.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
-pub struct JsonFile<A: AsAid> {
+pub struct JsonFile {
     /// Path to a file on each workers local filesystem.
     pub path: String,
-    /// Attributes to ingest.
-    pub attributes: Vec<A>,
+    /// Path to the field holding the entity id. Objects are
+    /// identified by their line number, if none is given.
+    pub eid_path: Option<String>,
+    /// Paths to the fields to ingest, with nested fields separated
+    /// by dots. See `attribute_name` for how these are named.
+    pub fields: Vec<String>,
+    /// Batch size.
+    pub fuel: Option<usize>,
+    /// Scheduling interval.
+    pub interval: Option<Duration>,
+}
+
+/// Derives the attribute a field is ingested into, by namespacing
+/// its last path segment with the ones preceding it
+/// (e.g. "address.city" becomes ":address/city", "name" becomes
+/// ":name").
+pub fn attribute_name<A: AsAid>(path: &str) -> A {
+    match path.rfind('.') {
+        None => format!(":{}", path).into(),
+        Some(idx) => format!(":{}/{}", &path[..idx], &path[idx + 1..]).into(),
+    }
+}
+
+/// Resolves a dot-separated path within a JSON object.
+pub fn lookup<'a>(obj: &'a serde_json::Value, path: &str) -> Option<&'a serde_json::Value> {
+    path.split('.')
+        .try_fold(obj, |value, segment| value.get(segment))
 }
 
-impl<A: AsAid> Sourceable<A, Duration> for JsonFile<A> {
-    fn source<S: Scope<Timestamp = Duration>>(
+/// Converts JSON scalars into values. Other JSON values are not
+/// supported.
+pub fn to_value(json_value: &serde_json::Value) -> Option<Value> {
+    match *json_value {
+        serde_json::Value::String(ref s) => Some(Value::String(s.to_string())),
+        serde_json::Value::Number(ref num) => num.as_i64().map(Value::Number),
+        serde_json::Value::Bool(b) => Some(Value::Bool(b)),
+        _ => None,
+    }
+}
+
+impl<A: AsAid, S: Scope<Timestamp = Duration>> Sourceable<A, S> for JsonFile {
+    fn source(
         &self,
         scope: &mut S,
-        t0: Instant,
-        _scheduler: Weak<RefCell<Scheduler<Duration>>>,
-    ) -> Vec<(A, AttributeConfig, Stream<S, ((Value, Value), Duration, isize)>)> {
+        context: SourcingContext<S::Timestamp>,
+    ) -> Vec<(
+        A,
+        AttributeConfig,
+        Stream<S, ((Value, Value), Duration, isize)>,
+    )> {
         let filename = self.path.clone();
 
         // The following is mostly the innards of
@@ -45,106 +79,137 @@ impl<A: AsAid> Sourceable<A, Duration> for JsonFile<A> {
         let operator_info = demux.operator_info();
         demux.set_notify(false);
 
-        let mut wrappers = Vec::with_capacity(self.attributes.len());
-        let mut streams = Vec::with_capacity(self.attributes.len());
+        // Order is very important here, because otherwise the
+        // capabilities won't match up with the output streams later
+        // on (when creating sessions). We stick to the order dictated
+        // by the fields.
+        let mut wrappers = Vec::with_capacity(self.fields.len());
+        let mut streams = Vec::with_capacity(self.fields.len());
 
-        for _ in self.attributes.iter() {
+        for _ in self.fields.iter() {
             let (wrapper, stream) = demux.new_output();
             wrappers.push(wrapper);
             streams.push(stream);
         }
 
-        let scope_handle = scope.clone();
-        let attributes = self.attributes.clone();
+        let fields = self.fields.clone();
+        let eid_path = self.eid_path.clone();
+        let total_fuel = self.fuel.unwrap_or(256);
 
-        demux.build(move |mut capabilities| {
+        // Grab scheduler handle for deferred re-activation.
+        let scheduler = context.scheduler;
+        let t0 = context.t0;
+        let interval = self.interval.unwrap_or(Duration::from_secs(1));
+        let poll_interval = context.poll_interval.clone();
 
-            let scope = scope_handle;
-            let activator = scope.activator_for(&operator_info.address[..]);
+        demux.build(move |mut capabilities| {
+            let activator = Rc::new(scope.activator_for(&operator_info.address[..]));
 
             let worker_index = scope.index();
             let num_workers = scope.peers();
 
-            let path = Path::new(&filename);
-            let file = File::open(&path).unwrap();
-            // let reader = BufReader::new(GzDecoder::new(file));
-            let reader = BufReader::new(file);
-            let mut iterator = reader.lines().peekable();
+            let file = File::open(&filename).expect("failed to open file");
+            let mut iterator = BufReader::new(file).lines().peekable();
 
             let mut num_objects_read = 0;
             let mut object_index = 0;
 
             move |_frontiers| {
-                let mut handles = Vec::with_capacity(attributes.len());
-                for wrapper in wrappers.iter_mut() {
-                    handles.push(wrapper.activate());
-                }
+                if iterator.peek().is_none() {
+                    info!(
+                        "[W{}] read {} out of {} objects",
+                        worker_index, num_objects_read, object_index
+                    );
+                    capabilities.drain(..);
+                } else {
+                    let mut handles = Vec::with_capacity(fields.len());
+                    for wrapper in wrappers.iter_mut() {
+                        handles.push(wrapper.activate());
+                    }
 
-                if iterator.peek().is_some() {
-                    let mut sessions = Vec::with_capacity(attributes.len());
+                    let mut sessions = Vec::with_capacity(fields.len());
                     for (idx, handle) in handles.iter_mut().enumerate() {
-                        sessions.push(handle.session(capabilities.get(idx).unwrap()));
+                        sessions.push(handle.session(&capabilities[idx]));
                     }
 
                     let time = Instant::now().duration_since(t0);
 
-                    for readline in iterator.by_ref().take(256 - 1) {
+                    for readline in iterator.by_ref().take(total_fuel) {
                         let line = readline.expect("read error");
 
                         if (object_index % num_workers == worker_index) && !line.is_empty() {
-                            // @TODO parse only the names we are interested in
-                            // @TODO run with Value = serde_json::Value
-
-                            let obj: serde_json::Value = serde_json::from_str(&line).unwrap();
-                            let obj_map = obj.as_object().unwrap();
-
-                            // In the common case we assume that all objects share
-                            // roughly the same number of attributes, a (potentially small)
-                            // subset of which is actually requested downstream.
-                            //
-                            // otherwise:
-                            // for (k, v) in obj.as_object().unwrap() {
-
-                            for (idx, aid) in attributes.iter().enumerate() {
-                                match obj_map.get(aid) {
-                                    None => {}
-                                    Some(json_value) => {
-                                        let v = match *json_value {
-                                            serde_json::Value::String(ref s) => Value::String(s.to_string()),
-                                            serde_json::Value::Number(ref num) => {
-                                                match num.as_i64() {
-                                                    None => panic!("only i64 supported at the moment"),
-                                                    Some(num) => Number(num),
-                                                }
-                                            },
-                                            serde_json::Value::Bool(ref b) => Bool(*b),
-                                            _ => panic!("only strings, booleans, and i64 types supported at the moment"),
-                                        };
-
-                                        let tuple = (Value::Eid(object_index as Eid), v);
-
-                                        sessions.get_mut(idx)
-                                            .unwrap()
-                                            .give((tuple, time, 1));
+                            let obj: serde_json::Value = match serde_json::from_str(&line) {
+                                Ok(obj) => obj,
+                                Err(error) => {
+                                    warn!(
+                                        "[W{}] invalid object on line {}: {}",
+                                        worker_index, object_index, error
+                                    );
+                                    object_index += 1;
+                                    continue;
+                                }
+                            };
+
+                            let eid = match eid_path {
+                                None => Some(object_index as Eid),
+                                Some(ref eid_path) => {
+                                    lookup(&obj, eid_path).and_then(|e| e.as_u64())
+                                }
+                            };
+
+                            match eid {
+                                None => warn!(
+                                    "[W{}] object on line {} has no eid",
+                                    worker_index, object_index
+                                ),
+                                Some(eid) => {
+                                    for (idx, path) in fields.iter().enumerate() {
+                                        if let Some(v) = lookup(&obj, path).and_then(to_value) {
+                                            sessions[idx].give(((Value::Eid(eid), v), time, 1));
+                                        }
                                     }
+
+                                    num_objects_read += 1;
                                 }
                             }
-
-                            num_objects_read += 1;
                         }
 
                         object_index += 1;
                     }
 
-                    // println!("[W{}] read {} out of {} objects", worker_index, num_objects_read, object_index);
+                    drop(sessions);
+                    drop(handles);
 
-                    activator.activate();
-                } else {
-                    capabilities.drain(..);
+                    // Incorporate processing time in downgrade
+                    let time = Instant::now().duration_since(t0);
+
+                    for cap in capabilities.iter_mut() {
+                        cap.downgrade(&time);
+                    }
+
+                    // Notify the server that we want to be scheduled again soon
+                    scheduler
+                        .upgrade()
+                        .unwrap()
+                        .borrow_mut()
+                        .realtime
+                        .schedule_after(
+                            poll_interval.get().unwrap_or(interval),
+                            Rc::downgrade(&activator),
+                        );
                 }
             }
         });
 
-        streams
+        let mut out = Vec::with_capacity(streams.len());
+        for (idx, stream) in streams.drain(..).enumerate() {
+            out.push((
+                attribute_name(&self.fields[idx]),
+                AttributeConfig::real_time(InputSemantics::Distinct),
+                stream,
+            ));
+        }
+
+        out
     }
 }
@@ -0,0 +1,268 @@
+//! Operator and utilities to source data from Kafka topics.
+
+use std::rc::Rc;
+use std::time::Duration;
+
+use timely::dataflow::operators::generic::builder_rc::OperatorBuilder;
+use timely::dataflow::{Scope, Stream};
+
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{BaseConsumer, Consumer};
+use rdkafka::message::Message;
+use rdkafka::{Offset, TopicPartitionList};
+
+use crate::sources::checkpoint::{Checkpointer, Positions, SourceCheckpoint};
+use crate::sources::decoders::{Decoder, Decoding};
+use crate::sources::{Sourceable, SourcingContext};
+use crate::{AsAid, Value};
+use crate::{AttributeConfig, InputSemantics};
+
+/// A Kafka topic, whose records are decoded using the configured
+/// wire format. Each worker consumes a disjoint subset of the topic's
+/// partitions, from the beginning or, if checkpointing is enabled,
+/// from the offsets reached before the last shutdown.
+///
+/// Records are timestamped by their offset (offset `n` happens at `n`
+/// nanoseconds), s.t. re-running a computation against the same topic
+/// reproduces the same times. Consequently, a partition that doesn't
+/// receive any records holds back the domain frontier.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct KafkaTopic<A: AsAid> {
+    /// Comma-separated list of brokers to bootstrap from.
+    pub brokers: String,
+    /// Name of the topic to consume.
+    pub topic: String,
+    /// Number of partitions of the topic.
+    pub partitions: usize,
+    /// Consumer group to report as. Offsets are never committed.
+    pub group_id: String,
+    /// Wire format of the records.
+    pub decoder: Decoding<A>,
+    /// Batch size.
+    pub fuel: Option<usize>,
+    /// Scheduling interval.
+    pub interval: Option<Duration>,
+}
+
+impl<A: AsAid> SourceCheckpoint for KafkaTopic<A> {
+    fn checkpoint_name(&self) -> String {
+        format!("kafka/{}/{}", self.group_id, self.topic)
+    }
+}
+
+impl<A: AsAid, S: Scope<Timestamp = Duration>> Sourceable<A, S> for KafkaTopic<A> {
+    fn source(
+        &self,
+        scope: &mut S,
+        context: SourcingContext<S::Timestamp>,
+    ) -> Vec<(
+        A,
+        AttributeConfig,
+        Stream<S, ((Value, Value), Duration, isize)>,
+    )> {
+        let topic = self.topic.clone();
+
+        let consumer: BaseConsumer = ClientConfig::new()
+            .set("bootstrap.servers", &self.brokers)
+            .set("group.id", &self.group_id)
+            .set("enable.auto.commit", "false")
+            .create()
+            .expect("failed to create consumer");
+
+        let mut demux = OperatorBuilder::new(format!("Kafka({})", topic), scope.clone());
+        let operator_info = demux.operator_info();
+        demux.set_notify(false);
+
+        // Order is very important here, because otherwise the
+        // capabilities won't match up with the output streams later
+        // on (when creating sessions). We stick to the order dictated
+        // by the decoder.
+        let attributes = self.decoder.attributes();
+        let mut wrappers = Vec::with_capacity(attributes.len());
+        let mut streams = Vec::with_capacity(attributes.len());
+
+        for _ in attributes.iter() {
+            let (wrapper, stream) = demux.new_output();
+            wrappers.push(wrapper);
+            streams.push(stream);
+        }
+
+        let num_partitions = self.partitions;
+        let decoder = self.decoder.clone();
+        let num_outputs = attributes.len();
+        let total_fuel = self.fuel.unwrap_or(256);
+
+        let mut checkpointer = context.checkpoints.clone().map(|store| {
+            Checkpointer::new(self.checkpoint_name(), store, context.domain_probe.clone())
+        });
+
+        // Grab scheduler handle for deferred re-activation.
+        let scheduler = context.scheduler;
+        let interval = self.interval.unwrap_or(Duration::from_millis(100));
+        let poll_interval = context.poll_interval.clone();
+
+        demux.build(move |mut capabilities| {
+            let activator = Rc::new(scope.activator_for(&operator_info.address[..]));
+
+            let worker_index = scope.index();
+            let num_workers = scope.peers();
+
+            let restored = checkpointer
+                .as_ref()
+                .map(|checkpointer| checkpointer.restore())
+                .unwrap_or_default();
+
+            // Each worker reads a disjoint subset of all partitions,
+            // tracking the next offset it expects from each of them.
+            let mut positions = Vec::new();
+            let mut assignment = TopicPartitionList::new();
+            for partition in (0..num_partitions).filter(|p| p % num_workers == worker_index) {
+                match restored.get(&partition.to_string()) {
+                    None => {
+                        assignment.add_partition_offset(
+                            &topic,
+                            partition as i32,
+                            Offset::Beginning,
+                        );
+                        positions.push((partition as i32, 0));
+                    }
+                    Some(offset) => {
+                        let offset = *offset as i64;
+                        assignment.add_partition_offset(
+                            &topic,
+                            partition as i32,
+                            Offset::Offset(offset),
+                        );
+                        positions.push((partition as i32, offset));
+                    }
+                }
+            }
+
+            consumer
+                .assign(&assignment)
+                .expect("failed to assign partitions");
+
+            if positions.is_empty() {
+                capabilities.drain(..);
+            }
+
+            let mut num_records_read = 0;
+
+            // A time beyond all records read so far.
+            let mut horizon = Duration::from_nanos(0);
+
+            move |_frontiers| {
+                if capabilities.is_empty() {
+                    return;
+                }
+
+                let mut handles = Vec::with_capacity(num_outputs);
+                for wrapper in wrappers.iter_mut() {
+                    handles.push(wrapper.activate());
+                }
+
+                let mut sessions = Vec::with_capacity(num_outputs);
+                for (idx, handle) in handles.iter_mut().enumerate() {
+                    sessions.push(handle.session(&capabilities[idx]));
+                }
+
+                let num_records_before = num_records_read;
+
+                for _ in 0..total_fuel {
+                    let message = match consumer.poll(Duration::from_millis(0)) {
+                        None => break,
+                        Some(Err(error)) => {
+                            error!("[W{}] failed to consume {}: {}", worker_index, topic, error);
+                            break;
+                        }
+                        Some(Ok(message)) => message,
+                    };
+
+                    let offset = message.offset();
+                    let time = Duration::from_nanos(offset as u64);
+
+                    if let Some(position) = positions
+                        .iter_mut()
+                        .find(|(partition, _)| *partition == message.partition())
+                    {
+                        position.1 = offset + 1;
+                    }
+
+                    let datoms = match message.payload().map(|payload| decoder.decode(payload)) {
+                        None => continue,
+                        Some(Err(error)) => {
+                            warn!(
+                                "[W{}] invalid record at offset {}: {}",
+                                worker_index, offset, error.message
+                            );
+                            continue;
+                        }
+                        Some(Ok(datoms)) => datoms,
+                    };
+
+                    for (idx, datom, diff) in datoms.into_iter() {
+                        sessions[idx].give((datom, time, diff));
+                    }
+
+                    horizon = std::cmp::max(horizon, time + Duration::from_nanos(1));
+
+                    num_records_read += 1;
+                }
+
+                drop(sessions);
+                drop(handles);
+
+                trace!(
+                    "[W{}] read {} records from {}",
+                    worker_index,
+                    num_records_read,
+                    topic
+                );
+
+                // All records up to the smallest expected offset have
+                // been read.
+                let frontier = positions
+                    .iter()
+                    .map(|(_partition, position)| *position)
+                    .min()
+                    .expect("no partitions assigned");
+                let time = Duration::from_nanos(frontier as u64);
+
+                for cap in capabilities.iter_mut() {
+                    cap.downgrade(&time);
+                }
+
+                if let Some(ref mut checkpointer) = checkpointer {
+                    if num_records_read > num_records_before {
+                        let checkpoint: Positions = positions
+                            .iter()
+                            .map(|(partition, position)| (partition.to_string(), *position as u64))
+                            .collect();
+
+                        checkpointer.record(std::cmp::max(horizon, time), checkpoint);
+                    }
+
+                    checkpointer.persist();
+                }
+
+                // Notify the server that we want to be scheduled again soon
+                scheduler
+                    .upgrade()
+                    .unwrap()
+                    .borrow_mut()
+                    .realtime
+                    .schedule_after(
+                        poll_interval.get().unwrap_or(interval),
+                        Rc::downgrade(&activator),
+                    );
+            }
+        });
+
+        let mut out = Vec::with_capacity(streams.len());
+        for (aid, stream) in attributes.into_iter().zip(streams.drain(..)) {
+            out.push((aid, AttributeConfig::real_time(InputSemantics::Raw), stream));
+        }
+
+        out
+    }
+}
@@ -0,0 +1,295 @@
+//! Operator and utilities to source synthetic data, for benchmarking
+//! query plans and server throughput without any external system.
+
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use timely::dataflow::operators::generic::builder_rc::OperatorBuilder;
+use timely::dataflow::{Scope, Stream};
+
+use crate::sources::{Sourceable, SourcingContext};
+use crate::{AsAid, Decimal, Rational32, Value, ValueType};
+use crate::{AttributeConfig, InputSemantics};
+
+/// An attribute fed by a load generator.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct GeneratedAttribute<A: AsAid> {
+    /// Name of the attribute.
+    pub name: A,
+    /// Type of the generated values.
+    pub value_type: ValueType,
+    /// Number of distinct values drawn from.
+    pub cardinality: u64,
+    /// How strongly draws favour the first few values. Zero draws
+    /// uniformly, each increment raises the exponent of the
+    /// underlying power-law distribution by one.
+    pub skew: u32,
+}
+
+/// A source of random datoms, updating one attribute of one entity
+/// at a time. Attributes use last-write-wins semantics, s.t. each
+/// datom replaces whatever value the entity had before, keeping the
+/// size of the generated dataset bounded by the number of entities.
+///
+/// All workers generate an equal share of the load, about disjoint
+/// entities. Generated data only depends on the seed and the number
+/// of workers, so runs are reproducible.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct LoadGenerator<A: AsAid> {
+    /// Attributes to generate datoms for, each chosen equally often.
+    pub attributes: Vec<GeneratedAttribute<A>>,
+    /// Number of distinct entities across all workers.
+    pub entities: u64,
+    /// Skew of the draw of entities, see `GeneratedAttribute::skew`.
+    #[serde(default)]
+    pub entity_skew: u32,
+    /// Datoms generated per second, across all workers.
+    pub rate: u64,
+    /// Total number of datoms to generate, after which the source
+    /// closes its attributes. Generates datoms indefinitely if unset.
+    #[serde(default)]
+    pub limit: Option<u64>,
+    /// Seed of the random number generator.
+    #[serde(default)]
+    pub seed: u64,
+    /// Interval at which batches of datoms are introduced.
+    #[serde(default)]
+    pub interval: Option<Duration>,
+}
+
+/// The state of a single worker's share of a load generator.
+pub struct Generator {
+    state: u64,
+    worker: u64,
+    peers: u64,
+    entities: u64,
+    entity_skew: u32,
+    attributes: Vec<(ValueType, u64, u32)>,
+}
+
+impl Generator {
+    /// Creates the share of the specified worker.
+    pub fn new<A: AsAid>(source: &LoadGenerator<A>, worker: usize, peers: usize) -> Self {
+        let (worker, peers) = (worker as u64, peers as u64);
+
+        // Entities worker, worker + peers, worker + 2 * peers, ...
+        // belong to this worker.
+        let entities = (source.entities + peers - 1 - worker) / peers;
+
+        Generator {
+            state: source.seed ^ worker.wrapping_mul(0x9E37_79B9_7F4A_7C15),
+            worker,
+            peers,
+            entities,
+            entity_skew: source.entity_skew,
+            attributes: source
+                .attributes
+                .iter()
+                .map(|attribute| (attribute.value_type, attribute.cardinality, attribute.skew))
+                .collect(),
+        }
+    }
+
+    /// Returns true iff this worker has anything to generate.
+    pub fn is_empty(&self) -> bool {
+        self.entities == 0
+            || self
+                .attributes
+                .iter()
+                .all(|(_, cardinality, _)| *cardinality == 0)
+    }
+
+    /// Generates the next datom, tagged with the index of its
+    /// attribute. Attributes without any values are skipped.
+    pub fn next_datom(&mut self) -> Option<(usize, (Value, Value))> {
+        if self.is_empty() {
+            return None;
+        }
+
+        loop {
+            let idx = (self.next_u64() % self.attributes.len() as u64) as usize;
+            let (value_type, cardinality, skew) = self.attributes[idx];
+
+            if cardinality == 0 {
+                continue;
+            }
+
+            let e = self.worker + self.peers * self.draw(self.entities, self.entity_skew);
+            let v = generate_value(value_type, self.draw(cardinality, skew));
+
+            return Some((idx, (Value::Eid(e), v)));
+        }
+    }
+
+    /// SplitMix64, which is plenty for generating load.
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Draws one of `n` indices, favouring small ones the more
+    /// skewed the draw is.
+    fn draw(&mut self, n: u64, skew: u32) -> u64 {
+        let uniform = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        let skewed = uniform.powi(1 + skew as i32);
+
+        std::cmp::min((skewed * n as f64) as u64, n - 1)
+    }
+}
+
+/// Returns the `i`-th distinct value of the specified type.
+fn generate_value(value_type: ValueType, i: u64) -> Value {
+    match value_type {
+        ValueType::Aid => Value::Aid(format!(":generated/{}", i)),
+        ValueType::String => Value::String(format!("value-{}", i)),
+        ValueType::Bool => Value::Bool(i % 2 == 0),
+        ValueType::Number => Value::Number(i as i64),
+        ValueType::Rational32 => Value::Rational32(Rational32::from_integer(i as i32)),
+        ValueType::Eid => Value::Eid(i),
+        ValueType::Instant => Value::Instant(i),
+        ValueType::Uuid => {
+            let mut bytes = [0; 16];
+            bytes[8..].copy_from_slice(&i.to_be_bytes());
+            Value::Uuid(uuid::Uuid::from_bytes(bytes))
+        }
+        ValueType::Date => Value::Date(i as i32),
+        ValueType::Bytes => Value::Bytes(i.to_be_bytes().to_vec()),
+        ValueType::Decimal => Value::Decimal(Decimal::from(i as i64)),
+        #[cfg(feature = "real")]
+        ValueType::Real => Value::from((i % (1 << 15)) as f64),
+    }
+}
+
+impl<A: AsAid, S: Scope<Timestamp = Duration>> Sourceable<A, S> for LoadGenerator<A> {
+    fn source(
+        &self,
+        scope: &mut S,
+        context: SourcingContext<S::Timestamp>,
+    ) -> Vec<(
+        A,
+        AttributeConfig,
+        Stream<S, ((Value, Value), Duration, isize)>,
+    )> {
+        let mut demux = OperatorBuilder::new("LoadGenerator".to_string(), scope.clone());
+        let operator_info = demux.operator_info();
+        demux.set_notify(false);
+
+        // Order is very important here, because otherwise the
+        // capabilities won't match up with the output streams later
+        // on (when creating sessions). We stick to the order dictated
+        // by the attributes.
+        let mut wrappers = Vec::with_capacity(self.attributes.len());
+        let mut streams = Vec::with_capacity(self.attributes.len());
+
+        for _ in self.attributes.iter() {
+            let (wrapper, stream) = demux.new_output();
+            wrappers.push(wrapper);
+            streams.push(stream);
+        }
+
+        let mut generator = Generator::new(self, scope.index(), scope.peers());
+
+        // Each worker is responsible for its share of the rate and
+        // of the limit.
+        let peers = scope.peers() as u64;
+        let worker = scope.index() as u64;
+        let share = |total: u64| (total + peers - 1 - worker) / peers;
+        let rate = share(self.rate);
+        let limit = self.limit.map(share);
+
+        // Grab scheduler handle for deferred re-activation.
+        let scheduler = context.scheduler;
+        let t0 = context.t0;
+        let interval = self.interval.unwrap_or(Duration::from_millis(100));
+        let poll_interval = context.poll_interval.clone();
+
+        demux.build(move |mut capabilities| {
+            let activator = Rc::new(scope.activator_for(&operator_info.address[..]));
+
+            let started = Instant::now();
+            let mut generated: u64 = 0;
+
+            if generator.is_empty() || rate == 0 || limit == Some(0) {
+                capabilities.drain(..);
+            }
+
+            move |_frontiers| {
+                if capabilities.is_empty() {
+                    return;
+                }
+
+                // Catch up with the configured rate, whatever the
+                // actual interval between activations.
+                let elapsed = Instant::now().duration_since(started);
+                let mut due = (rate as u128 * elapsed.as_nanos() / 1_000_000_000) as u64;
+
+                if let Some(limit) = limit {
+                    due = std::cmp::min(due, limit);
+                }
+
+                if due > generated {
+                    let mut handles = Vec::with_capacity(wrappers.len());
+                    for wrapper in wrappers.iter_mut() {
+                        handles.push(wrapper.activate());
+                    }
+
+                    let mut sessions = Vec::with_capacity(wrappers.len());
+                    for (idx, handle) in handles.iter_mut().enumerate() {
+                        sessions.push(handle.session(&capabilities[idx]));
+                    }
+
+                    let time = Instant::now().duration_since(t0);
+
+                    while generated < due {
+                        if let Some((idx, datom)) = generator.next_datom() {
+                            sessions[idx].give((datom, time, 1));
+                        }
+
+                        generated += 1;
+                    }
+                }
+
+                if Some(generated) == limit {
+                    info!("[W{}] generated {} datoms", worker, generated);
+                    capabilities.drain(..);
+                    return;
+                }
+
+                // Incorporate processing time in downgrade
+                let time = Instant::now().duration_since(t0);
+
+                for cap in capabilities.iter_mut() {
+                    cap.downgrade(&time);
+                }
+
+                // Notify the server that we want to be scheduled again soon
+                scheduler
+                    .upgrade()
+                    .unwrap()
+                    .borrow_mut()
+                    .realtime
+                    .schedule_after(
+                        poll_interval.get().unwrap_or(interval),
+                        Rc::downgrade(&activator),
+                    );
+            }
+        });
+
+        let mut out = Vec::with_capacity(streams.len());
+        for (idx, stream) in streams.drain(..).enumerate() {
+            let attribute = &self.attributes[idx];
+            let config = AttributeConfig {
+                value_type: Some(attribute.value_type),
+                ..AttributeConfig::real_time(InputSemantics::LastWriteWins)
+            };
+
+            out.push((attribute.name.clone(), config, stream));
+        }
+
+        out
+    }
+}
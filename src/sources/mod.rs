@@ -1,10 +1,11 @@
 //! Types and operators to work with external data sources.
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::{Rc, Weak};
 use std::time::{Duration, Instant};
 
 use timely::dataflow::operators::capture::event::link::EventLink;
+use timely::dataflow::operators::Partition;
 use timely::dataflow::{ProbeHandle, Scope, Stream};
 use timely::logging::TimelyEvent;
 use timely::progress::Timestamp;
@@ -13,19 +14,70 @@ use differential_dataflow::lattice::Lattice;
 use differential_dataflow::logging::DifferentialEvent;
 
 use crate::scheduling::Scheduler;
+use crate::sources::checkpoint::CheckpointStore;
 use crate::AttributeConfig;
 use crate::{AsAid, Value};
 
+pub mod checkpoint;
 #[cfg(feature = "csv-source")]
 pub mod csv_file;
+#[cfg(feature = "serde_json")]
+pub mod decoders;
 // pub mod declarative_logging;
 pub mod differential_logging;
-// pub mod json_file;
+#[cfg(feature = "http-source")]
+pub mod http_poll;
+#[cfg(feature = "json-source")]
+pub mod json_file;
+#[cfg(feature = "kafka-source")]
+pub mod kafka;
+pub mod load_generator;
+#[cfg(feature = "object-source")]
+pub mod object_store;
+#[cfg(feature = "postgres-source")]
+pub mod postgres_cdc;
+#[cfg(feature = "json-source")]
+pub mod tail_file;
 pub mod timely_logging;
+#[cfg(feature = "json-source")]
+pub mod tx_log;
+#[cfg(feature = "websocket-source")]
+pub mod websocket;
 
 #[cfg(feature = "csv-source")]
 pub use self::csv_file::CsvFile;
-// pub use self::json_file::JsonFile;
+#[cfg(feature = "http-source")]
+pub use self::http_poll::HttpPoll;
+#[cfg(feature = "json-source")]
+pub use self::json_file::JsonFile;
+#[cfg(feature = "kafka-source")]
+pub use self::kafka::KafkaTopic;
+pub use self::load_generator::LoadGenerator;
+#[cfg(feature = "object-source")]
+pub use self::object_store::ObjectStore;
+#[cfg(feature = "postgres-source")]
+pub use self::postgres_cdc::PostgresCdc;
+#[cfg(feature = "json-source")]
+pub use self::tail_file::TailFile;
+#[cfg(feature = "json-source")]
+pub use self::tx_log::TxLog;
+#[cfg(feature = "websocket-source")]
+pub use self::websocket::WebSocketClient;
+
+/// Decodes a JSON value into a value of the same type as the given
+/// type hint. Returns `None` if the JSON value is of a different
+/// type.
+#[cfg(feature = "serde_json")]
+pub fn decode_json(json_value: &serde_json::Value, type_hint: &Value) -> Option<Value> {
+    match (type_hint, json_value) {
+        (Value::String(_), serde_json::Value::String(s)) => Some(Value::String(s.to_string())),
+        (Value::Aid(_), serde_json::Value::String(s)) => Some(Value::Aid(s.to_string())),
+        (Value::Number(_), serde_json::Value::Number(num)) => num.as_i64().map(Value::Number),
+        (Value::Eid(_), serde_json::Value::Number(num)) => num.as_u64().map(Value::Eid),
+        (Value::Bool(_), serde_json::Value::Bool(b)) => Some(Value::Bool(*b)),
+        _ => None,
+    }
+}
 
 /// A struct encapsulating any state required to create sources.
 pub struct SourcingContext<T: Timestamp> {
@@ -41,6 +93,13 @@ pub struct SourcingContext<T: Timestamp> {
     pub timely_events: Rc<EventLink<Duration, (Duration, usize, TimelyEvent)>>,
     /// A weak handle to Differential event link.
     pub differential_events: Rc<EventLink<Duration, (Duration, usize, DifferentialEvent)>>,
+    /// A handle to the store for read positions, if checkpointing is
+    /// enabled.
+    pub checkpoints: Option<Rc<CheckpointStore>>,
+    /// A handle to the server's polling interval, if one is
+    /// configured. Polling sources prefer it over their own interval,
+    /// reading it anew whenever they defer their next activation.
+    pub poll_interval: Rc<Cell<Option<Duration>>>,
 }
 
 /// An external data source that can provide Datoms.
@@ -63,6 +122,69 @@ where
     )>;
 }
 
+/// An external data source providing datoms for many attributes on
+/// a single stream, e.g. a shared log of (e, a, v) tuples.
+///
+/// Datoms are tagged with the index of their attribute in the
+/// returned list of attributes. The server demultiplexes the stream
+/// into one stream per attribute in a single pass (see
+/// `demultiplex`), rather than filtering the entire stream once for
+/// each attribute.
+pub trait MultiplexedSourceable<A, S>
+where
+    A: AsAid,
+    S: Scope,
+    S::Timestamp: Timestamp + Lattice,
+{
+    /// Conjures from thin air (or from wherever the source lives) a
+    /// single timely stream of tagged datoms, along with the
+    /// attributes it feeds into.
+    fn source(
+        &self,
+        scope: &mut S,
+        context: SourcingContext<S::Timestamp>,
+    ) -> (
+        Vec<(A, AttributeConfig)>,
+        Stream<S, ((usize, (Value, Value)), S::Timestamp, isize)>,
+    );
+}
+
+/// Splits a stream of datoms tagged with attribute indices into one
+/// stream per attribute, examining each datom exactly once. Datoms
+/// tagged with an index beyond the specified attributes are
+/// discarded.
+pub fn demultiplex<A, S>(
+    attributes: Vec<(A, AttributeConfig)>,
+    datoms: &Stream<S, ((usize, (Value, Value)), S::Timestamp, isize)>,
+) -> Vec<(
+    A,
+    AttributeConfig,
+    Stream<S, ((Value, Value), S::Timestamp, isize)>,
+)>
+where
+    A: AsAid,
+    S: Scope,
+    S::Timestamp: Timestamp + Lattice,
+{
+    let num_attributes = attributes.len() as u64;
+
+    // Partition requires every datom to be routed somewhere, so
+    // datoms on unknown attributes are sent to an extra output which
+    // is then dropped.
+    let mut streams = datoms.partition(num_attributes + 1, move |((idx, datom), t, diff)| {
+        let idx = std::cmp::min(idx as u64, num_attributes);
+        (idx, (datom, t, diff))
+    });
+
+    streams.truncate(attributes.len());
+
+    attributes
+        .into_iter()
+        .zip(streams.into_iter())
+        .map(|((aid, config), stream)| (aid, config, stream))
+        .collect()
+}
+
 /// Supported external data sources.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub enum Source<A: AsAid + From<&'static str>> {
@@ -72,11 +194,35 @@ pub enum Source<A: AsAid + From<&'static str>> {
     DifferentialLogging(differential_logging::DifferentialLogging<A>),
     // /// Declarative logging streams
     // DeclarativeLogging(declarative_logging::DeclarativeLogging),
+    /// Synthetic datoms, for benchmarking
+    LoadGenerator(LoadGenerator<A>),
     /// CSV files
     #[cfg(feature = "csv-source")]
     CsvFile(CsvFile<A>),
-    // /// Files containing json objects
-    // JsonFile(JsonFile<A>),
+    /// Files containing json objects
+    #[cfg(feature = "json-source")]
+    JsonFile(JsonFile),
+    /// Kafka topics
+    #[cfg(feature = "kafka-source")]
+    KafkaTopic(KafkaTopic<A>),
+    /// Append-only files containing json objects
+    #[cfg(feature = "json-source")]
+    TailFile(TailFile),
+    /// PostgreSQL logical replication slots
+    #[cfg(feature = "postgres-source")]
+    PostgresCdc(PostgresCdc<A>),
+    /// Datomic-style transaction logs
+    #[cfg(feature = "json-source")]
+    TxLog(TxLog<A>),
+    /// WebSocket endpoints
+    #[cfg(feature = "websocket-source")]
+    WebSocket(WebSocketClient<A>),
+    /// Polled HTTP endpoints
+    #[cfg(feature = "http-source")]
+    HttpPoll(HttpPoll<A>),
+    /// Collections of objects in S3 or local directories
+    #[cfg(feature = "object-source")]
+    ObjectStore(ObjectStore<A>),
 }
 
 #[cfg(feature = "real-time")]
@@ -98,8 +244,25 @@ where
             Source::TimelyLogging(ref source) => source.source(scope, context),
             Source::DifferentialLogging(ref source) => source.source(scope, context),
             // Source::DeclarativeLogging(ref source) => source.source(scope, context),
+            Source::LoadGenerator(ref source) => source.source(scope, context),
             #[cfg(feature = "csv-source")]
             Source::CsvFile(ref source) => source.source(scope, context),
+            #[cfg(feature = "json-source")]
+            Source::JsonFile(ref source) => source.source(scope, context),
+            #[cfg(feature = "kafka-source")]
+            Source::KafkaTopic(ref source) => source.source(scope, context),
+            #[cfg(feature = "json-source")]
+            Source::TailFile(ref source) => source.source(scope, context),
+            #[cfg(feature = "postgres-source")]
+            Source::PostgresCdc(ref source) => source.source(scope, context),
+            #[cfg(feature = "json-source")]
+            Source::TxLog(ref source) => source.source(scope, context),
+            #[cfg(feature = "websocket-source")]
+            Source::WebSocket(ref source) => source.source(scope, context),
+            #[cfg(feature = "http-source")]
+            Source::HttpPoll(ref source) => source.source(scope, context),
+            #[cfg(feature = "object-source")]
+            Source::ObjectStore(ref source) => source.source(scope, context),
             _ => unimplemented!(),
         }
     }
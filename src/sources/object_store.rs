@@ -0,0 +1,441 @@
+//! Operator and utilities to bulk-load data from collections of
+//! objects, such as historical exports in S3 or a local directory.
+
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use timely::dataflow::operators::generic::builder_rc::OperatorBuilder;
+use timely::dataflow::{Scope, Stream};
+
+use crate::sources::{decode_json, Sourceable, SourcingContext};
+use crate::{AsAid, Eid, Value};
+use crate::{AttributeConfig, InputSemantics};
+
+/// Where objects are stored.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum Store {
+    /// A directory on each workers local filesystem.
+    Local(String),
+    /// An S3 bucket in the specified region.
+    #[cfg(feature = "s3-source")]
+    S3 {
+        /// Region name, e.g. "eu-central-1".
+        region: String,
+        /// Bucket name.
+        bucket: String,
+    },
+}
+
+/// How to decode the contents of each object.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum Format {
+    /// Comma-separated values, where the schema holds column offsets.
+    Csv {
+        /// Does each object include a header?
+        has_headers: bool,
+        /// Column delimiter to use.
+        delimiter: u8,
+        /// Special column offset for the entity id.
+        eid_offset: usize,
+    },
+    /// Newline-delimited JSON objects, where the schema holds field
+    /// names.
+    Json {
+        /// Name of the field holding the entity id.
+        eid_field: String,
+    },
+}
+
+/// A collection of objects sharing a common prefix, all decoded
+/// using the same format. Objects are distributed across workers and
+/// read in lexicographic order of their keys.
+///
+/// Each object is ingested as an epoch of its own, i.e. the
+/// frontier advances whenever an object has been read completely,
+/// s.t. large backfills make incremental progress.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct ObjectStore<A: AsAid> {
+    /// Where to look for objects.
+    pub store: Store,
+    /// Only objects whose key starts with this prefix are read.
+    pub prefix: String,
+    /// How to decode objects.
+    pub format: Format,
+    /// Specifies the column offsets or field names (depending on the
+    /// format) and their value types, that should be introduced.
+    pub schema: Vec<(A, (String, Value))>,
+    /// Batch size.
+    pub fuel: Option<usize>,
+    /// Scheduling interval.
+    pub interval: Option<Duration>,
+}
+
+/// Lists the keys of all objects under the prefix, in order.
+fn list(store: &Store, prefix: &str) -> Result<Vec<String>, String> {
+    let mut keys = match *store {
+        Store::Local(ref directory) => {
+            let mut keys = Vec::new();
+            for entry in fs::read_dir(directory).map_err(|error| error.to_string())? {
+                let entry = entry.map_err(|error| error.to_string())?;
+                let key = entry.file_name().to_string_lossy().to_string();
+
+                if key.starts_with(prefix) && entry.path().is_file() {
+                    keys.push(key);
+                }
+            }
+            keys
+        }
+        #[cfg(feature = "s3-source")]
+        Store::S3 {
+            ref region,
+            ref bucket,
+        } => s3::list(region, bucket, prefix)?,
+    };
+
+    keys.sort();
+    Ok(keys)
+}
+
+/// Opens the object with the specified key for reading.
+fn open(store: &Store, key: &str) -> Result<Box<dyn Read>, String> {
+    match *store {
+        Store::Local(ref directory) => {
+            let path = std::path::Path::new(directory).join(key);
+            let file = File::open(path).map_err(|error| error.to_string())?;
+            Ok(Box::new(file))
+        }
+        #[cfg(feature = "s3-source")]
+        Store::S3 {
+            ref region,
+            ref bucket,
+        } => s3::open(region, bucket, key),
+    }
+}
+
+#[cfg(feature = "s3-source")]
+mod s3 {
+    use std::io::Read;
+    use std::str::FromStr;
+
+    use rusoto_core::Region;
+    use rusoto_s3::{GetObjectRequest, ListObjectsV2Request, S3Client, S3};
+
+    fn client(region: &str) -> Result<S3Client, String> {
+        let region = Region::from_str(region).map_err(|error| error.to_string())?;
+        Ok(S3Client::new(region))
+    }
+
+    pub fn list(region: &str, bucket: &str, prefix: &str) -> Result<Vec<String>, String> {
+        let client = client(region)?;
+
+        let mut keys = Vec::new();
+        let mut continuation_token = None;
+
+        loop {
+            let request = ListObjectsV2Request {
+                bucket: bucket.to_string(),
+                prefix: Some(prefix.to_string()),
+                continuation_token: continuation_token.take(),
+                ..Default::default()
+            };
+
+            let response = client
+                .list_objects_v2(request)
+                .sync()
+                .map_err(|error| error.to_string())?;
+
+            for object in response.contents.unwrap_or_default() {
+                if let Some(key) = object.key {
+                    keys.push(key);
+                }
+            }
+
+            match response.next_continuation_token {
+                None => return Ok(keys),
+                Some(token) => continuation_token = Some(token),
+            }
+        }
+    }
+
+    pub fn open(region: &str, bucket: &str, key: &str) -> Result<Box<dyn Read>, String> {
+        let request = GetObjectRequest {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            ..Default::default()
+        };
+
+        let response = client(region)?
+            .get_object(request)
+            .sync()
+            .map_err(|error| error.to_string())?;
+
+        match response.body {
+            None => Err(format!("object {} has no body", key)),
+            Some(body) => Ok(Box::new(body.into_blocking_read())),
+        }
+    }
+}
+
+/// Decoded records of the object currently being read.
+enum Records {
+    Csv(csv::StringRecordsIntoIter<Box<dyn Read>>),
+    Json(std::io::Lines<BufReader<Box<dyn Read>>>),
+}
+
+impl Records {
+    fn new(format: &Format, reader: Box<dyn Read>) -> Self {
+        match *format {
+            Format::Csv {
+                has_headers,
+                delimiter,
+                ..
+            } => Records::Csv(
+                csv::ReaderBuilder::new()
+                    .has_headers(has_headers)
+                    .delimiter(delimiter)
+                    .from_reader(reader)
+                    .into_records(),
+            ),
+            Format::Json { .. } => Records::Json(BufReader::new(reader).lines()),
+        }
+    }
+
+    /// Decodes the next record into (output, datom) pairs. Returns
+    /// `None` once the object is exhausted.
+    fn next<A: AsAid>(
+        &mut self,
+        format: &Format,
+        schema: &[(A, (String, Value))],
+    ) -> Option<Result<Vec<(usize, (Value, Value))>, String>> {
+        let mut datoms = Vec::with_capacity(schema.len());
+
+        match (self, format) {
+            (Records::Csv(records), Format::Csv { eid_offset, .. }) => {
+                let record = match records.next()? {
+                    Ok(record) => record,
+                    Err(error) => return Some(Err(error.to_string())),
+                };
+
+                let eid = match record.get(*eid_offset).and_then(|e| e.parse::<Eid>().ok()) {
+                    None => return Some(Err("record has no eid".to_string())),
+                    Some(eid) => Value::Eid(eid),
+                };
+
+                for (idx, (_aid, (column, type_hint))) in schema.iter().enumerate() {
+                    let field = column
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|offset| record.get(offset));
+
+                    let v = match (type_hint, field) {
+                        (_, None) => None,
+                        (Value::String(_), Some(field)) => Some(Value::String(field.to_string())),
+                        (Value::Number(_), Some(field)) => {
+                            field.parse::<i64>().ok().map(Value::Number)
+                        }
+                        (Value::Eid(_), Some(field)) => field.parse::<Eid>().ok().map(Value::Eid),
+                        (Value::Bool(_), Some(field)) => {
+                            field.parse::<bool>().ok().map(Value::Bool)
+                        }
+                        _ => None,
+                    };
+
+                    if let Some(v) = v {
+                        datoms.push((idx, (eid.clone(), v)));
+                    }
+                }
+            }
+            (Records::Json(lines), Format::Json { eid_field }) => {
+                let line = match lines.next()? {
+                    Ok(line) => line,
+                    Err(error) => return Some(Err(error.to_string())),
+                };
+
+                if line.is_empty() {
+                    return Some(Ok(datoms));
+                }
+
+                let obj: serde_json::Value = match serde_json::from_str(&line) {
+                    Ok(obj) => obj,
+                    Err(error) => return Some(Err(error.to_string())),
+                };
+
+                let eid = match obj.get(eid_field).and_then(|e| e.as_u64()) {
+                    None => return Some(Err("object has no eid".to_string())),
+                    Some(eid) => Value::Eid(eid),
+                };
+
+                for (idx, (_aid, (field, type_hint))) in schema.iter().enumerate() {
+                    if let Some(v) = obj.get(field).and_then(|v| decode_json(v, type_hint)) {
+                        datoms.push((idx, (eid.clone(), v)));
+                    }
+                }
+            }
+            _ => unreachable!(),
+        }
+
+        Some(Ok(datoms))
+    }
+}
+
+impl<A: AsAid, S: Scope<Timestamp = Duration>> Sourceable<A, S> for ObjectStore<A> {
+    fn source(
+        &self,
+        scope: &mut S,
+        context: SourcingContext<S::Timestamp>,
+    ) -> Vec<(
+        A,
+        AttributeConfig,
+        Stream<S, ((Value, Value), Duration, isize)>,
+    )> {
+        let mut demux =
+            OperatorBuilder::new(format!("ObjectStore({})", self.prefix), scope.clone());
+        let operator_info = demux.operator_info();
+        demux.set_notify(false);
+
+        // Order is very important here, because otherwise the
+        // capabilities won't match up with the output streams later
+        // on (when creating sessions). We stick to the order dictated
+        // by the schema.
+        let mut wrappers = Vec::with_capacity(self.schema.len());
+        let mut streams = Vec::with_capacity(self.schema.len());
+
+        for _ in self.schema.iter() {
+            let (wrapper, stream) = demux.new_output();
+            wrappers.push(wrapper);
+            streams.push(stream);
+        }
+
+        let store = self.store.clone();
+        let prefix = self.prefix.clone();
+        let format = self.format.clone();
+        let schema = self.schema.clone();
+        let total_fuel = self.fuel.unwrap_or(256);
+
+        // Grab scheduler handle for deferred re-activation.
+        let scheduler = context.scheduler;
+        let t0 = context.t0;
+        let interval = self.interval.unwrap_or(Duration::from_millis(10));
+        let poll_interval = context.poll_interval.clone();
+
+        demux.build(move |mut capabilities| {
+            let activator = Rc::new(scope.activator_for(&operator_info.address[..]));
+
+            let worker_index = scope.index();
+            let num_workers = scope.peers();
+
+            // Each worker reads a disjoint subset of all objects.
+            let mut keys = list(&store, &prefix)
+                .expect("failed to list objects")
+                .into_iter()
+                .enumerate()
+                .filter(|(idx, _key)| idx % num_workers == worker_index)
+                .map(|(_idx, key)| key)
+                .collect::<Vec<String>>();
+
+            keys.reverse();
+
+            let mut records: Option<Records> = None;
+            let mut num_objects_read = 0;
+
+            move |_frontiers| {
+                if capabilities.is_empty() {
+                    return;
+                }
+
+                if records.is_none() {
+                    match keys.pop() {
+                        None => {
+                            info!("[W{}] read {} objects", worker_index, num_objects_read);
+                            capabilities.drain(..);
+                            return;
+                        }
+                        Some(key) => match open(&store, &key) {
+                            Ok(reader) => {
+                                debug!("[W{}] reading {}", worker_index, key);
+                                records = Some(Records::new(&format, reader));
+                            }
+                            Err(error) => {
+                                error!("[W{}] failed to open {}: {}", worker_index, key, error);
+                                activator.activate();
+                                return;
+                            }
+                        },
+                    }
+                }
+
+                let mut handles = Vec::with_capacity(schema.len());
+                for wrapper in wrappers.iter_mut() {
+                    handles.push(wrapper.activate());
+                }
+
+                let mut sessions = Vec::with_capacity(schema.len());
+                for (idx, handle) in handles.iter_mut().enumerate() {
+                    sessions.push(handle.session(&capabilities[idx]));
+                }
+
+                let time = capabilities[0].time().clone();
+                let mut exhausted = false;
+
+                for _ in 0..total_fuel {
+                    match records.as_mut().and_then(|r| r.next(&format, &schema)) {
+                        None => {
+                            exhausted = true;
+                            break;
+                        }
+                        Some(Err(error)) => warn!("[W{}] invalid record: {}", worker_index, error),
+                        Some(Ok(datoms)) => {
+                            for (idx, datom) in datoms.into_iter() {
+                                sessions[idx].give((datom, time, 1));
+                            }
+                        }
+                    }
+                }
+
+                drop(sessions);
+                drop(handles);
+
+                if exhausted {
+                    // The object is complete, the next one starts a
+                    // new epoch.
+                    records = None;
+                    num_objects_read += 1;
+
+                    let time = std::cmp::max(
+                        Instant::now().duration_since(t0),
+                        time + Duration::from_nanos(1),
+                    );
+
+                    for cap in capabilities.iter_mut() {
+                        cap.downgrade(&time);
+                    }
+                }
+
+                // Notify the server that we want to be scheduled again soon
+                scheduler
+                    .upgrade()
+                    .unwrap()
+                    .borrow_mut()
+                    .realtime
+                    .schedule_after(
+                        poll_interval.get().unwrap_or(interval),
+                        Rc::downgrade(&activator),
+                    );
+            }
+        });
+
+        let mut out = Vec::with_capacity(streams.len());
+        for (idx, stream) in streams.drain(..).enumerate() {
+            let aid = self.schema[idx].0.clone();
+            out.push((
+                aid,
+                AttributeConfig::real_time(InputSemantics::Distinct),
+                stream,
+            ));
+        }
+
+        out
+    }
+}
@@ -0,0 +1,275 @@
+//! Operator and utilities to source change data from PostgreSQL via
+//! logical replication.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+use timely::dataflow::operators::generic::builder_rc::OperatorBuilder;
+use timely::dataflow::{Scope, Stream};
+
+use postgres::{Connection, TlsMode};
+
+use crate::sources::json_file::to_value;
+use crate::sources::{Sourceable, SourcingContext};
+use crate::{AsAid, Value};
+use crate::{AttributeConfig, InputSemantics};
+
+/// A logical replication slot using the `wal2json` output plugin,
+/// e.g. as created via
+/// `SELECT pg_create_logical_replication_slot('3df', 'wal2json')`.
+///
+/// Row inserts are mapped to assertions, deletes to retractions,
+/// and updates to both. Changes are timestamped with the log
+/// sequence number (LSN) of the transaction they were committed in,
+/// s.t. each database transaction becomes visible atomically. Tables
+/// must use `REPLICA IDENTITY FULL`, otherwise the values retracted
+/// by updates and deletes are unknown.
+///
+/// Only the first worker reads from the slot. As LSNs only advance
+/// with database writes, the domain frontier is held back while the
+/// database is idle.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct PostgresCdc<A: AsAid> {
+    /// Connection string, e.g. "postgres://user@localhost/db".
+    pub url: String,
+    /// Name of the replication slot to consume.
+    pub slot: String,
+    /// Name of the column identifying rows in all tables.
+    pub eid_column: String,
+    /// Maps attributes to the table and column they are sourced
+    /// from.
+    pub schema: Vec<(A, (String, String))>,
+    /// Maximum number of changes consumed per activation.
+    pub fuel: Option<usize>,
+    /// Scheduling interval.
+    pub interval: Option<Duration>,
+}
+
+/// Parses a textual LSN (e.g. "16/B374D848") into its 64-bit
+/// position.
+fn parse_lsn(lsn: &str) -> Option<u64> {
+    let mut parts = lsn.split('/');
+    let hi = u64::from_str_radix(parts.next()?, 16).ok()?;
+    let lo = u64::from_str_radix(parts.next()?, 16).ok()?;
+
+    Some((hi << 32) | lo)
+}
+
+/// Extracts the row identifier and column values from a list of
+/// wal2json column objects.
+fn row(
+    columns: Option<&serde_json::Value>,
+    eid_column: &str,
+) -> Option<(u64, HashMap<String, Value>)> {
+    let mut eid = None;
+    let mut values = HashMap::new();
+
+    for column in columns?.as_array()? {
+        let name = column.get("name")?.as_str()?;
+        let value = column.get("value")?;
+
+        if name == eid_column {
+            eid = value
+                .as_u64()
+                .or_else(|| value.as_str().and_then(|s| s.parse::<u64>().ok()));
+        }
+
+        if let Some(v) = to_value(value) {
+            values.insert(name.to_string(), v);
+        }
+    }
+
+    Some((eid?, values))
+}
+
+impl<A: AsAid, S: Scope<Timestamp = Duration>> Sourceable<A, S> for PostgresCdc<A> {
+    fn source(
+        &self,
+        scope: &mut S,
+        context: SourcingContext<S::Timestamp>,
+    ) -> Vec<(
+        A,
+        AttributeConfig,
+        Stream<S, ((Value, Value), Duration, isize)>,
+    )> {
+        let mut demux = OperatorBuilder::new(format!("PostgresCdc({})", self.slot), scope.clone());
+        let operator_info = demux.operator_info();
+        demux.set_notify(false);
+
+        // Order is very important here, because otherwise the
+        // capabilities won't match up with the output streams later
+        // on (when creating sessions). We stick to the order dictated
+        // by the schema.
+        let mut wrappers = Vec::with_capacity(self.schema.len());
+        let mut streams = Vec::with_capacity(self.schema.len());
+
+        for _ in self.schema.iter() {
+            let (wrapper, stream) = demux.new_output();
+            wrappers.push(wrapper);
+            streams.push(stream);
+        }
+
+        let url = self.url.clone();
+        let slot = self.slot.clone();
+        let eid_column = self.eid_column.clone();
+        let schema = self.schema.clone();
+        let total_fuel = self.fuel.unwrap_or(1024);
+
+        // Grab scheduler handle for deferred re-activation.
+        let scheduler = context.scheduler;
+        let interval = self.interval.unwrap_or(Duration::from_secs(1));
+        let poll_interval = context.poll_interval.clone();
+
+        demux.build(move |mut capabilities| {
+            let activator = Rc::new(scope.activator_for(&operator_info.address[..]));
+
+            let worker_index = scope.index();
+
+            let connection = if worker_index == 0 {
+                Some(Connection::connect(url.as_str(), TlsMode::None).expect("failed to connect"))
+            } else {
+                capabilities.drain(..);
+                None
+            };
+
+            // Changes of the transaction currently being read, which
+            // are released once its commit is seen.
+            let mut pending: Vec<(usize, (Value, Value), isize)> = Vec::new();
+
+            move |_frontiers| {
+                let connection = match connection {
+                    None => return,
+                    Some(ref connection) => connection,
+                };
+
+                // Changes are removed from the slot as they are
+                // read. Incomplete transactions are kept pending
+                // until their commit is read in a later activation.
+                let query = "SELECT lsn::text, data \
+                             FROM pg_logical_slot_get_changes($1, NULL, $2, 'format-version', '2')";
+
+                let rows = match connection.query(query, &[&slot, &(total_fuel as i32)]) {
+                    Ok(rows) => rows,
+                    Err(error) => {
+                        error!(
+                            "[W{}] failed to read from slot {}: {}",
+                            worker_index, slot, error
+                        );
+
+                        scheduler
+                            .upgrade()
+                            .unwrap()
+                            .borrow_mut()
+                            .realtime
+                            .schedule_after(
+                                poll_interval.get().unwrap_or(interval),
+                                Rc::downgrade(&activator),
+                            );
+
+                        return;
+                    }
+                };
+
+                let mut handles = Vec::with_capacity(schema.len());
+                for wrapper in wrappers.iter_mut() {
+                    handles.push(wrapper.activate());
+                }
+
+                let mut sessions = Vec::with_capacity(schema.len());
+                for (idx, handle) in handles.iter_mut().enumerate() {
+                    sessions.push(handle.session(&capabilities[idx]));
+                }
+
+                let mut commit_lsn = None;
+
+                for change in rows.iter() {
+                    let lsn: String = change.get(0);
+                    let data: String = change.get(1);
+
+                    let message: serde_json::Value = match serde_json::from_str(&data) {
+                        Ok(message) => message,
+                        Err(error) => {
+                            warn!("[W{}] invalid change at {}: {}", worker_index, lsn, error);
+                            continue;
+                        }
+                    };
+
+                    let action = message.get("action").and_then(|a| a.as_str()).unwrap_or("");
+                    let table = message.get("table").and_then(|t| t.as_str()).unwrap_or("");
+
+                    let changes = match action {
+                        "I" => vec![(message.get("columns"), 1)],
+                        "U" => vec![(message.get("identity"), -1), (message.get("columns"), 1)],
+                        "D" => vec![(message.get("identity"), -1)],
+                        _ => Vec::new(),
+                    };
+
+                    for (columns, diff) in changes.into_iter() {
+                        if let Some((eid, mut values)) = row(columns, &eid_column) {
+                            for (idx, (_aid, (from_table, column))) in schema.iter().enumerate() {
+                                if from_table == table {
+                                    if let Some(v) = values.remove(column) {
+                                        pending.push((idx, (Value::Eid(eid), v), diff));
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    if action == "C" {
+                        match parse_lsn(&lsn) {
+                            None => error!("[W{}] invalid lsn {}", worker_index, lsn),
+                            Some(position) => {
+                                let time = Duration::from_nanos(position);
+
+                                for (idx, datom, diff) in pending.drain(..) {
+                                    sessions[idx].give((datom, time, diff));
+                                }
+
+                                commit_lsn = Some(position);
+                            }
+                        }
+                    }
+                }
+
+                drop(sessions);
+                drop(handles);
+
+                // All transactions up to the last commit are complete.
+                if let Some(position) = commit_lsn {
+                    trace!("[W{}] read {} up to {}", worker_index, slot, position);
+
+                    let time = Duration::from_nanos(position + 1);
+
+                    for cap in capabilities.iter_mut() {
+                        cap.downgrade(&time);
+                    }
+                }
+
+                if rows.len() >= total_fuel {
+                    activator.activate();
+                } else {
+                    // Notify the server that we want to be scheduled again soon
+                    scheduler
+                        .upgrade()
+                        .unwrap()
+                        .borrow_mut()
+                        .realtime
+                        .schedule_after(
+                            poll_interval.get().unwrap_or(interval),
+                            Rc::downgrade(&activator),
+                        );
+                }
+            }
+        });
+
+        let mut out = Vec::with_capacity(streams.len());
+        for (idx, stream) in streams.drain(..).enumerate() {
+            let aid = self.schema[idx].0.clone();
+            out.push((aid, AttributeConfig::real_time(InputSemantics::Raw), stream));
+        }
+
+        out
+    }
+}
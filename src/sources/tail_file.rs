@@ -0,0 +1,315 @@
+//! Operator and utilities to follow an append-only file, ingesting
+//! newline-delimited json objects as they are written.
+
+use std::fs::{self, File, Metadata};
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use timely::dataflow::operators::generic::builder_rc::OperatorBuilder;
+use timely::dataflow::{Scope, Stream};
+
+use crate::sources::checkpoint::{Checkpointer, Positions, SourceCheckpoint};
+use crate::sources::json_file::{attribute_name, lookup, to_value};
+use crate::sources::{Sourceable, SourcingContext};
+use crate::{AsAid, Value};
+use crate::{AttributeConfig, InputSemantics};
+
+/// A local file that is followed like `tail -f` would. Each complete
+/// line is expected to hold a JSON object, whose fields are ingested
+/// the same way `JsonFile` ingests them. Only the first worker reads
+/// the file.
+///
+/// The source never completes. If the file is replaced (e.g. by log
+/// rotation) or truncated, it is re-opened and read from its
+/// beginning. If checkpointing is enabled, reading resumes from the
+/// byte offset reached before the last shutdown.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct TailFile {
+    /// Path to the followed file on the first workers local filesystem.
+    pub path: String,
+    /// Path to the field holding the entity id.
+    pub eid_path: String,
+    /// Paths to the fields to ingest, with nested fields separated
+    /// by dots.
+    pub fields: Vec<String>,
+    /// Batch size.
+    pub fuel: Option<usize>,
+    /// Scheduling interval.
+    pub interval: Option<Duration>,
+}
+
+/// Identifies a file across renames, s.t. rotation can be detected.
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ino()
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &Metadata) -> u64 {
+    0
+}
+
+/// The file currently being followed.
+struct Tail {
+    reader: BufReader<File>,
+    id: u64,
+    offset: u64,
+    partial: String,
+}
+
+impl Tail {
+    fn open(path: &str, offset: u64) -> Option<Self> {
+        let mut file = File::open(path).ok()?;
+        let metadata = file.metadata().ok()?;
+
+        // A persisted offset past the end means that the file has
+        // been replaced since.
+        let offset = if offset > metadata.len() { 0 } else { offset };
+
+        file.seek(SeekFrom::Start(offset)).ok()?;
+
+        Some(Tail {
+            reader: BufReader::new(file),
+            id: file_id(&metadata),
+            offset,
+            partial: String::new(),
+        })
+    }
+
+    /// Returns the next complete line, if any. Incomplete lines are
+    /// buffered until the rest of them has been written.
+    fn next_line(&mut self) -> Option<String> {
+        match self.reader.read_line(&mut self.partial) {
+            Ok(0) => None,
+            Ok(_) if !self.partial.ends_with('\n') => None,
+            Ok(_) => {
+                let line = std::mem::replace(&mut self.partial, String::new());
+                self.offset += line.len() as u64;
+                Some(line)
+            }
+            Err(error) => {
+                error!("failed to read line: {}", error);
+                None
+            }
+        }
+    }
+
+    /// Returns true iff the path no longer refers to the file being
+    /// read, or if it was truncated.
+    fn is_replaced(&self, path: &str) -> bool {
+        match fs::metadata(path) {
+            Err(_) => false,
+            Ok(metadata) => {
+                file_id(&metadata) != self.id
+                    || metadata.len() < self.offset + self.partial.len() as u64
+            }
+        }
+    }
+}
+
+impl SourceCheckpoint for TailFile {
+    fn checkpoint_name(&self) -> String {
+        format!("tail/{}", self.path)
+    }
+}
+
+impl<A: AsAid, S: Scope<Timestamp = Duration>> Sourceable<A, S> for TailFile {
+    fn source(
+        &self,
+        scope: &mut S,
+        context: SourcingContext<S::Timestamp>,
+    ) -> Vec<(
+        A,
+        AttributeConfig,
+        Stream<S, ((Value, Value), Duration, isize)>,
+    )> {
+        let filename = self.path.clone();
+
+        let mut demux = OperatorBuilder::new(format!("TailFile({})", filename), scope.clone());
+        let operator_info = demux.operator_info();
+        demux.set_notify(false);
+
+        // Order is very important here, because otherwise the
+        // capabilities won't match up with the output streams later
+        // on (when creating sessions). We stick to the order dictated
+        // by the fields.
+        let mut wrappers = Vec::with_capacity(self.fields.len());
+        let mut streams = Vec::with_capacity(self.fields.len());
+
+        for _ in self.fields.iter() {
+            let (wrapper, stream) = demux.new_output();
+            wrappers.push(wrapper);
+            streams.push(stream);
+        }
+
+        let mut checkpointer = context.checkpoints.clone().map(|store| {
+            Checkpointer::new(self.checkpoint_name(), store, context.domain_probe.clone())
+        });
+        let fields = self.fields.clone();
+        let eid_path = self.eid_path.clone();
+        let total_fuel = self.fuel.unwrap_or(256);
+
+        // Grab scheduler handle for deferred re-activation.
+        let scheduler = context.scheduler;
+        let t0 = context.t0;
+        let interval = self.interval.unwrap_or(Duration::from_secs(1));
+        let poll_interval = context.poll_interval.clone();
+
+        demux.build(move |mut capabilities| {
+            let activator = Rc::new(scope.activator_for(&operator_info.address[..]));
+
+            let worker_index = scope.index();
+
+            if worker_index != 0 {
+                capabilities.drain(..);
+            }
+
+            let resume_offset = checkpointer
+                .as_ref()
+                .and_then(|checkpointer| checkpointer.restore().get("offset").cloned())
+                .unwrap_or(0);
+
+            let mut tail: Option<Tail> = None;
+            let mut num_lines_read = 0;
+
+            move |_frontiers| {
+                if capabilities.is_empty() {
+                    return;
+                }
+
+                let mut handles = Vec::with_capacity(fields.len());
+                for wrapper in wrappers.iter_mut() {
+                    handles.push(wrapper.activate());
+                }
+
+                let mut sessions = Vec::with_capacity(fields.len());
+                for (idx, handle) in handles.iter_mut().enumerate() {
+                    sessions.push(handle.session(&capabilities[idx]));
+                }
+
+                let time = Instant::now().duration_since(t0);
+                let mut fuel = total_fuel;
+                let mut consumed = false;
+
+                while fuel > 0 {
+                    if tail.is_none() {
+                        let offset = if num_lines_read == 0 {
+                            resume_offset
+                        } else {
+                            0
+                        };
+                        tail = Tail::open(&filename, offset);
+                    }
+
+                    let line = match tail.as_mut() {
+                        None => break,
+                        Some(tail) => tail.next_line(),
+                    };
+
+                    match line {
+                        None => {
+                            // We have caught up with the followed
+                            // file. The rest of a rotated file has
+                            // been read as well at this point.
+                            if tail
+                                .as_ref()
+                                .map(|t| t.is_replaced(&filename))
+                                .unwrap_or(false)
+                            {
+                                info!("[W{}] {} was replaced, re-opening", worker_index, filename);
+                                tail = Tail::open(&filename, 0);
+                            } else {
+                                break;
+                            }
+                        }
+                        Some(line) => {
+                            consumed = true;
+                            num_lines_read += 1;
+                            fuel -= 1;
+
+                            let obj: serde_json::Value = match serde_json::from_str(&line) {
+                                Ok(obj) => obj,
+                                Err(error) => {
+                                    warn!("[W{}] invalid object: {}", worker_index, error);
+                                    continue;
+                                }
+                            };
+
+                            match lookup(&obj, &eid_path).and_then(|e| e.as_u64()) {
+                                None => warn!("[W{}] object has no eid", worker_index),
+                                Some(eid) => {
+                                    for (idx, path) in fields.iter().enumerate() {
+                                        if let Some(v) = lookup(&obj, path).and_then(to_value) {
+                                            sessions[idx].give(((Value::Eid(eid), v), time, 1));
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+
+                drop(sessions);
+                drop(handles);
+
+                if consumed {
+                    trace!(
+                        "[W{}] read {} lines from {}",
+                        worker_index,
+                        num_lines_read,
+                        filename
+                    );
+                }
+
+                // Incorporate processing time in downgrade
+                let time = Instant::now().duration_since(t0);
+
+                for cap in capabilities.iter_mut() {
+                    cap.downgrade(&time);
+                }
+
+                if let Some(ref mut checkpointer) = checkpointer {
+                    match tail.as_ref() {
+                        Some(tail) if consumed => {
+                            let mut checkpoint = Positions::new();
+                            checkpoint.insert("offset".to_string(), tail.offset);
+
+                            checkpointer.record(time + Duration::from_nanos(1), checkpoint);
+                        }
+                        _ => {}
+                    }
+
+                    checkpointer.persist();
+                }
+
+                if fuel == 0 {
+                    activator.activate();
+                } else {
+                    // Notify the server that we want to be scheduled again soon
+                    scheduler
+                        .upgrade()
+                        .unwrap()
+                        .borrow_mut()
+                        .realtime
+                        .schedule_after(
+                            poll_interval.get().unwrap_or(interval),
+                            Rc::downgrade(&activator),
+                        );
+                }
+            }
+        });
+
+        let mut out = Vec::with_capacity(streams.len());
+        for (idx, stream) in streams.drain(..).enumerate() {
+            out.push((
+                attribute_name(&self.fields[idx]),
+                AttributeConfig::real_time(InputSemantics::Distinct),
+                stream,
+            ));
+        }
+
+        out
+    }
+}
@@ -81,6 +81,8 @@ where
 
         let schedule__started = A::from("schedule/started?");
 
+        let timely__event__messages__records = A::from("timely.event.messages/records");
+
         demux.build(move |_capability| {
             move |_frontiers| {
                 let mut handles = HashMap::with_capacity(num_interests);
@@ -178,9 +180,17 @@ where
                                     .get_mut(&schedule__started)
                                     .map(|s| s.give(((eid, is_started), time, 1)));
                             }
-                            TimelyEvent::Messages(_x) => {
-                                // @TODO
-                                // (MessagesChannel, (x.seq_noValue::Usize(x.channel), Value::Bool(x.is_send), Value::Usize(x.source), Value::Usize(x.target), Value::Usize(x.seq_no), Value::Usize(x.length)
+                            TimelyEvent::Messages(x) => {
+                                // Every message is both sent and
+                                // received, so we only count sends.
+                                if x.is_send {
+                                    let channel = Eid((x.channel as u64).into());
+                                    let records = Value::Number(x.length as i64);
+
+                                    sessions
+                                        .get_mut(&timely__event__messages__records)
+                                        .map(|s| s.give(((channel, records), time, 1)));
+                                }
                             }
                             _ => {}
                         }
@@ -189,12 +199,22 @@ where
             }
         });
 
+        let messages__records = A::from("timely.event.messages/records");
+
         self.attributes
             .iter()
             .map(|aid| {
+                // Message sizes are meant to be aggregated, so
+                // identical ones must not be collapsed.
+                let semantics = if *aid == messages__records {
+                    InputSemantics::Raw
+                } else {
+                    InputSemantics::Distinct
+                };
+
                 (
                     aid.clone(),
-                    AttributeConfig::real_time(InputSemantics::Distinct),
+                    AttributeConfig::real_time(semantics),
                     streams.remove(aid).unwrap(),
                 )
             })
@@ -0,0 +1,206 @@
+//! Operator and utilities to replay Datomic-style transaction logs.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::rc::Rc;
+use std::time::Duration;
+
+use timely::dataflow::operators::generic::builder_rc::OperatorBuilder;
+use timely::dataflow::{Scope, Stream};
+
+use crate::sources::{decode_json, Sourceable, SourcingContext};
+use crate::{AsAid, Eid, Value};
+use crate::{AttributeConfig, InputSemantics};
+
+/// A local file containing one JSON-encoded transaction report per
+/// line, as exported from Datomic or Datahike, e.g.
+///
+/// `{"tx": 13194139534312, "data": [[17592186045418, ":person/name", "Alice", 13194139534312, true]]}`
+///
+/// Each transaction is replayed at a time derived from its
+/// transaction id (`tx` happens at `tx` nanoseconds), s.t. its
+/// datoms become visible atomically and in log order. Only datoms on
+/// attributes in the schema are ingested. Only the first worker reads
+/// the log.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct TxLog<A: AsAid> {
+    /// Path to the log on the first workers local filesystem.
+    pub path: String,
+    /// Specifies the attributes to ingest and their value types.
+    pub schema: Vec<(A, Value)>,
+    /// Maximum number of transactions replayed per activation.
+    pub fuel: Option<usize>,
+    /// Scheduling interval.
+    pub interval: Option<Duration>,
+}
+
+impl<A: AsAid, S: Scope<Timestamp = Duration>> Sourceable<A, S> for TxLog<A> {
+    fn source(
+        &self,
+        scope: &mut S,
+        context: SourcingContext<S::Timestamp>,
+    ) -> Vec<(
+        A,
+        AttributeConfig,
+        Stream<S, ((Value, Value), Duration, isize)>,
+    )> {
+        let filename = self.path.clone();
+
+        let mut demux = OperatorBuilder::new(format!("TxLog({})", filename), scope.clone());
+        let operator_info = demux.operator_info();
+        demux.set_notify(false);
+
+        // Order is very important here, because otherwise the
+        // capabilities won't match up with the output streams later
+        // on (when creating sessions). We stick to the order dictated
+        // by the schema.
+        let mut wrappers = Vec::with_capacity(self.schema.len());
+        let mut streams = Vec::with_capacity(self.schema.len());
+
+        for _ in self.schema.iter() {
+            let (wrapper, stream) = demux.new_output();
+            wrappers.push(wrapper);
+            streams.push(stream);
+        }
+
+        // Maps attribute names to their output and type hint.
+        let outputs: HashMap<String, (usize, Value)> = self
+            .schema
+            .iter()
+            .enumerate()
+            .map(|(idx, (aid, type_hint))| (aid.to_string(), (idx, type_hint.clone())))
+            .collect();
+
+        let total_fuel = self.fuel.unwrap_or(256);
+
+        // Grab scheduler handle for deferred re-activation.
+        let scheduler = context.scheduler;
+        let interval = self.interval.unwrap_or(Duration::from_millis(100));
+
+        demux.build(move |mut capabilities| {
+            let activator = Rc::new(scope.activator_for(&operator_info.address[..]));
+
+            let worker_index = scope.index();
+
+            let mut iterator = if worker_index == 0 {
+                let file = File::open(&filename).expect("failed to open log");
+                Some(BufReader::new(file).lines().peekable())
+            } else {
+                capabilities.drain(..);
+                None
+            };
+
+            let mut num_txs_read = 0;
+            let mut last_tx = 0;
+
+            move |_frontiers| {
+                let iterator = match iterator {
+                    None => return,
+                    Some(ref mut iterator) => iterator,
+                };
+
+                if iterator.peek().is_none() {
+                    info!("[W{}] replayed {} transactions", worker_index, num_txs_read);
+                    capabilities.drain(..);
+                    return;
+                }
+
+                let mut handles = Vec::with_capacity(outputs.len());
+                for wrapper in wrappers.iter_mut() {
+                    handles.push(wrapper.activate());
+                }
+
+                let mut sessions = Vec::with_capacity(outputs.len());
+                for (idx, handle) in handles.iter_mut().enumerate() {
+                    sessions.push(handle.session(&capabilities[idx]));
+                }
+
+                for readline in iterator.by_ref().take(total_fuel) {
+                    let line = readline.expect("read error");
+
+                    if line.is_empty() {
+                        continue;
+                    }
+
+                    let report: serde_json::Value = match serde_json::from_str(&line) {
+                        Ok(report) => report,
+                        Err(error) => {
+                            warn!("[W{}] invalid transaction report: {}", worker_index, error);
+                            continue;
+                        }
+                    };
+
+                    let tx = match report.get("tx").and_then(|tx| tx.as_u64()) {
+                        None => {
+                            warn!("[W{}] transaction report without tx", worker_index);
+                            continue;
+                        }
+                        Some(tx) if tx < last_tx => {
+                            warn!(
+                                "[W{}] skipping out-of-order transaction {}",
+                                worker_index, tx
+                            );
+                            continue;
+                        }
+                        Some(tx) => tx,
+                    };
+
+                    let time = Duration::from_nanos(tx);
+
+                    let data = report.get("data").and_then(|data| data.as_array());
+                    for datom in data.into_iter().flatten() {
+                        let datom = match datom.as_array() {
+                            Some(datom) if datom.len() >= 3 => datom,
+                            _ => continue,
+                        };
+
+                        let e = datom[0].as_u64();
+                        let a = datom[1].as_str().and_then(|a| outputs.get(a));
+                        let added = datom
+                            .get(4)
+                            .and_then(|added| added.as_bool())
+                            .unwrap_or(true);
+
+                        if let (Some(e), Some((idx, type_hint))) = (e, a) {
+                            if let Some(v) = decode_json(&datom[2], type_hint) {
+                                let diff = if added { 1 } else { -1 };
+                                sessions[*idx].give(((Value::Eid(e as Eid), v), time, diff));
+                            }
+                        }
+                    }
+
+                    last_tx = tx;
+                    num_txs_read += 1;
+                }
+
+                drop(sessions);
+                drop(handles);
+
+                // All transactions up to the last one read are
+                // complete.
+                let time = Duration::from_nanos(last_tx + 1);
+
+                for cap in capabilities.iter_mut() {
+                    cap.downgrade(&time);
+                }
+
+                // Notify the server that we want to be scheduled again soon
+                scheduler
+                    .upgrade()
+                    .unwrap()
+                    .borrow_mut()
+                    .realtime
+                    .schedule_after(interval, Rc::downgrade(&activator));
+            }
+        });
+
+        let mut out = Vec::with_capacity(streams.len());
+        for (idx, stream) in streams.drain(..).enumerate() {
+            let aid = self.schema[idx].0.clone();
+            out.push((aid, AttributeConfig::real_time(InputSemantics::Raw), stream));
+        }
+
+        out
+    }
+}
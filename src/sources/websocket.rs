@@ -0,0 +1,241 @@
+//! Operator and utilities to source data from WebSocket endpoints.
+
+use std::io::ErrorKind;
+use std::net::TcpStream;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use timely::dataflow::operators::generic::builder_rc::OperatorBuilder;
+use timely::dataflow::{Scope, Stream};
+
+use tungstenite::{Message, WebSocket};
+use url::Url;
+
+use crate::sources::decoders::{Decoder, Decoding};
+use crate::sources::{Sourceable, SourcingContext};
+use crate::{AsAid, Value};
+use crate::{AttributeConfig, InputSemantics};
+
+/// An external WebSocket endpoint, whose text and binary messages
+/// are decoded using the configured wire format. Only the first
+/// worker connects.
+///
+/// Lost connections are re-established with exponential backoff. If
+/// a resume field is configured, the most recent token seen in that
+/// field (of messages that are JSON objects) is passed along when re-connecting (as the `resume_token`
+/// query parameter), s.t. the endpoint can continue where it left
+/// off.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct WebSocketClient<A: AsAid> {
+    /// Endpoint to connect to, e.g. "ws://localhost:6262".
+    pub url: String,
+    /// Wire format of the messages.
+    pub decoder: Decoding<A>,
+    /// Name of the field holding a resume token.
+    pub resume_field: Option<String>,
+    /// Token to pass along on the first connection.
+    pub resume_token: Option<String>,
+    /// Delay before the first re-connection attempt.
+    pub min_backoff: Option<Duration>,
+    /// Maximum delay between re-connection attempts.
+    pub max_backoff: Option<Duration>,
+    /// Batch size.
+    pub fuel: Option<usize>,
+    /// Scheduling interval.
+    pub interval: Option<Duration>,
+}
+
+/// Opens a connection, passing along the resume token if there is
+/// one.
+fn connect(url: &str, resume_token: Option<&String>) -> Result<WebSocket<TcpStream>, String> {
+    let mut url = Url::parse(url).map_err(|error| error.to_string())?;
+
+    if let Some(token) = resume_token {
+        url.query_pairs_mut().append_pair("resume_token", token);
+    }
+
+    let (socket, _response) = tungstenite::connect(url).map_err(|error| error.to_string())?;
+
+    socket
+        .get_ref()
+        .set_nonblocking(true)
+        .map_err(|error| error.to_string())?;
+
+    Ok(socket)
+}
+
+impl<A: AsAid, S: Scope<Timestamp = Duration>> Sourceable<A, S> for WebSocketClient<A> {
+    fn source(
+        &self,
+        scope: &mut S,
+        context: SourcingContext<S::Timestamp>,
+    ) -> Vec<(
+        A,
+        AttributeConfig,
+        Stream<S, ((Value, Value), Duration, isize)>,
+    )> {
+        let url = self.url.clone();
+
+        let mut demux = OperatorBuilder::new(format!("WebSocket({})", url), scope.clone());
+        let operator_info = demux.operator_info();
+        demux.set_notify(false);
+
+        // Order is very important here, because otherwise the
+        // capabilities won't match up with the output streams later
+        // on (when creating sessions). We stick to the order dictated
+        // by the decoder.
+        let attributes = self.decoder.attributes();
+        let mut wrappers = Vec::with_capacity(attributes.len());
+        let mut streams = Vec::with_capacity(attributes.len());
+
+        for _ in attributes.iter() {
+            let (wrapper, stream) = demux.new_output();
+            wrappers.push(wrapper);
+            streams.push(stream);
+        }
+
+        let decoder = self.decoder.clone();
+        let num_outputs = attributes.len();
+        let resume_field = self.resume_field.clone();
+        let mut resume_token = self.resume_token.clone();
+        let min_backoff = self.min_backoff.unwrap_or(Duration::from_millis(100));
+        let max_backoff = self.max_backoff.unwrap_or(Duration::from_secs(30));
+        let total_fuel = self.fuel.unwrap_or(256);
+
+        // Grab scheduler handle for deferred re-activation.
+        let scheduler = context.scheduler;
+        let t0 = context.t0;
+        let interval = self.interval.unwrap_or(Duration::from_millis(100));
+
+        demux.build(move |mut capabilities| {
+            let activator = Rc::new(scope.activator_for(&operator_info.address[..]));
+
+            let worker_index = scope.index();
+
+            if worker_index != 0 {
+                capabilities.drain(..);
+            }
+
+            let mut socket: Option<WebSocket<TcpStream>> = None;
+            let mut backoff = min_backoff;
+            let mut next_attempt = Instant::now();
+
+            move |_frontiers| {
+                if capabilities.is_empty() {
+                    return;
+                }
+
+                if socket.is_none() && Instant::now() >= next_attempt {
+                    match connect(&url, resume_token.as_ref()) {
+                        Ok(connected) => {
+                            info!("[W{}] connected to {}", worker_index, url);
+                            socket = Some(connected);
+                            backoff = min_backoff;
+                        }
+                        Err(error) => {
+                            warn!(
+                                "[W{}] failed to connect to {}, retrying in {:?}: {}",
+                                worker_index, url, backoff, error
+                            );
+                            next_attempt = Instant::now() + backoff;
+                            backoff = std::cmp::min(backoff * 2, max_backoff);
+                        }
+                    }
+                }
+
+                let mut handles = Vec::with_capacity(num_outputs);
+                for wrapper in wrappers.iter_mut() {
+                    handles.push(wrapper.activate());
+                }
+
+                let mut sessions = Vec::with_capacity(num_outputs);
+                for (idx, handle) in handles.iter_mut().enumerate() {
+                    sessions.push(handle.session(&capabilities[idx]));
+                }
+
+                let time = Instant::now().duration_since(t0);
+                let mut fuel = total_fuel;
+
+                while fuel > 0 {
+                    let message = match socket.as_mut().map(|socket| socket.read_message()) {
+                        None => break,
+                        Some(Ok(message)) => message,
+                        Some(Err(tungstenite::Error::Io(ref error)))
+                            if error.kind() == ErrorKind::WouldBlock =>
+                        {
+                            break;
+                        }
+                        Some(Err(error)) => {
+                            warn!("[W{}] lost connection to {}: {}", worker_index, url, error);
+                            socket = None;
+                            next_attempt = Instant::now() + backoff;
+                            break;
+                        }
+                    };
+
+                    let bytes = match message {
+                        Message::Text(text) => text.into_bytes(),
+                        Message::Binary(bytes) => bytes,
+                        _ => continue,
+                    };
+
+                    fuel -= 1;
+
+                    if let Some(field) = resume_field.as_ref() {
+                        let token = serde_json::from_slice::<serde_json::Value>(&bytes)
+                            .ok()
+                            .and_then(|obj| {
+                                obj.get(field)
+                                    .and_then(|t| t.as_str())
+                                    .map(|t| t.to_string())
+                            });
+
+                        if token.is_some() {
+                            resume_token = token;
+                        }
+                    }
+
+                    match decoder.decode(&bytes) {
+                        Err(error) => {
+                            warn!("[W{}] invalid message: {}", worker_index, error.message)
+                        }
+                        Ok(datoms) => {
+                            for (idx, datom, diff) in datoms.into_iter() {
+                                sessions[idx].give((datom, time, diff));
+                            }
+                        }
+                    }
+                }
+
+                drop(sessions);
+                drop(handles);
+
+                // Incorporate processing time in downgrade
+                let time = Instant::now().duration_since(t0);
+
+                for cap in capabilities.iter_mut() {
+                    cap.downgrade(&time);
+                }
+
+                if fuel == 0 {
+                    activator.activate();
+                } else {
+                    // Notify the server that we want to be scheduled again soon
+                    scheduler
+                        .upgrade()
+                        .unwrap()
+                        .borrow_mut()
+                        .realtime
+                        .schedule_after(interval, Rc::downgrade(&activator));
+                }
+            }
+        });
+
+        let mut out = Vec::with_capacity(streams.len());
+        for (aid, stream) in attributes.into_iter().zip(streams.drain(..)) {
+            out.push((aid, AttributeConfig::real_time(InputSemantics::Raw), stream));
+        }
+
+        out
+    }
+}
@@ -0,0 +1,219 @@
+//! Support for deterministic integration tests of queries, against a
+//! single-worker server running in-process.
+//!
+//! ```
+//! use declarative_dataflow::testing::TestServer;
+//! use declarative_dataflow::{AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+//!
+//! let mut server = TestServer::new();
+//!
+//! server
+//!     .create_attribute(":name", AttributeConfig::tx_time(InputSemantics::Raw))
+//!     .unwrap();
+//! server
+//!     .register(vec![Rule::named("names", Plan::match_a(0, ":name", 1))])
+//!     .unwrap();
+//! server.interest("names").unwrap();
+//!
+//! let t = server
+//!     .transact(vec![Datom::add(1, ":name", Value::from("Dipper"))])
+//!     .unwrap();
+//!
+//! server.expect_results("names", vec![(vec![Value::Eid(1), Value::from("Dipper")], t, 1)]);
+//! ```
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use timely::communication::allocator::Thread;
+use timely::worker::Worker;
+
+use crate::server::pagination::Page;
+use crate::server::{Configuration, Register, Server, Strategy};
+use crate::{Aid, AttributeConfig, Datom, Error, Rule, Value};
+
+/// A result tuple, along with the time it changed at and the change
+/// in its multiplicity.
+pub type ResultDiff = (Vec<Value>, u64, isize);
+
+/// A single-worker server driven by the calling thread. Every
+/// transaction is followed by a domain advance, and only returns
+/// once all relations of interest have caught up with it.
+pub struct TestServer {
+    worker: Worker<Thread>,
+    server: Server<Aid, u64, u64>,
+    results: HashMap<Aid, Rc<RefCell<Vec<ResultDiff>>>>,
+}
+
+impl TestServer {
+    /// Creates a server with the default configuration.
+    pub fn new() -> Self {
+        Self::with_config(Default::default())
+    }
+
+    /// Creates a server with the specified configuration.
+    pub fn with_config(config: Configuration) -> Self {
+        TestServer {
+            worker: Worker::new(Thread::new()),
+            server: Server::new(config),
+            results: HashMap::new(),
+        }
+    }
+
+    /// Provides access to the underlying server, for anything not
+    /// covered here.
+    pub fn server(&mut self) -> &mut Server<Aid, u64, u64> {
+        &mut self.server
+    }
+
+    /// Creates an attribute that can be transacted upon.
+    pub fn create_attribute(&mut self, name: &str, config: AttributeConfig) -> Result<(), Error> {
+        let server = &mut self.server;
+
+        self.worker
+            .dataflow::<u64, _, _>(|scope| server.create_attribute(scope, name, config))
+    }
+
+    /// Creates a query parameter that tuples can be bound to.
+    pub fn create_parameter(&mut self, name: &str) -> Result<(), Error> {
+        let server = &mut self.server;
+
+        self.worker
+            .dataflow::<u64, _, _>(|scope| server.create_parameter(scope, name))
+    }
+
+    /// Registers and publishes the specified rules.
+    pub fn register(&mut self, rules: Vec<Rule<Aid>>) -> Result<(), Error> {
+        let publish = rules.iter().map(|rule| rule.name.clone()).collect();
+
+        self.server.register(Register {
+            rules,
+            publish,
+            datalog: vec![],
+        })
+    }
+
+    /// Implements the named relation within the configured limits,
+    /// capturing its results from now on.
+    pub fn interest(&mut self, name: &str) -> Result<(), Error> {
+        self.capture(name, None, None)
+    }
+
+    /// Like `interest`, but implements the relation via the specified
+    /// strategy rather than the configured default.
+    pub fn interest_using(&mut self, name: &str, strategy: Strategy) -> Result<(), Error> {
+        self.capture(name, None, Some(strategy))
+    }
+
+    /// Like `interest`, but captures only the changes to the
+    /// specified page of results.
+    pub fn interest_page(&mut self, name: &str, page: Page) -> Result<(), Error> {
+        self.capture(name, Some(page), None)
+    }
+
+    fn capture(
+        &mut self,
+        name: &str,
+        page: Option<Page>,
+        strategy: Option<Strategy>,
+    ) -> Result<(), Error> {
+        let captured = Rc::new(RefCell::new(Vec::new()));
+        let sink = captured.clone();
+        let server = &mut self.server;
+        let limits = server.limits(None);
+
+        self.worker.dataflow::<u64, _, _>(|scope| {
+            server.check_limits(&name.to_string(), &limits)?;
+            if let Some(ref page) = page {
+                page.check()?;
+            }
+
+            let relation = server.interest_using(name.to_string(), scope, strategy)?;
+            let relation = server.enforce_limits(name.to_string(), &limits, relation);
+            let relation = match page {
+                None => relation,
+                Some(ref page) => page.paginate(relation),
+            };
+
+            relation
+                .inspect(move |x| sink.borrow_mut().push(x.clone()))
+                .probe_with(&mut server.probe);
+
+            Ok::<(), Error>(())
+        })?;
+
+        self.results.insert(name.to_string(), captured);
+        self.settle();
+
+        Ok(())
+    }
+
+    /// Transacts the specified datoms and advances the domain past
+    /// them, returning the time they were transacted at once all
+    /// relations of interest reflect them.
+    pub fn transact(&mut self, tx_data: Vec<Datom<Aid>>) -> Result<u64, Error> {
+        let t = *self.server.internal.epoch();
+
+        self.server.transact(tx_data, 0, 0)?;
+        self.server.advance_domain(None, t + 1)?;
+        self.settle();
+
+        Ok(t)
+    }
+
+    /// Returns and forgets all results of the named relation captured
+    /// so far, consolidated and ordered by time.
+    pub fn results(&mut self, name: &str) -> Vec<ResultDiff> {
+        let captured = match self.results.get(name) {
+            None => panic!("no interest in {} has been expressed", name),
+            Some(captured) => captured,
+        };
+
+        consolidate(captured.borrow_mut().drain(..).collect())
+    }
+
+    /// Asserts that the named relation changed exactly as specified
+    /// since its results were last inspected.
+    pub fn expect_results(&mut self, name: &str, expected: Vec<ResultDiff>) {
+        let results = self.results(name);
+
+        assert_eq!(
+            results,
+            consolidate(expected),
+            "unexpected results for {}",
+            name
+        );
+    }
+
+    /// Steps the worker until all relations of interest have caught
+    /// up with their inputs.
+    fn settle(&mut self) {
+        let server = &self.server;
+
+        self.worker.step_while(|| server.is_any_outdated());
+    }
+}
+
+impl Default for TestServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Accumulates the diffs of identical tuples at identical times,
+/// discarding those that cancel out.
+fn consolidate(mut diffs: Vec<ResultDiff>) -> Vec<ResultDiff> {
+    diffs.sort_by(|x, y| (x.1, &x.0).cmp(&(y.1, &y.0)));
+
+    let mut consolidated: Vec<ResultDiff> = Vec::with_capacity(diffs.len());
+    for (tuple, t, diff) in diffs.into_iter() {
+        match consolidated.last_mut() {
+            Some(last) if last.0 == tuple && last.1 == t => last.2 += diff,
+            _ => consolidated.push((tuple, t, diff)),
+        }
+    }
+
+    consolidated.retain(|x| x.2 != 0);
+    consolidated
+}
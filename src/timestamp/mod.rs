@@ -15,10 +15,34 @@ pub enum Time {
     TxId(u64),
     /// Real time.
     Real(Duration),
-    /// Bitemporal.
+    /// Bitemporal, consisting of an event time and a transaction
+    /// time.
     Bi(Duration, u64),
 }
 
+impl Time {
+    /// Returns the event-time coordinate of bitemporal times.
+    pub fn event_time(&self) -> Option<Duration> {
+        match *self {
+            Time::Bi(event, _) => Some(event),
+            _ => None,
+        }
+    }
+}
+
+impl std::ops::Add for Time {
+    type Output = Time;
+
+    fn add(self, other: Time) -> Time {
+        match (self, other) {
+            (Time::TxId(x), Time::TxId(y)) => Time::TxId(x + y),
+            (Time::Real(x), Time::Real(y)) => Time::Real(x + y),
+            (Time::Bi(x1, x2), Time::Bi(y1, y2)) => Time::Bi(x1 + y1, x2 + y2),
+            (x, y) => panic!("Times {:?} and {:?} can't be added", x, y),
+        }
+    }
+}
+
 impl std::convert::From<Time> for u64 {
     fn from(t: Time) -> u64 {
         if let Time::TxId(time) = t {
@@ -76,8 +100,9 @@ mod rewind {
     /// Extension trait for timestamp types that can be safely
     /// re-wound to an earlier time. This is required for
     /// automatically advancing traces according to their configured
-    /// slack.
-    pub trait Rewind: std::convert::From<Time> {
+    /// slack. Such timestamps must also convert back into `Time`, so
+    /// that they can be reported as data.
+    pub trait Rewind: std::convert::From<Time> + std::convert::Into<Time> {
         /// Returns a new timestamp corresponding to self rewound by the
         /// specified amount of slack. Calling rewind is always safe, in
         /// that no invalid times will be returned.
@@ -123,6 +148,15 @@ mod rewind {
         }
     }
 
+    impl<TOuter, TInner> std::convert::From<Product<TOuter, TInner>> for Time
+    where
+        TOuter: std::convert::Into<Time>,
+    {
+        fn from(t: Product<TOuter, TInner>) -> Time {
+            t.outer.into()
+        }
+    }
+
     impl<TOuter, TInner> Rewind for Product<TOuter, TInner>
     where
         Product<TOuter, TInner>: std::convert::From<Time>,
@@ -9,7 +9,9 @@ use timely::dataflow::operators::Operator;
 use declarative_dataflow::binding::Binding;
 use declarative_dataflow::plan::{Aggregate, AggregationFn, Implementable, Join, Project};
 use declarative_dataflow::server::Server;
-use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use declarative_dataflow::{
+    Aid, AttributeConfig, Datom, Decimal, InputSemantics, Plan, Rule, Value,
+};
 use Value::{Eid, Number, Rational32, String};
 
 use num_rational::Ratio;
@@ -160,6 +162,55 @@ fn count() {
     ]);
 }
 
+#[test]
+fn count_distinct() {
+    let (e, amount) = (1, 2);
+    let data = vec![
+        Datom::add(1, ":amount", Number(5)),
+        Datom::add(2, ":amount", Number(10)),
+        Datom::add(2, ":amount", Number(5)),
+        Datom::add(1, ":amount", Number(2)),
+        Datom::add(1, ":amount", Number(4)),
+        Datom::add(3, ":amount", Number(4)),
+    ];
+
+    run_cases(vec![
+        Case {
+            description: "[:find (count-distinct ?amount) :where [?e :amount ?amount]]",
+            plan: Plan::Aggregate(Aggregate {
+                variables: vec![amount],
+                plan: Box::new(Plan::Project(Project {
+                    variables: vec![amount],
+                    plan: Box::new(Plan::match_a(e, ":amount", amount)),
+                })),
+                aggregation_fns: vec![AggregationFn::COUNT_DISTINCT],
+                key_variables: vec![],
+                aggregation_variables: vec![amount],
+                with_variables: vec![],
+            }),
+            transactions: vec![data.clone()],
+            expectations: vec![vec![(vec![Number(4)], 0, 1)]],
+        },
+        Case {
+            description: "[:find ?e (count-distinct ?amount) :where [?e :amount ?amount]]",
+            plan: Plan::Aggregate(Aggregate {
+                variables: vec![e, amount],
+                plan: Box::new(Plan::match_a(e, ":amount", amount)),
+                aggregation_fns: vec![AggregationFn::COUNT_DISTINCT],
+                key_variables: vec![e],
+                aggregation_variables: vec![amount],
+                with_variables: vec![],
+            }),
+            transactions: vec![data.clone()],
+            expectations: vec![vec![
+                (vec![Eid(1), Number(3)], 0, 1),
+                (vec![Eid(2), Number(2)], 0, 1),
+                (vec![Eid(3), Number(1)], 0, 1),
+            ]],
+        },
+    ]);
+}
+
 #[test]
 fn max() {
     let (e, amount) = (1, 2);
@@ -374,6 +425,59 @@ fn avg() {
     ]);
 }
 
+#[test]
+fn decimal_sum_and_avg() {
+    let (e, amount) = (1, 2);
+    let decimal = Value::decimal_str;
+    let data = vec![
+        Datom::add(1, ":amount", decimal("0.10")),
+        Datom::add(1, ":amount", decimal("0.20")),
+        Datom::add(2, ":amount", decimal("92233720368547758.07")),
+        Datom::add(2, ":amount", decimal("92233720368547758.07")),
+        Datom::add(3, ":amount", decimal("1")),
+        Datom::add(3, ":amount", decimal("0")),
+        Datom::add(3, ":amount", decimal("0.00")),
+    ];
+
+    run_cases(vec![
+        Case {
+            description: "[:find ?e (sum ?amount) :where [?e :amount ?amount]]",
+            plan: Plan::Aggregate(Aggregate {
+                variables: vec![e, amount],
+                plan: Box::new(Plan::match_a(e, ":amount", amount)),
+                aggregation_fns: vec![AggregationFn::SUM],
+                key_variables: vec![e],
+                aggregation_variables: vec![amount],
+                with_variables: vec![],
+            }),
+            transactions: vec![data.clone()],
+            // Sums neither lose precision nor overflow 64 bits.
+            expectations: vec![vec![
+                (vec![Eid(1), decimal("0.3")], 0, 1),
+                (vec![Eid(2), decimal("184467440737095516.14")], 0, 1),
+                (vec![Eid(3), decimal("1")], 0, 1),
+            ]],
+        },
+        Case {
+            description: "[:find ?e (avg ?amount) :where [?e :amount ?amount]]",
+            plan: Plan::Aggregate(Aggregate {
+                variables: vec![e, amount],
+                plan: Box::new(Plan::match_a(e, ":amount", amount)),
+                aggregation_fns: vec![AggregationFn::AVG],
+                key_variables: vec![e],
+                aggregation_variables: vec![amount],
+                with_variables: vec![],
+            }),
+            transactions: vec![data.clone()],
+            expectations: vec![vec![
+                (vec![Eid(1), decimal("0.15")], 0, 1),
+                (vec![Eid(2), decimal("92233720368547758.07")], 0, 1),
+                (vec![Eid(3), decimal("0.3333333333")], 0, 1),
+            ]],
+        },
+    ]);
+}
+
 #[test]
 fn variance() {
     let (e, amount) = (1, 2);
@@ -426,6 +530,65 @@ fn variance() {
     ]);
 }
 
+#[test]
+fn decimal_sum() {
+    let (e, amount) = (1, 2);
+    let data = vec![
+        Datom::add(1, ":amount", Number(5)),
+        Datom::add(1, ":amount", Value::decimal_str("2.5")),
+        Datom::add(2, ":amount", Number(10)),
+        Datom::add(
+            3,
+            ":amount",
+            Value::decimal_str("170141183460469231731687303715884105727"),
+        ),
+        Datom::add(3, ":amount", Value::decimal_str("1")),
+    ];
+
+    let aggregate = |aggregation_fn| {
+        Plan::Aggregate(Aggregate {
+            variables: vec![e, amount],
+            plan: Box::new(Plan::match_a(e, ":amount", amount)),
+            aggregation_fns: vec![aggregation_fn],
+            key_variables: vec![e],
+            aggregation_variables: vec![amount],
+            with_variables: vec![],
+        })
+    };
+
+    // Groups mixing numbers and decimals are summed as decimals, in a
+    // single row, and sums beyond the range of decimals saturate.
+    run_cases(vec![
+        Case {
+            description: "[:find ?e (sum ?amount) :where [?e :amount ?amount]]",
+            plan: aggregate(AggregationFn::SUM),
+            transactions: vec![data.clone()],
+            expectations: vec![vec![
+                (vec![Eid(1), Value::decimal_str("7.5")], 0, 1),
+                (vec![Eid(2), Number(10)], 0, 1),
+                (vec![Eid(3), Value::Decimal(Decimal::MAX)], 0, 1),
+            ]],
+        },
+        Case {
+            description: "[:find ?e (avg ?amount) :where [?e :amount ?amount]]",
+            plan: aggregate(AggregationFn::AVG),
+            transactions: vec![data],
+            expectations: vec![vec![
+                (vec![Eid(1), Value::decimal_str("3.75")], 0, 1),
+                (vec![Eid(2), Rational32(Ratio::new(10, 1))], 0, 1),
+                (
+                    vec![
+                        Eid(3),
+                        Value::decimal_str("85070591730234615865843651857942052864"),
+                    ],
+                    0,
+                    1,
+                ),
+            ]],
+        },
+    ]);
+}
+
 #[test]
 fn median() {
     let (e, amount) = (1, 2);
@@ -474,6 +637,43 @@ fn median() {
     ]);
 }
 
+#[test]
+fn quantile() {
+    let (e, amount) = (1, 2);
+    let data = vec![
+        Datom::add(1, ":amount", Number(5)),
+        Datom::add(2, ":amount", Number(10)),
+        Datom::add(2, ":amount", Number(10)),
+        Datom::add(1, ":amount", Number(2)),
+        Datom::add(1, ":amount", Number(4)),
+        Datom::add(1, ":amount", Number(6)),
+    ];
+
+    let case = |description, q, expected| Case {
+        description,
+        plan: Plan::Aggregate(Aggregate {
+            variables: vec![amount],
+            plan: Box::new(Plan::Project(Project {
+                variables: vec![amount],
+                plan: Box::new(Plan::match_a(e, ":amount", amount)),
+            })),
+            aggregation_fns: vec![AggregationFn::QUANTILE(q)],
+            key_variables: vec![],
+            aggregation_variables: vec![amount],
+            with_variables: vec![],
+        }),
+        transactions: vec![data.clone()],
+        expectations: vec![vec![(vec![Number(expected)], 0, 1)]],
+    };
+
+    run_cases(vec![
+        case("p25", Ratio::new(1, 4), 4),
+        case("p50", Ratio::new(1, 2), 5),
+        case("p95", Ratio::new(95, 100), 10),
+        case("p0", Ratio::new(0, 1), 2),
+    ]);
+}
+
 #[test]
 fn multiple_aggregations() {
     run_cases(vec![
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use declarative_dataflow::server::auth::{Acl, Grant, Operation};
+use declarative_dataflow::server::{Configuration, Request, Server};
+use declarative_dataflow::{Aid, Datom, Value};
+use Value::String;
+
+fn acl() -> Acl {
+    let mut tokens = HashMap::new();
+    tokens.insert("secret".to_string(), "dipper".to_string());
+
+    Acl {
+        tokens,
+        grants: vec![
+            Grant {
+                principal: "dipper".to_string(),
+                namespace: ":user".to_string(),
+                operations: vec![Operation::Read, Operation::Transact],
+            },
+            Grant {
+                principal: "dipper".to_string(),
+                namespace: "*".to_string(),
+                operations: vec![Operation::Read],
+            },
+        ],
+    }
+}
+
+fn transact(a: &str) -> Request<Aid> {
+    Request::Transact(vec![Datom::add(1, a, String("Dipper".to_string()))])
+}
+
+#[test]
+fn namespaces() {
+    let acl = acl();
+
+    assert!(acl.allows("dipper", Operation::Transact, ":user"));
+    assert!(acl.allows("dipper", Operation::Transact, ":user/name"));
+    assert!(!acl.allows("dipper", Operation::Transact, ":username"));
+    assert!(!acl.allows("dipper", Operation::Transact, ":admin/role"));
+    assert!(acl.allows("dipper", Operation::Read, ":admin/role"));
+    assert!(!acl.allows("mabel", Operation::Read, ":user/name"));
+
+    assert!(acl.allows_all("dipper", Operation::Read));
+    assert!(!acl.allows_all("dipper", Operation::Transact));
+}
+
+#[test]
+fn authorization() {
+    let acl = acl();
+
+    assert!(acl
+        .authorize(Some("dipper"), &transact(":user/name"))
+        .is_ok());
+
+    let error = acl
+        .authorize(Some("dipper"), &transact(":admin/role"))
+        .unwrap_err();
+    assert_eq!(error.category, "df.error.category/forbidden");
+
+    let error = acl
+        .authorize(Some("dipper"), &Request::<Aid>::Shutdown)
+        .unwrap_err();
+    assert_eq!(error.category, "df.error.category/forbidden");
+
+    // Clients that haven't authenticated may only do harmless things.
+    assert!(acl.authorize(None, &Request::<Aid>::Status).is_ok());
+    assert!(acl.authorize(None, &transact(":user/name")).is_err());
+}
+
+#[test]
+fn authentication() {
+    let mut config = Configuration::default();
+    config.acl = Some(acl());
+
+    let mut server = Server::<Aid, u64, u64>::new(config);
+
+    assert!(server.authorize(0, &transact(":user/name")).is_err());
+
+    let error = server
+        .authorize(0, &Request::Authenticate("guess".to_string()))
+        .unwrap_err();
+    assert_eq!(error.category, "df.error.category/forbidden");
+
+    server
+        .authorize(0, &Request::Authenticate("secret".to_string()))
+        .unwrap();
+
+    assert!(server.authorize(0, &transact(":user/name")).is_ok());
+
+    // Principals are tracked per client.
+    assert!(server.authorize(1, &transact(":user/name")).is_err());
+
+    server.disconnect_client(0).unwrap();
+    assert!(server.authorize(0, &transact(":user/name")).is_err());
+}
+
+#[test]
+fn no_acl() {
+    let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+    assert!(server.authorize(0, &transact(":admin/role")).is_ok());
+    assert!(server.authorize(0, &Request::Shutdown).is_ok());
+}
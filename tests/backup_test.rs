@@ -0,0 +1,101 @@
+#![cfg(feature = "serde_json")]
+
+use std::sync::mpsc::channel;
+
+use declarative_dataflow::server::backup::{self, BackupPart};
+use declarative_dataflow::server::Server;
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Time, Value};
+
+#[test]
+fn backup_and_restore() {
+    let directory = std::env::temp_dir().join(format!("3df-backup-{}", std::process::id()));
+    let path = directory.clone();
+
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let config = AttributeConfig::tx_time(InputSemantics::Raw);
+
+            server
+                .create_attribute(scope, ":name", config.clone())
+                .unwrap();
+            server
+                .create_attribute(scope, ":name/clone", config)
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule::named("clones", Plan::match_a(0, ":name/clone", 1)),
+                )
+                .inspect(move |x| send_results.send((x.0.clone(), x.2)).unwrap());
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":name", Value::from("Alice")),
+                    Datom::add(2, ":name", Value::from("Bob")),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+
+        // Later changes are not part of the backup.
+        server
+            .transact(vec![Datom::retract(2, ":name", Value::from("Bob"))], 0, 0)
+            .unwrap();
+        server.advance_domain(None, 2).unwrap();
+
+        let tuples = loop {
+            worker.step();
+
+            if let Some(tuples) = server.internal.contents(&":name".to_string(), &0).unwrap() {
+                break tuples;
+            }
+        };
+
+        let part = BackupPart {
+            name: ":name".to_string(),
+            config: server.internal.attributes[":name"].clone(),
+            as_of: Time::TxId(0),
+            worker: 0,
+            tuples,
+        };
+
+        backup::write_part(&path, &part).unwrap();
+
+        let parts: Vec<BackupPart<Aid>> = backup::read(&path).unwrap();
+        assert_eq!(parts, vec![part]);
+
+        // Restore into a different attribute.
+        let tx_data = parts
+            .into_iter()
+            .flat_map(|part| part.tuples.into_iter())
+            .map(|(e, v, count)| Datom(e, ":name/clone".to_string(), v, None, count))
+            .collect();
+
+        server.transact(tx_data, 0, 0).unwrap();
+        server.advance_domain(None, 3).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut clones: Vec<_> = results.try_iter().collect();
+        clones.sort();
+
+        assert_eq!(
+            clones,
+            vec![
+                (vec![Value::Eid(1), Value::from("Alice")], 1),
+                (vec![Value::Eid(2), Value::from("Bob")], 1),
+            ]
+        );
+    });
+
+    assert!(backup::read::<Aid, _>(&directory.join("missing")).is_err());
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
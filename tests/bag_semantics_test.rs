@@ -0,0 +1,98 @@
+use declarative_dataflow::plan::{Antijoin, Union};
+use declarative_dataflow::testing::TestServer;
+use declarative_dataflow::{
+    Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Semantics, Value,
+};
+use Value::{Eid, String};
+
+fn server() -> TestServer {
+    let mut server = TestServer::new();
+
+    for name in &[":name", ":banned"] {
+        server
+            .create_attribute(name, AttributeConfig::tx_time(InputSemantics::Raw))
+            .unwrap();
+    }
+
+    server
+}
+
+fn twice() -> Plan<Aid> {
+    Plan::Union(Union {
+        variables: vec![0, 1],
+        plans: vec![Plan::match_a(0, ":name", 1), Plan::match_a(0, ":name", 1)],
+        semantics: None,
+    })
+}
+
+#[test]
+fn unions() {
+    let mut server = server();
+
+    server
+        .register(vec![
+            Rule::named("set", twice()),
+            Rule::named("bag", twice()).with_semantics(Semantics::Bag),
+        ])
+        .unwrap();
+
+    server.interest("set").unwrap();
+    server.interest("bag").unwrap();
+
+    let t = server
+        .transact(vec![Datom::add(1, ":name", String("Dipper".to_string()))])
+        .unwrap();
+
+    let dipper = vec![Eid(1), String("Dipper".to_string())];
+
+    server.expect_results("set", vec![(dipper.clone(), t, 1)]);
+    server.expect_results("bag", vec![(dipper.clone(), t, 2)]);
+
+    let t = server
+        .transact(vec![Datom::retract(
+            1,
+            ":name",
+            String("Dipper".to_string()),
+        )])
+        .unwrap();
+
+    server.expect_results("set", vec![(dipper.clone(), t, -1)]);
+    server.expect_results("bag", vec![(dipper, t, -2)]);
+}
+
+#[test]
+fn antijoins() {
+    let mut server = server();
+
+    let unbanned = || {
+        Plan::Antijoin(Antijoin {
+            variables: vec![0],
+            left_plan: Box::new(twice()),
+            right_plan: Box::new(Plan::match_a(0, ":banned", 2)),
+            semantics: None,
+        })
+    };
+
+    server
+        .register(vec![
+            Rule::named("set", unbanned()).with_semantics(Semantics::Set),
+            Rule::named("bag", unbanned()).with_semantics(Semantics::Bag),
+        ])
+        .unwrap();
+
+    server.interest("set").unwrap();
+    server.interest("bag").unwrap();
+
+    let t = server
+        .transact(vec![
+            Datom::add(1, ":name", String("Dipper".to_string())),
+            Datom::add(2, ":name", String("Mabel".to_string())),
+            Datom::add(2, ":banned", Value::Bool(true)),
+        ])
+        .unwrap();
+
+    let dipper = vec![Eid(1), String("Dipper".to_string())];
+
+    server.expect_results("set", vec![(dipper.clone(), t, 1)]);
+    server.expect_results("bag", vec![(dipper, t, 2)]);
+}
@@ -0,0 +1,104 @@
+use declarative_dataflow::plan::{Filter, Join, Predicate, Project, QueryBuilder};
+use declarative_dataflow::testing::TestServer;
+use declarative_dataflow::{
+    q, Aid, AttributeConfig, Datom, Error, InputSemantics, Plan, Rule, Value,
+};
+use Value::{Number, String};
+
+#[test]
+fn builds_plans() {
+    let (e, name, age) = (0, 1, 2);
+
+    let expected = Plan::Project(Project {
+        variables: vec![e, name],
+        plan: Box::new(Plan::Filter(Filter {
+            variables: vec![age],
+            predicate: Predicate::GT,
+            plan: Box::new(Plan::Join(Join {
+                variables: vec![e],
+                left_plan: Box::new(Plan::match_a(e, ":person/name", name)),
+                right_plan: Box::new(Plan::match_a(e, ":person/age", age)),
+            })),
+            constants: vec![None, Some(Number(21))],
+        })),
+    });
+
+    let built: Plan<Aid> = QueryBuilder::new()
+        .pattern("?e", ":person/name", "?name")
+        .pattern("?e", ":person/age", "?age")
+        .filter(Predicate::GT, "?age", Number(21))
+        .build(&["?e", "?name"])
+        .unwrap();
+
+    assert_eq!(built, expected);
+
+    let expanded: Plan<Aid> = q! {
+        find ?e ?name
+        where [?e :person/name ?name] [?e :person/age ?age] (> ?age 21)
+    }
+    .unwrap();
+
+    assert_eq!(expanded, expected);
+}
+
+fn is_invalid(plan: Result<Plan<Aid>, Error>) -> bool {
+    plan.is_err()
+}
+
+#[test]
+fn rejects_invalid_queries() {
+    // Unbound variables.
+    assert!(is_invalid(q! { find ?e ?age where [?e :name ?n] }));
+    assert!(is_invalid(q! { find ?e where [?e :name ?n] (> ?age 21) }));
+
+    // Patterns without shared variables.
+    assert!(is_invalid(
+        q! { find ?e ?f where [?e :name ?n] [?f :age ?a] }
+    ));
+
+    // Variables must be marked as such.
+    assert!(is_invalid(
+        QueryBuilder::new()
+            .pattern("e", ":name", "?n")
+            .build(&["?n"])
+    ));
+
+    // There has to be something to match.
+    assert!(is_invalid(QueryBuilder::new().build(&[])));
+}
+
+#[test]
+fn runs_built_plans() {
+    let mut server = TestServer::new();
+
+    for name in &[":person/name", ":person/age"] {
+        server
+            .create_attribute(name, AttributeConfig::tx_time(InputSemantics::Raw))
+            .unwrap();
+    }
+
+    let plan = q! {
+        find ?name ?age
+        where [?e :person/name ?name] [?e :person/age ?age] (>= ?age 13) (!= ?name "Soos")
+    }
+    .unwrap();
+
+    server.register(vec![Rule::named("teens", plan)]).unwrap();
+    server.interest("teens").unwrap();
+
+    let t = server
+        .transact(vec![
+            Datom::add(1, ":person/name", String("Dipper".to_string())),
+            Datom::add(1, ":person/age", Number(13)),
+            Datom::add(2, ":person/name", String("Mabel".to_string())),
+            Datom::add(2, ":person/age", Number(12)),
+            Datom::add(3, ":person/name", String("Soos".to_string())),
+            Datom::add(3, ":person/age", Number(22)),
+        ])
+        .unwrap();
+
+    server.expect_results(
+        "teens",
+        vec![(vec![String("Dipper".to_string()), Number(13)], t, 1)],
+    );
+}
@@ -0,0 +1,45 @@
+use std::rc::Rc;
+use std::time::Duration;
+
+use timely::dataflow::operators::{Input, Probe};
+
+use declarative_dataflow::sources::checkpoint::{CheckpointStore, Checkpointer, Positions};
+
+#[test]
+fn checkpoints_follow_domain() {
+    let directory = std::env::temp_dir().join(format!("3df-checkpoints-{}", std::process::id()));
+    let path = directory.clone();
+
+    timely::execute_directly(move |worker| {
+        let store = Rc::new(CheckpointStore::open(path).unwrap());
+        assert_eq!(store.load("kafka/group/topic").unwrap(), None);
+
+        let (mut input, probe) = worker.dataflow::<Duration, _, _>(|scope| {
+            let (input, stream) = scope.new_input::<()>();
+            (input, stream.probe())
+        });
+
+        let mut checkpointer = Checkpointer::new(
+            "kafka/group/topic".to_string(),
+            store.clone(),
+            probe.clone(),
+        );
+
+        let mut positions = Positions::new();
+        positions.insert("0".to_string(), 10);
+        checkpointer.record(Duration::from_nanos(10), positions.clone());
+
+        // Nothing is persisted while the domain lags behind.
+        checkpointer.persist();
+        assert_eq!(store.load("kafka/group/topic").unwrap(), None);
+
+        input.advance_to(Duration::from_nanos(10));
+        worker.step_while(|| probe.less_than(input.time()));
+
+        checkpointer.persist();
+        assert_eq!(store.load("kafka/group/topic").unwrap(), Some(positions));
+        assert_eq!(checkpointer.restore().get("0"), Some(&10));
+    });
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,81 @@
+#![cfg(feature = "client")]
+
+use futures::{SinkExt, StreamExt};
+
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+use declarative_dataflow::server::Request;
+use declarative_dataflow::{client, Aid, Error, Output, Plan, Time, Value};
+
+#[test]
+fn register_and_receive() {
+    let mut runtime = tokio::runtime::Builder::new()
+        .basic_scheduler()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    runtime.block_on(async {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        // A server answering the first batch of requests with a
+        // batch of results and an error.
+        let server = tokio::spawn(async move {
+            let (stream, _addr) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+            let requests = match socket.next().await.unwrap().unwrap() {
+                Message::Text(text) => serde_json::from_str::<Vec<Request<Aid>>>(&text).unwrap(),
+                other => panic!("unexpected message {:?}", other),
+            };
+
+            let outputs = vec![
+                Output::QueryDiff(
+                    "names".to_string(),
+                    vec![(vec![Value::Eid(1)], Time::TxId(0), 1)],
+                ),
+                Output::QueryDiff(
+                    "other".to_string(),
+                    vec![(vec![Value::Eid(2)], Time::TxId(0), 1)],
+                ),
+                Output::Error(0, Error::conflict("boom"), 0),
+            ];
+
+            for output in outputs.iter() {
+                let json = serde_json::to_string(output).unwrap();
+                socket.send(Message::Text(json)).await.unwrap();
+            }
+
+            requests
+        });
+
+        let client = client::connect(&url).await.unwrap();
+        let mut results = client
+            .register("names", Plan::match_a(0, ":name", 1))
+            .await
+            .unwrap();
+
+        // Only results of the registered relation arrive.
+        let batch = results.next().await.unwrap().unwrap();
+        assert_eq!(batch, vec![(vec![Value::Eid(1)], Time::TxId(0), 1)]);
+
+        let error = results.next().await.unwrap().unwrap_err();
+        assert_eq!(error.category, "df.error.category/conflict");
+
+        let requests = server.await.unwrap();
+        assert_eq!(requests.len(), 2);
+
+        match (&requests[0], &requests[1]) {
+            (Request::Register(register), Request::Interest(interest)) => {
+                assert_eq!(register.publish, vec!["names".to_string()]);
+                assert_eq!(interest.name, "names");
+            }
+            other => panic!("unexpected requests {:?}", other),
+        }
+
+        // Streams end along with the connection.
+        assert!(results.next().await.is_none());
+    });
+}
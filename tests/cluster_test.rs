@@ -0,0 +1,63 @@
+use declarative_dataflow::server::cluster::{ClusterConfiguration, Membership, Rendezvous};
+use declarative_dataflow::server::{Configuration, Server};
+use declarative_dataflow::Aid;
+
+#[test]
+fn cluster_configuration() {
+    let local = ClusterConfiguration::local(4, 2, 1);
+    assert_eq!(
+        local.addresses,
+        vec!["localhost:2101".to_string(), "localhost:2102".to_string()]
+    );
+    assert_eq!(local.processes(), 2);
+    assert_eq!(local.peers(), 8);
+    assert!(local.validate().is_ok());
+
+    let out_of_range = ClusterConfiguration::local(1, 2, 2);
+    assert!(out_of_range.validate().is_err());
+
+    let no_threads = ClusterConfiguration::with_addresses(0, vec!["host:2101".to_string()], 0);
+    assert!(no_threads.validate().is_err());
+
+    match ClusterConfiguration::default().into() {
+        timely::Configuration::Thread => {}
+        _ => panic!("expected a single-threaded configuration"),
+    }
+}
+
+#[test]
+fn rendezvous() {
+    let server = Server::<Aid, u64, u64>::new(Default::default());
+    let fingerprint = server.fingerprint();
+
+    let mut membership = Membership::new(2, fingerprint);
+    membership
+        .join(&Rendezvous {
+            worker: 0,
+            fingerprint,
+        })
+        .unwrap();
+    assert!(!membership.is_complete());
+
+    // Workers running with a different configuration are refused.
+    let diverging = Server::<Aid, u64, u64>::new(Configuration {
+        enable_sharing: true,
+        ..Default::default()
+    });
+    assert_ne!(diverging.fingerprint(), fingerprint);
+    assert!(membership
+        .join(&Rendezvous {
+            worker: 1,
+            fingerprint: diverging.fingerprint(),
+        })
+        .is_err());
+    assert!(!membership.is_complete());
+
+    membership
+        .join(&Rendezvous {
+            worker: 1,
+            fingerprint,
+        })
+        .unwrap();
+    assert!(membership.is_complete());
+}
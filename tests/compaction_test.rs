@@ -0,0 +1,120 @@
+#![cfg(feature = "serde_json")]
+
+use std::time::Duration;
+
+use declarative_dataflow::server::snapshot::{self, SnapshotPart, SnapshotWriter};
+use declarative_dataflow::server::storage::FileSystem;
+use declarative_dataflow::server::wal::WriteAheadLog;
+use declarative_dataflow::server::Server;
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Time, Value};
+
+#[test]
+fn compacted_snapshot_as_of() {
+    let directory = std::env::temp_dir().join(format!("3df-compaction-{}", std::process::id()));
+    let path = directory.clone();
+
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let config = AttributeConfig {
+                trace_slack: Some(Time::TxId(1)),
+                ..AttributeConfig::tx_time(InputSemantics::Raw)
+            };
+
+            server.create_attribute(scope, ":age", config).unwrap();
+        });
+
+        server
+            .transact(vec![Datom::add(1, ":age", Value::Number(10))], 0, 0)
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+
+        // Take a snapshot as of the current epoch, holding back
+        // compaction until it has been read.
+        server.internal.hold_compaction(Some(1));
+
+        server
+            .transact(
+                vec![
+                    Datom::retract(1, ":age", Value::Number(10)),
+                    Datom::add(2, ":age", Value::Number(20)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 10).unwrap();
+        server.advance().unwrap();
+
+        let attributes = loop {
+            worker.step();
+
+            if let Some(attributes) = server.internal.snapshot(&1) {
+                break attributes;
+            }
+        };
+
+        server.internal.hold_compaction(None);
+
+        let expected = vec![(
+            ":age".to_string(),
+            vec![(Value::Eid(1), Value::Number(10), 1)],
+        )];
+        assert_eq!(attributes, expected);
+
+        // Parts are written in the background and merged when read.
+        let writer = SnapshotWriter::spawn(Box::new(FileSystem::open(&path).unwrap()));
+        writer.write(SnapshotPart {
+            through: 3,
+            worker: 0,
+            attributes,
+        });
+
+        let backend = FileSystem::open(&path).unwrap();
+        while !snapshot::is_complete(&backend, 3, 1) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let restored: Vec<(Aid, Vec<(Value, Value, isize)>)> = snapshot::read(&backend, 3).unwrap();
+        assert_eq!(restored, expected);
+    });
+
+    // Rewriting the log replaces all of its entries.
+    {
+        let mut wal = WriteAheadLog::open(&directory).unwrap();
+        wal.append(&1).unwrap();
+        wal.append(&2).unwrap();
+        wal.rewrite(&[3]).unwrap();
+        wal.append(&4).unwrap();
+    }
+
+    let replayed: Vec<u64> = WriteAheadLog::replay(&directory).unwrap();
+    assert_eq!(replayed, vec![3, 4]);
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn finished_snapshot_writers() {
+    let directory =
+        std::env::temp_dir().join(format!("3df-compaction-finish-{}", std::process::id()));
+
+    // Finishing waits for all queued parts, s.t. none are lost when
+    // shutting down.
+    let writer = SnapshotWriter::<Aid>::spawn(Box::new(FileSystem::open(&directory).unwrap()));
+    writer.write(SnapshotPart {
+        through: 5,
+        worker: 0,
+        attributes: vec![(
+            ":age".to_string(),
+            vec![(Value::Eid(1), Value::Number(10), 1)],
+        )],
+    });
+    writer.finish();
+
+    let backend = FileSystem::open(&directory).unwrap();
+    assert!(snapshot::is_complete(&backend, 5, 1));
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,207 @@
+use std::collections::HashSet;
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use declarative_dataflow::plan::PullLevel;
+use declarative_dataflow::server::{CreateIndex, Server};
+use declarative_dataflow::{Aid, AttributeConfig, CompositeIndex, Datom, InputSemantics};
+use declarative_dataflow::{Plan, Rule, Value};
+use Value::{Bool, Eid, Number, String};
+
+#[test]
+fn pull_via_entity_index() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in &["admin?", "name", "age"] {
+                server
+                    .create_attribute(scope, *name, AttributeConfig::tx_time(InputSemantics::Raw))
+                    .unwrap();
+            }
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let req = CreateIndex {
+                name: "person".to_string(),
+                kind: CompositeIndex::Entity,
+                attributes: vec!["name".to_string(), "age".to_string()],
+            };
+
+            server.create_index(scope, req).unwrap();
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add(100, "admin?", Bool(true)),
+                    Datom::add(200, "admin?", Bool(false)),
+                    Datom::add(300, "admin?", Bool(false)),
+                    Datom::add(100, "name", String("Mabel".to_string())),
+                    Datom::add(200, "name", String("Dipper".to_string())),
+                    Datom::add(300, "name", String("Soos".to_string())),
+                    Datom::add(100, "age", Number(12)),
+                    Datom::add(200, "age", Number(13)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let plan = Plan::PullLevel(PullLevel {
+                variables: vec![],
+                pull_variable: 0,
+                plan: Box::new(Plan::match_av(0, "admin?", Bool(false))),
+                pull_attributes: vec!["name".to_string(), "age".to_string()],
+                path_attributes: vec![],
+                cardinality_many: false,
+            });
+
+            server
+                .test_single(scope, Rule::named("query", plan))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.1, x.2)).unwrap();
+                });
+        });
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut expected = HashSet::new();
+        expected.insert((vec![Eid(200), Value::aid("age"), Number(13)], 0, 1));
+        expected.insert((
+            vec![Eid(200), Value::aid("name"), String("Dipper".to_string())],
+            0,
+            1,
+        ));
+        expected.insert((
+            vec![Eid(300), Value::aid("name"), String("Soos".to_string())],
+            0,
+            1,
+        ));
+
+        for _i in 0..expected.len() {
+            let result = results.recv_timeout(Duration::from_millis(400)).unwrap();
+            if !expected.remove(&result) {
+                panic!("unknown result {:?}", result);
+            }
+        }
+
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+    });
+}
+
+#[test]
+fn pull_reverse_via_ref_index() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in &["admin?", "friend"] {
+                server
+                    .create_attribute(scope, *name, AttributeConfig::tx_time(InputSemantics::Raw))
+                    .unwrap();
+            }
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let req = CreateIndex {
+                name: "references".to_string(),
+                kind: CompositeIndex::Ref,
+                attributes: vec!["friend".to_string()],
+            };
+
+            server.create_index(scope, req).unwrap();
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add(100, "admin?", Bool(true)),
+                    Datom::add(200, "admin?", Bool(false)),
+                    Datom::add(100, "friend", Eid(200)),
+                    Datom::add(300, "friend", Eid(200)),
+                    Datom::add(200, "friend", Eid(100)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let plan = Plan::PullLevel(PullLevel {
+                variables: vec![],
+                pull_variable: 0,
+                plan: Box::new(Plan::match_av(0, "admin?", Bool(false))),
+                pull_attributes: vec!["_friend".to_string()],
+                path_attributes: vec![],
+                cardinality_many: false,
+            });
+
+            server
+                .test_single(scope, Rule::named("query", plan))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.1, x.2)).unwrap();
+                });
+        });
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut expected = HashSet::new();
+        expected.insert((vec![Eid(200), Value::aid("_friend"), Eid(100)], 0, 1));
+        expected.insert((vec![Eid(200), Value::aid("_friend"), Eid(300)], 0, 1));
+
+        for _i in 0..expected.len() {
+            let result = results.recv_timeout(Duration::from_millis(400)).unwrap();
+            if !expected.remove(&result) {
+                panic!("unknown result {:?}", result);
+            }
+        }
+
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+    });
+}
+
+#[test]
+fn index_requirements() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(scope, "name", AttributeConfig::tx_time(InputSemantics::Raw))
+                .unwrap();
+
+            let index = |name: &str, attributes: &[&str]| CreateIndex {
+                name: name.to_string(),
+                kind: CompositeIndex::Entity,
+                attributes: attributes.iter().map(|a| a.to_string()).collect(),
+            };
+
+            // Indices must cover existing attributes.
+            assert!(server.create_index(scope, index("person", &[])).is_err());
+            assert!(server
+                .create_index(scope, index("person", &["name", "age"]))
+                .is_err());
+
+            server
+                .create_index(scope, index("person", &["name"]))
+                .unwrap();
+
+            // Names are unique.
+            assert!(server
+                .create_index(scope, index("person", &["name"]))
+                .is_err());
+        });
+
+        // Indices go away along with their attributes.
+        server.drop_attribute(&"name".to_string()).unwrap();
+
+        let domain = server.domain_of(&"person".to_string()).unwrap();
+        assert!(domain.composite.is_empty());
+    });
+}
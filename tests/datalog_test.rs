@@ -0,0 +1,255 @@
+use declarative_dataflow::plan::datalog::parse;
+use declarative_dataflow::plan::{Predicate, QueryBuilder};
+use declarative_dataflow::server::{DatalogRule, Register};
+use declarative_dataflow::testing::{ResultDiff, TestServer};
+use declarative_dataflow::{
+    q, Aid, AttributeConfig, Datom, Error, InputSemantics, Plan, Rule, Value,
+};
+use Value::{Bool, Number, String};
+
+#[test]
+fn parses_queries() {
+    let parsed: Plan<Aid> = parse(
+        r#"[:find ?e ?name
+            :where [?e :person/name ?name] [?e :person/age ?age] [(> ?age 21)]]"#,
+    )
+    .unwrap();
+
+    let expanded: Plan<Aid> = q! {
+        find ?e ?name
+        where [?e :person/name ?name] [?e :person/age ?age] (> ?age 21)
+    }
+    .unwrap();
+
+    assert_eq!(parsed, expanded);
+}
+
+#[test]
+fn parses_constants_and_wildcards() {
+    let parsed: Plan<Aid> = parse(
+        r#"[:find ?e
+            :where [?e :name "Dipper \"Pines\""], [?e :alive true]
+                   [?e :age _] [?e :twin _] [(not= ?e 2)]]"#,
+    )
+    .unwrap();
+
+    let built: Plan<Aid> = QueryBuilder::new()
+        .pattern("?e", ":name", String("Dipper \"Pines\"".to_string()))
+        .pattern("?e", ":alive", Bool(true))
+        .pattern("?e", ":age", "?_1")
+        .pattern("?e", ":twin", "?_2")
+        .filter(Predicate::NEQ, "?e", Number(2))
+        .build(&["?e"])
+        .unwrap();
+
+    assert_eq!(parsed, built);
+}
+
+#[test]
+fn rejects_invalid_queries() {
+    let category = |query: &str| parse::<Aid>(query).unwrap_err().category;
+
+    assert_eq!(category("[:find ?e :where"), "df.error.category/incorrect");
+    assert_eq!(
+        category(r#"[:find ?e :where [?e :name "Dipper]]"#),
+        "df.error.category/incorrect"
+    );
+    assert_eq!(
+        category("[:find ?e :where [?e :name ?n]] [?e]"),
+        "df.error.category/incorrect"
+    );
+    assert_eq!(
+        category("[:find ?e :where [?e :name ?n] [(> ?age 21)]]"),
+        "df.error.category/incorrect"
+    );
+    assert_eq!(
+        category("[:find ?e :in $ :where [?e :name ?n]]"),
+        "df.error.category/unsupported"
+    );
+    assert_eq!(
+        category("[:find ?e :where [?e :name ?n] [(matches ?n 21)]]"),
+        "df.error.category/unsupported"
+    );
+    assert_eq!(
+        category("[:find ?e :where [1 :name ?n]]"),
+        "df.error.category/unsupported"
+    );
+    assert_eq!(
+        category("[:find ?e :where [?e name ?n]]"),
+        "df.error.category/unsupported"
+    );
+}
+
+#[test]
+fn registers_datalog_rules() {
+    let mut server = TestServer::new();
+
+    for name in &[":person/name", ":person/age"] {
+        server
+            .create_attribute(name, AttributeConfig::tx_time(InputSemantics::Raw))
+            .unwrap();
+    }
+
+    let rule = |name: &str, query: &str| DatalogRule {
+        name: name.to_string(),
+        query: query.to_string(),
+    };
+
+    // Rules that fail to parse leave the server unchanged.
+    let error: Error = server
+        .server()
+        .register(Register {
+            rules: vec![],
+            publish: vec![],
+            datalog: vec![
+                rule("names", "[:find ?name :where [?e :person/name ?name]]"),
+                rule("broken", "[:find ?e :where [?e :person/name"),
+            ],
+        })
+        .unwrap_err();
+    assert_eq!(error.category, "df.error.category/incorrect");
+    assert!(server.interest("names").is_err());
+
+    server
+        .server()
+        .register(Register {
+            rules: vec![],
+            publish: vec!["teens".to_string()],
+            datalog: vec![rule(
+                "teens",
+                r#"[:find ?name ?age
+                    :where [?e :person/name ?name] [?e :person/age ?age]
+                           [(>= ?age 13)] [(< ?age 20)]]"#,
+            )],
+        })
+        .unwrap();
+    server.interest("teens").unwrap();
+
+    let t = server
+        .transact(vec![
+            Datom::add(1, ":person/name", String("Dipper".to_string())),
+            Datom::add(1, ":person/age", Number(13)),
+            Datom::add(2, ":person/name", String("Soos".to_string())),
+            Datom::add(2, ":person/age", Number(22)),
+        ])
+        .unwrap();
+
+    server.expect_results(
+        "teens",
+        vec![(vec![String("Dipper".to_string()), Number(13)], t, 1)],
+    );
+}
+
+#[test]
+fn parses_disjunctions() {
+    let parsed: Plan<Aid> = parse(
+        r#"[:find ?e ?name
+            :where [?e :person/name ?name]
+                   (or-join [[?e]] [?e :person/nickname _]
+                                   (and [?e :person/age ?age] [(< ?age 18)]))]"#,
+    )
+    .unwrap();
+
+    let built: Plan<Aid> = QueryBuilder::new()
+        .pattern("?e", ":person/name", "?name")
+        .or_join(
+            &[],
+            &["?e"],
+            vec![
+                QueryBuilder::new().pattern("?e", ":person/nickname", "?_1"),
+                QueryBuilder::new()
+                    .pattern("?e", ":person/age", "?age")
+                    .filter(Predicate::LT, "?age", Number(18)),
+            ],
+        )
+        .build(&["?e", "?name"])
+        .unwrap();
+
+    assert_eq!(parsed, built);
+}
+
+#[test]
+fn rejects_invalid_disjunctions() {
+    let category = |query: &str| parse::<Aid>(query).unwrap_err().category;
+
+    // Branches of or must agree on their variables.
+    assert_eq!(
+        category("[:find ?e :where [?e :name _] (or [?e :age ?a] [?e :nickname ?n])]"),
+        "df.error.category/incorrect"
+    );
+
+    // Each branch must bind the variables of the or-join.
+    assert_eq!(
+        category("[:find ?e :where [?e :name _] (or-join [?e ?n] [?e :nickname ?n] [?e :age _])]"),
+        "df.error.category/incorrect"
+    );
+
+    // Required variables must be bound elsewhere.
+    assert_eq!(
+        category("[:find ?e :where (or-join [[?e]] [?e :nickname _] [?e :age _])]"),
+        "df.error.category/incorrect"
+    );
+
+    assert_eq!(
+        category("[:find ?e :where [?e :name _] (xor [?e :age _] [?e :nickname _])]"),
+        "df.error.category/unsupported"
+    );
+}
+
+#[test]
+fn runs_disjunctions() {
+    let mut server = TestServer::new();
+
+    for name in &[":person/name", ":person/nickname", ":person/age"] {
+        server
+            .create_attribute(name, AttributeConfig::tx_time(InputSemantics::Raw))
+            .unwrap();
+    }
+
+    let rules = vec![
+        (
+            "minors-or-nicknamed",
+            r#"[:find ?name
+                :where [?e :person/name ?name]
+                       (or-join [?e] [?e :person/nickname _]
+                                     (and [?e :person/age ?age] [(< ?age 18)]))]"#,
+        ),
+        (
+            "twins",
+            "[:find ?name :where [?e :person/name ?name] (or [?e :person/age 12] [?e :person/age 13])]",
+        ),
+    ];
+
+    for (name, query) in rules.into_iter() {
+        server
+            .register(vec![Rule::named(name, parse(query).unwrap())])
+            .unwrap();
+        server.interest(name).unwrap();
+    }
+
+    let t = server
+        .transact(vec![
+            Datom::add(1, ":person/name", String("Dipper".to_string())),
+            Datom::add(1, ":person/age", Number(12)),
+            Datom::add(2, ":person/name", String("Mabel".to_string())),
+            Datom::add(2, ":person/age", Number(12)),
+            Datom::add(2, ":person/nickname", String("Mabes".to_string())),
+            Datom::add(3, ":person/name", String("Stan".to_string())),
+            Datom::add(3, ":person/age", Number(60)),
+            Datom::add(3, ":person/nickname", String("Mr. Mystery".to_string())),
+            Datom::add(4, ":person/name", String("Soos".to_string())),
+            Datom::add(4, ":person/age", Number(22)),
+        ])
+        .unwrap();
+
+    let names = |names: &[&str]| -> Vec<ResultDiff> {
+        names
+            .iter()
+            .map(|name| (vec![String(name.to_string())], t, 1))
+            .collect()
+    };
+
+    // Mabel matches both branches, but is reported only once.
+    server.expect_results("minors-or-nicknamed", names(&["Dipper", "Mabel", "Stan"]));
+    server.expect_results("twins", names(&["Dipper", "Mabel"]));
+}
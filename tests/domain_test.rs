@@ -1,10 +1,19 @@
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
 use timely::dataflow::operators::UnorderedInput;
 use timely::progress::frontier::AntichainRef;
 
 use differential_dataflow::trace::TraceReader;
 
-use declarative_dataflow::domain::{AsSingletonDomain, Domain};
-use declarative_dataflow::{Aid, Value};
+use declarative_dataflow::domain::{AsSingletonDomain, Domain, TX_INSTANT, TX_LINK};
+use declarative_dataflow::plan::{Join, Project};
+use declarative_dataflow::server::{Register, Server};
+use declarative_dataflow::timestamp::Time;
+use declarative_dataflow::{
+    Aid, AttributeConfig, Cardinality, Datom, InputSemantics, Plan, Rule, Uniqueness, Value,
+    ValueType,
+};
 
 #[test]
 fn test_advance_epoch() {
@@ -21,6 +30,31 @@ fn test_advance_epoch() {
     assert_eq!(domain.epoch(), &1);
 }
 
+#[test]
+fn test_advance_clock() {
+    let mut domain = Domain::<Aid, Duration>::new(Duration::from_secs(0));
+
+    // Domains without a clock aren't affected.
+    assert!(domain.advance_clock(Duration::from_millis(250)).is_ok());
+    assert_eq!(domain.epoch(), &Duration::from_secs(0));
+
+    domain.set_clock(Some(Duration::from_millis(100)));
+
+    assert!(domain.advance_clock(Duration::from_millis(250)).is_ok());
+    assert_eq!(domain.epoch(), &Duration::from_millis(200));
+
+    // The clock never rewinds the domain.
+    assert!(domain.advance_epoch(Duration::from_millis(500)).is_ok());
+    assert!(domain.advance_clock(Duration::from_millis(350)).is_ok());
+    assert_eq!(domain.epoch(), &Duration::from_millis(500));
+
+    let mut domain = Domain::<Aid, u64>::new(0);
+    domain.set_clock(Some(Duration::from_millis(100)));
+
+    assert!(domain.advance_clock(Duration::from_millis(250)).is_err());
+    assert_eq!(domain.epoch(), &0);
+}
+
 #[test]
 fn test_advance_only_epoch() {
     timely::execute_directly(move |worker| {
@@ -192,3 +226,713 @@ fn test_advance_only_source() {
         );
     });
 }
+
+#[test]
+fn test_transact_batched() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+
+            server
+                .test_single(scope, Rule::named("names", Plan::match_a(0, ":name", 1)))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        // A transaction touching an unknown attribute must not be
+        // applied partially.
+        assert!(server
+            .internal
+            .transact_batched(vec![
+                Datom::add(1, ":name", Value::from("Dipper")),
+                Datom::add(1, ":unknown", Value::from("Alias")),
+            ])
+            .is_err());
+
+        server
+            .internal
+            .transact_batched(vec![
+                Datom::add(2, ":name", Value::from("Mabel")),
+                Datom::add(3, ":name", Value::from("Soos")),
+            ])
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Value::Eid(2), Value::from("Mabel")], 1)
+        );
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Value::Eid(3), Value::from("Soos")], 1)
+        );
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+    });
+}
+
+#[test]
+fn test_transact_atomic() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+
+            server
+                .test_single(scope, Rule::named("names", Plan::match_a(0, ":name", 1)))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.1, x.2)).unwrap();
+                });
+        });
+
+        server.advance_domain(None, 2).unwrap();
+
+        // Writing into the past must reject the entire transaction.
+        assert!(server
+            .transact(
+                vec![
+                    Datom::add_at(1, ":name", Value::from("Dipper"), Time::TxId(3)),
+                    Datom::add_at(2, ":name", Value::from("Mabel"), Time::TxId(1)),
+                ],
+                0,
+                0,
+            )
+            .is_err());
+
+        server
+            .transact(vec![Datom::add(3, ":name", Value::from("Soos"))], 0, 0)
+            .unwrap();
+
+        server.advance_domain(None, 4).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Value::Eid(3), Value::from("Soos")], 2, 1)
+        );
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+
+        // Rejected transactions don't use up any entity ids.
+        let before = server.internal.allocate_eid();
+
+        assert!(server
+            .transact(
+                vec![
+                    Datom::add_with_tempid("soos", ":name", Value::from("Soos")),
+                    Datom::add_with_tempid("soos", ":unknown", Value::Number(22)),
+                ],
+                0,
+                0,
+            )
+            .is_err());
+
+        assert_eq!(server.internal.allocate_eid(), before + 1);
+    });
+}
+
+#[test]
+fn test_tempids() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in [":name", ":age"].iter() {
+                server
+                    .create_attribute(scope, *name, AttributeConfig::tx_time(InputSemantics::Raw))
+                    .unwrap();
+            }
+
+            let plan = Plan::Join(Join {
+                variables: vec![0],
+                left_plan: Box::new(Plan::match_a(0, ":name", 1)),
+                right_plan: Box::new(Plan::match_a(0, ":age", 2)),
+            });
+
+            server
+                .test_single(scope, Rule::named("people", plan))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        let (tx_data, tempids) = server.internal.resolve_tempids(vec![
+            Datom::add_with_tempid("dipper", ":name", Value::from("Dipper")),
+            Datom::add_with_tempid("dipper", ":age", Value::Number(12)),
+        ]);
+
+        assert_eq!(tempids.len(), 1);
+        let eid = tempids["dipper"];
+
+        server.transact(tx_data, 0, 0).unwrap();
+
+        // Tempids are scoped to a single transaction.
+        let tempids = server
+            .transact(
+                vec![
+                    Datom::add_with_tempid("dipper", ":name", Value::from("Mabel")),
+                    Datom::add_with_tempid("dipper", ":age", Value::Number(12)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        assert_eq!(tempids.len(), 1);
+        assert_eq!(tempids["dipper"], eid + 1);
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (
+                vec![Value::Eid(eid), Value::from("Dipper"), Value::Number(12)],
+                1
+            )
+        );
+        assert_eq!(
+            results.recv().unwrap(),
+            (
+                vec![Value::Eid(eid + 1), Value::from("Mabel"), Value::Number(12)],
+                1
+            )
+        );
+    });
+}
+
+#[test]
+fn test_upsert() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let email = AttributeConfig {
+                unique: Uniqueness::Identity,
+                ..AttributeConfig::tx_time(InputSemantics::Raw)
+            };
+
+            server.create_attribute(scope, ":email", email).unwrap();
+            server
+                .create_attribute(scope, ":age", AttributeConfig::tx_time(InputSemantics::Raw))
+                .unwrap();
+
+            let plan = Plan::Join(Join {
+                variables: vec![0],
+                left_plan: Box::new(Plan::match_a(0, ":email", 1)),
+                right_plan: Box::new(Plan::match_a(0, ":age", 2)),
+            });
+
+            server
+                .test_single(scope, Rule::named("people", plan))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![Datom::add_with_tempid(
+                    "a",
+                    ":email",
+                    Value::from("dipper@gf"),
+                )],
+                0,
+                0,
+            )
+            .unwrap();
+
+        // The tempid resolves to the entity already owning the email.
+        server
+            .transact(
+                vec![
+                    Datom::add_with_tempid("b", ":email", Value::from("dipper@gf")),
+                    Datom::add_with_tempid("b", ":age", Value::Number(12)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        // Another existing entity can't claim the same email.
+        assert!(server
+            .transact(
+                vec![Datom::add(12345, ":email", Value::from("dipper@gf"))],
+                0,
+                0,
+            )
+            .is_err());
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let eid = declarative_dataflow::domain::TEMPID_PARTITION;
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (
+                vec![Value::Eid(eid), Value::from("dipper@gf"), Value::Number(12)],
+                1
+            )
+        );
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+    });
+}
+
+#[test]
+fn test_drop_attribute() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in [":name", ":age"].iter() {
+                server
+                    .create_attribute(scope, *name, AttributeConfig::tx_time(InputSemantics::Raw))
+                    .unwrap();
+            }
+        });
+
+        server
+            .register(Register {
+                rules: vec![Rule::named("ages", Plan::match_a(0, ":age", 1))],
+                publish: vec![],
+                datalog: vec![],
+            })
+            .unwrap();
+
+        // Attributes used by registered rules can't be dropped.
+        assert!(server.drop_attribute(&":age".to_string()).is_err());
+
+        server.drop_attribute(&":name".to_string()).unwrap();
+        assert!(!server.internal.has_attribute(&":name".to_string()));
+        assert!(server
+            .internal
+            .forward_propose(&":name".to_string())
+            .is_none());
+
+        // Transactions against a dropped attribute are rejected.
+        assert!(server
+            .transact(vec![Datom::add(1, ":name", Value::from("Dipper"))], 0, 0)
+            .is_err());
+
+        // Dropping it again fails.
+        assert!(server.drop_attribute(&":name".to_string()).is_err());
+    });
+}
+
+#[test]
+fn test_value_types() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let age = AttributeConfig {
+                value_type: Some(ValueType::Number),
+                ..AttributeConfig::tx_time(InputSemantics::Raw)
+            };
+
+            server.create_attribute(scope, ":age", age).unwrap();
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+        });
+
+        server
+            .transact(vec![Datom::add(1, ":age", Value::Number(12))], 0, 0)
+            .unwrap();
+
+        assert!(server
+            .transact(vec![Datom::add(1, ":age", Value::from("twelve"))], 0, 0)
+            .is_err());
+
+        // Untyped attributes accept anything.
+        server
+            .transact(vec![Datom::add(1, ":name", Value::Number(12))], 0, 0)
+            .unwrap();
+
+        let schema = server.internal.schema();
+        assert_eq!(schema.len(), 2);
+        assert_eq!(schema[0].0, ":age");
+        assert_eq!(schema[0].1.value_type, Some(ValueType::Number));
+        assert_eq!(schema[1].0, ":name");
+        assert_eq!(schema[1].1.value_type, None);
+    });
+}
+
+#[test]
+fn test_bulk_load() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(scope, ":age", AttributeConfig::tx_time(InputSemantics::Raw))
+                .unwrap();
+
+            server
+                .test_single(scope, Rule::named("ages", Plan::match_a(0, ":age", 1)))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        let data = (0..1000).map(|i| (Value::Eid(i), Value::Number(i as i64), 1));
+        server
+            .internal
+            .bulk_load(&":age".to_string(), data)
+            .unwrap();
+
+        assert!(server
+            .internal
+            .bulk_load(&":unknown".to_string(), vec![])
+            .is_err());
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut loaded: Vec<(Vec<Value>, isize)> = results.try_iter().collect();
+        loaded.sort();
+
+        assert_eq!(loaded.len(), 1000);
+        assert_eq!(loaded[0], (vec![Value::Eid(0), Value::Number(0)], 1));
+        assert_eq!(loaded[999], (vec![Value::Eid(999), Value::Number(999)], 1));
+    });
+}
+
+#[test]
+fn test_retract_entity() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in [":name", ":age"].iter() {
+                server
+                    .create_attribute(scope, *name, AttributeConfig::tx_time(InputSemantics::Raw))
+                    .unwrap();
+            }
+
+            let plan = Plan::Join(Join {
+                variables: vec![0],
+                left_plan: Box::new(Plan::match_a(0, ":name", 1)),
+                right_plan: Box::new(Plan::match_a(0, ":age", 2)),
+            });
+
+            server
+                .test_single(scope, Rule::named("people", plan))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.1, x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":name", Value::from("Dipper")),
+                    Datom::add(1, ":age", Value::Number(12)),
+                    Datom::add(2, ":name", Value::from("Mabel")),
+                    Datom::add(2, ":age", Value::Number(12)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(server.internal.retract_entity(&Value::Eid(1)).len(), 2);
+        server.retract_entity(None, Value::Eid(1), 0, 0).unwrap();
+
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut expected = vec![
+            (
+                vec![Value::Eid(1), Value::from("Dipper"), Value::Number(12)],
+                0,
+                1,
+            ),
+            (
+                vec![Value::Eid(2), Value::from("Mabel"), Value::Number(12)],
+                0,
+                1,
+            ),
+            (
+                vec![Value::Eid(1), Value::from("Dipper"), Value::Number(12)],
+                1,
+                -1,
+            ),
+        ];
+
+        for _ in 0..3 {
+            let result = results.recv_timeout(Duration::from_millis(400)).unwrap();
+            let position = expected.iter().position(|x| *x == result).unwrap();
+            expected.remove(position);
+        }
+
+        assert!(expected.is_empty());
+        assert!(server.internal.retract_entity(&Value::Eid(1)).is_empty());
+    });
+}
+
+#[test]
+fn test_tx_meta() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in [":name", ":audit/user", TX_LINK, TX_INSTANT].iter() {
+                server
+                    .create_attribute(scope, *name, AttributeConfig::tx_time(InputSemantics::Raw))
+                    .unwrap();
+            }
+
+            // [?e :name ?n] [?e :db/tx ?tx] [?tx :audit/user ?u]
+            let plan = Plan::Join(Join {
+                variables: vec![2],
+                left_plan: Box::new(Plan::Join(Join {
+                    variables: vec![0],
+                    left_plan: Box::new(Plan::match_a(0, ":name", 1)),
+                    right_plan: Box::new(Plan::match_a(0, TX_LINK, 2)),
+                })),
+                right_plan: Box::new(Plan::match_a(2, ":audit/user", 3)),
+            });
+
+            server
+                .test_single(scope, Rule::named("audit", plan))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact_with_meta(
+                vec![Datom::add(1, ":name", Value::from("Dipper"))],
+                vec![(":audit/user".to_string(), Value::from("Stan"))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let tx = Value::Eid(declarative_dataflow::domain::TEMPID_PARTITION);
+
+        assert_eq!(
+            results.recv_timeout(Duration::from_millis(400)).unwrap(),
+            (
+                vec![
+                    tx.clone(),
+                    Value::Eid(1),
+                    Value::from("Dipper"),
+                    Value::from("Stan")
+                ],
+                1
+            )
+        );
+
+        assert_eq!(server.internal.retract_entity(&tx).len(), 2);
+    });
+}
+
+#[test]
+fn test_set_trace_slack() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let audit = ":audit".to_string();
+        let clicks = ":clicks".to_string();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in [":audit", ":clicks"].iter() {
+                server
+                    .create_attribute(
+                        scope,
+                        *name,
+                        AttributeConfig::uncompacted(InputSemantics::Raw),
+                    )
+                    .unwrap();
+            }
+        });
+
+        server
+            .internal
+            .set_trace_slack(&clicks, Some(Time::TxId(1)))
+            .unwrap();
+        assert!(server
+            .internal
+            .set_trace_slack(&":unknown".to_string(), None)
+            .is_err());
+
+        server.advance_domain(None, 10).unwrap();
+        server.internal.advance().unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert!(server.internal.keeps_history(&audit));
+        assert!(server.internal.retains(&audit, &0));
+
+        assert!(!server.internal.keeps_history(&clicks));
+        assert!(!server.internal.retains(&clicks, &0));
+        assert!(server.internal.retains(&clicks, &9));
+    });
+}
+
+#[test]
+fn test_shared_arrangements() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":edge",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+        });
+
+        let arrangements = server.internal.arrangement_count();
+
+        server
+            .transact(vec![Datom::add(1, ":edge", Value::Eid(2))], 0, 0)
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        // Queries registered after the fact catch up from the shared
+        // traces, without arranging the attribute again.
+        for name in ["edges", "sources"].iter() {
+            let send_results = send_results.clone();
+            let plan = if *name == "edges" {
+                Plan::match_a(0, ":edge", 1)
+            } else {
+                Plan::Project(Project {
+                    variables: vec![0],
+                    plan: Box::new(Plan::match_a(0, ":edge", 1)),
+                })
+            };
+
+            let name = name.to_string();
+
+            worker.dataflow::<u64, _, _>(|scope| {
+                server
+                    .test_single(scope, Rule::named(name.clone(), plan))
+                    .inspect(move |x| {
+                        send_results.send((name.clone(), x.0.clone())).unwrap();
+                    });
+            });
+        }
+
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut received = vec![
+            results.recv_timeout(Duration::from_millis(400)).unwrap(),
+            results.recv_timeout(Duration::from_millis(400)).unwrap(),
+        ];
+        received.sort();
+
+        assert_eq!(
+            received,
+            vec![
+                ("edges".to_string(), vec![Value::Eid(1), Value::Eid(2)]),
+                ("sources".to_string(), vec![Value::Eid(1)]),
+            ]
+        );
+
+        assert_eq!(server.internal.arrangement_count(), arrangements);
+    });
+}
+
+#[test]
+fn test_index_sizes() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in [":name", ":age"].iter() {
+                server
+                    .create_attribute(scope, *name, AttributeConfig::tx_time(InputSemantics::Raw))
+                    .unwrap();
+            }
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":name", Value::String("Dipper".to_string())),
+                    Datom::add(2, ":name", Value::String("Mabel".to_string())),
+                    Datom::add(1, ":age", Value::Number(12)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let sizes = server.index_sizes();
+        let propose = |name: &str| {
+            sizes
+                .iter()
+                .find(|size| size.name == name && size.index == "forward_propose")
+                .cloned()
+                .unwrap()
+        };
+
+        assert_eq!(propose(":name").records, 2);
+        assert_eq!(propose(":age").records, 1);
+        assert!(propose(":name").batches > 0);
+        assert!(propose(":name").bytes > propose(":age").bytes);
+
+        // Sizes are reported largest first.
+        assert!(sizes.windows(2).all(|pair| pair[0].bytes >= pair[1].bytes));
+    });
+}
+
+#[test]
+fn test_schema_defaults() {
+    // Schemas predating cardinality and uniqueness constraints still
+    // load, without either of them.
+    let config: AttributeConfig = serde_json::from_str(
+        r#"{
+            "input_semantics": "Raw",
+            "trace_slack": null,
+            "index_direction": "Forward",
+            "query_support": "Basic"
+        }"#,
+    )
+    .unwrap();
+
+    assert_eq!(config.cardinality, Cardinality::Many);
+    assert_eq!(config.unique, Uniqueness::None);
+}
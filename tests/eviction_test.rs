@@ -0,0 +1,132 @@
+#![cfg(feature = "serde_json")]
+
+use std::sync::mpsc::channel;
+
+use declarative_dataflow::server::{Configuration, Server};
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+
+#[test]
+fn evict_and_page_in() {
+    let directory = std::env::temp_dir().join(format!("3df-eviction-{}", std::process::id()));
+    let path = directory.clone();
+
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Configuration {
+            eviction_directory: Some(path.to_string_lossy().to_string()),
+            ..Default::default()
+        });
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in &[":name", ":age"] {
+                server
+                    .create_attribute(scope, *name, AttributeConfig::tx_time(InputSemantics::Raw))
+                    .unwrap();
+            }
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":name", Value::from("Alice")),
+                    Datom::add(2, ":name", Value::from("Bob")),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let name = ":name".to_string();
+
+        // Attributes that may still change stay in memory.
+        assert!(server.evict(&name, worker.index()).is_err());
+        assert!(server.evict(&":age".to_string(), worker.index()).is_err());
+
+        server
+            .domain_of_mut(&name)
+            .unwrap()
+            .close_input(name.clone())
+            .unwrap();
+
+        for _i in 0..10 {
+            worker.step();
+        }
+
+        server.evict(&name, worker.index()).unwrap();
+        assert!(server.evict(&name, worker.index()).is_err());
+
+        {
+            let domain = server.domain_of(&name).unwrap();
+
+            assert!(domain.is_evicted(&name));
+            assert!(domain.has_attribute(&name));
+            assert!(!domain.forward_propose.contains_key(&name));
+        }
+
+        // Queries page the attribute back in.
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .test_single(scope, Rule::named("names", Plan::match_a(0, ":name", 1)))
+                .inspect(move |x| send_results.send((x.0.clone(), x.2)).unwrap());
+        });
+
+        worker.step_while(|| server.is_any_outdated());
+        for _i in 0..10 {
+            worker.step();
+        }
+
+        {
+            let domain = server.domain_of(&name).unwrap();
+
+            assert!(!domain.is_evicted(&name));
+            assert!(domain.forward_propose.contains_key(&name));
+        }
+
+        let mut names: Vec<_> = results.try_iter().collect();
+        names.sort();
+
+        assert_eq!(
+            names,
+            vec![
+                (vec![Value::Eid(1), Value::from("Alice")], 1),
+                (vec![Value::Eid(2), Value::from("Bob")], 1),
+            ]
+        );
+    });
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn eviction_requires_directory() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let name = ":name".to_string();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+        });
+
+        server
+            .domain_of_mut(&name)
+            .unwrap()
+            .close_input(name.clone())
+            .unwrap();
+
+        for _i in 0..10 {
+            worker.step();
+        }
+
+        assert!(server.evict(&name, worker.index()).is_err());
+        assert!(!server.domain_of(&name).unwrap().is_evicted(&name));
+    });
+}
@@ -0,0 +1,93 @@
+use declarative_dataflow::plan::explain::{Arrangement, Index};
+use declarative_dataflow::plan::Join;
+use declarative_dataflow::server::{Server, Strategy};
+use declarative_dataflow::{Aid, AttributeConfig, IndexDirection, Plan, QuerySupport};
+
+fn propose(attribute: &str, index: Index) -> Arrangement {
+    Arrangement::Attribute {
+        attribute: attribute.to_string(),
+        index,
+    }
+}
+
+#[test]
+fn explain_joins() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for aid in [":edge", ":name"].iter() {
+                let config = AttributeConfig {
+                    query_support: QuerySupport::AdaptiveWCO,
+                    index_direction: IndexDirection::Both,
+                    ..Default::default()
+                };
+
+                server.create_attribute(scope, *aid, config).unwrap();
+            }
+        });
+
+        let (a, b, n) = (0, 1, 2);
+        let plan = Plan::Join(Join {
+            variables: vec![b],
+            left_plan: Box::new(Plan::match_a(a, ":edge", b)),
+            right_plan: Box::new(Plan::match_a(b, ":name", n)),
+        });
+
+        let binary = server.explain(&plan, Some(Strategy::BinaryJoins));
+        assert!(binary.warnings.is_empty());
+        assert_eq!(binary.plan.operator, "Join");
+        assert_eq!(binary.plan.variables, vec![b, a, n]);
+        assert_eq!(binary.private_arrangements, 0);
+        assert_eq!(
+            binary.shared_arrangements,
+            vec![
+                propose(":edge", Index::ReversePropose),
+                propose(":name", Index::ForwardPropose),
+            ]
+        );
+
+        let wco = server.explain(&plan, Some(Strategy::WorstCaseOptimal));
+        assert!(wco.warnings.is_empty());
+        assert_eq!(wco.plan.operator, "Hector");
+        assert_eq!(wco.plan.inputs.len(), 2);
+        assert_eq!(wco.plan.inputs[0].operator, "Delta");
+        assert_eq!(wco.plan.inputs[0].inputs[0].variables, vec![a, b, n]);
+        assert_eq!(
+            wco.plan.inputs[0].inputs[0].arrangements,
+            vec![
+                propose(":name", Index::ForwardCount),
+                propose(":name", Index::ForwardPropose),
+                propose(":name", Index::ForwardValidate),
+            ]
+        );
+    });
+}
+
+#[test]
+fn explain_warnings() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(scope, ":edge", AttributeConfig::default())
+                .unwrap();
+        });
+
+        let plan = Plan::Join(Join {
+            variables: vec![1],
+            left_plan: Box::new(Plan::match_a(0, ":edge", 1)),
+            right_plan: Box::new(Plan::match_a(1, ":unknown", 2)),
+        });
+
+        let explanation = server.explain(&plan, Some(Strategy::BinaryJoins));
+        assert_eq!(
+            explanation.warnings,
+            vec![
+                "Attribute :edge doesn't maintain a ReversePropose index.".to_string(),
+                "Attribute :unknown does not exist.".to_string(),
+            ]
+        );
+    });
+}
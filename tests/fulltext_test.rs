@@ -0,0 +1,145 @@
+use std::collections::HashSet;
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use declarative_dataflow::plan::fulltext::tokenize;
+use declarative_dataflow::plan::Fulltext;
+use declarative_dataflow::server::{Register, Server};
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use Value::{Eid, String};
+
+#[test]
+fn tokenization() {
+    assert_eq!(
+        tokenize("The quick, quick brown-fox!"),
+        vec!["brown", "fox", "quick", "the"]
+    );
+    assert!(tokenize(" ... ").is_empty());
+}
+
+#[test]
+fn fulltext_search() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let config = AttributeConfig {
+                fulltext: true,
+                ..AttributeConfig::tx_time(InputSemantics::Raw)
+            };
+
+            server.create_attribute(scope, ":doc/body", config).unwrap();
+        });
+
+        let (quick, lazy, slow) = (
+            "The quick brown fox".to_string(),
+            "A lazy, quick Fox jumps".to_string(),
+            "A slow brown fox".to_string(),
+        );
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":doc/body", String(quick.clone())),
+                    Datom::add(2, ":doc/body", String(lazy.clone())),
+                    Datom::add(3, ":doc/body", String(slow.clone())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let plan = Plan::Fulltext(Fulltext {
+                variables: vec![0, 1],
+                attribute: ":doc/body".to_string(),
+                query: "fox QUICK".to_string(),
+            });
+
+            server
+                .test_single(scope, Rule::named("search", plan))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.1, x.2)).unwrap();
+                });
+        });
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut expected = HashSet::new();
+        expected.insert((vec![Eid(1), String(quick.clone())], 0, 1));
+        expected.insert((vec![Eid(2), String(lazy.clone())], 0, 1));
+
+        for _i in 0..expected.len() {
+            let result = results.recv_timeout(Duration::from_millis(400)).unwrap();
+            if !expected.remove(&result) {
+                panic!("unknown result {:?}", result);
+            }
+        }
+
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+
+        // Retracted values are no longer found, new ones are.
+        server
+            .transact(
+                vec![
+                    Datom::retract(2, ":doc/body", String(lazy.clone())),
+                    Datom::retract(3, ":doc/body", String(slow.clone())),
+                    Datom::add(3, ":doc/body", String("A quick fox".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut expected = HashSet::new();
+        expected.insert((vec![Eid(2), String(lazy)], 1, -1));
+        expected.insert((vec![Eid(3), String("A quick fox".to_string())], 1, 1));
+
+        for _i in 0..expected.len() {
+            let result = results.recv_timeout(Duration::from_millis(400)).unwrap();
+            if !expected.remove(&result) {
+                panic!("unknown result {:?}", result);
+            }
+        }
+
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+    });
+}
+
+#[test]
+fn fulltext_requires_index() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":doc/title",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+
+            let plan = Plan::Fulltext(Fulltext {
+                variables: vec![0, 1],
+                attribute: ":doc/title".to_string(),
+                query: "fox".to_string(),
+            });
+
+            server
+                .register(Register {
+                    rules: vec![Rule::named("search", plan)],
+                    publish: vec![],
+                    datalog: vec![],
+                })
+                .unwrap();
+
+            assert!(server.interest("search".to_string(), scope).is_err());
+        });
+    });
+}
@@ -0,0 +1,249 @@
+use declarative_dataflow::plan::graph::MAX_ITERATIONS;
+use declarative_dataflow::plan::{Graph, GraphAlgorithm};
+use declarative_dataflow::testing::TestServer;
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use Value::{Eid, Number};
+
+fn server() -> TestServer {
+    let mut server = TestServer::new();
+
+    for name in &[":edge/from", ":edge/to", ":edge/weight"] {
+        server
+            .create_attribute(name, AttributeConfig::tx_time(InputSemantics::Raw))
+            .unwrap();
+    }
+
+    server
+}
+
+fn graph(weighted: bool, algorithm: GraphAlgorithm) -> Plan<Aid> {
+    Plan::Graph(Graph {
+        variables: vec![0, 1],
+        source: ":edge/from".to_string(),
+        target: ":edge/to".to_string(),
+        weight: if weighted {
+            Some(":edge/weight".to_string())
+        } else {
+            None
+        },
+        algorithm,
+    })
+}
+
+fn edge(id: u64, from: u64, to: u64, weight: Option<i64>) -> Vec<Datom<Aid>> {
+    let mut tx_data = vec![
+        Datom::add(id, ":edge/from", Eid(from)),
+        Datom::add(id, ":edge/to", Eid(to)),
+    ];
+
+    if let Some(weight) = weight {
+        tx_data.push(Datom::add(id, ":edge/weight", Number(weight)));
+    }
+
+    tx_data
+}
+
+fn result(node: u64, value: i64) -> Vec<Value> {
+    vec![Eid(node), Number(value)]
+}
+
+#[test]
+fn shortest_paths() {
+    let mut server = server();
+
+    server
+        .register(vec![Rule::named(
+            "distances",
+            graph(true, GraphAlgorithm::ShortestPaths { source: Eid(1) }),
+        )])
+        .unwrap();
+    server.interest("distances").unwrap();
+
+    let mut tx_data = Vec::new();
+    tx_data.extend(edge(10, 1, 2, Some(4)));
+    tx_data.extend(edge(11, 1, 3, Some(1)));
+    tx_data.extend(edge(12, 3, 2, Some(1)));
+    tx_data.extend(edge(13, 2, 4, Some(1)));
+
+    let t0 = server.transact(tx_data).unwrap();
+
+    server.expect_results(
+        "distances",
+        vec![
+            (result(1, 0), t0, 1),
+            (result(2, 2), t0, 1),
+            (result(3, 1), t0, 1),
+            (result(4, 3), t0, 1),
+        ],
+    );
+
+    // Edges without a weight don't count, so the detour is gone.
+    let t1 = server
+        .transact(vec![Datom::retract(11, ":edge/weight", Number(1))])
+        .unwrap();
+
+    server.expect_results(
+        "distances",
+        vec![
+            (result(2, 2), t1, -1),
+            (result(2, 4), t1, 1),
+            (result(3, 1), t1, -1),
+            (result(4, 3), t1, -1),
+            (result(4, 5), t1, 1),
+        ],
+    );
+}
+
+#[test]
+fn connected_components() {
+    let mut server = server();
+
+    server
+        .register(vec![Rule::named(
+            "components",
+            graph(false, GraphAlgorithm::ConnectedComponents),
+        )])
+        .unwrap();
+    server.interest("components").unwrap();
+
+    let mut tx_data = Vec::new();
+    tx_data.extend(edge(10, 1, 2, None));
+    tx_data.extend(edge(11, 3, 2, None));
+    tx_data.extend(edge(12, 4, 5, None));
+
+    let t0 = server.transact(tx_data).unwrap();
+
+    let label = |node: u64, component: u64| vec![Eid(node), Eid(component)];
+
+    server.expect_results(
+        "components",
+        vec![
+            (label(1, 1), t0, 1),
+            (label(2, 1), t0, 1),
+            (label(3, 1), t0, 1),
+            (label(4, 4), t0, 1),
+            (label(5, 4), t0, 1),
+        ],
+    );
+
+    // Linking both components merges them.
+    let t1 = server.transact(edge(13, 5, 1, None)).unwrap();
+
+    server.expect_results(
+        "components",
+        vec![
+            (label(4, 4), t1, -1),
+            (label(4, 1), t1, 1),
+            (label(5, 4), t1, -1),
+            (label(5, 1), t1, 1),
+        ],
+    );
+}
+
+#[test]
+fn page_rank() {
+    let mut server = server();
+
+    server
+        .register(vec![Rule::named(
+            "ranks",
+            graph(false, GraphAlgorithm::PageRank { iterations: 2 }),
+        )])
+        .unwrap();
+    server.interest("ranks").unwrap();
+
+    let mut tx_data = Vec::new();
+    tx_data.extend(edge(10, 1, 2, None));
+    tx_data.extend(edge(11, 1, 3, None));
+    tx_data.extend(edge(12, 2, 3, None));
+
+    let t = server.transact(tx_data).unwrap();
+
+    server.expect_results(
+        "ranks",
+        vec![
+            (result(1, 150_000), t, 1),
+            (result(2, 213_750), t, 1),
+            (result(3, 702_500), t, 1),
+        ],
+    );
+}
+
+#[test]
+fn heavy_edges() {
+    let mut server = server();
+
+    server
+        .register(vec![
+            Rule::named(
+                "distances",
+                graph(true, GraphAlgorithm::ShortestPaths { source: Eid(1) }),
+            ),
+            Rule::named(
+                "ranks",
+                graph(true, GraphAlgorithm::PageRank { iterations: 1 }),
+            ),
+        ])
+        .unwrap();
+    server.interest("distances").unwrap();
+    server.interest("ranks").unwrap();
+
+    let mut tx_data = Vec::new();
+    tx_data.extend(edge(10, 1, 2, Some(std::i64::MAX)));
+    tx_data.extend(edge(11, 1, 3, Some(1)));
+    tx_data.extend(edge(12, 2, 4, Some(std::i64::MAX)));
+
+    let t = server.transact(tx_data).unwrap();
+
+    // Distances beyond the largest number are capped.
+    server.expect_results(
+        "distances",
+        vec![
+            (result(1, 0), t, 1),
+            (result(2, std::i64::MAX), t, 1),
+            (result(3, 1), t, 1),
+            (result(4, std::i64::MAX), t, 1),
+        ],
+    );
+
+    server.expect_results(
+        "ranks",
+        vec![
+            (result(1, 150_000), t, 1),
+            (result(2, 1_000_000), t, 1),
+            (result(3, 150_000), t, 1),
+            (result(4, 1_000_000), t, 1),
+        ],
+    );
+}
+
+#[test]
+fn invalid_graph() {
+    let mut server = server();
+
+    server
+        .register(vec![
+            Rule::named(
+                "ranks",
+                graph(false, GraphAlgorithm::PageRank { iterations: 0 }),
+            ),
+            Rule::named(
+                "unbounded",
+                graph(
+                    false,
+                    GraphAlgorithm::PageRank {
+                        iterations: MAX_ITERATIONS + 1,
+                    },
+                ),
+            ),
+        ])
+        .unwrap();
+
+    let error = server.interest("ranks").unwrap_err();
+    assert_eq!(error.category, "df.error.category/incorrect");
+    assert_eq!(error.clause, Some(vec![]));
+
+    let error = server.interest("unbounded").unwrap_err();
+    assert_eq!(error.category, "df.error.category/unsupported");
+    assert_eq!(error.clause, Some(vec![]));
+}
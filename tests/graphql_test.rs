@@ -0,0 +1,145 @@
+#![cfg(feature = "graphql")]
+
+use declarative_dataflow::plan::graphql::{field_name, schema, GraphQl, OperationKind};
+use declarative_dataflow::server::{GraphQlRequest, Request};
+use declarative_dataflow::testing::TestServer;
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Value, ValueType};
+use Value::{Eid, String};
+
+fn attributes() -> Vec<(Aid, AttributeConfig)> {
+    let typed = |value_type| AttributeConfig {
+        value_type: Some(value_type),
+        ..AttributeConfig::tx_time(InputSemantics::Raw)
+    };
+
+    vec![
+        (":person/age".to_string(), typed(ValueType::Number)),
+        (":person/first-name".to_string(), typed(ValueType::String)),
+        (":person/friend".to_string(), typed(ValueType::Eid)),
+        ("weird name".to_string(), typed(ValueType::String)),
+    ]
+}
+
+#[test]
+fn field_names() {
+    assert_eq!(
+        field_name(":person/first-name").unwrap(),
+        "person_first_name"
+    );
+    assert_eq!(field_name("name").unwrap(), "name");
+    assert_eq!(field_name(":db.type/ident").unwrap(), "db_type_ident");
+    assert!(field_name("weird name").is_none());
+    assert!(field_name(":1st").is_none());
+    assert!(field_name(":").is_none());
+}
+
+#[test]
+fn generates_schemas() {
+    let schema = schema(&attributes());
+
+    assert!(schema.contains("subscription: Entity"));
+    assert!(schema.contains("  \":person/age\"\n  person_age: Int\n"));
+    assert!(schema.contains("  person_first_name: String\n"));
+    assert!(schema.contains("  person_friend: [Entity]\n"));
+    assert!(!schema.contains("weird"));
+}
+
+#[test]
+fn compiles_operations() {
+    let compile = |query: &str| GraphQl::compile(query.to_string(), &attributes());
+
+    let (kind, _) = compile("{ person_friend { person_age } }").unwrap();
+    assert_eq!(kind, OperationKind::Query);
+
+    let (kind, _) = compile("query { person_first_name }").unwrap();
+    assert_eq!(kind, OperationKind::Query);
+
+    let (kind, _) =
+        compile(r#"subscription { person_friend(person_first_name: "Mabel") { person_age } }"#)
+            .unwrap();
+    assert_eq!(kind, OperationKind::Subscription);
+
+    let category = |query: &str| compile(query).unwrap_err().category;
+
+    assert_eq!(category("{ person_age"), "df.error.category/incorrect");
+    assert_eq!(category("{ unknown }"), "df.error.category/not-found");
+    assert_eq!(
+        category("{ person_friend(unknown: 1) { person_age } }"),
+        "df.error.category/not-found"
+    );
+    assert_eq!(
+        category("mutation { person_age }"),
+        "df.error.category/unsupported"
+    );
+    assert_eq!(
+        category("{ ...friends } fragment friends on Entity { person_age }"),
+        "df.error.category/unsupported"
+    );
+    assert_eq!(
+        category("query($name: String) { person_friend(person_first_name: $name) { person_age } }"),
+        "df.error.category/unsupported"
+    );
+}
+
+#[test]
+fn streams_subscriptions() {
+    let mut server = TestServer::new();
+
+    for (name, config) in attributes().into_iter() {
+        server.create_attribute(&name, config).unwrap();
+    }
+
+    let requests = server
+        .server()
+        .graphql(GraphQlRequest {
+            name: "names".to_string(),
+            query: "subscription { person_first_name }".to_string(),
+        })
+        .unwrap();
+
+    match &requests[..] {
+        [Request::Register(req), Request::Interest(interest)] => {
+            assert_eq!(req.publish, vec!["names".to_string()]);
+            assert_eq!(interest.name, "names");
+
+            server.server().register(req.clone()).unwrap();
+            server.interest(&interest.name).unwrap();
+        }
+        other => panic!("unexpected requests {:?}", other),
+    }
+
+    let t = server
+        .transact(vec![Datom::add(
+            1,
+            ":person/first-name",
+            String("Dipper".to_string()),
+        )])
+        .unwrap();
+
+    server.expect_results(
+        "names",
+        vec![(
+            vec![
+                Eid(1),
+                Value::Aid(":person/first-name".to_string()),
+                String("Dipper".to_string()),
+            ],
+            t,
+            1,
+        )],
+    );
+
+    // Queries are evaluated once, without registering anything.
+    let requests = server
+        .server()
+        .graphql(GraphQlRequest {
+            name: "ignored".to_string(),
+            query: "{ person_age }".to_string(),
+        })
+        .unwrap();
+
+    match &requests[..] {
+        [Request::Query(_)] => {}
+        other => panic!("unexpected requests {:?}", other),
+    }
+}
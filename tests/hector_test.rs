@@ -437,3 +437,99 @@ fn run_hector_cases() {
         });
     }
 }
+
+#[test]
+fn per_query_strategy() {
+    use declarative_dataflow::plan::{Join, Project};
+    use declarative_dataflow::server::{Register, Strategy};
+
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (a, b, c) = (0, 1, 2);
+        let plan = Plan::Project(Project {
+            variables: vec![a, b, c],
+            plan: Box::new(Plan::Join(Join {
+                variables: vec![b],
+                left_plan: Box::new(Plan::match_a(a, ":edge", b)),
+                right_plan: Box::new(Plan::match_a(b, ":edge", c)),
+            })),
+        });
+
+        // Interests are tracked by name, so each strategy gets a rule
+        // of its own.
+        server
+            .register(Register {
+                rules: vec![
+                    Rule::named("paths_binary", plan.clone()),
+                    Rule::named("paths_wco", plan),
+                ],
+                publish: vec![],
+                datalog: vec![],
+            })
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let config = AttributeConfig {
+                trace_slack: Some(Time::TxId(1)),
+                query_support: QuerySupport::AdaptiveWCO,
+                index_direction: IndexDirection::Both,
+                ..Default::default()
+            };
+
+            server.create_attribute(scope, ":edge", config).unwrap();
+
+            let strategies = [
+                ("paths_binary", Strategy::BinaryJoins),
+                ("paths_wco", Strategy::WorstCaseOptimal),
+            ];
+
+            for (name, strategy) in strategies.iter() {
+                let send_results = send_results.clone();
+                let strategy = *strategy;
+
+                server
+                    .interest_using(name.to_string(), scope, Some(strategy))
+                    .unwrap()
+                    .probe_with(&mut server.probe)
+                    .inspect(move |x| {
+                        send_results.send((strategy, x.clone())).unwrap();
+                    });
+            }
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":edge", Eid(2)),
+                    Datom::add(2, ":edge", Eid(3)),
+                    Datom::add(2, ":edge", Eid(4)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut binary = HashSet::new();
+        let mut wco = HashSet::new();
+
+        for _i in 0..4 {
+            match results.recv_timeout(Duration::from_millis(400)).unwrap() {
+                (Strategy::BinaryJoins, result) => binary.insert(result),
+                (Strategy::WorstCaseOptimal, result) => wco.insert(result),
+            };
+        }
+
+        let expected: HashSet<(Vec<Value>, u64, isize)> = HashSet::from_iter(vec![
+            (vec![Eid(1), Eid(2), Eid(3)], 0, 1),
+            (vec![Eid(1), Eid(2), Eid(4)], 0, 1),
+        ]);
+
+        assert_eq!(binary, expected);
+        assert_eq!(wco, expected);
+    });
+}
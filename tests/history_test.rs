@@ -0,0 +1,193 @@
+use std::collections::HashSet;
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use declarative_dataflow::plan::History;
+use declarative_dataflow::server::{Register, Server};
+use declarative_dataflow::timestamp::Time;
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+
+#[test]
+fn history() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":age",
+                    AttributeConfig::uncompacted(InputSemantics::Raw),
+                )
+                .unwrap();
+        });
+
+        server
+            .transact(vec![Datom::add(1, ":age", Value::Number(12))], 0, 0)
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+
+        server
+            .transact(
+                vec![
+                    Datom::retract(1, ":age", Value::Number(12)),
+                    Datom::add(1, ":age", Value::Number(13)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 2).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        // Queries created later on still see every change.
+        worker.dataflow::<u64, _, _>(|scope| {
+            let plan = Plan::History(History {
+                variables: vec![0, 1, 2, 3],
+                attribute: ":age".to_string(),
+            });
+
+            server
+                .test_single(scope, Rule::named("history", plan))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.1, x.2)).unwrap();
+                });
+        });
+
+        server.advance_domain(None, 3).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut expected = HashSet::new();
+        expected.insert((
+            vec![
+                Value::Eid(1),
+                Value::Number(12),
+                Value::Number(0),
+                Value::Number(1),
+            ],
+            0,
+            1,
+        ));
+        expected.insert((
+            vec![
+                Value::Eid(1),
+                Value::Number(12),
+                Value::Number(1),
+                Value::Number(-1),
+            ],
+            1,
+            1,
+        ));
+        expected.insert((
+            vec![
+                Value::Eid(1),
+                Value::Number(13),
+                Value::Number(1),
+                Value::Number(1),
+            ],
+            1,
+            1,
+        ));
+
+        for _ in 0..expected.len() {
+            let result = results.recv_timeout(Duration::from_millis(400)).unwrap();
+            assert!(expected.remove(&result), "unexpected result {:?}", result);
+        }
+
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+    });
+}
+
+#[test]
+fn as_of() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(scope, ":age", AttributeConfig::tx_time(InputSemantics::Raw))
+                .unwrap();
+        });
+
+        server
+            .register(Register {
+                rules: vec![Rule::named("ages", Plan::match_a(0, ":age", 1))],
+                publish: vec![],
+                datalog: vec![],
+            })
+            .unwrap();
+
+        server
+            .transact(vec![Datom::add(1, ":age", Value::Number(12))], 0, 0)
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+
+        server
+            .transact(
+                vec![
+                    Datom::retract(1, ":age", Value::Number(12)),
+                    Datom::add(1, ":age", Value::Number(13)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 2).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .interest_as_of("ages".to_string(), 0, scope)
+                .unwrap()
+                .probe_with(&mut server.probe)
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.1, x.2)).unwrap();
+                });
+        });
+
+        server.advance_domain(None, 3).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv_timeout(Duration::from_millis(400)).unwrap(),
+            (vec![Value::Eid(1), Value::Number(12)], 0, 1)
+        );
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+    });
+}
+
+#[test]
+fn as_of_compacted() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let config = AttributeConfig {
+                trace_slack: Some(Time::TxId(1)),
+                ..AttributeConfig::tx_time(InputSemantics::Raw)
+            };
+
+            server.create_attribute(scope, ":age", config).unwrap();
+        });
+
+        server
+            .register(Register {
+                rules: vec![Rule::named("ages", Plan::match_a(0, ":age", 1))],
+                publish: vec![],
+                datalog: vec![],
+            })
+            .unwrap();
+
+        server.advance_domain(None, 10).unwrap();
+        server.internal.advance().unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            assert!(server.interest_as_of("ages".to_string(), 0, scope).is_err());
+        });
+    });
+}
@@ -7,9 +7,12 @@ use std::time::Duration;
 use timely::dataflow::channels::pact::Pipeline;
 use timely::dataflow::operators::Operator;
 
-use declarative_dataflow::server::Server;
+use declarative_dataflow::plan::EventWindow;
+use declarative_dataflow::server::{Configuration, Server};
+use declarative_dataflow::testing::TestServer;
 use declarative_dataflow::timestamp::pair::Pair;
 use declarative_dataflow::timestamp::Time;
+use declarative_dataflow::Cardinality;
 use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
 use Time::TxId;
 use Value::{Eid, Number};
@@ -295,6 +298,115 @@ fn last_write_wins_unordered() {
     .run();
 }
 
+#[test]
+fn multiplicities() {
+    for (semantics, diff) in vec![(InputSemantics::Raw, 2), (InputSemantics::Distinct, 1)] {
+        let mut server = TestServer::new();
+
+        server
+            .create_attribute(":visit", AttributeConfig::tx_time(semantics))
+            .unwrap();
+        server
+            .register(vec![Rule::named("visits", Plan::match_a(0, ":visit", 1))])
+            .unwrap();
+        server.interest("visits").unwrap();
+
+        // Only distinct attributes pay for deduplicating inputs.
+        let t = server
+            .transact(vec![
+                Datom::add(1, ":visit", Number(1)),
+                Datom::add(1, ":visit", Number(1)),
+            ])
+            .unwrap();
+
+        server.expect_results("visits", vec![(vec![Eid(1), Number(1)], t, diff)]);
+    }
+
+    let semantics: InputSemantics = serde_json::from_str("\"CardinalityOne\"").unwrap();
+    assert_eq!(semantics, InputSemantics::LastWriteWins);
+}
+
+#[test]
+fn cardinality_one() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let config = AttributeConfig {
+                cardinality: Cardinality::One,
+                ..AttributeConfig::tx_time(InputSemantics::Raw)
+            };
+
+            server.create_attribute(scope, ":amount", config).unwrap();
+
+            server
+                .test_single(scope, Rule::named("query", Plan::match_a(0, ":amount", 1)))
+                .inner
+                .sink(Pipeline, "Results", move |input| {
+                    input.for_each(|_time, data| {
+                        for datum in data.iter() {
+                            send_results.send(datum.clone()).unwrap()
+                        }
+                    });
+                });
+        });
+
+        let mut transactions = vec![
+            vec![
+                Datom::add(1, ":amount", Number(5)),
+                Datom::add(2, ":amount", Number(5)),
+            ],
+            vec![
+                Datom::add(1, ":amount", Number(10)),
+                Datom::add(2, ":amount", Number(5)),
+            ],
+            vec![
+                Datom::retract(1, ":amount", Number(5)),
+                Datom::retract(2, ":amount", Number(5)),
+            ],
+        ];
+
+        let mut expectations = vec![
+            vec![
+                (vec![Eid(1), Number(5)], 0, 1),
+                (vec![Eid(2), Number(5)], 0, 1),
+            ],
+            vec![
+                (vec![Eid(1), Number(5)], 1, -1),
+                (vec![Eid(1), Number(10)], 1, 1),
+            ],
+            vec![(vec![Eid(2), Number(5)], 2, -1)],
+        ];
+
+        for (next_tx, (tx_data, mut expected_tuples)) in transactions
+            .drain(..)
+            .zip(expectations.drain(..))
+            .enumerate()
+        {
+            server.transact(tx_data, 0, 0).unwrap();
+            server.advance_domain(None, next_tx as u64 + 1).unwrap();
+
+            worker.step_while(|| server.is_any_outdated());
+
+            let mut expected: HashSet<(Vec<Value>, u64, isize)> =
+                HashSet::from_iter(expected_tuples.drain(..));
+
+            for _i in 0..expected.len() {
+                let result = results
+                    .recv_timeout(Duration::from_millis(400))
+                    .expect("no result");
+
+                if !expected.remove(&result) {
+                    panic!("Unknown result {:?}.", result);
+                }
+            }
+
+            assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+        }
+    });
+}
+
 // #[test]
 // fn compare_and_swap() {
 //     use differential_dataflow::input::Input;
@@ -506,3 +618,168 @@ fn bitemporal() {
     ]
     .run();
 }
+
+#[test]
+fn sliding_window() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let config = AttributeConfig {
+                window: Some(Time::TxId(2)),
+                ..AttributeConfig::tx_time(InputSemantics::Raw)
+            };
+
+            server.create_attribute(scope, ":click", config).unwrap();
+
+            server
+                .test_single(scope, Rule::named("clicks", Plan::match_a(0, ":click", 1)))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.1, x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(vec![Datom::add(1, ":click", Value::Number(100))], 0, 0)
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        server
+            .transact(vec![Datom::add(2, ":click", Value::Number(200))], 0, 0)
+            .unwrap();
+
+        // Explicit retractions cancel expiry.
+        server
+            .transact(vec![Datom::retract(2, ":click", Value::Number(200))], 0, 0)
+            .unwrap();
+
+        for t in 2..5 {
+            server.advance_domain(None, t).unwrap();
+        }
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut received: Vec<(Vec<Value>, u64, isize)> = results.try_iter().collect();
+        received.sort_by_key(|x| (x.1, x.2));
+
+        assert_eq!(
+            received,
+            vec![
+                (vec![Value::Eid(1), Value::Number(100)], 0, 1),
+                (vec![Value::Eid(1), Value::Number(100)], 2, -1),
+            ]
+        );
+    });
+}
+
+#[test]
+fn clock_driven_window() {
+    timely::execute_directly(move |worker| {
+        let config = Configuration {
+            clock: Some(Duration::from_millis(100)),
+            ..Default::default()
+        };
+        let mut server = Server::<Aid, Duration, u64>::new(config);
+        let (send_results, results) = channel();
+
+        worker.dataflow::<Duration, _, _>(|scope| {
+            let config = AttributeConfig {
+                window: Some(Time::Real(Duration::from_millis(150))),
+                ..AttributeConfig::real_time(InputSemantics::Raw)
+            };
+
+            server.create_attribute(scope, ":click", config).unwrap();
+
+            server
+                .test_single(scope, Rule::named("clicks", Plan::match_a(0, ":click", 1)))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.1, x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(vec![Datom::add(1, ":click", Value::Number(100))], 0, 0)
+            .unwrap();
+
+        // No further inputs arrive, but the clock moves on.
+        server.advance_clock(Duration::from_millis(250)).unwrap();
+        assert_eq!(server.internal.epoch(), &Duration::from_millis(200));
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut received: Vec<(Vec<Value>, Duration, isize)> = results.try_iter().collect();
+        received.sort_by_key(|x| (x.1, x.2));
+
+        assert_eq!(
+            received,
+            vec![
+                (
+                    vec![Value::Eid(1), Value::Number(100)],
+                    Duration::from_secs(0),
+                    1
+                ),
+                (
+                    vec![Value::Eid(1), Value::Number(100)],
+                    Duration::from_millis(150),
+                    -1
+                ),
+            ]
+        );
+    });
+}
+
+#[test]
+fn event_window() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, Pair<Duration, u64>, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<Pair<Duration, u64>, _, _>(|scope| {
+            server
+                .create_attribute(scope, ":reading", AttributeConfig::default())
+                .unwrap();
+
+            let plan = Plan::EventWindow(EventWindow {
+                variables: vec![0, 1],
+                plan: Box::new(Plan::match_a(0, ":reading", 1)),
+                from: Some(Duration::from_secs(1)),
+                until: Some(Duration::from_secs(3)),
+            });
+
+            server
+                .test_single(scope, Rule::named("readings", plan))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.1.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add_bitemporal(1, ":reading", Number(10), Duration::from_secs(0), 0),
+                    Datom::add_bitemporal(1, ":reading", Number(20), Duration::from_secs(2), 0),
+                    Datom::add_bitemporal(1, ":reading", Number(30), Duration::from_secs(4), 0),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server
+            .advance_domain(None, Pair::new(Duration::from_secs(10), 1))
+            .unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv_timeout(Duration::from_millis(400)).unwrap(),
+            (
+                vec![Eid(1), Number(20)],
+                Pair::new(Duration::from_secs(2), 0),
+                1
+            )
+        );
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+    });
+}
@@ -0,0 +1,95 @@
+use std::collections::HashSet;
+use std::sync::mpsc::channel;
+
+use declarative_dataflow::plan::Join;
+use declarative_dataflow::server::introspection::{entity, QUERY_CLIENTS, QUERY_NAME};
+use declarative_dataflow::server::{Register, Server};
+use declarative_dataflow::{Aid, AttributeConfig, InputSemantics, Plan, Rule, Value};
+use Value::{Eid, Number, String};
+
+#[test]
+fn registered_queries() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server.enable_introspection(scope).unwrap();
+            assert!(server.enable_introspection(scope).is_err());
+
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+        });
+
+        assert!(server.system.attributes.contains_key(QUERY_NAME));
+
+        server
+            .register(Register {
+                rules: vec![
+                    Rule::named("names", Plan::match_a(0, ":name", 1)),
+                    Rule::named(
+                        "queries",
+                        Plan::Join(Join {
+                            variables: vec![0],
+                            left_plan: Box::new(Plan::match_a(0, QUERY_NAME, 1)),
+                            right_plan: Box::new(Plan::match_a(0, QUERY_CLIENTS, 2)),
+                        }),
+                    ),
+                ],
+                publish: vec![],
+                datalog: vec![],
+            })
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .interest("queries".to_string(), scope)
+                .unwrap()
+                .probe_with(&mut server.probe)
+                .inspect(move |x| send_results.send(x.clone()).unwrap());
+        });
+
+        server
+            .interests
+            .entry("names".to_string())
+            .or_insert_with(HashSet::new)
+            .insert(7);
+
+        let query = |name: &str, clients: i64| {
+            vec![
+                Eid(entity("query", name)),
+                String(name.to_string()),
+                Number(clients),
+            ]
+        };
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.probe.less_than(&1));
+
+        let mut published: Vec<_> = results.try_iter().collect();
+        published.sort();
+
+        let mut expected = vec![(query("names", 1), 0, 1), (query("queries", 0), 0, 1)];
+        expected.sort();
+
+        assert_eq!(published, expected);
+
+        // Only changes since the last refresh are published.
+        server.interests.remove("names");
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.probe.less_than(&2));
+
+        let mut published: Vec<_> = results.try_iter().collect();
+        published.sort();
+
+        assert_eq!(
+            published,
+            vec![(query("names", 0), 1, 1), (query("names", 1), 1, -1)]
+        );
+    });
+}
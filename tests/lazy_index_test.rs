@@ -0,0 +1,127 @@
+use std::collections::HashSet;
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use declarative_dataflow::plan::Join;
+use declarative_dataflow::server::{Configuration, Register, Server};
+use declarative_dataflow::{Aid, AttributeConfig, Datom, IndexDirection, InputSemantics};
+use declarative_dataflow::{Plan, Rule, Value};
+use Value::{Eid, String};
+
+/// [:find ?person ?pet :where [?person :person/city ?city] [?pet :pet/city ?city]]
+fn neighbours() -> Plan<Aid> {
+    let (person, city, pet) = (0, 1, 2);
+
+    Plan::Join(Join {
+        variables: vec![city],
+        left_plan: Box::new(Plan::match_a(person, ":person/city", city)),
+        right_plan: Box::new(Plan::match_a(pet, ":pet/city", city)),
+    })
+}
+
+#[test]
+fn missing_reverse_indices() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in &[":person/city", ":pet/city"] {
+                server
+                    .create_attribute(scope, *name, AttributeConfig::tx_time(InputSemantics::Raw))
+                    .unwrap();
+            }
+
+            server
+                .register(Register {
+                    rules: vec![Rule::named("neighbours", neighbours())],
+                    publish: vec![],
+                    datalog: vec![],
+                })
+                .unwrap();
+
+            // Joining on values requires reverse indices.
+            assert!(server.interest("neighbours".to_string(), scope).is_err());
+        });
+    });
+}
+
+#[test]
+fn lazy_reverse_indices() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Configuration {
+            enable_lazy_indices: true,
+            ..Default::default()
+        });
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in &[":person/city", ":pet/city"] {
+                server
+                    .create_attribute(scope, *name, AttributeConfig::tx_time(InputSemantics::Raw))
+                    .unwrap();
+            }
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":person/city", String("Zurich".to_string())),
+                    Datom::add(2, ":person/city", String("Berlin".to_string())),
+                    Datom::add(10, ":pet/city", String("Zurich".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .test_single(scope, Rule::named("neighbours", neighbours()))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.1, x.2)).unwrap();
+                });
+        });
+
+        // The attributes are indexed in both directions from now on.
+        for name in [":person/city", ":pet/city"].iter() {
+            let aid = name.to_string();
+            let domain = server.domain_of(&aid).unwrap();
+
+            assert!(domain.reverse_propose.contains_key(&aid));
+            assert_eq!(
+                domain.attributes[&aid].index_direction,
+                IndexDirection::Both
+            );
+        }
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        server
+            .transact(
+                vec![Datom::add(11, ":pet/city", String("Berlin".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let zurich = String("Zurich".to_string());
+        let berlin = String("Berlin".to_string());
+
+        let mut expected = HashSet::new();
+        expected.insert((vec![zurich, Eid(1), Eid(10)], 0, 1));
+        expected.insert((vec![berlin, Eid(2), Eid(11)], 1, 1));
+
+        for _i in 0..expected.len() {
+            let result = results.recv_timeout(Duration::from_millis(400)).unwrap();
+            if !expected.remove(&result) {
+                panic!("unknown result {:?}", result);
+            }
+        }
+
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+    });
+}
@@ -0,0 +1,26 @@
+use declarative_dataflow::logging::LifecycleEvent;
+
+#[test]
+fn lifecycle_events_as_logfmt() {
+    let created = LifecycleEvent::AttributeCreated {
+        name: ":name".to_string(),
+    };
+    assert_eq!(created.to_string(), "event=attribute_created name=:name");
+
+    let attached = LifecycleEvent::SourceAttached {
+        attributes: vec![":a".to_string(), ":b".to_string()],
+    };
+    assert_eq!(
+        attached.to_string(),
+        "event=source_attached attributes=:a,:b"
+    );
+
+    let applied = LifecycleEvent::TxApplied {
+        datoms: 3,
+        epoch: "Duration { secs: 1, nanos: 0 }".to_string(),
+    };
+    assert_eq!(
+        applied.to_string(),
+        "event=tx_applied datoms=3 epoch=\"Duration { secs: 1, nanos: 0 }\""
+    );
+}
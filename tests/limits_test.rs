@@ -0,0 +1,112 @@
+use std::collections::HashSet;
+
+use declarative_dataflow::plan::Join;
+use declarative_dataflow::server::limits::Limits;
+use declarative_dataflow::server::Configuration;
+use declarative_dataflow::testing::TestServer;
+use declarative_dataflow::{AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+
+fn server(query_limits: Limits) -> TestServer {
+    let mut server = TestServer::with_config(Configuration {
+        query_limits,
+        ..Default::default()
+    });
+
+    for name in &[":name", ":age"] {
+        server
+            .create_attribute(name, AttributeConfig::tx_time(InputSemantics::Raw))
+            .unwrap();
+    }
+
+    server
+        .register(vec![Rule::named("names", Plan::match_a(0, ":name", 1))])
+        .unwrap();
+
+    server
+}
+
+fn names(from: u64, count: u64) -> Vec<Datom<String>> {
+    (from..from + count)
+        .map(|e| Datom::add(e, ":name", Value::String(format!("user {}", e))))
+        .collect()
+}
+
+#[test]
+fn oversized_queries() {
+    let mut server = server(Limits {
+        max_clauses: Some(2),
+        ..Default::default()
+    });
+
+    let join = Plan::Join(Join {
+        variables: vec![0],
+        left_plan: Box::new(Plan::match_a(0, ":name", 1)),
+        right_plan: Box::new(Plan::match_a(0, ":age", 2)),
+    });
+    assert_eq!(join.clauses(), 3);
+
+    server.register(vec![Rule::named("ages", join)]).unwrap();
+
+    server.interest("names").unwrap();
+    let error = server.interest("ages").unwrap_err();
+    assert_eq!(error.category, "df.error.category/unsupported");
+}
+
+#[test]
+fn runaway_queries() {
+    let mut server = server(Limits {
+        max_outputs: Some(2),
+        ..Default::default()
+    });
+
+    server.interest("names").unwrap();
+
+    server.transact(names(1, 2)).unwrap();
+    assert!(server.server().take_violations().is_empty());
+
+    server.transact(names(3, 3)).unwrap();
+    let violations = server.server().take_violations();
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].0, "names");
+    assert_eq!(violations[0].1.category, "df.error.category/interrupted");
+
+    // Each dataflow is reported only once.
+    server.transact(names(6, 3)).unwrap();
+    assert!(server.server().take_violations().is_empty());
+
+    server
+        .server()
+        .interests
+        .entry("names".to_string())
+        .or_insert_with(HashSet::new)
+        .insert(7);
+
+    let killed = server.server().kill(&"names".to_string()).unwrap();
+    let clients: HashSet<u64> = vec![7].into_iter().collect();
+    assert_eq!(killed, vec![("names".to_string(), clients)]);
+    assert!(server.server().interests.is_empty());
+
+    let error = server.server().kill(&"names".to_string()).unwrap_err();
+    assert_eq!(error.category, "df.error.category/not-found");
+
+    // The rule itself remains in place.
+    server.interest("names").unwrap();
+}
+
+#[test]
+fn bloated_queries() {
+    let mut server = server(Limits {
+        max_bytes: Some(1024),
+        ..Default::default()
+    });
+
+    server.interest("names").unwrap();
+
+    server.transact(names(1, 1)).unwrap();
+    assert!(server.server().take_violations().is_empty());
+
+    server.transact(names(2, 100)).unwrap();
+    let violations = server.server().take_violations();
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].1.category, "df.error.category/interrupted");
+}
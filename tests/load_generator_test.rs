@@ -0,0 +1,95 @@
+use std::collections::HashSet;
+
+use declarative_dataflow::sources::load_generator::{GeneratedAttribute, Generator};
+use declarative_dataflow::sources::LoadGenerator;
+use declarative_dataflow::{Aid, Value, ValueType};
+
+fn generator(entity_skew: u32) -> LoadGenerator<Aid> {
+    LoadGenerator {
+        attributes: vec![
+            GeneratedAttribute {
+                name: ":user/age".to_string(),
+                value_type: ValueType::Number,
+                cardinality: 100,
+                skew: 0,
+            },
+            GeneratedAttribute {
+                name: ":user/city".to_string(),
+                value_type: ValueType::String,
+                cardinality: 10,
+                skew: 0,
+            },
+        ],
+        entities: 1000,
+        entity_skew,
+        rate: 1000,
+        limit: None,
+        seed: 42,
+        interval: None,
+    }
+}
+
+#[test]
+fn generate_datoms() {
+    let source = generator(0);
+
+    let datoms = |worker| {
+        let mut generator = Generator::new(&source, worker, 4);
+        (0..1000)
+            .map(|_i| generator.next_datom().unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    // Runs are reproducible.
+    assert_eq!(datoms(1), datoms(1));
+    assert_ne!(datoms(1), datoms(2));
+
+    let mut cities = HashSet::new();
+    for (idx, (e, v)) in datoms(1).into_iter() {
+        // Workers generate disjoint entities.
+        match e {
+            Value::Eid(e) => assert!(e < 1000 && e % 4 == 1),
+            _ => panic!("unexpected entity {:?}", e),
+        }
+
+        match (idx, v) {
+            (0, Value::Number(age)) => assert!(age >= 0 && age < 100),
+            (1, Value::String(city)) => {
+                cities.insert(city);
+            }
+            (idx, v) => panic!("unexpected value {:?} for attribute {}", v, idx),
+        }
+    }
+
+    assert_eq!(cities.len(), 10);
+}
+
+#[test]
+fn skewed_entities() {
+    let hot_draws = |entity_skew| {
+        let mut generator = Generator::new(&generator(entity_skew), 0, 1);
+        (0..1000)
+            .filter(|_i| match generator.next_datom() {
+                Some((_idx, (Value::Eid(e), _v))) => e < 100,
+                _ => false,
+            })
+            .count()
+    };
+
+    // Skewed draws concentrate on the first few entities.
+    assert!(hot_draws(0) < 200);
+    assert!(hot_draws(3) > 400);
+}
+
+#[test]
+fn nothing_to_generate() {
+    let source = LoadGenerator {
+        entities: 2,
+        ..generator(0)
+    };
+
+    // There are more workers than entities.
+    assert!(!Generator::new(&source, 1, 4).is_empty());
+    assert!(Generator::new(&source, 2, 4).is_empty());
+    assert!(Generator::new(&source, 2, 4).next_datom().is_none());
+}
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use declarative_dataflow::metrics::{Metrics, Recorder, OUTPUT_QUEUE_DEPTH};
+use declarative_dataflow::server::Server;
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Value};
+use Value::String;
+
+#[test]
+fn transactions_are_counted() {
+    let metrics = Arc::new(Metrics::new());
+    let shared = metrics.clone();
+
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        server.enable_metrics(Recorder::new(shared.clone(), worker.index()));
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":name", String("Dipper".to_string())),
+                    Datom::add(2, ":name", String("Mabel".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server
+            .transact(
+                vec![Datom::retract(2, ":name", String("Mabel".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        server.internal.advance().unwrap();
+    });
+
+    let rendered = metrics.render();
+
+    assert!(rendered.contains("# TYPE declarative_transactions_total counter\n"));
+    assert!(rendered.contains("declarative_transactions_total{worker=\"0\"} 2\n"));
+    assert!(rendered.contains("declarative_datoms_total{worker=\"0\",attribute=\":name\"} 3\n"));
+    assert!(rendered.contains("declarative_attribute_datoms{worker=\"0\",attribute=\":name\"} 1\n"));
+    assert!(rendered.contains("declarative_domain_epoch{worker=\"0\"} 1\n"));
+    assert!(rendered.contains(
+        "declarative_arrangement_size{worker=\"0\",attribute=\":name\",index=\"forward_propose\"}"
+    ));
+}
+
+#[test]
+fn removed_samples_are_not_rendered() {
+    let metrics = Arc::new(Metrics::new());
+    let recorder = Recorder::new(metrics.clone(), 0);
+
+    recorder.set(OUTPUT_QUEUE_DEPTH, vec![("client", "1".to_string())], 3.0);
+    recorder.set(OUTPUT_QUEUE_DEPTH, vec![("client", "2".to_string())], 5.0);
+    recorder.remove(OUTPUT_QUEUE_DEPTH, vec![("client", "1".to_string())]);
+
+    let rendered = metrics.render();
+
+    assert!(rendered.contains("# TYPE declarative_output_queue_depth gauge\n"));
+    assert!(!rendered.contains("client=\"1\""));
+    assert!(rendered.contains("declarative_output_queue_depth{worker=\"0\",client=\"2\"} 5\n"));
+}
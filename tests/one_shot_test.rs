@@ -0,0 +1,119 @@
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::Input;
+
+use declarative_dataflow::operators::OneShot;
+use declarative_dataflow::server::{Query, Server};
+use declarative_dataflow::timestamp::Time;
+use declarative_dataflow::{Aid, AttributeConfig, Datom, Output, Plan, ResultDiff, Value};
+use Value::{Number, String};
+
+#[test]
+fn one_shot_as_of() {
+    timely::execute_directly(move |worker| {
+        let (send_results, results) = channel();
+
+        let (mut input, done) = worker.dataflow::<u64, _, _>(|scope| {
+            let (input, stream) = scope.new_input::<ResultDiff<u64>>();
+
+            let done = stream.one_shot(Pipeline, "q", 7, 2, move |out| {
+                send_results.send(out).unwrap();
+            });
+
+            (input, done)
+        });
+
+        input.send((vec![Number(1)], 0, 1));
+        input.send((vec![Number(2)], 1, 1));
+        input.send((vec![Number(1)], 1, -1));
+        input.advance_to(1);
+        worker.step();
+
+        // Results before `as_of` aren't complete yet.
+        assert!(!done.get());
+        assert!(results.try_recv().is_err());
+
+        input.send((vec![Number(3)], 2, 1));
+        input.advance_to(3);
+        worker.step_while(|| !done.get());
+
+        match results.recv_timeout(Duration::from_millis(400)).unwrap() {
+            Output::Snapshot(client, name, snapshot) => {
+                assert_eq!(client, 7);
+                assert_eq!(name, "q");
+                assert_eq!(snapshot, vec![(vec![Number(2)], Time::TxId(1), 1)]);
+            }
+            other => panic!("expected a snapshot, got {:?}", other),
+        }
+
+        // Nothing is sent afterwards.
+        input.send((vec![Number(4)], 3, 1));
+        input.advance_to(4);
+        worker.step();
+
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn query_leaves_no_rule_behind() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(scope, ":name", AttributeConfig::tx_time(Default::default()))
+                .unwrap();
+        });
+
+        server
+            .transact(
+                vec![Datom::add(1, ":name", String("Dipper".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let query = Query {
+            plan: Plan::match_a(0, ":name", 1),
+        };
+
+        let (done, shutdown_handle) = worker.dataflow::<u64, _, _>(|scope| {
+            let (relation, shutdown_handle) = server.query(query, scope).unwrap();
+
+            let done = relation
+                .inner
+                .one_shot(Pipeline, "df/query", 0, 1, move |out| {
+                    send_results.send(out).unwrap();
+                });
+
+            (done, shutdown_handle)
+        });
+
+        assert!(server.internal.rules.is_empty());
+
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| !done.get());
+
+        match results.recv_timeout(Duration::from_millis(400)).unwrap() {
+            Output::Snapshot(_, _, snapshot) => {
+                assert_eq!(
+                    snapshot,
+                    vec![(
+                        vec![Value::Eid(1), String("Dipper".to_string())],
+                        Time::TxId(0),
+                        1
+                    )]
+                );
+            }
+            other => panic!("expected a snapshot, got {:?}", other),
+        }
+
+        drop(shutdown_handle);
+    });
+}
@@ -150,6 +150,7 @@ fn or() {
                         ],
                     }),
                 ],
+                semantics: None,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![
@@ -179,6 +180,7 @@ fn or() {
                         ],
                     }),
                 ],
+                semantics: None,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![(vec![Eid(3)], 0, 1), (vec![Eid(4)], 0, 1)]],
@@ -203,6 +205,7 @@ fn or() {
                         ],
                     }),
                 ],
+                semantics: None,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![]],
@@ -270,6 +273,7 @@ fn or() {
                         ],
                     }),
                 ],
+                semantics: None,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![
@@ -319,6 +323,7 @@ fn or_join() {
                     ],
                 }),
             ],
+            semantics: None,
         }),
         transactions: vec![data.clone()],
         expectations: vec![vec![
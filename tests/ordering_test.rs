@@ -0,0 +1,99 @@
+use std::sync::mpsc::channel;
+
+use declarative_dataflow::plan::{Join, Project};
+use declarative_dataflow::server::{Configuration, Register, Server, Strategy};
+use declarative_dataflow::{Aid, AttributeConfig, Datom, IndexDirection, InputSemantics};
+use declarative_dataflow::{Plan, Rule, Value};
+use Value::{Bool, Eid, String};
+
+#[test]
+fn join_selective_patterns_first() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Configuration {
+            enable_reordering: true,
+            ..Default::default()
+        });
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for aid in [":name", ":admin"].iter() {
+                let config = AttributeConfig {
+                    index_direction: IndexDirection::Both,
+                    ..AttributeConfig::tx_time(InputSemantics::Raw)
+                };
+
+                server.create_attribute(scope, *aid, config).unwrap();
+            }
+
+            // Makes sure all inputs have made it into the indices
+            // before statistics are computed.
+            server.test_single(scope, Rule::named("names", Plan::match_a(0, ":name", 1)));
+        });
+
+        let mut tx_data: Vec<Datom<Aid>> = (0..50)
+            .map(|e| Datom::add(e, ":name", String(format!("user{}", e))))
+            .collect();
+        tx_data.push(Datom::add(7, ":admin", Bool(true)));
+
+        server.transact(tx_data, 0, 0).unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+        server.advance().unwrap();
+
+        let statistics = server.internal.statistics[&":name".to_string()];
+        assert_eq!(statistics.forward.keys, 50);
+        assert_eq!(statistics.forward.tuples, 50);
+        assert_eq!(statistics.reverse.unwrap().keys, 50);
+
+        let (e, n, x) = (0, 1, 2);
+        let plan = Plan::Join(Join {
+            variables: vec![e],
+            left_plan: Box::new(Plan::match_a(e, ":name", n)),
+            right_plan: Box::new(Plan::match_a(e, ":admin", x)),
+        });
+
+        // The original variable order is restored on top of the
+        // reordered join.
+        let explanation = server.explain(&plan, Some(Strategy::BinaryJoins));
+        assert_eq!(explanation.plan.operator, "Project");
+        assert_eq!(explanation.plan.variables, vec![e, n, x]);
+
+        let join = &explanation.plan.inputs[0];
+        assert_eq!(join.operator, "Join");
+        assert_eq!(join.variables, vec![e, x, n]);
+
+        server
+            .register(Register {
+                rules: vec![Rule::named("admins", plan)],
+                publish: vec![],
+                datalog: vec![],
+            })
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .interest("admins".to_string(), scope)
+                .unwrap()
+                .probe_with(&mut server.probe)
+                .inspect(move |x| send_results.send((x.0.clone(), x.2)).unwrap());
+        });
+
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(7), String("user7".to_string()), Bool(true)], 1)
+        );
+
+        match server.internal.rules[&"admins".to_string()].plan {
+            Plan::Project(Project { ref plan, .. }) => match **plan {
+                Plan::Join(ref join) => {
+                    assert_eq!(*join.left_plan, Plan::match_a(e, ":admin", x));
+                }
+                _ => panic!("expected a join"),
+            },
+            _ => panic!("expected a projection"),
+        }
+    });
+}
@@ -0,0 +1,147 @@
+use declarative_dataflow::server::pagination::Page;
+use declarative_dataflow::server::Interest;
+use declarative_dataflow::testing::TestServer;
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use Value::{Eid, String};
+
+fn server() -> TestServer {
+    let mut server = TestServer::new();
+
+    server
+        .create_attribute(":name", AttributeConfig::tx_time(InputSemantics::Raw))
+        .unwrap();
+    server
+        .register(vec![Rule::named("names", Plan::match_a(0, ":name", 1))])
+        .unwrap();
+
+    server
+}
+
+fn name(e: u64, name: &str) -> Datom<Aid> {
+    Datom::add(e, ":name", String(name.to_string()))
+}
+
+fn tuple(e: u64, name: &str) -> Vec<Value> {
+    vec![Eid(e), String(name.to_string())]
+}
+
+#[test]
+fn visible_changes_only() {
+    let mut server = server();
+
+    server
+        .interest_page(
+            "names",
+            Page {
+                sort_by: vec![1],
+                descending: false,
+                offset: 1,
+                limit: 2,
+            },
+        )
+        .unwrap();
+
+    let t0 = server
+        .transact(vec![name(1, "A"), name(2, "B"), name(3, "C"), name(4, "D")])
+        .unwrap();
+
+    server.expect_results(
+        "names",
+        vec![(tuple(2, "B"), t0, 1), (tuple(3, "C"), t0, 1)],
+    );
+
+    // Shifts the page by one.
+    let t1 = server.transact(vec![name(5, "AA")]).unwrap();
+    server.expect_results(
+        "names",
+        vec![(tuple(3, "C"), t1, -1), (tuple(5, "AA"), t1, 1)],
+    );
+
+    // Changes beyond the page aren't visible.
+    let t2 = server.transact(vec![name(6, "E")]).unwrap();
+    server.expect_results("names", vec![]);
+
+    let t3 = server
+        .transact(vec![Datom::retract(1, ":name", String("A".to_string()))])
+        .unwrap();
+    server.expect_results(
+        "names",
+        vec![(tuple(3, "C"), t3, 1), (tuple(5, "AA"), t3, -1)],
+    );
+
+    assert!(t1 < t2 && t2 < t3);
+}
+
+#[test]
+fn descending_pages() {
+    let mut server = server();
+
+    server
+        .interest_page(
+            "names",
+            Page {
+                sort_by: vec![1],
+                descending: true,
+                offset: 0,
+                limit: 1,
+            },
+        )
+        .unwrap();
+
+    let t = server
+        .transact(vec![name(1, "A"), name(2, "C"), name(3, "B")])
+        .unwrap();
+
+    server.expect_results("names", vec![(tuple(2, "C"), t, 1)]);
+}
+
+#[test]
+fn paginated_interests() {
+    let page: Page = serde_json::from_str(r#"{"sort_by": [1], "limit": 10}"#).unwrap();
+    assert_eq!(page.offset, 0);
+    assert!(!page.descending);
+
+    let interest: Interest = serde_json::from_str(
+        r#"{"name": "names", "granularity": null, "sink": null, "disable_logging": null,
+            "page": {"sort_by": [1], "offset": 10, "limit": 10}}"#,
+    )
+    .unwrap();
+
+    // Pages are served by dataflows of their own.
+    assert_ne!(interest.key(), "names");
+    assert!(interest.key().starts_with("names#"));
+
+    let mut server = server();
+    let error = server
+        .interest_page("names", Page { limit: 0, ..page })
+        .unwrap_err();
+    assert_eq!(error.category, "df.error.category/incorrect");
+}
+
+#[test]
+fn unbounded_pages() {
+    let page = Page {
+        sort_by: vec![1],
+        descending: false,
+        offset: std::usize::MAX,
+        limit: std::usize::MAX,
+    };
+
+    // Keys are derived before any checks.
+    assert!(page
+        .key()
+        .ends_with(&format!("[{}+{}]", page.offset, page.limit)));
+
+    let mut server = server();
+
+    for page in vec![
+        Page {
+            offset: 1,
+            ..page.clone()
+        },
+        Page { limit: 1, ..page },
+    ] {
+        let error = server.interest_page("names", page).unwrap_err();
+        assert_eq!(error.category, "df.error.category/unsupported");
+    }
+}
@@ -0,0 +1,70 @@
+use std::sync::{Arc, Mutex};
+
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::Operator;
+
+use declarative_dataflow::server::Server;
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use Value::{Eid, Number};
+
+#[test]
+fn partitioned_ingestion() {
+    let results = Arc::new(Mutex::new(Vec::new()));
+    let shared = results.clone();
+
+    timely::execute(timely::Configuration::Process(2), move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        server
+            .internal
+            .partition_across(worker.index(), worker.peers());
+
+        let results = shared.clone();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let config = AttributeConfig {
+                partitioned: true,
+                ..AttributeConfig::tx_time(InputSemantics::Raw)
+            };
+
+            server.create_attribute(scope, ":score", config).unwrap();
+
+            server
+                .test_single(scope, Rule::named("scores", Plan::match_a(0, ":score", 1)))
+                .inner
+                .sink(Pipeline, "Results", move |input| {
+                    input.for_each(|_time, data| {
+                        let mut results = results.lock().unwrap();
+                        for (tuple, _t, diff) in data.iter() {
+                            results.push((tuple.clone(), *diff));
+                        }
+                    });
+                });
+        });
+
+        let tx_data: Vec<Datom<Aid>> = (0..100)
+            .map(|e| Datom::add(e, ":score", Number(e as i64)))
+            .collect();
+
+        // All workers see all transactions, but each one only
+        // introduces its own share of entities.
+        let local = tx_data
+            .iter()
+            .filter(|datom| server.internal.partition_of(&datom.0) == worker.index())
+            .count();
+        assert!(local < tx_data.len());
+
+        server.transact(tx_data, 0, worker.index()).unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+    })
+    .unwrap();
+
+    let mut results = results.lock().unwrap().clone();
+    results.sort();
+
+    let expected: Vec<(Vec<Value>, isize)> = (0..100)
+        .map(|e| (vec![Eid(e), Number(e as i64)], 1))
+        .collect();
+
+    assert_eq!(results, expected);
+}
@@ -6,7 +6,7 @@ use std::time::Duration;
 use timely::dataflow::channels::pact::Pipeline;
 use timely::dataflow::operators::Operator;
 
-use declarative_dataflow::plan::{Implementable, PullLevel};
+use declarative_dataflow::plan::{Implementable, Pull, PullLevel, PullPattern};
 use declarative_dataflow::server::Server;
 use declarative_dataflow::timestamp::Time;
 use declarative_dataflow::{Aid, Datom, Plan, Rule, Value};
@@ -142,6 +142,58 @@ fn pull_level() {
     }]);
 }
 
+#[test]
+fn pull_pattern() {
+    run_cases(vec![Case {
+        description: "[:find (pull ?e [:name {:friend [:name]}]) :where [?e :admin? false]]",
+        plan: Plan::Pull(Pull::from_pattern(
+            Plan::match_av(0, "admin?", Bool(false)),
+            0,
+            &[
+                PullPattern::Attribute("name".to_string()),
+                PullPattern::Nested {
+                    attribute: "friend".to_string(),
+                    pattern: vec![PullPattern::Attribute("name".to_string())],
+                    cardinality_many: false,
+                },
+            ],
+        )),
+        transactions: vec![vec![
+            Datom::add(100, "admin?", Bool(false)),
+            Datom::add(100, "name", String("Mabel".to_string())),
+            Datom::add(100, "friend", Eid(200)),
+            Datom::add(200, "name", String("Dipper".to_string())),
+        ]],
+        expectations: vec![vec![
+            (
+                vec![Eid(100), Value::aid("name"), String("Mabel".to_string())],
+                0,
+                1,
+            ),
+            (
+                vec![
+                    Eid(100),
+                    Value::aid("friend"),
+                    Value::aid("name"),
+                    String("Dipper".to_string()),
+                ],
+                0,
+                1,
+            ),
+            (
+                vec![
+                    Eid(100),
+                    Value::aid("friend"),
+                    Value::aid("db__id"),
+                    Eid(200),
+                ],
+                0,
+                1,
+            ),
+        ]],
+    }]);
+}
+
 #[cfg(feature = "graphql")]
 #[test]
 #[rustfmt::skip]
@@ -7,12 +7,14 @@ use timely::dataflow::channels::pact::Pipeline;
 use timely::dataflow::operators::Operator;
 
 use declarative_dataflow::binding::Binding;
-use declarative_dataflow::plan::{Implementable, Join, Project};
-use declarative_dataflow::server::Server;
+use declarative_dataflow::plan::{Antijoin, Filter, Implementable, Join, Predicate, Project};
+use declarative_dataflow::server::{Register, Server};
 use declarative_dataflow::timestamp::Time;
 use declarative_dataflow::{q, Aid, Datom, Plan, Rule, Value};
-use declarative_dataflow::{AttributeConfig, IndexDirection, InputSemantics, QuerySupport};
-use Value::{Eid, Number, String};
+use declarative_dataflow::{
+    AttributeConfig, IndexDirection, InputSemantics, QuerySupport, ValueType,
+};
+use Value::{Eid, Instant, Number, String};
 
 struct Case {
     description: &'static str,
@@ -259,6 +261,185 @@ fn wco_base_patterns() {
     ]);
 }
 
+#[test]
+fn reverse_refs() {
+    let data = vec![
+        Datom::add(100, ":name", String("Stan".to_string())),
+        Datom::add(100, ":parent/children", Eid(200)),
+        Datom::add(100, ":parent/children", Eid(300)),
+        Datom::add(200, ":name", String("Dipper".to_string())),
+        Datom::add(300, ":name", String("Mabel".to_string())),
+    ];
+
+    run_cases(vec![
+        {
+            let (child, parent) = (0, 1);
+            Case {
+                description: "[:find ?child ?parent :where [?child :parent/_children ?parent]]",
+                plan: Plan::Project(Project {
+                    variables: vec![child, parent],
+                    plan: Box::new(Plan::match_a(child, ":parent/_children", parent)),
+                }),
+                transactions: vec![data.clone()],
+                expectations: vec![vec![
+                    (vec![Eid(200), Eid(100)], 0, 1),
+                    (vec![Eid(300), Eid(100)], 0, 1),
+                ]],
+            }
+        },
+        Case {
+            description: "[:find ?parent :where [200 :parent/_children ?parent]]",
+            plan: Plan::match_ea(200, ":parent/_children", 0),
+            transactions: vec![data.clone()],
+            expectations: vec![vec![(vec![Eid(100)], 0, 1)]],
+        },
+        {
+            let (child, parent, n) = (0, 1, 2);
+            Case {
+                description:
+                    "[:find ?child ?n :where [?child :parent/_children ?parent] [?parent :name ?n]]",
+                plan: Plan::Project(Project {
+                    variables: vec![child, n],
+                    plan: Box::new(Plan::Join(Join {
+                        variables: vec![parent],
+                        left_plan: Box::new(Plan::match_a(child, ":parent/_children", parent)),
+                        right_plan: Box::new(Plan::match_a(parent, ":name", n)),
+                    })),
+                }),
+                transactions: vec![data.clone()],
+                expectations: vec![vec![
+                    (vec![Eid(200), String("Stan".to_string())], 0, 1),
+                    (vec![Eid(300), String("Stan".to_string())], 0, 1),
+                ]],
+            }
+        },
+    ]);
+}
+
+#[test]
+fn reverse_refs_type_checking() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let config = AttributeConfig {
+                value_type: Some(ValueType::String),
+                index_direction: IndexDirection::Both,
+                ..Default::default()
+            };
+
+            server.create_attribute(scope, ":name", config).unwrap();
+
+            // [:find ?e ?n :where [?n :_name ?e]]
+            server
+                .register(Register {
+                    rules: vec![Rule::named("reverse", Plan::match_a(0, ":_name", 1))],
+                    publish: vec![],
+                    datalog: vec![],
+                })
+                .unwrap();
+
+            assert!(server.interest("reverse".to_string(), scope).is_err());
+        });
+    });
+}
+
+#[test]
+fn parameters() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        // [:find ?e ?n :in [?n ...] :where [?e :name ?n]]
+        let (e, n) = (1, 2);
+        let plan = Plan::Project(Project {
+            variables: vec![e, n],
+            plan: Box::new(Plan::Join(Join {
+                variables: vec![n],
+                left_plan: Box::new(Plan::Parameter(vec![n], "names".to_string())),
+                right_plan: Box::new(Plan::match_a(e, ":name", n)),
+            })),
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let config = AttributeConfig {
+                trace_slack: Some(Time::TxId(1)),
+                index_direction: IndexDirection::Both,
+                ..Default::default()
+            };
+
+            server.create_attribute(scope, ":name", config).unwrap();
+            server.create_parameter(scope, "names").unwrap();
+
+            server
+                .test_single(scope, Rule::named("parameterized", plan))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":name", String("Dipper".to_string())),
+                    Datom::add(2, ":name", String("Mabel".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server
+            .bind(
+                "names".to_string(),
+                vec![(vec![String("Dipper".to_string())], 1)],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv_timeout(Duration::from_millis(400)).unwrap(),
+            (vec![Eid(1), String("Dipper".to_string())], 1)
+        );
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+
+        server
+            .bind(
+                "names".to_string(),
+                vec![
+                    (vec![String("Dipper".to_string())], -1),
+                    (vec![String("Mabel".to_string())], 1),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut batch = vec![
+            results.recv_timeout(Duration::from_millis(400)).unwrap(),
+            results.recv_timeout(Duration::from_millis(400)).unwrap(),
+        ];
+        batch.sort();
+
+        assert_eq!(
+            batch,
+            vec![
+                (vec![Eid(1), String("Dipper".to_string())], -1),
+                (vec![Eid(2), String("Mabel".to_string())], 1),
+            ]
+        );
+
+        assert!(server.bind("unknown".to_string(), vec![], 0, 0).is_err());
+    });
+}
+
 #[test]
 fn joins() {
     run_cases(vec![{
@@ -286,6 +467,271 @@ fn joins() {
     }]);
 }
 
+#[test]
+fn antijoins() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        // [:find ?e ?n :where [?e :name ?n] (not [?e :age ?a])]
+        let (e, a, n) = (1, 2, 3);
+        let plan = Plan::Antijoin(Antijoin {
+            variables: vec![e],
+            left_plan: Box::new(Plan::match_a(e, ":name", n)),
+            right_plan: Box::new(Plan::match_a(e, ":age", a)),
+            semantics: None,
+        });
+
+        assert_eq!(plan.variables(), vec![e, n]);
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for attribute in vec![":name", ":age"] {
+                let config = AttributeConfig {
+                    trace_slack: Some(Time::TxId(1)),
+                    index_direction: IndexDirection::Both,
+                    ..Default::default()
+                };
+
+                server.create_attribute(scope, attribute, config).unwrap();
+            }
+
+            server
+                .test_single(scope, Rule::named("antijoin", plan))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":name", String("Dipper".to_string())),
+                    Datom::add(1, ":age", Number(12)),
+                    Datom::add(2, ":name", String("Mabel".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv_timeout(Duration::from_millis(400)).unwrap(),
+            (vec![Eid(2), String("Mabel".to_string())], 1)
+        );
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+    });
+}
+
+#[test]
+#[should_panic]
+fn antijoin_unbound_variables() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        // The negated ?a is not bound by the positive clause.
+        let (e, a, n) = (1, 2, 3);
+        let plan = Plan::Antijoin(Antijoin {
+            variables: vec![a],
+            left_plan: Box::new(Plan::match_a(e, ":name", n)),
+            right_plan: Box::new(Plan::match_a(e, ":age", a)),
+            semantics: None,
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for attribute in vec![":name", ":age"] {
+                server
+                    .create_attribute(scope, attribute, Default::default())
+                    .unwrap();
+            }
+
+            server.test_single(scope, Rule::named("antijoin", plan));
+        });
+    });
+}
+
+#[test]
+fn string_predicates() {
+    let cases = vec![
+        (
+            Predicate::CONTAINS,
+            "ip",
+            vec![String("Dipper".to_string())],
+        ),
+        (
+            Predicate::STARTS_WITH,
+            "Ma",
+            vec![String("Mabel".to_string())],
+        ),
+    ];
+
+    for (predicate, constant, expected) in cases.into_iter() {
+        timely::execute_directly(move |worker| {
+            let mut server = Server::<Aid, u64, u64>::new(Default::default());
+            let (send_results, results) = channel();
+
+            // [:find ?e ?n :where [?e :name ?n] [(predicate ?n constant)]]
+            let (e, n) = (1, 2);
+            let plan = Plan::Filter(Filter {
+                variables: vec![n],
+                predicate,
+                plan: Box::new(Plan::match_a(e, ":name", n)),
+                constants: vec![None, Some(String(constant.to_string()))],
+            });
+
+            worker.dataflow::<u64, _, _>(|scope| {
+                let config = AttributeConfig {
+                    trace_slack: Some(Time::TxId(1)),
+                    value_type: Some(ValueType::String),
+                    ..Default::default()
+                };
+
+                server.create_attribute(scope, ":name", config).unwrap();
+
+                server
+                    .test_single(scope, Rule::named("filter", plan))
+                    .inspect(move |x| {
+                        send_results.send((x.0.clone(), x.2)).unwrap();
+                    });
+            });
+
+            server
+                .transact(
+                    vec![
+                        Datom::add(1, ":name", String("Dipper".to_string())),
+                        Datom::add(2, ":name", String("Mabel".to_string())),
+                    ],
+                    0,
+                    0,
+                )
+                .unwrap();
+
+            server.advance_domain(None, 1).unwrap();
+            worker.step_while(|| server.is_any_outdated());
+
+            let (tuple, diff) = results.recv_timeout(Duration::from_millis(400)).unwrap();
+            assert_eq!(tuple[1..].to_vec(), expected);
+            assert_eq!(diff, 1);
+            assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+        });
+    }
+}
+
+#[test]
+fn temporal_predicates() {
+    let cases = vec![
+        (Predicate::BEFORE, Instant(1_000), vec![Instant(500)]),
+        (Predicate::AFTER, Instant(1_000), vec![Instant(1_500)]),
+    ];
+
+    for (predicate, constant, expected) in cases.into_iter() {
+        timely::execute_directly(move |worker| {
+            let mut server = Server::<Aid, u64, u64>::new(Default::default());
+            let (send_results, results) = channel();
+
+            // [:find ?e ?t :where [?e :seen ?t] [(predicate ?t constant)]]
+            let (e, t) = (1, 2);
+            let plan = Plan::Filter(Filter {
+                variables: vec![t],
+                predicate,
+                plan: Box::new(Plan::match_a(e, ":seen", t)),
+                constants: vec![None, Some(constant)],
+            });
+
+            worker.dataflow::<u64, _, _>(|scope| {
+                let config = AttributeConfig {
+                    trace_slack: Some(Time::TxId(1)),
+                    value_type: Some(ValueType::Instant),
+                    ..Default::default()
+                };
+
+                server.create_attribute(scope, ":seen", config).unwrap();
+
+                server
+                    .test_single(scope, Rule::named("filter", plan))
+                    .inspect(move |x| {
+                        send_results.send((x.0.clone(), x.2)).unwrap();
+                    });
+            });
+
+            server
+                .transact(
+                    vec![
+                        Datom::add(1, ":seen", Instant(500)),
+                        Datom::add(2, ":seen", Instant(1_000)),
+                        Datom::add(3, ":seen", Instant(1_500)),
+                    ],
+                    0,
+                    0,
+                )
+                .unwrap();
+
+            server.advance_domain(None, 1).unwrap();
+            worker.step_while(|| server.is_any_outdated());
+
+            let (tuple, diff) = results.recv_timeout(Duration::from_millis(400)).unwrap();
+            assert_eq!(tuple[1..].to_vec(), expected);
+            assert_eq!(diff, 1);
+            assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+        });
+    }
+}
+
+#[test]
+fn predicate_type_checking() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let config = AttributeConfig {
+                value_type: Some(ValueType::Number),
+                ..Default::default()
+            };
+
+            server.create_attribute(scope, ":age", config).unwrap();
+
+            // [:find ?e ?a :where [?e :age ?a] [(starts-with ?a "1")]]
+            let (e, a) = (1, 2);
+            let plan = Plan::Filter(Filter {
+                variables: vec![a],
+                predicate: Predicate::STARTS_WITH,
+                plan: Box::new(Plan::match_a(e, ":age", a)),
+                constants: vec![None, Some(String("1".to_string()))],
+            });
+
+            server
+                .register(Register {
+                    rules: vec![Rule::named("ill_typed", plan)],
+                    publish: vec![],
+                    datalog: vec![],
+                })
+                .unwrap();
+
+            assert!(server.interest("ill_typed".to_string(), scope).is_err());
+
+            // [:find ?e ?a :where [?e :age ?a] [(< ?a 18)]]
+            let plan = Plan::Filter(Filter {
+                variables: vec![a],
+                predicate: Predicate::LT,
+                plan: Box::new(Plan::match_a(e, ":age", a)),
+                constants: vec![None, Some(Number(18))],
+            });
+
+            server
+                .register(Register {
+                    rules: vec![Rule::named("well_typed", plan)],
+                    publish: vec![],
+                    datalog: vec![],
+                })
+                .unwrap();
+
+            assert!(server.interest("well_typed".to_string(), scope).is_ok());
+        });
+    });
+}
+
 #[test]
 fn wco_joins() {
     let data = vec![
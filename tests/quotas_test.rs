@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use declarative_dataflow::server::quotas::Quotas;
+use declarative_dataflow::server::{Configuration, Register, Request};
+use declarative_dataflow::testing::TestServer;
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+
+fn server(client_quotas: Quotas) -> TestServer {
+    let mut server = TestServer::with_config(Configuration {
+        client_quotas,
+        ..Default::default()
+    });
+
+    server
+        .create_attribute(":name", AttributeConfig::tx_time(InputSemantics::Raw))
+        .unwrap();
+
+    server
+}
+
+fn transact() -> Request<Aid> {
+    Request::Transact(vec![Datom::add(1, ":name", Value::from("Dipper"))])
+}
+
+fn register(name: &str) -> Request<Aid> {
+    Request::Register(Register {
+        rules: vec![Rule::named(name, Plan::match_a(0, ":name", 1))],
+        publish: vec![],
+        datalog: vec![],
+    })
+}
+
+#[test]
+fn transaction_rates() {
+    let mut server = server(Quotas {
+        max_transactions_per_second: Some(2),
+        ..Default::default()
+    });
+    let server = server.server();
+
+    let at = Duration::from_millis(1500);
+    server.check_quotas(1, &transact(), at).unwrap();
+    server.check_quotas(1, &transact(), at).unwrap();
+
+    let error = server.check_quotas(1, &transact(), at).unwrap_err();
+    assert_eq!(error.category, "df.error.category/busy");
+
+    // Other clients and requests are unaffected.
+    server.check_quotas(2, &transact(), at).unwrap();
+    server.check_quotas(1, &register("names"), at).unwrap();
+
+    // Quotas are replenished every second.
+    server
+        .check_quotas(1, &transact(), Duration::from_millis(2000))
+        .unwrap();
+}
+
+#[test]
+fn registered_queries() {
+    let mut server = server(Quotas {
+        max_queries: Some(1),
+        ..Default::default()
+    });
+    let server = server.server();
+    let at = Duration::from_secs(0);
+
+    server.check_quotas(1, &register("names"), at).unwrap();
+    if let Request::Register(req) = register("names") {
+        server.register(req).unwrap();
+    }
+
+    let error = server.check_quotas(1, &register("others"), at).unwrap_err();
+    assert_eq!(error.category, "df.error.category/conflict");
+
+    // Registered rules don't count again, regardless of who
+    // registered them.
+    server.check_quotas(1, &register("names"), at).unwrap();
+    server.check_quotas(2, &register("names"), at).unwrap();
+
+    server.unregister(&"names".to_string()).unwrap();
+    server.check_quotas(1, &register("others"), at).unwrap();
+}
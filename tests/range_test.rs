@@ -0,0 +1,155 @@
+use std::collections::HashSet;
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use declarative_dataflow::plan::Range;
+use declarative_dataflow::server::{Register, Server};
+use declarative_dataflow::{
+    Aid, AttributeConfig, Datom, IndexDirection, InputSemantics, Plan, Rule,
+};
+use declarative_dataflow::{Value, ValueType};
+use Value::{Eid, Number};
+
+#[test]
+fn range_scan() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let config = AttributeConfig {
+                index_direction: IndexDirection::Both,
+                value_type: Some(ValueType::Number),
+                ..AttributeConfig::tx_time(InputSemantics::Raw)
+            };
+
+            server
+                .create_attribute(scope, ":order/total", config)
+                .unwrap();
+        });
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":order/total", Number(5)),
+                    Datom::add(2, ":order/total", Number(10)),
+                    Datom::add(3, ":order/total", Number(50)),
+                    Datom::add(4, ":order/total", Number(100)),
+                    Datom::add(5, ":order/total", Number(150)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let plan = Plan::Range(Range {
+                variables: vec![0, 1],
+                attribute: ":order/total".to_string(),
+                lower: Number(10),
+                upper: Number(100),
+            });
+
+            server
+                .test_single(scope, Rule::named("orders", plan))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.1, x.2)).unwrap();
+                });
+        });
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        // Both bounds are inclusive.
+        let mut expected = HashSet::new();
+        expected.insert((vec![Eid(2), Number(10)], 0, 1));
+        expected.insert((vec![Eid(3), Number(50)], 0, 1));
+        expected.insert((vec![Eid(4), Number(100)], 0, 1));
+
+        for _i in 0..expected.len() {
+            let result = results.recv_timeout(Duration::from_millis(400)).unwrap();
+            if !expected.remove(&result) {
+                panic!("unknown result {:?}", result);
+            }
+        }
+
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+
+        // Changes outside the range go unnoticed.
+        server
+            .transact(
+                vec![
+                    Datom::retract(3, ":order/total", Number(50)),
+                    Datom::add(3, ":order/total", Number(500)),
+                    Datom::add(6, ":order/total", Number(99)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut expected = HashSet::new();
+        expected.insert((vec![Eid(3), Number(50)], 1, -1));
+        expected.insert((vec![Eid(6), Number(99)], 1, 1));
+
+        for _i in 0..expected.len() {
+            let result = results.recv_timeout(Duration::from_millis(400)).unwrap();
+            if !expected.remove(&result) {
+                panic!("unknown result {:?}", result);
+            }
+        }
+
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+    });
+}
+
+#[test]
+fn range_requirements() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":order/total",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+
+            let plans = vec![
+                // No reverse index to scan.
+                Plan::Range(Range {
+                    variables: vec![0, 1],
+                    attribute: ":order/total".to_string(),
+                    lower: Number(10),
+                    upper: Number(100),
+                }),
+                // Bounds of different types.
+                Plan::Range(Range {
+                    variables: vec![0, 1],
+                    attribute: ":order/total".to_string(),
+                    lower: Number(10),
+                    upper: Value::Instant(100),
+                }),
+            ];
+
+            for (i, plan) in plans.into_iter().enumerate() {
+                let name = format!("orders{}", i);
+
+                server
+                    .register(Register {
+                        rules: vec![Rule::named(name.clone(), plan)],
+                        publish: vec![],
+                        datalog: vec![],
+                    })
+                    .unwrap();
+
+                assert!(server.interest(name, scope).is_err());
+            }
+        });
+    });
+}
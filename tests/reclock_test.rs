@@ -0,0 +1,134 @@
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use declarative_dataflow::plan::{Join, Project};
+use declarative_dataflow::server::{Bridge, Server};
+use declarative_dataflow::{Aid, AttributeConfig, Datom, Plan, Rule, Value};
+use Value::{Eid, Number, String};
+
+#[test]
+fn bridged_attributes() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in &[":sensor/name", "kafka//:reading"] {
+                server
+                    .create_attribute(scope, *name, AttributeConfig::tx_time(Default::default()))
+                    .unwrap();
+            }
+        });
+
+        // The source domain runs far ahead of the internal one.
+        server
+            .advance_domain(Some("kafka".to_string()), 100)
+            .unwrap();
+
+        server
+            .transact(vec![Datom::add(1, "kafka//:reading", Number(42))], 0, 0)
+            .unwrap();
+
+        server
+            .transact(
+                vec![Datom::add(1, ":sensor/name", String("thermo".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .bridge(
+                    scope,
+                    Bridge {
+                        source: "kafka//:reading".to_string(),
+                        name: ":reading".to_string(),
+                    },
+                )
+                .unwrap();
+
+            let (e, n, v) = (0, 1, 2);
+            server
+                .test_single(
+                    scope,
+                    Rule::named(
+                        "readings",
+                        Plan::Project(Project {
+                            variables: vec![n, v],
+                            plan: Box::new(Plan::Join(Join {
+                                variables: vec![e],
+                                left_plan: Box::new(Plan::match_a(e, ":sensor/name", n)),
+                                right_plan: Box::new(Plan::match_a(e, ":reading", v)),
+                            })),
+                        }),
+                    ),
+                )
+                .inspect(move |x| {
+                    send_results.send(x.clone()).unwrap();
+                });
+        });
+
+        // The reading isn't complete until the source domain
+        // advances beyond it.
+        server.advance_domain(None, 1).unwrap();
+        server.advance().unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+
+        // Once it is, it is revealed at the internal epoch.
+        server
+            .advance_domain(Some("kafka".to_string()), 101)
+            .unwrap();
+        server.advance_domain(None, 2).unwrap();
+        server.advance().unwrap();
+        server.advance_domain(None, 3).unwrap();
+        server.advance().unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv_timeout(Duration::from_millis(400)).unwrap(),
+            (vec![String("thermo".to_string()), Number(42)], 2, 1)
+        );
+    });
+}
+
+#[test]
+fn bridging_within_a_domain() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    "kafka//:reading",
+                    AttributeConfig::tx_time(Default::default()),
+                )
+                .unwrap();
+
+            let error = server
+                .bridge(
+                    scope,
+                    Bridge {
+                        source: "kafka//:reading".to_string(),
+                        name: "kafka//:copy".to_string(),
+                    },
+                )
+                .unwrap_err();
+            assert_eq!(error.category, "df.error.category/incorrect");
+
+            let error = server
+                .bridge(
+                    scope,
+                    Bridge {
+                        source: "kafka//:missing".to_string(),
+                        name: ":missing".to_string(),
+                    },
+                )
+                .unwrap_err();
+            assert_eq!(error.category, "df.error.category/not-found");
+        });
+    });
+}
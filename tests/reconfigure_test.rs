@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use declarative_dataflow::server::{Configuration, Reconfigure};
+use declarative_dataflow::testing::TestServer;
+use declarative_dataflow::{AttributeConfig, InputSemantics, Output, Time, Value};
+
+#[test]
+fn split_outputs() {
+    let diffs = (0..5)
+        .map(|x| (vec![Value::Number(x)], Time::TxId(1), 1))
+        .collect::<Vec<_>>();
+
+    let split = Output::QueryDiff("q".to_string(), diffs).split(2);
+    let sizes: Vec<usize> = split
+        .iter()
+        .map(|out| match out {
+            Output::QueryDiff(name, results) => {
+                assert_eq!(name, "q");
+                results.len()
+            }
+            other => panic!("unexpected output {:?}", other),
+        })
+        .collect();
+    assert_eq!(sizes, vec![2, 2, 1]);
+
+    // Batches are consolidated per time and arrive whole.
+    let batch = vec![(vec![Value::Number(1)], 1), (vec![Value::Number(2)], 1)];
+    let split = Output::QueryBatch("q".to_string(), Time::TxId(1), batch).split(1);
+    assert_eq!(split.len(), 1);
+}
+
+#[test]
+fn reconfigures_running_servers() {
+    let mut server = TestServer::with_config(Configuration {
+        batch_size: Some(100),
+        ..Default::default()
+    });
+
+    let history = AttributeConfig {
+        trace_slack: None,
+        ..AttributeConfig::tx_time(InputSemantics::Raw)
+    };
+
+    server
+        .create_attribute(":age", AttributeConfig::tx_time(InputSemantics::Raw))
+        .unwrap();
+    server.create_attribute(":name", history).unwrap();
+
+    let batch_size = server.server().batch_size();
+    assert_eq!(batch_size.get(), Some(100));
+
+    // Unset settings remain as they are.
+    server
+        .server()
+        .reconfigure(Reconfigure {
+            trace_slack: Some(Time::TxId(10)),
+            poll_interval: Some(Duration::from_millis(50)),
+            ..Default::default()
+        })
+        .unwrap();
+
+    let attributes = &server.server().internal.attributes;
+    assert_eq!(attributes[":age"].trace_slack, Some(Time::TxId(10)));
+    assert_eq!(attributes[":name"].trace_slack, None);
+    assert_eq!(batch_size.get(), Some(100));
+    assert_eq!(
+        server.server().config.poll_interval,
+        Some(Duration::from_millis(50))
+    );
+
+    server
+        .server()
+        .reconfigure(Reconfigure {
+            batch_size: Some(10),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(batch_size.get(), Some(10));
+
+    let error = server
+        .server()
+        .reconfigure(Reconfigure {
+            batch_size: Some(0),
+            ..Default::default()
+        })
+        .unwrap_err();
+    assert_eq!(error.category, "df.error.category/incorrect");
+    assert_eq!(batch_size.get(), Some(10));
+}
@@ -1,10 +1,11 @@
 use std::sync::mpsc::channel;
+use std::time::Duration;
 
-use declarative_dataflow::plan::{Join, Project};
-use declarative_dataflow::server::Server;
+use declarative_dataflow::plan::{Antijoin, Join, Project, Union};
+use declarative_dataflow::server::{Register, Server};
 use declarative_dataflow::timestamp::Time;
 use declarative_dataflow::{Aid, Datom, Plan, Rule, Value};
-use declarative_dataflow::{AttributeConfig, IndexDirection, QuerySupport};
+use declarative_dataflow::{AttributeConfig, IndexDirection, InputSemantics, QuerySupport};
 use Value::{Eid, String};
 
 #[test]
@@ -156,3 +157,306 @@ fn join_after_input() {
         assert_eq!(results.recv().unwrap(), (vec![Eid(101), Eid(1)], 1));
     });
 }
+
+#[test]
+fn reject_unstratifiable_negation() {
+    let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+    let (x, y) = (0, 1);
+
+    // [reachable ?x ?y] <- [?x :edge ?y] or [?x ?y] [blocked ?x ?y]
+    // [blocked ?x ?y] <- [?x :edge ?y] (not [reachable ?x ?y])
+    let reachable = Rule::named(
+        "reachable",
+        Plan::Union(Union {
+            variables: vec![x, y],
+            plans: vec![
+                Plan::match_a(x, ":edge", y),
+                Plan::NameExpr(vec![x, y], "blocked".into()),
+            ],
+            semantics: None,
+        }),
+    );
+
+    let blocked = Rule::named(
+        "blocked",
+        Plan::Antijoin(Antijoin {
+            variables: vec![x, y],
+            left_plan: Box::new(Plan::match_a(x, ":edge", y)),
+            right_plan: Box::new(Plan::NameExpr(vec![x, y], "reachable".into())),
+            semantics: None,
+        }),
+    );
+
+    let result = server.register(Register {
+        rules: vec![reachable, blocked],
+        publish: vec![],
+        datalog: vec![],
+    });
+
+    assert!(result.is_err());
+    assert!(server.internal.rule(&"reachable".into()).is_none());
+
+    // Negating a recursive rule from outside of its stratum is fine.
+    let recursive = Rule::named(
+        "reachable",
+        Plan::Union(Union {
+            variables: vec![x, y],
+            plans: vec![
+                Plan::match_a(x, ":edge", y),
+                Plan::NameExpr(vec![x, y], "reachable".into()),
+            ],
+            semantics: None,
+        }),
+    );
+
+    let unreachable = Rule::named(
+        "unreachable",
+        Plan::Antijoin(Antijoin {
+            variables: vec![x, y],
+            left_plan: Box::new(Plan::match_a(x, ":edge", y)),
+            right_plan: Box::new(Plan::NameExpr(vec![x, y], "reachable".into())),
+            semantics: None,
+        }),
+    );
+
+    server
+        .register(Register {
+            rules: vec![recursive, unreachable],
+            publish: vec![],
+            datalog: vec![],
+        })
+        .unwrap();
+
+    assert!(server.internal.rule(&"unreachable".into()).is_some());
+}
+
+#[test]
+fn reject_mismatched_heads() {
+    let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+    let (e, n, a) = (0, 1, 2);
+
+    let register = |server: &mut Server<Aid, u64, u64>, rules: Vec<Rule<Aid>>| {
+        server.register(Register {
+            rules,
+            publish: vec![],
+            datalog: vec![],
+        })
+    };
+
+    // The second branch doesn't bind ?n.
+    let labels = Rule::named(
+        "labels",
+        Plan::Union(Union {
+            variables: vec![e, n],
+            plans: vec![
+                Plan::match_a(e, ":name", n),
+                Plan::Join(Join {
+                    variables: vec![e],
+                    left_plan: Box::new(Plan::match_a(e, ":name", a)),
+                    right_plan: Box::new(Plan::match_a(e, ":age", a + 1)),
+                }),
+            ],
+            semantics: None,
+        }),
+    );
+
+    let error = register(&mut server, vec![labels]).unwrap_err();
+    assert_eq!(error.category, "df.error.category/incorrect");
+    assert_eq!(error.clause, Some(vec![1]));
+    assert!(server.internal.rule(&"labels".into()).is_none());
+
+    // Rules must be referred to with as many variables as they bind.
+    let names = Rule::named(
+        "names",
+        Plan::Project(Project {
+            variables: vec![e, n],
+            plan: Box::new(Plan::match_a(e, ":name", n)),
+        }),
+    );
+
+    let adults = Rule::named(
+        "adults",
+        Plan::Join(Join {
+            variables: vec![e],
+            left_plan: Box::new(Plan::NameExpr(vec![e], "names".into())),
+            right_plan: Box::new(Plan::match_a(e, ":age", a)),
+        }),
+    );
+
+    let error = register(&mut server, vec![names.clone(), adults]).unwrap_err();
+    assert_eq!(error.category, "df.error.category/incorrect");
+    assert_eq!(error.clause, Some(vec![0]));
+    assert!(server.internal.rule(&"names".into()).is_none());
+
+    register(&mut server, vec![names]).unwrap();
+    assert!(server.internal.rule(&"names".into()).is_some());
+}
+
+#[test]
+fn unregister() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (e, n) = (0, 1);
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+
+            server
+                .test_single(scope, Rule::named("names", Plan::match_a(e, ":name", n)))
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![Datom::add(1, ":name", String("Dipper".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv_timeout(Duration::from_millis(400)).unwrap(),
+            (vec![Eid(1), String("Dipper".to_string())], 1)
+        );
+
+        server.unregister(&"names".to_string()).unwrap();
+        assert!(server.internal.rule(&"names".to_string()).is_none());
+
+        // The dataflow has been shut down and won't produce any
+        // further results.
+        server
+            .transact(
+                vec![Datom::add(2, ":name", String("Mabel".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+
+        // Rules that others depend on must be unregistered last.
+        server
+            .register(Register {
+                rules: vec![
+                    Rule::named("a", Plan::match_a(e, ":name", n)),
+                    Rule::named("b", Plan::NameExpr(vec![e, n], "a".into())),
+                ],
+                publish: vec![],
+                datalog: vec![],
+            })
+            .unwrap();
+
+        assert!(server.unregister(&"a".to_string()).is_err());
+        server.unregister(&"b".to_string()).unwrap();
+        server.unregister(&"a".to_string()).unwrap();
+        assert!(server.unregister(&"a".to_string()).is_err());
+    });
+}
+
+#[test]
+fn materialized_views() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (e, n) = (0, 1);
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+        });
+
+        server
+            .register(Register {
+                rules: vec![
+                    Rule::named("names", Plan::match_a(e, ":name", n)),
+                    Rule::named(
+                        "named",
+                        Plan::Project(Project {
+                            variables: vec![e],
+                            plan: Box::new(Plan::NameExpr(vec![e, n], "names".into())),
+                        }),
+                    ),
+                ],
+                publish: vec![],
+                datalog: vec![],
+            })
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server.materialize(scope, "names".to_string()).unwrap();
+        });
+
+        assert!(server.internal.is_materialized(&"names".to_string()));
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            assert!(server.materialize(scope, "names".to_string()).is_err());
+        });
+
+        server
+            .transact(
+                vec![Datom::add(1, ":name", String("Dipper".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        // Later queries import the view instead of implementing it.
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .interest("named".to_string(), scope)
+                .unwrap()
+                .probe_with(&mut server.probe)
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![Datom::add(2, ":name", String("Mabel".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut received = vec![
+            results.recv_timeout(Duration::from_millis(400)).unwrap(),
+            results.recv_timeout(Duration::from_millis(400)).unwrap(),
+        ];
+        received.sort();
+
+        assert_eq!(received, vec![(vec![Eid(1)], 1), (vec![Eid(2)], 1)]);
+
+        // Views can't be unregistered while other rules use them.
+        assert!(server.unregister(&"names".to_string()).is_err());
+        server.unregister(&"named".to_string()).unwrap();
+        server.unregister(&"names".to_string()).unwrap();
+        assert!(!server.internal.is_materialized(&"names".to_string()));
+    });
+}
@@ -0,0 +1,107 @@
+use std::sync::mpsc::channel;
+
+use declarative_dataflow::plan::Join;
+use declarative_dataflow::server::{Configuration, Register, Replan, Server};
+use declarative_dataflow::{Aid, AttributeConfig, Datom, IndexDirection, InputSemantics};
+use declarative_dataflow::{Plan, Rule, Value};
+use Value::{Bool, Eid, String};
+
+#[test]
+fn replan_diverged_joins() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Configuration {
+            enable_reordering: true,
+            ..Default::default()
+        });
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for aid in [":name", ":admin"].iter() {
+                let config = AttributeConfig {
+                    index_direction: IndexDirection::Both,
+                    ..AttributeConfig::tx_time(InputSemantics::Raw)
+                };
+
+                server.create_attribute(scope, *aid, config).unwrap();
+            }
+        });
+
+        let (e, n, x) = (0, 1, 2);
+        let plan = Plan::Join(Join {
+            variables: vec![e],
+            left_plan: Box::new(Plan::match_a(e, ":name", n)),
+            right_plan: Box::new(Plan::match_a(e, ":admin", x)),
+        });
+
+        server
+            .register(Register {
+                rules: vec![Rule::named("admins", plan.clone())],
+                publish: vec![],
+                datalog: vec![],
+            })
+            .unwrap();
+
+        // Without any data, the join is implemented as written.
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .interest("admins".to_string(), scope)
+                .unwrap()
+                .probe_with(&mut server.probe);
+        });
+
+        assert_eq!(server.internal.rules[&"admins".to_string()].plan, plan);
+        assert!(server.replan_candidates().is_empty());
+
+        let mut tx_data: Vec<Datom<Aid>> = (0..50)
+            .map(|e| Datom::add(e, ":name", String(format!("user{}", e))))
+            .collect();
+        tx_data.push(Datom::add(7, ":admin", Bool(true)));
+
+        server.transact(tx_data, 0, 0).unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+        server.advance().unwrap();
+
+        let mut candidates = server.replan_candidates();
+        assert_eq!(candidates.len(), 1);
+
+        let replan = candidates.remove(0);
+        assert_eq!(replan.name, "admins");
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .replan(replan, scope)
+                .unwrap()
+                .probe_with(&mut server.probe)
+                .inspect(move |x| send_results.send((x.0.clone(), x.2)).unwrap());
+        });
+
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        // The new dataflow starts over from the current state.
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(7), String("user7".to_string()), Bool(true)], 1)
+        );
+
+        // Statistics are now in line with the chosen order.
+        assert!(server.replan_candidates().is_empty());
+    });
+}
+
+#[test]
+fn replan_unknown_relation() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        let replan = Replan {
+            name: "unknown".to_string(),
+            rules: vec![],
+        };
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            assert!(server.replan(replan, scope).is_err());
+        });
+    });
+}
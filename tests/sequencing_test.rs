@@ -0,0 +1,61 @@
+use std::sync::{Arc, Mutex};
+
+use declarative_dataflow::server::sequencing::CommandLog;
+
+#[test]
+fn commands_arrive_in_the_same_order() {
+    let logs = Arc::new(Mutex::new(Vec::new()));
+    let shared = logs.clone();
+
+    timely::execute(timely::Configuration::Process(3), move |worker| {
+        let timer = worker.timer();
+        let mut log: CommandLog<(usize, usize)> =
+            CommandLog::preloaded(worker, timer, vec![(worker.peers(), 0)]);
+
+        for command in 0..10 {
+            log.push((worker.index(), command));
+        }
+
+        // Each worker receives the preloaded command, as well as all
+        // commands issued by any worker.
+        let expected = 1 + 10 * worker.peers();
+
+        let mut received = Vec::new();
+        while received.len() < expected {
+            worker.step();
+
+            while let Some(sequenced) = log.next() {
+                received.push(sequenced);
+            }
+        }
+
+        for (index, sequenced) in received.iter().enumerate() {
+            assert_eq!(sequenced.tx, index as u64 + 1);
+
+            if index > 0 {
+                assert!(sequenced.issued_at > received[index - 1].issued_at);
+            }
+        }
+
+        let commands: Vec<(usize, usize)> = received.into_iter().map(|x| x.command).collect();
+        shared.lock().unwrap().push(commands);
+    })
+    .unwrap();
+
+    let logs = logs.lock().unwrap();
+    assert_eq!(logs.len(), 3);
+    assert_eq!(logs[0], logs[1]);
+    assert_eq!(logs[1], logs[2]);
+
+    // Commands issued by the same worker retain their order.
+    for worker in 0..3 {
+        let issued: Vec<usize> = logs[0]
+            .iter()
+            .skip(1)
+            .filter(|(issuer, _)| *issuer == worker)
+            .map(|(_, command)| *command)
+            .collect();
+
+        assert_eq!(issued, (0..10).collect::<Vec<usize>>());
+    }
+}
@@ -0,0 +1,196 @@
+use std::time::Duration;
+
+use declarative_dataflow::plan::{Aggregate, AggregationFn, Join, Session};
+use declarative_dataflow::server::Strategy;
+use declarative_dataflow::testing::TestServer;
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use Value::{Eid, Instant, Number};
+
+fn server() -> TestServer {
+    let mut server = TestServer::new();
+
+    for name in &[":event/user", ":event/time"] {
+        server
+            .create_attribute(name, AttributeConfig::tx_time(InputSemantics::Raw))
+            .unwrap();
+    }
+
+    server
+}
+
+fn sessions() -> Plan<Aid> {
+    let (e, u, t, s) = (0, 1, 2, 3);
+
+    Plan::Session(Session {
+        variables: vec![u, t, e],
+        plan: Box::new(Plan::Join(Join {
+            variables: vec![e],
+            left_plan: Box::new(Plan::match_a(e, ":event/user", u)),
+            right_plan: Box::new(Plan::match_a(e, ":event/time", t)),
+        })),
+        key_variables: vec![u],
+        time_variable: t,
+        gap: Duration::from_millis(10),
+        session_variable: s,
+    })
+}
+
+fn event(e: u64, user: u64, time: u64) -> Vec<Datom<Aid>> {
+    vec![
+        Datom::add(e, ":event/user", Eid(user)),
+        Datom::add(e, ":event/time", Instant(time)),
+    ]
+}
+
+fn result(user: u64, time: u64, e: u64, session: u64) -> Vec<Value> {
+    vec![Eid(user), Instant(time), Eid(e), Instant(session)]
+}
+
+#[test]
+fn sessions_per_group() {
+    let mut server = server();
+
+    server
+        .register(vec![Rule::named("sessions", sessions())])
+        .unwrap();
+    server.interest("sessions").unwrap();
+
+    let mut tx_data = Vec::new();
+    tx_data.extend(event(1, 100, 0));
+    tx_data.extend(event(2, 100, 10));
+    tx_data.extend(event(3, 100, 30));
+    tx_data.extend(event(4, 200, 5));
+
+    let t = server.transact(tx_data).unwrap();
+
+    server.expect_results(
+        "sessions",
+        vec![
+            (result(100, 0, 1, 0), t, 1),
+            (result(100, 10, 2, 0), t, 1),
+            (result(100, 30, 3, 30), t, 1),
+            (result(200, 5, 4, 5), t, 1),
+        ],
+    );
+}
+
+#[test]
+fn late_events() {
+    let mut server = server();
+
+    server
+        .register(vec![Rule::named("sessions", sessions())])
+        .unwrap();
+    server.interest("sessions").unwrap();
+
+    let mut tx_data = Vec::new();
+    tx_data.extend(event(1, 100, 0));
+    tx_data.extend(event(2, 100, 20));
+
+    let t0 = server.transact(tx_data).unwrap();
+
+    server.expect_results(
+        "sessions",
+        vec![
+            (result(100, 0, 1, 0), t0, 1),
+            (result(100, 20, 2, 20), t0, 1),
+        ],
+    );
+
+    // An event arriving late bridges the gap, merging both sessions.
+    let t1 = server.transact(event(3, 100, 10)).unwrap();
+
+    server.expect_results(
+        "sessions",
+        vec![
+            (result(100, 10, 3, 0), t1, 1),
+            (result(100, 20, 2, 20), t1, -1),
+            (result(100, 20, 2, 0), t1, 1),
+        ],
+    );
+
+    // Retracting it splits them up again.
+    let t2 = server
+        .transact(vec![Datom::retract(3, ":event/time", Instant(10))])
+        .unwrap();
+
+    server.expect_results(
+        "sessions",
+        vec![
+            (result(100, 10, 3, 0), t2, -1),
+            (result(100, 20, 2, 0), t2, -1),
+            (result(100, 20, 2, 20), t2, 1),
+        ],
+    );
+}
+
+#[test]
+fn aggregate_per_session() {
+    let mut server = server();
+    let (e, u, s) = (0, 1, 3);
+
+    server
+        .register(vec![Rule::named(
+            "activity",
+            Plan::Aggregate(Aggregate {
+                variables: vec![u, s, e],
+                plan: Box::new(sessions()),
+                aggregation_fns: vec![AggregationFn::COUNT],
+                key_variables: vec![u, s],
+                aggregation_variables: vec![e],
+                with_variables: vec![],
+            }),
+        )])
+        .unwrap();
+    server.interest("activity").unwrap();
+
+    let mut tx_data = Vec::new();
+    tx_data.extend(event(1, 100, 0));
+    tx_data.extend(event(2, 100, 5));
+    tx_data.extend(event(3, 100, 40));
+
+    let t = server.transact(tx_data).unwrap();
+
+    server.expect_results(
+        "activity",
+        vec![
+            (vec![Eid(100), Instant(0), Number(2)], t, 1),
+            (vec![Eid(100), Instant(40), Number(1)], t, 1),
+        ],
+    );
+}
+
+#[test]
+fn invalid_session() {
+    let mut server = server();
+
+    let mut rebound = sessions();
+    if let Plan::Session(ref mut session) = rebound {
+        session.session_variable = 2;
+    }
+
+    server
+        .register(vec![Rule::named("rebound", rebound)])
+        .unwrap();
+
+    let error = server.interest("rebound").unwrap_err();
+    assert_eq!(error.category, "df.error.category/incorrect");
+    assert_eq!(error.clause, Some(vec![]));
+}
+
+#[test]
+fn worst_case_optimal_session() {
+    let mut server = server();
+
+    server
+        .register(vec![Rule::named("sessions", sessions())])
+        .unwrap();
+
+    // Hector would drop the session variable, so such interests are
+    // turned away.
+    let error = server
+        .interest_using("sessions", Strategy::WorstCaseOptimal)
+        .unwrap_err();
+    assert_eq!(error.category, "df.error.category/unsupported");
+    assert_eq!(error.clause, Some(vec![]));
+}
@@ -0,0 +1,133 @@
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use declarative_dataflow::plan::sharing::is_shared;
+use declarative_dataflow::plan::{Join, Project};
+use declarative_dataflow::server::{Configuration, Register, Server};
+use declarative_dataflow::{
+    Aid, AttributeConfig, Datom, Implementable, IndexDirection, InputSemantics, Plan, Rule, Value,
+};
+use Value::Eid;
+
+fn two_hops(x: u32, y: u32, z: u32, find: Vec<u32>) -> Plan<Aid> {
+    Plan::Project(Project {
+        variables: find,
+        plan: Box::new(Plan::Join(Join {
+            variables: vec![y],
+            left_plan: Box::new(Plan::match_a(x, ":edge", y)),
+            right_plan: Box::new(Plan::match_a(y, ":edge", z)),
+        })),
+    })
+}
+
+#[test]
+fn shared_join_trees() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Configuration {
+            enable_sharing: true,
+            ..Default::default()
+        });
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":edge",
+                    AttributeConfig {
+                        index_direction: IndexDirection::Both,
+                        ..AttributeConfig::tx_time(InputSemantics::Raw)
+                    },
+                )
+                .unwrap();
+        });
+
+        // Both rules join the same pair of patterns, modulo variable
+        // naming.
+        server
+            .register(Register {
+                rules: vec![
+                    Rule::named("paths", two_hops(0, 1, 2, vec![0, 2])),
+                    Rule::named("sources", two_hops(10, 11, 12, vec![10])),
+                ],
+                publish: vec![],
+                datalog: vec![],
+            })
+            .unwrap();
+
+        let shared: Vec<Aid> = server
+            .internal
+            .rules
+            .keys()
+            .filter(|name| is_shared(*name))
+            .cloned()
+            .collect();
+
+        assert_eq!(shared.len(), 1);
+        assert!(server
+            .internal
+            .rule(&"paths".to_string())
+            .unwrap()
+            .plan
+            .dependencies()
+            .names
+            .contains(&shared[0]));
+
+        server
+            .transact(
+                vec![
+                    Datom::add(1, ":edge", Eid(2)),
+                    Datom::add(2, ":edge", Eid(3)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        for name in ["paths", "sources"].iter() {
+            let name = name.to_string();
+            let send_results = send_results.clone();
+
+            worker.dataflow::<u64, _, _>(|scope| {
+                server
+                    .interest(name.clone(), scope)
+                    .unwrap()
+                    .probe_with(&mut server.probe)
+                    .inspect(move |x| {
+                        send_results.send((name.clone(), x.0.clone())).unwrap();
+                    });
+            });
+
+            // The first dataflow materializes the shared join, the
+            // second one imports it.
+            assert_eq!(server.internal.intermediates.len(), 1);
+        }
+
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut received = vec![
+            results.recv_timeout(Duration::from_millis(400)).unwrap(),
+            results.recv_timeout(Duration::from_millis(400)).unwrap(),
+        ];
+        received.sort();
+
+        assert_eq!(
+            received,
+            vec![
+                ("paths".to_string(), vec![Eid(1), Eid(3)]),
+                ("sources".to_string(), vec![Eid(1)]),
+            ]
+        );
+
+        // Shared rules are cleaned up along with their last user.
+        server.unregister(&"paths".to_string()).unwrap();
+        assert!(server.internal.rule(&shared[0]).is_some());
+
+        server.unregister(&"sources".to_string()).unwrap();
+        assert!(server.internal.rule(&shared[0]).is_none());
+        assert!(server.internal.intermediates.is_empty());
+    });
+}
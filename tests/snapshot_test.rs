@@ -0,0 +1,197 @@
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::{Input, Probe};
+
+use declarative_dataflow::operators::{SnapshotRequests, Snapshots};
+use declarative_dataflow::timestamp::Time;
+use declarative_dataflow::{Output, ResultDiff, Value};
+use Value::Number;
+
+#[test]
+fn snapshot_then_diffs() {
+    timely::execute_directly(move |worker| {
+        let (send_results, results) = channel();
+        let requests = SnapshotRequests::new();
+
+        let (mut input, probe) = worker.dataflow::<u64, _, _>(|scope| {
+            let (input, stream) = scope.new_input::<ResultDiff<u64>>();
+
+            let probe = stream
+                .snapshots(Pipeline, "q", &requests, move |out| {
+                    send_results.send(out).unwrap();
+                })
+                .probe();
+
+            (input, probe)
+        });
+
+        // The initial results are delivered as a snapshot.
+        requests.request(0);
+
+        input.send((vec![Number(1)], 0, 1));
+        input.send((vec![Number(2)], 0, 1));
+        input.advance_to(1);
+        worker.step_while(|| probe.less_than(input.time()));
+
+        match results.recv_timeout(Duration::from_millis(400)).unwrap() {
+            Output::Snapshot(client, name, snapshot) => {
+                assert_eq!(client, 0);
+                assert_eq!(name, "q");
+                assert_eq!(
+                    snapshot,
+                    vec![
+                        (vec![Number(1)], Time::TxId(0), 1),
+                        (vec![Number(2)], Time::TxId(0), 1),
+                    ]
+                );
+            }
+            other => panic!("expected a snapshot, got {:?}", other),
+        }
+
+        // Further changes are delivered as diffs.
+        input.send((vec![Number(1)], 1, -1));
+        input.advance_to(2);
+        worker.step_while(|| probe.less_than(input.time()));
+
+        match results.recv_timeout(Duration::from_millis(400)).unwrap() {
+            Output::QueryDiff(name, diffs) => {
+                assert_eq!(name, "q");
+                assert_eq!(diffs, vec![(vec![Number(1)], Time::TxId(1), -1)]);
+            }
+            other => panic!("expected diffs, got {:?}", other),
+        }
+
+        // Late subscribers receive the consolidated state.
+        requests.request(1);
+        input.advance_to(3);
+        worker.step_while(|| probe.less_than(input.time()));
+
+        match results.recv_timeout(Duration::from_millis(400)).unwrap() {
+            Output::Snapshot(client, name, snapshot) => {
+                assert_eq!(client, 1);
+                assert_eq!(name, "q");
+                assert_eq!(snapshot, vec![(vec![Number(2)], Time::TxId(1), 1)]);
+            }
+            other => panic!("expected a snapshot, got {:?}", other),
+        }
+
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn batched_diffs() {
+    timely::execute_directly(move |worker| {
+        let (send_results, results) = channel();
+        let requests = SnapshotRequests::new();
+
+        let (mut input, probe) = worker.dataflow::<u64, _, _>(|scope| {
+            let (input, stream) = scope.new_input::<ResultDiff<u64>>();
+
+            let probe = stream
+                .batched_snapshots(Pipeline, "q", &requests, move |out| {
+                    send_results.send(out).unwrap();
+                })
+                .probe();
+
+            (input, probe)
+        });
+
+        requests.request(0);
+
+        input.send((vec![Number(1)], 0, 1));
+        input.advance_to(1);
+        worker.step_while(|| probe.less_than(input.time()));
+
+        match results.recv_timeout(Duration::from_millis(400)).unwrap() {
+            Output::Snapshot(_, _, snapshot) => {
+                assert_eq!(snapshot, vec![(vec![Number(1)], Time::TxId(0), 1)]);
+            }
+            other => panic!("expected a snapshot, got {:?}", other),
+        }
+
+        // Changes at each time arrive as a single, consolidated batch,
+        // once that time is complete.
+        input.send((vec![Number(2)], 1, 1));
+        input.send((vec![Number(2)], 1, 1));
+        input.send((vec![Number(3)], 1, 1));
+        input.send((vec![Number(3)], 1, -1));
+        input.send((vec![Number(1)], 2, -1));
+        input.send((vec![Number(4)], 3, 1));
+        input.send((vec![Number(4)], 3, -1));
+        input.advance_to(4);
+        worker.step_while(|| probe.less_than(input.time()));
+
+        match results.recv_timeout(Duration::from_millis(400)).unwrap() {
+            Output::QueryBatch(name, time, diffs) => {
+                assert_eq!(name, "q");
+                assert_eq!(time, Time::TxId(1));
+                assert_eq!(diffs, vec![(vec![Number(2)], 2)]);
+            }
+            other => panic!("expected a batch, got {:?}", other),
+        }
+
+        match results.recv_timeout(Duration::from_millis(400)).unwrap() {
+            Output::QueryBatch(name, time, diffs) => {
+                assert_eq!(name, "q");
+                assert_eq!(time, Time::TxId(2));
+                assert_eq!(diffs, vec![(vec![Number(1)], -1)]);
+            }
+            other => panic!("expected a batch, got {:?}", other),
+        }
+
+        // Times whose changes cancel out aren't sent at all.
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn resumed_diffs() {
+    timely::execute_directly(move |worker| {
+        let (send_results, results) = channel();
+        let requests = SnapshotRequests::resuming().with_resume_tokens();
+
+        let (mut input, probe) = worker.dataflow::<u64, _, _>(|scope| {
+            let (input, stream) = scope.new_input::<ResultDiff<u64>>();
+
+            let probe = stream
+                .snapshots(Pipeline, "q", &requests, move |out| {
+                    send_results.send(out).unwrap();
+                })
+                .probe();
+
+            (input, probe)
+        });
+
+        // Resuming clients aren't sent a snapshot, only the diffs
+        // they have missed, followed by a new token.
+        input.advance_to(3);
+        input.send((vec![Number(1)], 3, 1));
+        input.advance_to(4);
+        worker.step_while(|| probe.less_than(input.time()));
+
+        match results.recv_timeout(Duration::from_millis(400)).unwrap() {
+            Output::QueryDiff(name, diffs) => {
+                assert_eq!(name, "q");
+                assert_eq!(diffs, vec![(vec![Number(1)], Time::TxId(3), 1)]);
+            }
+            other => panic!("expected diffs, got {:?}", other),
+        }
+
+        match results.recv_timeout(Duration::from_millis(400)).unwrap() {
+            Output::ResumeToken(name, time) => {
+                assert_eq!(name, "q");
+                assert_eq!(time, Time::TxId(3));
+            }
+            other => panic!("expected a resume token, got {:?}", other),
+        }
+
+        // No tokens are issued while nothing is forwarded.
+        input.advance_to(6);
+        worker.step_while(|| probe.less_than(input.time()));
+
+        assert!(results.try_recv().is_err());
+    });
+}
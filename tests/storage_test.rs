@@ -0,0 +1,46 @@
+use declarative_dataflow::server::storage::{FileSystem, Storage, StorageBackend};
+
+#[test]
+fn filesystem_storage() {
+    let directory = std::env::temp_dir().join(format!("3df-storage-{}", std::process::id()));
+
+    {
+        let mut backend = FileSystem::open(&directory).unwrap();
+
+        backend.append_tx(b"one").unwrap();
+        backend.append_tx(b"two").unwrap();
+        backend.append_tx(b"three").unwrap();
+
+        assert_eq!(
+            backend.read_range(1, 3).unwrap(),
+            vec![b"two".to_vec(), b"three".to_vec()]
+        );
+
+        backend.rewrite_log(&[b"four".to_vec()]).unwrap();
+        backend.append_tx(b"five").unwrap();
+
+        backend.write_snapshot(1, 1, b"first").unwrap();
+        backend.write_snapshot(2, 0, b"second").unwrap();
+        backend.write_snapshot(2, 1, b"third").unwrap();
+        backend.retain_snapshot(2).unwrap();
+    }
+
+    // State survives re-opening the backend.
+    let backend = Storage::FileSystem(directory.to_string_lossy().to_string())
+        .open()
+        .unwrap();
+
+    assert_eq!(
+        backend.read_range(0, usize::max_value()).unwrap(),
+        vec![b"four".to_vec(), b"five".to_vec()]
+    );
+
+    assert!(backend.snapshot_parts(1).unwrap().is_empty());
+    assert_eq!(backend.snapshot_parts(2).unwrap(), vec![0, 1]);
+    assert_eq!(
+        backend.read_snapshot(2).unwrap(),
+        vec![(0, b"second".to_vec()), (1, b"third".to_vec())]
+    );
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
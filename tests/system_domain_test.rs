@@ -0,0 +1,109 @@
+use std::sync::mpsc::channel;
+
+use timely::dataflow::operators::ToStream;
+use timely::dataflow::{Scope, Stream};
+
+use declarative_dataflow::plan::Join;
+use declarative_dataflow::server::{Register, Server};
+use declarative_dataflow::sources::{Sourceable, SourcingContext};
+use declarative_dataflow::{Aid, AttributeConfig, InputSemantics, Plan, Rule, Value};
+use Value::{Eid, Number};
+
+/// A source feeding two attributes from fixed data.
+struct Introspection;
+
+impl<S: Scope<Timestamp = u64>> Sourceable<Aid, S> for Introspection {
+    fn source(
+        &self,
+        scope: &mut S,
+        _context: SourcingContext<u64>,
+    ) -> Vec<(
+        Aid,
+        AttributeConfig,
+        Stream<S, ((Value, Value), u64, isize)>,
+    )> {
+        vec![
+            (
+                ":operator/name".to_string(),
+                AttributeConfig::tx_time(InputSemantics::Raw),
+                vec![((Eid(1), Value::String("Map".to_string())), 0, 1)].to_stream(scope),
+            ),
+            (
+                ":operator/records".to_string(),
+                AttributeConfig::tx_time(InputSemantics::Raw),
+                vec![((Eid(1), Number(10)), 0, 1)].to_stream(scope),
+            ),
+        ]
+    }
+}
+
+#[test]
+fn introspection_in_system_domain() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .register_system_source(Box::new(Introspection), scope)
+                .unwrap();
+
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+        });
+
+        assert!(server.system.attributes.contains_key(":operator/records"));
+        assert!(!server.internal.attributes.contains_key(":operator/records"));
+
+        server
+            .register(Register {
+                rules: vec![
+                    Rule::named(
+                        "records",
+                        Plan::Join(Join {
+                            variables: vec![0],
+                            left_plan: Box::new(Plan::match_a(0, ":operator/name", 1)),
+                            right_plan: Box::new(Plan::match_a(0, ":operator/records", 2)),
+                        }),
+                    ),
+                    Rule::named(
+                        "mixed",
+                        Plan::Join(Join {
+                            variables: vec![0],
+                            left_plan: Box::new(Plan::match_a(0, ":operator/name", 1)),
+                            right_plan: Box::new(Plan::match_a(0, ":name", 2)),
+                        }),
+                    ),
+                ],
+                publish: vec![],
+                datalog: vec![],
+            })
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            assert!(server.interest("mixed".to_string(), scope).is_err());
+
+            server
+                .interest("records".to_string(), scope)
+                .unwrap()
+                .probe_with(&mut server.probe)
+                .inspect(move |x| send_results.send((x.0.clone(), x.2)).unwrap());
+        });
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (
+                vec![Eid(1), Value::String("Map".to_string()), Number(10)],
+                1
+            )
+        );
+    });
+}
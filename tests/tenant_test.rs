@@ -0,0 +1,156 @@
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use declarative_dataflow::server::{Register, Server};
+use declarative_dataflow::{Aid, AttributeConfig, Datom, Plan, Rule, Value};
+use Value::{Eid, String};
+
+#[test]
+fn tenants_progress_independently() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in &[":name", "acme//:name", "initech//:name"] {
+                server
+                    .create_attribute(scope, *name, AttributeConfig::tx_time(Default::default()))
+                    .unwrap();
+            }
+        });
+
+        assert_eq!(server.tenants.len(), 2);
+
+        server
+            .transact(
+                vec![Datom::add(1, "acme//:name", String("Dipper".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server
+            .transact(
+                vec![Datom::add(1, "initech//:name", String("Mabel".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .test_single(
+                    scope,
+                    Rule::named("acme//names", Plan::match_a(0, "acme//:name", 1)),
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        // Only the tenant advanced reveals its results.
+        server.advance_domain(Some("acme".to_string()), 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv_timeout(Duration::from_millis(400)).unwrap(),
+            (vec![Eid(1), String("Dipper".to_string())], 1)
+        );
+
+        assert_eq!(*server.tenants["acme"].epoch(), 1);
+        assert_eq!(*server.tenants["initech"].epoch(), 0);
+        assert_eq!(*server.internal.epoch(), 0);
+
+        assert!(server.advance_domain(Some("hooli".to_string()), 1).is_err());
+    });
+}
+
+#[test]
+fn cross_tenant_references() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in &["acme//:name", "initech//:name"] {
+                server
+                    .create_attribute(scope, *name, AttributeConfig::tx_time(Default::default()))
+                    .unwrap();
+            }
+        });
+
+        let error = server
+            .transact(
+                vec![
+                    Datom::add(1, "acme//:name", String("Dipper".to_string())),
+                    Datom::add(1, "initech//:name", String("Mabel".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap_err();
+        assert_eq!(error.category, "df.error.category/incorrect");
+
+        let error = server
+            .register(Register {
+                rules: vec![Rule::named(
+                    "acme//names",
+                    Plan::match_a(0, "initech//:name", 1),
+                )],
+                publish: vec![],
+                datalog: vec![],
+            })
+            .unwrap_err();
+        assert_eq!(error.category, "df.error.category/incorrect");
+
+        // Rules of tenants that don't exist can't be registered.
+        let error = server
+            .register(Register {
+                rules: vec![Rule::named(
+                    "hooli//names",
+                    Plan::match_a(0, "hooli//:name", 1),
+                )],
+                publish: vec![],
+                datalog: vec![],
+            })
+            .unwrap_err();
+        assert_eq!(error.category, "df.error.category/not-found");
+
+        assert!(server.internal.rules.is_empty());
+        assert!(server.tenants["acme"].rules.is_empty());
+    });
+}
+
+#[test]
+fn tenant_retractions() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in &[":name", "acme//:name"] {
+                server
+                    .create_attribute(scope, *name, AttributeConfig::tx_time(Default::default()))
+                    .unwrap();
+            }
+        });
+
+        for name in &[":name", "acme//:name"] {
+            server
+                .transact(
+                    vec![Datom::add(1, *name, String("Dipper".to_string()))],
+                    0,
+                    0,
+                )
+                .unwrap();
+        }
+
+        // Entities are retracted within their tenant only.
+        let acme = Some("acme".to_string());
+        server.retract_entity(acme, Eid(1), 0, 0).unwrap();
+
+        assert!(server.tenants["acme"].retract_entity(&Eid(1)).is_empty());
+        assert_eq!(server.internal.retract_entity(&Eid(1)).len(), 1);
+
+        let hooli = Some("hooli".to_string());
+        assert!(server.retract_entity(hooli, Eid(1), 0, 0).is_err());
+    });
+}
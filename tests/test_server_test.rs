@@ -0,0 +1,87 @@
+use declarative_dataflow::plan::Join;
+use declarative_dataflow::testing::TestServer;
+use declarative_dataflow::{AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use Value::{Eid, Number, String};
+
+#[test]
+fn join_results() {
+    let mut server = TestServer::new();
+    let (e, n, a) = (0, 1, 2);
+
+    for name in &[":name", ":age"] {
+        server
+            .create_attribute(name, AttributeConfig::tx_time(InputSemantics::Raw))
+            .unwrap();
+    }
+
+    server
+        .register(vec![Rule::named(
+            "people",
+            Plan::Join(Join {
+                variables: vec![e],
+                left_plan: Box::new(Plan::match_a(e, ":name", n)),
+                right_plan: Box::new(Plan::match_a(e, ":age", a)),
+            }),
+        )])
+        .unwrap();
+
+    server.interest("people").unwrap();
+
+    let t0 = server
+        .transact(vec![
+            Datom::add(1, ":name", String("Dipper".to_string())),
+            Datom::add(1, ":age", Number(12)),
+            Datom::add(2, ":name", String("Mabel".to_string())),
+        ])
+        .unwrap();
+
+    server.expect_results(
+        "people",
+        vec![(
+            vec![Eid(1), String("Dipper".to_string()), Number(12)],
+            t0,
+            1,
+        )],
+    );
+
+    let t1 = server
+        .transact(vec![
+            Datom::retract(1, ":age", Number(12)),
+            Datom::add(1, ":age", Number(13)),
+            Datom::add(2, ":age", Number(12)),
+        ])
+        .unwrap();
+
+    assert!(t1 > t0);
+
+    server.expect_results(
+        "people",
+        vec![
+            (
+                vec![Eid(1), String("Dipper".to_string()), Number(12)],
+                t1,
+                -1,
+            ),
+            (
+                vec![Eid(1), String("Dipper".to_string()), Number(13)],
+                t1,
+                1,
+            ),
+            (vec![Eid(2), String("Mabel".to_string()), Number(12)], t1, 1),
+        ],
+    );
+
+    // Results are only reported once.
+    assert!(server.results("people").is_empty());
+}
+
+#[test]
+fn failing_interest() {
+    let mut server = TestServer::new();
+
+    assert!(server.interest("unknown").is_err());
+    assert!(server.create_attribute(":name", Default::default()).is_ok());
+    assert!(server
+        .create_attribute(":name", Default::default())
+        .is_err());
+}
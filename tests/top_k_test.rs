@@ -0,0 +1,178 @@
+use declarative_dataflow::plan::{Join, TopK};
+use declarative_dataflow::server::Strategy;
+use declarative_dataflow::testing::TestServer;
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use Value::{Eid, Number};
+
+fn server() -> TestServer {
+    let mut server = TestServer::new();
+
+    for name in &[":event/user", ":event/time"] {
+        server
+            .create_attribute(name, AttributeConfig::tx_time(InputSemantics::Raw))
+            .unwrap();
+    }
+
+    server
+}
+
+fn events(descending: bool, limit: usize) -> Plan<Aid> {
+    let (e, u, t) = (0, 1, 2);
+
+    Plan::TopK(TopK {
+        variables: vec![u, t, e],
+        plan: Box::new(Plan::Join(Join {
+            variables: vec![e],
+            left_plan: Box::new(Plan::match_a(e, ":event/user", u)),
+            right_plan: Box::new(Plan::match_a(e, ":event/time", t)),
+        })),
+        key_variables: vec![u],
+        sort_variables: vec![t],
+        descending,
+        limit,
+    })
+}
+
+fn event(e: u64, user: u64, time: i64) -> Vec<Datom<Aid>> {
+    vec![
+        Datom::add(e, ":event/user", Eid(user)),
+        Datom::add(e, ":event/time", Number(time)),
+    ]
+}
+
+fn result(user: u64, time: i64, e: u64) -> Vec<Value> {
+    vec![Eid(user), Number(time), Eid(e)]
+}
+
+#[test]
+fn most_recent_per_group() {
+    let mut server = server();
+
+    server
+        .register(vec![Rule::named("recent", events(true, 2))])
+        .unwrap();
+    server.interest("recent").unwrap();
+
+    let mut tx_data = Vec::new();
+    tx_data.extend(event(1, 100, 10));
+    tx_data.extend(event(2, 100, 20));
+    tx_data.extend(event(3, 100, 30));
+    tx_data.extend(event(4, 200, 5));
+
+    let t0 = server.transact(tx_data).unwrap();
+
+    server.expect_results(
+        "recent",
+        vec![
+            (result(100, 20, 2), t0, 1),
+            (result(100, 30, 3), t0, 1),
+            (result(200, 5, 4), t0, 1),
+        ],
+    );
+
+    // Newer events push older ones out of their group only.
+    let t1 = server.transact(event(5, 100, 40)).unwrap();
+
+    server.expect_results(
+        "recent",
+        vec![(result(100, 20, 2), t1, -1), (result(100, 40, 5), t1, 1)],
+    );
+
+    // Retracting one of the kept events brings the next one back.
+    let t2 = server
+        .transact(vec![Datom::retract(5, ":event/time", Number(40))])
+        .unwrap();
+
+    server.expect_results(
+        "recent",
+        vec![(result(100, 20, 2), t2, 1), (result(100, 40, 5), t2, -1)],
+    );
+}
+
+#[test]
+fn earliest_per_group() {
+    let mut server = server();
+
+    server
+        .register(vec![Rule::named("first", events(false, 1))])
+        .unwrap();
+    server.interest("first").unwrap();
+
+    let mut tx_data = Vec::new();
+    tx_data.extend(event(1, 100, 10));
+    tx_data.extend(event(2, 100, 20));
+    tx_data.extend(event(3, 200, 15));
+
+    let t = server.transact(tx_data).unwrap();
+
+    server.expect_results(
+        "first",
+        vec![(result(100, 10, 1), t, 1), (result(200, 15, 3), t, 1)],
+    );
+}
+
+#[test]
+fn retracted_tuples() {
+    let mut server = server();
+
+    server
+        .register(vec![Rule::named("recent", events(true, 2))])
+        .unwrap();
+    server.interest("recent").unwrap();
+
+    // Raw inputs may retract tuples that were never added, which must
+    // neither show up nor take up room in their group.
+    let mut tx_data = vec![
+        Datom::retract(1, ":event/user", Eid(100)),
+        Datom::add(1, ":event/time", Number(50)),
+    ];
+    tx_data.extend(event(2, 100, 20));
+    tx_data.extend(event(3, 100, 30));
+
+    let t = server.transact(tx_data).unwrap();
+
+    server.expect_results(
+        "recent",
+        vec![(result(100, 20, 2), t, 1), (result(100, 30, 3), t, 1)],
+    );
+}
+
+#[test]
+fn invalid_top_k() {
+    let mut server = server();
+
+    let mut unsorted = events(true, 2);
+    if let Plan::TopK(ref mut top_k) = unsorted {
+        top_k.sort_variables = vec![3];
+    }
+
+    server
+        .register(vec![
+            Rule::named("unsorted", unsorted),
+            Rule::named("empty", events(true, 0)),
+        ])
+        .unwrap();
+
+    let error = server.interest("unsorted").unwrap_err();
+    assert_eq!(error.category, "df.error.category/incorrect");
+    assert_eq!(error.clause, Some(vec![]));
+
+    let error = server.interest("empty").unwrap_err();
+    assert_eq!(error.category, "df.error.category/incorrect");
+}
+
+#[test]
+fn worst_case_optimal_top_k() {
+    let mut server = server();
+
+    server
+        .register(vec![Rule::named("recent", events(true, 2))])
+        .unwrap();
+
+    // Hector would drop the limit, so such interests are turned away.
+    let error = server
+        .interest_using("recent", Strategy::WorstCaseOptimal)
+        .unwrap_err();
+    assert_eq!(error.category, "df.error.category/unsupported");
+    assert_eq!(error.clause, Some(vec![]));
+}
@@ -10,7 +10,7 @@ use declarative_dataflow::binding::Binding;
 use declarative_dataflow::plan::{Function, Implementable, Transform};
 use declarative_dataflow::server::Server;
 use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
-use Value::{Eid, Instant};
+use Value::{Eid, Instant, Number};
 
 struct Case {
     description: &'static str,
@@ -33,45 +33,150 @@ fn dependencies(case: &Case) -> HashSet<Aid> {
 
 #[test]
 fn run_transform_cases() {
-    let mut cases = vec![Case {
-        description: "[:find ?h :where [?e :timestamp ?t] [(interval ?t) ?h]]",
-        plan: {
-            let (e, t, h) = (1, 2, 3);
-            let constants = vec![None, None];
-            // let constants = vec![None, Some(Value::String(String::from("hour")))];
-            Plan::Transform(Transform {
-                variables: vec![t],
-                result_variable: h,
-                plan: Box::new(Plan::match_a(e, ":timestamp", t)),
-                function: Function::TRUNCATE,
-                constants,
-            })
+    let mut cases = vec![
+        Case {
+            description: "[:find ?h :where [?e :timestamp ?t] [(interval ?t) ?h]]",
+            plan: {
+                let (e, t, h) = (1, 2, 3);
+                let constants = vec![None, None];
+                // let constants = vec![None, Some(Value::String(String::from("hour")))];
+                Plan::Transform(Transform {
+                    variables: vec![t],
+                    result_variable: h,
+                    plan: Box::new(Plan::match_a(e, ":timestamp", t)),
+                    function: Function::TRUNCATE,
+                    constants,
+                })
+            },
+            transactions: vec![vec![
+                Datom::add(1, ":timestamp", Instant(1_540_048_515_500)),
+                Datom::add(2, ":timestamp", Instant(1_540_048_515_616)),
+            ]],
+            expectations: vec![vec![
+                (
+                    vec![
+                        Eid(1),
+                        Instant(1_540_048_515_500),
+                        Instant(1_540_047_600_000),
+                    ],
+                    0,
+                    1,
+                ),
+                (
+                    vec![
+                        Eid(2),
+                        Instant(1_540_048_515_616),
+                        Instant(1_540_047_600_000),
+                    ],
+                    0,
+                    1,
+                ),
+            ]],
         },
-        transactions: vec![vec![
-            Datom::add(1, ":timestamp", Instant(1_540_048_515_500)),
-            Datom::add(2, ":timestamp", Instant(1_540_048_515_616)),
-        ]],
-        expectations: vec![vec![
-            (
+        Case {
+            description: "[:find ?e ?a ?x :where [?e :age ?a] [(* ?a 2) ?x]]",
+            plan: {
+                let (e, a, x) = (1, 2, 3);
+                Plan::Transform(Transform {
+                    variables: vec![a],
+                    result_variable: x,
+                    plan: Box::new(Plan::match_a(e, ":age", a)),
+                    function: Function::MULTIPLY,
+                    constants: vec![None, Some(Number(2))],
+                })
+            },
+            transactions: vec![vec![
+                Datom::add(1, ":age", Number(12)),
+                Datom::add(2, ":age", Number(0)),
+            ]],
+            expectations: vec![vec![
+                (vec![Eid(1), Number(12), Number(24)], 0, 1),
+                (vec![Eid(2), Number(0), Number(0)], 0, 1),
+            ]],
+        },
+        Case {
+            description: "[:find ?e ?a ?x :where [?e :age ?a] [(/ 120 ?a) ?x]]",
+            plan: {
+                let (e, a, x) = (1, 2, 3);
+                Plan::Transform(Transform {
+                    variables: vec![a],
+                    result_variable: x,
+                    plan: Box::new(Plan::match_a(e, ":age", a)),
+                    function: Function::DIVIDE,
+                    constants: vec![Some(Number(120)), None],
+                })
+            },
+            transactions: vec![vec![
+                Datom::add(1, ":age", Number(12)),
+                Datom::add(2, ":age", Number(0)),
+            ]],
+            expectations: vec![vec![(vec![Eid(1), Number(12), Number(10)], 0, 1)]],
+        },
+        Case {
+            description: "[:find ?e ?t ?d :where [?e :timestamp ?t] [(date ?t) ?d]]",
+            plan: {
+                let (e, t, d) = (1, 2, 3);
+                Plan::Transform(Transform {
+                    variables: vec![t],
+                    result_variable: d,
+                    plan: Box::new(Plan::match_a(e, ":timestamp", t)),
+                    function: Function::DATE,
+                    constants: vec![None],
+                })
+            },
+            transactions: vec![vec![Datom::add(
+                1,
+                ":timestamp",
+                Instant(1_540_048_515_500),
+            )]],
+            expectations: vec![vec![(
                 vec![
                     Eid(1),
                     Instant(1_540_048_515_500),
-                    Instant(1_540_047_600_000),
+                    Value::date(2018, 10, 20),
                 ],
                 0,
                 1,
-            ),
-            (
-                vec![
-                    Eid(2),
-                    Instant(1_540_048_515_616),
-                    Instant(1_540_047_600_000),
-                ],
-                0,
-                1,
-            ),
-        ]],
-    }];
+            )]],
+        },
+        Case {
+            description: "[:find ?e ?t ?b :where [?e :timestamp ?t] [(bucket ?t 900000) ?b]]",
+            plan: {
+                let (e, t, b) = (1, 2, 3);
+                Plan::Transform(Transform {
+                    variables: vec![t],
+                    result_variable: b,
+                    plan: Box::new(Plan::match_a(e, ":timestamp", t)),
+                    function: Function::BUCKET,
+                    constants: vec![None, Some(Number(900_000))],
+                })
+            },
+            transactions: vec![vec![
+                Datom::add(1, ":timestamp", Instant(1_540_048_515_500)),
+                Datom::add(2, ":timestamp", Instant(1_540_049_415_616)),
+            ]],
+            expectations: vec![vec![
+                (
+                    vec![
+                        Eid(1),
+                        Instant(1_540_048_515_500),
+                        Instant(1_540_048_500_000),
+                    ],
+                    0,
+                    1,
+                ),
+                (
+                    vec![
+                        Eid(2),
+                        Instant(1_540_049_415_616),
+                        Instant(1_540_049_400_000),
+                    ],
+                    0,
+                    1,
+                ),
+            ]],
+        },
+    ];
 
     for case in cases.drain(..) {
         timely::execute_directly(move |worker| {
@@ -0,0 +1,169 @@
+use declarative_dataflow::plan::TransitiveClosure;
+use declarative_dataflow::testing::TestServer;
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use Value::Eid;
+
+fn server() -> TestServer {
+    let mut server = TestServer::new();
+
+    server
+        .create_attribute(
+            ":node/parent",
+            AttributeConfig::tx_time(InputSemantics::Raw),
+        )
+        .unwrap();
+
+    server
+}
+
+fn closure(attribute: &str, max_hops: Option<usize>) -> Plan<Aid> {
+    Plan::TransitiveClosure(TransitiveClosure {
+        variables: vec![0, 1],
+        attribute: attribute.to_string(),
+        max_hops,
+    })
+}
+
+fn parent(child: u64, parent: u64) -> Datom<Aid> {
+    Datom::add(child, ":node/parent", Eid(parent))
+}
+
+fn pair(e: u64, v: u64) -> Vec<Value> {
+    vec![Eid(e), Eid(v)]
+}
+
+#[test]
+fn ancestors() {
+    let mut server = server();
+
+    server
+        .register(vec![Rule::named(
+            "ancestors",
+            closure(":node/parent", None),
+        )])
+        .unwrap();
+    server.interest("ancestors").unwrap();
+
+    let t0 = server
+        .transact(vec![parent(1, 2), parent(2, 3), parent(3, 4)])
+        .unwrap();
+
+    server.expect_results(
+        "ancestors",
+        vec![
+            (pair(1, 2), t0, 1),
+            (pair(1, 3), t0, 1),
+            (pair(1, 4), t0, 1),
+            (pair(2, 3), t0, 1),
+            (pair(2, 4), t0, 1),
+            (pair(3, 4), t0, 1),
+        ],
+    );
+
+    // Cutting the chain retracts everything reached through it.
+    let t1 = server
+        .transact(vec![Datom::retract(2, ":node/parent", Eid(3))])
+        .unwrap();
+
+    server.expect_results(
+        "ancestors",
+        vec![
+            (pair(1, 3), t1, -1),
+            (pair(1, 4), t1, -1),
+            (pair(2, 3), t1, -1),
+            (pair(2, 4), t1, -1),
+        ],
+    );
+}
+
+#[test]
+fn descendants() {
+    let mut server = server();
+
+    server
+        .register(vec![Rule::named(
+            "descendants",
+            closure(":node/_parent", None),
+        )])
+        .unwrap();
+    server.interest("descendants").unwrap();
+
+    let t = server.transact(vec![parent(1, 2), parent(2, 3)]).unwrap();
+
+    server.expect_results(
+        "descendants",
+        vec![(pair(2, 1), t, 1), (pair(3, 1), t, 1), (pair(3, 2), t, 1)],
+    );
+}
+
+#[test]
+fn cycles() {
+    let mut server = server();
+
+    server
+        .register(vec![
+            Rule::named("unbounded", closure(":node/parent", None)),
+            Rule::named("bounded", closure(":node/parent", Some(5))),
+        ])
+        .unwrap();
+    server.interest("unbounded").unwrap();
+    server.interest("bounded").unwrap();
+
+    let t = server.transact(vec![parent(1, 2), parent(2, 1)]).unwrap();
+
+    let expected = vec![
+        (pair(1, 1), t, 1),
+        (pair(1, 2), t, 1),
+        (pair(2, 1), t, 1),
+        (pair(2, 2), t, 1),
+    ];
+
+    server.expect_results("unbounded", expected.clone());
+    server.expect_results("bounded", expected);
+}
+
+#[test]
+fn max_hops() {
+    let mut server = server();
+
+    server
+        .register(vec![Rule::named(
+            "nearby",
+            closure(":node/parent", Some(2)),
+        )])
+        .unwrap();
+    server.interest("nearby").unwrap();
+
+    let t0 = server
+        .transact(vec![parent(1, 2), parent(2, 3), parent(3, 4)])
+        .unwrap();
+
+    server.expect_results(
+        "nearby",
+        vec![
+            (pair(1, 2), t0, 1),
+            (pair(1, 3), t0, 1),
+            (pair(2, 3), t0, 1),
+            (pair(2, 4), t0, 1),
+            (pair(3, 4), t0, 1),
+        ],
+    );
+
+    // A shortcut brings more distant ancestors within reach.
+    let t1 = server.transact(vec![parent(1, 3)]).unwrap();
+
+    server.expect_results("nearby", vec![(pair(1, 4), t1, 1)]);
+}
+
+#[test]
+fn invalid_closure() {
+    let mut server = server();
+
+    server
+        .register(vec![Rule::named("empty", closure(":node/parent", Some(0)))])
+        .unwrap();
+
+    let error = server.interest("empty").unwrap_err();
+    assert_eq!(error.category, "df.error.category/incorrect");
+    assert_eq!(error.clause, Some(vec![]));
+}
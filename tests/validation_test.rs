@@ -0,0 +1,252 @@
+use declarative_dataflow::plan::{
+    Aggregate, AggregationFn, Antijoin, Filter, History, Join, Predicate, Project,
+};
+use declarative_dataflow::server::{Configuration, Strategy};
+use declarative_dataflow::testing::TestServer;
+use declarative_dataflow::{
+    AttributeConfig, Datom, Error, InputSemantics, Plan, Rule, Value, ValueType,
+};
+
+fn server() -> TestServer {
+    let mut server = TestServer::new();
+
+    for (name, value_type) in &[(":name", ValueType::String), (":age", ValueType::Number)] {
+        let config = AttributeConfig {
+            value_type: Some(*value_type),
+            ..AttributeConfig::tx_time(InputSemantics::Raw)
+        };
+
+        server.create_attribute(name, config).unwrap();
+    }
+
+    server
+}
+
+/// Registers the plan and reports why no interest can be expressed
+/// in it.
+fn rejection(plan: Plan<String>) -> Error {
+    let mut server = server();
+
+    server.register(vec![Rule::named("q", plan)]).unwrap();
+    server.interest("q").unwrap_err()
+}
+
+#[test]
+fn unbound_projections() {
+    let error = rejection(Plan::Project(Project {
+        variables: vec![0, 2],
+        plan: Box::new(Plan::match_a(0, ":name", 1)),
+    }));
+
+    assert_eq!(error.category, "df.error.category/incorrect");
+    assert_eq!(error.clause, Some(vec![]));
+}
+
+#[test]
+fn unknown_attributes() {
+    let error = rejection(Plan::Project(Project {
+        variables: vec![0],
+        plan: Box::new(Plan::Join(Join {
+            variables: vec![0],
+            left_plan: Box::new(Plan::match_a(0, ":name", 1)),
+            right_plan: Box::new(Plan::match_a(0, ":unknown", 2)),
+        })),
+    }));
+
+    assert_eq!(error.category, "df.error.category/not-found");
+    assert_eq!(error.clause, Some(vec![0, 1]));
+}
+
+#[test]
+fn unbound_join_variables() {
+    let error = rejection(Plan::Join(Join {
+        variables: vec![1],
+        left_plan: Box::new(Plan::match_a(0, ":name", 1)),
+        right_plan: Box::new(Plan::match_a(0, ":age", 2)),
+    }));
+
+    assert_eq!(error.category, "df.error.category/incorrect");
+    assert_eq!(error.clause, Some(vec![]));
+}
+
+#[test]
+fn mistyped_patterns() {
+    let error = rejection(Plan::Project(Project {
+        variables: vec![0],
+        plan: Box::new(Plan::Join(Join {
+            variables: vec![0],
+            left_plan: Box::new(Plan::match_av(0, ":age", Value::String("old".to_string()))),
+            right_plan: Box::new(Plan::match_a(0, ":name", 1)),
+        })),
+    }));
+
+    assert_eq!(error.category, "df.error.category/incorrect");
+    assert_eq!(error.clause, Some(vec![0, 0]));
+}
+
+#[test]
+fn unbound_aggregates() {
+    let error = rejection(Plan::Aggregate(Aggregate {
+        variables: vec![2],
+        plan: Box::new(Plan::match_a(0, ":age", 1)),
+        aggregation_fns: vec![AggregationFn::COUNT],
+        key_variables: vec![],
+        aggregation_variables: vec![2],
+        with_variables: vec![],
+    }));
+
+    assert_eq!(error.category, "df.error.category/incorrect");
+    assert_eq!(error.clause, Some(vec![]));
+}
+
+#[test]
+fn mistyped_aggregates() {
+    let error = rejection(Plan::Aggregate(Aggregate {
+        variables: vec![1],
+        plan: Box::new(Plan::Project(Project {
+            variables: vec![1],
+            plan: Box::new(Plan::match_a(0, ":name", 1)),
+        })),
+        aggregation_fns: vec![AggregationFn::SUM],
+        key_variables: vec![],
+        aggregation_variables: vec![1],
+        with_variables: vec![],
+    }));
+
+    assert_eq!(error.category, "df.error.category/incorrect");
+    assert_eq!(error.clause, Some(vec![]));
+}
+
+#[test]
+fn unimplementable_plans() {
+    // Data patterns are joined on a single variable only.
+    let error = rejection(Plan::Project(Project {
+        variables: vec![0, 1],
+        plan: Box::new(Plan::Join(Join {
+            variables: vec![0, 1],
+            left_plan: Box::new(Plan::match_a(0, ":name", 1)),
+            right_plan: Box::new(Plan::match_a(0, ":name", 1)),
+        })),
+    }));
+
+    assert_eq!(error.category, "df.error.category/unsupported");
+    assert_eq!(error.clause, Some(vec![0]));
+
+    // Compacted attributes don't provide their full history.
+    let error = rejection(Plan::History(History {
+        variables: vec![0, 1, 2, 3],
+        attribute: ":name".to_string(),
+    }));
+
+    assert_eq!(error.category, "df.error.category/unsupported");
+    assert_eq!(error.clause, Some(vec![]));
+}
+
+#[test]
+fn duplicate_attributes() {
+    let mut server = server();
+
+    let error = server
+        .create_attribute(":name", AttributeConfig::tx_time(InputSemantics::Raw))
+        .unwrap_err();
+
+    assert_eq!(error.category, "df.error.category/conflict");
+}
+
+#[test]
+fn unknown_parameters() {
+    let error = rejection(Plan::Project(Project {
+        variables: vec![0],
+        plan: Box::new(Plan::Parameter(vec![0], "missing".to_string())),
+    }));
+
+    assert_eq!(error.category, "df.error.category/not-found");
+    assert_eq!(error.clause, Some(vec![0]));
+}
+
+#[test]
+fn parameters_without_bindings() {
+    let mut server = TestServer::with_config(Configuration {
+        enable_optimizer: true,
+        ..Default::default()
+    });
+
+    server
+        .create_attribute(":name", AttributeConfig::tx_time(InputSemantics::Raw))
+        .unwrap();
+    server.create_parameter("names").unwrap();
+
+    // Worst-case optimal joins can't draw on parameters.
+    let plan = Plan::Join(Join {
+        variables: vec![1],
+        left_plan: Box::new(Plan::Parameter(vec![1], "names".to_string())),
+        right_plan: Box::new(Plan::match_a(0, ":name", 1)),
+    });
+
+    server.register(vec![Rule::named("q", plan)]).unwrap();
+    let error = server.interest("q").unwrap_err();
+
+    assert_eq!(error.category, "df.error.category/unsupported");
+    assert_eq!(error.clause, Some(vec![0]));
+}
+
+#[test]
+fn unbindable_interests() {
+    let (e, n) = (0, 1);
+
+    let filtered = Plan::Filter(Filter {
+        variables: vec![n],
+        predicate: Predicate::EQ,
+        plan: Box::new(Plan::match_a(e, ":name", n)),
+        constants: vec![None, Some(Value::String("Mabel".to_string()))],
+    });
+
+    let negated = Plan::Antijoin(Antijoin {
+        variables: vec![e],
+        left_plan: Box::new(Plan::match_a(e, ":name", n)),
+        right_plan: Box::new(Plan::match_a(e, ":age", 2)),
+        semantics: None,
+    });
+
+    // Neither can be implemented via worst-case optimal joins, which
+    // interests may ask for nonetheless.
+    for plan in vec![filtered, negated].into_iter() {
+        let mut server = server();
+
+        server.register(vec![Rule::named("q", plan)]).unwrap();
+        let error = server
+            .interest_using("q", Strategy::WorstCaseOptimal)
+            .unwrap_err();
+
+        assert_eq!(error.category, "df.error.category/unsupported");
+        assert_eq!(error.clause, Some(vec![]));
+
+        server.interest("q").unwrap();
+    }
+}
+
+#[test]
+fn valid_plans() {
+    let mut server = server();
+
+    let plan = Plan::Project(Project {
+        variables: vec![1],
+        plan: Box::new(Plan::Join(Join {
+            variables: vec![0],
+            left_plan: Box::new(Plan::match_a(0, ":name", 1)),
+            right_plan: Box::new(Plan::match_av(0, ":age", Value::Number(12))),
+        })),
+    });
+
+    server.register(vec![Rule::named("q", plan)]).unwrap();
+    server.interest("q").unwrap();
+
+    let t = server
+        .transact(vec![
+            Datom::add(1, ":name", Value::String("Mabel".to_string())),
+            Datom::add(1, ":age", Value::Number(12)),
+        ])
+        .unwrap();
+
+    server.expect_results("q", vec![(vec![Value::String("Mabel".to_string())], t, 1)]);
+}
@@ -0,0 +1,164 @@
+#![cfg(feature = "serde_json")]
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::Duration;
+
+use declarative_dataflow::server::sequencing::{CommandLog, Stamped};
+use declarative_dataflow::server::storage::FileSystem;
+use declarative_dataflow::server::wal::WriteAheadLog;
+use declarative_dataflow::server::{Request, Server};
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+
+type Entry = Stamped<Vec<Request<Aid>>>;
+
+#[test]
+fn replay_transactions() {
+    let directory = std::env::temp_dir().join(format!("3df-wal-{}", std::process::id()));
+    let path = directory.clone();
+
+    let entries: Vec<Entry> = vec![
+        Stamped {
+            issued_at: Duration::from_secs(1),
+            command: vec![Request::Transact(vec![Datom::add(
+                1,
+                ":name",
+                Value::from("Alice"),
+            )])],
+            tx: None,
+        },
+        Stamped {
+            issued_at: Duration::from_secs(2),
+            command: vec![Request::Transact(vec![Datom::add(
+                2,
+                ":name",
+                Value::from("Bob"),
+            )])],
+            tx: None,
+        },
+    ];
+
+    {
+        let mut wal = WriteAheadLog::open(&directory).unwrap();
+        for entry in entries.iter() {
+            wal.append(entry).unwrap();
+        }
+
+        // Simulate a crash in the middle of an append.
+        let log_path = FileSystem::open(&directory)
+            .unwrap()
+            .log_path()
+            .to_path_buf();
+        let mut file = OpenOptions::new().append(true).open(log_path).unwrap();
+        file.write_all(b"{\"issued_at\":").unwrap();
+    }
+
+    let replayed: Vec<Entry> = WriteAheadLog::replay(&directory).unwrap();
+    assert_eq!(replayed, entries);
+
+    // Re-opening the log discards the partial entry, s.t. further
+    // appends remain readable.
+    {
+        let mut wal = WriteAheadLog::open(&directory).unwrap();
+        wal.append(&entries[0]).unwrap();
+    }
+
+    let replayed: Vec<Entry> = WriteAheadLog::replay(&directory).unwrap();
+    assert_eq!(replayed.len(), 3);
+
+    // Replaying the log into a fresh server restores its state.
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Aid, u64, u64>::new(Default::default());
+        let (send_results, results) = std::sync::mpsc::channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    scope,
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                )
+                .unwrap();
+
+            server
+                .test_single(scope, Rule::named("names", Plan::match_a(0, ":name", 1)))
+                .inspect(move |x| send_results.send((x.0.clone(), x.2)).unwrap());
+        });
+
+        let replayed: Vec<Entry> = WriteAheadLog::replay(&path).unwrap();
+        for (tx, entry) in replayed.into_iter().take(2).enumerate() {
+            for req in entry.command.into_iter() {
+                match req {
+                    Request::Transact(tx_data) => {
+                        server.transact(tx_data, 0, 0).unwrap();
+                    }
+                    _ => panic!("unexpected request"),
+                }
+            }
+
+            server.advance_domain(None, tx as u64 + 1).unwrap();
+        }
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut names: Vec<_> = results.try_iter().collect();
+        names.sort();
+
+        assert_eq!(
+            names,
+            vec![
+                (vec![Value::Eid(1), Value::from("Alice")], 1),
+                (vec![Value::Eid(2), Value::from("Bob")], 1),
+            ]
+        );
+    });
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn replay_compacted_log() {
+    let directory = std::env::temp_dir().join(format!("3df-wal-compacted-{}", std::process::id()));
+    let path = directory.clone();
+
+    let entry = |command: usize, tx: Option<u64>| Stamped {
+        issued_at: Duration::from_secs(command as u64),
+        command,
+        tx,
+    };
+
+    // Following a built-in command, entries are logged from
+    // transaction 2 on. Compacting them through transaction 4 leaves
+    // a single entry in their stead.
+    {
+        let mut wal = WriteAheadLog::open(&directory).unwrap();
+        for command in 2..6 {
+            wal.append(&entry(command, None)).unwrap();
+        }
+
+        wal.rewrite(&[entry(4, Some(4)), entry(5, None)]).unwrap();
+    }
+
+    // Restarting retains the transaction ids of all commands.
+    timely::execute_directly(move |worker| {
+        let mut preload = vec![entry(1, None)];
+        preload.extend(WriteAheadLog::replay::<Stamped<usize>, _>(&path).unwrap());
+
+        let timer = worker.timer();
+        let mut log: CommandLog<usize> = CommandLog::replayed(worker, timer, preload);
+        log.push(6);
+
+        let mut received = Vec::new();
+        while received.len() < 4 {
+            worker.step();
+
+            while let Some(sequenced) = log.next() {
+                received.push((sequenced.tx, sequenced.command));
+            }
+        }
+
+        assert_eq!(received, vec![(1, 1), (4, 4), (5, 5), (6, 6)]);
+    });
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
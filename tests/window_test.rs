@@ -0,0 +1,155 @@
+use std::time::Duration;
+
+use declarative_dataflow::plan::{Aggregate, AggregationFn, Window};
+use declarative_dataflow::server::Strategy;
+use declarative_dataflow::testing::TestServer;
+use declarative_dataflow::{Aid, AttributeConfig, Datom, InputSemantics, Plan, Rule, Value};
+use Value::{Eid, Instant, Number};
+
+fn server() -> TestServer {
+    let mut server = TestServer::new();
+
+    server
+        .create_attribute(":event/time", AttributeConfig::tx_time(InputSemantics::Raw))
+        .unwrap();
+
+    server
+}
+
+fn windows(size: u64, hop: Option<u64>, retention: Option<u64>) -> Plan<Aid> {
+    let (e, t, w) = (0, 1, 2);
+
+    Plan::Window(Window {
+        variables: vec![e, t],
+        plan: Box::new(Plan::match_a(e, ":event/time", t)),
+        time_variable: t,
+        size: Duration::from_millis(size),
+        hop: hop.map(Duration::from_millis),
+        retention: retention.map(Duration::from_millis),
+        window_variable: w,
+    })
+}
+
+fn event(e: u64, time: u64) -> Datom<Aid> {
+    Datom::add(e, ":event/time", Instant(time))
+}
+
+fn result(e: u64, time: u64, window: u64) -> Vec<Value> {
+    vec![Eid(e), Instant(time), Instant(window)]
+}
+
+#[test]
+fn tumbling() {
+    let mut server = server();
+    let (e, w) = (0, 2);
+
+    server
+        .register(vec![Rule::named(
+            "per_bucket",
+            Plan::Aggregate(Aggregate {
+                variables: vec![w, e],
+                plan: Box::new(windows(10, None, None)),
+                aggregation_fns: vec![AggregationFn::COUNT],
+                key_variables: vec![w],
+                aggregation_variables: vec![e],
+                with_variables: vec![],
+            }),
+        )])
+        .unwrap();
+    server.interest("per_bucket").unwrap();
+
+    let t = server
+        .transact(vec![event(1, 0), event(2, 9), event(3, 10), event(4, 25)])
+        .unwrap();
+
+    server.expect_results(
+        "per_bucket",
+        vec![
+            (vec![Instant(0), Number(2)], t, 1),
+            (vec![Instant(10), Number(1)], t, 1),
+            (vec![Instant(20), Number(1)], t, 1),
+        ],
+    );
+}
+
+#[test]
+fn hopping() {
+    let mut server = server();
+
+    server
+        .register(vec![Rule::named("windows", windows(10, Some(5), None))])
+        .unwrap();
+    server.interest("windows").unwrap();
+
+    let t = server.transact(vec![event(1, 12)]).unwrap();
+
+    server.expect_results(
+        "windows",
+        vec![(result(1, 12, 5), t, 1), (result(1, 12, 10), t, 1)],
+    );
+}
+
+#[test]
+fn retention() {
+    let mut server = server();
+
+    server
+        .register(vec![Rule::named("windows", windows(10, None, Some(10)))])
+        .unwrap();
+    server.interest("windows").unwrap();
+
+    let t0 = server.transact(vec![event(1, 5), event(2, 15)]).unwrap();
+
+    server.expect_results(
+        "windows",
+        vec![(result(1, 5, 0), t0, 1), (result(2, 15, 10), t0, 1)],
+    );
+
+    // Later windows push earlier ones beyond the horizon.
+    let t1 = server.transact(vec![event(3, 35)]).unwrap();
+
+    server.expect_results(
+        "windows",
+        vec![
+            (result(1, 5, 0), t1, -1),
+            (result(2, 15, 10), t1, -1),
+            (result(3, 35, 30), t1, 1),
+        ],
+    );
+
+    // Events for windows beyond the horizon never show up.
+    server.transact(vec![event(4, 7)]).unwrap();
+    let t2 = server.transact(vec![event(5, 31)]).unwrap();
+
+    server.expect_results("windows", vec![(result(5, 31, 30), t2, 1)]);
+}
+
+#[test]
+fn invalid_window() {
+    let mut server = server();
+
+    server
+        .register(vec![Rule::named("empty", windows(0, None, None))])
+        .unwrap();
+
+    let error = server.interest("empty").unwrap_err();
+    assert_eq!(error.category, "df.error.category/incorrect");
+    assert_eq!(error.clause, Some(vec![]));
+}
+
+#[test]
+fn worst_case_optimal_window() {
+    let mut server = server();
+
+    server
+        .register(vec![Rule::named("windows", windows(10, None, None))])
+        .unwrap();
+
+    // Hector would drop the window variable, so such interests are
+    // turned away.
+    let error = server
+        .interest_using("windows", Strategy::WorstCaseOptimal)
+        .unwrap_err();
+    assert_eq!(error.category, "df.error.category/unsupported");
+    assert_eq!(error.clause, Some(vec![]));
+}